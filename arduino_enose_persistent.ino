@@ -14,6 +14,7 @@ const char* ssid     = "vivo V60 Lite 5G";
 const char* pass     = "12345678";
 const char* RUST_IP  = "10.175.177.11";   // GANTI KALAU IP BERUBAH
 const int   RUST_PORT = 8081;
+const char* DEVICE_ID = "DEV1";          // UNIK PER UNIT kalau banyak e-nose
 WiFiClient client;
 
 // ==================== SENSOR ====================
@@ -118,6 +119,7 @@ void ensureConnected() {
   if (client.connect(RUST_IP, RUST_PORT)) {
     Serial.println("✅ Connected to backend!");
     client.println("HELLO:Arduino E-NOSE ZIZU");
+    client.print("ID:"); client.println(DEVICE_ID);
   } else {
     Serial.println("❌ Connection failed, will retry...");
   }
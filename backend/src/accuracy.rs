@@ -0,0 +1,298 @@
+use influxdb2::models::DataPoint;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::features::CycleFeatures;
+use crate::influxdb::InfluxDBHandler;
+use crate::replay::REPROCESSED;
+use crate::store::LocalStore;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AccuracyConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Range of the periodic report and of ACCURACY without arguments, days
+    #[serde(default = "default_window_days")]
+    pub window_days: u32,
+    /// Interval of the "accuracy" event and "model_accuracy" point, hours; 0 = off
+    #[serde(default = "default_report_hours")]
+    pub report_hours: u64,
+}
+
+fn default_enabled() -> bool { true }
+fn default_window_days() -> u32 { 7 }
+fn default_report_hours() -> u64 { 24 }
+
+impl Default for AccuracyConfig {
+    fn default() -> Self {
+        Self { enabled: default_enabled(), window_days: default_window_days(), report_hours: default_report_hours() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    accuracy: AccuracyConfig,
+}
+
+impl AccuracyConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().accuracy
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_days == 0 {
+            return Err("accuracy.window_days must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// GET /api/accuracy query; all optional
+#[derive(Debug, Deserialize, Default)]
+pub struct AccuracyQuery {
+    /// Epoch ms, default window_days ago
+    pub from: Option<i64>,
+    /// Epoch ms, default now
+    pub to: Option<i64>,
+    /// Default: the live models; a shadow model is reported only by name
+    pub model: Option<String>,
+    pub device: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ClassScore {
+    /// Cycles whose session is labeled with this class
+    pub support: u64,
+    /// Share of those predicted as this class
+    pub recall: Option<f64>,
+    /// Share of predictions of this class that were right
+    pub precision: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DailyAccuracy {
+    pub day: String,
+    pub samples: u64,
+    pub accuracy: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AccuracyReport {
+    pub from: i64,
+    pub to: i64,
+    pub models: Vec<String>,
+    /// Predictions with a labeled session
+    pub samples: u64,
+    /// Predictions in the range whose session has no label (yet)
+    pub unlabeled: u64,
+    pub accuracy: Option<f64>,
+    /// Row = true label, column = predicted label, in `labels` order
+    pub labels: Vec<String>,
+    pub matrix: Vec<Vec<u64>>,
+    pub classes: BTreeMap<String, ClassScore>,
+    /// Local days, oldest first
+    pub daily: Vec<DailyAccuracy>,
+}
+
+/// Labels compared case-insensitively ("Ethanol" session, "ethanol" model)
+fn normalize(label: &str) -> String {
+    label.trim().to_lowercase()
+}
+
+fn ratio(part: u64, whole: u64) -> Option<f64> {
+    (whole > 0).then(|| part as f64 / whole as f64)
+}
+
+// ================= Classifier Accuracy =================
+/// Every live prediction is kept with its session; the session label, set
+/// during or after the run (SESSION_LABEL), is the ground truth. Reports
+/// are computed on demand, so a label added later counts retroactively.
+#[derive(Clone)]
+pub struct AccuracyTracker {
+    config: Arc<AccuracyConfig>,
+    store: LocalStore,
+}
+
+impl AccuracyTracker {
+    pub fn new(config: AccuracyConfig, store: LocalStore) -> Self {
+        Self { config: Arc::new(config), store }
+    }
+
+    /// Keep a classified cycle of a session, live and shadow prediction;
+    /// replays are left out
+    pub fn record(&self, result: &CycleFeatures) {
+        if !self.config.enabled || result.session.is_empty() || result.source == REPROCESSED {
+            return;
+        }
+        let predictions = [(&result.prediction, false), (&result.shadow, true)];
+        for (prediction, shadow) in predictions.iter().filter_map(|(p, shadow)| Some((p.as_ref()?, *shadow))) {
+            if let Err(e) = self.store.conn().execute(
+                "INSERT INTO predictions (timestamp, device, session, label, confidence, model, shadow)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![
+                    result.ended_at,
+                    result.device,
+                    result.session,
+                    prediction.label,
+                    prediction.confidence,
+                    prediction.model,
+                    shadow
+                ],
+            ) {
+                eprintln!("❌ Failed to record prediction: {}", e);
+            }
+        }
+    }
+
+    pub fn report(&self, query: &AccuracyQuery) -> Result<AccuracyReport, String> {
+        let to = query.to.unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+        let from = query.from.unwrap_or(to - self.config.window_days as i64 * 86_400_000);
+
+        // (timestamp, predicted, truth, model)
+        let rows: Vec<(i64, String, Option<String>, String)> = {
+            let conn = self.store.conn();
+            let mut statement = conn
+                .prepare(
+                    "SELECT p.timestamp, p.label, s.label, p.model FROM predictions p
+                     LEFT JOIN sessions s ON s.id = p.session
+                     WHERE p.timestamp >= ?1 AND p.timestamp <= ?2
+                       AND ((?3 IS NULL AND p.shadow = 0) OR p.model = ?3) AND (?4 IS NULL OR p.device = ?4)
+                     ORDER BY p.timestamp",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = statement
+                .query_map(rusqlite::params![from, to, query.model, query.device], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                })
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+
+        let models: BTreeSet<String> = rows.iter().map(|r| r.3.clone()).collect();
+        let labeled: Vec<(i64, String, String)> = rows
+            .iter()
+            .filter_map(|(ts, predicted, truth, _)| {
+                let truth = truth.as_deref().map(normalize).filter(|t| !t.is_empty())?;
+                Some((*ts, normalize(predicted), truth))
+            })
+            .collect();
+        let labels: Vec<String> =
+            labeled.iter().flat_map(|(_, p, t)| [p.clone(), t.clone()]).collect::<BTreeSet<_>>().into_iter().collect();
+        let index = |label: &str| labels.iter().position(|l| l == label).unwrap_or_default();
+
+        let mut matrix = vec![vec![0u64; labels.len()]; labels.len()];
+        let mut daily: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+        for (ts, predicted, truth) in &labeled {
+            matrix[index(truth)][index(predicted)] += 1;
+            let day = chrono::DateTime::from_timestamp_millis(*ts)
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string())
+                .unwrap_or_default();
+            let entry = daily.entry(day).or_default();
+            entry.0 += 1;
+            entry.1 += (predicted == truth) as u64;
+        }
+
+        let correct: u64 = (0..labels.len()).map(|i| matrix[i][i]).sum();
+        let samples = labeled.len() as u64;
+        let classes = labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                let support: u64 = matrix[i].iter().sum();
+                let predicted: u64 = matrix.iter().map(|row| row[i]).sum();
+                let score = ClassScore { support, recall: ratio(matrix[i][i], support), precision: ratio(matrix[i][i], predicted) };
+                (label.clone(), score)
+            })
+            .collect();
+        Ok(AccuracyReport {
+            from,
+            to,
+            models: models.into_iter().collect(),
+            samples,
+            unlabeled: rows.len() as u64 - samples,
+            accuracy: ratio(correct, samples),
+            labels,
+            matrix,
+            classes,
+            daily: daily
+                .into_iter()
+                .map(|(day, (samples, correct))| DailyAccuracy { day, samples, accuracy: correct as f64 / samples as f64 })
+                .collect(),
+        })
+    }
+}
+
+fn point(report: &AccuracyReport) -> Option<DataPoint> {
+    let mut builder = DataPoint::builder("model_accuracy")
+        .tag("model", report.models.join(","))
+        .field("samples", report.samples as i64)
+        .field("unlabeled", report.unlabeled as i64)
+        .field("accuracy", report.accuracy?);
+    for (label, class) in &report.classes {
+        if let Some(recall) = class.recall {
+            builder = builder.field(format!("recall_{}", label), recall);
+        }
+    }
+    builder.timestamp(report.to * 1_000_000).build().ok()
+}
+
+/// Periodic report over the last window_days: "accuracy" GUI event and a
+/// "model_accuracy" point, so a falling accuracy (drift) shows on dashboards
+pub async fn run(tracker: AccuracyTracker, data_tx: broadcast::Sender<String>, influx: InfluxDBHandler) {
+    let mut interval = tokio::time::interval(Duration::from_secs(tracker.config.report_hours * 3600));
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let report = match tracker.report(&AccuracyQuery::default()) {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("❌ Accuracy report failed: {}", e);
+                continue;
+            }
+        };
+        if let Some(accuracy) = report.accuracy {
+            println!(
+                "🎯 Classifier accuracy over {} day(s): {:.1}% of {} labeled cycle(s)",
+                tracker.config.window_days,
+                accuracy * 100.0,
+                report.samples
+            );
+        }
+        let mut event = serde_json::to_value(&report).unwrap_or_default();
+        event["type"] = "accuracy".into();
+        let _ = data_tx.send(event.to_string());
+        if let Some(point) = point(&report) {
+            if let Err(e) = influx.write_points(vec![point]).await {
+                eprintln!("❌ Accuracy write error: {:?}", e);
+            }
+        }
+    }
+}
+
+/// GUI command:
+///   ACCURACY [days] [model]   -> reply: "accuracy" report over the last days (default window_days)
+pub fn handle_command(tracker: &AccuracyTracker, args: &[&str]) -> Result<Option<String>, String> {
+    let (days, model) = match args {
+        ["ACCURACY"] => (None, None),
+        ["ACCURACY", days, model @ ..] if model.len() <= 1 => {
+            let days: f64 = days.parse().map_err(|_| format!("invalid number of days '{}'", days))?;
+            (Some(days), model.first())
+        }
+        _ => return Err("usage: ACCURACY [days] [model]".to_string()),
+    };
+    let to = chrono::Utc::now().timestamp_millis();
+    let query = AccuracyQuery {
+        from: days.map(|d| to - (d * 86_400_000.0) as i64),
+        to: Some(to),
+        model: model.map(|m| m.to_string()),
+        device: None,
+    };
+    let mut event = serde_json::to_value(tracker.report(&query)?).unwrap_or_default();
+    event["type"] = "accuracy".into();
+    Ok(Some(event.to_string()))
+}
@@ -0,0 +1,163 @@
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::alerts::{AlertEngine, AlertState};
+use crate::clock::SharedClock;
+use crate::influxdb::UnifiedSensorData as InfluxData;
+use crate::UnifiedSensorData;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AdaptiveStorageConfig {
+    /// Thin storage outside events; GUI and subsystems still see every frame
+    #[serde(default)]
+    pub enabled: bool,
+    /// Normal rate: store 1 of every N frames
+    #[serde(default = "default_keep_every")]
+    pub keep_every: u64,
+    /// Frames this long before an event are stored retroactively
+    #[serde(default = "default_pre_secs")]
+    pub pre_secs: u64,
+    /// Full rate continues this long after the last event
+    #[serde(default = "default_post_secs")]
+    pub post_secs: u64,
+    /// Frame-to-frame change (percent) on any channel that counts as a peak, 0 = off
+    #[serde(default = "default_peak_percent")]
+    pub peak_percent: f32,
+    #[serde(default = "default_true")]
+    pub on_state_change: bool,
+    /// Firing alert rules and flow alarms
+    #[serde(default = "default_true")]
+    pub on_alarm: bool,
+}
+
+fn default_keep_every() -> u64 { 10 }
+fn default_pre_secs() -> u64 { 10 }
+fn default_post_secs() -> u64 { 30 }
+fn default_peak_percent() -> f32 { 5.0 }
+fn default_true() -> bool { true }
+
+impl Default for AdaptiveStorageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keep_every: default_keep_every(),
+            pre_secs: default_pre_secs(),
+            post_secs: default_post_secs(),
+            peak_percent: default_peak_percent(),
+            on_state_change: true,
+            on_alarm: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    adaptive_storage: AdaptiveStorageConfig,
+}
+
+impl AdaptiveStorageConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().adaptive_storage
+    }
+}
+
+#[derive(Default)]
+struct DeviceRate {
+    counter: u64,
+    last: Option<(i32, [f32; 7])>,
+    /// Frames skipped recently, written if an event follows within pre_secs
+    skipped: VecDeque<(i64, InfluxData)>,
+    full_until: i64,
+}
+
+// ================= Adaptive Storage Rate =================
+/// Decides which processed frames are written to InfluxDB
+#[derive(Clone, Default)]
+pub struct AdaptiveStorage {
+    config: Arc<AdaptiveStorageConfig>,
+    devices: Arc<Mutex<HashMap<String, DeviceRate>>>,
+    /// Last alarm; every device stores at full rate for post_secs after it
+    alarm_at: Arc<Mutex<Option<i64>>>,
+}
+
+impl AdaptiveStorage {
+    pub fn new(config: AdaptiveStorageConfig) -> Self {
+        Self { config: Arc::new(config), ..Default::default() }
+    }
+
+    /// Switch to full rate from the next frame on (alarm raised elsewhere)
+    pub fn trigger(&self, timestamp: i64) {
+        if self.config.enabled && self.config.on_alarm {
+            *self.alarm_at.lock().unwrap() = Some(timestamp);
+        }
+    }
+
+    /// Points to write for this frame: none, the frame itself, or the
+    /// frame preceded by the skipped pre-event frames
+    pub fn admit(&self, frame: &UnifiedSensorData, data: InfluxData) -> Vec<InfluxData> {
+        if !self.config.enabled {
+            return vec![data];
+        }
+        let config = &self.config;
+        let alarm = self
+            .alarm_at
+            .lock()
+            .unwrap()
+            .is_some_and(|at| frame.timestamp - at <= config.post_secs as i64 * 1000);
+
+        let mut devices = self.devices.lock().unwrap();
+        let device = devices.entry(frame.device.clone()).or_default();
+        let channels = frame.channels.values();
+        let state_change = config.on_state_change && device.last.is_some_and(|(state, _)| state != frame.state);
+        let peak = config.peak_percent > 0.0
+            && device.last.is_some_and(|(_, last)| {
+                last.iter().zip(channels).any(|(old, new)| {
+                    old.abs() > f32::EPSILON && (new - old).abs() / old.abs() * 100.0 > config.peak_percent
+                })
+            });
+        device.last = Some((frame.state, channels));
+
+        if state_change || peak || alarm {
+            if frame.timestamp >= device.full_until {
+                let reason = if alarm { "alarm" } else if state_change { "state change" } else { "peak" };
+                println!("💾 Full-rate storage for {} ({})", frame.device, reason);
+            }
+            device.full_until = frame.timestamp + config.post_secs as i64 * 1000;
+            // Frame sebelum event ikut disimpan supaya onset-nya lengkap
+            let mut points: Vec<InfluxData> = device.skipped.drain(..).map(|(_, point)| point).collect();
+            points.push(data);
+            return points;
+        }
+        if frame.timestamp < device.full_until {
+            return vec![data];
+        }
+
+        device.counter += 1;
+        if device.counter.is_multiple_of(config.keep_every.max(1)) {
+            return vec![data];
+        }
+        device.skipped.push_back((frame.timestamp, data));
+        let horizon = frame.timestamp - config.pre_secs as i64 * 1000;
+        while device.skipped.front().is_some_and(|(at, _)| *at < horizon) {
+            device.skipped.pop_front();
+        }
+        Vec::new()
+    }
+}
+
+/// Firing alert rules switch storage to full rate
+pub async fn watch_alerts(storage: AdaptiveStorage, alerts: AlertEngine, clock: SharedClock) {
+    let mut events = alerts.subscribe();
+    loop {
+        match events.recv().await {
+            Ok(event) if event.state == AlertState::Firing => storage.trigger(clock.now_millis()),
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio_native_tls::native_tls;
+
+use crate::provenance::Provenance;
+use crate::{AppState, UnifiedSensorData};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AggregatorConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_bind")]
+    pub bind: String,
+    /// PEM certificate chain and PKCS#8 key for TLS
+    #[serde(default)]
+    pub cert_path: String,
+    #[serde(default)]
+    pub key_path: String,
+    /// Edge name -> shared token; unknown edges are rejected
+    #[serde(default)]
+    pub edges: BTreeMap<String, String>,
+}
+
+fn default_bind() -> String { "0.0.0.0:8090".to_string() }
+
+impl Default for AggregatorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: default_bind(),
+            cert_path: String::new(),
+            key_path: String::new(),
+            edges: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    aggregator: AggregatorConfig,
+}
+
+impl AggregatorConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().aggregator
+    }
+}
+
+/// Edge -> aggregator messages, one JSON per line
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UplinkMessage {
+    Hello { edge: String, token: String },
+    /// `seq` increases per edge and is acknowledged once the frame is ingested
+    Frame { seq: u64, frame: Box<UnifiedSensorData>, provenance: Provenance },
+    /// Keeps an idle link open
+    Ping,
+}
+
+/// Aggregator -> edge replies
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AggregatorReply {
+    Welcome,
+    Ack { seq: u64 },
+    Error { error: String },
+}
+
+/// Edges that stay silent this long (no frames or pings) are disconnected
+const IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+// ================= Aggregator =================
+/// Central fan-in: accepts frames forwarded by edge backends over TLS and
+/// publishes them under "<edge>/<device>" like local ones
+pub async fn serve(config: AggregatorConfig, state: AppState) -> anyhow::Result<()> {
+    let cert = std::fs::read(&config.cert_path)?;
+    let key = std::fs::read(&config.key_path)?;
+    let identity = native_tls::Identity::from_pkcs8(&cert, &key)?;
+    let acceptor = tokio_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity)?);
+
+    let listener = state.diagnostics.bind("aggregator", &config.bind).await?;
+    println!("🛰️ Aggregator listening on {} ({} edges)", config.bind, config.edges.len());
+
+    loop {
+        let (socket, addr) = listener.accept().await?;
+        let acceptor = acceptor.clone();
+        let edges = config.edges.clone();
+        let state = state.clone();
+        tokio::spawn(async move {
+            match acceptor.accept(socket).await {
+                Ok(stream) => {
+                    if let Err(e) = handle_edge(stream, &edges, &state).await {
+                        eprintln!("❌ Edge {}: {}", addr, e);
+                    }
+                }
+                Err(e) => eprintln!("❌ TLS handshake with {} failed: {}", addr, e),
+            }
+        });
+    }
+}
+
+async fn reply<W: AsyncWrite + Unpin>(writer: &mut W, message: &AggregatorReply) -> anyhow::Result<()> {
+    writer.write_all(format!("{}\n", serde_json::to_string(message)?).as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+async fn handle_edge<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    edges: &BTreeMap<String, String>,
+    state: &AppState,
+) -> anyhow::Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    let hello = tokio::time::timeout(Duration::from_secs(10), lines.next_line()).await??;
+    let edge = match hello.as_deref().map(serde_json::from_str::<UplinkMessage>) {
+        Some(Ok(UplinkMessage::Hello { edge, token })) if edges.get(&edge) == Some(&token) => edge,
+        _ => {
+            let error = "unknown edge or bad token".to_string();
+            reply(&mut writer, &AggregatorReply::Error { error: error.clone() }).await?;
+            anyhow::bail!(error);
+        }
+    };
+    reply(&mut writer, &AggregatorReply::Welcome).await?;
+    println!("🛰️ Edge '{}' connected", edge);
+
+    while let Some(line) = tokio::time::timeout(IDLE_TIMEOUT, lines.next_line()).await?? {
+        match serde_json::from_str::<UplinkMessage>(&line) {
+            Ok(UplinkMessage::Frame { seq, mut frame, provenance }) => {
+                // Namespace device per edge supaya id tidak bentrok antar lokasi
+                let device = if frame.device.is_empty() { "arduino" } else { frame.device.as_str() };
+                frame.device = format!("{}/{}", edge, device);
+                crate::publish_frame(state, *frame, &provenance, None, None).await;
+                reply(&mut writer, &AggregatorReply::Ack { seq }).await?;
+            }
+            Ok(UplinkMessage::Hello { .. } | UplinkMessage::Ping) => {}
+            Err(e) => eprintln!("⚠️ Edge '{}' sent an invalid message: {}", edge, e),
+        }
+    }
+    println!("🛰️ Edge '{}' disconnected", edge);
+    Ok(())
+}
@@ -0,0 +1,680 @@
+use chrono::{Datelike, Local, NaiveTime, Utc, Weekday};
+use evalexpr::{ContextWithMutableVariables, HashMapContext, Node, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::audit::AuditLog;
+use crate::noise_floor::NoiseFloor;
+use crate::UnifiedSensorData;
+
+/// One rule from `[[alerts]]`, e.g.
+/// `condition = "co > 30 && d_co_dt > 5 for 20s"`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AlertRuleDef {
+    pub name: String,
+    pub condition: String,
+    /// Condition must hold this long before firing (overrides a `for` suffix)
+    #[serde(default)]
+    pub for_secs: Option<f64>,
+    /// Once firing, the clear condition must hold this long before resolving
+    #[serde(default)]
+    pub clear_for_secs: f64,
+    #[serde(default = "default_severity")]
+    pub severity: String,
+    /// Hysteresis: once firing, resolve only when this holds
+    /// (e.g. fire at `co > 30`, clear at `co < 25`). Default: !condition
+    #[serde(default)]
+    pub clear_condition: Option<String>,
+    /// Repeat the notification while firing, 0 = never
+    #[serde(default)]
+    pub renotify_secs: f64,
+    /// Notification targets when the alert starts firing
+    #[serde(default)]
+    pub targets: Vec<String>,
+    /// Extra targets added the longer the alert stays unresolved
+    #[serde(default)]
+    pub escalation: Vec<Escalation>,
+    /// Only evaluate on frames of this mode, "cycle" or "ambient" (see [ambient]); None = both
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    /// Daily windows in which the rule does not start firing (e.g. cleaning hours)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quiet_hours: Vec<QuietHours>,
+}
+
+/// `{ from = "06:00", to = "07:30", days = ["mon", "fri"] }`, local time;
+/// `to` before `from` runs past midnight
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct QuietHours {
+    pub from: String,
+    pub to: String,
+    /// Days the window starts on; empty = every day
+    #[serde(default)]
+    pub days: Vec<String>,
+}
+
+struct QuietWindow {
+    from: NaiveTime,
+    to: NaiveTime,
+    days: Vec<Weekday>,
+}
+
+impl QuietWindow {
+    fn parse(def: &QuietHours) -> Result<Self, String> {
+        let time = |s: &str| NaiveTime::parse_from_str(s, "%H:%M").map_err(|_| format!("quiet hours time '{}' is not HH:MM", s));
+        let days = def
+            .days
+            .iter()
+            .map(|d| d.parse::<Weekday>().map_err(|_| format!("unknown weekday '{}'", d)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { from: time(&def.from)?, to: time(&def.to)?, days })
+    }
+
+    fn contains(&self, timestamp: i64) -> bool {
+        let Some(at) = chrono::DateTime::from_timestamp_millis(timestamp) else { return false };
+        let at = at.with_timezone(&Local);
+        let (time, day) = (at.time(), at.weekday());
+        let on = |day: Weekday| self.days.is_empty() || self.days.contains(&day);
+        if self.from <= self.to {
+            on(day) && time >= self.from && time < self.to
+        } else {
+            // Melewati tengah malam: bagian pagi milik hari sebelumnya
+            (on(day) && time >= self.from) || (on(day.pred()) && time < self.to)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Escalation {
+    pub after_secs: f64,
+    pub target: String,
+    /// Raise the severity at this level
+    #[serde(default)]
+    pub severity: Option<String>,
+}
+
+fn default_severity() -> String { "warning".to_string() }
+
+impl AlertRuleDef {
+    pub fn new(name: &str, condition: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            condition: condition.to_string(),
+            for_secs: None,
+            clear_for_secs: 0.0,
+            severity: default_severity(),
+            clear_condition: None,
+            renotify_secs: 0.0,
+            targets: Vec::new(),
+            escalation: Vec::new(),
+            mode: None,
+            quiet_hours: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    alerts: Vec<AlertRuleDef>,
+}
+
+/// Split `"<expr> for 20s"` into the expression and its duration in ms
+fn split_duration(condition: &str) -> Result<(&str, i64), String> {
+    let Some((expr, duration)) = condition.rsplit_once(" for ") else {
+        return Ok((condition.trim(), 0));
+    };
+    let duration = duration.trim();
+    let (number, scale) = match duration.chars().last() {
+        Some('s') => (&duration[..duration.len() - 1], 1000.0),
+        Some('m') => (&duration[..duration.len() - 1], 60_000.0),
+        Some('h') => (&duration[..duration.len() - 1], 3_600_000.0),
+        _ => (duration, 1000.0),
+    };
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", duration))?;
+    Ok((expr.trim(), (value * scale) as i64))
+}
+
+/// A rule definition checked and parsed
+pub struct Compiled {
+    node: Node,
+    clear: Option<Node>,
+    hold_ms: i64,
+    clear_hold_ms: i64,
+    quiet: Vec<QuietWindow>,
+}
+
+pub fn compile(def: &AlertRuleDef) -> Result<Compiled, String> {
+    let err = |e: String| format!("alert '{}': {}", def.name, e);
+    if def.mode.as_deref().is_some_and(|m| m != "cycle" && m != "ambient") {
+        return Err(err("mode must be \"cycle\" or \"ambient\"".to_string()));
+    }
+    let (expr, hold_ms) = split_duration(&def.condition).map_err(err)?;
+    let node = evalexpr::build_operator_tree(expr).map_err(|e| err(e.to_string()))?;
+    let clear = def
+        .clear_condition
+        .as_deref()
+        .map(evalexpr::build_operator_tree)
+        .transpose()
+        .map_err(|e| err(e.to_string()))?;
+    let hold_ms = def.for_secs.map(|s| (s * 1000.0) as i64).unwrap_or(hold_ms);
+    if def.clear_for_secs < 0.0 {
+        return Err(err("clear_for_secs must not be negative".to_string()));
+    }
+    let quiet = def.quiet_hours.iter().map(QuietWindow::parse).collect::<Result<_, _>>().map_err(err)?;
+    Ok(Compiled { node, clear, hold_ms, clear_hold_ms: (def.clear_for_secs * 1000.0) as i64, quiet })
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    #[default]
+    Inactive,
+    /// Condition holds, waiting out the `for` duration
+    Pending,
+    Firing,
+    Resolved,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyReason {
+    Transition,
+    Renotify,
+    Escalation,
+    Acknowledged,
+    Silenced,
+}
+
+/// Lifecycle of one rule on one device
+#[derive(Default)]
+struct Lifecycle {
+    state: AlertState,
+    /// Timestamp since which the condition has held
+    true_since: i64,
+    /// Timestamp since which the clear condition has held while firing
+    clear_since: Option<i64>,
+    fired_at: i64,
+    last_notified: i64,
+    /// Number of escalation levels reached
+    escalation_level: usize,
+    /// Set by ALERT_ACK, cleared when the alert resolves
+    acknowledged_by: Option<String>,
+}
+
+struct Rule {
+    def: AlertRuleDef,
+    node: Node,
+    clear: Option<Node>,
+    hold_ms: i64,
+    clear_hold_ms: i64,
+    quiet: Vec<QuietWindow>,
+    /// Silences the rule on every device
+    silenced_until: Option<i64>,
+    /// Lifecycle per device, each device's frames evaluated on their own
+    devices: HashMap<String, Lifecycle>,
+}
+
+impl Rule {
+    fn event(&self, device: &str, lifecycle: &Lifecycle, state: AlertState, reason: NotifyReason, timestamp: i64) -> AlertEvent {
+        let reached = &self.def.escalation[..lifecycle.escalation_level];
+        AlertEvent {
+            name: self.def.name.clone(),
+            device: device.to_string(),
+            severity: reached
+                .iter()
+                .rev()
+                .find_map(|e| e.severity.clone())
+                .unwrap_or_else(|| self.def.severity.clone()),
+            condition: self.def.condition.clone(),
+            state,
+            reason,
+            active: state == AlertState::Firing,
+            escalation_level: lifecycle.escalation_level,
+            acknowledged_by: lifecycle.acknowledged_by.clone(),
+            silenced: self.silenced(timestamp),
+            silenced_until: self.silenced_until.filter(|until| *until > timestamp),
+            targets: self
+                .def
+                .targets
+                .iter()
+                .cloned()
+                .chain(reached.iter().map(|e| e.target.clone()))
+                .collect(),
+            timestamp,
+        }
+    }
+
+    fn silenced(&self, now: i64) -> bool {
+        self.silenced_until.is_some_and(|until| until > now)
+    }
+
+    fn quiet(&self, timestamp: i64) -> bool {
+        self.quiet.iter().any(|window| window.contains(timestamp))
+    }
+
+    /// Advance a device's lifecycle by one of its frames
+    fn step(&self, device: &str, lc: &mut Lifecycle, context: &HashMapContext, timestamp: i64) -> Option<AlertEvent> {
+        // Variabel yang belum tersedia (mis. rate pada frame pertama) dianggap false
+        let holds = self.node.eval_boolean_with_context(context).unwrap_or(false);
+        // Jam tenang: tidak mulai firing; alert yang sudah firing tetap berjalan
+        let quiet = lc.state != AlertState::Firing && self.quiet(timestamp);
+
+        match lc.state {
+            AlertState::Inactive | AlertState::Resolved => {
+                if !holds || quiet {
+                    lc.state = AlertState::Inactive;
+                    return None;
+                }
+                lc.true_since = timestamp;
+                lc.state = AlertState::Pending;
+                if self.hold_ms > 0 {
+                    return Some(self.event(device, lc, AlertState::Pending, NotifyReason::Transition, timestamp));
+                }
+                self.fire(device, lc, timestamp)
+            }
+            AlertState::Pending if !holds || quiet => {
+                lc.state = AlertState::Inactive;
+                Some(self.event(device, lc, AlertState::Inactive, NotifyReason::Transition, timestamp))
+            }
+            AlertState::Pending if timestamp - lc.true_since >= self.hold_ms => self.fire(device, lc, timestamp),
+            AlertState::Pending => None,
+            AlertState::Firing => {
+                let cleared = match &self.clear {
+                    Some(clear) => clear.eval_boolean_with_context(context).unwrap_or(false),
+                    None => !holds,
+                };
+                if !cleared {
+                    lc.clear_since = None;
+                } else if timestamp - *lc.clear_since.get_or_insert(timestamp) >= self.clear_hold_ms {
+                    lc.state = AlertState::Resolved;
+                    lc.clear_since = None;
+                    let event = self.event(device, lc, AlertState::Resolved, NotifyReason::Transition, timestamp);
+                    lc.escalation_level = 0;
+                    lc.acknowledged_by = None;
+                    return Some(event);
+                }
+
+                // Sudah di-acknowledge: tidak ada renotify/eskalasi sampai resolved
+                if lc.acknowledged_by.is_some() {
+                    return None;
+                }
+
+                let elapsed = (timestamp - lc.fired_at) as f64 / 1000.0;
+                if let Some(next) = self.def.escalation.get(lc.escalation_level) {
+                    if elapsed >= next.after_secs {
+                        lc.escalation_level += 1;
+                        lc.last_notified = timestamp;
+                        return Some(self.event(device, lc, AlertState::Firing, NotifyReason::Escalation, timestamp));
+                    }
+                }
+                let renotify_ms = (self.def.renotify_secs * 1000.0) as i64;
+                if renotify_ms > 0 && timestamp - lc.last_notified >= renotify_ms {
+                    lc.last_notified = timestamp;
+                    return Some(self.event(device, lc, AlertState::Firing, NotifyReason::Renotify, timestamp));
+                }
+                None
+            }
+        }
+    }
+
+    fn fire(&self, device: &str, lc: &mut Lifecycle, timestamp: i64) -> Option<AlertEvent> {
+        lc.state = AlertState::Firing;
+        lc.fired_at = timestamp;
+        lc.last_notified = timestamp;
+        lc.escalation_level = 0;
+        Some(self.event(device, lc, AlertState::Firing, NotifyReason::Transition, timestamp))
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AlertEvent {
+    pub name: String,
+    /// Device whose frames (or limits, aborts) raised it
+    pub device: String,
+    pub severity: String,
+    pub condition: String,
+    pub state: AlertState,
+    pub reason: NotifyReason,
+    pub active: bool,
+    pub escalation_level: usize,
+    pub acknowledged_by: Option<String>,
+    /// Still shown to the GUI, but notification channels should skip it
+    pub silenced: bool,
+    pub silenced_until: Option<i64>,
+    /// Base targets plus those of every escalation level reached
+    pub targets: Vec<String>,
+    pub timestamp: i64,
+}
+
+impl AlertEvent {
+    /// Whether a person should be notified (firing, renotify, escalation,
+    /// resolved), as opposed to GUI-only status changes
+    pub fn should_notify(&self) -> bool {
+        if self.silenced {
+            return false;
+        }
+        match self.reason {
+            NotifyReason::Transition => matches!(self.state, AlertState::Firing | AlertState::Resolved),
+            NotifyReason::Renotify | NotifyReason::Escalation => true,
+            NotifyReason::Acknowledged | NotifyReason::Silenced => false,
+        }
+    }
+}
+
+// ================= Alert Engine =================
+/// Expression rules over channels, virtual channels, their rates of
+/// change (`d_<name>_dt`, units per second) and, for characterized
+/// channels, their distance from the quiet level in noise RMS (`snr_<name>`)
+#[derive(Clone)]
+pub struct AlertEngine {
+    rules: Arc<Mutex<Vec<Rule>>>,
+    /// Firing events raised outside the rules, by device and name, until resolved
+    raised: Arc<Mutex<HashMap<(String, String), AlertEvent>>>,
+    noise: NoiseFloor,
+    data_tx: broadcast::Sender<String>,
+    /// Lifecycle events for notification channels (SMS, MQTT, ...)
+    events_tx: broadcast::Sender<AlertEvent>,
+}
+
+impl AlertEngine {
+    pub fn load(path: &str, data_tx: broadcast::Sender<String>, noise: NoiseFloor) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        let file: ConfigFile = toml::from_str(&content).unwrap_or_default();
+        let (events_tx, _) = broadcast::channel(100);
+        let engine = Self { rules: Arc::new(Mutex::new(Vec::new())), raised: Arc::default(), noise, data_tx, events_tx };
+        for def in file.alerts {
+            if let Err(e) = engine.add(def) {
+                eprintln!("⚠️ {}", e);
+            }
+        }
+        engine
+    }
+
+    /// Add or replace a rule by name
+    pub fn add(&self, def: AlertRuleDef) -> Result<(), String> {
+        let Compiled { node, clear, hold_ms, clear_hold_ms, quiet } = compile(&def)?;
+        let mut rules = self.rules.lock().unwrap();
+        rules.retain(|r| r.def.name != def.name);
+        rules.push(Rule {
+            def,
+            node,
+            clear,
+            hold_ms,
+            clear_hold_ms,
+            quiet,
+            silenced_until: None,
+            devices: HashMap::new(),
+        });
+        Ok(())
+    }
+
+    pub fn remove(&self, name: &str) -> Result<(), String> {
+        let mut rules = self.rules.lock().unwrap();
+        let before = rules.len();
+        rules.retain(|r| r.def.name != name);
+        if rules.len() == before {
+            return Err(format!("no alert rule '{}'", name));
+        }
+        Ok(())
+    }
+
+    /// Broadcast rule definitions and their current state
+    pub fn publish(&self) {
+        let now = Utc::now().timestamp_millis();
+        let rules = self.rules.lock().unwrap();
+        let list: Vec<_> = rules
+            .iter()
+            .map(|r| {
+                let mut entry = serde_json::to_value(&r.def).unwrap_or_default();
+                let devices: Vec<_> = r
+                    .devices
+                    .iter()
+                    .map(|(device, lc)| {
+                        serde_json::json!({
+                            "device": device,
+                            "state": lc.state,
+                            "escalation_level": lc.escalation_level,
+                            "acknowledged_by": lc.acknowledged_by,
+                        })
+                    })
+                    .collect();
+                entry["devices"] = devices.into();
+                entry["silenced_until"] = r.silenced_until.filter(|until| *until > now).into();
+                entry["quiet"] = r.quiet(now).into();
+                entry
+            })
+            .collect();
+        let event = serde_json::json!({ "type": "alert_rules", "rules": list });
+        let _ = self.data_tx.send(event.to_string());
+    }
+
+    /// Acknowledge a firing alert on every device it fires on: stops
+    /// re-notification and escalation
+    pub fn acknowledge(&self, name: &str, by: &str) -> Result<(), String> {
+        let now = Utc::now().timestamp_millis();
+        let events: Vec<_> = {
+            let mut rules = self.rules.lock().unwrap();
+            let rule = rules
+                .iter_mut()
+                .find(|r| r.def.name == name)
+                .ok_or_else(|| format!("no alert rule '{}'", name))?;
+            for lc in rule.devices.values_mut().filter(|lc| lc.state == AlertState::Firing) {
+                lc.acknowledged_by = Some(by.to_string());
+            }
+            rule.devices
+                .iter()
+                .filter(|(_, lc)| lc.state == AlertState::Firing)
+                .map(|(device, lc)| rule.event(device, lc, AlertState::Firing, NotifyReason::Acknowledged, now))
+                .collect()
+        };
+        if events.is_empty() {
+            return Err(format!("alert '{}' is not firing", name));
+        }
+        for event in &events {
+            self.send(event);
+        }
+        Ok(())
+    }
+
+    /// Suppress notifications for `secs` seconds (0 lifts the silence)
+    pub fn silence(&self, name: &str, secs: f64) -> Result<(), String> {
+        let now = Utc::now().timestamp_millis();
+        let events: Vec<_> = {
+            let mut rules = self.rules.lock().unwrap();
+            let rule = rules
+                .iter_mut()
+                .find(|r| r.def.name == name)
+                .ok_or_else(|| format!("no alert rule '{}'", name))?;
+            rule.silenced_until = (secs > 0.0).then(|| now + (secs * 1000.0) as i64);
+            rule.devices
+                .iter()
+                .map(|(device, lc)| rule.event(device, lc, lc.state, NotifyReason::Silenced, now))
+                .collect()
+        };
+        for event in &events {
+            self.send(event);
+        }
+        Ok(())
+    }
+
+    /// Publish an event raised outside the rules (e.g. [limits]) to the GUI and notifiers
+    pub fn raise(&self, event: &AlertEvent) {
+        {
+            let mut raised = self.raised.lock().unwrap();
+            match event.state {
+                AlertState::Firing => raised.insert((event.device.clone(), event.name.clone()), event.clone()),
+                _ => raised.remove(&(event.device.clone(), event.name.clone())),
+            };
+        }
+        self.send(event);
+    }
+
+    /// Alerts currently firing, rules and raised events alike, oldest first
+    pub fn open(&self) -> Vec<AlertEvent> {
+        let now = Utc::now().timestamp_millis();
+        let mut open: Vec<AlertEvent> = {
+            let rules = self.rules.lock().unwrap();
+            rules
+                .iter()
+                .flat_map(|r| r.devices.iter().map(move |(device, lc)| (r, device, lc)))
+                .filter(|(_, _, lc)| lc.state == AlertState::Firing)
+                .map(|(r, device, lc)| AlertEvent {
+                    timestamp: lc.fired_at,
+                    ..r.event(device, lc, AlertState::Firing, NotifyReason::Transition, now)
+                })
+                .collect()
+        };
+        open.extend(self.raised.lock().unwrap().values().cloned());
+        open.sort_by_key(|event| event.timestamp);
+        open
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<AlertEvent> {
+        self.events_tx.subscribe()
+    }
+
+    fn send(&self, event: &AlertEvent) {
+        let mut json = serde_json::to_value(event).unwrap_or_default();
+        json["type"] = "alert".into();
+        let _ = self.data_tx.send(json.to_string());
+        let _ = self.events_tx.send(event.clone());
+    }
+
+    /// Evaluate the rules of a frame's mode against one device's frame,
+    /// returning lifecycle events
+    fn evaluate(&self, device: &str, context: &HashMapContext, timestamp: i64, mode: &str) -> Vec<AlertEvent> {
+        let mut rules = self.rules.lock().unwrap();
+        rules
+            .iter_mut()
+            .filter(|rule| rule.def.mode.as_deref().is_none_or(|m| m == mode))
+            .filter_map(|rule| {
+                let mut lc = rule.devices.remove(device).unwrap_or_default();
+                let event = rule.step(device, &mut lc, context, timestamp);
+                rule.devices.insert(device.to_string(), lc);
+                event
+            })
+            .collect()
+    }
+
+    pub async fn run(self, frame_tx: broadcast::Sender<UnifiedSensorData>) {
+        let mut frame_rx = frame_tx.subscribe();
+        // Previous frame per device, for the d_<name>_dt rates
+        let mut previous: HashMap<String, (i64, HashMap<String, f64>)> = HashMap::new();
+
+        loop {
+            let frame = match frame_rx.recv().await {
+                Ok(frame) => frame,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            let values = frame_values(&frame);
+            let mut context = HashMapContext::new();
+            for (name, value) in &values {
+                let _ = context.set_value(name.clone(), Value::Float(*value));
+            }
+            for (name, snr) in self.noise.snr(&frame.device, values.iter()) {
+                let _ = context.set_value(format!("snr_{}", name), Value::Float(snr));
+            }
+            let _ = context.set_value("state".into(), Value::Int(frame.state as i64));
+            let _ = context.set_value("level".into(), Value::Int(frame.level as i64));
+
+            if let Some((last_ts, last_values)) = previous.get(&frame.device) {
+                let dt = (frame.timestamp - last_ts) as f64 / 1000.0;
+                if dt > 0.0 {
+                    for (name, value) in &values {
+                        if let Some(last) = last_values.get(name) {
+                            let _ = context.set_value(format!("d_{}_dt", name), Value::Float((value - last) / dt));
+                        }
+                    }
+                }
+            }
+
+            let mode = if frame.state == crate::ambient::AMBIENT { "ambient" } else { "cycle" };
+            for event in self.evaluate(&frame.device, &context, frame.timestamp, mode) {
+                match (event.state, event.reason) {
+                    (AlertState::Firing, NotifyReason::Escalation) => {
+                        eprintln!("🚨 Alert '{}' on {} escalated to {:?}", event.name, event.device, event.targets)
+                    }
+                    (AlertState::Firing, NotifyReason::Transition) => {
+                        eprintln!("🚨 Alert '{}' firing on {}: {}", event.name, event.device, event.condition)
+                    }
+                    (AlertState::Resolved, _) => println!("✅ Alert '{}' resolved on {}", event.name, event.device),
+                    _ => {}
+                }
+                self.send(&event);
+            }
+
+            previous.insert(frame.device.clone(), (frame.timestamp, values));
+        }
+    }
+}
+
+/// Numeric inputs available to alert expressions
+pub fn frame_values(frame: &UnifiedSensorData) -> HashMap<String, f64> {
+    let mut values: HashMap<String, f64> = frame
+        .channels
+        .iter()
+        .map(|(channel, value)| (channel.name().to_string(), value as f64))
+        .collect();
+    let aux = [
+        ("flow", frame.flow),
+        ("pressure", frame.pressure),
+        ("humidity", frame.humidity),
+        ("heater_temp", frame.heater_temp),
+        ("delivered_ppm", frame.delivered_ppm),
+    ];
+    for (name, value) in aux {
+        if let Some(value) = value {
+            values.insert(name.to_string(), value as f64);
+        }
+    }
+    for (name, value) in &frame.virtual_channels {
+        values.insert(name.clone(), *value as f64);
+    }
+    values
+}
+
+/// GUI commands:
+///   ALERT_ADD <name> <condition...>   e.g. ALERT_ADD co_spike co > 30 && d_co_dt > 5 for 20s
+///   ALERT_REMOVE <name>
+///   ALERT_LIST
+///   ALERT_ACK <name> [comment...]
+///   ALERT_SILENCE <name> <secs>       0 lifts the silence
+/// Runtime changes are not written back; add permanent rules to config.toml.
+/// Acknowledgments and silences are recorded in the audit log.
+pub fn handle_command(engine: &AlertEngine, audit: &AuditLog, client: &str, args: &[&str]) -> Result<(), String> {
+    match args {
+        ["ALERT_ACK", name, comment @ ..] => {
+            engine.acknowledge(name, client)?;
+            audit.record(client, "alert_ack", serde_json::json!({ "alert": name, "comment": comment.join(" ") }));
+            println!("🔕 Alert '{}' acknowledged by {}", name, client);
+        }
+        ["ALERT_SILENCE", name, secs] => {
+            let secs: f64 = secs.parse().map_err(|_| format!("invalid duration '{}'", secs))?;
+            engine.silence(name, secs)?;
+            audit.record(client, "alert_silence", serde_json::json!({ "alert": name, "secs": secs }));
+            println!("🔕 Alert '{}' silenced for {}s by {}", name, secs, client);
+        }
+        ["ALERT_ADD", name, condition @ ..] if !condition.is_empty() => {
+            engine.add(AlertRuleDef::new(name, &condition.join(" ")))?;
+            println!("🔔 Alert rule '{}' set", name);
+        }
+        ["ALERT_REMOVE", name] => engine.remove(name)?,
+        ["ALERT_LIST"] => {}
+        _ => {
+            return Err(
+                "usage: ALERT_ADD <name> <condition> | ALERT_REMOVE <name> | ALERT_LIST | ALERT_ACK <name> | ALERT_SILENCE <name> <secs>"
+                    .to_string(),
+            )
+        }
+    }
+    engine.publish();
+    Ok(())
+}
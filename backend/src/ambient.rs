@@ -0,0 +1,328 @@
+use influxdb2::models::DataPoint;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::channels::{Channel, Channels};
+use crate::weather::{LatestWeather, Wind};
+use crate::UnifiedSensorData;
+
+/// FSM state of every frame from a device in ambient mode (see state_to_name)
+pub const AMBIENT: i32 = 7;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AmbientConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Sent to the device on connect, in order (e.g. a slower sampling rate)
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// One InfluxDB point per this interval outside events, ms; 0 = every frame
+    #[serde(default = "default_store_interval_ms")]
+    pub store_interval_ms: i64,
+    /// Time constant of the slow baseline per channel, s; frozen during events
+    #[serde(default = "default_baseline_tau_secs")]
+    pub baseline_tau_secs: f64,
+    /// Deviation from baseline (percent) on any channel that opens an event
+    #[serde(default = "default_event_percent")]
+    pub event_percent: f32,
+    /// The event closes once every channel is back within this percent
+    #[serde(default = "default_clear_percent")]
+    pub clear_percent: f32,
+    /// Anemometer channel (degrees the wind comes from); empty = [weather] wind
+    #[serde(default)]
+    pub wind_direction_channel: String,
+    /// Anemometer speed channel, km/h; empty = no calm check for the channel
+    #[serde(default)]
+    pub wind_speed_channel: String,
+    /// Below this wind speed the direction says nothing about the source, km/h
+    #[serde(default = "default_calm_kmh")]
+    pub calm_kmh: f32,
+    /// [weather] readings older than this are not used for bearings, s
+    #[serde(default = "default_weather_max_age_secs")]
+    pub weather_max_age_secs: i64,
+}
+
+fn default_store_interval_ms() -> i64 { 60_000 }
+fn default_baseline_tau_secs() -> f64 { 1800.0 }
+fn default_event_percent() -> f32 { 20.0 }
+fn default_clear_percent() -> f32 { 10.0 }
+fn default_calm_kmh() -> f32 { 1.0 }
+fn default_weather_max_age_secs() -> i64 { 1800 }
+
+impl Default for AmbientConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            commands: Vec::new(),
+            store_interval_ms: default_store_interval_ms(),
+            baseline_tau_secs: default_baseline_tau_secs(),
+            event_percent: default_event_percent(),
+            clear_percent: default_clear_percent(),
+            wind_direction_channel: String::new(),
+            wind_speed_channel: String::new(),
+            calm_kmh: default_calm_kmh(),
+            weather_max_age_secs: default_weather_max_age_secs(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    ambient: AmbientConfig,
+}
+
+impl AmbientConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc).try_into::<ConfigFile>().unwrap_or_default().ambient
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.baseline_tau_secs <= 0.0 {
+            return Err("ambient.baseline_tau_secs must be positive".to_string());
+        }
+        if self.store_interval_ms < 0 {
+            return Err("ambient.store_interval_ms must not be negative".to_string());
+        }
+        if !(0.0 < self.clear_percent && self.clear_percent <= self.event_percent) {
+            return Err("ambient.clear_percent must be positive and at most event_percent".to_string());
+        }
+        if self.calm_kmh < 0.0 || self.weather_max_age_secs <= 0 {
+            return Err("ambient.calm_kmh must not be negative and weather_max_age_secs must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// One excursion from baseline, published when it opens and when it closes
+#[derive(Debug, Serialize, Clone)]
+pub struct AmbientEvent {
+    pub device: String,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    /// Channel that opened the event
+    pub trigger: String,
+    /// Largest deviation from baseline per channel, percent
+    pub peak_percent: BTreeMap<String, f32>,
+    /// Likely direction of the source: where the wind came from while the
+    /// event was strongest, degrees from north; None in calm or without wind data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_bearing_deg: Option<f32>,
+    /// 0..1, how steady that wind was (1 = one direction throughout)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bearing_confidence: Option<f32>,
+    /// "channel" (anemometer) or "weather"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wind_source: Option<String>,
+}
+
+impl AmbientEvent {
+    /// "ambient_events" point for a closed event
+    pub fn point(&self) -> Option<DataPoint> {
+        let ended_at = self.ended_at?;
+        let mut builder = DataPoint::builder("ambient_events")
+            .tag("device", self.device.clone())
+            .tag("trigger", self.trigger.clone())
+            .field("duration_s", (ended_at - self.started_at) as f64 / 1000.0);
+        for (channel, peak) in &self.peak_percent {
+            builder = builder.field(format!("{}_peak_percent", channel), *peak as f64);
+        }
+        if let (Some(bearing), Some(confidence)) = (self.source_bearing_deg, self.bearing_confidence) {
+            builder = builder.field("source_bearing_deg", bearing as f64).field("bearing_confidence", confidence as f64);
+        }
+        if let Some(source) = &self.wind_source {
+            builder = builder.tag("wind_source", source.clone());
+        }
+        builder.timestamp(self.started_at * 1_000_000).build().ok()
+    }
+}
+
+// ================= Ambient Monitoring =================
+/// Continuous monitoring instead of measurement cycles: the firmware FSM is
+/// ignored (frames carry state AMBIENT, level 0), a slow baseline is tracked
+/// per channel, excursions from it are reported as events, and storage is
+/// thinned to one point per `store_interval_ms` outside events.
+pub struct Ambient {
+    config: AmbientConfig,
+    baseline: Option<Channels>,
+    last_timestamp: Option<i64>,
+    last_stored: Option<i64>,
+    event: Option<AmbientEvent>,
+    plume: PlumeBearing,
+}
+
+/// Wind directions during an event as unit vectors, weighted by how far the
+/// gas was off baseline; the mean points upwind to the likely source
+#[derive(Default, Clone, Copy)]
+struct PlumeBearing {
+    east: f64,
+    north: f64,
+    weight: f64,
+}
+
+impl PlumeBearing {
+    fn add(&mut self, wind: Wind, weight: f32) {
+        let direction = (wind.direction_deg as f64).to_radians();
+        self.east += weight as f64 * direction.sin();
+        self.north += weight as f64 * direction.cos();
+        self.weight += weight as f64;
+    }
+
+    /// (bearing, confidence)
+    fn estimate(&self) -> Option<(f32, f32)> {
+        if self.weight <= 0.0 {
+            return None;
+        }
+        let bearing = self.east.atan2(self.north).to_degrees().rem_euclid(360.0);
+        let confidence = self.east.hypot(self.north) / self.weight;
+        Some((bearing as f32, confidence as f32))
+    }
+}
+
+impl Ambient {
+    pub fn new(config: AmbientConfig) -> Option<Self> {
+        config.enabled.then_some(Self {
+            config,
+            baseline: None,
+            last_timestamp: None,
+            last_stored: None,
+            event: None,
+            plume: PlumeBearing::default(),
+        })
+    }
+
+    /// New settings from a config reload; the baseline is kept
+    pub fn reload(current: Option<Self>, config: AmbientConfig) -> Option<Self> {
+        match current {
+            Some(ambient) if config.enabled => Some(Self { config, ..ambient }),
+            _ => Self::new(config),
+        }
+    }
+
+    pub fn commands(&self) -> &[String] {
+        &self.config.commands
+    }
+
+    /// Wind for a frame: the anemometer channel if configured, else a recent
+    /// [weather] reading; None in calm
+    fn wind(&self, frame: &UnifiedSensorData, weather: &LatestWeather) -> Option<(Wind, &'static str)> {
+        let channel = |name: &str| frame.virtual_channels.get(name).copied().filter(|v| v.is_finite());
+        let (wind, source) = if self.config.wind_direction_channel.is_empty() {
+            (weather.wind(frame.timestamp, self.config.weather_max_age_secs * 1000)?, "weather")
+        } else {
+            let wind = Wind {
+                direction_deg: channel(&self.config.wind_direction_channel)?,
+                speed_kmh: channel(&self.config.wind_speed_channel),
+            };
+            (wind, "channel")
+        };
+        match wind.speed_kmh {
+            Some(speed) if speed < self.config.calm_kmh => None,
+            _ => Some((wind, source)),
+        }
+    }
+
+    /// Turn a frame into an ambient frame; returns the event if one opened or closed
+    pub fn update(&mut self, frame: &mut UnifiedSensorData, weather: &LatestWeather) -> Option<AmbientEvent> {
+        frame.state = AMBIENT;
+        frame.state_name = crate::state_to_name(AMBIENT);
+        frame.level = 0;
+
+        let wind = self.wind(frame, weather);
+        let baseline = self.baseline.get_or_insert(frame.channels);
+        let deviation = |channel: Channel, baseline: &Channels| {
+            let (value, base) = (frame.channels[channel], baseline[channel]);
+            (base.abs() > f32::EPSILON && value.is_finite()).then(|| (value - base) / base.abs() * 100.0)
+        };
+        let deviations: Vec<(Channel, f32)> =
+            Channel::ALL.into_iter().filter_map(|c| Some((c, deviation(c, baseline)?))).collect();
+
+        let mut opened = false;
+        match self.event.as_mut() {
+            None => {
+                let trigger = deviations
+                    .iter()
+                    .filter(|(_, d)| d.abs() > self.config.event_percent)
+                    .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()));
+                if let Some((channel, _)) = trigger {
+                    let event = AmbientEvent {
+                        device: frame.device.clone(),
+                        started_at: frame.timestamp,
+                        ended_at: None,
+                        trigger: channel.name().to_string(),
+                        peak_percent: BTreeMap::new(),
+                        source_bearing_deg: None,
+                        bearing_confidence: None,
+                        wind_source: None,
+                    };
+                    println!("🌫️ Ambient event on {} ({} off baseline)", frame.device, channel.name());
+                    self.event = Some(event);
+                    self.plume = PlumeBearing::default();
+                    opened = true;
+                }
+            }
+            Some(event) if deviations.iter().all(|(_, d)| d.abs() <= self.config.clear_percent) => {
+                event.ended_at = Some(frame.timestamp);
+                println!("🌫️ Ambient event on {} over after {}s", frame.device, (frame.timestamp - event.started_at) / 1000);
+            }
+            Some(_) => {}
+        }
+        let mut changed = None;
+        if let Some(event) = self.event.as_mut() {
+            for (channel, d) in &deviations {
+                let peak = event.peak_percent.entry(channel.name().to_string()).or_insert(0.0);
+                if d.abs() > peak.abs() {
+                    *peak = *d;
+                }
+            }
+            // Arah angin dibobot besar simpangan gas: puncak plume paling menentukan
+            if let Some((wind, source)) = wind {
+                let strongest = deviations.iter().map(|(_, d)| d.abs()).fold(0.0, f32::max);
+                self.plume.add(wind, strongest);
+                event.wind_source = Some(source.to_string());
+            }
+            if let Some((bearing, confidence)) = self.plume.estimate() {
+                event.source_bearing_deg = Some(bearing);
+                event.bearing_confidence = Some(confidence);
+            }
+            if let (Some(_), Some(bearing)) = (event.ended_at, event.source_bearing_deg) {
+                println!("🧭 Likely source of {}'s event: {:.0}° upwind", frame.device, bearing);
+            }
+            if opened || event.ended_at.is_some() {
+                changed = Some(event.clone());
+            }
+        }
+        if self.event.as_ref().is_some_and(|e| e.ended_at.is_some()) {
+            self.event = None;
+        }
+
+        // Baseline hanya mengikuti udara sekitar, dibekukan selama event
+        if self.event.is_none() {
+            let dt = self.last_timestamp.map_or(0.0, |last| (frame.timestamp - last).max(0) as f64 / 1000.0);
+            let alpha = (1.0 - (-dt / self.config.baseline_tau_secs).exp()) as f32;
+            for channel in Channel::ALL {
+                let value = frame.channels[channel];
+                if !value.is_finite() {
+                    continue;
+                }
+                let base = &mut baseline[channel];
+                *base = if base.is_finite() { *base + alpha * (value - *base) } else { value };
+            }
+        }
+        self.last_timestamp = Some(frame.timestamp);
+
+        for channel in Channel::ALL {
+            frame.virtual_channels.insert(format!("{}_baseline", channel.name()), baseline[channel]);
+        }
+        frame.virtual_channels.insert("ambient_event".to_string(), if self.event.is_some() { 1.0 } else { 0.0 });
+
+        // Di luar event cukup satu titik per interval
+        let due = self.last_stored.is_none_or(|at| frame.timestamp - at >= self.config.store_interval_ms);
+        frame.skip_storage = self.event.is_none() && changed.is_none() && !due;
+        if !frame.skip_storage {
+            self.last_stored = Some(frame.timestamp);
+        }
+        changed
+    }
+}
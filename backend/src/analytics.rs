@@ -0,0 +1,412 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::history_spill::HistorySpill;
+use crate::spectral;
+use crate::{UnifiedSensorData, CHANNEL_NAMES};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AnalyticsConfig {
+    /// Frames kept in memory for analytics queries (36000 = 1 h at 10 Hz)
+    #[serde(default = "default_history")]
+    pub history: usize,
+    /// Histogram bins when a request does not specify them
+    #[serde(default = "default_histogram_bins")]
+    pub histogram_bins: usize,
+    /// Samples per spectrogram FFT segment when a request does not specify them
+    #[serde(default = "default_spectrogram_segment")]
+    pub spectrogram_segment: usize,
+    /// Most segments per spectrogram; the hop grows to stay within it
+    #[serde(default = "default_spectrogram_columns")]
+    pub spectrogram_columns: usize,
+    /// Move frames older than `history` to memory-mapped files on disk
+    #[serde(default)]
+    pub spill: bool,
+    #[serde(default = "default_spill_dir")]
+    pub spill_dir: String,
+    /// Frames kept on disk beyond the in-memory ones (864000 = 24 h at 10 Hz)
+    #[serde(default = "default_spill_frames")]
+    pub spill_frames: usize,
+    /// Frames per segment file; whole segments are dropped when full
+    #[serde(default = "default_segment_frames")]
+    pub segment_frames: usize,
+}
+
+fn default_history() -> usize { 36000 }
+fn default_histogram_bins() -> usize { 20 }
+fn default_spectrogram_segment() -> usize { 256 }
+fn default_spectrogram_columns() -> usize { 500 }
+fn default_spill_dir() -> String { "history_spill".to_string() }
+fn default_spill_frames() -> usize { 864_000 }
+fn default_segment_frames() -> usize { 36000 }
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            history: default_history(),
+            histogram_bins: default_histogram_bins(),
+            spectrogram_segment: default_spectrogram_segment(),
+            spectrogram_columns: default_spectrogram_columns(),
+            spill: false,
+            spill_dir: default_spill_dir(),
+            spill_frames: default_spill_frames(),
+            segment_frames: default_segment_frames(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    analytics: AnalyticsConfig,
+}
+
+impl AnalyticsConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().analytics
+    }
+}
+
+pub struct Sample {
+    pub timestamp: i64,
+    pub device: String,
+    pub channels: [f32; 7],
+    pub virtual_channels: BTreeMap<String, f32>,
+}
+
+impl Sample {
+    fn value(&self, channel: &str) -> Option<f32> {
+        match CHANNEL_NAMES.iter().position(|c| *c == channel) {
+            Some(i) => Some(self.channels[i]),
+            None => self.virtual_channels.get(channel).copied(),
+        }
+    }
+}
+
+/// Which samples a query covers
+#[derive(Debug, Deserialize, Default)]
+pub struct Selection {
+    pub device: Option<String>,
+    /// Epoch ms bounds (inclusive)
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Only the newest N matching samples
+    pub window: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CorrelationMatrix {
+    pub channels: Vec<String>,
+    pub samples: usize,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Pearson r, row/column order of `channels`; None where a channel is flat
+    pub matrix: Vec<Vec<Option<f32>>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Histogram {
+    pub channel: String,
+    pub samples: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// bins + 1 edges; bin i covers [edges[i], edges[i+1]), the last one is closed
+    pub edges: Vec<f64>,
+    pub counts: Vec<u64>,
+    pub percentiles: Vec<Percentile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Percentile {
+    pub p: u32,
+    pub value: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Spectrogram {
+    pub channel: String,
+    pub device: Option<String>,
+    pub samples: usize,
+    /// Estimated from the median frame interval, Hz
+    pub sample_rate: f64,
+    /// Samples per FFT segment and between segment starts
+    pub segment: usize,
+    pub hop: usize,
+    /// Bin frequencies, Hz
+    pub frequencies: Vec<f64>,
+    /// Epoch ms at the middle of each segment
+    pub times: Vec<i64>,
+    /// Sine amplitude, one row per segment in `times` order, one column per frequency
+    pub magnitudes: Vec<Vec<f32>>,
+}
+
+const PERCENTILES: [u32; 7] = [1, 5, 25, 50, 75, 95, 99];
+
+fn histogram(channel: &str, values: &[f64], bins: usize) -> Option<Histogram> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    let width = (max - min) / bins as f64;
+
+    let mut counts = vec![0u64; bins];
+    for v in &sorted {
+        let bin = if width > 0.0 { ((v - min) / width) as usize } else { 0 };
+        counts[bin.min(bins - 1)] += 1;
+    }
+    // Persentil dengan interpolasi linear antar rank
+    let percentile = |p: u32| {
+        let rank = p as f64 / 100.0 * (sorted.len() - 1) as f64;
+        let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+        sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64)
+    };
+    Some(Histogram {
+        channel: channel.to_string(),
+        samples: sorted.len(),
+        min,
+        max,
+        mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        edges: (0..=bins).map(|i| min + width * i as f64).collect(),
+        counts,
+        percentiles: PERCENTILES.iter().map(|&p| Percentile { p, value: percentile(p) }).collect(),
+    })
+}
+
+fn pearson(a: &[f64], b: &[f64]) -> Option<f32> {
+    let n = a.len() as f64;
+    if a.len() < 2 {
+        return None;
+    }
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    (var_a > 0.0 && var_b > 0.0).then(|| (cov / (var_a * var_b).sqrt()) as f32)
+}
+
+// ================= Frame History =================
+/// Recent processed frames kept for on-demand analytics: the newest in
+/// memory, older ones optionally spilled to disk. Queries span both tiers.
+#[derive(Clone)]
+pub struct FrameHistory {
+    samples: Arc<Mutex<VecDeque<Sample>>>,
+    capacity: usize,
+    /// Locked after `samples` (push and series), never on its own first
+    spill: Option<Arc<Mutex<HistorySpill>>>,
+}
+
+impl FrameHistory {
+    pub fn new(config: &AnalyticsConfig) -> Self {
+        let spill = config.spill.then(|| HistorySpill::new(config)).and_then(|spill| match spill {
+            Ok(spill) => {
+                println!("💽 History spills to {} (up to {} frames on disk)", config.spill_dir, config.spill_frames);
+                Some(Arc::new(Mutex::new(spill)))
+            }
+            Err(e) => {
+                eprintln!("⚠️ History spill disabled, {}: {}", config.spill_dir, e);
+                None
+            }
+        });
+        Self { samples: Arc::new(Mutex::new(VecDeque::new())), capacity: config.history.max(1), spill }
+    }
+
+    fn push(&self, frame: &UnifiedSensorData) {
+        let mut samples = self.samples.lock().unwrap();
+        samples.push_back(Sample {
+            timestamp: frame.timestamp,
+            device: frame.device.clone(),
+            channels: frame.channels.values(),
+            virtual_channels: frame.virtual_channels.clone(),
+        });
+        while samples.len() > self.capacity {
+            let Some(oldest) = samples.pop_front() else { break };
+            if let Some(spill) = &self.spill {
+                if let Err(e) = spill.lock().unwrap().append(&oldest) {
+                    eprintln!("⚠️ History spill write failed: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Per-channel value series for the selection, in time order. Channels
+    /// missing from any selected sample (e.g. a virtual channel added later)
+    /// are left out.
+    fn series(&self, selection: &Selection) -> (Vec<String>, Vec<Vec<f64>>, Vec<i64>) {
+        let samples = self.samples.lock().unwrap();
+        let limit = selection.window.unwrap_or(usize::MAX);
+        let mut selected: Vec<&Sample> = samples
+            .iter()
+            .rev()
+            .filter(|s| selection.device.as_deref().is_none_or(|d| s.device == d))
+            .filter(|s| selection.from.is_none_or(|from| s.timestamp >= from))
+            .filter(|s| selection.to.is_none_or(|to| s.timestamp <= to))
+            .take(limit)
+            .collect();
+        // Sisanya dari disk, hanya kalau seleksi lebih tua dari isi memori
+        let spilled = match &self.spill {
+            Some(spill) if selected.len() < limit => spill.lock().unwrap().select(selection, limit - selected.len()),
+            _ => Vec::new(),
+        };
+        selected.extend(&spilled);
+        selected.reverse();
+
+        // Kanal yang sempat dimatikan (NaN) dalam seleksi tidak diikutkan
+        let mut names: Vec<String> = CHANNEL_NAMES
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| selected.iter().all(|s| !s.channels[*i].is_nan()))
+            .map(|(_, c)| c.to_string())
+            .collect();
+        if let Some(first) = selected.first() {
+            names.extend(
+                first
+                    .virtual_channels
+                    .keys()
+                    .filter(|name| selected.iter().all(|s| s.virtual_channels.contains_key(*name)))
+                    .cloned(),
+            );
+        }
+        let series = names
+            .iter()
+            .map(|name| selected.iter().filter_map(|s| s.value(name)).map(|v| v as f64).collect())
+            .collect();
+        (names, series, selected.iter().map(|s| s.timestamp).collect())
+    }
+
+    /// One channel's values over the selection as (timestamps, values)
+    pub fn channel_series(&self, selection: &Selection, channel: &str) -> Option<(Vec<i64>, Vec<f64>)> {
+        let (names, mut series, timestamps) = self.series(selection);
+        let index = names.iter().position(|name| name == channel)?;
+        let values = series.swap_remove(index);
+        (!values.is_empty()).then_some((timestamps, values))
+    }
+
+    /// Rolling correlation matrix across all channels
+    pub fn correlation(&self, selection: &Selection) -> CorrelationMatrix {
+        let (channels, series, timestamps) = self.series(selection);
+        let (from, to) = (timestamps.first().copied(), timestamps.last().copied());
+        let matrix = series
+            .iter()
+            .map(|a| series.iter().map(|b| pearson(a, b)).collect())
+            .collect();
+        CorrelationMatrix { samples: series.first().map_or(0, Vec::len), channels, from, to, matrix }
+    }
+
+    /// Value distribution per channel (or only `channel`) over the selection
+    pub fn histograms(&self, selection: &Selection, bins: usize, channel: Option<&str>) -> Vec<Histogram> {
+        let (names, series, _) = self.series(selection);
+        names
+            .iter()
+            .zip(&series)
+            .filter(|(name, _)| channel.is_none_or(|c| c == name.as_str()))
+            .filter_map(|(name, values)| histogram(name, values, bins.clamp(1, 1000)))
+            .collect()
+    }
+
+    /// Rolling spectrogram per channel (or only `channel`): windowed FFT
+    /// magnitudes of `segment` samples every `hop` samples (default half a
+    /// segment, widened to at most spectrogram_columns segments), up to
+    /// `max_hz`. Without a device the newest frame's device is used, as
+    /// interleaved units would not be evenly sampled.
+    pub fn spectrograms(
+        &self,
+        selection: &Selection,
+        channel: Option<&str>,
+        segment: usize,
+        hop: Option<usize>,
+        max_hz: Option<f64>,
+    ) -> Vec<Spectrogram> {
+        let device = selection
+            .device
+            .clone()
+            .or_else(|| self.samples.lock().unwrap().back().map(|s| s.device.clone()));
+        let selection = Selection { device: device.clone(), ..*selection };
+        let (names, series, timestamps) = self.series(&selection);
+        let segment = segment.clamp(8, 8192);
+        if timestamps.len() < segment {
+            return Vec::new();
+        }
+        let mut intervals: Vec<i64> = timestamps.windows(2).map(|w| w[1] - w[0]).collect();
+        intervals.sort_unstable();
+        let interval = intervals[intervals.len() / 2];
+        if interval <= 0 {
+            return Vec::new();
+        }
+        let sample_rate = 1000.0 / interval as f64;
+
+        let columns = AnalyticsConfig::load("config.toml").spectrogram_columns.max(1);
+        let span = timestamps.len() - segment;
+        let hop = hop.unwrap_or(segment / 2).max(1).max(span.div_ceil(columns));
+        let starts: Vec<usize> = (0..=span).step_by(hop).collect();
+        let bin_hz = spectral::bin_hz(segment, sample_rate);
+        let bins = (segment.next_power_of_two() / 2 + 1)
+            .min(max_hz.map_or(usize::MAX, |max| (max / bin_hz).floor() as usize + 1));
+        names
+            .iter()
+            .zip(&series)
+            .filter(|(name, _)| channel.is_none_or(|c| c == name.as_str()))
+            .map(|(name, values)| Spectrogram {
+                channel: name.clone(),
+                device: device.clone(),
+                samples: values.len(),
+                sample_rate,
+                segment,
+                hop,
+                frequencies: (0..bins).map(|k| k as f64 * bin_hz).collect(),
+                times: starts.iter().map(|&start| timestamps[start + segment / 2]).collect(),
+                magnitudes: starts
+                    .iter()
+                    .map(|&start| {
+                        let magnitudes = spectral::magnitudes(&values[start..start + segment]);
+                        magnitudes.iter().take(bins).map(|m| *m as f32).collect()
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// GUI commands:
+///   HISTOGRAM [channel|*] [bins] [window]
+///   SPECTROGRAM [channel|*] [segment] [window]
+pub fn handle_command(history: &FrameHistory, args: &[&str]) -> Result<Option<String>, String> {
+    let num = |s: &str| s.parse::<usize>().map_err(|_| format!("invalid number '{}'", s));
+    let channel = args.get(1).filter(|c| **c != "*").copied();
+    let selection = Selection { window: args.get(3).map(|w| num(w)).transpose()?, ..Default::default() };
+    if args.first() == Some(&"SPECTROGRAM") {
+        let segment = match args.get(2) {
+            Some(segment) => num(segment)?,
+            None => AnalyticsConfig::load("config.toml").spectrogram_segment,
+        };
+        let spectrograms = history.spectrograms(&selection, channel, segment, None, None);
+        return Ok(Some(serde_json::json!({ "type": "spectrogram", "spectrograms": spectrograms }).to_string()));
+    }
+    let bins = match args.get(2) {
+        Some(bins) => num(bins)?,
+        None => AnalyticsConfig::load("config.toml").histogram_bins,
+    };
+    let histograms = history.histograms(&selection, bins, channel);
+    Ok(Some(serde_json::json!({ "type": "histogram", "histograms": histograms }).to_string()))
+}
+
+/// Feed processed frames into the history
+pub async fn run(history: FrameHistory, frame_tx: broadcast::Sender<UnifiedSensorData>) {
+    let mut frame_rx = frame_tx.subscribe();
+    loop {
+        match frame_rx.recv().await {
+            Ok(frame) => history.push(&frame),
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
@@ -0,0 +1,681 @@
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Extension, Json, Router};
+use serde::Deserialize;
+
+use crate::accuracy::AccuracyQuery;
+use crate::analytics::{AnalyticsConfig, Selection};
+use crate::auth::{Group, Identity};
+use crate::calibration::{CalibrationCurve, DEFAULT_PREVIEW_WINDOW};
+use crate::campaign::TrackFormat;
+use crate::jobs::JobSpec;
+use crate::location::TrackQuery;
+use crate::provenance::CalibrationConfig;
+use crate::sessions::SessionFilter;
+use crate::shift_log::NoteQuery;
+use crate::waveform::CycleQuery;
+use crate::AppState;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_bind")]
+    pub bind: String,
+}
+
+fn default_enabled() -> bool { true }
+fn default_bind() -> String { "0.0.0.0:8083".to_string() }
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self { enabled: default_enabled(), bind: default_bind() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    api: ApiConfig,
+}
+
+impl ApiConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().api
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct RunRequest {
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    device: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistogramQuery {
+    bins: Option<usize>,
+    channel: Option<String>,
+    device: Option<String>,
+    from: Option<i64>,
+    to: Option<i64>,
+    window: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpectrogramQuery {
+    channel: Option<String>,
+    segment: Option<usize>,
+    hop: Option<usize>,
+    max_hz: Option<f64>,
+    device: Option<String>,
+    from: Option<i64>,
+    to: Option<i64>,
+    window: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConsoleRequest {
+    command: String,
+    timeout_ms: Option<u64>,
+    device: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrackExportQuery {
+    format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceLogQuery {
+    lines: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CurveQuery {
+    device: Option<String>,
+    from: Option<i64>,
+    to: Option<i64>,
+    window: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PostNote {
+    text: String,
+    author: Option<String>,
+    device: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HandoverQuery {
+    hours: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageQuery {
+    days: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChannelQuery {
+    lang: Option<String>,
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(serde_json::json!({ "error": message.into() }))).into_response()
+}
+
+/// Who made the request (None = no token), for checks inside a handler
+#[derive(Clone)]
+struct Caller(Option<Identity>);
+
+/// Permission group a route needs
+fn required_group(method: &Method, path: &str) -> Group {
+    if method == Method::GET || method == Method::HEAD {
+        return Group::View;
+    }
+    let segments: Vec<&str> = path.trim_start_matches("/api/").split('/').collect();
+    match segments.as_slice() {
+        ["calibration", "curves", _, "preview"] | ["webrtc", "offer"] => Group::View,
+        ["shift-log"] | ["sessions", _, "label"] => Group::Annotate,
+        ["experiments", ..] | ["jobs", ..] => Group::RunExperiments,
+        ["console"] => Group::ActuateHardware,
+        _ => Group::AdminConfig,
+    }
+}
+
+/// `Authorization: Bearer <token>` against the route's group ([auth])
+async fn authorize(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let identity = match crate::auth::bearer(request.headers()) {
+        Some(token) => match state.auth.lookup(token) {
+            Some(identity) => Some(identity),
+            None => return error(StatusCode::UNAUTHORIZED, "invalid token"),
+        },
+        None => None,
+    };
+    let group = required_group(request.method(), request.uri().path());
+    if let Err(e) = state.auth.allow(identity.as_ref(), group) {
+        let status = if identity.is_some() { StatusCode::FORBIDDEN } else { StatusCode::UNAUTHORIZED };
+        return error(status, e);
+    }
+    if group != Group::View {
+        state.usage.command(identity.as_ref().map_or("api", |identity| identity.name.as_str()));
+    }
+    request.extensions_mut().insert(Caller(identity));
+    next.run(request).await
+}
+
+// ================= REST API =================
+pub async fn serve(config: ApiConfig, state: AppState) -> anyhow::Result<()> {
+    let listener = state.diagnostics.bind("api", &config.bind).await?;
+    let app = Router::new()
+        .route("/api/experiments/{name}/run", post(run_experiment))
+        .route("/api/experiments/runs/{id}", get(experiment_run))
+        .route("/api/channels", get(channel_metadata))
+        .route("/api/channels/disabled", get(disabled_channels))
+        .route("/api/channels/{name}/{action}", post(set_channel))
+        .route("/api/clients", get(client_metrics))
+        .route("/api/latency", get(latency))
+        .route("/api/devices", get(devices))
+        .route("/api/devices/{id}/log", get(device_log))
+        .route("/api/influx", get(influx_status))
+        .route("/api/calibration/curves", get(calibration_curves))
+        .route("/api/calibration/curves/{channel}", put(save_curve).delete(delete_curve))
+        .route("/api/calibration/curves/{channel}/preview", post(preview_curve))
+        .route("/api/analytics/correlation", get(correlation_matrix))
+        .route("/api/analytics/histogram", get(histograms))
+        .route("/api/analytics/spectrogram", get(spectrograms))
+        .route("/api/geojson", get(geojson))
+        .route("/api/campaigns", get(campaigns))
+        .route("/api/campaigns/{id}/track", get(campaign_track))
+        .route("/api/accuracy", get(accuracy))
+        .route("/api/usage", get(usage))
+        .route("/api/noise-floor", get(noise_floor))
+        .route("/api/jobs", get(jobs).post(submit_job))
+        .route("/api/jobs/{id}", get(job))
+        .route("/api/jobs/{id}/cancel", post(cancel_job))
+        .route("/api/jobs/{id}/artifacts/{name}", get(job_artifact))
+        .route("/api/models", get(models).post(register_model))
+        .route("/api/models/rollback", post(rollback_model))
+        .route("/api/models/shadow", post(shadow_model))
+        .route("/api/models/{id}/activate", post(activate_model))
+        .route("/api/waveforms", get(waveform_cycles))
+        .route("/api/waveforms/{id}", get(waveform_cycle))
+        .route("/api/health", get(health))
+        .route("/api/diagnostics", get(diagnostics))
+        .route("/api/debug-bundle", get(debug_bundle))
+        .route("/api/sessions", get(sessions))
+        .route("/api/sessions/{id}", get(session).delete(delete_session))
+        .route("/api/sessions/{id}/label", post(label_session))
+        .route("/api/sessions/{id}/lock", post(lock_session))
+        .route("/api/console", post(console))
+        .route("/api/shift-log", get(shift_notes).post(post_shift_note))
+        .route("/api/handover", get(handover))
+        .route("/api/webrtc/offer", post(webrtc_offer))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state);
+
+    println!("🌐 REST API listening on {}", config.bind);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// POST /api/experiments/{name}/run   body (optional): {"priority": 0, "device": "<id>"}
+/// Without a device the experiment runs on the only connected one.
+async fn run_experiment(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    body: Option<Json<RunRequest>>,
+) -> Response {
+    let request = body.map(|Json(r)| r).unwrap_or_default();
+    match state.queue.enqueue(&name, request.priority, request.device.as_deref()) {
+        Ok(id) => (StatusCode::ACCEPTED, Json(state.queue.run(id))).into_response(),
+        Err(e) if e.starts_with("unknown experiment") => error(StatusCode::NOT_FOUND, e),
+        Err(e) => error(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+/// GET /api/experiments/runs/{id}
+async fn experiment_run(State(state): State<AppState>, Path(id): Path<u64>) -> Response {
+    match state.queue.run(id) {
+        Some(run) => Json(run).into_response(),
+        None => error(StatusCode::NOT_FOUND, format!("no experiment run #{}", id)),
+    }
+}
+
+/// GET /api/channels?lang=id
+async fn channel_metadata(Query(query): Query<ChannelQuery>) -> Response {
+    Json(crate::channels::catalog("config.toml", query.lang.as_deref())).into_response()
+}
+
+/// GET /api/channels/disabled
+async fn disabled_channels(State(state): State<AppState>) -> Response {
+    Json(serde_json::json!({ "disabled": state.channel_mask.disabled() })).into_response()
+}
+
+/// POST /api/channels/{name}/enable | /api/channels/{name}/disable
+async fn set_channel(State(state): State<AppState>, Path((name, action)): Path<(String, String)>) -> Response {
+    let enabled = match action.as_str() {
+        "enable" => true,
+        "disable" => false,
+        _ => return error(StatusCode::NOT_FOUND, format!("unknown action '{}'", action)),
+    };
+    match state.channel_mask.set(&name, enabled) {
+        Ok(()) => {
+            let audit_action = if enabled { "channel_enable" } else { "channel_disable" };
+            state.audit.record("api", audit_action, serde_json::json!({ "channel": name }));
+            Json(serde_json::json!({ "disabled": state.channel_mask.disabled() })).into_response()
+        }
+        Err(e) => error(StatusCode::NOT_FOUND, e),
+    }
+}
+
+/// GET /api/clients
+async fn client_metrics(State(state): State<AppState>) -> Response {
+    Json(state.clients.snapshot()).into_response()
+}
+
+/// GET /api/devices   connected Arduinos and their IDs
+async fn devices(State(state): State<AppState>) -> Response {
+    Json(state.devices.snapshot()).into_response()
+}
+
+/// GET /api/devices/{id}/log?lines=<n>   the device's latest non-data lines
+async fn device_log(State(state): State<AppState>, Path(id): Path<String>, Query(query): Query<DeviceLogQuery>) -> Response {
+    match state.device_log.recent(&id, query.lines.unwrap_or(crate::device_log::DEFAULT_LINES)) {
+        Ok(lines) => Json(lines).into_response(),
+        Err(e) => error(StatusCode::NOT_FOUND, e),
+    }
+}
+
+/// GET /api/calibration/curves?device=   active curves, with the device profile applied
+async fn calibration_curves(Query(query): Query<CurveQuery>) -> Response {
+    Json(CalibrationConfig::load("config.toml", query.device.as_deref()).curves).into_response()
+}
+
+/// PUT /api/calibration/curves/{channel}?device=   body: {"kind": "power", "coefficients": [a, b]}
+async fn save_curve(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+    Query(query): Query<CurveQuery>,
+    Json(curve): Json<CalibrationCurve>,
+) -> Response {
+    match crate::calibration::save_curve(&state.config, query.device.as_deref(), &channel, &curve, "api") {
+        Ok(version) => Json(serde_json::json!({ "channel": channel, "version": version })).into_response(),
+        Err(e) => error(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+/// DELETE /api/calibration/curves/{channel}?device=
+async fn delete_curve(State(state): State<AppState>, Path(channel): Path<String>, Query(query): Query<CurveQuery>) -> Response {
+    match crate::calibration::delete_curve(&state.config, query.device.as_deref(), &channel, "api") {
+        Ok(version) => Json(serde_json::json!({ "channel": channel, "version": version })).into_response(),
+        Err(e) if e.starts_with("no curve") => error(StatusCode::NOT_FOUND, e),
+        Err(e) => error(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+/// POST /api/calibration/curves/{channel}/preview?device=&window=&from=&to=   candidate curve on recent data, not saved
+async fn preview_curve(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+    Query(query): Query<CurveQuery>,
+    Json(curve): Json<CalibrationCurve>,
+) -> Response {
+    let current = CalibrationConfig::load("config.toml", query.device.as_deref()).curves.remove(&channel);
+    let window = query.window.or((query.from.is_none() && query.to.is_none()).then_some(DEFAULT_PREVIEW_WINDOW));
+    let selection = Selection { device: query.device, from: query.from, to: query.to, window };
+    match crate::calibration::preview(&state.history, current.as_ref(), &channel, &curve, &selection) {
+        Ok(preview) => Json(preview).into_response(),
+        Err(e) if e.starts_with("no recent data") => error(StatusCode::NOT_FOUND, e),
+        Err(e) => error(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+/// GET /api/influx   InfluxDB writer counters and backlog
+async fn influx_status(State(state): State<AppState>) -> Response {
+    Json(state.influx.stats()).into_response()
+}
+
+/// GET /api/latency   per-stage pipeline latency histograms
+async fn latency(State(state): State<AppState>) -> Response {
+    Json(state.latency.report()).into_response()
+}
+
+/// GET /api/analytics/correlation?window=<samples>&device=&from=&to=
+async fn correlation_matrix(State(state): State<AppState>, Query(selection): Query<Selection>) -> Response {
+    Json(state.history.correlation(&selection)).into_response()
+}
+
+/// GET /api/analytics/histogram?bins=20&channel=&window=&device=&from=&to=
+async fn histograms(State(state): State<AppState>, Query(query): Query<HistogramQuery>) -> Response {
+    let bins = query.bins.unwrap_or_else(|| AnalyticsConfig::load("config.toml").histogram_bins);
+    let selection = Selection { device: query.device, from: query.from, to: query.to, window: query.window };
+    Json(state.history.histograms(&selection, bins, query.channel.as_deref())).into_response()
+}
+
+/// GET /api/analytics/spectrogram?channel=&segment=256&hop=&max_hz=&window=&device=&from=&to=
+async fn spectrograms(State(state): State<AppState>, Query(query): Query<SpectrogramQuery>) -> Response {
+    let segment = query.segment.unwrap_or_else(|| AnalyticsConfig::load("config.toml").spectrogram_segment);
+    let selection = Selection { device: query.device, from: query.from, to: query.to, window: query.window };
+    Json(state.history.spectrograms(&selection, query.channel.as_deref(), segment, query.hop, query.max_hz)).into_response()
+}
+
+/// GET /api/geojson?device=&from=&to=&limit=   recent located readings
+async fn geojson(State(state): State<AppState>, Query(query): Query<TrackQuery>) -> Response {
+    ([(header::CONTENT_TYPE, "application/geo+json")], Json(state.track.geojson(&query))).into_response()
+}
+
+/// GET /api/campaigns   campaigns on disk, newest first
+async fn campaigns(State(state): State<AppState>) -> Response {
+    Json(state.campaigns.list()).into_response()
+}
+
+/// GET /api/campaigns/{id}/track?format=geojson|kml|gpx
+async fn campaign_track(State(state): State<AppState>, Path(id): Path<String>, Query(query): Query<TrackExportQuery>) -> Response {
+    let format = match TrackFormat::parse(query.format.as_deref().unwrap_or("geojson")) {
+        Ok(format) => format,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e),
+    };
+    match state.campaigns.export(&id, format) {
+        Ok(track) => {
+            let disposition = format!("attachment; filename=\"campaign_{}.{}\"", id, format.extension());
+            ([(header::CONTENT_TYPE, format.content_type().to_string()), (header::CONTENT_DISPOSITION, disposition)], track)
+                .into_response()
+        }
+        Err(e) => error(StatusCode::NOT_FOUND, e),
+    }
+}
+
+/// GET /api/usage?days=<n>
+/// cycles, active hours and utilization per device, commands per operator,
+/// alarms per alert; totals and per day
+async fn usage(State(state): State<AppState>, Query(query): Query<UsageQuery>) -> Response {
+    match state.usage.report(query.days.filter(|d| *d > 0)) {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// GET /api/accuracy?from=<ms>&to=<ms>&model=&device=
+/// confusion matrix, accuracy and per-class recall against session labels
+async fn accuracy(State(state): State<AppState>, Query(query): Query<AccuracyQuery>) -> Response {
+    match state.accuracy.report(&query) {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// GET /api/noise-floor   latest noise characterization per device
+async fn noise_floor(State(state): State<AppState>) -> Response {
+    Json(state.noise_floor.records()).into_response()
+}
+
+/// GET /api/jobs   jobs with progress and artifacts, newest first
+async fn jobs(State(state): State<AppState>) -> Response {
+    Json(state.jobs.list()).into_response()
+}
+
+/// POST /api/jobs   body: {"kind": "replay"|"export", "device": "...", "from": <ms>, "to": <ms>, "format": "csv"|"ndjson"}
+///                  or {"kind": "report", "session": "..."}
+///                  calibrate/transfer jobs need admin-config ([auth])
+async fn submit_job(State(state): State<AppState>, Extension(caller): Extension<Caller>, Json(spec): Json<JobSpec>) -> Response {
+    if let Err(e) = state.auth.permit(caller.0.as_ref(), spec.command()) {
+        return error(StatusCode::FORBIDDEN, e);
+    }
+    match state.jobs.submit(spec, "api") {
+        Ok(job) => (StatusCode::ACCEPTED, Json(job)).into_response(),
+        Err(e) => error(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+/// GET /api/jobs/{id}
+async fn job(State(state): State<AppState>, Path(id): Path<u64>) -> Response {
+    match state.jobs.get(id) {
+        Some(job) => Json(job).into_response(),
+        None => error(StatusCode::NOT_FOUND, format!("unknown job #{}", id)),
+    }
+}
+
+/// POST /api/jobs/{id}/cancel
+async fn cancel_job(State(state): State<AppState>, Path(id): Path<u64>) -> Response {
+    match state.jobs.cancel(id, "api") {
+        Ok(job) => Json(job).into_response(),
+        Err(e) if e.starts_with("unknown job") => error(StatusCode::NOT_FOUND, e),
+        Err(e) => error(StatusCode::CONFLICT, e),
+    }
+}
+
+/// GET /api/jobs/{id}/artifacts/{name}   download a result file
+async fn job_artifact(State(state): State<AppState>, Path((id, name)): Path<(u64, String)>) -> Response {
+    let path = match state.jobs.artifact_path(id, &name) {
+        Ok(path) => path,
+        Err(e) => return error(StatusCode::NOT_FOUND, e),
+    };
+    let content_type = match path.extension().and_then(|e| e.to_str()) {
+        Some("csv") => "text/csv",
+        Some("json") => "application/json",
+        Some("ndjson") => "application/x-ndjson",
+        Some("md") => "text/markdown; charset=utf-8",
+        _ => "application/octet-stream",
+    };
+    match tokio::fs::read(&path).await {
+        Ok(contents) => {
+            let disposition = format!("attachment; filename=\"job{}_{}\"", id, name);
+            ([(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)], contents)
+                .into_response()
+        }
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, format!("cannot read {}: {}", path.display(), e)),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ShadowModel {
+    model: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegisterModel {
+    path: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// GET /api/models   registered versions, the active one and the activation history
+async fn models(State(state): State<AppState>) -> Response {
+    Json(state.models.snapshot()).into_response()
+}
+
+/// POST /api/models   body: {"path": "models/gases.json", "name": "gases", "description": "..."}
+/// stores the next version; it is not activated
+async fn register_model(State(state): State<AppState>, Json(body): Json<RegisterModel>) -> Response {
+    match state.models.register(&body.path, &body.name, &body.description, "api") {
+        Ok(model) => (StatusCode::CREATED, Json(model)).into_response(),
+        Err(e) => error(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+/// POST /api/models/{id}/activate   e.g. /api/models/gases@v2/activate
+async fn activate_model(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match state.models.activate(&id, "api") {
+        Ok(()) => Json(state.models.snapshot()).into_response(),
+        Err(e) if e.starts_with("unknown model") => error(StatusCode::NOT_FOUND, e),
+        Err(e) => error(StatusCode::CONFLICT, e),
+    }
+}
+
+/// POST /api/models/rollback   back to the previously active version
+async fn rollback_model(State(state): State<AppState>) -> Response {
+    match state.models.rollback("api") {
+        Ok(_) => Json(state.models.snapshot()).into_response(),
+        Err(e) => error(StatusCode::CONFLICT, e),
+    }
+}
+
+/// POST /api/models/shadow   body: {"model": "gases@v3"} or {"model": null} to stop
+async fn shadow_model(State(state): State<AppState>, Json(body): Json<ShadowModel>) -> Response {
+    match state.models.set_shadow(body.model.as_deref(), "api") {
+        Ok(()) => Json(state.models.snapshot()).into_response(),
+        Err(e) if e.starts_with("unknown model") => error(StatusCode::NOT_FOUND, e),
+        Err(e) => error(StatusCode::CONFLICT, e),
+    }
+}
+
+/// GET /api/waveforms?device=&from=&to=&limit=   completed modulation cycles
+async fn waveform_cycles(State(state): State<AppState>, Query(query): Query<CycleQuery>) -> Response {
+    Json(state.waveforms.list(&query)).into_response()
+}
+
+/// GET /api/waveforms/{id}   one cycle's raw waveform as dense arrays
+async fn waveform_cycle(State(state): State<AppState>, Path(id): Path<u64>) -> Response {
+    match state.waveforms.get(id) {
+        Some(cycle) => Json(cycle).into_response(),
+        None => error(StatusCode::NOT_FOUND, format!("no waveform cycle {}", id)),
+    }
+}
+
+/// GET /api/health   (polled by a standby backend)
+async fn health(State(state): State<AppState>) -> Response {
+    Json(state.failover.health()).into_response()
+}
+
+/// GET /api/diagnostics   startup self-check report
+async fn diagnostics(State(state): State<AppState>) -> Response {
+    Json(state.diagnostics.report()).into_response()
+}
+
+/// GET /api/debug-bundle   zip of recent raw lines, logs, config, metrics
+async fn debug_bundle(State(state): State<AppState>) -> Response {
+    match crate::debug_bundle::capture("config.toml", &state) {
+        Ok(bundle) => {
+            let disposition = format!("attachment; filename=\"{}\"", crate::debug_bundle::file_name());
+            ([(header::CONTENT_TYPE, "application/zip".to_string()), (header::CONTENT_DISPOSITION, disposition)], bundle)
+                .into_response()
+        }
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// GET /api/sessions?from=<ms>&to=<ms>&label=<text>&device=<id>
+/// sessions with their summary, newest first
+async fn sessions(State(state): State<AppState>, Query(filter): Query<SessionFilter>) -> Response {
+    match state.sessions.list(&filter) {
+        Ok(sessions) => Json(sessions).into_response(),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// GET /api/sessions/{id}
+async fn session(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match state.sessions.get(&id) {
+        Ok(Some(session)) => Json(session).into_response(),
+        Ok(None) => error(StatusCode::NOT_FOUND, format!("unknown session {}", id)),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct LabelRequest {
+    label: String,
+}
+
+/// POST /api/sessions/{id}/label   body: {"label": "..."}, "" clears it
+async fn label_session(State(state): State<AppState>, Path(id): Path<String>, Json(request): Json<LabelRequest>) -> Response {
+    match state.sessions.relabel(&id, &request.label) {
+        Ok(()) => {
+            state.audit.record("api", "session_label", serde_json::json!({ "session": id, "label": request.label }));
+            Json(serde_json::json!({ "session": id, "label": request.label })).into_response()
+        }
+        Err(e) if e.starts_with("unknown session") => error(StatusCode::NOT_FOUND, e),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// DELETE /api/sessions/{id}   closed, unlocked sessions: InfluxDB points and record
+async fn delete_session(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match state.sessions.delete(&id, &state.influx).await {
+        Ok(()) => {
+            state.audit.record("api", "session_delete", serde_json::json!({ "session": id }));
+            Json(serde_json::json!({ "session": id, "deleted": true })).into_response()
+        }
+        Err(e) if e.starts_with("unknown session") => error(StatusCode::NOT_FOUND, e),
+        Err(e) if e.starts_with("InfluxDB") => error(StatusCode::BAD_GATEWAY, e),
+        Err(e) => error(StatusCode::CONFLICT, e),
+    }
+}
+
+/// POST /api/sessions/{id}/lock   finalize a closed session, no unlock
+async fn lock_session(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match state.sessions.lock(&id, "api") {
+        Ok(()) => {
+            state.audit.record("api", "session_lock", serde_json::json!({ "session": id }));
+            Json(serde_json::json!({ "session": id, "locked": true })).into_response()
+        }
+        Err(e) if e.starts_with("unknown session") => error(StatusCode::NOT_FOUND, e),
+        Err(e) => error(StatusCode::CONFLICT, e),
+    }
+}
+
+/// GET /api/shift-log?from=&to=&session=&limit=   operator notes, oldest first
+async fn shift_notes(State(state): State<AppState>, Query(query): Query<NoteQuery>) -> Response {
+    match state.shift_log.notes(&query) {
+        Ok(notes) => Json(notes).into_response(),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// POST /api/shift-log   {"text": "...", "author": "shift-a", "device": "<id>"}
+async fn post_shift_note(State(state): State<AppState>, Json(request): Json<PostNote>) -> Response {
+    let author = request.author.as_deref().unwrap_or("api");
+    match state.shift_log.post(author, &request.text, request.device.as_deref()).await {
+        Ok(note) => (StatusCode::CREATED, Json(note)).into_response(),
+        Err(e) => error(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+/// GET /api/handover?hours=   open alerts, running experiments, recent notes
+async fn handover(State(state): State<AppState>, Query(query): Query<HandoverQuery>) -> Response {
+    match state.shift_log.handover(&state.alerts, &state.queue, query.hours.filter(|h| *h > 0.0)) {
+        Ok(handover) => Json(handover).into_response(),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// POST /api/console   {"command": "STATUS", "timeout_ms": 1000, "device": "<id>"}
+async fn console(State(state): State<AppState>, Extension(caller): Extension<Caller>, Json(request): Json<ConsoleRequest>) -> Response {
+    if !state.console.enabled() {
+        return error(StatusCode::NOT_FOUND, "console is disabled ([console] enabled = false)");
+    }
+    let command = match state.console.validate(&request.command) {
+        Ok(command) => command,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e),
+    };
+    // Same checks as a GUI command: the command's permission group, then the control lock
+    if let Err(e) = state.auth.permit(caller.0.as_ref(), &command) {
+        return error(StatusCode::FORBIDDEN, e);
+    }
+    if let Err(e) = state.control.check("api", &command) {
+        return error(StatusCode::CONFLICT, e);
+    }
+    if let Some(device) = request.device.as_deref().filter(|d| !state.devices.connected(d)) {
+        return error(StatusCode::NOT_FOUND, format!("device '{}' is not connected", device));
+    }
+    let timeout = state.console.timeout(request.timeout_ms);
+    state.audit.record("api", "console", serde_json::json!({ "command": command, "device": request.device }));
+    let lines = state.console.execute(&command, request.device.as_deref(), timeout, &state.cmd_tx).await;
+    Json(serde_json::json!({ "command": command, "timeout_ms": timeout.as_millis() as u64, "lines": lines })).into_response()
+}
+
+/// POST /api/webrtc/offer   body: {"type": "offer", "sdp": "..."} -> SDP answer
+async fn webrtc_offer(State(state): State<AppState>, Json(offer): Json<serde_json::Value>) -> Response {
+    match state.rtc.answer(offer).await {
+        Ok(answer) => Json(answer).into_response(),
+        Err(e) => error(StatusCode::SERVICE_UNAVAILABLE, e),
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Deserialize;
+use std::time::Duration;
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ArduinoConfig {
+    /// Accept Arduinos (or a bridge) connecting over TCP
+    #[serde(default = "default_true")]
+    pub tcp_enabled: bool,
+    #[serde(default = "default_tcp_bind")]
+    pub tcp_bind: String,
+    /// Read the Arduino directly from a USB serial port
+    #[serde(default)]
+    pub serial_enabled: bool,
+    #[serde(default = "default_serial_port")]
+    pub serial_port: String,
+    #[serde(default = "default_serial_baud")]
+    pub serial_baud: u32,
+    /// Device id for config profiles
+    #[serde(default = "default_serial_device")]
+    pub serial_device: String,
+    /// How often a missing/unplugged port is retried
+    #[serde(default = "default_reconnect_ms")]
+    pub reconnect_ms: u64,
+}
+
+fn default_true() -> bool { true }
+fn default_tcp_bind() -> String { "192.168.100.187:8081".to_string() }
+fn default_serial_port() -> String { "/dev/ttyACM0".to_string() }
+fn default_serial_baud() -> u32 { 9600 }
+fn default_serial_device() -> String { "usb".to_string() }
+fn default_reconnect_ms() -> u64 { 2000 }
+
+impl Default for ArduinoConfig {
+    fn default() -> Self {
+        Self {
+            tcp_enabled: true,
+            tcp_bind: default_tcp_bind(),
+            serial_enabled: false,
+            serial_port: default_serial_port(),
+            serial_baud: default_serial_baud(),
+            serial_device: default_serial_device(),
+            reconnect_ms: default_reconnect_ms(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    arduino: ArduinoConfig,
+}
+
+impl ArduinoConfig {
+    /// ARDUINO_SERIAL_PORT (enables serial) and ARDUINO_SERIAL_BAUD override the file
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        let mut config = toml::from_str::<ConfigFile>(&content).unwrap_or_default().arduino;
+        if let Ok(port) = std::env::var("ARDUINO_SERIAL_PORT") {
+            config.serial_port = port;
+            config.serial_enabled = true;
+        }
+        if let Some(baud) = std::env::var("ARDUINO_SERIAL_BAUD").ok().and_then(|b| b.parse().ok()) {
+            config.serial_baud = baud;
+        }
+        config
+    }
+}
+
+// ================= Serial Transport =================
+/// Open the serial port, retrying until the board is plugged in
+pub async fn open_serial(config: &ArduinoConfig) -> SerialStream {
+    let mut reported = false;
+    loop {
+        match tokio_serial::new(&config.serial_port, config.serial_baud).open_native_async() {
+            Ok(port) => return port,
+            Err(e) => {
+                // Cukup sekali dilaporkan, port yang dicabut dicoba terus tanpa spam log
+                if !reported {
+                    eprintln!("⚠️ Serial {}: {}, retrying every {} ms", config.serial_port, e, config.reconnect_ms);
+                    reported = true;
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(config.reconnect_ms.max(100))).await;
+    }
+}
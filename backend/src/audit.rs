@@ -0,0 +1,70 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuditConfig {
+    #[serde(default = "default_log_path")]
+    pub log_path: String,
+}
+
+fn default_log_path() -> String { "audit.jsonl".to_string() }
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self { log_path: default_log_path() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    audit: AuditConfig,
+}
+
+impl AuditConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().audit
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct AuditRecord<'a> {
+    timestamp: i64,
+    /// Who did it (GUI client address)
+    actor: &'a str,
+    action: &'a str,
+    details: serde_json::Value,
+}
+
+// ================= Audit Log =================
+/// Append-only record of operator actions (one JSON per line)
+#[derive(Clone)]
+pub struct AuditLog {
+    path: Arc<String>,
+    lock: Arc<Mutex<()>>,
+}
+
+impl AuditLog {
+    pub fn new(config: &AuditConfig) -> Self {
+        Self {
+            path: Arc::new(config.log_path.clone()),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn record(&self, actor: &str, action: &str, details: serde_json::Value) {
+        let record = AuditRecord { timestamp: Utc::now().timestamp_millis(), actor, action, details };
+        let _guard = self.lock.lock().unwrap();
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path.as_str())
+            .and_then(|mut file| writeln!(file, "{}", serde_json::to_string(&record)?));
+        if let Err(e) = result {
+            eprintln!("❌ Failed to write audit log: {}", e);
+        }
+    }
+}
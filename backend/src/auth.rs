@@ -0,0 +1,267 @@
+use axum::http::{header, HeaderMap};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::audit::AuditLog;
+
+/// Permission group; a token holds any combination of them
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Group {
+    /// Status, lists, history, analytics
+    View,
+    /// Shift notes, session labels, alert acknowledge/silence
+    Annotate,
+    /// Queue, recordings, campaigns, jobs, sampling start/stop
+    RunExperiments,
+    /// Heater, modulation, valves and any other raw device command
+    ActuateHardware,
+    /// Config, calibration, models, channel masks, alert rules, session lock/delete
+    AdminConfig,
+}
+
+impl Group {
+    pub const ALL: [Group; 5] = [Group::View, Group::Annotate, Group::RunExperiments, Group::ActuateHardware, Group::AdminConfig];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::View => "view",
+            Self::Annotate => "annotate",
+            Self::RunExperiments => "run-experiments",
+            Self::ActuateHardware => "actuate-hardware",
+            Self::AdminConfig => "admin-config",
+        }
+    }
+}
+
+/// One `[[auth.tokens]]` entry
+#[derive(Debug, Deserialize, Clone)]
+pub struct TokenConfig {
+    pub token: String,
+    /// Shown in logs and the audit trail instead of the token
+    pub name: String,
+    pub groups: Vec<Group>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuthConfig {
+    /// Commands and REST calls need a token holding the command's group
+    #[serde(default)]
+    pub enabled: bool,
+    /// Groups of clients that have not sent AUTH
+    #[serde(default = "default_anonymous")]
+    pub anonymous: Vec<Group>,
+    /// Groups of commands arriving over NATS (the NATS server authenticates publishers)
+    #[serde(default = "default_nats")]
+    pub nats: Vec<Group>,
+    #[serde(default)]
+    pub tokens: Vec<TokenConfig>,
+    /// Command word → group, overriding the built-in classification
+    #[serde(default)]
+    pub commands: BTreeMap<String, Group>,
+}
+
+fn default_anonymous() -> Vec<Group> { vec![Group::View] }
+fn default_nats() -> Vec<Group> { Group::ALL.to_vec() }
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            anonymous: default_anonymous(),
+            nats: default_nats(),
+            tokens: Vec::new(),
+            commands: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    auth: AuthConfig,
+}
+
+impl AuthConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().auth
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let mut tokens = HashSet::new();
+        let mut names = HashSet::new();
+        for entry in &self.tokens {
+            if entry.token.trim().is_empty() || entry.name.trim().is_empty() {
+                return Err("auth.tokens entries need a token and a name".to_string());
+            }
+            if !tokens.insert(entry.token.as_str()) {
+                return Err(format!("auth.tokens: token of '{}' is used twice", entry.name));
+            }
+            if !names.insert(entry.name.as_str()) {
+                return Err(format!("auth.tokens: name '{}' is used twice", entry.name));
+            }
+        }
+        if self.enabled && self.tokens.is_empty() {
+            return Err("auth.enabled needs at least one [[auth.tokens]] entry".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Who sent a command, and what they may do
+#[derive(Debug, Serialize, Clone)]
+pub struct Identity {
+    pub name: String,
+    pub groups: Vec<Group>,
+}
+
+/// Command word without a "device:" prefix, upper case
+fn command_word(cmd: &str) -> String {
+    let word = cmd.split_whitespace().next().unwrap_or_default();
+    word.rsplit_once(':').map_or(word, |(_, c)| c).to_ascii_uppercase()
+}
+
+/// Built-in group of a command word; None = allowed for everyone (AUTH)
+fn classify(word: &str) -> Option<Group> {
+    let group = match word {
+        "AUTH" => return None,
+        "SHIFT_NOTE" | "SESSION_LABEL" | "ALERT_ACK" | "ALERT_SILENCE" => Group::Annotate,
+        "CONFIG_SET" | "CONFIG_ROLLBACK" | "RELOAD_CONFIG" | "ALERT_ADD" | "ALERT_REMOVE" | "SESSION_LOCK"
+        | "SESSION_DELETE" | "BOARD_CONFIRM" | "LATENCY_RESET" | "JOB_CALIBRATE" | "JOB_TRANSFER" => Group::AdminConfig,
+        // Config reads show the tokens themselves
+        "CONFIG_GET" | "CONFIG_VERSIONS" | "CONFIG_DIFF" => Group::AdminConfig,
+        "CHANNEL_ENABLE" | "CHANNEL_DISABLE" => Group::AdminConfig,
+        w if w.starts_with("MODEL_") => Group::AdminConfig,
+        "DEVICES" | "CLIENTS" | "INFLUX_STATUS" | "LATENCY" | "ACCURACY" | "HISTOGRAM" | "SPECTROGRAM" | "CHANNELS"
+        | "SESSIONS" | "SESSION_SUMMARY" | "SHIFT_LOG" | "HANDOVER" | "ALERT_LIST" | "QUEUE_LIST" | "MODELS" | "JOBS"
+        | "CAMPAIGNS" | "DEVICE_LOG" | "NOISE_FLOOR" | "USAGE" => Group::View,
+        w if w.ends_with("_STATUS") || w.starts_with("CONTROL_") => Group::View,
+        "LEAK_TEST" | "DILUTE" | "BACKFILL" | "CAPTURE_DEBUG_BUNDLE" | "START_SAMPLING" | "STOP_SAMPLING" => {
+            Group::RunExperiments
+        }
+        w if ["QUEUE_", "REC_", "CAMPAIGN_", "JOB_", "REPLAY", "BURN_IN_", "NOISE_FLOOR_"].iter().any(|p| w.starts_with(p)) => {
+            Group::RunExperiments
+        }
+        // Heater, modulation, valves and everything forwarded to the device
+        _ => Group::ActuateHardware,
+    };
+    Some(group)
+}
+
+// ================= Access Control =================
+/// Token-based permission groups. A GUI connection sends `AUTH <token>`
+/// once; HTTP callers send `Authorization: Bearer <token>` per request.
+/// Every command is checked against the group it belongs to.
+#[derive(Clone)]
+pub struct Auth {
+    config: Arc<AuthConfig>,
+    /// Client ID → identity of its AUTH
+    sessions: Arc<Mutex<HashMap<String, Identity>>>,
+    audit: AuditLog,
+}
+
+impl Auth {
+    pub fn new(config: AuthConfig, audit: AuditLog) -> Self {
+        Self { config: Arc::new(config), sessions: Arc::new(Mutex::new(HashMap::new())), audit }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Identity of a token
+    pub fn lookup(&self, token: &str) -> Option<Identity> {
+        self.config
+            .tokens
+            .iter()
+            .find(|entry| entry.token == token)
+            .map(|entry| Identity { name: entry.name.clone(), groups: entry.groups.clone() })
+    }
+
+    /// Identity of a GUI client that sent AUTH
+    pub fn identity(&self, client: &str) -> Option<Identity> {
+        self.sessions.lock().unwrap().get(client).cloned()
+    }
+
+    /// Identity of commands arriving over NATS
+    pub fn nats(&self) -> Identity {
+        Identity { name: "nats".to_string(), groups: self.config.nats.clone() }
+    }
+
+    pub fn login(&self, client: &str, token: &str) -> Result<Identity, String> {
+        let Some(identity) = self.lookup(token) else {
+            eprintln!("🔒 Invalid token from {}", client);
+            self.audit.record(client, "auth_failed", serde_json::json!({}));
+            return Err("invalid token".to_string());
+        };
+        println!("🔑 {} authenticated as {}", client, identity.name);
+        self.audit.record(client, "auth", serde_json::json!({ "name": identity.name, "groups": identity.groups }));
+        self.sessions.lock().unwrap().insert(client.to_string(), identity.clone());
+        Ok(identity)
+    }
+
+    pub fn logout(&self, client: &str) {
+        self.sessions.lock().unwrap().remove(client);
+    }
+
+    /// Group a command needs; None = always allowed
+    pub fn required(&self, cmd: &str) -> Option<Group> {
+        let word = command_word(cmd);
+        match self.config.commands.iter().find(|(name, _)| name.eq_ignore_ascii_case(&word)) {
+            Some((_, group)) => Some(*group),
+            None => classify(&word),
+        }
+    }
+
+    /// May `identity` (None = not authenticated) send `cmd`?
+    pub fn permit(&self, identity: Option<&Identity>, cmd: &str) -> Result<(), String> {
+        match self.required(cmd) {
+            Some(group) => self.allow(identity, group).map_err(|e| format!("{} ({})", e, command_word(cmd))),
+            None => Ok(()),
+        }
+    }
+
+    /// Does `identity` (None = not authenticated) hold `group`?
+    pub fn allow(&self, identity: Option<&Identity>, group: Group) -> Result<(), String> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        match identity {
+            Some(identity) if identity.groups.contains(&group) => Ok(()),
+            Some(identity) => Err(format!("'{}' lacks the {} permission", identity.name, group.name())),
+            None if self.config.anonymous.contains(&group) => Ok(()),
+            None => Err(format!("{} permission required; authenticate first", group.name())),
+        }
+    }
+}
+
+/// Token of an `Authorization: Bearer <token>` header
+pub fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers.get(header::AUTHORIZATION)?.to_str().ok()?.strip_prefix("Bearer ").map(str::trim)
+}
+
+/// The command as it may be logged: AUTH tokens masked
+pub fn redact(cmd: &str) -> String {
+    if command_word(cmd) == "AUTH" {
+        "AUTH ***".to_string()
+    } else {
+        cmd.to_string()
+    }
+}
+
+/// GUI command:
+///   AUTH <token>    reply {"type": "auth", "name": ..., "groups": [...]}
+pub fn handle_command(auth: &Auth, client: &str, args: &[&str]) -> Result<Option<String>, String> {
+    if !auth.enabled() {
+        return Err("authentication is disabled ([auth] enabled = false)".to_string());
+    }
+    match args {
+        ["AUTH", token] => {
+            let identity = auth.login(client, token)?;
+            Ok(Some(serde_json::json!({ "type": "auth", "name": identity.name, "groups": identity.groups }).to_string()))
+        }
+        _ => Err("usage: AUTH <token>".to_string()),
+    }
+}
@@ -0,0 +1,230 @@
+use rusqlite::{params, OptionalExtension};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::clock_drift::ClockDrift;
+use crate::filtering::UnifiedSensorRaw;
+use crate::influxdb::UnifiedSensorData as InfluxData;
+use crate::provenance::Provenance;
+use crate::store::LocalStore;
+
+/// Re-sent SD card rows: `BACKFILL:<epoch>,<SENSOR fields...>`
+pub const BACKFILL_PREFIX: &str = "BACKFILL:";
+/// Sent by the firmware once the requested range is done
+pub const BACKFILL_END: &str = "BACKFILL_END";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct BackfillConfig {
+    /// Firmware logs to SD and understands the backfill request
+    #[serde(default)]
+    pub enabled: bool,
+    /// Request the downtime range automatically when a device connects
+    #[serde(default = "default_true")]
+    pub auto_on_connect: bool,
+    /// Shorter gaps are not worth a request
+    #[serde(default = "default_min_gap_secs")]
+    pub min_gap_secs: i64,
+    /// Only the newest part of longer gaps is requested
+    #[serde(default = "default_max_hours")]
+    pub max_hours: f64,
+    /// {from}/{to} = epoch seconds
+    #[serde(default = "default_request_command")]
+    pub request_command: String,
+    /// How often the last received frame time is persisted
+    #[serde(default = "default_checkpoint_secs")]
+    pub checkpoint_secs: i64,
+}
+
+fn default_true() -> bool { true }
+fn default_min_gap_secs() -> i64 { 30 }
+fn default_max_hours() -> f64 { 24.0 }
+fn default_request_command() -> String { "BACKFILL {from} {to}".to_string() }
+fn default_checkpoint_secs() -> i64 { 10 }
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            auto_on_connect: true,
+            min_gap_secs: default_min_gap_secs(),
+            max_hours: default_max_hours(),
+            request_command: default_request_command(),
+            checkpoint_secs: default_checkpoint_secs(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    backfill: BackfillConfig,
+}
+
+impl BackfillConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().backfill
+    }
+}
+
+/// Epoch seconds or milliseconds into milliseconds
+fn epoch_millis(value: f64) -> i64 {
+    // > 1e11 berarti sudah dalam milidetik
+    if value > 1e11 { value as i64 } else { (value * 1000.0) as i64 }
+}
+
+// ================= SD Card Backfill =================
+/// Tracks when each device was last heard from, so the gap left by backend
+/// downtime can be requested from the firmware's SD log on reconnect
+#[derive(Clone)]
+pub struct Backfill {
+    config: Arc<BackfillConfig>,
+    store: LocalStore,
+    checkpoints: Arc<Mutex<HashMap<String, i64>>>,
+    /// Points received per device for the running request
+    received: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl Backfill {
+    pub fn new(config: BackfillConfig, store: LocalStore) -> Self {
+        Self {
+            config: Arc::new(config),
+            store,
+            checkpoints: Arc::new(Mutex::new(HashMap::new())),
+            received: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Persist the device's last frame time every checkpoint_secs
+    pub fn seen(&self, device: &str, timestamp: i64) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut checkpoints = self.checkpoints.lock().unwrap();
+        let last = checkpoints.get(device).copied().unwrap_or(0);
+        if timestamp - last < self.config.checkpoint_secs * 1000 {
+            return;
+        }
+        checkpoints.insert(device.to_string(), timestamp);
+        let result = self.store.conn().execute(
+            "INSERT INTO device_seen (device, last_frame) VALUES (?1, ?2)
+             ON CONFLICT(device) DO UPDATE SET last_frame = excluded.last_frame",
+            params![device, timestamp],
+        );
+        if let Err(e) = result {
+            eprintln!("❌ Failed to record device checkpoint: {}", e);
+        }
+    }
+
+    /// Request command for the gap since the device was last seen, if any
+    pub fn on_connect(&self, device: &str, now: i64) -> Option<String> {
+        if !self.config.enabled || !self.config.auto_on_connect {
+            return None;
+        }
+        let last: Option<i64> = self
+            .store
+            .conn()
+            .query_row("SELECT last_frame FROM device_seen WHERE device = ?1", [device], |row| row.get(0))
+            .optional()
+            .unwrap_or_else(|e| {
+                eprintln!("❌ Failed to read device checkpoint: {}", e);
+                None
+            });
+        let last = last?;
+        if now - last < self.config.min_gap_secs * 1000 {
+            return None;
+        }
+        let from = last.max(now - (self.config.max_hours * 3_600_000.0) as i64);
+        println!(
+            "🗂️ {} was offline {:.0} s, requesting backfill from SD card",
+            device,
+            (now - last) as f64 / 1000.0
+        );
+        Some(self.request(from, now))
+    }
+
+    /// Firmware command for an explicit range (epoch ms)
+    pub fn request(&self, from: i64, to: i64) -> String {
+        self.config
+            .request_command
+            .replace("{from}", &(from / 1000).to_string())
+            .replace("{to}", &(to / 1000).to_string())
+    }
+
+    /// Turn one `BACKFILL:` row (prefix stripped) into a point with its
+    /// original timestamp (drift-corrected when the device has an estimate).
+    /// Rows are stored as logged (unfiltered), with source "backfill".
+    pub fn ingest(
+        &self,
+        data: &str,
+        device: &str,
+        provenance: &Provenance,
+        clock_drift: Option<&ClockDrift>,
+    ) -> Option<InfluxData> {
+        let values: Vec<f64> = data.split(',').filter_map(|s| s.trim().parse::<f64>().ok()).collect();
+        if values.len() < 10 {
+            return None;
+        }
+        let timestamp = epoch_millis(values[0]);
+        let timestamp = clock_drift.and_then(|drift| drift.correct(timestamp)).unwrap_or(timestamp);
+        let raw = crate::parse_sensor_values(&values[1..].iter().map(|v| *v as f32).collect::<Vec<_>>())?;
+        *self.received.lock().unwrap().entry(device.to_string()).or_default() += 1;
+        Some(point(&raw, timestamp, device, provenance))
+    }
+
+    /// BACKFILL_END: number of points received for the finished request
+    pub fn finish(&self, device: &str) -> usize {
+        self.received.lock().unwrap().remove(device).unwrap_or(0)
+    }
+}
+
+fn point(raw: &UnifiedSensorRaw, timestamp: i64, device: &str, provenance: &Provenance) -> InfluxData {
+    InfluxData {
+        channels: raw.channels,
+        state: raw.state,
+        level: raw.level,
+        flow: raw.flow,
+        pressure: raw.pressure,
+        humidity: raw.humidity,
+        heater_temp: raw.heater_temp,
+        heater_setpoint: None,
+        target_ppm: None,
+        delivered_ppm: None,
+        cycle_valid: true,
+        timestamp: timestamp * 1_000_000,
+        source: "backfill".to_string(),
+        device: device.to_string(),
+        session: None,
+        reference: None,
+        raw: None,
+        location: None,
+        modulation: Default::default(),
+        saturated: Vec::new(),
+        ranges: Default::default(),
+        virtual_channels: Default::default(),
+        provenance: provenance.clone(),
+    }
+}
+
+/// Epoch s/ms or RFC3339, as epoch ms
+pub fn parse_time(s: &str) -> Result<i64, String> {
+    s.parse::<f64>()
+        .map(epoch_millis)
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(s).map(|t| t.timestamp_millis()))
+        .map_err(|_| format!("invalid time '{}'", s))
+}
+
+/// GUI command: BACKFILL <from> <to> [device]   (epoch s/ms or RFC3339)
+pub fn parse_range<'a>(args: &[&'a str]) -> Result<(i64, i64, Option<&'a str>), String> {
+    match args {
+        [_, from, to, device @ ..] if device.len() <= 1 => {
+            let (from, to) = (parse_time(from)?, parse_time(to)?);
+            if from >= to {
+                return Err("backfill range is empty".to_string());
+            }
+            Ok((from, to, device.first().copied()))
+        }
+        _ => Err("usage: BACKFILL <from> <to> [device]".to_string()),
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::Context;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Read;
+use std::path::Path;
+
+use crate::audit::AuditConfig;
+use crate::baseline::BaselineConfig;
+use crate::leak_test::LeakTestConfig;
+use crate::plugins::PluginConfig;
+use crate::scripting::ScriptingConfig;
+use crate::store::{self, StoreConfig};
+use crate::trigger::TriggerConfig;
+use crate::video_sync::VideoSyncConfig;
+
+const MANIFEST: &str = "manifest.json";
+
+/// Config revision history kept by the ConfigStore
+const VERSIONS_DIR: &str = "config_versions";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    created: String,
+    backend_version: String,
+    schema_version: u32,
+    /// Archive member -> original path
+    files: BTreeMap<String, String>,
+}
+
+/// Everything a deployment needs besides InfluxDB: config (with calibration),
+/// its history, the local store, session/maintenance logs, plugins and scripts.
+/// `.env` is left out on purpose, it holds credentials.
+fn deployment_files(config_path: &str) -> Vec<String> {
+    let mut files = vec![config_path.to_string()];
+    if let Ok(entries) = std::fs::read_dir(VERSIONS_DIR) {
+        let mut history: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path().to_string_lossy().into_owned())
+            .collect();
+        history.sort();
+        files.extend(history);
+    }
+    files.push(AuditConfig::load(config_path).log_path);
+    files.push(BaselineConfig::load(config_path).log_path);
+    files.push(LeakTestConfig::load(config_path).log_path);
+    files.push(TriggerConfig::load(config_path).log_path);
+    files.push(VideoSyncConfig::load(config_path).marker_path);
+    files.extend(PluginConfig::load(config_path, None).wasm);
+    files.extend(ScriptingConfig::load(config_path).scripts);
+    let mut seen = HashSet::new();
+    files.retain(|f| !f.is_empty() && Path::new(f).is_file() && seen.insert(f.clone()));
+    files
+}
+
+// ================= Backup =================
+/// Write a .tar.gz of the deployment; returns the number of files archived
+pub fn backup(config_path: &str, archive_path: &str) -> anyhow::Result<usize> {
+    let store_path = StoreConfig::load(config_path).path;
+    let mut files = BTreeMap::new();
+
+    let archive = std::fs::File::create(archive_path).with_context(|| format!("creating {}", archive_path))?;
+    let mut tar = tar::Builder::new(GzEncoder::new(archive, Compression::default()));
+
+    for (i, path) in deployment_files(config_path).into_iter().enumerate() {
+        let name = format!("files/{}-{}", i, file_name(&path));
+        tar.append_path_with_name(&path, &name).with_context(|| format!("archiving {}", path))?;
+        files.insert(name, path);
+    }
+
+    // Snapshot konsisten walau backend sedang menulis
+    let mut schema_version = 0;
+    if Path::new(&store_path).is_file() {
+        schema_version = store::status(&store_path)?.current;
+        let snapshot = format!("{}.backup-tmp", store_path);
+        let _ = std::fs::remove_file(&snapshot);
+        let conn = rusqlite::Connection::open(&store_path)?;
+        conn.execute("VACUUM INTO ?1", [&snapshot])?;
+        let name = format!("store/{}", file_name(&store_path));
+        let result = tar.append_path_with_name(&snapshot, &name);
+        let _ = std::fs::remove_file(&snapshot);
+        result?;
+        files.insert(name, store_path);
+    }
+
+    let manifest = Manifest {
+        created: chrono::Local::now().to_rfc3339(),
+        backend_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version,
+        files,
+    };
+    let json = serde_json::to_vec_pretty(&manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp() as u64);
+    tar.append_data(&mut header, MANIFEST, json.as_slice())?;
+
+    tar.into_inner()?.finish()?;
+    Ok(manifest.files.len())
+}
+
+// ================= Restore =================
+/// Unpack a backup to the paths recorded in its manifest. Existing files are
+/// only overwritten with `force`; returns the restored paths.
+pub fn restore(archive_path: &str, force: bool) -> anyhow::Result<Vec<String>> {
+    let manifest = read_manifest(archive_path)?;
+    if manifest.schema_version > store::latest_version() {
+        anyhow::bail!(
+            "backup has local store schema v{}, this release only knows up to v{}",
+            manifest.schema_version,
+            store::latest_version()
+        );
+    }
+    if !force {
+        let existing: Vec<&String> = manifest.files.values().filter(|p| Path::new(p).exists()).collect();
+        if !existing.is_empty() {
+            anyhow::bail!(
+                "would overwrite {} (use --force)",
+                existing.iter().map(|p| p.as_str()).collect::<Vec<_>>().join(", ")
+            );
+        }
+    }
+
+    let mut restored = Vec::new();
+    let mut tar = open_archive(archive_path)?;
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let Some(target) = manifest.files.get(&name) else {
+            continue;
+        };
+        if let Some(parent) = Path::new(target).parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        entry.unpack(target).with_context(|| format!("restoring {}", target))?;
+        restored.push(target.clone());
+    }
+    Ok(restored)
+}
+
+fn open_archive(path: &str) -> anyhow::Result<tar::Archive<GzDecoder<std::fs::File>>> {
+    let file = std::fs::File::open(path).with_context(|| format!("opening {}", path))?;
+    Ok(tar::Archive::new(GzDecoder::new(file)))
+}
+
+fn read_manifest(archive_path: &str) -> anyhow::Result<Manifest> {
+    let mut tar = open_archive(archive_path)?;
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.as_os_str() == MANIFEST {
+            let mut json = String::new();
+            entry.read_to_string(&mut json)?;
+            return Ok(serde_json::from_str(&json)?);
+        }
+    }
+    anyhow::bail!("{} is not an e-nose backup (no {})", archive_path, MANIFEST)
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use chrono::{Local, NaiveTime, Duration as ChronoDuration};
+use std::io::{BufRead, Write};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::{UnifiedSensorData, CHANNEL_NAMES};
+use crate::provenance::CalibrationConfig;
+use crate::devices::DeviceRegistry;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct BaselineConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Local time of day to run the zero cycle, "HH:MM"
+    #[serde(default = "default_run_at")]
+    pub run_at: String,
+    /// Command sent to the Arduino to start flushing the chamber with clean air
+    #[serde(default = "default_purge_command")]
+    pub purge_command: String,
+    /// Optional command sent after capture (e.g. to return the rig to idle)
+    #[serde(default)]
+    pub stop_command: Option<String>,
+    #[serde(default = "default_purge_secs")]
+    pub purge_secs: u64,
+    #[serde(default = "default_capture_secs")]
+    pub capture_secs: u64,
+    /// Maximum allowed change vs the previous night, in percent
+    #[serde(default = "default_max_drift_percent")]
+    pub max_drift_percent: f32,
+    #[serde(default = "default_log_path")]
+    pub log_path: String,
+}
+
+fn default_run_at() -> String { "02:00".to_string() }
+fn default_purge_command() -> String { "PURGE".to_string() }
+fn default_purge_secs() -> u64 { 120 }
+fn default_capture_secs() -> u64 { 60 }
+fn default_max_drift_percent() -> f32 { 20.0 }
+fn default_log_path() -> String { "baselines.jsonl".to_string() }
+
+impl Default for BaselineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            run_at: default_run_at(),
+            purge_command: default_purge_command(),
+            stop_command: None,
+            purge_secs: default_purge_secs(),
+            capture_secs: default_capture_secs(),
+            max_drift_percent: default_max_drift_percent(),
+            log_path: default_log_path(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    baseline: BaselineConfig,
+}
+
+impl BaselineConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().baseline
+    }
+}
+
+/// One zero-cycle result, appended as a JSON line to `log_path`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BaselineRecord {
+    /// Device the cycle ran on; empty for records written before multi-device support
+    #[serde(default)]
+    pub device: String,
+    pub timestamp: i64,
+    pub samples: usize,
+    pub values: [f32; 7],
+}
+
+/// Channel yang bergeser melebihi batas: (nama, nilai lama, nilai baru, persen)
+type Drift = (&'static str, f32, f32, f32);
+
+// ================= Scheduler =================
+pub async fn run_scheduler(
+    config: BaselineConfig,
+    cmd_tx: broadcast::Sender<String>,
+    data_tx: broadcast::Sender<String>,
+    frame_tx: broadcast::Sender<UnifiedSensorData>,
+    devices: DeviceRegistry,
+) {
+    let run_at = match NaiveTime::parse_from_str(&config.run_at, "%H:%M") {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("❌ Invalid baseline run_at '{}': {}", config.run_at, e);
+            return;
+        }
+    };
+    println!("🌙 Nightly baseline cycle scheduled at {}", config.run_at);
+
+    loop {
+        tokio::time::sleep(until_next(run_at)).await;
+
+        // Satu zero cycle per device, berjalan bersamaan
+        let ids: Vec<String> = devices.snapshot().into_iter().map(|info| info.device).collect();
+        if ids.is_empty() {
+            eprintln!("❌ No device connected, skipping nightly baseline cycle");
+            continue;
+        }
+        println!("🌙 Starting nightly baseline cycle on {}", ids.join(", "));
+        let cycles = ids.iter().map(|device| run_zero_cycle(&config, device, &cmd_tx, &frame_tx));
+        for (device, record) in ids.iter().zip(futures::future::join_all(cycles).await) {
+            match record {
+                Some(record) => report(&config, record, &data_tx),
+                None => eprintln!("❌ Baseline cycle on {} captured no data, skipping", device),
+            }
+        }
+    }
+}
+
+/// Time remaining until the next occurrence of `run_at` in local time
+fn until_next(run_at: NaiveTime) -> Duration {
+    let now = Local::now().naive_local();
+    let mut next = now.date().and_time(run_at);
+    if next <= now {
+        next += ChronoDuration::days(1);
+    }
+    (next - now).to_std().unwrap_or_default()
+}
+
+async fn run_zero_cycle(
+    config: &BaselineConfig,
+    device: &str,
+    cmd_tx: &broadcast::Sender<String>,
+    frame_tx: &broadcast::Sender<UnifiedSensorData>,
+) -> Option<BaselineRecord> {
+    let _ = cmd_tx.send(format!("{}:{}", device, config.purge_command));
+    tokio::time::sleep(Duration::from_secs(config.purge_secs)).await;
+
+    // Rata-rata frame device ini selama jendela capture
+    let mut frame_rx = frame_tx.subscribe();
+    let mut sums = [0.0f64; 7];
+    let mut samples = 0usize;
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(config.capture_secs);
+
+    loop {
+        match tokio::time::timeout_at(deadline, frame_rx.recv()).await {
+            Ok(Ok(frame)) if frame.device == device => {
+                for (sum, value) in sums.iter_mut().zip(frame.channels.values()) {
+                    *sum += value as f64;
+                }
+                samples += 1;
+            }
+            Ok(Ok(_)) | Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+            Ok(Err(broadcast::error::RecvError::Closed)) | Err(_) => break,
+        }
+    }
+
+    if let Some(stop) = &config.stop_command {
+        let _ = cmd_tx.send(format!("{}:{}", device, stop));
+    }
+
+    if samples == 0 {
+        return None;
+    }
+
+    Some(BaselineRecord {
+        device: device.to_string(),
+        timestamp: Local::now().timestamp_millis(),
+        samples,
+        values: sums.map(|s| (s / samples as f64) as f32),
+    })
+}
+
+fn report(config: &BaselineConfig, record: BaselineRecord, data_tx: &broadcast::Sender<String>) {
+    // Belum ada riwayat: bandingkan dengan baseline kalibrasi dari burn-in
+    let previous = last_record(&config.log_path, &record.device).or_else(|| {
+        CalibrationConfig::load("config.toml", Some(&record.device))
+            .baseline
+            .map(|values| BaselineRecord { device: record.device.clone(), timestamp: 0, samples: 0, values })
+    });
+    let drifts = previous
+        .as_ref()
+        .map(|prev| compare(prev, &record, config.max_drift_percent))
+        .unwrap_or_default();
+
+    println!("🌙 Baseline on {} captured from {} samples:", record.device, record.samples);
+    for (name, value) in CHANNEL_NAMES.iter().zip(record.values) {
+        println!("   {}: {:.3}", name, value);
+    }
+    for (name, old, new, percent) in &drifts {
+        eprintln!(
+            "⚠️ Baseline drift on {} {}: {:.3} → {:.3} ({:.1}% > {:.1}%)",
+            record.device, name, old, new, percent, config.max_drift_percent
+        );
+    }
+
+    if let Err(e) = append_record(&config.log_path, &record) {
+        eprintln!("❌ Failed to write baseline log: {}", e);
+    }
+
+    let event = serde_json::json!({
+        "type": "baseline",
+        "device": record.device,
+        "timestamp": record.timestamp,
+        "samples": record.samples,
+        "values": CHANNEL_NAMES.iter().zip(record.values).collect::<std::collections::BTreeMap<_, _>>(),
+        "drift_alerts": drifts.iter().map(|(name, old, new, percent)| serde_json::json!({
+            "channel": name,
+            "previous": old,
+            "current": new,
+            "percent": percent,
+        })).collect::<Vec<_>>(),
+    });
+    let _ = data_tx.send(event.to_string());
+}
+
+fn compare(prev: &BaselineRecord, current: &BaselineRecord, limit: f32) -> Vec<Drift> {
+    CHANNEL_NAMES
+        .iter()
+        .zip(prev.values.iter().zip(current.values))
+        .filter_map(|(name, (&old, new))| {
+            if old.abs() < f32::EPSILON {
+                return None;
+            }
+            let percent = (new - old).abs() / old.abs() * 100.0;
+            (percent > limit).then_some((*name, old, new, percent))
+        })
+        .collect()
+}
+
+/// Latest record for `device`; untagged records only count while no tagged one exists
+fn last_record(path: &str, device: &str) -> Option<BaselineRecord> {
+    let file = std::fs::File::open(path).ok()?;
+    let records: Vec<BaselineRecord> = std::io::BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect();
+    let tagged = records.iter().rev().find(|r| r.device == device);
+    tagged.or_else(|| records.iter().rev().find(|r| r.device.is_empty())).cloned()
+}
+
+fn append_record(path: &str, record: &BaselineRecord) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+}
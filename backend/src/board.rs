@@ -0,0 +1,193 @@
+use rusqlite::{params, OptionalExtension};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::audit::AuditLog;
+use crate::store::LocalStore;
+
+/// Sensor board identity announced by the firmware: `BOARD:<serial>`
+pub const BOARD_PREFIX: &str = "BOARD:";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct BoardConfig {
+    /// Check the board serial on every connect
+    #[serde(default)]
+    pub enabled: bool,
+    /// Sent on connect to make the firmware announce its board, "" = wait
+    #[serde(default = "default_request_command")]
+    pub request_command: String,
+    /// Treat frames as unverified until the board has identified itself
+    #[serde(default)]
+    pub require_id: bool,
+}
+
+fn default_request_command() -> String { "BOARD_ID".to_string() }
+
+impl Default for BoardConfig {
+    fn default() -> Self {
+        Self { enabled: false, request_command: default_request_command(), require_id: false }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    board: BoardConfig,
+}
+
+impl BoardConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().board
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct DeviceBoard {
+    /// Board the device's calibration belongs to
+    confirmed: Option<String>,
+    /// Board reported since the last connect
+    reported: Option<String>,
+}
+
+impl DeviceBoard {
+    fn verified(&self, require_id: bool) -> bool {
+        match (&self.confirmed, &self.reported) {
+            (Some(confirmed), Some(reported)) => confirmed == reported,
+            (_, None) => !require_id,
+            (None, Some(_)) => true,
+        }
+    }
+}
+
+// ================= Sensor Board Identity =================
+/// Remembers which physical sensor board each device id was confirmed with.
+/// A different board on reconnect marks the device's frames unverified (not
+/// stored, not used for baselines or calibration) until an operator confirms
+/// the swap with BOARD_CONFIRM.
+#[derive(Clone)]
+pub struct Boards {
+    config: Arc<BoardConfig>,
+    store: LocalStore,
+    devices: Arc<Mutex<HashMap<String, DeviceBoard>>>,
+    data_tx: broadcast::Sender<String>,
+}
+
+impl Boards {
+    pub fn new(config: BoardConfig, store: LocalStore, data_tx: broadcast::Sender<String>) -> Self {
+        Self { config: Arc::new(config), store, devices: Arc::new(Mutex::new(HashMap::new())), data_tx }
+    }
+
+    /// Forget the previous connection's report; returns the request command
+    pub fn on_connect(&self, device: &str) -> Option<String> {
+        if !self.config.enabled {
+            return None;
+        }
+        let confirmed = self.confirmed(device);
+        self.devices.lock().unwrap().insert(device.to_string(), DeviceBoard { confirmed, reported: None });
+        (!self.config.request_command.is_empty()).then(|| self.config.request_command.clone())
+    }
+
+    /// `BOARD:` line (prefix stripped). The first board seen for a device is
+    /// confirmed implicitly.
+    pub fn identify(&self, device: &str, board: &str) {
+        if !self.config.enabled {
+            return;
+        }
+        let board = board.trim().to_string();
+        let confirmed = {
+            let mut devices = self.devices.lock().unwrap();
+            let entry = devices.entry(device.to_string()).or_default();
+            entry.reported = Some(board.clone());
+            entry.confirmed.get_or_insert_with(|| board.clone()).clone()
+        };
+        if self.confirmed(device).is_none() {
+            println!("🪪 {} board {} registered", device, board);
+            if let Err(e) = self.save(device, &board, "auto") {
+                eprintln!("❌ Failed to record board: {}", e);
+            }
+        } else if confirmed != board {
+            eprintln!(
+                "⚠️ {} reports board {} but was calibrated on {}; data unverified until BOARD_CONFIRM {}",
+                device, board, confirmed, device
+            );
+        }
+        self.publish(device);
+    }
+
+    /// Frames from this device may be stored and used
+    pub fn verified(&self, device: &str) -> bool {
+        if !self.config.enabled {
+            return true;
+        }
+        let devices = self.devices.lock().unwrap();
+        devices.get(device).is_none_or(|board| board.verified(self.config.require_id))
+    }
+
+    /// Operator accepts the board the device currently reports
+    pub fn confirm(&self, device: &str, actor: &str, audit: &AuditLog) -> Result<(), String> {
+        let (previous, board) = {
+            let devices = self.devices.lock().unwrap();
+            let entry = devices.get(device).ok_or_else(|| format!("unknown device {}", device))?;
+            let board = entry.reported.clone().ok_or_else(|| format!("{} has not reported a board", device))?;
+            (entry.confirmed.clone(), board)
+        };
+        self.save(device, &board, actor).map_err(|e| e.to_string())?;
+        self.devices.lock().unwrap().entry(device.to_string()).or_default().confirmed = Some(board.clone());
+        println!("🪪 {} board {} confirmed by {}", device, board, actor);
+        audit.record(actor, "board_confirm", serde_json::json!({ "device": device, "board": board, "previous": previous }));
+        self.publish(device);
+        Ok(())
+    }
+
+    fn confirmed(&self, device: &str) -> Option<String> {
+        self.store
+            .conn()
+            .query_row("SELECT board FROM device_boards WHERE device = ?1", [device], |row| row.get(0))
+            .optional()
+            .unwrap_or_else(|e| {
+                eprintln!("❌ Failed to read board: {}", e);
+                None
+            })
+    }
+
+    fn save(&self, device: &str, board: &str, actor: &str) -> rusqlite::Result<usize> {
+        self.store.conn().execute(
+            "INSERT INTO device_boards (device, board, confirmed_at, confirmed_by) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(device) DO UPDATE SET board = excluded.board,
+                 confirmed_at = excluded.confirmed_at, confirmed_by = excluded.confirmed_by",
+            params![device, board, chrono::Utc::now().timestamp_millis(), actor],
+        )
+    }
+
+    /// "board" GUI event for one device, or all known devices
+    pub fn publish(&self, device: &str) {
+        let devices = self.devices.lock().unwrap();
+        for (id, board) in devices.iter().filter(|(id, _)| device.is_empty() || id.as_str() == device) {
+            let event = serde_json::json!({
+                "type": "board",
+                "device": id,
+                "confirmed": board.confirmed,
+                "reported": board.reported,
+                "verified": board.verified(self.config.require_id),
+            });
+            let _ = self.data_tx.send(event.to_string());
+        }
+    }
+}
+
+/// GUI commands:
+///   BOARD_CONFIRM <device>
+///   BOARD_STATUS
+pub fn handle_command(boards: &Boards, audit: &AuditLog, client: &str, args: &[&str]) -> Result<(), String> {
+    match args {
+        ["BOARD_CONFIRM", device] => boards.confirm(device, client, audit),
+        ["BOARD_STATUS"] => {
+            boards.publish("");
+            Ok(())
+        }
+        _ => Err("usage: BOARD_CONFIRM <device> | BOARD_STATUS".to_string()),
+    }
+}
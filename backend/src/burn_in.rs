@@ -0,0 +1,392 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, watch};
+
+use crate::audit::AuditLog;
+use crate::config::ConfigStore;
+use crate::devices::DeviceRegistry;
+use crate::heater::HeaterControl;
+use crate::{UnifiedSensorData, CHANNEL_NAMES};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct BurnInConfig {
+    /// Default duration when BURN_IN_START gives none
+    #[serde(default = "default_hours")]
+    pub hours: f32,
+    /// Sent at start to keep the heater on continuously
+    #[serde(default = "default_start_commands")]
+    pub start_commands: Vec<String>,
+    /// Sent when burn-in ends or is stopped, after the [heater] settings
+    /// are pushed again
+    #[serde(default)]
+    pub stop_commands: Vec<String>,
+    /// Baseline evolution is averaged and logged once per interval
+    #[serde(default = "default_log_every_secs")]
+    pub log_every_secs: u64,
+    /// Final baselines are the average over this last stretch of the run
+    #[serde(default = "default_final_window_secs")]
+    pub final_window_secs: u64,
+    #[serde(default = "default_log_path")]
+    pub log_path: String,
+    /// Write final baselines to the device's [calibration] profile
+    #[serde(default = "default_save_calibration")]
+    pub save_calibration: bool,
+}
+
+fn default_hours() -> f32 { 24.0 }
+fn default_start_commands() -> Vec<String> { vec!["HEATER_SET 300".to_string()] }
+fn default_log_every_secs() -> u64 { 60 }
+fn default_final_window_secs() -> u64 { 600 }
+fn default_log_path() -> String { "burn_in.jsonl".to_string() }
+fn default_save_calibration() -> bool { true }
+
+impl Default for BurnInConfig {
+    fn default() -> Self {
+        Self {
+            hours: default_hours(),
+            start_commands: default_start_commands(),
+            stop_commands: Vec::new(),
+            log_every_secs: default_log_every_secs(),
+            final_window_secs: default_final_window_secs(),
+            log_path: default_log_path(),
+            save_calibration: default_save_calibration(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    burn_in: BurnInConfig,
+}
+
+impl BurnInConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().burn_in
+    }
+}
+
+/// One logged interval of baseline evolution
+#[derive(Debug, Serialize, Clone)]
+struct BurnInSample {
+    timestamp: i64,
+    elapsed_secs: u64,
+    samples: usize,
+    heater_temp: Option<f32>,
+    values: [f32; 7],
+}
+
+/// Completion report, also appended to `log_path`
+#[derive(Debug, Serialize, Clone)]
+pub struct BurnInReport {
+    pub device: String,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub planned_secs: u64,
+    pub elapsed_secs: u64,
+    /// False if stopped early (baselines are then not saved)
+    pub completed: bool,
+    pub intervals: usize,
+    pub final_baselines: BTreeMap<&'static str, f32>,
+    /// Change over the final window in percent, per channel (lower = more stable)
+    pub final_drift_percent: BTreeMap<&'static str, f32>,
+    pub calibration_saved: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct Status {
+    device: String,
+    started_at: i64,
+    planned_secs: u64,
+}
+
+// ================= Burn-In =================
+/// Conditioning run for new sensors: heater on for hours, baseline evolution
+/// logged at a reduced rate, final baselines become the initial calibration
+#[derive(Clone)]
+pub struct BurnIn {
+    running: Arc<Mutex<Option<Status>>>,
+    stop: Arc<watch::Sender<bool>>,
+    cmd_tx: broadcast::Sender<String>,
+    data_tx: broadcast::Sender<String>,
+    frame_tx: broadcast::Sender<UnifiedSensorData>,
+    heater: HeaterControl,
+    config: ConfigStore,
+    audit: AuditLog,
+    devices: DeviceRegistry,
+}
+
+impl BurnIn {
+    pub fn new(
+        cmd_tx: broadcast::Sender<String>,
+        data_tx: broadcast::Sender<String>,
+        frame_tx: broadcast::Sender<UnifiedSensorData>,
+        heater: HeaterControl,
+        config: ConfigStore,
+        audit: AuditLog,
+        devices: DeviceRegistry,
+    ) -> Self {
+        let (stop, _) = watch::channel(false);
+        Self {
+            running: Arc::new(Mutex::new(None)),
+            stop: Arc::new(stop),
+            cmd_tx,
+            data_tx,
+            frame_tx,
+            heater,
+            config,
+            audit,
+            devices,
+        }
+    }
+
+    pub fn start(&self, hours: Option<f32>, device: Option<String>, actor: &str) -> Result<(), String> {
+        let config = BurnInConfig::load("config.toml");
+        let hours = hours.unwrap_or(config.hours);
+        if hours <= 0.0 {
+            return Err("burn-in duration must be positive".to_string());
+        }
+        let device = self.devices.resolve(device.as_deref())?;
+        let status = Status {
+            device,
+            started_at: chrono::Utc::now().timestamp_millis(),
+            planned_secs: (hours * 3600.0) as u64,
+        };
+        {
+            let mut running = self.running.lock().unwrap();
+            if running.is_some() {
+                return Err("burn-in already running".to_string());
+            }
+            *running = Some(status.clone());
+        }
+        self.stop.send_replace(false);
+        self.audit.record(actor, "burn_in_start", serde_json::json!({ "hours": hours, "device": status.device }));
+
+        let burn_in = self.clone();
+        tokio::spawn(async move {
+            burn_in.run(config, status).await;
+            *burn_in.running.lock().unwrap() = None;
+        });
+        Ok(())
+    }
+
+    pub fn stop(&self, actor: &str) -> Result<(), String> {
+        if self.running.lock().unwrap().is_none() {
+            return Err("no burn-in running".to_string());
+        }
+        self.audit.record(actor, "burn_in_stop", serde_json::json!({}));
+        self.stop.send_replace(true);
+        Ok(())
+    }
+
+    pub fn publish(&self) {
+        let status = self.running.lock().unwrap().clone();
+        let event = match status {
+            Some(status) => serde_json::json!({
+                "type": "burn_in",
+                "status": "running",
+                "device": status.device,
+                "started_at": status.started_at,
+                "planned_secs": status.planned_secs,
+                "elapsed_secs": (chrono::Utc::now().timestamp_millis() - status.started_at) / 1000,
+            }),
+            None => serde_json::json!({ "type": "burn_in", "status": "idle" }),
+        };
+        let _ = self.data_tx.send(event.to_string());
+    }
+
+    async fn run(&self, config: BurnInConfig, status: Status) {
+        println!("🔥 Burn-in started on {} for {:.1} h", status.device, status.planned_secs as f32 / 3600.0);
+        for cmd in &config.start_commands {
+            let _ = self.cmd_tx.send(format!("{}:{}", status.device, cmd));
+        }
+        self.publish();
+
+        let mut frame_rx = self.frame_tx.subscribe();
+        let mut stop_rx = self.stop.subscribe();
+        let start = tokio::time::Instant::now();
+        let deadline = start + Duration::from_secs(status.planned_secs);
+        let mut tick = tokio::time::interval_at(
+            start + Duration::from_secs(config.log_every_secs.max(1)),
+            Duration::from_secs(config.log_every_secs.max(1)),
+        );
+        let mut sums = [0.0f64; 7];
+        let mut temps = (0.0f64, 0usize);
+        let mut count = 0usize;
+        let mut intervals: Vec<BurnInSample> = Vec::new();
+
+        let completed = loop {
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => break true,
+                _ = stop_rx.wait_for(|stop| *stop) => break false,
+                frame = frame_rx.recv() => match frame {
+                    Ok(frame) => {
+                        if frame.device != status.device {
+                            continue;
+                        }
+                        for (sum, value) in sums.iter_mut().zip(frame.channels.values()) {
+                            *sum += value as f64;
+                        }
+                        if let Some(temp) = frame.heater_temp {
+                            temps = (temps.0 + temp as f64, temps.1 + 1);
+                        }
+                        count += 1;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break false,
+                },
+                _ = tick.tick() => {
+                    // Rata-rata per interval: log baseline dengan rate rendah
+                    if count == 0 {
+                        continue;
+                    }
+                    let sample = BurnInSample {
+                        timestamp: chrono::Utc::now().timestamp_millis(),
+                        elapsed_secs: start.elapsed().as_secs(),
+                        samples: count,
+                        heater_temp: (temps.1 > 0).then(|| (temps.0 / temps.1 as f64) as f32),
+                        values: sums.map(|s| (s / count as f64) as f32),
+                    };
+                    if let Err(e) = append_line(&config.log_path, "interval", &sample) {
+                        eprintln!("❌ Failed to write burn-in log: {}", e);
+                    }
+                    let mut event = serde_json::to_value(&sample).unwrap_or_default();
+                    event["type"] = "burn_in".into();
+                    event["status"] = "running".into();
+                    event["planned_secs"] = status.planned_secs.into();
+                    let _ = self.data_tx.send(event.to_string());
+                    intervals.push(sample);
+                    sums = [0.0; 7];
+                    temps = (0.0, 0);
+                    count = 0;
+                }
+            }
+        };
+
+        // Kembalikan heater ke setting normal
+        self.heater.push(&status.device);
+        for cmd in &config.stop_commands {
+            let _ = self.cmd_tx.send(format!("{}:{}", status.device, cmd));
+        }
+        let report = self.report(&config, &status, &intervals, completed, start.elapsed().as_secs());
+        if let Err(e) = append_line(&config.log_path, "report", &report) {
+            eprintln!("❌ Failed to write burn-in log: {}", e);
+        }
+        let mut event = serde_json::to_value(&report).unwrap_or_default();
+        event["type"] = "burn_in".into();
+        event["status"] = if completed { "completed" } else { "stopped" }.into();
+        let _ = self.data_tx.send(event.to_string());
+    }
+
+    fn report(
+        &self,
+        config: &BurnInConfig,
+        status: &Status,
+        intervals: &[BurnInSample],
+        completed: bool,
+        elapsed_secs: u64,
+    ) -> BurnInReport {
+        // Baseline akhir = rata-rata interval dalam final window
+        let window_start = elapsed_secs.saturating_sub(config.final_window_secs);
+        let tail: Vec<&BurnInSample> = intervals.iter().filter(|s| s.elapsed_secs >= window_start).collect();
+        let total: usize = tail.iter().map(|s| s.samples).sum();
+        let final_values = (total > 0).then(|| {
+            let mut values = [0.0f32; 7];
+            for (i, value) in values.iter_mut().enumerate() {
+                *value = tail.iter().map(|s| s.values[i] as f64 * s.samples as f64).sum::<f64>() as f32 / total as f32;
+            }
+            values
+        });
+        let final_drift_percent = match (tail.first(), tail.last()) {
+            (Some(first), Some(last)) if tail.len() > 1 => CHANNEL_NAMES
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| first.values[*i].abs() > f32::EPSILON)
+                .map(|(i, name)| (*name, (last.values[i] - first.values[i]) / first.values[i].abs() * 100.0))
+                .collect(),
+            _ => BTreeMap::new(),
+        };
+
+        let mut calibration_saved = false;
+        if let (true, Some(values)) = (completed, final_values) {
+            println!("🔥 Burn-in complete, final baselines:");
+            for (name, value) in CHANNEL_NAMES.iter().zip(values) {
+                println!("   {}: {:.3}", name, value);
+            }
+            if config.save_calibration {
+                calibration_saved = self.save_calibration(&status.device, &values);
+            }
+        } else if !completed {
+            println!("🔥 Burn-in stopped early, baselines not saved");
+        }
+
+        BurnInReport {
+            device: status.device.clone(),
+            started_at: status.started_at,
+            finished_at: chrono::Utc::now().timestamp_millis(),
+            planned_secs: status.planned_secs,
+            elapsed_secs,
+            completed,
+            intervals: intervals.len(),
+            final_baselines: final_values
+                .map(|values| CHANNEL_NAMES.iter().copied().zip(values).collect())
+                .unwrap_or_default(),
+            final_drift_percent,
+            calibration_saved,
+        }
+    }
+
+    /// Store baselines as `calibration.baseline` under the device profile
+    fn save_calibration(&self, device: &str, values: &[f32; 7]) -> bool {
+        let prefix = format!("devices.\"{}\".", device);
+        let literal = format!("[{}]", values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", "));
+        let version = format!("burn-in-{}", chrono::Local::now().format("%Y-%m-%d"));
+        let result = self
+            .config
+            .set(&format!("{}calibration.baseline", prefix), &literal, "burn_in")
+            .and_then(|_| self.config.set(&format!("{}calibration.version", prefix), &format!("\"{}\"", version), "burn_in"));
+        match result {
+            Ok(config_version) => {
+                println!("🔥 Initial calibration saved ({}, config v{})", version, config_version);
+                true
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to save burn-in calibration: {}", e);
+                false
+            }
+        }
+    }
+}
+
+fn append_line<T: Serialize>(path: &str, kind: &str, record: &T) -> std::io::Result<()> {
+    let mut line = serde_json::to_value(record)?;
+    line["kind"] = kind.into();
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+/// GUI commands (device defaults to the only connected one):
+///   BURN_IN_START [hours] [device]
+///   BURN_IN_STOP
+///   BURN_IN_STATUS
+pub fn handle_command(burn_in: &BurnIn, client: &str, args: &[&str]) -> Result<(), String> {
+    match args {
+        ["BURN_IN_START", rest @ ..] => {
+            let hours = rest
+                .first()
+                .map(|h| h.parse::<f32>().map_err(|_| format!("invalid number '{}'", h)))
+                .transpose()?;
+            burn_in.start(hours, rest.get(1).map(|d| d.to_string()), client)
+        }
+        ["BURN_IN_STOP"] => burn_in.stop(client),
+        ["BURN_IN_STATUS"] => {
+            burn_in.publish();
+            Ok(())
+        }
+        _ => Err("usage: BURN_IN_START [hours] [device] | BURN_IN_STOP | BURN_IN_STATUS".to_string()),
+    }
+}
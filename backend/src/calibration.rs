@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::analytics::{FrameHistory, Selection};
+use crate::channels::Channel;
+use crate::config::ConfigStore;
+use crate::UnifiedSensorData;
+
+/// Samples a preview runs over when the request gives no window
+pub const DEFAULT_PREVIEW_WINDOW: usize = 500;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CurveKind {
+    /// ppm = c0 + c1·x + c2·x² + ...
+    Polynomial,
+    /// ppm = a·x^b, coefficients [a, b] (typical MOS response)
+    Power,
+    /// Piecewise linear through `points` ([signal, ppm], rising signal), clamped at the ends
+    Table,
+}
+
+/// Signal → concentration for one channel, `[calibration.curves.<channel>]`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CalibrationCurve {
+    pub kind: CurveKind,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub coefficients: Vec<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub points: Vec<[f64; 2]>,
+}
+
+impl CurveKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Polynomial => "polynomial",
+            Self::Power => "power",
+            Self::Table => "table",
+        }
+    }
+}
+
+impl CalibrationCurve {
+    pub fn validate(&self, channel: &str) -> Result<(), String> {
+        let name = format!("calibration.curves.{}", channel);
+        if self.coefficients.iter().chain(self.points.iter().flatten()).any(|v| !v.is_finite()) {
+            return Err(format!("{}: values must be finite", name));
+        }
+        match self.kind {
+            CurveKind::Polynomial if self.coefficients.is_empty() => Err(format!("{}: polynomial needs coefficients", name)),
+            CurveKind::Power if self.coefficients.len() != 2 => Err(format!("{}: power needs coefficients [a, b]", name)),
+            CurveKind::Table if self.points.len() < 2 => Err(format!("{}: table needs at least 2 points", name)),
+            CurveKind::Table if self.points.windows(2).any(|w| w[1][0] <= w[0][0]) => {
+                Err(format!("{}: table signal values must rise", name))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Concentration for one signal value, None where the curve is undefined
+    pub fn evaluate(&self, x: f64) -> Option<f64> {
+        let y = match self.kind {
+            CurveKind::Polynomial => self.coefficients.iter().rev().fold(0.0, |acc, c| acc * x + c),
+            CurveKind::Power => self.coefficients[0] * x.powf(self.coefficients[1]),
+            CurveKind::Table => {
+                let (first, last) = (self.points.first()?, self.points.last()?);
+                if x <= first[0] {
+                    first[1]
+                } else {
+                    match self.points.windows(2).find(|w| x <= w[1][0]) {
+                        Some(w) => w[0][1] + (w[1][1] - w[0][1]) * (x - w[0][0]) / (w[1][0] - w[0][0]),
+                        None => last[1],
+                    }
+                }
+            }
+        };
+        y.is_finite().then_some(y)
+    }
+
+    /// Inline TOML table, for writing the curve into config.toml
+    fn literal(&self) -> String {
+        let list = |values: &[f64]| values.iter().map(|v| format!("{:?}", v)).collect::<Vec<_>>().join(", ");
+        let mut fields = vec![format!("kind = \"{}\"", self.kind.name())];
+        if !self.coefficients.is_empty() {
+            fields.push(format!("coefficients = [{}]", list(&self.coefficients)));
+        }
+        if !self.points.is_empty() {
+            let points: Vec<String> = self.points.iter().map(|p| format!("[{}]", list(p))).collect();
+            fields.push(format!("points = [{}]", points.join(", ")));
+        }
+        format!("{{ {} }}", fields.join(", "))
+    }
+}
+
+pub fn validate_curves(curves: &BTreeMap<String, CalibrationCurve>) -> Result<(), String> {
+    curves.iter().try_for_each(|(channel, curve)| curve.validate(channel))
+}
+
+/// `<channel>_ppm` for every curve whose channel (physical or virtual) the frame has
+pub fn concentrations(curves: &BTreeMap<String, CalibrationCurve>, frame: &UnifiedSensorData) -> Vec<(String, f64)> {
+    curves
+        .iter()
+        .filter_map(|(channel, curve)| {
+            let signal = match Channel::from_name(channel) {
+                Some(c) => frame.channels[c],
+                None => *frame.virtual_channels.get(channel)?,
+            };
+            Some((format!("{}_ppm", channel), curve.evaluate(signal.is_finite().then_some(signal as f64)?)?))
+        })
+        .collect()
+}
+
+// ================= Curve Editor =================
+/// Dotted config key of a channel's curve, under the device profile if any
+fn curve_key(device: Option<&str>, channel: &str) -> String {
+    let prefix = device.map(|d| format!("devices.\"{}\".", d)).unwrap_or_default();
+    format!("{}calibration.curves.{}", prefix, channel)
+}
+
+/// Write a curve into config.toml as a new config version
+pub fn save_curve(store: &ConfigStore, device: Option<&str>, channel: &str, curve: &CalibrationCurve, actor: &str) -> Result<u64, String> {
+    curve.validate(channel)?;
+    let version = store.set(&curve_key(device, channel), &curve.literal(), actor)?;
+    println!("📈 Calibration curve for {} {} saved by {} (config version {})", device.unwrap_or("all devices"), channel, actor, version);
+    Ok(version)
+}
+
+/// Remove a curve from config.toml as a new config version
+pub fn delete_curve(store: &ConfigStore, device: Option<&str>, channel: &str, actor: &str) -> Result<u64, String> {
+    let key = curve_key(device, channel);
+    store.edit(actor, "delete", Some(key.clone()), |doc| {
+        let mut segments: Vec<&str> = device.map(|d| vec!["devices", d]).unwrap_or_default();
+        segments.extend(["calibration", "curves"]);
+        let mut table: &mut dyn toml_edit::TableLike = doc.as_table_mut();
+        for segment in segments {
+            table = table.get_mut(segment).and_then(|item| item.as_table_like_mut()).ok_or_else(|| format!("no curve at {}", key))?;
+        }
+        table.remove(channel).map(|_| ()).ok_or_else(|| format!("no curve at {}", key))
+    })
+}
+
+/// Candidate vs active curve over recent history
+#[derive(Debug, Serialize)]
+pub struct CurvePreview {
+    pub channel: String,
+    pub device: Option<String>,
+    pub samples: usize,
+    pub timestamps: Vec<i64>,
+    pub signal: Vec<f64>,
+    /// Candidate curve, null where undefined
+    pub ppm: Vec<Option<f64>>,
+    /// Active curve, if the channel has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_ppm: Option<Vec<Option<f64>>>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+}
+
+/// Evaluate `curve` on the channel's recent values without saving it
+pub fn preview(
+    history: &FrameHistory,
+    current: Option<&CalibrationCurve>,
+    channel: &str,
+    curve: &CalibrationCurve,
+    selection: &Selection,
+) -> Result<CurvePreview, String> {
+    curve.validate(channel)?;
+    let (timestamps, signal) =
+        history.channel_series(selection, channel).ok_or_else(|| format!("no recent data for channel '{}'", channel))?;
+    let ppm: Vec<Option<f64>> = signal.iter().map(|x| curve.evaluate(*x)).collect();
+    let defined: Vec<f64> = ppm.iter().flatten().copied().collect();
+    Ok(CurvePreview {
+        channel: channel.to_string(),
+        device: selection.device.clone(),
+        samples: signal.len(),
+        current_ppm: current.map(|c| signal.iter().map(|x| c.evaluate(*x)).collect()),
+        min: defined.iter().copied().reduce(f64::min),
+        max: defined.iter().copied().reduce(f64::max),
+        mean: (!defined.is_empty()).then(|| defined.iter().sum::<f64>() / defined.len() as f64),
+        timestamps,
+        signal,
+        ppm,
+    })
+}
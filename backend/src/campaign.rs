@@ -0,0 +1,449 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::audit::AuditLog;
+use crate::recording::file_safe;
+use crate::UnifiedSensorData;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CampaignConfig {
+    /// One sub-directory per campaign
+    #[serde(default = "default_dir")]
+    pub dir: String,
+    /// At most one track point per device per this interval, ms; 0 = every frame
+    #[serde(default = "default_min_interval_ms")]
+    pub min_interval_ms: i64,
+    /// Gas values kept per point, physical or virtual; empty = all channels
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+fn default_dir() -> String { "campaigns".to_string() }
+fn default_min_interval_ms() -> i64 { 1000 }
+
+impl Default for CampaignConfig {
+    fn default() -> Self {
+        Self { dir: default_dir(), min_interval_ms: default_min_interval_ms(), channels: Vec::new() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    campaign: CampaignConfig,
+}
+
+impl CampaignConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().campaign
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_interval_ms < 0 {
+            return Err("campaign.min_interval_ms must not be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// One position + concentration sample, a line of track.jsonl
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct TrackPoint {
+    timestamp: i64,
+    device: String,
+    latitude: f64,
+    longitude: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    altitude: Option<f64>,
+    values: BTreeMap<String, f32>,
+}
+
+/// metadata.json of a campaign directory
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CampaignInfo {
+    pub id: String,
+    pub name: String,
+    pub started_by: String,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub points: usize,
+    pub devices: Vec<String>,
+}
+
+struct Active {
+    info: CampaignInfo,
+    dir: PathBuf,
+    track: File,
+    /// Last recorded point per device
+    last: HashMap<String, i64>,
+}
+
+/// Track export formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackFormat {
+    GeoJson,
+    Kml,
+    Gpx,
+}
+
+impl TrackFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "geojson" | "json" => Ok(Self::GeoJson),
+            "kml" => Ok(Self::Kml),
+            "gpx" => Ok(Self::Gpx),
+            _ => Err(format!("unknown track format '{}' (geojson, kml, gpx)", name)),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::GeoJson => "geojson",
+            Self::Kml => "kml",
+            Self::Gpx => "gpx",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::GeoJson => "application/geo+json",
+            Self::Kml => "application/vnd.google-earth.kml+xml",
+            Self::Gpx => "application/gpx+xml",
+        }
+    }
+}
+
+// ================= Mobile Campaigns =================
+/// CAMPAIGN_START/CAMPAIGN_STOP record a track of GPS-located frames
+/// (position + gas values per point) into a campaign directory, exported
+/// as GeoJSON, KML or GPX for mapping odor plumes
+#[derive(Clone)]
+pub struct Campaigns {
+    config: Arc<CampaignConfig>,
+    active: Arc<Mutex<Option<Active>>>,
+    audit: AuditLog,
+    data_tx: broadcast::Sender<String>,
+}
+
+impl Campaigns {
+    pub fn new(config_path: &str, audit: AuditLog, data_tx: broadcast::Sender<String>) -> Self {
+        Self {
+            config: Arc::new(CampaignConfig::load(config_path)),
+            active: Arc::new(Mutex::new(None)),
+            audit,
+            data_tx,
+        }
+    }
+
+    pub fn start(&self, name: &str, actor: &str) -> Result<(), String> {
+        let mut active = self.active.lock().unwrap();
+        if let Some(campaign) = active.as_ref() {
+            return Err(format!("campaign '{}' is running", campaign.info.name));
+        }
+        let now = chrono::Local::now();
+        let id = now.format("%Y%m%d-%H%M%S").to_string();
+        let dir = PathBuf::from(&self.config.dir).join(format!("{}_{}", id, file_safe(name)));
+        std::fs::create_dir_all(&dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+        let track = File::create(dir.join("track.jsonl")).map_err(|e| format!("cannot create track: {}", e))?;
+        let campaign = Active {
+            info: CampaignInfo {
+                id,
+                name: name.to_string(),
+                started_by: actor.to_string(),
+                started_at: now.timestamp_millis(),
+                ended_at: None,
+                points: 0,
+                devices: Vec::new(),
+            },
+            dir: dir.clone(),
+            track,
+            last: HashMap::new(),
+        };
+        write_metadata(&campaign);
+        *active = Some(campaign);
+        drop(active);
+        println!("🗺️ Campaign '{}' recording into {}", name, dir.display());
+        self.audit.record(actor, "campaign_start", serde_json::json!({ "name": name, "dir": dir }));
+        self.publish();
+        Ok(())
+    }
+
+    pub fn stop(&self, actor: &str) -> Result<(), String> {
+        let mut campaign = self.active.lock().unwrap().take().ok_or("no campaign running")?;
+        campaign.info.ended_at = Some(chrono::Utc::now().timestamp_millis());
+        if let Err(e) = campaign.track.flush() {
+            eprintln!("❌ Failed to write campaign track: {}", e);
+        }
+        write_metadata(&campaign);
+        println!("🗺️ Campaign '{}' stopped: {} point(s)", campaign.info.name, campaign.info.points);
+        self.audit.record(
+            actor,
+            "campaign_stop",
+            serde_json::json!({ "name": campaign.info.name, "id": campaign.info.id, "points": campaign.info.points }),
+        );
+        self.publish();
+        Ok(())
+    }
+
+    /// Append a GPS-located frame, at most one per device per min_interval_ms
+    fn observe(&self, frame: &UnifiedSensorData) {
+        let Some(location) = frame.location.as_ref().filter(|l| l.source == "gps") else { return };
+        let mut active = self.active.lock().unwrap();
+        let Some(campaign) = active.as_mut() else { return };
+        if campaign.last.get(&frame.device).is_some_and(|at| frame.timestamp - at < self.config.min_interval_ms) {
+            return;
+        }
+        let wanted = |name: &str| self.config.channels.is_empty() || self.config.channels.iter().any(|c| c == name);
+        let values = frame
+            .channels
+            .iter()
+            .map(|(channel, value)| (channel.name().to_string(), value))
+            .chain(frame.virtual_channels.iter().map(|(name, value)| (name.clone(), *value)))
+            .filter(|(name, value)| value.is_finite() && wanted(name))
+            .collect();
+        let point = TrackPoint {
+            timestamp: frame.timestamp,
+            device: frame.device.clone(),
+            latitude: location.latitude,
+            longitude: location.longitude,
+            altitude: location.altitude,
+            values,
+        };
+        let line = serde_json::to_string(&point).unwrap_or_default();
+        if let Err(e) = writeln!(campaign.track, "{}", line) {
+            eprintln!("❌ Failed to write campaign track: {}", e);
+            return;
+        }
+        campaign.last.insert(frame.device.clone(), frame.timestamp);
+        campaign.info.points += 1;
+        if !campaign.info.devices.contains(&frame.device) {
+            campaign.info.devices.push(frame.device.clone());
+            write_metadata(campaign);
+        }
+    }
+
+    /// Every campaign on disk, newest first
+    pub fn list(&self) -> Vec<CampaignInfo> {
+        if let Some(campaign) = self.active.lock().unwrap().as_ref() {
+            write_metadata(campaign);
+        }
+        let Ok(entries) = std::fs::read_dir(&self.config.dir) else { return Vec::new() };
+        let mut campaigns: Vec<CampaignInfo> = entries
+            .flatten()
+            .filter_map(|entry| std::fs::read_to_string(entry.path().join("metadata.json")).ok())
+            .filter_map(|json| serde_json::from_str(&json).ok())
+            .collect();
+        campaigns.sort_by_key(|c| std::cmp::Reverse(c.started_at));
+        campaigns
+    }
+
+    fn dir(&self, id: &str) -> Result<PathBuf, String> {
+        let entries = std::fs::read_dir(&self.config.dir).map_err(|_| format!("unknown campaign {}", id))?;
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .find(|path| path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(&format!("{}_", id))))
+            .ok_or_else(|| format!("unknown campaign {}", id))
+    }
+
+    /// The campaign's track in `format`, also while it is still running
+    pub fn export(&self, id: &str, format: TrackFormat) -> Result<String, String> {
+        if let Some(campaign) = self.active.lock().unwrap().as_mut() {
+            let _ = campaign.track.flush();
+        }
+        let dir = self.dir(id)?;
+        let info: CampaignInfo = std::fs::read_to_string(dir.join("metadata.json"))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .ok_or_else(|| format!("campaign {} has no metadata", id))?;
+        let file = File::open(dir.join("track.jsonl")).map_err(|e| format!("cannot read track: {}", e))?;
+        let points: Vec<TrackPoint> = BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect();
+        Ok(match format {
+            TrackFormat::GeoJson => geojson(&info, &points),
+            TrackFormat::Kml => kml(&info, &points),
+            TrackFormat::Gpx => gpx(&info, &points),
+        })
+    }
+
+    /// "campaign" GUI event
+    pub fn publish(&self) {
+        let active = self.active.lock().unwrap();
+        let event = match active.as_ref() {
+            Some(campaign) => serde_json::json!({
+                "type": "campaign",
+                "status": "recording",
+                "id": campaign.info.id,
+                "name": campaign.info.name,
+                "started_at": campaign.info.started_at,
+                "points": campaign.info.points,
+                "devices": campaign.info.devices,
+            }),
+            None => serde_json::json!({ "type": "campaign", "status": "idle" }),
+        };
+        let _ = self.data_tx.send(event.to_string());
+    }
+}
+
+fn write_metadata(campaign: &Active) {
+    let path = campaign.dir.join("metadata.json");
+    if let Err(e) = std::fs::write(&path, serde_json::to_string_pretty(&campaign.info).unwrap_or_default()) {
+        eprintln!("❌ Failed to write {}: {}", path.display(), e);
+    }
+}
+
+/// Points in time order per device
+fn by_device(points: &[TrackPoint]) -> BTreeMap<&str, Vec<&TrackPoint>> {
+    let mut devices: BTreeMap<&str, Vec<&TrackPoint>> = BTreeMap::new();
+    for point in points {
+        devices.entry(&point.device).or_default().push(point);
+    }
+    devices
+}
+
+fn time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(timestamp)
+        .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .unwrap_or_default()
+}
+
+fn coordinates(point: &TrackPoint) -> Vec<f64> {
+    let mut coordinates = vec![point.longitude, point.latitude];
+    coordinates.extend(point.altitude);
+    coordinates
+}
+
+/// One LineString per device, then a Point per sample with its gas values
+fn geojson(info: &CampaignInfo, points: &[TrackPoint]) -> String {
+    let lines = by_device(points).into_iter().map(|(device, track)| {
+        serde_json::json!({
+            "type": "Feature",
+            "geometry": { "type": "LineString", "coordinates": track.iter().map(|p| coordinates(p)).collect::<Vec<_>>() },
+            "properties": { "device": device, "campaign": info.name },
+        })
+    });
+    let samples = points.iter().map(|p| {
+        let mut properties = serde_json::json!({ "device": p.device, "timestamp": p.timestamp, "time": time(p.timestamp) });
+        for (name, value) in &p.values {
+            properties[name] = (*value as f64).into();
+        }
+        serde_json::json!({
+            "type": "Feature",
+            "geometry": { "type": "Point", "coordinates": coordinates(p) },
+            "properties": properties,
+        })
+    });
+    let features: Vec<serde_json::Value> = lines.chain(samples).collect();
+    serde_json::json!({ "type": "FeatureCollection", "name": info.name, "features": features }).to_string()
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Placemark per sample (gas values as ExtendedData) plus a path per device
+fn kml(info: &CampaignInfo, points: &[TrackPoint]) -> String {
+    let mut kml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n");
+    kml += &format!("<name>{}</name>\n", xml_escape(&info.name));
+    for (device, track) in by_device(points) {
+        let path: Vec<String> = track.iter().map(|p| coordinates(p).iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",")).collect();
+        kml += &format!(
+            "<Placemark><name>{}</name><LineString><coordinates>{}</coordinates></LineString></Placemark>\n",
+            xml_escape(device),
+            path.join(" ")
+        );
+    }
+    for p in points {
+        let data: String = p
+            .values
+            .iter()
+            .map(|(name, value)| format!("<Data name=\"{}\"><value>{}</value></Data>", xml_escape(name), value))
+            .collect();
+        kml += &format!(
+            "<Placemark><name>{}</name><TimeStamp><when>{}</when></TimeStamp><ExtendedData>{}</ExtendedData><Point><coordinates>{}</coordinates></Point></Placemark>\n",
+            xml_escape(&p.device),
+            time(p.timestamp),
+            data,
+            coordinates(p).iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",")
+        );
+    }
+    kml += "</Document>\n</kml>\n";
+    kml
+}
+
+/// One track per device; gas values go in each trkpt's <extensions>
+fn gpx(info: &CampaignInfo, points: &[TrackPoint]) -> String {
+    let mut gpx = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gpx version=\"1.1\" creator=\"enose\" xmlns=\"http://www.topografix.com/GPX/1/1\" xmlns:enose=\"urn:enose:gas\">\n",
+    );
+    gpx += &format!("<metadata><name>{}</name><time>{}</time></metadata>\n", xml_escape(&info.name), time(info.started_at));
+    for (device, track) in by_device(points) {
+        gpx += &format!("<trk><name>{}</name><trkseg>\n", xml_escape(device));
+        for p in track {
+            let elevation = p.altitude.map(|a| format!("<ele>{}</ele>", a)).unwrap_or_default();
+            let values: String =
+                p.values.iter().map(|(name, value)| format!("<enose:{0}>{1}</enose:{0}>", xml_escape(name), value)).collect();
+            gpx += &format!(
+                "<trkpt lat=\"{}\" lon=\"{}\">{}<time>{}</time><extensions>{}</extensions></trkpt>\n",
+                p.latitude,
+                p.longitude,
+                elevation,
+                time(p.timestamp),
+                values
+            );
+        }
+        gpx += "</trkseg></trk>\n";
+    }
+    gpx += "</gpx>\n";
+    gpx
+}
+
+/// GUI commands:
+///   CAMPAIGN_START <name>   name may contain spaces
+///   CAMPAIGN_STOP
+///   CAMPAIGN_STATUS
+///   CAMPAIGNS                              -> reply: campaigns on disk
+///   CAMPAIGN_EXPORT <id> <geojson|kml|gpx> -> writes track.<ext> into the campaign directory
+pub fn handle_command(campaigns: &Campaigns, client: &str, args: &[&str]) -> Result<Option<String>, String> {
+    match args {
+        ["CAMPAIGN_START", name @ ..] if !name.is_empty() => campaigns.start(&name.join(" "), client).map(|_| None),
+        ["CAMPAIGN_STOP"] => campaigns.stop(client).map(|_| None),
+        ["CAMPAIGN_STATUS"] => {
+            campaigns.publish();
+            Ok(None)
+        }
+        ["CAMPAIGNS"] => Ok(Some(serde_json::json!({ "type": "campaigns", "campaigns": campaigns.list() }).to_string())),
+        ["CAMPAIGN_EXPORT", id, format] => {
+            let format = TrackFormat::parse(format)?;
+            let track = campaigns.export(id, format)?;
+            let path = campaigns.dir(id)?.join(format!("track.{}", format.extension()));
+            std::fs::write(&path, track).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+            println!("🗺️ Campaign {} exported to {}", id, path.display());
+            Ok(Some(serde_json::json!({ "type": "campaign_export", "id": id, "path": path }).to_string()))
+        }
+        _ => Err(
+            "usage: CAMPAIGN_START <name> | CAMPAIGN_STOP | CAMPAIGN_STATUS | CAMPAIGNS | CAMPAIGN_EXPORT <id> <geojson|kml|gpx>"
+                .to_string(),
+        ),
+    }
+}
+
+pub async fn run(campaigns: Campaigns, frame_tx: broadcast::Sender<UnifiedSensorData>) {
+    let mut frame_rx = frame_tx.subscribe();
+    loop {
+        match frame_rx.recv().await {
+            Ok(frame) => campaigns.observe(&frame),
+            Err(broadcast::error::RecvError::Lagged(n)) => eprintln!("⚠️ Campaign recorder lagged, {} frame(s) not recorded", n),
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
@@ -0,0 +1,525 @@
+use serde::Deserialize;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+
+use crate::recording::file_safe;
+use crate::CHANNEL_NAMES;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CaptureConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// One file per device per UTC day: <dir>/<device>_<YYYYMMDD>.ecap
+    #[serde(default = "default_dir")]
+    pub dir: String,
+    /// Frames per compressed block
+    #[serde(default = "default_block_frames")]
+    pub block_frames: usize,
+    /// A partial block is written after this long, s (the most a crash loses)
+    #[serde(default = "default_flush_secs")]
+    pub flush_secs: u64,
+    /// Capture files older than this are deleted, days; 0 = keep all
+    #[serde(default)]
+    pub retention_days: u64,
+}
+
+fn default_dir() -> String { "capture".to_string() }
+fn default_block_frames() -> usize { 1000 }
+fn default_flush_secs() -> u64 { 10 }
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: default_dir(),
+            block_frames: default_block_frames(),
+            flush_secs: default_flush_secs(),
+            retention_days: 0,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    capture: CaptureConfig,
+}
+
+impl CaptureConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().capture
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.block_frames == 0 {
+            return Err("capture.block_frames must be at least 1".to_string());
+        }
+        if self.flush_secs == 0 {
+            return Err("capture.flush_secs must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+// File layout (all integers LEB128 varints unless noted):
+//   "ECAP" 0x01, device name length, device name (UTF-8)
+//   then blocks, each: payload length, payload
+// Block payload: frame count, zigzag first timestamp (epoch ms), then a
+// bit stream (MSB first) with per frame:
+//   value count   first frame: 5 bits; later: '0' same, '1' + 5 bits
+//   timestamp     (not for the first frame) delta-of-delta, zigzag:
+//                 '0' = 0, '10' + 7 bits, '110' + 9, '1110' + 12, '1111' + 64
+//   each value    f32 bits XOR the same slot in the previous frame (Gorilla):
+//                 '0' equal, '10' + bits inside the previous leading/trailing
+//                 zero window, '11' + 5 bits leading zeros + 5 bits (length - 1)
+//                 + the meaningful bits
+// Every block starts from zero state, so a torn last block after a crash
+// costs only that block.
+const MAGIC: &[u8] = b"ECAP\x01";
+pub const EXTENSION: &str = "ecap";
+/// Values per frame the format can hold (5-bit count)
+const MAX_VALUES: usize = 31;
+
+/// Column names of the SENSOR values, in line order
+pub fn value_name(index: usize) -> String {
+    const EXTRA: [&str; 6] = ["state", "level", "flow", "pressure", "humidity", "heater_temp"];
+    match index.checked_sub(CHANNEL_NAMES.len()) {
+        None => CHANNEL_NAMES[index].to_string(),
+        Some(extra) => EXTRA.get(extra).map_or_else(|| format!("v{}", index), |name| name.to_string()),
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn get_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    /// Bits used in the last byte, 0 = full
+    used: u32,
+}
+
+impl BitWriter {
+    fn bit(&mut self, bit: bool) {
+        if self.used == 0 {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 0x80 >> self.used;
+        }
+        self.used = (self.used + 1) % 8;
+    }
+
+    fn bits(&mut self, value: u64, count: u32) {
+        for i in (0..count).rev() {
+            self.bit((value >> i) & 1 == 1);
+        }
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> Option<bool> {
+        let byte = self.bytes.get(self.pos / 8)?;
+        let bit = byte & (0x80 >> (self.pos % 8)) != 0;
+        self.pos += 1;
+        Some(bit)
+    }
+
+    fn bits(&mut self, count: u32) -> Option<u64> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.bit()? as u64;
+        }
+        Some(value)
+    }
+}
+
+/// Previous value and XOR window of one value position
+#[derive(Default, Clone)]
+struct Slot {
+    prev: u32,
+    leading: u32,
+    trailing: u32,
+    window: bool,
+}
+
+/// (value bits, prefix) of the delta-of-delta sizes below 64 bits
+const TIMESTAMP_BUCKETS: [(u32, u64); 3] = [(7, 0b10), (9, 0b110), (12, 0b1110)];
+
+/// One compressed block being filled
+#[derive(Default)]
+struct BlockEncoder {
+    stream: BitWriter,
+    frames: usize,
+    first: i64,
+    last: i64,
+    delta: i64,
+    width: usize,
+    slots: Vec<Slot>,
+}
+
+impl BlockEncoder {
+    fn push(&mut self, timestamp: i64, values: &[f32]) {
+        let values = &values[..values.len().min(MAX_VALUES)];
+        if self.frames == 0 {
+            self.first = timestamp;
+            self.stream.bits(values.len() as u64, 5);
+        } else {
+            if values.len() == self.width {
+                self.stream.bit(false);
+            } else {
+                self.stream.bit(true);
+                self.stream.bits(values.len() as u64, 5);
+            }
+            let delta = timestamp - self.last;
+            let encoded = zigzag(delta - self.delta);
+            if encoded == 0 {
+                self.stream.bit(false);
+            } else if let Some((bits, prefix)) = TIMESTAMP_BUCKETS.iter().find(|(bits, _)| encoded < 1 << bits) {
+                self.stream.bits(*prefix, prefix.ilog2() + 1);
+                self.stream.bits(encoded, *bits);
+            } else {
+                self.stream.bits(0b1111, 4);
+                self.stream.bits(encoded, 64);
+            }
+            self.delta = delta;
+        }
+        self.last = timestamp;
+        self.width = values.len();
+        if self.slots.len() < values.len() {
+            self.slots.resize(values.len(), Slot::default());
+        }
+        for (slot, value) in self.slots.iter_mut().zip(values) {
+            let bits = value.to_bits();
+            let xor = bits ^ slot.prev;
+            slot.prev = bits;
+            if xor == 0 {
+                self.stream.bit(false);
+                continue;
+            }
+            let (leading, trailing) = (xor.leading_zeros(), xor.trailing_zeros());
+            if slot.window && leading >= slot.leading && trailing >= slot.trailing {
+                self.stream.bits(0b10, 2);
+                self.stream.bits((xor >> slot.trailing) as u64, 32 - slot.leading - slot.trailing);
+            } else {
+                let length = 32 - leading - trailing;
+                self.stream.bits(0b11, 2);
+                self.stream.bits(leading as u64, 5);
+                self.stream.bits(length as u64 - 1, 5);
+                self.stream.bits((xor >> trailing) as u64, length);
+                *slot = Slot { prev: bits, leading, trailing, window: true };
+            }
+        }
+        self.frames += 1;
+    }
+
+    /// Length-prefixed block, resetting the encoder
+    fn finish(&mut self) -> Vec<u8> {
+        let block = std::mem::take(self);
+        let mut payload = Vec::new();
+        put_varint(&mut payload, block.frames as u64);
+        put_varint(&mut payload, zigzag(block.first));
+        payload.extend(block.stream.bytes);
+        let mut out = Vec::with_capacity(payload.len() + 4);
+        put_varint(&mut out, payload.len() as u64);
+        out.extend(payload);
+        out
+    }
+}
+
+/// One captured SENSOR frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureFrame {
+    /// Backend receive time, epoch ms
+    pub timestamp: i64,
+    pub values: Vec<f32>,
+}
+
+fn decode_block(payload: &[u8]) -> Option<Vec<CaptureFrame>> {
+    let mut pos = 0;
+    let count = get_varint(payload, &mut pos)? as usize;
+    let mut timestamp = unzigzag(get_varint(payload, &mut pos)?);
+    let mut stream = BitReader { bytes: &payload[pos..], pos: 0 };
+    let mut frames = Vec::with_capacity(count);
+    let mut slots: Vec<Slot> = Vec::new();
+    let mut width = 0;
+    let mut delta = 0;
+    for i in 0..count {
+        if i == 0 || stream.bit()? {
+            width = stream.bits(5)? as usize;
+        }
+        if i > 0 {
+            let mut prefix = 0;
+            while prefix < 4 && stream.bit()? {
+                prefix += 1;
+            }
+            let encoded = match prefix {
+                0 => 0,
+                1..=3 => stream.bits(TIMESTAMP_BUCKETS[prefix - 1].0)?,
+                _ => stream.bits(64)?,
+            };
+            delta += unzigzag(encoded);
+            timestamp += delta;
+        }
+        if slots.len() < width {
+            slots.resize(width, Slot::default());
+        }
+        let mut values = Vec::with_capacity(width);
+        for slot in slots.iter_mut().take(width) {
+            if stream.bit()? {
+                if stream.bit()? {
+                    let leading = stream.bits(5)? as u32;
+                    let length = stream.bits(5)? as u32 + 1;
+                    let trailing = 32u32.checked_sub(leading + length)?;
+                    slot.prev ^= (stream.bits(length)? as u32) << trailing;
+                    slot.leading = leading;
+                    slot.trailing = trailing;
+                    slot.window = true;
+                } else {
+                    let length = 32 - slot.leading - slot.trailing;
+                    slot.prev ^= (stream.bits(length)? as u32) << slot.trailing;
+                }
+            }
+            values.push(f32::from_bits(slot.prev));
+        }
+        frames.push(CaptureFrame { timestamp, values });
+    }
+    Some(frames)
+}
+
+/// Contents of one capture file
+pub struct CaptureFile {
+    pub device: String,
+    pub frames: Vec<CaptureFrame>,
+    /// Bytes at the end that did not form a whole block (crash mid-write)
+    pub torn_bytes: usize,
+}
+
+// ================= Capture Reader =================
+pub fn read(path: &Path) -> Result<CaptureFile, String> {
+    let data = std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    if !data.starts_with(MAGIC) {
+        return Err(format!("{} is not a capture file", path.display()));
+    }
+    let mut pos = MAGIC.len();
+    let name_len = get_varint(&data, &mut pos).ok_or("truncated header")? as usize;
+    let device = data
+        .get(pos..pos + name_len)
+        .map(|name| String::from_utf8_lossy(name).to_string())
+        .ok_or("truncated header")?;
+    pos += name_len;
+
+    let mut frames = Vec::new();
+    while pos < data.len() {
+        let start = pos;
+        let Some(payload) = get_varint(&data, &mut pos).and_then(|len| data.get(pos..pos + len as usize)) else {
+            return Ok(CaptureFile { device, frames, torn_bytes: data.len() - start });
+        };
+        pos += payload.len();
+        frames.extend(decode_block(payload).ok_or_else(|| format!("corrupt block at byte {}", start))?);
+    }
+    Ok(CaptureFile { device, frames, torn_bytes: 0 })
+}
+
+/// Export formats of `enose capture-export`
+pub fn export(capture: &CaptureFile, format: &str) -> Result<String, String> {
+    let value = |v: &f32| if v.is_finite() { v.to_string() } else { String::new() };
+    match format {
+        "csv" => {
+            let width = capture.frames.iter().map(|f| f.values.len()).max().unwrap_or(0);
+            let mut columns = vec!["timestamp".to_string(), "epoch_ms".to_string(), "device".to_string()];
+            columns.extend((0..width).map(value_name));
+            let mut out = columns.join(",") + "\n";
+            for frame in &capture.frames {
+                let timestamp =
+                    chrono::DateTime::from_timestamp_millis(frame.timestamp).map(|t| t.to_rfc3339()).unwrap_or_default();
+                let mut cells = vec![timestamp, frame.timestamp.to_string(), capture.device.clone()];
+                cells.extend(frame.values.iter().map(value));
+                out += &(cells.join(",") + "\n");
+            }
+            Ok(out)
+        }
+        "ndjson" => Ok(capture
+            .frames
+            .iter()
+            .map(|frame| {
+                let mut line = serde_json::json!({ "timestamp": frame.timestamp, "device": capture.device });
+                for (i, v) in frame.values.iter().enumerate() {
+                    line[value_name(i)] = if v.is_finite() { (*v as f64).into() } else { serde_json::Value::Null };
+                }
+                line.to_string() + "\n"
+            })
+            .collect()),
+        // Baris mentah lagi, untuk diputar ulang lewat simulator Arduino
+        "sensor" => Ok(capture
+            .frames
+            .iter()
+            .map(|frame| format!("SENSOR:{}\n", frame.values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")))
+            .collect()),
+        _ => Err(format!("unknown export format '{}' (csv, ndjson, sensor)", format)),
+    }
+}
+
+// ================= Raw Capture =================
+/// Continuous capture of every parsed SENSOR line, before plugins and
+/// filtering, into compact per-device files; a no-op when disabled
+#[derive(Clone, Default)]
+pub struct RawCapture {
+    tx: Option<mpsc::Sender<(String, i64, Vec<f32>)>>,
+}
+
+impl RawCapture {
+    pub fn start(config: CaptureConfig) -> Self {
+        let (tx, rx) = mpsc::channel(4096);
+        tokio::spawn(run(config, rx));
+        Self { tx: Some(tx) }
+    }
+
+    pub fn record(&self, device: &str, timestamp: i64, values: &[f32]) {
+        if let Some(tx) = &self.tx {
+            if tx.try_send((device.to_string(), timestamp, values.to_vec())).is_err() {
+                eprintln!("⚠️ Capture queue full, frame not captured");
+            }
+        }
+    }
+}
+
+struct DeviceFile {
+    day: String,
+    path: PathBuf,
+    file: File,
+    block: BlockEncoder,
+}
+
+impl DeviceFile {
+    fn open(dir: &Path, device: &str, day: &str) -> std::io::Result<Self> {
+        let path = dir.join(format!("{}_{}.{}", file_safe(device), day, EXTENSION));
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        // Restart di hari yang sama: lanjut append ke file yang ada
+        if file.metadata()?.len() == 0 {
+            let mut header = MAGIC.to_vec();
+            put_varint(&mut header, device.len() as u64);
+            header.extend(device.as_bytes());
+            file.write_all(&header)?;
+        }
+        println!("📼 Raw capture → {}", path.display());
+        Ok(Self { day: day.to_string(), path, file, block: BlockEncoder::default() })
+    }
+
+    fn flush(&mut self) {
+        if self.block.frames == 0 {
+            return;
+        }
+        let block = self.block.finish();
+        if let Err(e) = self.file.write_all(&block).and_then(|_| self.file.flush()) {
+            eprintln!("❌ Failed to write {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+fn utc_day(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(timestamp).unwrap_or_default().format("%Y%m%d").to_string()
+}
+
+/// Delete capture files last written more than retention_days ago
+fn prune(config: &CaptureConfig) {
+    if config.retention_days == 0 {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(&config.dir) else { return };
+    let Some(cutoff) = SystemTime::now().checked_sub(Duration::from_secs(config.retention_days * 86_400)) else { return };
+    for path in entries.flatten().map(|e| e.path()) {
+        let old = path.extension().is_some_and(|e| e == EXTENSION)
+            && std::fs::metadata(&path).and_then(|m| m.modified()).is_ok_and(|modified| modified < cutoff);
+        if old {
+            match std::fs::remove_file(&path) {
+                Ok(()) => println!("🗑️ Capture {} past retention, deleted", path.display()),
+                Err(e) => eprintln!("❌ Failed to delete {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+async fn run(config: CaptureConfig, mut rx: mpsc::Receiver<(String, i64, Vec<f32>)>) {
+    let dir = PathBuf::from(&config.dir);
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        eprintln!("❌ Raw capture disabled, cannot create {}: {}", dir.display(), e);
+        return;
+    }
+    prune(&config);
+    let mut files: HashMap<String, DeviceFile> = HashMap::new();
+    let mut flush = tokio::time::interval(Duration::from_secs(config.flush_secs));
+
+    loop {
+        tokio::select! {
+            sample = rx.recv() => {
+                let Some((device, timestamp, values)) = sample else { break };
+                let day = utc_day(timestamp);
+                if let Some(file) = files.get_mut(&device).filter(|f| f.day != day) {
+                    file.flush();
+                    files.remove(&device);
+                    prune(&config);
+                }
+                let file = match files.entry(device.clone()) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => match DeviceFile::open(&dir, &device, &day) {
+                        Ok(file) => entry.insert(file),
+                        Err(e) => {
+                            eprintln!("❌ Failed to open capture file for {}: {}", device, e);
+                            continue;
+                        }
+                    },
+                };
+                file.block.push(timestamp, &values);
+                if file.block.frames >= config.block_frames {
+                    file.flush();
+                }
+            }
+            _ = flush.tick() => {
+                for file in files.values_mut() {
+                    file.flush();
+                }
+            }
+        }
+    }
+    for file in files.values_mut() {
+        file.flush();
+    }
+}
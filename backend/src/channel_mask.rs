@@ -0,0 +1,115 @@
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::audit::AuditLog;
+use crate::filtering::UnifiedSensorRaw;
+use crate::channels::Channel;
+use crate::CHANNEL_NAMES;
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ChannelMaskConfig {
+    /// Channels disabled from startup, e.g. a sensor known to be dead
+    #[serde(default)]
+    pub disabled: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    channel_mask: ChannelMaskConfig,
+}
+
+impl ChannelMaskConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().channel_mask
+    }
+}
+
+// ================= Channel Mask =================
+/// Physical channels switched off at runtime. A disabled channel reads as
+/// NaN from the raw frame on: filters reset it, frames carry `null`, and
+/// storage and derived outputs skip it instead of being poisoned by it.
+#[derive(Clone)]
+pub struct ChannelMask {
+    disabled: Arc<Mutex<BTreeSet<String>>>,
+    data_tx: broadcast::Sender<String>,
+}
+
+impl ChannelMask {
+    pub fn new(config: &ChannelMaskConfig, data_tx: broadcast::Sender<String>) -> Self {
+        let disabled = config
+            .disabled
+            .iter()
+            .filter(|name| {
+                let known = CHANNEL_NAMES.contains(&name.as_str());
+                if !known {
+                    eprintln!("⚠️ channel_mask: unknown channel '{}'", name);
+                }
+                known
+            })
+            .cloned()
+            .collect();
+        Self { disabled: Arc::new(Mutex::new(disabled)), data_tx }
+    }
+
+    pub fn set(&self, name: &str, enabled: bool) -> Result<(), String> {
+        if !CHANNEL_NAMES.contains(&name) {
+            return Err(format!("unknown channel '{}'", name));
+        }
+        let changed = {
+            let mut disabled = self.disabled.lock().unwrap();
+            if enabled { disabled.remove(name) } else { disabled.insert(name.to_string()) }
+        };
+        if changed {
+            println!("🔌 Channel {} {}", name, if enabled { "enabled" } else { "disabled" });
+            self.publish();
+        }
+        Ok(())
+    }
+
+    pub fn disabled(&self) -> Vec<String> {
+        self.disabled.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Blank disabled channels in a raw frame before filtering
+    pub fn apply(&self, raw: &mut UnifiedSensorRaw) {
+        let disabled = self.disabled.lock().unwrap();
+        if disabled.is_empty() {
+            return;
+        }
+        for channel in Channel::ALL {
+            if disabled.contains(channel.name()) {
+                raw.channels[channel] = f32::NAN;
+            }
+        }
+    }
+
+    pub fn publish(&self) {
+        let event = serde_json::json!({ "type": "channel_mask", "disabled": self.disabled() });
+        let _ = self.data_tx.send(event.to_string());
+    }
+}
+
+/// GUI commands:
+///   CHANNEL_DISABLE <name>
+///   CHANNEL_ENABLE <name>
+///   CHANNEL_STATUS
+pub fn handle_command(mask: &ChannelMask, audit: &AuditLog, client: &str, args: &[&str]) -> Result<(), String> {
+    match args {
+        [cmd @ ("CHANNEL_DISABLE" | "CHANNEL_ENABLE"), name] => {
+            let enabled = *cmd == "CHANNEL_ENABLE";
+            mask.set(name, enabled)?;
+            let action = if enabled { "channel_enable" } else { "channel_disable" };
+            audit.record(client, action, serde_json::json!({ "channel": name }));
+            Ok(())
+        }
+        ["CHANNEL_STATUS"] => {
+            mask.publish();
+            Ok(())
+        }
+        _ => Err("usage: CHANNEL_DISABLE <name> | CHANNEL_ENABLE <name> | CHANNEL_STATUS".to_string()),
+    }
+}
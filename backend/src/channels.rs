@@ -0,0 +1,304 @@
+use serde::de::{MapAccess, Visitor};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::ops::{Index, IndexMut};
+
+use crate::differential::DifferentialConfig;
+use crate::filtering::FilterConfig;
+use crate::virtual_channels::AUX_INPUTS;
+use crate::CHANNEL_NAMES;
+
+// ================= Physical Channels =================
+/// One of the seven gas channels, in SENSOR line order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Channel {
+    No2,
+    Eth,
+    Voc,
+    Co,
+    Com,
+    Ethm,
+    Vocm,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 7] =
+        [Channel::No2, Channel::Eth, Channel::Voc, Channel::Co, Channel::Com, Channel::Ethm, Channel::Vocm];
+    pub const NAMES: [&'static str; 7] = ["no2", "eth", "voc", "co", "com", "ethm", "vocm"];
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self.index()]
+    }
+
+    /// Position in SENSOR line / `CHANNEL_NAMES` order
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter().position(|n| *n == name).map(|i| Self::ALL[i])
+    }
+}
+
+/// Values of the seven gas channels, indexed by `Channel` (`frame.channels[Channel::Co]`).
+/// Serialized as top-level `no2` .. `vocm` fields when flattened into a frame;
+/// disabled channels are NaN and travel as `null`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Channels([f32; 7]);
+
+impl Channels {
+    pub fn new(values: [f32; 7]) -> Self {
+        Self(values)
+    }
+
+    /// The first seven values of a SENSOR/REF line, None if it is shorter
+    pub fn from_slice(values: &[f32]) -> Option<Self> {
+        values.get(..7)?.try_into().ok().map(Self)
+    }
+
+    /// Values in `Channel::ALL` order
+    pub fn values(&self) -> [f32; 7] {
+        self.0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Channel, f32)> + '_ {
+        Channel::ALL.into_iter().zip(self.0)
+    }
+}
+
+impl From<[f32; 7]> for Channels {
+    fn from(values: [f32; 7]) -> Self {
+        Self(values)
+    }
+}
+
+impl Index<Channel> for Channels {
+    type Output = f32;
+
+    fn index(&self, channel: Channel) -> &f32 {
+        &self.0[channel.index()]
+    }
+}
+
+impl IndexMut<Channel> for Channels {
+    fn index_mut(&mut self, channel: Channel) -> &mut f32 {
+        &mut self.0[channel.index()]
+    }
+}
+
+impl Serialize for Channels {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Channels", 7)?;
+        for (channel, value) in self.iter() {
+            state.serialize_field(channel.name(), &Some(value).filter(|v| !v.is_nan()))?;
+        }
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Channels {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ChannelsVisitor;
+
+        impl<'de> Visitor<'de> for ChannelsVisitor {
+            type Value = Channels;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("gas channel fields")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Channels, A::Error> {
+                // Kanal yang tidak ada / null dibaca sebagai NaN
+                let mut channels = Channels([f32::NAN; 7]);
+                while let Some(key) = map.next_key::<String>()? {
+                    match Channel::from_name(&key) {
+                        Some(channel) => channels[channel] = map.next_value::<Option<f32>>()?.unwrap_or(f32::NAN),
+                        None => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(channels)
+            }
+        }
+
+        deserializer.deserialize_struct("Channels", &Channel::NAMES, ChannelsVisitor)
+    }
+}
+
+/// `[channels.<name>]` overrides; anything left out keeps the built-in value
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ChannelMeta {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub display_min: Option<f32>,
+    #[serde(default)]
+    pub display_max: Option<f32>,
+    /// Display labels per language code, e.g. `{ en = "Ethanol", id = "Etanol" }`
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// Trace/legend color, e.g. "#00d9ff"
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Severity bands for gauges, e.g. `[{ min = 5.0, severity = "warning" }]`
+    #[serde(default)]
+    pub bands: Vec<ColorBand>,
+}
+
+/// Value range shown in a severity color; open-ended when min/max is missing
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ColorBand {
+    #[serde(default)]
+    pub min: Option<f32>,
+    #[serde(default)]
+    pub max: Option<f32>,
+    pub severity: String,
+    /// Defaults to the severity's color
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+fn severity_color(severity: &str) -> &'static str {
+    match severity {
+        "ok" | "normal" => "#39ff14",
+        "info" => "#00d9ff",
+        "warning" => "#ffbe0b",
+        "critical" => "#ff006e",
+        _ => "#888888",
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    channels: BTreeMap<String, ChannelMeta>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelKind {
+    Sensor,
+    /// Optional rig input (flow, pressure, ...)
+    Auxiliary,
+    Virtual,
+    /// Sample vs reference chamber (dual-chamber rigs)
+    Differential,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ChannelInfo {
+    pub name: String,
+    pub kind: ChannelKind,
+    pub description: String,
+    pub unit: String,
+    pub display_min: Option<f32>,
+    pub display_max: Option<f32>,
+    pub labels: BTreeMap<String, String>,
+    pub color: String,
+    pub bands: Vec<ColorBand>,
+    /// Label in the requested language (falls back to English, then the name)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Built-in (description, unit, English label, color) per physical channel
+fn builtin(name: &str) -> (&'static str, &'static str, &'static str, &'static str) {
+    match name {
+        "no2" => ("NO2, Grove multichannel gas sensor (GM-102B)", "ppm", "NO2", "#00d9ff"),
+        "eth" => ("Ethanol, Grove multichannel gas sensor (GM-302B)", "ppm", "Ethanol", "#0096c7"),
+        "voc" => ("VOC, Grove multichannel gas sensor (GM-502B)", "ppm", "VOC", "#00b4d8"),
+        "co" => ("CO, Grove multichannel gas sensor (GM-702B)", "ppm", "CO", "#48cae4"),
+        "com" => ("CO, MiCS-5524", "ppm", "CO (MiCS)", "#0077b6"),
+        "ethm" => ("Ethanol, MiCS-5524", "ppm", "Ethanol (MiCS)", "#90e0ef"),
+        "vocm" => ("VOC, MiCS-5524", "ppm", "VOC (MiCS)", "#023e8a"),
+        "flow" => ("Sample flow rate", "L/min", "Flow", "#ffbe0b"),
+        "pressure" => ("Chamber pressure", "kPa", "Pressure", "#fb5607"),
+        "humidity" => ("Chamber relative humidity", "%RH", "Humidity", "#8338ec"),
+        "heater_temp" => ("Sensor heater temperature", "°C", "Heater", "#ff006e"),
+        _ => ("", "", "", "#888888"),
+    }
+}
+
+// ================= Channel Metadata =================
+/// Every channel the backend publishes, with config overrides merged over the
+/// built-in descriptions. `lang` fills `label` for GUIs that only want one.
+pub fn catalog(path: &str, lang: Option<&str>) -> Vec<ChannelInfo> {
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    let mut overrides = toml::from_str::<ConfigFile>(&content).unwrap_or_default().channels;
+    let formulas = FilterConfig::load(path, None).virtual_channels;
+
+    let physical = CHANNEL_NAMES
+        .iter()
+        .map(|name| (name.to_string(), ChannelKind::Sensor))
+        .chain(AUX_INPUTS.iter().map(|name| (name.to_string(), ChannelKind::Auxiliary)));
+    let virtual_channels = formulas.keys().map(|name| (name.clone(), ChannelKind::Virtual));
+    let differential = DifferentialConfig::load(path, None);
+    let sources: BTreeMap<String, &str> = match differential.enabled {
+        true => differential.output_channels().into_iter().collect(),
+        false => BTreeMap::new(),
+    };
+    let differential_channels = sources.keys().map(|name| (name.clone(), ChannelKind::Differential));
+
+    physical
+        .chain(virtual_channels)
+        .chain(differential_channels)
+        .map(|(name, kind)| {
+            let meta = overrides.remove(&name).unwrap_or_default();
+            let derived;
+            let (description, unit, label, color) = match kind {
+                ChannelKind::Virtual => (formulas[&name].as_str(), "", name.as_str(), "#888888"),
+                ChannelKind::Differential => {
+                    let (_, unit, _, color) = builtin(sources[&name]);
+                    let (text, unit) = match name.ends_with("_ratio") {
+                        true => ("sample / reference chamber", ""),
+                        false => ("sample - reference chamber", unit),
+                    };
+                    derived = format!("{} {}", sources[&name], text);
+                    (derived.as_str(), unit, name.as_str(), color)
+                }
+                _ => builtin(&name),
+            };
+
+            let mut labels = meta.labels;
+            labels.entry("en".to_string()).or_insert_with(|| label.to_string());
+            let label = lang.map(|lang| {
+                labels
+                    .get(lang)
+                    .or_else(|| labels.get("en"))
+                    .cloned()
+                    .unwrap_or_else(|| name.clone())
+            });
+
+            let bands = meta
+                .bands
+                .into_iter()
+                .map(|mut band| {
+                    band.color.get_or_insert_with(|| severity_color(&band.severity).to_string());
+                    band
+                })
+                .collect();
+
+            ChannelInfo {
+                description: meta.description.unwrap_or_else(|| description.to_string()),
+                unit: meta.unit.unwrap_or_else(|| unit.to_string()),
+                display_min: meta.display_min,
+                display_max: meta.display_max,
+                labels,
+                color: meta.color.unwrap_or_else(|| color.to_string()),
+                bands,
+                label,
+                name,
+                kind,
+            }
+        })
+        .collect()
+}
+
+/// `channel_metadata` GUI message, sent on connect and for CHANNELS [lang]
+pub fn metadata_message(path: &str, lang: Option<&str>) -> String {
+    serde_json::json!({ "type": "channel_metadata", "channels": catalog(path, lang) }).to_string()
+}
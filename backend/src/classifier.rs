@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Labeled feature vector from a training run
+#[derive(Debug, Deserialize, Clone)]
+pub struct TrainingSample {
+    pub label: String,
+    /// In the model's `features` order
+    pub features: Vec<f64>,
+}
+
+/// Model file (JSON):
+/// `{"type": "knn", "k": 3, "features": ["eth.peak", ...], "samples": [{"label": "ethanol", "features": [...]}]}`
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModelFile {
+    #[serde(rename = "type", default = "default_type")]
+    pub kind: String,
+    /// Feature names the sample vectors are made of
+    pub features: Vec<String>,
+    pub samples: Vec<TrainingSample>,
+    #[serde(default = "default_k")]
+    pub k: usize,
+    /// Scale every feature to unit variance over the samples first
+    #[serde(default = "default_normalize")]
+    pub normalize: bool,
+}
+
+fn default_type() -> String { "knn".to_string() }
+fn default_k() -> usize { 3 }
+fn default_normalize() -> bool { true }
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Prediction {
+    pub label: String,
+    /// Share of the k neighbours voting for `label` (centroid: 1 / (1 + distance))
+    pub confidence: f64,
+    /// Distance to the nearest neighbour / centroid of `label`
+    pub distance: f64,
+    pub model: String,
+}
+
+/// Pluggable classifier over the named cycle features
+pub trait Classifier: Send + Sync {
+    fn name(&self) -> &str;
+    fn predict(&self, features: &BTreeMap<String, f64>) -> Result<Prediction, String>;
+}
+
+/// Per-feature scaling and vector assembly shared by the distance models
+struct FeatureSpace {
+    names: Vec<String>,
+    mean: Vec<f64>,
+    scale: Vec<f64>,
+}
+
+impl FeatureSpace {
+    fn fit(model: &ModelFile) -> Self {
+        let n = model.features.len();
+        let count = model.samples.len().max(1) as f64;
+        let mut mean = vec![0.0; n];
+        let mut scale = vec![1.0; n];
+        if model.normalize {
+            for sample in &model.samples {
+                for (m, v) in mean.iter_mut().zip(&sample.features) {
+                    *m += v / count;
+                }
+            }
+            for (i, s) in scale.iter_mut().enumerate() {
+                let var = model.samples.iter().map(|x| (x.features[i] - mean[i]).powi(2)).sum::<f64>() / count;
+                // Fitur konstan tidak diskalakan
+                *s = if var > f64::EPSILON { var.sqrt() } else { 1.0 };
+            }
+        }
+        Self { names: model.features.clone(), mean, scale }
+    }
+
+    fn project(&self, values: &[f64]) -> Vec<f64> {
+        values.iter().zip(&self.mean).zip(&self.scale).map(|((v, m), s)| (v - m) / s).collect()
+    }
+
+    fn vector(&self, features: &BTreeMap<String, f64>) -> Result<Vec<f64>, String> {
+        let values = self
+            .names
+            .iter()
+            .map(|name| features.get(name).copied().filter(|v| v.is_finite()).ok_or_else(|| format!("feature '{}' missing", name)))
+            .collect::<Result<Vec<f64>, String>>()?;
+        Ok(self.project(&values))
+    }
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f64>().sqrt()
+}
+
+/// k nearest neighbours, majority vote (ties go to the nearer label)
+pub struct Knn {
+    name: String,
+    k: usize,
+    space: FeatureSpace,
+    samples: Vec<(String, Vec<f64>)>,
+}
+
+impl Classifier for Knn {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn predict(&self, features: &BTreeMap<String, f64>) -> Result<Prediction, String> {
+        let query = self.space.vector(features)?;
+        let mut neighbours: Vec<(f64, &str)> =
+            self.samples.iter().map(|(label, v)| (distance(&query, v), label.as_str())).collect();
+        neighbours.sort_by(|a, b| a.0.total_cmp(&b.0));
+        neighbours.truncate(self.k);
+
+        // label → (votes, nearest distance)
+        let mut votes: BTreeMap<&str, (usize, f64)> = BTreeMap::new();
+        for (d, label) in &neighbours {
+            let entry = votes.entry(label).or_insert((0, *d));
+            entry.0 += 1;
+            entry.1 = entry.1.min(*d);
+        }
+        let (label, (count, nearest)) = votes
+            .into_iter()
+            .max_by(|a, b| a.1 .0.cmp(&b.1 .0).then(b.1 .1.total_cmp(&a.1 .1)))
+            .ok_or("model has no samples")?;
+        Ok(Prediction {
+            label: label.to_string(),
+            confidence: count as f64 / neighbours.len() as f64,
+            distance: nearest,
+            model: self.name.clone(),
+        })
+    }
+}
+
+/// Nearest class mean
+pub struct Centroid {
+    name: String,
+    space: FeatureSpace,
+    centroids: Vec<(String, Vec<f64>)>,
+}
+
+impl Classifier for Centroid {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn predict(&self, features: &BTreeMap<String, f64>) -> Result<Prediction, String> {
+        let query = self.space.vector(features)?;
+        let (label, d) = self
+            .centroids
+            .iter()
+            .map(|(label, c)| (label, distance(&query, c)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .ok_or("model has no samples")?;
+        Ok(Prediction { label: label.clone(), confidence: 1.0 / (1.0 + d), distance: d, model: self.name.clone() })
+    }
+}
+
+/// Load a model file; `type` picks the implementation ("knn" or "centroid")
+pub fn load(path: &str) -> Result<Box<dyn Classifier>, String> {
+    let name = std::path::Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string());
+    load_named(path, &name)
+}
+
+/// As `load`, with the name reported in predictions (registry version id)
+pub fn load_named(path: &str, name: &str) -> Result<Box<dyn Classifier>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let model: ModelFile = serde_json::from_str(&content).map_err(|e| format!("{}: {}", path, e))?;
+    if model.features.is_empty() || model.samples.is_empty() {
+        return Err(format!("{}: model needs features and samples", path));
+    }
+    if let Some(bad) = model.samples.iter().find(|s| s.features.len() != model.features.len()) {
+        return Err(format!("{}: sample '{}' has {} values for {} features", path, bad.label, bad.features.len(), model.features.len()));
+    }
+
+    let name = name.to_string();
+    let space = FeatureSpace::fit(&model);
+    let projected: Vec<(String, Vec<f64>)> =
+        model.samples.iter().map(|s| (s.label.clone(), space.project(&s.features))).collect();
+
+    match model.kind.as_str() {
+        "knn" => Ok(Box::new(Knn { name, k: model.k.max(1), space, samples: projected })),
+        "centroid" => {
+            let mut sums: BTreeMap<String, (Vec<f64>, usize)> = BTreeMap::new();
+            for (label, v) in &projected {
+                let entry = sums.entry(label.clone()).or_insert((vec![0.0; v.len()], 0));
+                entry.0.iter_mut().zip(v).for_each(|(s, x)| *s += x);
+                entry.1 += 1;
+            }
+            let centroids = sums
+                .into_iter()
+                .map(|(label, (sum, n))| (label, sum.into_iter().map(|s| s / n as f64).collect()))
+                .collect();
+            Ok(Box::new(Centroid { name, space, centroids }))
+        }
+        other => Err(format!("{}: unknown model type '{}' (knn, centroid)", path, other)),
+    }
+}
@@ -0,0 +1,116 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Default)]
+struct Counters {
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    messages_sent: AtomicU64,
+    commands_received: AtomicU64,
+    /// Messages lost because the client fell behind the broadcast channel
+    dropped: AtomicU64,
+}
+
+#[derive(Debug)]
+struct ClientEntry {
+    addr: SocketAddr,
+    connected_at: chrono::DateTime<chrono::Utc>,
+    privacy: bool,
+    read_only: bool,
+    counters: Counters,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClientStats {
+    pub id: u64,
+    pub addr: String,
+    pub connected_at: i64,
+    pub duration_secs: i64,
+    pub privacy: bool,
+    pub read_only: bool,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub messages_sent: u64,
+    pub commands_received: u64,
+    pub dropped: u64,
+}
+
+// ================= GUI Client Registry =================
+/// Live per-connection traffic counters, to spot lagging or flooding clients
+#[derive(Clone, Default)]
+pub struct ClientRegistry {
+    clients: Arc<Mutex<BTreeMap<u64, Arc<ClientEntry>>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl ClientRegistry {
+    pub fn register(&self, addr: SocketAddr, privacy: bool, read_only: bool) -> ClientHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let entry = Arc::new(ClientEntry {
+            addr,
+            connected_at: chrono::Utc::now(),
+            privacy,
+            read_only,
+            counters: Counters::default(),
+        });
+        self.clients.lock().unwrap().insert(id, entry.clone());
+        ClientHandle { id, entry, registry: self.clone() }
+    }
+
+    pub fn snapshot(&self) -> Vec<ClientStats> {
+        let now = chrono::Utc::now();
+        self.clients
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, entry)| {
+                let c = &entry.counters;
+                ClientStats {
+                    id: *id,
+                    addr: entry.addr.to_string(),
+                    connected_at: entry.connected_at.timestamp_millis(),
+                    duration_secs: (now - entry.connected_at).num_seconds(),
+                    privacy: entry.privacy,
+                    read_only: entry.read_only,
+                    bytes_sent: c.bytes_sent.load(Ordering::Relaxed),
+                    bytes_received: c.bytes_received.load(Ordering::Relaxed),
+                    messages_sent: c.messages_sent.load(Ordering::Relaxed),
+                    commands_received: c.commands_received.load(Ordering::Relaxed),
+                    dropped: c.dropped.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Counters of one connection; unregisters the client when dropped
+pub struct ClientHandle {
+    id: u64,
+    entry: Arc<ClientEntry>,
+    registry: ClientRegistry,
+}
+
+impl ClientHandle {
+    pub fn sent(&self, bytes: usize) {
+        self.entry.counters.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.entry.counters.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn received(&self, bytes: usize) {
+        self.entry.counters.bytes_received.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.entry.counters.commands_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dropped(&self, count: u64) {
+        self.entry.counters.dropped.fetch_add(count, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ClientHandle {
+    fn drop(&mut self) {
+        self.registry.clients.lock().unwrap().remove(&self.id);
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+// ================= Clock =================
+/// Source of wall-clock time for the processing pipeline (payload timestamps,
+/// sine modulation). Injected so tests and replays produce exact outputs.
+pub trait Clock: Send + Sync {
+    /// Current time, epoch ms
+    fn now_millis(&self) -> i64;
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The real system clock
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> i64 {
+        chrono::Utc::now().timestamp_millis()
+    }
+}
+
+pub fn system() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// Clock that only moves when told to, e.g. set to each recorded frame's
+/// timestamp during a replay or golden-vector run
+#[derive(Default)]
+pub struct ManualClock {
+    now: AtomicI64,
+}
+
+impl ManualClock {
+    pub fn new(start_ms: i64) -> Arc<Self> {
+        Arc::new(Self { now: AtomicI64::new(start_ms) })
+    }
+
+    pub fn set(&self, ms: i64) {
+        self.now.store(ms, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
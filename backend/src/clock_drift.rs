@@ -0,0 +1,153 @@
+use influxdb2::models::DataPoint;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::influxdb::InfluxDBHandler;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ClockDriftConfig {
+    /// Timestamp frames with the device clock, corrected against backend time
+    #[serde(default)]
+    pub enabled: bool,
+    /// Index of the device time field on SENSOR lines (epoch ms, RTC)
+    #[serde(default = "default_field")]
+    pub field: usize,
+    /// One correction segment per this many seconds
+    #[serde(default = "default_segment_secs")]
+    pub segment_secs: i64,
+}
+
+fn default_field() -> usize { 13 }
+fn default_segment_secs() -> i64 { 600 }
+
+impl Default for ClockDriftConfig {
+    fn default() -> Self {
+        Self { enabled: false, field: default_field(), segment_secs: default_segment_secs() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    clock_drift: ClockDriftConfig,
+}
+
+impl ClockDriftConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc)
+            .try_into::<ConfigFile>()
+            .unwrap_or_default()
+            .clock_drift
+    }
+}
+
+/// Published when a segment closes
+#[derive(Debug, Serialize, Clone)]
+pub struct DriftEstimate {
+    /// Backend time minus device time (ms), lower envelope of the segment
+    pub offset_ms: f64,
+    /// Device clock rate error; positive = device clock runs slow
+    pub drift_ppm: Option<f64>,
+    pub samples: usize,
+    pub timestamp: i64,
+}
+
+// ================= Clock Drift =================
+/// Estimates the device clock's offset and rate against backend time and
+/// maps device timestamps onto the backend timeline.
+///
+/// Transport delay only ever adds to the offset, so each segment keeps its
+/// smallest offset as the anchor. Device time is corrected piecewise-linearly
+/// through the last two anchors (extrapolated past the newest one).
+pub struct ClockDrift {
+    config: ClockDriftConfig,
+    segment_start: Option<i64>,
+    /// (device ms, offset ms) with the smallest offset in the open segment
+    segment_min: Option<(i64, f64)>,
+    segment_samples: usize,
+    anchors: Vec<(i64, f64)>,
+}
+
+impl ClockDrift {
+    pub fn new(device: Option<&str>) -> Option<Self> {
+        let config = ClockDriftConfig::load("config.toml", device);
+        config.enabled.then(|| Self {
+            config,
+            segment_start: None,
+            segment_min: None,
+            segment_samples: 0,
+            anchors: Vec::new(),
+        })
+    }
+
+    /// Device time field of a SENSOR line (prefix stripped)
+    pub fn device_time(&self, fields: &str) -> Option<i64> {
+        let value = fields.split(',').nth(self.config.field)?.trim().parse::<f64>().ok()?;
+        (value > 0.0).then_some(value as i64)
+    }
+
+    /// Record one (device, backend) time pair; returns an estimate when a
+    /// segment closes
+    pub fn observe(&mut self, device_ms: i64, backend_ms: i64) -> Option<DriftEstimate> {
+        let offset = (backend_ms - device_ms) as f64;
+        let start = *self.segment_start.get_or_insert(backend_ms);
+        if self.segment_min.is_none_or(|(_, min)| offset < min) {
+            self.segment_min = Some((device_ms, offset));
+        }
+        self.segment_samples += 1;
+        if backend_ms - start < self.config.segment_secs * 1000 {
+            return None;
+        }
+
+        let anchor = self.segment_min.take()?;
+        let drift_ppm = self
+            .anchors
+            .last()
+            .filter(|(device, _)| anchor.0 > *device)
+            .map(|(device, offset)| (anchor.1 - offset) / (anchor.0 - device) as f64 * 1e6);
+        self.anchors.push(anchor);
+        if self.anchors.len() > 2 {
+            self.anchors.remove(0);
+        }
+        let estimate =
+            DriftEstimate { offset_ms: anchor.1, drift_ppm, samples: self.segment_samples, timestamp: backend_ms };
+        self.segment_start = Some(backend_ms);
+        self.segment_samples = 0;
+        Some(estimate)
+    }
+
+    /// Device time mapped onto backend time, None before the first estimate
+    pub fn correct(&self, device_ms: i64) -> Option<i64> {
+        let offset = match self.anchors.as_slice() {
+            [(d1, o1), (d2, o2)] if d2 > d1 => o1 + (device_ms - d1) as f64 * (o2 - o1) / (d2 - d1) as f64,
+            [.., (_, offset)] => *offset,
+            [] => self.segment_min?.1,
+        };
+        Some(device_ms + offset.round() as i64)
+    }
+}
+
+/// Diagnostic: "clock_drift" GUI event and InfluxDB measurement
+pub async fn publish(device: &str, estimate: &DriftEstimate, data_tx: &broadcast::Sender<String>, influx: &InfluxDBHandler) {
+    match estimate.drift_ppm {
+        Some(ppm) => println!("⏱️ {} clock offset {:.0} ms, drift {:.1} ppm", device, estimate.offset_ms, ppm),
+        None => println!("⏱️ {} clock offset {:.0} ms", device, estimate.offset_ms),
+    }
+    let mut event = serde_json::to_value(estimate).unwrap_or_default();
+    event["type"] = "clock_drift".into();
+    event["device"] = device.into();
+    let _ = data_tx.send(event.to_string());
+
+    let mut builder = DataPoint::builder("clock_drift")
+        .tag("device", device.to_string())
+        .field("offset_ms", estimate.offset_ms);
+    if let Some(ppm) = estimate.drift_ppm {
+        builder = builder.field("drift_ppm", ppm);
+    }
+    if let Ok(point) = builder.timestamp(estimate.timestamp * 1_000_000).build() {
+        if let Err(e) = influx.write_points(vec![point]).await {
+            eprintln!("❌ Clock drift write error: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,855 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+use toml::{Table, Value};
+
+use crate::adaptive::AdaptiveStorageConfig;
+use crate::aggregator::AggregatorConfig;
+use crate::ambient::AmbientConfig;
+use crate::arduino_link::ArduinoConfig;
+use crate::alerts::AlertRuleDef;
+use crate::analytics::AnalyticsConfig;
+use crate::board::BoardConfig;
+use crate::burn_in::BurnInConfig;
+use crate::console::ConsoleConfig;
+use crate::control_lock::ControlLockConfig;
+use crate::deadband::DeadbandConfig;
+use crate::debug_bundle::DebugBundleConfig;
+use crate::derived::DerivedConfig;
+use crate::devices::DeviceRegistryConfig;
+use crate::differential::DifferentialConfig;
+use crate::gui_web::GuiConfig;
+use crate::recording::RecordingConfig;
+use crate::capture::CaptureConfig;
+use crate::sessions::SessionsConfig;
+use crate::weather::WeatherConfig;
+use crate::location::LocationConfig;
+use crate::campaign::CampaignConfig;
+use crate::diagnostics::DiagnosticsConfig;
+use crate::api::ApiConfig;
+use crate::audit::AuditConfig;
+use crate::backfill::BackfillConfig;
+use crate::jobs::JobsConfig;
+use crate::roc::RocConfig;
+use crate::accuracy::AccuracyConfig;
+use crate::baseline::BaselineConfig;
+use crate::channel_mask::ChannelMaskConfig;
+use crate::channels::ChannelMeta;
+use crate::clock_drift::ClockDriftConfig;
+use crate::cross_correlation::CrossCorrelationConfig;
+use crate::dialer::EndpointConfig;
+use crate::disk::DiskConfig;
+use crate::experiments::{ExperimentDef, QueueConfig};
+use crate::failover::FailoverConfig;
+use crate::features::FeaturesConfig;
+use crate::filtering::FilterConfig;
+use crate::flow::FlowConfig;
+use crate::heater::HeaterConfig;
+use crate::humidity::HumidityConfig;
+use crate::influx_bootstrap::InfluxBootstrapConfig;
+use crate::influxdb::InfluxWriterConfig;
+use crate::latency::LatencyConfig;
+use crate::leak_test::LeakTestConfig;
+use crate::mfc::DilutionConfig;
+use crate::models::ModelsConfig;
+use crate::mqtt::MqttConfig;
+use crate::kafka::KafkaConfig;
+use crate::nats::NatsConfig;
+use crate::noise_floor::NoiseFloorConfig;
+use crate::saturation::SaturationConfig;
+use crate::ranges::RangeConfig;
+use crate::device_log::DeviceLogConfig;
+use crate::pre_trigger::PreTriggerConfig;
+use crate::limits::LimitsConfig;
+use crate::cycle_abort::CycleAbortConfig;
+use crate::shift_log::ShiftLogConfig;
+use crate::auth::AuthConfig;
+use crate::usage::UsageConfig;
+use crate::redis_cache::RedisConfig;
+use crate::plugins::PluginConfig;
+use crate::privacy::PrivacyConfig;
+use crate::scripting::ScriptingConfig;
+use crate::provenance::CalibrationConfig;
+use crate::rtc_stream::WebRtcConfig;
+use crate::sms::SmsConfig;
+use crate::store::StoreConfig;
+use crate::reference::ReferenceConfig;
+use crate::transfer::TransferConfig;
+use crate::trigger::TriggerConfig;
+use crate::uplink::UplinkConfig;
+use crate::video_sync::VideoSyncConfig;
+use crate::viewer::ViewerConfig;
+use crate::waveform::WaveformConfig;
+
+/// Maximum `inherits` chain length (guards against cycles)
+const MAX_INHERITANCE_DEPTH: usize = 8;
+
+/// Load config.toml with per-device profile overrides merged in.
+///
+/// Resolution order (later wins):
+///   1. top-level keys of config.toml (the default profile)
+///   2. `[profiles.<name>]` chain, following `inherits = "<parent>"`
+///   3. `[devices.<id>]` overrides, which may select `profile = "<name>"`
+///
+/// Tables are merged key by key; scalars and arrays are replaced.
+pub fn load_document(path: &str, device: Option<&str>) -> Table {
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    match toml::from_str(&content) {
+        Ok(doc) => resolve(doc, device),
+        Err(e) => {
+            if !content.is_empty() {
+                eprintln!("❌ Failed to parse {}: {}", path, e);
+            }
+            Table::new()
+        }
+    }
+}
+
+/// Apply profile/device overrides to a parsed config document
+fn resolve(mut doc: Table, device: Option<&str>) -> Table {
+    let profiles = match doc.remove("profiles") {
+        Some(Value::Table(t)) => t,
+        _ => Table::new(),
+    };
+    let devices = match doc.remove("devices") {
+        Some(Value::Table(t)) => t,
+        _ => Table::new(),
+    };
+
+    let Some(device) = device else {
+        return doc;
+    };
+    let Some(Value::Table(mut overrides)) = devices.get(device).cloned() else {
+        return doc;
+    };
+
+    if let Some(Value::String(profile)) = overrides.remove("profile") {
+        for layer in profile_chain(&profiles, &profile) {
+            merge(&mut doc, layer);
+        }
+    }
+    merge(&mut doc, overrides);
+    doc
+}
+
+/// Profile layers from the root ancestor down to `name`
+fn profile_chain(profiles: &Table, name: &str) -> Vec<Table> {
+    let mut chain = Vec::new();
+    let mut current = Some(name.to_string());
+
+    while let Some(name) = current.take() {
+        if chain.len() >= MAX_INHERITANCE_DEPTH {
+            eprintln!("⚠️ Profile inheritance too deep at '{}', ignoring the rest", name);
+            break;
+        }
+        let Some(Value::Table(mut layer)) = profiles.get(&name).cloned() else {
+            eprintln!("⚠️ Unknown config profile '{}'", name);
+            break;
+        };
+        if let Some(Value::String(parent)) = layer.remove("inherits") {
+            current = Some(parent);
+        }
+        chain.push(layer);
+    }
+
+    chain.reverse();
+    chain
+}
+
+fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base_table)), Value::Table(overlay_table)) => merge(base_table, overlay_table),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+// ================= Validation =================
+/// Every config section, deserialized strictly to catch type errors
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Sections {
+    #[serde(flatten)]
+    filters: FilterConfig,
+    #[serde(default)]
+    channels: BTreeMap<String, ChannelMeta>,
+    #[serde(default)]
+    baseline: BaselineConfig,
+    #[serde(default)]
+    experiment_queue: QueueConfig,
+    #[serde(default)]
+    experiments: Vec<ExperimentDef>,
+    #[serde(default)]
+    flow: FlowConfig,
+    #[serde(default)]
+    leak_test: LeakTestConfig,
+    #[serde(default)]
+    humidity: HumidityConfig,
+    #[serde(default)]
+    heater: HeaterConfig,
+    #[serde(default)]
+    dilution: DilutionConfig,
+    #[serde(default)]
+    trigger: TriggerConfig,
+    #[serde(default)]
+    video_sync: VideoSyncConfig,
+    #[serde(default)]
+    reference: ReferenceConfig,
+    #[serde(default)]
+    cross_correlation: CrossCorrelationConfig,
+    #[serde(default)]
+    alerts: Vec<AlertRuleDef>,
+    #[serde(default)]
+    audit: AuditConfig,
+    #[serde(default)]
+    store: StoreConfig,
+    #[serde(default)]
+    influx_bootstrap: InfluxBootstrapConfig,
+    #[serde(default)]
+    diagnostics: DiagnosticsConfig,
+    #[serde(default)]
+    board: BoardConfig,
+    #[serde(default)]
+    debug_bundle: DebugBundleConfig,
+    #[serde(default)]
+    control_lock: ControlLockConfig,
+    #[serde(default)]
+    gui: GuiConfig,
+    #[serde(default)]
+    recording: RecordingConfig,
+    #[serde(default)]
+    capture: CaptureConfig,
+    #[serde(default)]
+    console: ConsoleConfig,
+    #[serde(default)]
+    disk: DiskConfig,
+    #[serde(default)]
+    sms: SmsConfig,
+    #[serde(default)]
+    mqtt: MqttConfig,
+    #[serde(default)]
+    kafka: KafkaConfig,
+    #[serde(default)]
+    nats: NatsConfig,
+    #[serde(default)]
+    redis: RedisConfig,
+    #[serde(default)]
+    calibration: CalibrationConfig,
+    #[serde(default)]
+    plugins: PluginConfig,
+    #[serde(default)]
+    scripting: ScriptingConfig,
+    #[serde(default)]
+    api: ApiConfig,
+    #[serde(default)]
+    webrtc: WebRtcConfig,
+    #[serde(default)]
+    privacy: PrivacyConfig,
+    #[serde(default)]
+    endpoints: Vec<EndpointConfig>,
+    #[serde(default)]
+    failover: FailoverConfig,
+    #[serde(default)]
+    aggregator: AggregatorConfig,
+    #[serde(default)]
+    uplink: UplinkConfig,
+    #[serde(default)]
+    viewer: ViewerConfig,
+    #[serde(default)]
+    analytics: AnalyticsConfig,
+    #[serde(default)]
+    burn_in: BurnInConfig,
+    #[serde(default)]
+    differential: DifferentialConfig,
+    #[serde(default)]
+    waveform: WaveformConfig,
+    #[serde(default)]
+    adaptive_storage: AdaptiveStorageConfig,
+    #[serde(default)]
+    backfill: BackfillConfig,
+    #[serde(default)]
+    jobs: JobsConfig,
+    #[serde(default)]
+    roc: RocConfig,
+    #[serde(default)]
+    accuracy: AccuracyConfig,
+    #[serde(default)]
+    models: ModelsConfig,
+    #[serde(default)]
+    transfer: TransferConfig,
+    #[serde(default)]
+    noise_floor: NoiseFloorConfig,
+    #[serde(default)]
+    saturation: SaturationConfig,
+    #[serde(default)]
+    ranges: RangeConfig,
+    #[serde(default)]
+    device_log: DeviceLogConfig,
+    #[serde(default)]
+    pre_trigger: PreTriggerConfig,
+    #[serde(default)]
+    limits: LimitsConfig,
+    #[serde(default)]
+    cycle_abort: CycleAbortConfig,
+    #[serde(default)]
+    shift_log: ShiftLogConfig,
+    #[serde(default)]
+    auth: AuthConfig,
+    #[serde(default)]
+    usage: UsageConfig,
+    #[serde(default)]
+    clock_drift: ClockDriftConfig,
+    #[serde(default)]
+    channel_mask: ChannelMaskConfig,
+    #[serde(default)]
+    reload: ReloadConfig,
+    #[serde(default)]
+    arduino: ArduinoConfig,
+    #[serde(default)]
+    latency: LatencyConfig,
+    #[serde(default)]
+    device_registry: DeviceRegistryConfig,
+    #[serde(default)]
+    influx_writer: InfluxWriterConfig,
+    #[serde(default)]
+    features: FeaturesConfig,
+    #[serde(default)]
+    derived: DerivedConfig,
+    #[serde(default)]
+    deadband: DeadbandConfig,
+    #[serde(default)]
+    sessions: SessionsConfig,
+    #[serde(default)]
+    ambient: AmbientConfig,
+    #[serde(default)]
+    weather: WeatherConfig,
+    #[serde(default)]
+    location: LocationConfig,
+    #[serde(default)]
+    campaign: CampaignConfig,
+}
+
+/// Check that a config document parses and every device profile is sane
+pub fn validate(content: &str) -> Result<(), String> {
+    let doc: Table = toml::from_str(content).map_err(|e| format!("TOML syntax: {}", e))?;
+
+    let devices: Vec<String> = match doc.get("devices") {
+        Some(Value::Table(t)) => t.keys().cloned().collect(),
+        _ => Vec::new(),
+    };
+
+    validate_resolved(resolve(doc.clone(), None)).map_err(|e| format!("default profile: {}", e))?;
+    for device in devices {
+        validate_resolved(resolve(doc.clone(), Some(&device)))
+            .map_err(|e| format!("device '{}': {}", device, e))?;
+    }
+    Ok(())
+}
+
+fn validate_resolved(doc: Table) -> Result<(), String> {
+    let sections: Sections = Value::Table(doc).try_into().map_err(|e| e.to_string())?;
+
+    let filters = &sections.filters;
+    if filters.window_size == 0 {
+        return Err("window_size must be at least 1".to_string());
+    }
+    if !(filters.ema_alpha > 0.0 && filters.ema_alpha <= 1.0) {
+        return Err(format!("ema_alpha {} must be within (0, 1]", filters.ema_alpha));
+    }
+    if !(0.0..=1.0).contains(&filters.sine_amplitude) {
+        return Err("sine_amplitude must be within 0.0..1.0".to_string());
+    }
+    if filters.sine_frequency < 0.0 {
+        return Err("sine_frequency must not be negative".to_string());
+    }
+    for (name, formula) in &filters.virtual_channels {
+        crate::virtual_channels::compile(name, formula)?;
+    }
+    filters.validate_chain()?;
+    sections.derived.validate()?;
+    sections.deadband.validate(&filters.virtual_channels)?;
+    sections.sessions.validate()?;
+    sections.ambient.validate()?;
+    sections.weather.validate()?;
+    sections.location.validate()?;
+    sections.campaign.validate()?;
+    sections.kafka.validate()?;
+    sections.nats.validate()?;
+    sections.redis.validate()?;
+    sections.capture.validate()?;
+    sections.jobs.validate()?;
+    sections.roc.validate()?;
+    sections.accuracy.validate()?;
+    sections.models.validate()?;
+    sections.transfer.validate()?;
+    sections.noise_floor.validate()?;
+    sections.saturation.validate()?;
+    sections.ranges.validate()?;
+    sections.device_log.validate()?;
+    sections.pre_trigger.validate()?;
+    sections.calibration.validate()?;
+    sections.limits.validate()?;
+    sections.cycle_abort.validate()?;
+    sections.shift_log.validate()?;
+    sections.auth.validate()?;
+    sections.usage.validate()?;
+    sections.heater.validate()?;
+    chrono::NaiveTime::parse_from_str(&sections.baseline.run_at, "%H:%M")
+        .map_err(|_| format!("baseline.run_at '{}' is not HH:MM", sections.baseline.run_at))?;
+
+    let mut names = std::collections::HashSet::new();
+    for def in &sections.experiments {
+        if !names.insert(&def.name) {
+            return Err(format!("duplicate experiment '{}'", def.name));
+        }
+        if def.steps.is_empty() {
+            return Err(format!("experiment '{}' has no steps", def.name));
+        }
+    }
+
+    for rule in &sections.alerts {
+        crate::alerts::compile(rule)?;
+    }
+
+    if sections.humidity.output_min > sections.humidity.output_max {
+        return Err("humidity.output_min is greater than output_max".to_string());
+    }
+    if sections.dilution.enabled && (sections.dilution.analyte.is_none() || sections.dilution.source_ppm <= 0.0) {
+        return Err("dilution needs an analyte MFC and a positive source_ppm".to_string());
+    }
+    Ok(())
+}
+
+// ================= Remote Configuration =================
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReloadConfig {
+    /// Reload filter settings when config.toml changes on disk
+    #[serde(default = "default_watch")]
+    pub watch: bool,
+    #[serde(default = "default_poll_ms")]
+    pub poll_ms: u64,
+}
+
+fn default_watch() -> bool { true }
+fn default_poll_ms() -> u64 { 1000 }
+
+impl Default for ReloadConfig {
+    fn default() -> Self {
+        Self { watch: default_watch(), poll_ms: default_poll_ms() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ReloadFile {
+    #[serde(default)]
+    reload: ReloadConfig,
+}
+
+impl ReloadConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ReloadFile>(&content).unwrap_or_default().reload
+    }
+}
+
+/// One entry of the config/calibration history (versions_dir/history.jsonl)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VersionEntry {
+    pub version: u64,
+    pub timestamp: i64,
+    /// GUI client address, or "external" for edits made outside the backend
+    pub actor: String,
+    /// "initial", "external", "set" or "rollback"
+    pub action: String,
+    /// Key that was set, or the version restored by a rollback
+    #[serde(default)]
+    pub detail: Option<String>,
+    /// Version this one was derived from (CONFIG_ROLLBACK returns to it)
+    #[serde(default)]
+    pub parent: Option<u64>,
+    #[serde(default)]
+    pub calibration_version: Option<String>,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+/// Runtime read/update of config.toml with validation, a versioned
+/// history (who/when/what) and rollback. Comments and layout are
+/// preserved on edit. Version N's full content is kept as config.vN.toml.
+#[derive(Clone)]
+pub struct ConfigStore {
+    path: Arc<String>,
+    versions_dir: Arc<String>,
+    lock: Arc<Mutex<()>>,
+    /// Version the running pipelines should be using
+    reloads: Arc<watch::Sender<u64>>,
+}
+
+impl ConfigStore {
+    pub fn new(path: &str, versions_dir: &str) -> Self {
+        let store = Self {
+            path: Arc::new(path.to_string()),
+            versions_dir: Arc::new(versions_dir.to_string()),
+            lock: Arc::new(Mutex::new(())),
+            reloads: Arc::new(watch::channel(0).0),
+        };
+        // Catat edit manual (di luar backend) sebagai versi baru
+        if let Err(e) = store.sync() {
+            eprintln!("⚠️ Config history unavailable: {}", e);
+        }
+        store.reloads.send_replace(store.current_version());
+        store
+    }
+
+    /// Resolved value of a dotted key (or the whole document) as JSON
+    pub fn get(&self, key: Option<&str>, device: Option<&str>) -> Result<serde_json::Value, String> {
+        let doc = load_document(&self.path, device);
+        let mut value = Value::Table(doc);
+        for segment in key.map(split_key).unwrap_or_default() {
+            value = value
+                .get(&segment)
+                .cloned()
+                .ok_or_else(|| format!("no config key '{}'", key.unwrap_or_default()))?;
+        }
+        serde_json::to_value(value).map_err(|e| e.to_string())
+    }
+
+    /// Set a dotted key to a TOML literal (bare words are taken as strings)
+    pub fn set(&self, key: &str, literal: &str, actor: &str) -> Result<u64, String> {
+        self.edit(actor, "set", Some(key.to_string()), |doc| set_value(doc, key, literal))
+    }
+
+    /// Apply `change` to the document as one recorded version
+    pub fn edit(
+        &self,
+        actor: &str,
+        action: &str,
+        detail: Option<String>,
+        change: impl FnOnce(&mut toml_edit::DocumentMut) -> Result<(), String>,
+    ) -> Result<u64, String> {
+        let _guard = self.lock.lock().unwrap();
+        let parent = self.sync()?;
+        let content = std::fs::read_to_string(self.path.as_str()).map_err(|e| e.to_string())?;
+        let mut doc: toml_edit::DocumentMut = content.parse().map_err(|e| format!("{}", e))?;
+        change(&mut doc)?;
+
+        self.commit(&content, doc.to_string())?;
+        let version = self.record(&doc.to_string(), actor, action, detail, Some(parent))?;
+        self.reloads.send_replace(version);
+        Ok(version)
+    }
+
+    /// Return to the version the current one was derived from
+    pub fn rollback(&self, actor: &str) -> Result<u64, String> {
+        let _guard = self.lock.lock().unwrap();
+        let current = self.sync()?;
+        let history = self.history();
+        let entry = |v: u64| history.iter().find(|e| e.version == v);
+        let target = entry(current)
+            .and_then(|e| e.parent)
+            .ok_or("no previous config version")?;
+        let content = self.content(target)?;
+        let previous = std::fs::read_to_string(self.path.as_str()).map_err(|e| e.to_string())?;
+        self.commit(&previous, content.clone())?;
+        let parent = entry(target).and_then(|e| e.parent);
+        let version = self.record(&content, actor, "rollback", Some(format!("v{}", target)), parent)?;
+        println!("⏪ Config rolled back to version {} (now version {})", target, version);
+        self.reloads.send_replace(version);
+        Ok(version)
+    }
+
+    /// Apply the live config.toml to running pipelines (filters), after
+    /// validating it; the Arduino connections stay open
+    pub fn reload(&self, actor: &str) -> Result<u64, String> {
+        self.apply(actor, true)
+    }
+
+    fn apply(&self, actor: &str, force: bool) -> Result<u64, String> {
+        let _guard = self.lock.lock().unwrap();
+        let content = std::fs::read_to_string(self.path.as_str()).map_err(|e| e.to_string())?;
+        validate(&content)?;
+        let version = self.sync()?;
+        // File watcher juga melihat tulisan CONFIG_SET sendiri, yang sudah diterapkan
+        if force || version != *self.reloads.borrow() {
+            println!("🔄 Config version {} reloaded by {}", version, actor);
+            self.reloads.send_replace(version);
+        }
+        Ok(version)
+    }
+
+    /// Notified with the new version whenever the config should be re-read
+    pub fn subscribe_reloads(&self) -> watch::Receiver<u64> {
+        self.reloads.subscribe()
+    }
+
+    /// Version of the live config.toml as of the last recorded change
+    pub fn current_version(&self) -> u64 {
+        self.history().last().map_or(1, |e| e.version)
+    }
+
+    /// All recorded versions, oldest first
+    pub fn history(&self) -> Vec<VersionEntry> {
+        std::fs::read_to_string(self.history_path())
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    /// Line diff between two recorded versions
+    pub fn diff(&self, from: u64, to: u64) -> Result<Vec<String>, String> {
+        Ok(diff_lines(&self.content(from)?, &self.content(to)?))
+    }
+
+    fn content(&self, version: u64) -> Result<String, String> {
+        std::fs::read_to_string(self.version_path(version))
+            .map_err(|_| format!("config version {} not found", version))
+    }
+
+    fn version_path(&self, version: u64) -> String {
+        format!("{}/config.v{}.toml", self.versions_dir, version)
+    }
+
+    fn history_path(&self) -> String {
+        format!("{}/history.jsonl", self.versions_dir)
+    }
+
+    /// Make sure the live file is recorded, returning its version
+    fn sync(&self) -> Result<u64, String> {
+        let live = std::fs::read_to_string(self.path.as_str()).map_err(|e| e.to_string())?;
+        match self.history().last() {
+            Some(last) if self.content(last.version).ok().as_deref() == Some(live.as_str()) => Ok(last.version),
+            Some(last) => self.record(&live, "external", "external", None, Some(last.version)),
+            None => self.record(&live, "external", "initial", None, None),
+        }
+    }
+
+    /// Store `content` as the next version and append its history entry
+    fn record(
+        &self,
+        content: &str,
+        actor: &str,
+        action: &str,
+        detail: Option<String>,
+        parent: Option<u64>,
+    ) -> Result<u64, String> {
+        std::fs::create_dir_all(self.versions_dir.as_str()).map_err(|e| e.to_string())?;
+        let version = self.history().last().map_or(1, |e| e.version + 1);
+
+        let before = parent.and_then(|p| self.content(p).ok()).unwrap_or_default();
+        let diff = diff_lines(&before, content);
+        let calibration_version = toml::from_str::<Table>(content)
+            .ok()
+            .and_then(|doc| doc.get("calibration")?.get("version")?.as_str().map(str::to_string));
+
+        let entry = VersionEntry {
+            version,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            actor: actor.to_string(),
+            action: action.to_string(),
+            detail,
+            parent,
+            calibration_version,
+            lines_added: diff.iter().filter(|l| l.starts_with('+')).count(),
+            lines_removed: diff.iter().filter(|l| l.starts_with('-')).count(),
+        };
+
+        std::fs::write(self.version_path(version), content).map_err(|e| e.to_string())?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.history_path())
+            .map_err(|e| e.to_string())?;
+        writeln!(file, "{}", serde_json::to_string(&entry).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+
+        println!("📝 Config version {} recorded ({} by {})", version, action, actor);
+        Ok(version)
+    }
+
+    /// Validate, write, and undo if the result is unreadable
+    fn commit(&self, previous: &str, updated: String) -> Result<(), String> {
+        validate(&updated)?;
+
+        if let Err(e) = self.write(&updated).and_then(|_| {
+            let written = std::fs::read_to_string(self.path.as_str()).map_err(|e| e.to_string())?;
+            validate(&written)
+        }) {
+            eprintln!("❌ Config write failed, restoring previous version: {}", e);
+            self.write(previous)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Atomic replace via temp file + rename
+    fn write(&self, content: &str) -> Result<(), String> {
+        let tmp = format!("{}.tmp", self.path);
+        std::fs::write(&tmp, content).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp, self.path.as_str()).map_err(|e| e.to_string())
+    }
+}
+
+/// Poll config.toml's mtime and reload when it changes; an invalid edit is
+/// reported and the running config kept
+pub async fn watch_file(store: ConfigStore, config: ReloadConfig) {
+    let modified = || std::fs::metadata(store.path.as_str()).and_then(|m| m.modified()).ok();
+    let mut last: Option<SystemTime> = modified();
+    let mut interval = tokio::time::interval(Duration::from_millis(config.poll_ms.max(100)));
+    loop {
+        interval.tick().await;
+        let current = modified();
+        if current == last {
+            continue;
+        }
+        last = current;
+        // Tunggu sebentar, editor kadang menulis file dalam beberapa langkah
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        last = modified().or(last);
+        if let Err(e) = store.apply("external", false) {
+            eprintln!("⚠️ config.toml changed but was not reloaded: {}", e);
+        }
+    }
+}
+
+/// Changed lines between two texts (LCS), as "-<line>: text" / "+<line>: text"
+fn diff_lines(before: &str, after: &str) -> Vec<String> {
+    let a: Vec<&str> = before.lines().collect();
+    let b: Vec<&str> = after.lines().collect();
+
+    // lcs[i][j] = panjang LCS dari a[i..] dan b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(format!("-{}: {}", i + 1, a[i]));
+            i += 1;
+        } else {
+            diff.push(format!("+{}: {}", j + 1, b[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// Set a dotted key to a TOML literal (bare words are taken as strings),
+/// creating missing tables
+pub fn set_value(doc: &mut toml_edit::DocumentMut, key: &str, literal: &str) -> Result<(), String> {
+    // Literal TOML kecuali datetime, supaya "03:30" tetap string
+    let value = match literal.parse::<toml_edit::Value>() {
+        Ok(v) if !v.is_datetime() => v,
+        _ => toml_edit::Value::from(literal),
+    };
+
+    let segments = split_key(key);
+    let Some((last, parents)) = segments.split_last() else {
+        return Err("empty config key".to_string());
+    };
+    let mut table: &mut dyn toml_edit::TableLike = doc.as_table_mut();
+    for segment in parents {
+        if table.get(segment).is_none() {
+            let mut new_table = toml_edit::Table::new();
+            new_table.set_implicit(true);
+            table.insert(segment, toml_edit::Item::Table(new_table));
+        }
+        table = table
+            .get_mut(segment)
+            .and_then(|item| item.as_table_like_mut())
+            .ok_or_else(|| format!("'{}' is not a table", segment))?;
+    }
+    // Ganti nilai di tempat supaya komentar di sekitar key tetap ada
+    match table.get_mut(last).and_then(|item| item.as_value_mut()) {
+        Some(existing) => {
+            let decor = existing.decor().clone();
+            *existing = value;
+            *existing.decor_mut() = decor;
+        }
+        None => {
+            table.insert(last, toml_edit::value(value));
+        }
+    }
+    Ok(())
+}
+
+/// Split `a.b."192.168.1.5".c` into segments, honouring quotes
+fn split_key(key: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in key.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '.' if !quoted => segments.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    segments.push(current);
+    segments
+}
+
+/// GUI commands:
+///   CONFIG_GET [key] [device]
+///   CONFIG_SET <key> <value>
+///   CONFIG_ROLLBACK
+///   CONFIG_VERSIONS
+///   CONFIG_DIFF <from> [to]      versions, `to` defaults to the current one
+///   RELOAD_CONFIG                re-read config.toml into the running pipelines
+pub fn handle_command(store: &ConfigStore, client: &str, args: &[&str]) -> Result<Option<String>, String> {
+    let reply = match args {
+        ["CONFIG_GET", rest @ ..] if rest.len() <= 2 => {
+            let key = rest.first().copied();
+            let value = store.get(key, rest.get(1).copied())?;
+            serde_json::json!({ "type": "config", "key": key, "value": value })
+        }
+        ["CONFIG_SET", key, value @ ..] if !value.is_empty() => {
+            let version = store.set(key, &value.join(" "), client)?;
+            serde_json::json!({
+                "type": "config_updated",
+                "key": key,
+                "version": version,
+                "note": "filter settings apply immediately; other global settings need a restart",
+            })
+        }
+        ["CONFIG_ROLLBACK"] => {
+            let version = store.rollback(client)?;
+            serde_json::json!({ "type": "config_rolled_back", "version": version })
+        }
+        ["RELOAD_CONFIG"] => {
+            let version = store.reload(client)?;
+            serde_json::json!({ "type": "config_reloaded", "version": version })
+        }
+        ["CONFIG_VERSIONS"] => serde_json::json!({
+            "type": "config_versions",
+            "current": store.current_version(),
+            "versions": store.history(),
+        }),
+        ["CONFIG_DIFF", from, rest @ ..] if rest.len() <= 1 => {
+            let parse = |v: &str| v.trim_start_matches('v').parse::<u64>().map_err(|_| format!("invalid version '{}'", v));
+            let from = parse(from)?;
+            let to = rest.first().map(|v| parse(v)).transpose()?.unwrap_or_else(|| store.current_version());
+            serde_json::json!({ "type": "config_diff", "from": from, "to": to, "diff": store.diff(from, to)? })
+        }
+        _ => {
+            return Err(
+                "usage: CONFIG_GET [key] [device] | CONFIG_SET <key> <value> | CONFIG_ROLLBACK | CONFIG_VERSIONS | CONFIG_DIFF <from> [to] | RELOAD_CONFIG"
+                    .to_string(),
+            )
+        }
+    };
+    Ok(Some(reply.to_string()))
+}
@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ConsoleConfig {
+    /// Serve POST /api/console (raw commands straight to the device)
+    #[serde(default)]
+    pub enabled: bool,
+    /// How long device lines are collected after the command
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Upper bound for a per-request `timeout_ms`
+    #[serde(default = "default_max_timeout_ms")]
+    pub max_timeout_ms: u64,
+    #[serde(default = "default_max_length")]
+    pub max_length: usize,
+    /// Allowed command words (first token), empty = any
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Refused command words, checked after `allow`
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Device lines within this window after a GUI command are sent back to
+    /// the issuing client as command_response, 0 = off
+    #[serde(default = "default_response_window_ms")]
+    pub response_window_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 { 1000 }
+fn default_max_timeout_ms() -> u64 { 10_000 }
+fn default_max_length() -> usize { 128 }
+fn default_response_window_ms() -> u64 { 1000 }
+
+impl Default for ConsoleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: default_timeout_ms(),
+            max_timeout_ms: default_max_timeout_ms(),
+            max_length: default_max_length(),
+            allow: Vec::new(),
+            deny: Vec::new(),
+            response_window_ms: default_response_window_ms(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    console: ConsoleConfig,
+}
+
+impl ConsoleConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().console
+    }
+}
+
+/// A non-data line received from a device
+#[derive(Debug, Serialize, Clone)]
+pub struct DeviceLine {
+    pub device: String,
+    pub line: String,
+    pub timestamp: i64,
+}
+
+/// A GUI command waiting for device replies
+struct Pending {
+    id: u64,
+    command: String,
+    replies: mpsc::UnboundedSender<String>,
+    until: Instant,
+}
+
+// ================= Command Console =================
+/// Request/response over the line protocol: the REST console collects the
+/// device's reply lines for a short window, GUI commands get theirs forwarded
+/// tagged with a command id
+#[derive(Clone)]
+pub struct Console {
+    config: Arc<ConsoleConfig>,
+    lines_tx: broadcast::Sender<DeviceLine>,
+    pending: Arc<Mutex<Vec<Pending>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Console {
+    pub fn new(config: ConsoleConfig) -> Self {
+        let (lines_tx, _) = broadcast::channel(256);
+        Self {
+            config: Arc::new(config),
+            lines_tx,
+            pending: Arc::new(Mutex::new(Vec::new())),
+            next_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Every device line that is not sensor data (firmware replies, errors, ...)
+    pub fn observe(&self, device: &str, line: &str) {
+        let line = DeviceLine {
+            device: device.to_string(),
+            line: line.trim().to_string(),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        };
+        self.correlate(&line);
+        if self.lines_tx.receiver_count() > 0 {
+            let _ = self.lines_tx.send(line);
+        }
+    }
+
+    /// Remember a command forwarded for a GUI client; returns its id, or None
+    /// when response capture is off
+    pub fn track(&self, command: &str, replies: mpsc::UnboundedSender<String>) -> Option<u64> {
+        if self.config.response_window_ms == 0 {
+            return None;
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let until = Instant::now() + Duration::from_millis(self.config.response_window_ms);
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|p| p.until > Instant::now() && !p.replies.is_closed());
+        pending.push(Pending { id, command: command.to_string(), replies, until });
+        Some(id)
+    }
+
+    /// A reply belongs to the newest command still inside its window
+    fn correlate(&self, line: &DeviceLine) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|p| p.until > Instant::now() && !p.replies.is_closed());
+        if let Some(command) = pending.last() {
+            let event = serde_json::json!({
+                "type": "command_response",
+                "id": command.id,
+                "command": command.command,
+                "device": line.device,
+                "line": line.line,
+                "timestamp": line.timestamp,
+            });
+            let _ = command.replies.send(event.to_string());
+        }
+    }
+
+    /// Single printable line within the length limit and the allow/deny lists
+    pub fn validate(&self, command: &str) -> Result<String, String> {
+        let command = command.trim();
+        if command.is_empty() {
+            return Err("empty command".to_string());
+        }
+        if command.len() > self.config.max_length {
+            return Err(format!("command longer than {} characters", self.config.max_length));
+        }
+        if command.chars().any(|c| c.is_control()) {
+            return Err("command must be a single line without control characters".to_string());
+        }
+        let word = command.split_whitespace().next().unwrap_or_default();
+        if !self.config.allow.is_empty() && !self.config.allow.iter().any(|a| a == word) {
+            return Err(format!("'{}' is not in [console] allow", word));
+        }
+        if self.config.deny.iter().any(|d| d == word) {
+            return Err(format!("'{}' is denied by [console] deny", word));
+        }
+        Ok(command.to_string())
+    }
+
+    /// Requested timeout, clamped to `max_timeout_ms`
+    pub fn timeout(&self, requested: Option<u64>) -> Duration {
+        Duration::from_millis(requested.unwrap_or(self.config.timeout_ms).min(self.config.max_timeout_ms))
+    }
+
+    /// Send `command` (only to `device` if given) and return the lines
+    /// received until the timeout
+    pub async fn execute(
+        &self,
+        command: &str,
+        device: Option<&str>,
+        timeout: Duration,
+        cmd_tx: &broadcast::Sender<String>,
+    ) -> Vec<DeviceLine> {
+        // Subscribe dulu supaya balasan yang cepat tidak terlewat
+        let mut lines_rx = self.lines_tx.subscribe();
+        let _ = cmd_tx.send(match device {
+            Some(device) => format!("{}:{}", device, command),
+            None => command.to_string(),
+        });
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut lines = Vec::new();
+        loop {
+            match tokio::time::timeout_at(deadline, lines_rx.recv()).await {
+                Ok(Ok(line)) => {
+                    if device.is_none_or(|d| d == line.device) {
+                        lines.push(line);
+                    }
+                }
+                Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+                Ok(Err(broadcast::error::RecvError::Closed)) | Err(_) => break,
+            }
+        }
+        lines
+    }
+}
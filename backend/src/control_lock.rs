@@ -0,0 +1,250 @@
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+use crate::audit::AuditLog;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ControlLockConfig {
+    /// Only the client holding control may send commands
+    #[serde(default)]
+    pub enabled: bool,
+    /// Control is released after this long without a command, 0 = never
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    /// A takeover request is granted if the holder does not answer in time
+    #[serde(default = "default_takeover_timeout_secs")]
+    pub takeover_timeout_secs: u64,
+}
+
+fn default_idle_timeout_secs() -> u64 { 300 }
+fn default_takeover_timeout_secs() -> u64 { 30 }
+
+impl Default for ControlLockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_timeout_secs: default_idle_timeout_secs(),
+            takeover_timeout_secs: default_takeover_timeout_secs(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    control_lock: ControlLockConfig,
+}
+
+impl ControlLockConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().control_lock
+    }
+}
+
+struct Holder {
+    client: String,
+    since: chrono::DateTime<chrono::Utc>,
+    last_active: Instant,
+}
+
+struct Takeover {
+    client: String,
+    requested: Instant,
+}
+
+#[derive(Default)]
+struct LockState {
+    holder: Option<Holder>,
+    takeover: Option<Takeover>,
+}
+
+// ================= GUI Control Lock =================
+/// One GUI client at a time holds control; everyone else is read-only until
+/// control is released, times out, or is handed over after a takeover request
+#[derive(Clone)]
+pub struct ControlLock {
+    config: Arc<ControlLockConfig>,
+    state: Arc<Mutex<LockState>>,
+    audit: AuditLog,
+    data_tx: broadcast::Sender<String>,
+}
+
+impl ControlLock {
+    pub fn new(config: ControlLockConfig, audit: AuditLog, data_tx: broadcast::Sender<String>) -> Self {
+        Self { config: Arc::new(config), state: Arc::new(Mutex::new(LockState::default())), audit, data_tx }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// May `client` send `cmd`? A free lock goes to the first client that
+    /// sends a command; the holder's activity keeps the lock alive.
+    /// CONTROL_* commands are always allowed so others can ask for control.
+    pub fn check(&self, client: &str, cmd: &str) -> Result<(), String> {
+        if !self.config.enabled || cmd.starts_with("CONTROL_") {
+            return Ok(());
+        }
+        self.expire();
+        let mut state = self.state.lock().unwrap();
+        match state.holder.as_mut() {
+            Some(holder) if holder.client == client => {
+                holder.last_active = Instant::now();
+                Ok(())
+            }
+            Some(holder) => Err(format!("control is held by {}; send CONTROL_TAKE to request it", holder.client)),
+            None => {
+                Self::grant(&mut state, client);
+                drop(state);
+                self.audit.record(client, "control_take", serde_json::json!({ "reason": "first_command" }));
+                self.publish();
+                Ok(())
+            }
+        }
+    }
+
+    /// Take free control, or ask the holder to hand it over
+    pub fn take(&self, client: &str) -> Result<(), String> {
+        self.expire();
+        let mut state = self.state.lock().unwrap();
+        match state.holder.as_ref().map(|h| h.client.clone()) {
+            Some(holder) if holder == client => Ok(()),
+            Some(holder) => {
+                if state.takeover.as_ref().is_some_and(|t| t.client != client) {
+                    return Err("another takeover request is pending".to_string());
+                }
+                state.takeover = Some(Takeover { client: client.to_string(), requested: Instant::now() });
+                drop(state);
+                println!("🎛️ {} requests control from {}", client, holder);
+                self.audit.record(client, "control_request", serde_json::json!({ "holder": holder }));
+                self.publish();
+                Ok(())
+            }
+            None => {
+                Self::grant(&mut state, client);
+                drop(state);
+                self.audit.record(client, "control_take", serde_json::json!({ "reason": "request" }));
+                self.publish();
+                Ok(())
+            }
+        }
+    }
+
+    /// Holder gives up control; a pending takeover gets it right away
+    pub fn release(&self, client: &str, reason: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.holder.as_ref().is_none_or(|h| h.client != client) {
+            // Yang keluar mungkin peminta takeover
+            if state.takeover.as_ref().is_some_and(|t| t.client == client) {
+                state.takeover = None;
+                drop(state);
+                self.publish();
+            }
+            return;
+        }
+        state.holder = None;
+        let next = state.takeover.take().map(|t| t.client);
+        if let Some(next) = &next {
+            Self::grant(&mut state, next);
+        }
+        drop(state);
+        println!("🎛️ {} released control ({})", client, reason);
+        self.audit.record(client, "control_release", serde_json::json!({ "reason": reason, "to": next }));
+        self.publish();
+    }
+
+    /// Holder refuses the pending takeover request
+    pub fn deny(&self, client: &str) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        if state.holder.as_ref().is_none_or(|h| h.client != client) {
+            return Err("only the control holder can deny a takeover".to_string());
+        }
+        let requester = state.takeover.take().ok_or("no takeover request pending")?.client;
+        drop(state);
+        self.audit.record(client, "control_deny", serde_json::json!({ "requester": requester }));
+        self.publish();
+        Ok(())
+    }
+
+    /// Drop an idle holder and grant unanswered takeover requests
+    pub fn expire(&self) {
+        if !self.config.enabled {
+            return;
+        }
+        let idle = Duration::from_secs(self.config.idle_timeout_secs);
+        let takeover = Duration::from_secs(self.config.takeover_timeout_secs);
+        let (holder, reason) = {
+            let state = self.state.lock().unwrap();
+            let Some(holder) = state.holder.as_ref() else { return };
+            if state.takeover.as_ref().is_some_and(|t| t.requested.elapsed() >= takeover) {
+                (holder.client.clone(), "takeover timeout")
+            } else if !idle.is_zero() && holder.last_active.elapsed() >= idle {
+                (holder.client.clone(), "idle timeout")
+            } else {
+                return;
+            }
+        };
+        self.release(&holder, reason);
+    }
+
+    fn grant(state: &mut LockState, client: &str) {
+        println!("🎛️ {} has control", client);
+        state.holder = Some(Holder { client: client.to_string(), since: chrono::Utc::now(), last_active: Instant::now() });
+    }
+
+    /// Who holds control and who is waiting for it
+    pub fn status(&self) -> serde_json::Value {
+        let state = self.state.lock().unwrap();
+        serde_json::json!({
+            "enabled": self.config.enabled,
+            "holder": state.holder.as_ref().map(|h| &h.client),
+            "since": state.holder.as_ref().map(|h| h.since.timestamp_millis()),
+            "takeover": state.takeover.as_ref().map(|t| &t.client),
+            "idle_timeout_secs": self.config.idle_timeout_secs,
+        })
+    }
+
+    /// "control" GUI event with the current status
+    pub fn publish(&self) {
+        let mut event = self.status();
+        event["type"] = "control".into();
+        let _ = self.data_tx.send(event.to_string());
+    }
+}
+
+/// GUI commands:
+///   CONTROL_TAKE      take free control or request a takeover
+///   CONTROL_RELEASE   give up control (hands it to a pending requester)
+///   CONTROL_DENY      holder refuses the pending takeover
+///   CONTROL_STATUS
+pub fn handle_command(lock: &ControlLock, client: &str, args: &[&str]) -> Result<(), String> {
+    if !lock.enabled() {
+        return Err("control lock is disabled ([control_lock] enabled = false)".to_string());
+    }
+    match args {
+        ["CONTROL_TAKE"] => lock.take(client),
+        ["CONTROL_RELEASE"] => {
+            lock.release(client, "released");
+            Ok(())
+        }
+        ["CONTROL_DENY"] => lock.deny(client),
+        ["CONTROL_STATUS"] => {
+            lock.expire();
+            lock.publish();
+            Ok(())
+        }
+        _ => Err("usage: CONTROL_TAKE | CONTROL_RELEASE | CONTROL_DENY | CONTROL_STATUS".to_string()),
+    }
+}
+
+/// Timeouts are also enforced while nobody sends commands
+pub async fn run(lock: ControlLock) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        interval.tick().await;
+        lock.expire();
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::{HashMap, VecDeque};
+
+use influxdb2::models::DataPoint;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::influxdb::InfluxDBHandler;
+use crate::{UnifiedSensorData, CHANNEL_NAMES};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CrossCorrelationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Channel pairs, e.g. [["eth", "ethm"], ["voc", "vocm"]]
+    #[serde(default = "default_pairs")]
+    pub pairs: Vec<[String; 2]>,
+    /// Rolling window in samples
+    #[serde(default = "default_window")]
+    pub window: usize,
+    /// Publish after every N frames
+    #[serde(default = "default_publish_every")]
+    pub publish_every: usize,
+}
+
+fn default_pairs() -> Vec<[String; 2]> {
+    vec![
+        ["eth".to_string(), "ethm".to_string()],
+        ["voc".to_string(), "vocm".to_string()],
+    ]
+}
+fn default_window() -> usize { 50 }
+fn default_publish_every() -> usize { 10 }
+
+impl Default for CrossCorrelationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pairs: default_pairs(),
+            window: default_window(),
+            publish_every: default_publish_every(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    cross_correlation: CrossCorrelationConfig,
+}
+
+impl CrossCorrelationConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().cross_correlation
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PairMetric {
+    pub a: String,
+    pub b: String,
+    /// Pearson correlation over the window (None if a channel is flat)
+    pub correlation: Option<f32>,
+    /// mean(a) / mean(b) over the window
+    pub ratio: Option<f32>,
+}
+
+/// (Pearson correlation, mean ratio) of two equally long windows
+pub fn pair_metric(a: &VecDeque<f32>, b: &VecDeque<f32>) -> (Option<f32>, Option<f32>) {
+    // Kanal yang dimatikan (NaN) tidak menghasilkan metrik
+    if a.iter().chain(b).any(|v| v.is_nan()) {
+        return (None, None);
+    }
+    let n = a.len() as f64;
+    let mean_a = a.iter().map(|v| *v as f64).sum::<f64>() / n;
+    let mean_b = b.iter().map(|v| *v as f64).sum::<f64>() / n;
+
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        let dx = *x as f64 - mean_a;
+        let dy = *y as f64 - mean_b;
+        cov += dx * dy;
+        var_a += dx * dx;
+        var_b += dy * dy;
+    }
+
+    let correlation = (var_a > 0.0 && var_b > 0.0).then(|| (cov / (var_a * var_b).sqrt()) as f32);
+    let ratio = (mean_b.abs() > f64::EPSILON).then(|| (mean_a / mean_b) as f32);
+    (correlation, ratio)
+}
+
+// ================= Cross-Correlation =================
+/// Rolling correlation/ratio between channel pairs of different sensor
+/// types, one window per device
+pub async fn run(
+    config: CrossCorrelationConfig,
+    data_tx: broadcast::Sender<String>,
+    frame_tx: broadcast::Sender<UnifiedSensorData>,
+    influx: InfluxDBHandler,
+) {
+    let index = |name: &str| CHANNEL_NAMES.iter().position(|c| *c == name);
+    let pairs: Vec<(usize, usize)> = config
+        .pairs
+        .iter()
+        .filter_map(|[a, b]| match (index(a), index(b)) {
+            (Some(ia), Some(ib)) => Some((ia, ib)),
+            _ => {
+                eprintln!("⚠️ Cross-correlation: unknown channel pair {}/{}", a, b);
+                None
+            }
+        })
+        .collect();
+    if pairs.is_empty() {
+        return;
+    }
+    println!("🔗 Cross-correlation enabled for {} pair(s), window {}", pairs.len(), config.window);
+
+    let window = config.window.max(2);
+    let mut histories: HashMap<String, ([VecDeque<f32>; 7], usize)> = HashMap::new();
+    let mut frame_rx = frame_tx.subscribe();
+
+    loop {
+        let frame = match frame_rx.recv().await {
+            Ok(frame) => frame,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        let (history, count) = histories.entry(frame.device.clone()).or_default();
+        for (buffer, value) in history.iter_mut().zip(frame.channels.values()) {
+            buffer.push_back(value);
+            if buffer.len() > window {
+                buffer.pop_front();
+            }
+        }
+
+        *count += 1;
+        if history[0].len() < window || !count.is_multiple_of(config.publish_every.max(1)) {
+            continue;
+        }
+
+        let metrics: Vec<PairMetric> = pairs
+            .iter()
+            .map(|&(ia, ib)| {
+                let (correlation, ratio) = pair_metric(&history[ia], &history[ib]);
+                PairMetric {
+                    a: CHANNEL_NAMES[ia].to_string(),
+                    b: CHANNEL_NAMES[ib].to_string(),
+                    correlation,
+                    ratio,
+                }
+            })
+            .collect();
+
+        let event = serde_json::json!({
+            "type": "cross_correlation",
+            "device": frame.device,
+            "timestamp": frame.timestamp,
+            "window": window,
+            "pairs": metrics,
+        });
+        let _ = data_tx.send(event.to_string());
+
+        let points = metrics
+            .iter()
+            .filter_map(|m| {
+                let mut builder = DataPoint::builder("cross_correlation")
+                    .tag("source", frame.source.clone())
+                    .tag("pair", format!("{}_{}", m.a, m.b));
+                if let Some(correlation) = m.correlation {
+                    builder = builder.field("correlation", correlation as f64);
+                }
+                if let Some(ratio) = m.ratio {
+                    builder = builder.field("ratio", ratio as f64);
+                }
+                builder.timestamp(frame.timestamp * 1_000_000).build().ok()
+            })
+            .collect();
+        if let Err(e) = influx.write_points(points).await {
+            eprintln!("❌ Cross-correlation write error: {:?}", e);
+        }
+    }
+}
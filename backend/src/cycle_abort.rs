@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::alerts::{frame_values, AlertEvent, AlertState, NotifyReason};
+use crate::channels::Channel;
+use crate::flow::FlowAlarmKind;
+use crate::UnifiedSensorData;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AbortKind {
+    /// Flow/pressure alarm active (see [flow])
+    Flow,
+    /// Channel saturated (see [saturation])
+    Saturation,
+    /// An input outside [min, max]
+    Band,
+}
+
+/// One rule from `[[cycle_abort.rules]]`
+#[derive(Debug, Deserialize, Clone)]
+pub struct AbortRule {
+    pub name: String,
+    pub kind: AbortKind,
+    /// flow: only these alarms; empty = any
+    #[serde(default)]
+    pub alarms: Vec<FlowAlarmKind>,
+    /// saturation: only these channels; empty = any
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// band: channel, virtual channel or input (flow, pressure, humidity, heater_temp, ...)
+    #[serde(default)]
+    pub input: Option<String>,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    /// Condition must hold this long before the cycle is aborted
+    #[serde(default)]
+    pub for_secs: f64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CycleAbortConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Sent to the device when no queued experiment is running on it
+    #[serde(default = "default_command")]
+    pub command: String,
+    #[serde(default)]
+    pub rules: Vec<AbortRule>,
+}
+
+fn default_command() -> String { "STOP_SAMPLING".to_string() }
+
+impl Default for CycleAbortConfig {
+    fn default() -> Self {
+        Self { enabled: false, command: default_command(), rules: Vec::new() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    cycle_abort: CycleAbortConfig,
+}
+
+impl CycleAbortConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc).try_into::<ConfigFile>().unwrap_or_default().cycle_abort
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in &self.rules {
+            let err = |e: &str| Err(format!("cycle_abort rule '{}': {}", rule.name, e));
+            if rule.for_secs < 0.0 {
+                return err("for_secs must not be negative");
+            }
+            if let Some(channel) = rule.channels.iter().find(|c| Channel::from_name(c).is_none()) {
+                return err(&format!("unknown channel '{}'", channel));
+            }
+            if rule.kind == AbortKind::Band {
+                if rule.input.is_none() || (rule.min.is_none() && rule.max.is_none()) {
+                    return err("band needs input and min and/or max");
+                }
+                if rule.min.zip(rule.max).is_some_and(|(min, max)| min >= max) {
+                    return err("min must be below max");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AbortRule {
+    /// Why the rule matches this frame, None if it does not
+    fn check(&self, frame: &UnifiedSensorData, flow_alarms: &[FlowAlarmKind]) -> Option<String> {
+        match self.kind {
+            AbortKind::Flow => flow_alarms
+                .iter()
+                .find(|alarm| self.alarms.is_empty() || self.alarms.contains(alarm))
+                .map(|alarm| format!("flow alarm {:?}", alarm)),
+            AbortKind::Saturation => frame
+                .saturated
+                .iter()
+                .find(|channel| self.channels.is_empty() || self.channels.contains(channel))
+                .map(|channel| format!("{} saturated", channel)),
+            AbortKind::Band => {
+                let input = self.input.as_deref()?;
+                let value = *frame_values(frame).get(input)?;
+                let outside = self.min.is_some_and(|min| value < min) || self.max.is_some_and(|max| value > max);
+                outside.then(|| format!("{} {:.2} outside [{}, {}]", input, value, show(self.min), show(self.max)))
+            }
+        }
+    }
+}
+
+fn show(bound: Option<f64>) -> String {
+    bound.map_or("-".to_string(), |b| b.to_string())
+}
+
+/// "cycle_abort" GUI event
+#[derive(Debug, Serialize, Clone)]
+pub struct CycleAbortEvent {
+    pub device: String,
+    pub rule: String,
+    pub reason: String,
+    /// false once the device starts its next cycle
+    pub active: bool,
+    /// FSM state the cycle was in
+    pub state: i32,
+    pub command: String,
+    /// Queued experiment cancelled instead of sending `command`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub experiment: Option<u64>,
+    /// Session and its cycle number, marked invalid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycle: Option<u32>,
+    pub timestamp: i64,
+}
+
+impl CycleAbortEvent {
+    /// Operator notification through the alert notifiers
+    pub fn alert_event(&self) -> AlertEvent {
+        AlertEvent {
+            name: format!("cycle_abort:{}", self.device),
+            device: self.device.clone(),
+            severity: "warning".to_string(),
+            condition: format!("{}: {}", self.rule, self.reason),
+            state: if self.active { AlertState::Firing } else { AlertState::Resolved },
+            reason: NotifyReason::Transition,
+            active: self.active,
+            escalation_level: 0,
+            acknowledged_by: None,
+            silenced: false,
+            silenced_until: None,
+            targets: Vec::new(),
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+// ================= Cycle Auto-Abort =================
+/// Per device: stops a running cycle (PRE_COND..RECOVERY) once one of the
+/// rules has held for its for_secs. A cycle is aborted at most once; the
+/// rest of it is marked invalid until the next PRE_COND, which ends the
+/// abort (an inactive event).
+pub struct CycleAbort {
+    config: CycleAbortConfig,
+    /// Rule index → since when it holds
+    since: HashMap<usize, i64>,
+    last_state: i32,
+    aborted: Option<CycleAbortEvent>,
+}
+
+impl CycleAbort {
+    pub fn new(config: CycleAbortConfig) -> Option<Self> {
+        (config.enabled && !config.rules.is_empty()).then(|| Self { config, since: HashMap::new(), last_state: 0, aborted: None })
+    }
+
+    /// Whether the current cycle has not been aborted
+    pub fn cycle_valid(&self) -> bool {
+        self.aborted.is_none()
+    }
+
+    pub fn update(&mut self, frame: &UnifiedSensorData, flow_alarms: &[FlowAlarmKind]) -> Option<CycleAbortEvent> {
+        if frame.state == 1 && self.last_state != 1 {
+            if let Some(mut ended) = self.aborted.take() {
+                self.last_state = frame.state;
+                ended.active = false;
+                ended.timestamp = frame.timestamp;
+                return Some(ended);
+            }
+        }
+        self.last_state = frame.state;
+        if self.aborted.is_some() || !(1..=5).contains(&frame.state) {
+            self.since.clear();
+            return None;
+        }
+        for (index, rule) in self.config.rules.iter().enumerate() {
+            let Some(reason) = rule.check(frame, flow_alarms) else {
+                self.since.remove(&index);
+                continue;
+            };
+            let since = *self.since.entry(index).or_insert(frame.timestamp);
+            if (frame.timestamp - since) as f64 >= rule.for_secs * 1000.0 {
+                self.since.clear();
+                let event = CycleAbortEvent {
+                    device: frame.device.clone(),
+                    rule: rule.name.clone(),
+                    reason,
+                    active: true,
+                    state: frame.state,
+                    command: self.config.command.clone(),
+                    experiment: None,
+                    session: None,
+                    cycle: None,
+                    timestamp: frame.timestamp,
+                };
+                self.aborted = Some(event.clone());
+                return Some(event);
+            }
+        }
+        None
+    }
+}
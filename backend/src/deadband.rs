@@ -0,0 +1,139 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::channels::Channel;
+use crate::{UnifiedSensorData, CHANNEL_NAMES};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DeadbandConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Band for channels not listed in `channels`, in channel units; 0 = every change
+    #[serde(default)]
+    pub default: f32,
+    /// channel = band, physical or virtual
+    #[serde(default)]
+    pub channels: BTreeMap<String, f32>,
+    /// Publish a frame at least this often even if nothing moved, ms
+    #[serde(default = "default_max_hold_ms")]
+    pub max_hold_ms: i64,
+}
+
+fn default_max_hold_ms() -> i64 { 5000 }
+
+impl Default for DeadbandConfig {
+    fn default() -> Self {
+        Self { enabled: false, default: 0.0, channels: BTreeMap::new(), max_hold_ms: default_max_hold_ms() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    deadband: DeadbandConfig,
+}
+
+impl DeadbandConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc).try_into::<ConfigFile>().unwrap_or_default().deadband
+    }
+
+    /// Bands are non-negative and name known channels
+    pub fn validate(&self, virtual_channels: &BTreeMap<String, String>) -> Result<(), String> {
+        if self.default < 0.0 {
+            return Err("deadband.default must not be negative".to_string());
+        }
+        for (channel, band) in &self.channels {
+            if !CHANNEL_NAMES.contains(&channel.as_str()) && !virtual_channels.contains_key(channel) {
+                return Err(format!("deadband.channels: unknown channel '{}'", channel));
+            }
+            if *band < 0.0 {
+                return Err(format!("deadband.channels.{} must not be negative", channel));
+            }
+        }
+        Ok(())
+    }
+
+    fn band(&self, channel: &str) -> f32 {
+        self.channels.get(channel).copied().unwrap_or(self.default)
+    }
+}
+
+/// What the GUI last saw of a device
+struct Published {
+    timestamp: i64,
+    state: i32,
+    level: i32,
+    cycle_valid: bool,
+    values: BTreeMap<String, f32>,
+}
+
+// ================= Dead-band =================
+/// Per-device dead-band on GUI frames: a channel keeps its last published
+/// value until it moves by more than its band, and frames where nothing
+/// moved (and the FSM state is unchanged) are not sent at all, up to
+/// `max_hold_ms`. Storage and subsystems always get the real values.
+pub struct Deadband {
+    config: DeadbandConfig,
+    published: Option<Published>,
+}
+
+impl Deadband {
+    pub fn new(config: DeadbandConfig) -> Self {
+        Self { config, published: None }
+    }
+
+    /// GUI JSON for `frame`, None if it is suppressed
+    pub fn publish(&mut self, frame: &UnifiedSensorData) -> Option<String> {
+        if !self.config.enabled {
+            return serde_json::to_string(frame).ok();
+        }
+        let mut shown = frame.clone();
+        let mut moved = false;
+        let mut values = self.published.as_ref().map(|p| p.values.clone()).unwrap_or_default();
+
+        let mut hold = |name: &str, value: &mut f32| {
+            let band = self.config.band(name);
+            match values.get(name) {
+                // Di dalam band: tampilkan nilai lama
+                Some(last) if last.is_finite() && value.is_finite() && (*value - last).abs() <= band && band > 0.0 => {
+                    *value = *last
+                }
+                Some(last) if last.is_nan() && value.is_nan() => {}
+                Some(last) if last == value => {}
+                _ => {
+                    moved = true;
+                    values.insert(name.to_string(), *value);
+                }
+            }
+        };
+        for channel in Channel::ALL {
+            hold(channel.name(), &mut shown.channels[channel]);
+        }
+        for (name, value) in shown.virtual_channels.iter_mut() {
+            hold(name, value);
+        }
+
+        let changed = match &self.published {
+            None => true,
+            Some(p) => {
+                p.state != frame.state
+                    || p.level != frame.level
+                    || p.cycle_valid != frame.cycle_valid
+                    || frame.timestamp - p.timestamp >= self.config.max_hold_ms
+            }
+        };
+        if !moved && !changed {
+            return None;
+        }
+        self.published = Some(Published {
+            timestamp: frame.timestamp,
+            state: frame.state,
+            level: frame.level,
+            cycle_valid: frame.cycle_valid,
+            values,
+        });
+        serde_json::to_string(&shown).ok()
+    }
+}
@@ -0,0 +1,245 @@
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use serde::Deserialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::audit::AuditConfig;
+use crate::baseline::BaselineConfig;
+use crate::burn_in::BurnInConfig;
+use crate::leak_test::LeakTestConfig;
+use crate::provenance::Provenance;
+use crate::trigger::TriggerConfig;
+use crate::AppState;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DebugBundleConfig {
+    /// Raw device lines kept in memory for the next bundle
+    #[serde(default = "default_raw_lines")]
+    pub raw_lines: usize,
+    /// Newest lines of each log file included
+    #[serde(default = "default_log_lines")]
+    pub log_lines: usize,
+    /// Where bundles requested from the GUI are written
+    #[serde(default = "default_dir")]
+    pub dir: String,
+}
+
+fn default_raw_lines() -> usize { 2000 }
+fn default_log_lines() -> usize { 1000 }
+fn default_dir() -> String { ".".to_string() }
+
+impl Default for DebugBundleConfig {
+    fn default() -> Self {
+        Self { raw_lines: default_raw_lines(), log_lines: default_log_lines(), dir: default_dir() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    debug_bundle: DebugBundleConfig,
+}
+
+impl DebugBundleConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().debug_bundle
+    }
+}
+
+/// Config keys whose values never leave the machine
+const SECRET_KEYS: [&str; 5] = ["token", "password", "secret", "sid", "api_key"];
+
+// ================= Debug Capture =================
+/// Recent raw device lines and per-device info, kept for field debug bundles
+#[derive(Clone, Default)]
+pub struct DebugCapture {
+    keep: usize,
+    lines: Arc<Mutex<VecDeque<String>>>,
+    devices: Arc<Mutex<BTreeMap<String, Provenance>>>,
+}
+
+impl DebugCapture {
+    pub fn new(config: &DebugBundleConfig) -> Self {
+        Self { keep: config.raw_lines, ..Default::default() }
+    }
+
+    pub fn record_line(&self, device: &str, line: &str) {
+        if self.keep == 0 {
+            return;
+        }
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() >= self.keep {
+            lines.pop_front();
+        }
+        lines.push_back(format!("{} {} {}", chrono::Utc::now().to_rfc3339(), device, line));
+    }
+
+    pub fn record_device(&self, device: &str, provenance: &Provenance) {
+        self.devices.lock().unwrap().insert(device.to_string(), provenance.clone());
+    }
+}
+
+/// Build the bundle from the running backend's state
+pub fn capture(config_path: &str, state: &AppState) -> anyhow::Result<Vec<u8>> {
+    let capture = &state.debug;
+    let mut zip = files_only(config_path)?;
+    zip.add("raw_lines.txt", capture.lines.lock().unwrap().iter().cloned().collect::<Vec<_>>().join("\n").as_bytes())?;
+    let devices = serde_json::json!({
+        "devices": *capture.devices.lock().unwrap(),
+        "disabled_channels": state.channel_mask.disabled(),
+    });
+    zip.add("device_info.json", serde_json::to_string_pretty(&devices)?.as_bytes())?;
+    let metrics = serde_json::json!({
+        "clients": state.clients.snapshot(),
+        "failover": state.failover.health(),
+        "command_receivers": state.cmd_tx.receiver_count(),
+        "data_receivers": state.data_tx.receiver_count(),
+    });
+    zip.add("metrics.json", serde_json::to_string_pretty(&metrics)?.as_bytes())?;
+    zip.add("diagnostics.json", serde_json::to_string_pretty(&state.diagnostics.report())?.as_bytes())?;
+    Ok(zip.finish())
+}
+
+/// Bundle without a running backend: config, logs and the last diagnostics file
+pub fn capture_offline(config_path: &str) -> anyhow::Result<Vec<u8>> {
+    let mut zip = files_only(config_path)?;
+    let diagnostics = crate::diagnostics::DiagnosticsConfig::load(config_path).path;
+    if let Ok(report) = std::fs::read(&diagnostics) {
+        zip.add("diagnostics.json", &report)?;
+    }
+    Ok(zip.finish())
+}
+
+fn files_only(config_path: &str) -> anyhow::Result<ZipWriter> {
+    let config = DebugBundleConfig::load(config_path);
+    let mut zip = ZipWriter::default();
+    let manifest = serde_json::json!({
+        "created": chrono::Utc::now().to_rfc3339(),
+        "backend_version": env!("CARGO_PKG_VERSION"),
+        "host": std::env::var("HOSTNAME").or_else(|_| std::env::var("COMPUTERNAME")).unwrap_or_default(),
+    });
+    zip.add("manifest.json", serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    let content = std::fs::read_to_string(config_path).unwrap_or_default();
+    zip.add("config.toml", redact(&content).as_bytes())?;
+
+    let logs = [
+        AuditConfig::load(config_path).log_path,
+        BaselineConfig::load(config_path).log_path,
+        BurnInConfig::load(config_path).log_path,
+        LeakTestConfig::load(config_path).log_path,
+        TriggerConfig::load(config_path).log_path,
+    ];
+    for path in logs.iter().filter(|p| !p.is_empty()) {
+        if let Ok(content) = std::fs::read_to_string(path) {
+            let lines: Vec<&str> = content.lines().collect();
+            let tail = &lines[lines.len().saturating_sub(config.log_lines)..];
+            let name = std::path::Path::new(path).file_name().map_or(path.clone(), |n| n.to_string_lossy().into_owned());
+            zip.add(&format!("logs/{}", name), tail.join("\n").as_bytes())?;
+        }
+    }
+    Ok(zip)
+}
+
+/// Blank out credentials (`token = "..."`, `password = "..."`, ...)
+fn redact(config: &str) -> String {
+    config
+        .lines()
+        .map(|line| match line.split_once('=') {
+            Some((key, _)) if !line.trim_start().starts_with('#')
+                && SECRET_KEYS.iter().any(|secret| key.trim().to_lowercase().contains(secret)) =>
+            {
+                format!("{}= \"<redacted>\"", key)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Default file name for a new bundle
+pub fn file_name() -> String {
+    format!("enose-debug-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S"))
+}
+
+/// Write a bundle requested from the GUI into `[debug_bundle] dir`
+pub fn write(config_path: &str, state: &AppState) -> anyhow::Result<String> {
+    let bundle = capture(config_path, state)?;
+    let dir = DebugBundleConfig::load(config_path).dir;
+    let path = std::path::Path::new(&dir).join(file_name()).to_string_lossy().into_owned();
+    std::fs::write(&path, bundle)?;
+    println!("🧰 Debug bundle written to {}", path);
+    Ok(path)
+}
+
+// ================= Zip Writer =================
+/// Minimal in-memory zip archive (deflate, UTF-8 names), enough for a
+/// bundle that opens with any unzip tool
+#[derive(Default)]
+struct ZipWriter {
+    data: Vec<u8>,
+    central: Vec<u8>,
+    entries: u16,
+}
+
+impl ZipWriter {
+    fn add(&mut self, name: &str, content: &[u8]) -> std::io::Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content)?;
+        let compressed = encoder.finish()?;
+        let mut crc = Crc::new();
+        crc.update(content);
+        let (time, date) = dos_time(chrono::Local::now().naive_local());
+        let offset = self.data.len() as u32;
+
+        // Field yang sama dipakai di local header dan central directory
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        common.extend_from_slice(&0x0800u16.to_le_bytes()); // flags: UTF-8 names
+        common.extend_from_slice(&8u16.to_le_bytes()); // deflate
+        common.extend_from_slice(&time.to_le_bytes());
+        common.extend_from_slice(&date.to_le_bytes());
+        common.extend_from_slice(&crc.sum().to_le_bytes());
+        common.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); // extra length
+
+        self.data.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        self.data.extend_from_slice(&common);
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.extend_from_slice(&compressed);
+
+        self.central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        self.central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        self.central.extend_from_slice(&common);
+        self.central.extend_from_slice(&[0; 6]); // comment length, disk, internal attributes
+        self.central.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        self.central.extend_from_slice(&offset.to_le_bytes());
+        self.central.extend_from_slice(name.as_bytes());
+        self.entries += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let offset = self.data.len() as u32;
+        self.data.extend_from_slice(&self.central);
+        self.data.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        self.data.extend_from_slice(&[0; 4]); // disk numbers
+        self.data.extend_from_slice(&self.entries.to_le_bytes());
+        self.data.extend_from_slice(&self.entries.to_le_bytes());
+        self.data.extend_from_slice(&(self.central.len() as u32).to_le_bytes());
+        self.data.extend_from_slice(&offset.to_le_bytes());
+        self.data.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.data
+    }
+}
+
+fn dos_time(t: chrono::NaiveDateTime) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+    let time = ((t.hour() << 11) | (t.minute() << 5) | (t.second() / 2)) as u16;
+    let date = ((((t.year().max(1980) - 1980) as u32) << 9) | (t.month() << 5) | t.day()) as u16;
+    (time, date)
+}
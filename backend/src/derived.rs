@@ -0,0 +1,679 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::channels::{Channel, Channels};
+use crate::features::{SniffCycle, HOLD, PRE_COND, RAMP_UP, RECOVERY};
+use crate::spectral::{self, Peak};
+use crate::UnifiedSensorData;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DerivedConfig {
+    /// Metrics to compute; their dependencies are added automatically
+    #[serde(default = "default_enabled")]
+    pub enabled: Vec<String>,
+    /// Per-metric settings, `[derived.<name>]`
+    #[serde(flatten)]
+    pub settings: BTreeMap<String, toml::Value>,
+}
+
+fn default_enabled() -> Vec<String> {
+    vec!["response".to_string(), "slopes".to_string()]
+}
+
+impl Default for DerivedConfig {
+    fn default() -> Self {
+        Self { enabled: default_enabled(), settings: BTreeMap::new() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    derived: DerivedConfig,
+}
+
+impl DerivedConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc).try_into::<ConfigFile>().unwrap_or_default().derived
+    }
+
+    /// Every enabled metric exists, builds from its settings and the
+    /// dependency graph has no cycles
+    pub fn validate(&self) -> Result<(), String> {
+        DerivedMetrics::new(self, Scope::Frame)?;
+        DerivedMetrics::new(self, Scope::Cycle)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    /// Computed for every frame; values join the frame as extra fields
+    Frame,
+    /// Computed once per finished sniff cycle (see features.rs)
+    Cycle,
+}
+
+/// Values of the metrics computed so far for this frame/cycle; a metric
+/// reads its dependencies' outputs here and adds its own
+pub type Values = BTreeMap<String, f64>;
+
+/// One derived metric. Implement `frame` or `cycle` depending on `scope`,
+/// add a `MetricInfo` and list it in `REGISTRY`.
+pub trait DerivedMetric: Send {
+    fn frame(&mut self, _frame: &UnifiedSensorData, _values: &mut Values) {}
+    fn cycle(&mut self, _cycle: &SniffCycle, _values: &mut Values) {}
+}
+
+pub struct MetricInfo {
+    pub name: &'static str,
+    pub scope: Scope,
+    /// Metrics whose outputs this one reads
+    pub dependencies: &'static [&'static str],
+    /// Built from `[derived.<name>]` (empty table if absent)
+    pub build: fn(&toml::Table) -> Result<Box<dyn DerivedMetric>, String>,
+}
+
+const REGISTRY: &[MetricInfo] = &[
+    Rates::INFO,
+    Ratios::INFO,
+    RollingMean::INFO,
+    Aqi::INFO,
+    LockIn::INFO,
+    Response::INFO,
+    Slopes::INFO,
+    Fingerprint::INFO,
+];
+
+fn settings<'de, T: Deserialize<'de>>(table: &toml::Table, name: &str) -> Result<T, String> {
+    toml::Value::Table(table.clone()).try_into().map_err(|e| format!("derived.{}: {}", name, e))
+}
+
+fn info(name: &str) -> Result<&'static MetricInfo, String> {
+    REGISTRY.iter().find(|m| m.name == name).ok_or_else(|| format!("unknown derived metric '{}'", name))
+}
+
+/// Depth-first over dependencies so they come first; `path` catches cycles
+fn visit(name: &str, path: &mut Vec<&'static str>, ordered: &mut Vec<&'static MetricInfo>) -> Result<(), String> {
+    if ordered.iter().any(|m| m.name == name) {
+        return Ok(());
+    }
+    let metric = info(name)?;
+    if path.contains(&metric.name) {
+        return Err(format!("derived metrics depend on each other: {} -> {}", path.join(" -> "), name));
+    }
+    path.push(metric.name);
+    for dependency in metric.dependencies {
+        if metric.scope == Scope::Frame && info(dependency)?.scope == Scope::Cycle {
+            return Err(format!("frame metric '{}' cannot use cycle metric '{}'", name, dependency));
+        }
+        visit(dependency, path, ordered)?;
+    }
+    path.pop();
+    ordered.push(metric);
+    Ok(())
+}
+
+// ================= Derived Metrics =================
+/// Enabled metrics of one scope in dependency order. Frame-scoped
+/// instances keep per-device state (rolling windows, last values), so each
+/// device pipeline owns its own.
+pub struct DerivedMetrics {
+    metrics: Vec<(&'static str, Box<dyn DerivedMetric>)>,
+}
+
+impl DerivedMetrics {
+    pub fn new(config: &DerivedConfig, scope: Scope) -> Result<Self, String> {
+        let mut ordered = Vec::new();
+        for name in &config.enabled {
+            visit(name, &mut Vec::new(), &mut ordered)?;
+        }
+
+        let empty = toml::Table::new();
+        let metrics = ordered
+            .into_iter()
+            .filter(|m| m.scope == scope)
+            .map(|m| {
+                let table = config.settings.get(m.name).and_then(|v| v.as_table()).unwrap_or(&empty);
+                (m.build)(table).map(|metric| (m.name, metric))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self { metrics })
+    }
+
+    /// Like `new`, logging the error and computing nothing
+    pub fn load(config: &DerivedConfig, scope: Scope) -> Self {
+        Self::new(config, scope).unwrap_or_else(|e| {
+            eprintln!("⚠️ {}", e);
+            Self { metrics: Vec::new() }
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metrics.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.metrics.iter().map(|(name, _)| *name).collect()
+    }
+
+    pub fn frame(&mut self, frame: &UnifiedSensorData) -> Values {
+        let mut values = Values::new();
+        for (_, metric) in self.metrics.iter_mut() {
+            metric.frame(frame, &mut values);
+        }
+        values.retain(|_, v| v.is_finite());
+        values
+    }
+
+    pub fn cycle(&mut self, cycle: &SniffCycle) -> Values {
+        let mut values = Values::new();
+        for (_, metric) in self.metrics.iter_mut() {
+            metric.cycle(cycle, &mut values);
+        }
+        values.retain(|_, v| v.is_finite());
+        values
+    }
+}
+
+// ----- Frame metrics -----
+
+/// `<channel>_rate`: change per second since the previous frame
+struct Rates {
+    last: Option<(i64, Channels)>,
+}
+
+impl Rates {
+    const INFO: MetricInfo = MetricInfo {
+        name: "rates",
+        scope: Scope::Frame,
+        dependencies: &[],
+        build: |_| Ok(Box::new(Rates { last: None })),
+    };
+}
+
+impl DerivedMetric for Rates {
+    fn frame(&mut self, frame: &UnifiedSensorData, values: &mut Values) {
+        if let Some((ts, last)) = self.last {
+            let dt = (frame.timestamp - ts) as f64 / 1000.0;
+            if dt > 0.0 {
+                for (channel, value) in frame.channels.iter() {
+                    values.insert(format!("{}_rate", channel.name()), (value as f64 - last[channel] as f64) / dt);
+                }
+            }
+        }
+        self.last = Some((frame.timestamp, frame.channels));
+    }
+}
+
+/// `<a>_<b>_ratio` for configured channel pairs
+struct Ratios {
+    pairs: Vec<(Channel, Channel)>,
+}
+
+#[derive(Deserialize)]
+struct RatiosSettings {
+    #[serde(default = "default_ratio_pairs")]
+    pairs: Vec<[String; 2]>,
+}
+
+fn default_ratio_pairs() -> Vec<[String; 2]> {
+    vec![["eth".to_string(), "ethm".to_string()], ["voc".to_string(), "vocm".to_string()]]
+}
+
+impl Ratios {
+    const INFO: MetricInfo = MetricInfo {
+        name: "ratios",
+        scope: Scope::Frame,
+        dependencies: &[],
+        build: |table| {
+            let settings: RatiosSettings = settings(table, "ratios")?;
+            let channel =
+                |name: &str| Channel::from_name(name).ok_or(format!("derived.ratios: unknown channel '{}'", name));
+            let pairs = settings.pairs.iter().map(|[a, b]| Ok((channel(a)?, channel(b)?))).collect::<Result<_, String>>()?;
+            Ok(Box::new(Ratios { pairs }))
+        },
+    };
+}
+
+impl DerivedMetric for Ratios {
+    fn frame(&mut self, frame: &UnifiedSensorData, values: &mut Values) {
+        let channels = &frame.channels;
+        for &(a, b) in &self.pairs {
+            if channels[b].abs() > f32::EPSILON {
+                values.insert(format!("{}_{}_ratio", a.name(), b.name()), (channels[a] / channels[b]) as f64);
+            }
+        }
+    }
+}
+
+/// `<channel>_mean` over the last `window` frames
+struct RollingMean {
+    window: usize,
+    history: [VecDeque<f32>; 7],
+}
+
+#[derive(Deserialize)]
+struct RollingMeanSettings {
+    #[serde(default = "default_mean_window")]
+    window: usize,
+}
+
+fn default_mean_window() -> usize { 60 }
+
+impl RollingMean {
+    const INFO: MetricInfo = MetricInfo {
+        name: "rolling_mean",
+        scope: Scope::Frame,
+        dependencies: &[],
+        build: |table| {
+            let settings: RollingMeanSettings = settings(table, "rolling_mean")?;
+            Ok(Box::new(RollingMean { window: settings.window.max(1), history: Default::default() }))
+        },
+    };
+}
+
+impl DerivedMetric for RollingMean {
+    fn frame(&mut self, frame: &UnifiedSensorData, values: &mut Values) {
+        for (channel, value) in frame.channels.iter() {
+            let buffer = &mut self.history[channel.index()];
+            buffer.push_back(value);
+            if buffer.len() > self.window {
+                buffer.pop_front();
+            }
+            let mean = buffer.iter().map(|v| *v as f64).sum::<f64>() / buffer.len() as f64;
+            values.insert(format!("{}_mean", channel.name()), mean);
+        }
+    }
+}
+
+/// US EPA breakpoints: (concentration low, high, index low, high)
+const CO_BREAKPOINTS: [(f64, f64, f64, f64); 6] = [
+    (0.0, 4.4, 0.0, 50.0),
+    (4.5, 9.4, 51.0, 100.0),
+    (9.5, 12.4, 101.0, 150.0),
+    (12.5, 15.4, 151.0, 200.0),
+    (15.5, 30.4, 201.0, 300.0),
+    (30.5, 50.4, 301.0, 500.0),
+];
+const NO2_BREAKPOINTS: [(f64, f64, f64, f64); 6] = [
+    (0.0, 53.0, 0.0, 50.0),
+    (54.0, 100.0, 51.0, 100.0),
+    (101.0, 360.0, 101.0, 150.0),
+    (361.0, 649.0, 151.0, 200.0),
+    (650.0, 1249.0, 201.0, 300.0),
+    (1250.0, 2049.0, 301.0, 500.0),
+];
+
+fn sub_index(concentration: f64, breakpoints: &[(f64, f64, f64, f64)]) -> Option<f64> {
+    if concentration < 0.0 {
+        return None;
+    }
+    // Di atas tabel dianggap 500
+    let Some((c_lo, c_hi, i_lo, i_hi)) = breakpoints.iter().find(|(_, hi, _, _)| concentration <= *hi + 0.05).copied() else {
+        return Some(500.0);
+    };
+    Some(i_lo + (i_hi - i_lo) * (concentration.max(c_lo) - c_lo) / (c_hi - c_lo))
+}
+
+/// `aqi_co`, `aqi_no2` and `aqi` (their maximum) from the rolling means
+struct Aqi {
+    settings: AqiSettings,
+}
+
+#[derive(Deserialize)]
+struct AqiSettings {
+    /// Sensor units → ppm
+    #[serde(default = "default_co_scale")]
+    co_scale: f64,
+    /// Sensor units → ppb
+    #[serde(default = "default_no2_scale")]
+    no2_scale: f64,
+}
+
+fn default_co_scale() -> f64 { 1.0 }
+fn default_no2_scale() -> f64 { 1000.0 }
+
+impl Aqi {
+    const INFO: MetricInfo = MetricInfo {
+        name: "aqi",
+        scope: Scope::Frame,
+        dependencies: &["rolling_mean"],
+        build: |table| Ok(Box::new(Aqi { settings: settings(table, "aqi")? })),
+    };
+}
+
+impl DerivedMetric for Aqi {
+    fn frame(&mut self, _frame: &UnifiedSensorData, values: &mut Values) {
+        let co = values.get("co_mean").and_then(|v| sub_index(v * self.settings.co_scale, &CO_BREAKPOINTS));
+        let no2 = values.get("no2_mean").and_then(|v| sub_index(v * self.settings.no2_scale, &NO2_BREAKPOINTS));
+        if let Some(co) = co {
+            values.insert("aqi_co".to_string(), co.round());
+        }
+        if let Some(no2) = no2 {
+            values.insert("aqi_no2".to_string(), no2.round());
+        }
+        if let Some(aqi) = co.into_iter().chain(no2).reduce(f64::max) {
+            values.insert("aqi".to_string(), aqi.round());
+        }
+    }
+}
+
+/// `<channel>_lockin`: amplitude of the channel's modulation, demodulated
+/// at `frequency` or, when 0, at the dominant frequency found in the last
+/// `window` frames (firmware modulating the heater on its own), reported
+/// as `<channel>_mod_hz` while locked
+struct LockIn {
+    settings: LockInSettings,
+    channels: Vec<(Channel, LockInState)>,
+}
+
+#[derive(Deserialize)]
+struct LockInSettings {
+    /// Empty = every physical channel
+    #[serde(default)]
+    channels: Vec<String>,
+    #[serde(default)]
+    frequency: f64,
+    #[serde(default = "default_lockin_window")]
+    window: usize,
+    #[serde(default = "default_detect_every")]
+    detect_every: usize,
+    #[serde(default = "default_min_hz")]
+    min_hz: f64,
+    #[serde(default = "default_max_hz")]
+    max_hz: f64,
+    #[serde(default = "default_min_snr")]
+    min_snr: f64,
+    #[serde(default = "default_tau_s")]
+    tau_s: f64,
+}
+
+fn default_lockin_window() -> usize { 256 }
+fn default_detect_every() -> usize { 64 }
+fn default_min_hz() -> f64 { 0.05 }
+fn default_max_hz() -> f64 { 5.0 }
+fn default_min_snr() -> f64 { 20.0 }
+fn default_tau_s() -> f64 { 10.0 }
+
+#[derive(Default)]
+struct LockInState {
+    /// (timestamp, value), the last `window` frames
+    history: VecDeque<(i64, f64)>,
+    since_detection: usize,
+    detected: Option<Peak>,
+    /// Low-passed mean, in-phase and quadrature
+    mean: Option<f64>,
+    i: f64,
+    q: f64,
+    /// Reference phase, radians
+    phase: f64,
+    last: Option<i64>,
+}
+
+impl LockIn {
+    const INFO: MetricInfo = MetricInfo {
+        name: "lockin",
+        scope: Scope::Frame,
+        dependencies: &[],
+        build: |table| {
+            let settings: LockInSettings = settings(table, "lockin")?;
+            if settings.frequency < 0.0 || settings.tau_s <= 0.0 {
+                return Err("derived.lockin: frequency must not be negative and tau_s must be positive".to_string());
+            }
+            if settings.window < 16 || settings.min_hz <= 0.0 || settings.max_hz <= settings.min_hz {
+                return Err("derived.lockin: needs window >= 16 and 0 < min_hz < max_hz".to_string());
+            }
+            let channels = match settings.channels.as_slice() {
+                [] => Channel::ALL.to_vec(),
+                names => names
+                    .iter()
+                    .map(|n| Channel::from_name(n).ok_or(format!("derived.lockin: unknown channel '{}'", n)))
+                    .collect::<Result<_, _>>()?,
+            };
+            let channels = channels.into_iter().map(|c| (c, LockInState::default())).collect();
+            Ok(Box::new(LockIn { settings, channels }))
+        },
+    };
+
+    /// Dominant modulation in the history; None below min_snr
+    fn detect(settings: &LockInSettings, history: &VecDeque<(i64, f64)>) -> Option<Peak> {
+        let (first, last) = (history.front()?.0, history.back()?.0);
+        if last <= first {
+            return None;
+        }
+        let sample_rate = (history.len() - 1) as f64 * 1000.0 / (last - first) as f64;
+        let values: Vec<f64> = history.iter().map(|(_, v)| *v).collect();
+        let magnitudes = spectral::magnitudes(&values);
+        let max_hz = settings.max_hz.min(sample_rate / 2.0);
+        spectral::dominant(&magnitudes, spectral::bin_hz(values.len(), sample_rate), settings.min_hz, max_hz)
+            .filter(|peak| peak.snr >= settings.min_snr)
+    }
+}
+
+impl DerivedMetric for LockIn {
+    fn frame(&mut self, frame: &UnifiedSensorData, values: &mut Values) {
+        let settings = &self.settings;
+        for (channel, state) in &mut self.channels {
+            let value = frame.channels[*channel] as f64;
+            if !value.is_finite() {
+                continue;
+            }
+            state.history.push_back((frame.timestamp, value));
+            if state.history.len() > settings.window {
+                state.history.pop_front();
+            }
+
+            if settings.frequency == 0.0 {
+                state.since_detection += 1;
+                if state.history.len() == settings.window && state.since_detection >= settings.detect_every {
+                    state.since_detection = 0;
+                    let detected = Self::detect(settings, &state.history);
+                    match (state.detected, detected) {
+                        (None, Some(peak)) => println!(
+                            "〰️ {} {}: modulation detected at {:.3} Hz (SNR {:.0})",
+                            frame.device,
+                            channel.name(),
+                            peak.frequency,
+                            peak.snr
+                        ),
+                        (Some(_), None) => println!("〰️ {} {}: modulation lost", frame.device, channel.name()),
+                        _ => {}
+                    }
+                    state.detected = detected;
+                }
+            }
+            let frequency = match settings.frequency {
+                f if f > 0.0 => Some(f),
+                _ => state.detected.map(|p| p.frequency),
+            };
+
+            let dt = state.last.map_or(0.0, |last| (frame.timestamp - last).max(0) as f64 / 1000.0);
+            state.last = Some(frame.timestamp);
+            let alpha = 1.0 - (-dt / settings.tau_s).exp();
+            let mean = state.mean.map_or(value, |mean| mean + alpha * (value - mean));
+            state.mean = Some(mean);
+            let Some(frequency) = frequency else { continue };
+            // Fase diakumulasi per dt: frame yang hilang tetap koheren, frekuensi baru tidak melompat
+            state.phase = (state.phase + 2.0 * std::f64::consts::PI * frequency * dt) % std::f64::consts::TAU;
+            let x = value - mean;
+            state.i += alpha * (x * state.phase.cos() - state.i);
+            state.q += alpha * (x * state.phase.sin() - state.q);
+            values.insert(format!("{}_lockin", channel.name()), 2.0 * state.i.hypot(state.q));
+            values.insert(format!("{}_mod_hz", channel.name()), frequency);
+        }
+    }
+}
+
+// ----- Cycle metrics -----
+
+/// `<channel>.baseline` (PRE_COND mean), `.peak` (largest deviation from
+/// baseline in RAMP_UP/HOLD) and `.auc` (area over baseline, units·s)
+struct Response;
+
+impl Response {
+    const INFO: MetricInfo = MetricInfo {
+        name: "response",
+        scope: Scope::Cycle,
+        dependencies: &[],
+        build: |_| Ok(Box::new(Response)),
+    };
+}
+
+impl DerivedMetric for Response {
+    fn cycle(&mut self, cycle: &SniffCycle, values: &mut Values) {
+        for channel in Channel::ALL {
+            let exposure = cycle.series(channel, &[RAMP_UP, HOLD]);
+            if exposure.is_empty() {
+                continue;
+            }
+            let pre_cond = cycle.series(channel, &[PRE_COND]);
+            // Tanpa PRE_COND, sampel pertama RAMP_UP jadi baseline
+            let baseline = if pre_cond.is_empty() {
+                exposure[0].1
+            } else {
+                pre_cond.iter().map(|(_, v)| v).sum::<f64>() / pre_cond.len() as f64
+            };
+            let peak = exposure.iter().map(|(_, v)| v - baseline).fold(0.0, |a: f64, d| if d.abs() > a.abs() { d } else { a });
+            let auc: f64 = exposure.windows(2).map(|w| (w[1].0 - w[0].0) * ((w[0].1 - baseline) + (w[1].1 - baseline)) / 2.0).sum();
+
+            values.insert(format!("{}.baseline", channel.name()), baseline);
+            values.insert(format!("{}.peak", channel.name()), peak);
+            values.insert(format!("{}.auc", channel.name()), auc);
+        }
+    }
+}
+
+/// `<channel>.rise_slope` (RAMP_UP) and `.recovery_slope` (RECOVERY), units/s
+struct Slopes;
+
+impl Slopes {
+    const INFO: MetricInfo = MetricInfo {
+        name: "slopes",
+        scope: Scope::Cycle,
+        dependencies: &[],
+        build: |_| Ok(Box::new(Slopes)),
+    };
+}
+
+impl DerivedMetric for Slopes {
+    fn cycle(&mut self, cycle: &SniffCycle, values: &mut Values) {
+        for channel in Channel::ALL {
+            if let Some(slope) = slope(&cycle.series(channel, &[RAMP_UP])) {
+                values.insert(format!("{}.rise_slope", channel.name()), slope);
+            }
+            if let Some(slope) = slope(&cycle.series(channel, &[RECOVERY])) {
+                values.insert(format!("{}.recovery_slope", channel.name()), slope);
+            }
+        }
+    }
+}
+
+/// `<channel>.t<temp>`: response at each heater temperature step of a
+/// staircase profile, the mean over the settled part of every step at that
+/// temperature (`step_c` bins); nothing for cycles with fewer than two steps
+struct Fingerprint {
+    step_c: f64,
+    settle: f64,
+    min_samples: usize,
+}
+
+#[derive(Deserialize)]
+struct FingerprintSettings {
+    #[serde(default = "default_step_c")]
+    step_c: f64,
+    #[serde(default = "default_settle")]
+    settle: f64,
+    #[serde(default = "default_step_samples")]
+    min_samples: usize,
+}
+
+fn default_step_c() -> f64 { 25.0 }
+fn default_settle() -> f64 { 0.5 }
+fn default_step_samples() -> usize { 3 }
+
+impl Fingerprint {
+    const INFO: MetricInfo = MetricInfo {
+        name: "fingerprint",
+        scope: Scope::Cycle,
+        dependencies: &[],
+        build: |table| {
+            let settings: FingerprintSettings = settings(table, "fingerprint")?;
+            if settings.step_c < 1.0 {
+                return Err("derived.fingerprint: step_c must be at least 1 °C".to_string());
+            }
+            if !(0.0..1.0).contains(&settings.settle) {
+                return Err("derived.fingerprint: settle must be within 0.0..1.0".to_string());
+            }
+            Ok(Box::new(Fingerprint {
+                step_c: settings.step_c,
+                settle: settings.settle,
+                min_samples: settings.min_samples.max(1),
+            }))
+        },
+    };
+
+    /// Runs of consecutive samples at one binned temperature: (temperature, sample indices)
+    fn steps(&self, cycle: &SniffCycle) -> Vec<(i64, Vec<usize>)> {
+        let mut steps: Vec<(i64, Vec<usize>)> = Vec::new();
+        for (i, heater) in cycle.heater.iter().enumerate() {
+            let Some(temp) = heater.filter(|t| t.is_finite()) else { continue };
+            let bin = ((temp as f64 / self.step_c).round() * self.step_c).round() as i64;
+            match steps.last_mut() {
+                Some((last, indices)) if *last == bin => indices.push(i),
+                _ => steps.push((bin, vec![i])),
+            }
+        }
+        // Awal tiap anak tangga (transien pemanasan) dibuang
+        steps
+            .into_iter()
+            .filter_map(|(bin, indices)| {
+                let settled = indices[(indices.len() as f64 * self.settle) as usize..].to_vec();
+                (settled.len() >= self.min_samples).then_some((bin, settled))
+            })
+            .collect()
+    }
+}
+
+impl DerivedMetric for Fingerprint {
+    fn cycle(&mut self, cycle: &SniffCycle, values: &mut Values) {
+        let steps = self.steps(cycle);
+        let mut by_temp: BTreeMap<i64, Vec<usize>> = BTreeMap::new();
+        for (bin, indices) in steps {
+            by_temp.entry(bin).or_default().extend(indices);
+        }
+        if by_temp.len() < 2 {
+            return;
+        }
+        for channel in Channel::ALL {
+            for (temp, indices) in &by_temp {
+                let samples: Vec<f64> = indices
+                    .iter()
+                    .map(|&i| cycle.samples[i].2[channel] as f64)
+                    .filter(|v| v.is_finite())
+                    .collect();
+                if !samples.is_empty() {
+                    let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+                    values.insert(format!("{}.t{}", channel.name(), temp), mean);
+                }
+            }
+        }
+    }
+}
+
+/// Least-squares slope in units per second
+fn slope(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+    let mean_v = points.iter().map(|(_, v)| v).sum::<f64>() / n;
+    let (mut cov, mut var) = (0.0, 0.0);
+    for (t, v) in points {
+        cov += (t - mean_t) * (v - mean_v);
+        var += (t - mean_t).powi(2);
+    }
+    (var > 0.0).then(|| cov / var)
+}
@@ -0,0 +1,126 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// `BEGIN <KIND>` ... `END <KIND>` wraps a multi-line firmware message
+pub const BLOCK_BEGIN: &str = "BEGIN ";
+pub const BLOCK_END: &str = "END ";
+
+/// A block longer than this is a firmware bug or a lost END line
+const MAX_LINES: usize = 1000;
+/// Blocks still open after this are dropped, ms
+const TIMEOUT_MS: i64 = 10_000;
+
+/// What a completed block is published as
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockKind {
+    /// `BEGIN CONFIG`: the firmware's settings, "device_config" event
+    Config,
+    /// `BEGIN CAL` / `BEGIN CALIBRATION`: calibration dump, "calibration_dump" event
+    Calibration,
+    /// Anything else, "device_block" event
+    Other(String),
+}
+
+impl BlockKind {
+    fn parse(name: &str) -> Self {
+        match name.to_ascii_uppercase().as_str() {
+            "CONFIG" => Self::Config,
+            "CAL" | "CALIBRATION" => Self::Calibration,
+            _ => Self::Other(name.to_string()),
+        }
+    }
+
+    fn event_type(&self) -> &'static str {
+        match self {
+            Self::Config => "device_config",
+            Self::Calibration => "calibration_dump",
+            Self::Other(_) => "device_block",
+        }
+    }
+}
+
+/// One assembled multi-line message
+#[derive(Debug, Serialize, Clone)]
+pub struct DeviceBlock {
+    pub device: String,
+    /// Name after BEGIN, as sent
+    pub name: String,
+    #[serde(skip)]
+    pub kind: BlockKind,
+    /// `key=value` / `key: value` lines
+    pub entries: BTreeMap<String, String>,
+    /// Every line between BEGIN and END, trimmed
+    pub lines: Vec<String>,
+    pub started_at: i64,
+    pub timestamp: i64,
+}
+
+impl DeviceBlock {
+    /// GUI event for the block, typed by its kind
+    pub fn event(&self) -> serde_json::Value {
+        let mut event = serde_json::to_value(self).unwrap_or_default();
+        event["type"] = self.kind.event_type().into();
+        event
+    }
+}
+
+/// What the parser did with a line
+pub enum BlockLine {
+    /// Not part of a block, handle it as usual
+    Pass,
+    /// Taken into the open block
+    Consumed,
+    /// END line: the completed block
+    Complete(DeviceBlock),
+}
+
+// ================= Block Parser =================
+/// Per connection: collects the lines of a `BEGIN`/`END` message so they
+/// arrive as one event instead of one log line each. A block is only
+/// published once its END arrives; a new BEGIN, too many lines or a
+/// timeout drop it. SENSOR frames interleaved by the firmware are not
+/// passed here and keep flowing.
+#[derive(Default)]
+pub struct BlockParser {
+    open: Option<DeviceBlock>,
+}
+
+impl BlockParser {
+    pub fn feed(&mut self, device: &str, line: &str, now: i64) -> BlockLine {
+        let line = line.trim();
+        if let Some(block) = self.open.as_ref() {
+            if now - block.started_at > TIMEOUT_MS || block.lines.len() >= MAX_LINES {
+                eprintln!("⚠️ {} {} block dropped after {} lines without END", device, block.name, block.lines.len());
+                self.open = None;
+            }
+        }
+        if let Some(name) = line.strip_prefix(BLOCK_BEGIN).map(str::trim).filter(|n| !n.is_empty()) {
+            if let Some(block) = self.open.take() {
+                eprintln!("⚠️ {} {} block dropped: BEGIN {} before its END", device, block.name, name);
+            }
+            self.open = Some(DeviceBlock {
+                device: device.to_string(),
+                name: name.to_string(),
+                kind: BlockKind::parse(name),
+                entries: BTreeMap::new(),
+                lines: Vec::new(),
+                started_at: now,
+                timestamp: now,
+            });
+            return BlockLine::Consumed;
+        }
+        let Some(mut block) = self.open.take() else {
+            return BlockLine::Pass;
+        };
+        if line.strip_prefix(BLOCK_END).is_some_and(|name| name.trim().eq_ignore_ascii_case(&block.name)) {
+            block.timestamp = now;
+            return BlockLine::Complete(block);
+        }
+        if let Some((key, value)) = line.split_once('=').or_else(|| line.split_once(':')) {
+            block.entries.insert(key.trim().to_string(), value.trim().to_string());
+        }
+        block.lines.push(line.to_string());
+        self.open = Some(block);
+        BlockLine::Consumed
+    }
+}
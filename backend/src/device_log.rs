@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::recording::file_safe;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DeviceLogConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// One log per device: <dir>/<device>.log, rotated to <device>.log.1 ...
+    #[serde(default = "default_dir")]
+    pub dir: String,
+    /// Rotate once the current file reaches this size
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+    /// Rotated files kept per device
+    #[serde(default = "default_files")]
+    pub files: usize,
+}
+
+fn default_enabled() -> bool { true }
+fn default_dir() -> String { "device_logs".to_string() }
+fn default_max_bytes() -> u64 { 1_000_000 }
+fn default_files() -> usize { 5 }
+
+impl Default for DeviceLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            dir: default_dir(),
+            max_bytes: default_max_bytes(),
+            files: default_files(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    device_log: DeviceLogConfig,
+}
+
+impl DeviceLogConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().device_log
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_bytes < 1024 {
+            return Err("device_log.max_bytes must be at least 1024".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Lines requested when the command / endpoint gives no count
+pub const DEFAULT_LINES: usize = 200;
+
+/// One logged line, as returned by DEVICE_LOG and GET /api/devices/{id}/log
+#[derive(Debug, Serialize, Clone)]
+pub struct LogLine {
+    pub timestamp: String,
+    pub line: String,
+}
+
+struct OpenLog {
+    file: File,
+    size: u64,
+}
+
+// ================= Device Log =================
+/// Persists what devices print besides data (boot messages, errors, command
+/// replies) per device with timestamps, rotating by size, so it can be
+/// read back remotely instead of only scrolling past on stdout.
+#[derive(Clone)]
+pub struct DeviceLog {
+    config: Arc<DeviceLogConfig>,
+    open: Arc<Mutex<HashMap<String, OpenLog>>>,
+}
+
+impl DeviceLog {
+    pub fn new(config: DeviceLogConfig) -> Self {
+        Self { config: Arc::new(config), open: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    fn path(&self, device: &str, index: usize) -> PathBuf {
+        let name = match index {
+            0 => format!("{}.log", file_safe(device)),
+            n => format!("{}.log.{}", file_safe(device), n),
+        };
+        PathBuf::from(&self.config.dir).join(name)
+    }
+
+    pub fn record(&self, device: &str, line: &str) {
+        if !self.config.enabled {
+            return;
+        }
+        let entry = format!("{} {}\n", chrono::Utc::now().to_rfc3339(), line.trim_end());
+        let mut open = self.open.lock().unwrap();
+        if let Err(e) = self.append(&mut open, device, &entry) {
+            eprintln!("❌ Failed to write device log for {}: {}", device, e);
+            open.remove(device);
+        }
+    }
+
+    fn append(&self, open: &mut HashMap<String, OpenLog>, device: &str, entry: &str) -> std::io::Result<()> {
+        if open.get(device).is_some_and(|log| log.size + entry.len() as u64 > self.config.max_bytes) {
+            open.remove(device);
+            self.rotate(device)?;
+        }
+        let log = match open.entry(device.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(vacant) => {
+                std::fs::create_dir_all(&self.config.dir)?;
+                let file = OpenOptions::new().create(true).append(true).open(self.path(device, 0))?;
+                let size = file.metadata()?.len();
+                vacant.insert(OpenLog { file, size })
+            }
+        };
+        log.file.write_all(entry.as_bytes())?;
+        log.size += entry.len() as u64;
+        Ok(())
+    }
+
+    /// <device>.log → .log.1 → ... → .log.<files>, the oldest is dropped
+    fn rotate(&self, device: &str) -> std::io::Result<()> {
+        if self.config.files == 0 {
+            return std::fs::remove_file(self.path(device, 0));
+        }
+        let _ = std::fs::remove_file(self.path(device, self.config.files));
+        for index in (0..self.config.files).rev() {
+            let from = self.path(device, index);
+            if from.exists() {
+                std::fs::rename(from, self.path(device, index + 1))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The last `lines` logged lines of a device, oldest first
+    pub fn recent(&self, device: &str, lines: usize) -> Result<Vec<LogLine>, String> {
+        let mut recent = VecDeque::new();
+        for index in 0..=self.config.files {
+            if recent.len() >= lines {
+                break;
+            }
+            let Ok(file) = File::open(self.path(device, index)) else {
+                if index == 0 {
+                    return Err(format!("no log for device '{}'", device));
+                }
+                break;
+            };
+            let mut tail = VecDeque::new();
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                if tail.len() == lines - recent.len() {
+                    tail.pop_front();
+                }
+                let (timestamp, line) = line.split_once(' ').unwrap_or(("", &line));
+                tail.push_back(LogLine { timestamp: timestamp.to_string(), line: line.to_string() });
+            }
+            while let Some(line) = tail.pop_back() {
+                recent.push_front(line);
+            }
+        }
+        Ok(recent.into())
+    }
+}
+
+/// GUI command: DEVICE_LOG <device> [lines]
+pub fn handle_command(log: &DeviceLog, args: &[&str]) -> Result<Option<String>, String> {
+    let (device, lines) = match args {
+        ["DEVICE_LOG", device] => (*device, DEFAULT_LINES),
+        ["DEVICE_LOG", device, lines] => (*device, lines.parse().map_err(|_| format!("invalid line count '{}'", lines))?),
+        _ => return Err("usage: DEVICE_LOG <device> [lines]".to_string()),
+    };
+    let lines = log.recent(device, lines)?;
+    Ok(Some(serde_json::json!({ "type": "device_log", "device": device, "lines": lines }).to_string()))
+}
@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// `ID:<device id>` handshake line
+pub const ID_PREFIX: &str = "ID:";
+/// `CAPS:<command>,<command>,...` reply listing the firmware's commands
+pub const CAPS_PREFIX: &str = "CAPS:";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DeviceRegistryConfig {
+    /// Drop SENSOR frames from a connection until it has announced an ID
+    #[serde(default)]
+    pub require_id: bool,
+    /// Commands the device's firmware accepts, for GUI control panels
+    #[serde(default = "default_commands")]
+    pub commands: Vec<String>,
+    /// Sent on connect to ask the firmware for its commands, "" = use `commands`
+    #[serde(default)]
+    pub query_command: String,
+}
+
+fn default_commands() -> Vec<String> { vec!["START_SAMPLING".to_string(), "STOP_SAMPLING".to_string()] }
+
+impl Default for DeviceRegistryConfig {
+    fn default() -> Self {
+        Self { require_id: false, commands: default_commands(), query_command: String::new() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    device_registry: DeviceRegistryConfig,
+}
+
+impl DeviceRegistryConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc).try_into::<ConfigFile>().unwrap_or_default().device_registry
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DeviceInfo {
+    /// Announced ID, or the transport address until then
+    pub device: String,
+    pub identified: bool,
+    /// Peer address or serial port
+    pub transport: String,
+    pub connected_at: i64,
+    pub frames: u64,
+    pub last_frame: Option<i64>,
+    /// Commands the GUI may offer for this device
+    pub commands: Vec<String>,
+    /// "config", or "firmware" once the device answered `query_command`
+    pub commands_source: &'static str,
+}
+
+// ================= Device Registry =================
+/// Connected Arduinos by identity. Each connection starts out named by its
+/// address and takes the ID from an `ID:<id>` line or a `SENSOR:<id>:...`
+/// frame; GUI commands written `<id>:<command>` go to that device only.
+#[derive(Clone)]
+pub struct DeviceRegistry {
+    config: Arc<DeviceRegistryConfig>,
+    devices: Arc<Mutex<BTreeMap<u64, DeviceInfo>>>,
+    next_id: Arc<AtomicU64>,
+    data_tx: broadcast::Sender<String>,
+}
+
+/// One connection's entry in the registry
+#[derive(Clone)]
+pub struct DeviceLink {
+    id: u64,
+    registry: DeviceRegistry,
+}
+
+impl DeviceRegistry {
+    pub fn new(config: DeviceRegistryConfig, data_tx: broadcast::Sender<String>) -> Self {
+        Self {
+            config: Arc::new(config),
+            devices: Arc::new(Mutex::new(BTreeMap::new())),
+            next_id: Arc::new(AtomicU64::new(0)),
+            data_tx,
+        }
+    }
+
+    pub fn require_id(&self) -> bool {
+        self.config.require_id
+    }
+
+    pub fn connect(&self, device: &str, transport: &str) -> DeviceLink {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.devices.lock().unwrap().insert(
+            id,
+            DeviceInfo {
+                device: device.to_string(),
+                identified: false,
+                transport: transport.to_string(),
+                connected_at: chrono::Utc::now().timestamp_millis(),
+                frames: 0,
+                last_frame: None,
+                commands: self.config.commands.clone(),
+                commands_source: "config",
+            },
+        );
+        self.publish();
+        DeviceLink { id, registry: self.clone() }
+    }
+
+    pub fn snapshot(&self) -> Vec<DeviceInfo> {
+        self.devices.lock().unwrap().values().cloned().collect()
+    }
+
+    /// "devices" GUI event
+    pub fn publish(&self) {
+        let event = serde_json::json!({ "type": "devices", "devices": self.snapshot() });
+        let _ = self.data_tx.send(event.to_string());
+    }
+
+    /// Whether a device with this ID is connected
+    pub fn connected(&self, device: &str) -> bool {
+        self.devices.lock().unwrap().values().any(|info| info.device == device)
+    }
+
+    /// ID of the connected device, if there is exactly one
+    pub fn only(&self) -> Option<String> {
+        let devices = self.devices.lock().unwrap();
+        let mut ids = devices.values().map(|info| info.device.clone());
+        let first = ids.next()?;
+        ids.next().is_none().then_some(first)
+    }
+
+    /// Device a device-scoped routine runs on: the named one if connected, otherwise the only connected device
+    pub fn resolve(&self, device: Option<&str>) -> Result<String, String> {
+        match device {
+            Some(device) if self.connected(device) => Ok(device.to_string()),
+            Some(device) => Err(format!("device '{}' is not connected", device)),
+            None => self.only().ok_or_else(|| "no single connected device; name the device".to_string()),
+        }
+    }
+
+    /// Whether a connected device lists `command` (its first word)
+    pub fn supports(&self, device: &str, command: &str) -> bool {
+        let name = command.split_whitespace().next().unwrap_or_default();
+        let devices = self.devices.lock().unwrap();
+        devices.values().any(|info| info.device == device && info.commands.iter().any(|c| c == name))
+    }
+
+    /// `<device>:<command>` as (device, command), whether or not the device
+    /// is connected: the ID is what precedes the first ':' of the first
+    /// word. Connected devices named by an address containing ':' (IPv6)
+    /// are matched whole first.
+    pub fn addressed<'a>(&self, command: &'a str) -> Option<(String, &'a str)> {
+        let connected = self.devices.lock().unwrap().values().find_map(|info| {
+            let rest = command.strip_prefix(info.device.as_str())?.strip_prefix(':')?;
+            Some((info.device.clone(), rest))
+        });
+        connected.or_else(|| {
+            let (device, rest) = command.split_once(':')?;
+            (!device.is_empty() && !device.contains(char::is_whitespace)).then(|| (device.to_string(), rest))
+        })
+    }
+}
+
+impl DeviceLink {
+    pub fn device(&self) -> String {
+        self.registry.devices.lock().unwrap().get(&self.id).map(|info| info.device.clone()).unwrap_or_default()
+    }
+
+    pub fn identified(&self) -> bool {
+        self.registry.devices.lock().unwrap().get(&self.id).is_some_and(|info| info.identified)
+    }
+
+    /// Take the announced ID; refused while another connection uses it
+    pub fn identify(&self, device: &str) -> Result<(), String> {
+        let device = device.trim();
+        if device.is_empty() || device.contains(char::is_whitespace) || device.contains(':') {
+            return Err(format!("invalid device id '{}'", device));
+        }
+        {
+            let mut devices = self.registry.devices.lock().unwrap();
+            if devices.iter().any(|(id, info)| *id != self.id && info.device == device) {
+                return Err(format!("device id '{}' is already connected", device));
+            }
+            let info = devices.get_mut(&self.id).ok_or("connection closed")?;
+            info.device = device.to_string();
+            info.identified = true;
+        }
+        self.registry.publish();
+        Ok(())
+    }
+
+    pub fn frame(&self, timestamp: i64) {
+        if let Some(info) = self.registry.devices.lock().unwrap().get_mut(&self.id) {
+            info.frames += 1;
+            info.last_frame = Some(timestamp);
+        }
+    }
+
+    /// What to write to this device for a broadcast command: addressed
+    /// commands lose their prefix and only reach their device (none at all
+    /// when that device is not connected)
+    pub fn route(&self, command: &str) -> Option<String> {
+        match self.registry.addressed(command) {
+            Some((device, rest)) => (device == self.device()).then(|| rest.to_string()),
+            None => Some(command.to_string()),
+        }
+    }
+
+    /// Load the device profile's command list (unless the firmware already
+    /// reported one); returns the query command to send, if any
+    pub fn configure_commands(&self, path: &str) -> Option<String> {
+        let device = self.device();
+        let config = DeviceRegistryConfig::load(path, Some(&device));
+        {
+            let mut devices = self.registry.devices.lock().unwrap();
+            let info = devices.get_mut(&self.id)?;
+            if info.commands_source == "config" {
+                info.commands = config.commands;
+            }
+        }
+        self.registry.publish();
+        (!config.query_command.is_empty()).then_some(config.query_command)
+    }
+
+    /// `CAPS:` reply (prefix stripped), replaces the configured commands
+    pub fn report_commands(&self, list: &str) {
+        let commands: Vec<String> =
+            list.split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect();
+        println!("🎛️ {} supports {}", self.device(), commands.join(", "));
+        if let Some(info) = self.registry.devices.lock().unwrap().get_mut(&self.id) {
+            info.commands = commands;
+            info.commands_source = "firmware";
+        }
+        self.registry.publish();
+    }
+
+    pub fn disconnect(&self) {
+        self.registry.devices.lock().unwrap().remove(&self.id);
+        self.registry.publish();
+    }
+}
+
+/// Split `SENSOR:<id>:<values>` (prefix already stripped) into id and values;
+/// frames without an ID are returned unchanged
+pub fn split_sensor_id(data: &str) -> (Option<&str>, &str) {
+    match data.split_once(':') {
+        Some((id, values)) if !id.is_empty() && id.trim().parse::<f32>().is_err() => (Some(id.trim()), values),
+        _ => (None, data),
+    }
+}
@@ -0,0 +1,242 @@
+use influxdb2::models::health::Status;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+use crate::influxdb::InfluxDBHandler;
+use crate::plugins::PluginConfig;
+use crate::provenance::CalibrationConfig;
+use crate::scripting::ScriptingConfig;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DiagnosticsConfig {
+    /// Startup report, rewritten whenever a listener binds
+    #[serde(default = "default_path")]
+    pub path: String,
+}
+
+fn default_path() -> String { "diagnostics.json".to_string() }
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self { path: default_path() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    diagnostics: DiagnosticsConfig,
+}
+
+impl DiagnosticsConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().diagnostics
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, ok: bool, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), ok, detail: detail.into() }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct Report {
+    pub generated_at: i64,
+    pub backend_version: String,
+    /// Config version, filter chain and which optional subsystems are on
+    pub config: serde_json::Value,
+    pub ports: Vec<Check>,
+    pub storage: Vec<Check>,
+    pub calibration: Vec<Check>,
+    /// Processing modules loaded into the pipeline (WASM plugins, scripts)
+    pub models: Vec<Check>,
+    /// Where frames come from: Arduino listener, dialed endpoints, edges
+    pub data_sources: Vec<Check>,
+}
+
+impl Report {
+    pub fn ok(&self) -> bool {
+        [&self.ports, &self.storage, &self.calibration, &self.models].iter().all(|checks| checks.iter().all(|c| c.ok))
+    }
+}
+
+// ================= Startup Diagnostics =================
+/// Structured startup self-check, printed once, written as JSON to
+/// `[diagnostics] path` and served at GET /api/diagnostics
+#[derive(Clone)]
+pub struct Diagnostics {
+    path: Arc<String>,
+    report: Arc<Mutex<Report>>,
+}
+
+impl Diagnostics {
+    /// Run the static checks; listeners report in later via `bind`
+    pub async fn run(config_path: &str, config_version: u64, store_path: &str, influx: &InfluxDBHandler) -> Self {
+        let doc = crate::config::load_document(config_path, None);
+        let report = Report {
+            generated_at: chrono::Utc::now().timestamp_millis(),
+            backend_version: env!("CARGO_PKG_VERSION").to_string(),
+            config: config_summary(&doc, config_version, influx),
+            ports: Vec::new(),
+            storage: storage_checks(store_path, influx).await,
+            calibration: calibration_checks(config_path),
+            models: model_checks(config_path),
+            data_sources: data_source_checks(config_path, &doc),
+        };
+        let diagnostics = Self {
+            path: Arc::new(DiagnosticsConfig::load(config_path).path),
+            report: Arc::new(Mutex::new(report)),
+        };
+        diagnostics.print();
+        diagnostics.write();
+        diagnostics
+    }
+
+    /// Bind a listener and record the outcome in the report
+    pub async fn bind(&self, name: &str, addr: &str) -> std::io::Result<TcpListener> {
+        let result = TcpListener::bind(addr).await;
+        let check = match &result {
+            Ok(_) => Check::new(name, true, addr),
+            Err(e) => Check::new(name, false, format!("{}: {}", addr, e)),
+        };
+        if !check.ok {
+            eprintln!("❌ Port {} {}", name, check.detail);
+        }
+        {
+            let mut report = self.report.lock().unwrap();
+            report.ports.retain(|c| c.name != name);
+            report.ports.push(check);
+        }
+        self.write();
+        result
+    }
+
+    pub fn report(&self) -> Report {
+        self.report.lock().unwrap().clone()
+    }
+
+    fn write(&self) {
+        let json = serde_json::to_string_pretty(&self.report()).unwrap_or_default();
+        if let Err(e) = std::fs::write(self.path.as_str(), json) {
+            eprintln!("❌ Failed to write diagnostics {}: {}", self.path, e);
+        }
+    }
+
+    fn print(&self) {
+        let report = self.report();
+        let status = if report.ok() { "all checks passed" } else { "CHECKS FAILED" };
+        println!("🩺 Startup diagnostics (v{}, config v{}): {}", report.backend_version, report.config["version"], status);
+        if let Some(subsystems) = report.config["enabled"].as_array() {
+            let names: Vec<&str> = subsystems.iter().filter_map(|s| s.as_str()).collect();
+            println!("   enabled: {}", if names.is_empty() { "-".to_string() } else { names.join(", ") });
+        }
+        for (section, checks) in [
+            ("storage", &report.storage),
+            ("calibration", &report.calibration),
+            ("models", &report.models),
+            ("sources", &report.data_sources),
+        ] {
+            for check in checks {
+                println!("   {} {} {}: {}", if check.ok { "✅" } else { "❌" }, section, check.name, check.detail);
+            }
+        }
+        println!("   report: {}", self.path);
+    }
+}
+
+fn config_summary(doc: &toml::Table, version: u64, influx: &InfluxDBHandler) -> serde_json::Value {
+    let enabled: Vec<&String> = doc
+        .iter()
+        .filter(|(_, value)| value.get("enabled").and_then(|e| e.as_bool()) == Some(true))
+        .map(|(name, _)| name)
+        .collect();
+    let filter = |key: &str| doc.get(key).map(|v| serde_json::to_value(v).unwrap_or_default());
+    serde_json::json!({
+        "version": version,
+        "filter": {
+            "type": filter("filter"),
+            "window_size": filter("window_size"),
+            "sine_enabled": filter("sine_enabled"),
+        },
+        "enabled": enabled,
+        "influxdb": { "org": influx.org(), "bucket": influx.bucket() },
+    })
+}
+
+async fn storage_checks(store_path: &str, influx: &InfluxDBHandler) -> Vec<Check> {
+    let local = match crate::store::status(store_path) {
+        Ok(status) if status.pending.is_empty() => Check::new("local_store", true, format!("{} schema v{}", store_path, status.current)),
+        Ok(status) => Check::new("local_store", false, format!("{} schema v{} of v{}", store_path, status.current, status.latest)),
+        Err(e) => Check::new("local_store", false, e.to_string()),
+    };
+    let timeout = std::time::Duration::from_secs(3);
+    let influx = match tokio::time::timeout(timeout, influx.client().health()).await {
+        Ok(Ok(health)) => Check::new(
+            "influxdb",
+            health.status == Status::Pass,
+            format!("{} {}", health.version.unwrap_or_default(), health.message.unwrap_or_default()).trim().to_string(),
+        ),
+        Ok(Err(e)) => Check::new("influxdb", false, e.to_string()),
+        Err(_) => Check::new("influxdb", false, "no response within 3 s"),
+    };
+    vec![local, influx]
+}
+
+fn calibration_checks(config_path: &str) -> Vec<Check> {
+    let describe = |calibration: CalibrationConfig| {
+        let version = calibration.version.unwrap_or_else(|| "unversioned".to_string());
+        let baseline = if calibration.baseline.is_some() { "baseline set" } else { "no baseline" };
+        format!("{}, {}", version, baseline)
+    };
+    let mut checks = vec![Check::new("default", true, describe(CalibrationConfig::load(config_path, None)))];
+    // load_document() sudah membuang [devices], baca ulang daftar device dari file
+    let devices = std::fs::read_to_string(config_path)
+        .ok()
+        .and_then(|content| toml::from_str::<toml::Table>(&content).ok())
+        .and_then(|raw| raw.get("devices").and_then(|d| d.as_table()).map(|d| d.keys().cloned().collect::<Vec<_>>()))
+        .unwrap_or_default();
+    for device in devices {
+        checks.push(Check::new(device.clone(), true, describe(CalibrationConfig::load(config_path, Some(&device)))));
+    }
+    checks
+}
+
+fn model_checks(config_path: &str) -> Vec<Check> {
+    let file = |kind: &str, path: &str| {
+        let exists = std::path::Path::new(path).is_file();
+        Check::new(format!("{} {}", kind, path), exists, if exists { "found" } else { "missing" })
+    };
+    let plugins = PluginConfig::load(config_path, None).wasm;
+    let scripts = ScriptingConfig::load(config_path).scripts;
+    plugins.iter().map(|p| file("plugin", p)).chain(scripts.iter().map(|s| file("script", s))).collect()
+}
+
+fn data_source_checks(config_path: &str, doc: &toml::Table) -> Vec<Check> {
+    let count = |key: &str| doc.get(key).and_then(|v| v.as_array()).map_or(0, |a| a.len());
+    let enabled = |key: &str| doc.get(key).and_then(|v| v.get("enabled")).and_then(|e| e.as_bool()) == Some(true);
+    let arduino = crate::arduino_link::ArduinoConfig::load(config_path);
+    let mut checks = Vec::new();
+    if arduino.tcp_enabled {
+        checks.push(Check::new("arduino_listener", true, format!("accepting Arduino connections on {}", arduino.tcp_bind)));
+    }
+    if arduino.serial_enabled {
+        checks.push(Check::new("arduino_serial", true, format!("{} @ {} baud", arduino.serial_port, arduino.serial_baud)));
+    }
+    if count("endpoints") > 0 {
+        checks.push(Check::new("endpoints", true, format!("{} dialed device(s)", count("endpoints"))));
+    }
+    if enabled("aggregator") {
+        checks.push(Check::new("aggregator", true, "frames from edge backends"));
+    }
+    checks
+}
@@ -0,0 +1,148 @@
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct BackoffConfig {
+    #[serde(default = "default_initial_ms")]
+    pub initial_ms: u64,
+    #[serde(default = "default_max_ms")]
+    pub max_ms: u64,
+    #[serde(default = "default_multiplier")]
+    pub multiplier: f64,
+    /// Random spread as a fraction of the delay (0.2 = ±20%)
+    #[serde(default = "default_jitter")]
+    pub jitter: f64,
+    /// Consecutive failed attempts before giving up, 0 = retry forever
+    #[serde(default)]
+    pub max_retries: u32,
+}
+
+fn default_initial_ms() -> u64 { 1000 }
+fn default_max_ms() -> u64 { 60000 }
+fn default_multiplier() -> f64 { 2.0 }
+fn default_jitter() -> f64 { 0.2 }
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial_ms: default_initial_ms(),
+            max_ms: default_max_ms(),
+            multiplier: default_multiplier(),
+            jitter: default_jitter(),
+            max_retries: 0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EndpointKind {
+    /// Arduino running as a TCP server; frames are processed like an inbound connection
+    Arduino,
+}
+
+/// Outbound connection the backend dials instead of waiting for it
+#[derive(Debug, Deserialize, Clone)]
+pub struct EndpointConfig {
+    pub name: String,
+    /// host:port
+    pub address: String,
+    #[serde(default = "default_kind")]
+    pub kind: EndpointKind,
+    /// Device id for config profiles; defaults to the host part of address
+    #[serde(default)]
+    pub device: Option<String>,
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    #[serde(default)]
+    pub backoff: BackoffConfig,
+}
+
+fn default_kind() -> EndpointKind { EndpointKind::Arduino }
+fn default_connect_timeout_ms() -> u64 { 5000 }
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    endpoints: Vec<EndpointConfig>,
+}
+
+impl EndpointConfig {
+    pub fn load_all(path: &str) -> Vec<Self> {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().endpoints
+    }
+
+    pub fn device(&self) -> String {
+        self.device.clone().unwrap_or_else(|| {
+            self.address.rsplit_once(':').map_or(self.address.as_str(), |(host, _)| host).to_string()
+        })
+    }
+}
+
+// ================= Backoff =================
+/// Exponential backoff with jitter; `reset()` after a successful connect
+pub struct Backoff {
+    config: BackoffConfig,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(config: BackoffConfig) -> Self {
+        Self { config, attempt: 0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+
+    /// Delay before the next attempt, or None once max_retries is used up
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        if self.config.max_retries > 0 && self.attempt >= self.config.max_retries {
+            return None;
+        }
+        let base = self.config.initial_ms as f64 * self.config.multiplier.max(1.0).powi(self.attempt as i32);
+        let base = base.min(self.config.max_ms as f64);
+        self.attempt += 1;
+        let jitter = self.config.jitter.clamp(0.0, 1.0) * (2.0 * unit_random() - 1.0);
+        Some(Duration::from_millis((base * (1.0 + jitter)).max(0.0) as u64))
+    }
+}
+
+/// Cheap [0, 1) value for jitter; it only needs to spread reconnecting clients apart
+fn unit_random() -> f64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    // Knuth multiplicative hash supaya bit rendah ikut teracak
+    (nanos.wrapping_mul(2654435761) as f64) / (u32::MAX as f64 + 1.0)
+}
+
+/// Dial the endpoint until it answers or the retry budget runs out
+pub async fn connect(endpoint: &EndpointConfig, backoff: &mut Backoff) -> Option<TcpStream> {
+    let timeout = Duration::from_millis(endpoint.connect_timeout_ms);
+    loop {
+        let error = match tokio::time::timeout(timeout, TcpStream::connect(&endpoint.address)).await {
+            Ok(Ok(stream)) => {
+                backoff.reset();
+                return Some(stream);
+            }
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => "connect timed out".to_string(),
+        };
+        let Some(delay) = backoff.next_delay() else {
+            eprintln!("❌ {} ({}): {}, giving up", endpoint.name, endpoint.address, error);
+            return None;
+        };
+        eprintln!(
+            "⚠️ {} ({}): {}, retrying in {:.1}s",
+            endpoint.name,
+            endpoint.address,
+            error,
+            delay.as_secs_f64()
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
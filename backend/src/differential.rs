@@ -0,0 +1,134 @@
+use serde::Deserialize;
+
+use crate::channels::Channels;
+use crate::clock::SharedClock;
+use crate::filtering::{FilterConfig, SensorFilters, UnifiedSensorRaw};
+use crate::{UnifiedSensorData, CHANNEL_NAMES};
+
+/// Line prefix of the reference (clean-air) chamber array, 7 channels in
+/// SENSOR order
+pub const REFERENCE_PREFIX: &str = "REF:";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DifferentialConfig {
+    /// Dual-chamber rig: the firmware also sends REF: lines
+    #[serde(default)]
+    pub enabled: bool,
+    /// Publish `<channel>_diff` = sample - reference
+    #[serde(default = "default_true")]
+    pub difference: bool,
+    /// Publish `<channel>_ratio` = sample / reference
+    #[serde(default = "default_true")]
+    pub ratio: bool,
+    /// Reference readings older than this are not paired with sample frames
+    #[serde(default = "default_max_age_ms")]
+    pub max_age_ms: i64,
+}
+
+fn default_true() -> bool { true }
+fn default_max_age_ms() -> i64 { 2000 }
+
+impl Default for DifferentialConfig {
+    fn default() -> Self {
+        Self { enabled: false, difference: true, ratio: true, max_age_ms: default_max_age_ms() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    differential: DifferentialConfig,
+}
+
+impl DifferentialConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc)
+            .try_into::<ConfigFile>()
+            .unwrap_or_default()
+            .differential
+    }
+
+    /// (name, source channel) added to every paired frame, for the catalog
+    pub fn output_channels(&self) -> Vec<(String, &'static str)> {
+        let mut names = Vec::new();
+        for channel in CHANNEL_NAMES {
+            if self.difference {
+                names.push((format!("{}_diff", channel), channel));
+            }
+            if self.ratio {
+                names.push((format!("{}_ratio", channel), channel));
+            }
+        }
+        names
+    }
+}
+
+// ================= Differential (reference chamber) =================
+/// Pairs each sample frame with the latest reference-chamber reading.
+/// The reference array runs through its own copy of the device filter chain
+/// so both arrays are smoothed identically before they are subtracted.
+pub struct Differential {
+    config: DifferentialConfig,
+    filters: SensorFilters,
+    clock: SharedClock,
+    latest: Option<(i64, [f32; 7])>,
+}
+
+impl Differential {
+    pub fn new(device: Option<&str>, clock: SharedClock) -> Option<Self> {
+        let config = DifferentialConfig::load("config.toml", device);
+        if !config.enabled {
+            return None;
+        }
+        let filters = SensorFilters::with_clock(&FilterConfig::load("config.toml", device), clock.clone());
+        Some(Self { config, filters, clock, latest: None })
+    }
+
+    /// Same reload as the sample array, so both stay filtered identically
+    pub fn reload_filters(&mut self, config: &FilterConfig) {
+        self.filters.reload(config);
+    }
+
+    /// Ingest one `REF:` line (prefix already stripped)
+    pub fn ingest(&mut self, data: &str) {
+        let values: Vec<f32> = data.split(',').filter_map(|s| s.trim().parse::<f32>().ok()).collect();
+        let Some(channels) = Channels::from_slice(&values) else {
+            return;
+        };
+        let raw = UnifiedSensorRaw {
+            channels,
+            state: 0,
+            level: 0,
+            flow: None,
+            pressure: None,
+            humidity: None,
+            heater_temp: None,
+        };
+        let filtered = self.filters.update(&raw);
+        self.latest = Some((self.clock.now_millis(), filtered.channels.values()));
+    }
+
+    /// Attach the reference reading and difference/ratio channels to a frame
+    pub fn apply(&self, frame: &mut UnifiedSensorData) {
+        let Some((at, reference)) = self.latest else {
+            return;
+        };
+        // Referensi basi tidak dipakai, drift ambient bisa sudah berubah
+        if frame.timestamp - at > self.config.max_age_ms {
+            return;
+        }
+        for ((channel, sample), reference) in frame.channels.iter().zip(reference) {
+            if sample.is_nan() || reference.is_nan() {
+                continue;
+            }
+            if self.config.difference {
+                frame.virtual_channels.insert(format!("{}_diff", channel.name()), sample - reference);
+            }
+            if self.config.ratio && reference.abs() > f32::EPSILON {
+                frame.virtual_channels.insert(format!("{}_ratio", channel.name()), sample / reference);
+            }
+        }
+        frame.reference = Some(reference);
+    }
+}
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+
+use crate::audit::AuditLog;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DiskConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Any path on the filesystem to watch (the SD card)
+    #[serde(default = "default_path")]
+    pub path: String,
+    #[serde(default = "default_check_secs")]
+    pub check_secs: u64,
+    /// Below this a warning is emitted and pruning starts
+    #[serde(default = "default_min_free_mb")]
+    pub min_free_mb: u64,
+    /// Pruning stops once this much is free again
+    #[serde(default = "default_target_free_mb")]
+    pub target_free_mb: u64,
+    /// Directories whose files may be pruned (not recursive)
+    #[serde(default = "default_prune_dirs")]
+    pub prune_dirs: Vec<String>,
+    /// File name suffixes eligible for pruning
+    #[serde(default = "default_extensions")]
+    pub extensions: Vec<String>,
+    /// Files modified more recently than this are never pruned
+    #[serde(default = "default_keep_hours")]
+    pub keep_hours: f64,
+}
+
+fn default_enabled() -> bool { true }
+fn default_path() -> String { ".".to_string() }
+fn default_check_secs() -> u64 { 300 }
+fn default_min_free_mb() -> u64 { 500 }
+fn default_target_free_mb() -> u64 { 1000 }
+fn default_prune_dirs() -> Vec<String> { vec!["recordings".to_string(), "archive".to_string()] }
+fn default_extensions() -> Vec<String> { vec![".csv".to_string(), ".wal".to_string(), ".tar.gz".to_string()] }
+fn default_keep_hours() -> f64 { 24.0 }
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            path: default_path(),
+            check_secs: default_check_secs(),
+            min_free_mb: default_min_free_mb(),
+            target_free_mb: default_target_free_mb(),
+            prune_dirs: default_prune_dirs(),
+            extensions: default_extensions(),
+            keep_hours: default_keep_hours(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    disk: DiskConfig,
+}
+
+impl DiskConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().disk
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DiskEvent {
+    free_mb: u64,
+    total_mb: u64,
+    min_free_mb: u64,
+    pruned: Vec<String>,
+    pruned_mb: f64,
+    /// Still below min_free_mb after pruning
+    critical: bool,
+    timestamp: i64,
+}
+
+fn free_mb(path: &str) -> std::io::Result<(u64, u64)> {
+    Ok((fs2::available_space(path)? / 1_048_576, fs2::total_space(path)? / 1_048_576))
+}
+
+/// Prunable files, oldest first, as (path, size, modified)
+fn candidates(config: &DiskConfig) -> Vec<(PathBuf, u64, SystemTime)> {
+    let cutoff = SystemTime::now() - Duration::from_secs_f64(config.keep_hours.max(0.0) * 3600.0);
+    let mut files: Vec<_> = config
+        .prune_dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            config.extensions.iter().any(|ext| name.ends_with(ext.as_str()))
+        })
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            let modified = meta.modified().ok()?;
+            (meta.is_file() && modified < cutoff).then(|| (entry.path(), meta.len(), modified))
+        })
+        .collect();
+    files.sort_by_key(|(_, _, modified)| *modified);
+    files
+}
+
+/// Delete oldest candidates until target_free_mb is reached
+fn prune(config: &DiskConfig) -> (Vec<String>, u64) {
+    let mut pruned = Vec::new();
+    let mut bytes = 0;
+    for (path, size, _) in candidates(config) {
+        match free_mb(&config.path) {
+            Ok((free, _)) if free >= config.target_free_mb => break,
+            Err(_) => break,
+            _ => {}
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                println!("🧹 Pruned {}", path.display());
+                pruned.push(path.display().to_string());
+                bytes += size;
+            }
+            Err(e) => eprintln!("❌ Failed to prune {}: {}", path.display(), e),
+        }
+    }
+    (pruned, bytes)
+}
+
+// ================= Disk Monitor =================
+/// Watches free space on the data partition and prunes old data files before the card fills
+pub async fn run(config: DiskConfig, data_tx: broadcast::Sender<String>, audit: AuditLog) {
+    if !Path::new(&config.path).exists() {
+        eprintln!("⚠️ Disk monitor: {} does not exist", config.path);
+        return;
+    }
+    println!("💾 Disk monitor enabled ({} MB minimum free on {})", config.min_free_mb, config.path);
+    let mut interval = tokio::time::interval(Duration::from_secs(config.check_secs.max(1)));
+
+    loop {
+        interval.tick().await;
+        let (free, total) = match free_mb(&config.path) {
+            Ok(space) => space,
+            Err(e) => {
+                eprintln!("❌ Disk monitor: {}", e);
+                continue;
+            }
+        };
+        if free >= config.min_free_mb {
+            continue;
+        }
+
+        eprintln!("⚠️ Low disk space: {} MB free on {}", free, config.path);
+        let prune_config = config.clone();
+        let (pruned, bytes) = tokio::task::spawn_blocking(move || prune(&prune_config))
+            .await
+            .unwrap_or_default();
+        let free = free_mb(&config.path).map(|(free, _)| free).unwrap_or(free);
+
+        if !pruned.is_empty() {
+            audit.record(
+                "disk_monitor",
+                "prune",
+                serde_json::json!({ "files": pruned, "bytes": bytes, "free_mb": free }),
+            );
+        }
+        let event = DiskEvent {
+            free_mb: free,
+            total_mb: total,
+            min_free_mb: config.min_free_mb,
+            pruned,
+            pruned_mb: bytes as f64 / 1_048_576.0,
+            critical: free < config.min_free_mb,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        };
+        let mut event = serde_json::to_value(&event).unwrap_or_default();
+        event["type"] = "disk_space".into();
+        let _ = data_tx.send(event.to_string());
+    }
+}
@@ -0,0 +1,521 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, Notify};
+
+use crate::devices::DeviceRegistry;
+use crate::mfc::Dilution;
+use crate::UnifiedSensorData;
+
+/// Number of finished runs kept for status queries
+const HISTORY_LIMIT: usize = 50;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExperimentStep {
+    /// Command sent to the run's device
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Analyte concentration set on the dilution system before the command
+    #[serde(default)]
+    pub concentration_ppm: Option<f32>,
+    /// Fixed wait after the command (ignored when `until_state` is set)
+    #[serde(default)]
+    pub wait_secs: u64,
+    /// Wait until the run's device reports this FSM state name (e.g. "DONE")
+    #[serde(default)]
+    pub until_state: Option<String>,
+    #[serde(default = "default_step_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_step_timeout() -> u64 { 3600 }
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExperimentDef {
+    pub name: String,
+    pub steps: Vec<ExperimentStep>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct QueueConfig {
+    /// Sent to the run's device when a running experiment is cancelled
+    #[serde(default = "default_abort_command")]
+    pub abort_command: String,
+}
+
+fn default_abort_command() -> String { "STOP_SAMPLING".to_string() }
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self { abort_command: default_abort_command() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    experiment_queue: QueueConfig,
+    #[serde(default)]
+    experiments: Vec<ExperimentDef>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Pending,
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ExperimentRun {
+    pub id: u64,
+    pub name: String,
+    pub device: String,
+    pub priority: i32,
+    pub status: RunStatus,
+    pub current_step: usize,
+    pub total_steps: usize,
+    pub queued_at: i64,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    next_id: u64,
+    pending: Vec<ExperimentRun>,
+    running: HashMap<String, (ExperimentRun, Arc<Notify>)>,
+    history: Vec<ExperimentRun>,
+    wakers: HashMap<String, Arc<Notify>>,
+}
+
+// ================= ExperimentQueue =================
+#[derive(Clone)]
+pub struct ExperimentQueue {
+    config: Arc<QueueConfig>,
+    definitions: Arc<Vec<ExperimentDef>>,
+    state: Arc<Mutex<QueueState>>,
+    dilution: Dilution,
+    devices: DeviceRegistry,
+    cmd_tx: broadcast::Sender<String>,
+    data_tx: broadcast::Sender<String>,
+    frame_tx: broadcast::Sender<UnifiedSensorData>,
+}
+
+impl ExperimentQueue {
+    pub fn load(
+        path: &str,
+        dilution: Dilution,
+        devices: DeviceRegistry,
+        cmd_tx: broadcast::Sender<String>,
+        data_tx: broadcast::Sender<String>,
+        frame_tx: broadcast::Sender<UnifiedSensorData>,
+    ) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        let file: ConfigFile = toml::from_str(&content).unwrap_or_default();
+        if !file.experiments.is_empty() {
+            println!("🧪 Loaded {} experiment definitions", file.experiments.len());
+        }
+        Self {
+            config: Arc::new(file.experiment_queue),
+            definitions: Arc::new(file.experiments),
+            state: Arc::new(Mutex::new(QueueState { next_id: 1, ..Default::default() })),
+            dilution,
+            devices,
+            cmd_tx,
+            data_tx,
+            frame_tx,
+        }
+    }
+
+    /// Queue an experiment; higher priority runs first, FIFO among equals.
+    /// Without a device it runs on the only connected one.
+    pub fn enqueue(&self, name: &str, priority: i32, device: Option<&str>) -> Result<u64, String> {
+        let def = self
+            .definitions
+            .iter()
+            .find(|d| d.name == name)
+            .ok_or_else(|| format!("unknown experiment '{}'", name))?;
+        let device = match device {
+            Some(device) => device.to_string(),
+            None => self.devices.only().ok_or("no single connected device; name the device to run on")?,
+        };
+        let device = device.as_str();
+
+        let (id, spawn_runner) = {
+            let mut state = self.state.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.pending.push(ExperimentRun {
+                id,
+                name: def.name.clone(),
+                device: device.to_string(),
+                priority,
+                status: RunStatus::Pending,
+                current_step: 0,
+                total_steps: def.steps.len(),
+                queued_at: chrono::Utc::now().timestamp_millis(),
+                started_at: None,
+                finished_at: None,
+                error: None,
+            });
+            sort_pending(&mut state.pending);
+
+            let spawn_runner = !state.wakers.contains_key(device);
+            let waker = state.wakers.entry(device.to_string()).or_default().clone();
+            waker.notify_one();
+            (id, spawn_runner)
+        };
+
+        if spawn_runner {
+            tokio::spawn(self.clone().run_device(device.to_string()));
+        }
+        println!("🧪 Queued experiment #{} '{}' (priority {}, device {})", id, name, priority, device);
+        self.publish();
+        Ok(id)
+    }
+
+    /// Cancel a pending run, or abort it if it is currently running
+    pub fn cancel(&self, id: u64) -> Result<(), String> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(pos) = state.pending.iter().position(|r| r.id == id) {
+                let mut run = state.pending.remove(pos);
+                run.status = RunStatus::Cancelled;
+                run.finished_at = Some(chrono::Utc::now().timestamp_millis());
+                push_history(&mut state.history, run);
+            } else if let Some((_, cancel)) = state.running.values().find(|(r, _)| r.id == id) {
+                cancel.notify_one();
+            } else {
+                return Err(format!("no queued or running experiment #{}", id));
+            }
+        }
+        self.publish();
+        Ok(())
+    }
+
+    /// Runs currently executing, one per device
+    pub fn running(&self) -> Vec<ExperimentRun> {
+        self.state.lock().unwrap().running.values().map(|(run, _)| run.clone()).collect()
+    }
+
+    /// Abort the run currently executing on `device`, if any; returns its id
+    pub fn abort_device(&self, device: &str) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        let (run, cancel) = state.running.get(device)?;
+        cancel.notify_one();
+        Some(run.id)
+    }
+
+    /// Change the priority of a pending run (reorders the queue)
+    pub fn set_priority(&self, id: u64, priority: i32) -> Result<(), String> {
+        {
+            let mut state = self.state.lock().unwrap();
+            let run = state
+                .pending
+                .iter_mut()
+                .find(|r| r.id == id)
+                .ok_or_else(|| format!("no pending experiment #{}", id))?;
+            run.priority = priority;
+            sort_pending(&mut state.pending);
+        }
+        self.publish();
+        Ok(())
+    }
+
+    /// Look up a run by id, wherever it is in its lifecycle
+    pub fn run(&self, id: u64) -> Option<ExperimentRun> {
+        let state = self.state.lock().unwrap();
+        state
+            .pending
+            .iter()
+            .chain(state.running.values().map(|(r, _)| r))
+            .chain(state.history.iter())
+            .find(|r| r.id == id)
+            .cloned()
+    }
+
+    pub fn snapshot(&self) -> serde_json::Value {
+        let state = self.state.lock().unwrap();
+        let running: Vec<_> = state.running.values().map(|(r, _)| r.clone()).collect();
+        serde_json::json!({
+            "type": "queue",
+            "running": running,
+            "pending": state.pending,
+            "recent": state.history.iter().rev().take(10).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Broadcast queue state to all GUI clients
+    pub fn publish(&self) {
+        let _ = self.data_tx.send(self.snapshot().to_string());
+    }
+
+    // ================= Runner =================
+    async fn run_device(self, device: String) {
+        let waker = self.state.lock().unwrap().wakers[&device].clone();
+
+        loop {
+            let next = {
+                let mut state = self.state.lock().unwrap();
+                state
+                    .pending
+                    .iter()
+                    .position(|r| r.device == device)
+                    .map(|pos| state.pending.remove(pos))
+            };
+            let Some(mut run) = next else {
+                waker.notified().await;
+                continue;
+            };
+
+            let Some(def) = self.definitions.iter().find(|d| d.name == run.name).cloned() else {
+                continue;
+            };
+
+            let cancel = Arc::new(Notify::new());
+            run.status = RunStatus::Running;
+            run.started_at = Some(chrono::Utc::now().timestamp_millis());
+            self.state.lock().unwrap().running.insert(device.clone(), (run.clone(), cancel.clone()));
+            self.publish();
+            println!("🧪 Running experiment #{} '{}' on {}", run.id, run.name, device);
+
+            let result = tokio::select! {
+                result = self.run_steps(&device, &def) => result,
+                _ = cancel.notified() => {
+                    let _ = self.send(&device, &self.config.abort_command);
+                    Err(None)
+                }
+            };
+
+            let mut state = self.state.lock().unwrap();
+            if let Some((mut finished, _)) = state.running.remove(&device) {
+                finished.finished_at = Some(chrono::Utc::now().timestamp_millis());
+                match result {
+                    Ok(()) => finished.status = RunStatus::Completed,
+                    Err(None) => finished.status = RunStatus::Cancelled,
+                    Err(Some(e)) => {
+                        eprintln!("❌ Experiment #{} failed: {}", finished.id, e);
+                        finished.status = RunStatus::Failed;
+                        finished.error = Some(e);
+                    }
+                }
+                println!("🧪 Experiment #{} finished: {:?}", finished.id, finished.status);
+                push_history(&mut state.history, finished);
+            }
+            drop(state);
+            self.publish();
+        }
+    }
+
+    /// Command addressed to `device` only
+    fn send(&self, device: &str, command: &str) -> Result<(), String> {
+        if !self.devices.connected(device) {
+            return Err(format!("device '{}' is not connected", device));
+        }
+        let _ = self.cmd_tx.send(format!("{}:{}", device, command));
+        Ok(())
+    }
+
+    /// Err(None) = cancelled, Err(Some(msg)) = failed
+    async fn run_steps(&self, device: &str, def: &ExperimentDef) -> Result<(), Option<String>> {
+        let mut frame_rx = self.frame_tx.subscribe();
+
+        for (index, step) in def.steps.iter().enumerate() {
+            if let Some((run, _)) = self.state.lock().unwrap().running.get_mut(device) {
+                run.current_step = index + 1;
+            }
+            self.publish();
+
+            if let Some(ppm) = step.concentration_ppm {
+                self.dilution.set_concentration(ppm).await.map_err(Some)?;
+            }
+            if let Some(command) = &step.command {
+                self.send(device, command).map_err(Some)?;
+            }
+
+            match &step.until_state {
+                Some(target) => {
+                    let wait = async {
+                        loop {
+                            match frame_rx.recv().await {
+                                Ok(frame) if frame.device == device && &frame.state_name == target => return Ok(()),
+                                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                                Err(broadcast::error::RecvError::Closed) => {
+                                    return Err(Some("frame channel closed".to_string()))
+                                }
+                            }
+                        }
+                    };
+                    tokio::time::timeout(Duration::from_secs(step.timeout_secs), wait)
+                        .await
+                        .map_err(|_| Some(format!("timeout waiting for state {}", target)))??;
+                }
+                None => tokio::time::sleep(Duration::from_secs(step.wait_secs)).await,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn sort_pending(pending: &mut [ExperimentRun]) {
+    // Stable sort keeps FIFO order among equal priorities
+    pending.sort_by_key(|r| std::cmp::Reverse(r.priority));
+}
+
+fn push_history(history: &mut Vec<ExperimentRun>, run: ExperimentRun) {
+    history.push(run);
+    if history.len() > HISTORY_LIMIT {
+        history.remove(0);
+    }
+}
+
+/// GUI commands:
+///   QUEUE_ADD <name> [priority] [device]
+///   QUEUE_CANCEL <id>
+///   QUEUE_PRIORITY <id> <priority>
+///   QUEUE_LIST
+pub fn handle_command(queue: &ExperimentQueue, args: &[&str]) -> Result<(), String> {
+    match args {
+        ["QUEUE_ADD", name, rest @ ..] => {
+            let priority = match rest.first() {
+                Some(p) => p.parse().map_err(|_| format!("invalid priority '{}'", p))?,
+                None => 0,
+            };
+            queue.enqueue(name, priority, rest.get(1).copied()).map(|_| ())
+        }
+        ["QUEUE_CANCEL", id] => queue.cancel(parse_id(id)?),
+        ["QUEUE_PRIORITY", id, priority] => {
+            let priority = priority.parse().map_err(|_| format!("invalid priority '{}'", priority))?;
+            queue.set_priority(parse_id(id)?, priority)
+        }
+        ["QUEUE_LIST"] => {
+            queue.publish();
+            Ok(())
+        }
+        _ => Err("usage: QUEUE_ADD <name> [priority] [device] | QUEUE_CANCEL <id> | QUEUE_PRIORITY <id> <priority> | QUEUE_LIST".to_string()),
+    }
+}
+
+fn parse_id(id: &str) -> Result<u64, String> {
+    id.parse().map_err(|_| format!("invalid experiment id '{}'", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mfc::DilutionConfig;
+
+    fn queue(devices: &DeviceRegistry) -> (ExperimentQueue, broadcast::Receiver<String>) {
+        let (data_tx, _) = broadcast::channel(64);
+        let (cmd_tx, cmd_rx) = broadcast::channel(64);
+        let (frame_tx, _) = broadcast::channel(64);
+        let step = ExperimentStep {
+            command: Some("START_SAMPLING".to_string()),
+            concentration_ppm: None,
+            wait_secs: 0,
+            until_state: Some("DONE".to_string()),
+            timeout_secs: 60,
+        };
+        let queue = ExperimentQueue {
+            config: Arc::new(QueueConfig::default()),
+            definitions: Arc::new(vec![ExperimentDef { name: "full_cycle".to_string(), steps: vec![step] }]),
+            state: Arc::new(Mutex::new(QueueState { next_id: 1, ..Default::default() })),
+            dilution: Dilution::new(DilutionConfig::default(), data_tx.clone()),
+            devices: devices.clone(),
+            cmd_tx,
+            data_tx,
+            frame_tx,
+        };
+        (queue, cmd_rx)
+    }
+
+    fn frame(device: &str, state_name: &str) -> UnifiedSensorData {
+        serde_json::from_value(serde_json::json!({
+            "state": 0, "state_name": state_name, "level": 0, "cycle_valid": true,
+            "timestamp": 0, "source": device, "device": device,
+        }))
+        .unwrap()
+    }
+
+    async fn command(cmd_rx: &mut broadcast::Receiver<String>) -> String {
+        tokio::time::timeout(Duration::from_secs(5), cmd_rx.recv()).await.unwrap().unwrap()
+    }
+
+    async fn status(queue: &ExperimentQueue, id: u64, expected: RunStatus) {
+        for _ in 0..100 {
+            if queue.run(id).unwrap().status == expected {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("run #{} never became {:?}", id, expected);
+    }
+
+    #[tokio::test]
+    async fn commands_and_wait_stay_on_the_run_device() {
+        let (data_tx, _) = broadcast::channel(64);
+        let devices = DeviceRegistry::new(Default::default(), data_tx);
+        let link_a = devices.connect("A", "test");
+        let link_b = devices.connect("B", "test");
+        let (queue, mut cmd_rx) = queue(&devices);
+
+        let id = queue.enqueue("full_cycle", 0, Some("A")).unwrap();
+        let cmd = command(&mut cmd_rx).await;
+        assert_eq!(link_a.route(&cmd).as_deref(), Some("START_SAMPLING"));
+        assert_eq!(link_b.route(&cmd), None);
+
+        // Device B finishing its own cycle must not complete A's step
+        queue.frame_tx.send(frame("B", "DONE")).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(queue.run(id).unwrap().status, RunStatus::Running);
+
+        queue.frame_tx.send(frame("A", "DONE")).unwrap();
+        status(&queue, id, RunStatus::Completed).await;
+        assert!(cmd_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn abort_stops_only_the_aborted_device() {
+        let (data_tx, _) = broadcast::channel(64);
+        let devices = DeviceRegistry::new(Default::default(), data_tx);
+        let link_a = devices.connect("A", "test");
+        let link_b = devices.connect("B", "test");
+        let (queue, mut cmd_rx) = queue(&devices);
+
+        let run_a = queue.enqueue("full_cycle", 0, Some("A")).unwrap();
+        let run_b = queue.enqueue("full_cycle", 0, Some("B")).unwrap();
+        let mut started = vec![command(&mut cmd_rx).await, command(&mut cmd_rx).await];
+        started.sort();
+        assert_eq!(started, ["A:START_SAMPLING", "B:START_SAMPLING"]);
+
+        // What cycle_abort does when a rule trips on device A
+        assert_eq!(queue.abort_device("A"), Some(run_a));
+        let abort = command(&mut cmd_rx).await;
+        assert_eq!(link_a.route(&abort).as_deref(), Some("STOP_SAMPLING"));
+        assert_eq!(link_b.route(&abort), None);
+        status(&queue, run_a, RunStatus::Cancelled).await;
+        assert_eq!(queue.run(run_b).unwrap().status, RunStatus::Running);
+        assert!(cmd_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn enqueue_needs_a_device_when_several_are_connected() {
+        let (data_tx, _) = broadcast::channel(64);
+        let devices = DeviceRegistry::new(Default::default(), data_tx);
+        let _link_a = devices.connect("A", "test");
+        let (queue, mut cmd_rx) = queue(&devices);
+
+        let id = queue.enqueue("full_cycle", 0, None).unwrap();
+        assert_eq!(queue.run(id).unwrap().device, "A");
+        assert_eq!(command(&mut cmd_rx).await, "A:START_SAMPLING");
+
+        let _link_b = devices.connect("B", "test");
+        assert!(queue.enqueue("full_cycle", 0, None).is_err());
+    }
+}
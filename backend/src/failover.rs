@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, watch};
+
+use crate::audit::AuditLog;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Active,
+    Standby,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FailoverConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Role on a cold start; a peer that is already ingesting always wins
+    #[serde(default = "default_role")]
+    pub role: Role,
+    #[serde(default = "default_instance")]
+    pub instance: String,
+    /// The other instance's health endpoint, e.g. "http://192.168.100.11:8083/api/health"
+    #[serde(default)]
+    pub peer_url: String,
+    #[serde(default = "default_check_secs")]
+    pub check_secs: u64,
+    /// Consecutive failed checks before the standby takes over
+    #[serde(default = "default_failures")]
+    pub failures: u32,
+}
+
+fn default_role() -> Role { Role::Active }
+fn default_instance() -> String { "enose".to_string() }
+fn default_check_secs() -> u64 { 5 }
+fn default_failures() -> u32 { 3 }
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            role: default_role(),
+            instance: default_instance(),
+            peer_url: String::new(),
+            check_secs: default_check_secs(),
+            failures: default_failures(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    failover: FailoverConfig,
+}
+
+impl FailoverConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().failover
+    }
+}
+
+/// Reply of `GET /api/health`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Health {
+    pub instance: String,
+    pub active: bool,
+    /// Epoch ms this instance started ingesting
+    pub active_since: Option<i64>,
+}
+
+// ================= Failover =================
+/// Active/standby coordination between two backends via their health endpoints.
+/// Only the active instance accepts Arduino connections and writes data.
+#[derive(Clone)]
+pub struct Failover {
+    instance: Arc<String>,
+    active: Arc<watch::Sender<Option<i64>>>,
+}
+
+impl Failover {
+    /// Without failover configured the instance is active right away
+    pub fn new(config: &FailoverConfig) -> Self {
+        let (active, _) = watch::channel(None);
+        let failover = Self { instance: Arc::new(config.instance.clone()), active: Arc::new(active) };
+        if !config.enabled {
+            failover.promote();
+        }
+        failover
+    }
+
+    pub fn health(&self) -> Health {
+        let since = *self.active.borrow();
+        Health { instance: self.instance.to_string(), active: since.is_some(), active_since: since }
+    }
+
+    /// Resolves once this instance is (or becomes) the active one
+    pub async fn wait_active(&self) {
+        let mut rx = self.active.subscribe();
+        let _ = rx.wait_for(|since| since.is_some()).await;
+    }
+
+    fn promote(&self) {
+        self.active.send_if_modified(|since| {
+            let changed = since.is_none();
+            since.get_or_insert_with(|| chrono::Utc::now().timestamp_millis());
+            changed
+        });
+    }
+}
+
+async fn peer_health(client: &reqwest::Client, url: &str) -> Result<Health, reqwest::Error> {
+    client.get(url).send().await?.error_for_status()?.json().await
+}
+
+/// Decide the startup role, then watch the peer and take over when it stops answering
+pub async fn run(
+    config: FailoverConfig,
+    failover: Failover,
+    data_tx: broadcast::Sender<String>,
+    audit: AuditLog,
+) {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.check_secs.max(1)))
+        .build()
+        .unwrap_or_default();
+
+    // Peer yang sudah ingest selalu menang, cegah dua instance aktif
+    match peer_health(&client, &config.peer_url).await {
+        Ok(peer) if peer.active => println!("🪞 Peer '{}' is active, starting as standby", peer.instance),
+        _ if config.role == Role::Active => {
+            println!("🪞 Starting as active instance '{}'", config.instance);
+            failover.promote();
+            return;
+        }
+        _ => println!("🪞 Starting as standby instance '{}'", config.instance),
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(config.check_secs.max(1)));
+    let mut failed = 0;
+    loop {
+        interval.tick().await;
+        let reason = match peer_health(&client, &config.peer_url).await {
+            Ok(peer) if peer.active => {
+                failed = 0;
+                continue;
+            }
+            // Dua standby: nama instance terkecil yang naik
+            Ok(peer) if peer.instance < config.instance => {
+                failed = 0;
+                continue;
+            }
+            Ok(peer) => format!("peer '{}' is not active", peer.instance),
+            Err(e) => e.to_string(),
+        };
+        failed += 1;
+        eprintln!("⚠️ Failover check {}/{}: {}", failed, config.failures, reason);
+        if failed >= config.failures {
+            break;
+        }
+    }
+
+    println!("🪞 Peer lost, instance '{}' takes over ingestion", config.instance);
+    failover.promote();
+    audit.record(&config.instance, "failover_takeover", serde_json::json!({ "peer": config.peer_url }));
+    let event = serde_json::json!({
+        "type": "failover",
+        "instance": config.instance,
+        "active": true,
+        "timestamp": chrono::Utc::now().timestamp_millis(),
+    });
+    let _ = data_tx.send(event.to_string());
+}
@@ -0,0 +1,403 @@
+use influxdb2::models::DataPoint;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::{broadcast, watch};
+
+use crate::accuracy::AccuracyTracker;
+use crate::channels::{Channel, Channels};
+use crate::classifier::{self, Classifier, Prediction};
+use crate::derived::{DerivedConfig, DerivedMetrics, Scope};
+use crate::influxdb::InfluxDBHandler;
+use crate::models::ModelRegistry;
+use crate::noise_floor::NoiseFloor;
+use crate::transfer::TransferConfig;
+use crate::UnifiedSensorData;
+
+// FSM states (see state_to_name)
+pub const IDLE: i32 = 0;
+pub const PRE_COND: i32 = 1;
+pub const RAMP_UP: i32 = 2;
+pub const HOLD: i32 = 3;
+pub const RECOVERY: i32 = 5;
+pub const DONE: i32 = 6;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FeaturesConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Classifier model (JSON, see classifier.rs), "" = features only
+    #[serde(default)]
+    pub model: String,
+    /// Cycles with fewer RAMP_UP + HOLD frames are skipped
+    #[serde(default = "default_min_samples")]
+    pub min_samples: usize,
+    /// Also write every cycle's features to the "cycle_features" measurement
+    #[serde(default = "default_store_features")]
+    pub store_features: bool,
+    /// Detection thresholds per cycle feature, e.g. `"eth.peak" = { above = 12.5 }`
+    #[serde(default)]
+    pub thresholds: BTreeMap<String, FeatureThreshold>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FeatureThreshold {
+    #[serde(default)]
+    pub above: Option<f64>,
+    #[serde(default)]
+    pub below: Option<f64>,
+}
+
+impl FeatureThreshold {
+    fn crossed(&self, value: f64) -> bool {
+        self.above.is_some_and(|t| value > t) || self.below.is_some_and(|t| value < t)
+    }
+}
+
+fn default_min_samples() -> usize { 5 }
+fn default_store_features() -> bool { true }
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: String::new(),
+            min_samples: default_min_samples(),
+            store_features: default_store_features(),
+            thresholds: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    features: FeaturesConfig,
+}
+
+impl FeaturesConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().features
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CycleFeatures {
+    pub device: String,
+    pub source: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub session: String,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub samples: usize,
+    /// False if the flow monitor flagged any frame of the cycle
+    pub valid: bool,
+    /// Cycle-scoped derived metrics, `<channel>.<feature>` (see derived.rs)
+    pub features: BTreeMap<String, f64>,
+    pub prediction: Option<Prediction>,
+    /// Shadow model's prediction, logged only; never published as a decision
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<Prediction>,
+    /// Master unit the features were mapped onto for classification ([transfer])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transferred: Option<String>,
+    /// Features past their [features.thresholds]; None without thresholds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detections: Option<Vec<String>>,
+    /// Share of channel responses clear of the device's noise floor ([noise_floor])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<f64>,
+}
+
+/// Frames of one sniff cycle on one device, PRE_COND through RECOVERY
+pub struct SniffCycle {
+    pub started_at: i64,
+    /// (timestamp, state, channels)
+    pub samples: Vec<(i64, i32, Channels)>,
+    /// Heater setpoint (else measured temperature) of each sample, °C
+    pub heater: Vec<Option<f32>>,
+    valid: bool,
+    source: String,
+    session: String,
+}
+
+impl SniffCycle {
+    fn new(frame: &UnifiedSensorData) -> Self {
+        Self {
+            started_at: frame.timestamp,
+            samples: Vec::new(),
+            heater: Vec::new(),
+            valid: true,
+            source: frame.source.clone(),
+            session: frame.session.clone(),
+        }
+    }
+
+    fn push(&mut self, frame: &UnifiedSensorData) {
+        self.valid &= frame.cycle_valid;
+        self.samples.push((frame.timestamp, frame.state, frame.channels));
+        self.heater.push(frame.heater_setpoint.or(frame.heater_temp));
+    }
+
+    fn exposure(&self) -> usize {
+        self.samples.iter().filter(|(_, state, _)| matches!(*state, RAMP_UP | HOLD)).count()
+    }
+
+    /// (seconds since start, value) of one channel during `states`;
+    /// disabled (NaN) samples are left out
+    pub fn series(&self, channel: Channel, states: &[i32]) -> Vec<(f64, f64)> {
+        self.samples
+            .iter()
+            .filter(|(_, state, values)| states.contains(state) && values[channel].is_finite())
+            .map(|(ts, _, values)| ((ts - self.started_at) as f64 / 1000.0, values[channel] as f64))
+            .collect()
+    }
+}
+
+fn points(result: &CycleFeatures, store_features: bool) -> Vec<DataPoint> {
+    let timestamp = result.ended_at * 1_000_000;
+    let mut points = Vec::new();
+    if store_features && !result.features.is_empty() {
+        let mut builder = DataPoint::builder("cycle_features")
+            .tag("source", result.source.clone())
+            .tag("device", result.device.clone())
+            .field("samples", result.samples as i64)
+            .field("valid", result.valid);
+        if let Some(detections) = &result.detections {
+            builder = builder.field("detected", !detections.is_empty());
+        }
+        if let Some(quality) = result.quality {
+            builder = builder.field("quality", quality);
+        }
+        if !result.session.is_empty() {
+            builder = builder.tag("session", result.session.clone());
+        }
+        for (name, value) in &result.features {
+            builder = builder.field(name.clone(), *value);
+        }
+        points.extend(builder.timestamp(timestamp).build().ok());
+    }
+    if let Some(prediction) = &result.prediction {
+        let mut builder = DataPoint::builder("classification")
+            .tag("source", result.source.clone())
+            .tag("device", result.device.clone())
+            .tag("label", prediction.label.clone())
+            .tag("model", prediction.model.clone())
+            .field("confidence", prediction.confidence)
+            .field("distance", prediction.distance);
+        if !result.session.is_empty() {
+            builder = builder.tag("session", result.session.clone());
+        }
+        points.extend(builder.timestamp(timestamp).build().ok());
+    }
+    // Terpisah dari "classification" agar dashboard keputusan tidak tercampur
+    if let Some(shadow) = &result.shadow {
+        let mut builder = DataPoint::builder("shadow_classification")
+            .tag("source", result.source.clone())
+            .tag("device", result.device.clone())
+            .tag("label", shadow.label.clone())
+            .tag("model", shadow.model.clone())
+            .field("confidence", shadow.confidence)
+            .field("distance", shadow.distance);
+        if let Some(live) = &result.prediction {
+            builder = builder.tag("live_model", live.model.clone()).field("agrees", live.label == shadow.label);
+        }
+        if !result.session.is_empty() {
+            builder = builder.tag("session", result.session.clone());
+        }
+        points.extend(builder.timestamp(timestamp).build().ok());
+    }
+    points
+}
+
+// ================= Cycle Features =================
+/// Standard e-nose features at the end of every sniff cycle (PRE_COND →
+/// DONE/IDLE), optionally classified
+pub struct CycleExtractor {
+    min_samples: usize,
+    thresholds: BTreeMap<String, FeatureThreshold>,
+    /// `[features] model`, used while no registry version is active
+    model: Option<Arc<dyn Classifier>>,
+    registry: ModelRegistry,
+    noise: NoiseFloor,
+    /// Per-device [transfer], loaded on a device's first cycle
+    transfers: HashMap<String, TransferConfig>,
+    metrics: DerivedMetrics,
+    cycles: HashMap<String, SniffCycle>,
+}
+
+impl CycleExtractor {
+    pub fn new(config: &FeaturesConfig, registry: ModelRegistry, noise: NoiseFloor) -> Self {
+        let model: Option<Arc<dyn Classifier>> = match config.model.as_str() {
+            "" => None,
+            path => match classifier::load(path) {
+                Ok(model) => Some(Arc::from(model)),
+                Err(e) => {
+                    eprintln!("❌ Classifier not loaded, extracting features only: {}", e);
+                    None
+                }
+            },
+        };
+        let metrics = DerivedMetrics::load(&DerivedConfig::load("config.toml", None), Scope::Cycle);
+        Self {
+            min_samples: config.min_samples,
+            thresholds: config.thresholds.clone(),
+            model,
+            registry,
+            noise,
+            transfers: HashMap::new(),
+            metrics,
+            cycles: HashMap::new(),
+        }
+    }
+
+    /// Active registry version, else the configured model
+    fn classifier(&self) -> Option<Arc<dyn Classifier>> {
+        self.registry.active().or_else(|| self.model.clone())
+    }
+
+    /// Re-read [transfer] on the next cycle of every device (config changed)
+    pub fn reload_transfers(&mut self) {
+        self.transfers.clear();
+    }
+
+    /// Feed one frame; returns the features of the cycle it finished, if any
+    pub fn observe(&mut self, frame: &UnifiedSensorData) -> Option<CycleFeatures> {
+        let previous = self.cycles.get(&frame.device).and_then(|c| c.samples.last()).map(|(_, state, _)| *state);
+        let finished = match frame.state {
+            // Siklus baru (atau berakhir) menutup siklus yang sedang berjalan
+            PRE_COND if previous != Some(PRE_COND) => self.cycles.insert(frame.device.clone(), SniffCycle::new(frame)),
+            IDLE | DONE => self.cycles.remove(&frame.device),
+            // Backend start di tengah siklus: mulai dari RAMP_UP
+            RAMP_UP if previous.is_none() => self.cycles.insert(frame.device.clone(), SniffCycle::new(frame)),
+            _ => None,
+        };
+        if let Some(cycle) = self.cycles.get_mut(&frame.device) {
+            cycle.push(frame);
+        }
+        let cycle = finished?;
+
+        if cycle.exposure() < self.min_samples {
+            return None;
+        }
+        let features = self.metrics.cycle(&cycle);
+        // Klasifikasi di ruang respons unit master; fitur yang disimpan tetap asli
+        let transfer = self
+            .transfers
+            .entry(frame.device.clone())
+            .or_insert_with(|| TransferConfig::load("config.toml", Some(&frame.device)));
+        let mapped = (!transfer.is_empty()).then(|| transfer.apply(&features));
+        let transferred = mapped.is_some().then(|| transfer.master.clone().unwrap_or_default());
+        let inputs = mapped.as_ref().unwrap_or(&features);
+        let predict = |model: Option<Arc<dyn Classifier>>| match (model, cycle.valid) {
+            (Some(model), true) => model
+                .predict(inputs)
+                .map_err(|e| eprintln!("⚠️ Classifier {}: {}", model.name(), e))
+                .ok(),
+            _ => None,
+        };
+        let prediction = predict(self.classifier());
+        let shadow = predict(self.registry.shadow());
+        let detections = (!self.thresholds.is_empty()).then(|| {
+            self.thresholds
+                .iter()
+                .filter(|(name, threshold)| features.get(*name).is_some_and(|v| threshold.crossed(*v)))
+                .map(|(name, _)| name.clone())
+                .collect()
+        });
+        let quality = self.noise.quality(&frame.device, &features);
+        Some(CycleFeatures {
+            device: frame.device.clone(),
+            source: cycle.source.clone(),
+            session: cycle.session.clone(),
+            started_at: cycle.started_at,
+            ended_at: cycle.samples.last().map_or(frame.timestamp, |(ts, _, _)| *ts),
+            samples: cycle.samples.len(),
+            valid: cycle.valid,
+            features,
+            prediction,
+            shadow,
+            transferred,
+            detections,
+            quality,
+        })
+    }
+}
+
+/// "cycle_features" and "prediction" GUI events and the matching InfluxDB measurements
+pub async fn publish(
+    result: &CycleFeatures,
+    store_features: bool,
+    data_tx: &broadcast::Sender<String>,
+    influx: &InfluxDBHandler,
+) {
+    let mut event = serde_json::to_value(result).unwrap_or_default();
+    event["type"] = "cycle_features".into();
+    let _ = data_tx.send(event.to_string());
+    if let Some(prediction) = &result.prediction {
+        println!(
+            "🧪 {} cycle classified as {} ({:.0}%)",
+            result.device,
+            prediction.label,
+            prediction.confidence * 100.0
+        );
+        let event = serde_json::json!({
+            "type": "prediction",
+            "device": result.device,
+            "source": result.source,
+            "started_at": result.started_at,
+            "ended_at": result.ended_at,
+            "label": prediction.label,
+            "confidence": prediction.confidence,
+            "distance": prediction.distance,
+            "model": prediction.model,
+        });
+        let _ = data_tx.send(event.to_string());
+    }
+
+    if let Err(e) = influx.write_points(points(result, store_features)).await {
+        eprintln!("❌ Cycle features write error: {:?}", e);
+    }
+}
+
+/// Cycle features of live frames; published as "cycle_features" and
+/// "prediction" GUI events and the matching InfluxDB measurements
+pub async fn run(
+    config: FeaturesConfig,
+    mut extractor: CycleExtractor,
+    data_tx: broadcast::Sender<String>,
+    frame_tx: broadcast::Sender<UnifiedSensorData>,
+    influx: InfluxDBHandler,
+    accuracy: AccuracyTracker,
+    mut reloads: watch::Receiver<u64>,
+) {
+    if extractor.metrics.is_empty() {
+        eprintln!("⚠️ Cycle features: no cycle metrics enabled in [derived]");
+    }
+    println!(
+        "🧪 Cycle feature extraction enabled ({}){}",
+        extractor.metrics.names().join(", "),
+        extractor.classifier().map(|m| format!(" (classifier {})", m.name())).unwrap_or_default()
+    );
+    if let Some(shadow) = extractor.registry.shadow() {
+        println!("🧪 Shadow model {} evaluated on every cycle (not published)", shadow.name());
+    }
+
+    let mut frame_rx = frame_tx.subscribe();
+    loop {
+        let frame = match frame_rx.recv().await {
+            Ok(frame) => frame,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if reloads.has_changed().unwrap_or(false) {
+            reloads.borrow_and_update();
+            extractor.reload_transfers();
+        }
+        if let Some(result) = extractor.observe(&frame) {
+            publish(&result, config.store_features, &data_tx, &influx).await;
+            accuracy.record(&result);
+        }
+    }
+}
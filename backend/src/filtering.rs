@@ -1,145 +1,667 @@
-use serde::Deserialize;
-use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
 
-#[derive(Debug, Deserialize, Clone)]
+use crate::channels::{Channel, Channels};
+use crate::clock::{self, SharedClock};
+use crate::provenance::fingerprint;
+use crate::virtual_channels::VirtualChannels;
+use crate::wavelet::{Wavelet, WaveletDenoiser};
+use crate::CHANNEL_NAMES;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmoothingType {
+    MovingAverage,
+    Wavelet,
+    /// Exponential moving average: faster step response, constant memory
+    Ema,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct FilterConfig {
     pub window_size: usize,
+    #[serde(default = "default_filter")]
+    pub filter: SmoothingType,
+    #[serde(default = "default_wavelet")]
+    pub wavelet: Wavelet,
+    #[serde(default = "default_wavelet_level")]
+    pub wavelet_level: usize,
+    /// Sliding window (samples) the wavelet transform runs on
+    #[serde(default = "default_wavelet_window")]
+    pub wavelet_window: usize,
+    /// Smoothing factor for `filter = "ema"`, (0, 1]; higher follows faster
+    #[serde(default = "default_ema_alpha")]
+    pub ema_alpha: f32,
     #[serde(default = "default_sine_amplitude")]
     pub sine_amplitude: f32,
     #[serde(default = "default_sine_frequency")]
     pub sine_frequency: f32,
     #[serde(default = "default_sine_enabled")]
     pub sine_enabled: bool,
+    /// Rolling window (samples) for noise-reduction metrics, 0 = off
+    #[serde(default = "default_metrics_window")]
+    pub metrics_window: usize,
+    /// Attach the unfiltered channels to GUI frames as `raw` (archive export)
+    #[serde(default)]
+    pub send_raw: bool,
+    /// `[virtual_channels]` name = formula over physical channels
+    #[serde(default)]
+    pub virtual_channels: BTreeMap<String, String>,
+    /// `[[filter_chain]]` stages run in order; empty = the `filter` setting
+    #[serde(default)]
+    pub filter_chain: Vec<StageConfig>,
+    /// `[filter_channels]` channel = [stages], replaces the chain for that
+    /// channel (physical or virtual)
+    #[serde(default)]
+    pub filter_channels: BTreeMap<String, Vec<StageConfig>>,
+    /// `[modulation_channels]` channel = { enabled, amplitude, frequency },
+    /// overriding the sine_* defaults for that channel (physical or virtual)
+    #[serde(default)]
+    pub modulation_channels: BTreeMap<String, ModulationOverride>,
+}
+
+/// Per-channel sine modulation settings; unset keys fall back to the
+/// next layer (runtime toggle → `[modulation_channels]` → sine_*)
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+pub struct ModulationOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amplitude: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<f32>,
 }
 
+impl ModulationOverride {
+    /// `self` layered over `base`
+    pub fn over(self, base: ModulationOverride) -> ModulationOverride {
+        ModulationOverride {
+            enabled: self.enabled.or(base.enabled),
+            amplitude: self.amplitude.or(base.amplitude),
+            frequency: self.frequency.or(base.frequency),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.amplitude.is_some_and(|a| !(0.0..=1.0).contains(&a)) {
+            return Err("amplitude must be within 0.0..1.0".to_string());
+        }
+        if self.frequency.is_some_and(|f| f < 0.0) {
+            return Err("frequency must not be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// One filter stage as written in config.toml: `{ type = "median", window = 5 }`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StageConfig {
+    MovingAverage {
+        window: usize,
+    },
+    /// y = alpha × x + (1 - alpha) × y_prev, seeded with the first sample
+    #[serde(alias = "ema")]
+    Exponential {
+        alpha: f32,
+    },
+    /// Rolling median, removes spikes without smearing steps
+    Median {
+        window: usize,
+    },
+    Wavelet {
+        #[serde(default = "default_wavelet")]
+        wavelet: Wavelet,
+        #[serde(default = "default_wavelet_level")]
+        level: usize,
+        #[serde(default = "default_wavelet_window")]
+        window: usize,
+    },
+    /// Subtracts a slow baseline that only follows the signal in clean-air
+    /// states, compensating sensor drift between sniff cycles
+    Baseline {
+        #[serde(default = "default_baseline_alpha")]
+        alpha: f32,
+        #[serde(default = "default_baseline_states")]
+        states: Vec<i32>,
+    },
+}
+
+fn default_filter() -> SmoothingType { SmoothingType::MovingAverage }
+fn default_wavelet() -> Wavelet { Wavelet::Db4 }
+fn default_wavelet_level() -> usize { 3 }
+fn default_wavelet_window() -> usize { 32 }
+fn default_ema_alpha() -> f32 { 0.3 }
 fn default_sine_amplitude() -> f32 { 0.15 }  // 15% amplitude
 fn default_sine_frequency() -> f32 { 0.5 }   // 0.5 Hz (1 cycle per 2 seconds)
 fn default_sine_enabled() -> bool { true }
+fn default_metrics_window() -> usize { 100 }
+fn default_baseline_alpha() -> f32 { 0.01 }
+fn default_baseline_states() -> Vec<i32> { vec![0] }  // IDLE
 
 impl FilterConfig {
-    pub fn load(path: &str) -> Self {
-        let content = std::fs::read_to_string(path).unwrap_or_default();
-        toml::from_str(&content).unwrap_or(Self { 
+    /// Load filter settings, applying the device's profile overrides
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc).try_into().unwrap_or(Self { 
             window_size: 5,
+            filter: default_filter(),
+            wavelet: default_wavelet(),
+            wavelet_level: default_wavelet_level(),
+            wavelet_window: default_wavelet_window(),
+            ema_alpha: default_ema_alpha(),
             sine_amplitude: default_sine_amplitude(),
             sine_frequency: default_sine_frequency(),
             sine_enabled: default_sine_enabled(),
+            metrics_window: default_metrics_window(),
+            send_raw: false,
+            virtual_channels: BTreeMap::new(),
+            filter_chain: Vec::new(),
+            filter_channels: BTreeMap::new(),
+            modulation_channels: BTreeMap::new(),
         })
     }
+
+    /// Configured modulation of one channel, fully resolved
+    pub fn modulation_for(&self, channel: &str) -> ModulationOverride {
+        let global = ModulationOverride {
+            enabled: Some(self.sine_enabled),
+            amplitude: Some(self.sine_amplitude),
+            frequency: Some(self.sine_frequency),
+        };
+        self.modulation_channels.get(channel).copied().unwrap_or_default().over(global)
+    }
+
+    /// Stages for one channel: its own chain, else `filter_chain`, else the
+    /// single smoothing stage selected by `filter`
+    pub fn stages_for(&self, channel: &str) -> Vec<StageConfig> {
+        if let Some(stages) = self.filter_channels.get(channel) {
+            return stages.clone();
+        }
+        if !self.filter_chain.is_empty() {
+            return self.filter_chain.clone();
+        }
+        match self.filter {
+            SmoothingType::MovingAverage => vec![StageConfig::MovingAverage { window: self.window_size }],
+            SmoothingType::Wavelet => vec![StageConfig::Wavelet {
+                wavelet: self.wavelet,
+                level: self.wavelet_level,
+                window: self.wavelet_window,
+            }],
+            SmoothingType::Ema => vec![StageConfig::Exponential { alpha: self.ema_alpha }],
+        }
+    }
+
+    /// Stage parameters and channel names are usable
+    pub fn validate_chain(&self) -> Result<(), String> {
+        for (channel, stages) in &self.filter_channels {
+            if !CHANNEL_NAMES.contains(&channel.as_str()) && !self.virtual_channels.contains_key(channel) {
+                return Err(format!("filter_channels: unknown channel '{}'", channel));
+            }
+            validate_stages(stages).map_err(|e| format!("filter_channels.{}: {}", channel, e))?;
+        }
+        for (channel, modulation) in &self.modulation_channels {
+            if !CHANNEL_NAMES.contains(&channel.as_str()) && !self.virtual_channels.contains_key(channel) {
+                return Err(format!("modulation_channels: unknown channel '{}'", channel));
+            }
+            modulation.validate().map_err(|e| format!("modulation_channels.{}: {}", channel, e))?;
+        }
+        validate_stages(&self.filter_chain).map_err(|e| format!("filter_chain: {}", e))
+    }
+}
+
+fn validate_stages(stages: &[StageConfig]) -> Result<(), String> {
+    for stage in stages {
+        match stage {
+            StageConfig::MovingAverage { window } | StageConfig::Median { window } if *window == 0 => {
+                return Err("window must be at least 1".to_string())
+            }
+            StageConfig::Exponential { alpha } | StageConfig::Baseline { alpha, .. } if !(*alpha > 0.0 && *alpha <= 1.0) => {
+                return Err(format!("alpha {} must be within (0, 1]", alpha))
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// ================= Filter Stages =================
+/// Frame context a stage may use besides the sample itself
+pub struct StageInput {
+    pub state: i32,
+}
+
+/// One step of a channel's filter chain
+pub trait FilterStage: Send {
+    /// Next output for `value` (never NaN)
+    fn process(&mut self, value: f32, input: &StageInput) -> f32;
+    /// Forget history, e.g. when the channel is switched off
+    fn reset(&mut self);
+}
+
+struct MovingAverage {
+    window: usize,
+    values: Vec<f32>,
+}
+
+impl FilterStage for MovingAverage {
+    fn process(&mut self, value: f32, _: &StageInput) -> f32 {
+        self.values.push(value);
+        if self.values.len() > self.window {
+            self.values.remove(0);
+        }
+        let sum: f32 = self.values.iter().sum();
+        sum / self.values.len() as f32
+    }
+
+    fn reset(&mut self) {
+        self.values.clear();
+    }
+}
+
+struct Exponential {
+    alpha: f32,
+    last: Option<f32>,
+}
+
+impl FilterStage for Exponential {
+    fn process(&mut self, value: f32, _: &StageInput) -> f32 {
+        let next = match self.last {
+            Some(last) => self.alpha * value + (1.0 - self.alpha) * last,
+            None => value,
+        };
+        self.last = Some(next);
+        next
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+struct Median {
+    window: usize,
+    values: VecDeque<f32>,
+}
+
+impl FilterStage for Median {
+    fn process(&mut self, value: f32, _: &StageInput) -> f32 {
+        push_bounded(&mut self.values, value, self.window);
+        let mut sorted: Vec<f32> = self.values.iter().copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let mid = sorted.len() / 2;
+        if sorted.len().is_multiple_of(2) {
+            (sorted[mid - 1] + sorted[mid]) / 2.0
+        } else {
+            sorted[mid]
+        }
+    }
+
+    fn reset(&mut self) {
+        self.values.clear();
+    }
+}
+
+struct WaveletStage {
+    denoiser: WaveletDenoiser,
+    values: Vec<f32>,
+}
+
+impl FilterStage for WaveletStage {
+    fn process(&mut self, value: f32, _: &StageInput) -> f32 {
+        self.denoiser.push(&mut self.values, value)
+    }
+
+    fn reset(&mut self) {
+        self.values.clear();
+    }
+}
+
+struct BaselineCompensation {
+    alpha: f32,
+    states: Vec<i32>,
+    baseline: Option<f32>,
+}
+
+impl FilterStage for BaselineCompensation {
+    fn process(&mut self, value: f32, input: &StageInput) -> f32 {
+        // Baseline hanya mengikuti sinyal saat udara bersih, dibekukan selama paparan gas
+        let baseline = match self.baseline {
+            None => value,
+            Some(baseline) if self.states.contains(&input.state) => baseline + self.alpha * (value - baseline),
+            Some(baseline) => baseline,
+        };
+        self.baseline = Some(baseline);
+        value - baseline
+    }
+
+    fn reset(&mut self) {
+        self.baseline = None;
+    }
+}
+
+fn build_stage(config: &StageConfig) -> Box<dyn FilterStage> {
+    match config {
+        StageConfig::MovingAverage { window } => Box::new(MovingAverage { window: (*window).max(1), values: Vec::new() }),
+        StageConfig::Exponential { alpha } => Box::new(Exponential { alpha: *alpha, last: None }),
+        StageConfig::Median { window } => Box::new(Median { window: (*window).max(1), values: VecDeque::new() }),
+        StageConfig::Wavelet { wavelet, level, window } => {
+            Box::new(WaveletStage { denoiser: WaveletDenoiser::new(*wavelet, *level, *window), values: Vec::new() })
+        }
+        StageConfig::Baseline { alpha, states } => {
+            Box::new(BaselineCompensation { alpha: *alpha, states: states.clone(), baseline: None })
+        }
+    }
+}
+
+/// The stages of one channel, run in order
+struct ChannelChain {
+    stages: Vec<Box<dyn FilterStage>>,
+}
+
+impl ChannelChain {
+    fn new(stages: &[StageConfig]) -> Self {
+        Self { stages: stages.iter().map(build_stage).collect() }
+    }
+
+    fn push(&mut self, value: f32, input: &StageInput) -> f32 {
+        // Kanal dimatikan (NaN): buang riwayat supaya mulai bersih saat aktif lagi
+        if value.is_nan() {
+            self.stages.iter_mut().for_each(|stage| stage.reset());
+            return f32::NAN;
+        }
+        self.stages.iter_mut().fold(value, |value, stage| stage.process(value, input))
+    }
 }
 
 // Data mentah dari Arduino
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnifiedSensorRaw {
-    pub no2: f32,
-    pub eth: f32,
-    pub voc: f32,
-    pub co: f32,
-    pub com: f32,
-    pub ethm: f32,
-    pub vocm: f32,
+    #[serde(flatten)]
+    pub channels: Channels,
     pub state: i32,
     pub level: i32,
+    pub flow: Option<f32>,
+    pub pressure: Option<f32>,
+    pub humidity: Option<f32>,
+    pub heater_temp: Option<f32>,
 }
 
 // Hasil filter moving average
 #[derive(Debug, Clone)]
 pub struct UnifiedSensorFiltered {
-    pub no2: f32,
-    pub eth: f32,
-    pub voc: f32,
-    pub co: f32,
-    pub com: f32,
-    pub ethm: f32,
-    pub vocm: f32,
+    pub channels: Channels,
     pub state: i32,
     pub level: i32,
+    pub flow: Option<f32>,
+    pub pressure: Option<f32>,
+    pub humidity: Option<f32>,
+    pub heater_temp: Option<f32>,
+    /// Virtual channels, smoothed and modulated like physical ones
+    pub virtual_channels: BTreeMap<String, f32>,
+    /// Modulation applied to each modulated channel of this frame
+    pub modulation: BTreeMap<String, Modulation>,
+}
+
+/// Sine modulation injected into one channel of a frame, so downstream analysis can
+/// demodulate it or divide it back out (`value / factor`)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Modulation {
+    /// Hz
+    pub frequency: f32,
+    pub amplitude: f32,
+    /// Cycles completed since the filters were built
+    pub cycle: u64,
+    /// Position within the current cycle, 0..1
+    pub phase: f32,
+    /// Multiplier applied to the channel: 1 + amplitude × sin(2π × frequency × t)
+    pub factor: f32,
+}
+
+/// Variance before vs after smoothing for one channel
+#[derive(Debug, Serialize, Clone)]
+pub struct ChannelNoise {
+    pub raw_variance: f32,
+    pub filtered_variance: f32,
+    /// raw_variance / filtered_variance (higher = more smoothing)
+    pub noise_reduction_ratio: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FilterMetrics {
+    pub window: usize,
+    pub channels: BTreeMap<&'static str, ChannelNoise>,
 }
 
 // ================= SensorFilters =================
-#[derive(Clone)]
 pub struct SensorFilters {
-    window_size: usize,
-    no2: Vec<f32>,
-    eth: Vec<f32>,
-    voc: Vec<f32>,
-    co: Vec<f32>,
-    com: Vec<f32>,
-    ethm: Vec<f32>,
-    vocm: Vec<f32>,
-    // Sinusoidal modulation parameters
-    sine_amplitude: f32,
-    sine_frequency: f32,
-    sine_enabled: bool,
-    start_time: SystemTime,
+    /// Filter chain per physical channel, in CHANNEL_NAMES order
+    chains: [ChannelChain; 7],
+    virtual_channels: VirtualChannels,
+    virtual_chains: BTreeMap<String, ChannelChain>,
+    /// Configured sinusoidal modulation per channel (physical and virtual)
+    modulation: BTreeMap<String, ModulationOverride>,
+    /// Runtime toggles per channel or "all", layered over `modulation`
+    runtime_modulation: BTreeMap<String, ModulationOverride>,
+    clock: SharedClock,
+    start_ms: i64,
+    // Rolling raw vs smoothed history for effectiveness metrics
+    metrics_window: usize,
+    raw_history: [VecDeque<f32>; 7],
+    smoothed_history: [VecDeque<f32>; 7],
+    frames_since_metrics: usize,
+    send_raw: bool,
+    config_hash: String,
 }
 
 impl SensorFilters {
     pub fn new(config: &FilterConfig) -> Self {
+        Self::with_clock(config, clock::system())
+    }
+
+    /// Sine modulation phase follows `clock` instead of the system time
+    pub fn with_clock(config: &FilterConfig, clock: SharedClock) -> Self {
         Self {
-            window_size: config.window_size,
-            no2: Vec::new(),
-            eth: Vec::new(),
-            voc: Vec::new(),
-            co: Vec::new(),
-            com: Vec::new(),
-            ethm: Vec::new(),
-            vocm: Vec::new(),
-            sine_amplitude: config.sine_amplitude,
-            sine_frequency: config.sine_frequency,
-            sine_enabled: config.sine_enabled,
-            start_time: SystemTime::now(),
+            chains: CHANNEL_NAMES.map(|name| ChannelChain::new(&config.stages_for(name))),
+            virtual_channels: VirtualChannels::new(&config.virtual_channels),
+            virtual_chains: config
+                .virtual_channels
+                .keys()
+                .map(|name| (name.clone(), ChannelChain::new(&config.stages_for(name))))
+                .collect(),
+            modulation: CHANNEL_NAMES
+                .iter()
+                .map(|name| name.to_string())
+                .chain(config.virtual_channels.keys().cloned())
+                .map(|name| (name.clone(), config.modulation_for(&name)))
+                .collect(),
+            runtime_modulation: BTreeMap::new(),
+            start_ms: clock.now_millis(),
+            clock,
+            metrics_window: config.metrics_window,
+            raw_history: Default::default(),
+            smoothed_history: Default::default(),
+            frames_since_metrics: 0,
+            send_raw: config.send_raw,
+            config_hash: fingerprint(serde_json::to_string(config).unwrap_or_default().as_bytes()),
         }
     }
 
-    fn moving_average(values: &mut Vec<f32>, new_val: f32, window_size: usize) -> f32 {
-        values.push(new_val);
-        if values.len() > window_size {
-            values.remove(0);
+    /// Fingerprint of the settings this filter chain was built from
+    pub fn config_hash(&self) -> &str {
+        &self.config_hash
+    }
+
+    /// Rebuild every chain from new settings; the modulation phase and
+    /// runtime modulation toggles continue
+    pub fn reload(&mut self, config: &FilterConfig) {
+        let start_ms = self.start_ms;
+        let runtime_modulation = std::mem::take(&mut self.runtime_modulation);
+        *self = Self::with_clock(config, self.clock.clone());
+        self.start_ms = start_ms;
+        self.runtime_modulation = runtime_modulation;
+    }
+
+    /// Unfiltered channels in `CHANNEL_NAMES` order, if frames should carry them
+    pub fn raw_channels(&self, raw: &UnifiedSensorRaw) -> Option<[f32; 7]> {
+        self.send_raw.then_some(raw.channels.values())
+    }
+
+    /// Replace the runtime modulation toggles (see modulation.rs)
+    pub fn set_runtime_modulation(&mut self, overrides: &BTreeMap<String, ModulationOverride>) {
+        if self.runtime_modulation != *overrides {
+            self.runtime_modulation = overrides.clone();
         }
-        let sum: f32 = values.iter().sum();
-        sum / values.len() as f32
     }
 
-    /// Apply sinusoidal modulation: output = input × (1 + A × sin(2πft))
-    fn apply_sine_modulation(&self, value: f32) -> f32 {
-        if !self.sine_enabled {
-            return value;
+    /// Effective settings of one channel: its runtime toggle, then the
+    /// runtime "all" toggle, then config
+    fn modulation_settings(&self, channel: &str) -> ModulationOverride {
+        let runtime = self.runtime_modulation.get(channel).copied().unwrap_or_default();
+        let all = self.runtime_modulation.get("all").copied().unwrap_or_default();
+        runtime.over(all).over(self.modulation.get(channel).copied().unwrap_or_default())
+    }
+
+    /// Sinusoidal modulation of one channel `elapsed_ms` after start, None while it is off
+    fn modulation_at(&self, channel: &str, elapsed_ms: i64) -> Option<Modulation> {
+        let settings = self.modulation_settings(channel);
+        if settings.enabled != Some(true) {
+            return None;
         }
+        let amplitude = settings.amplitude.unwrap_or_default();
+        let frequency = settings.frequency.unwrap_or_default();
 
         // Calculate elapsed time in seconds
-        let elapsed = self.start_time.elapsed().unwrap_or_default();
-        let t = elapsed.as_secs_f32();
+        let t = elapsed_ms as f32 / 1000.0;
 
         // Calculate sine wave: sin(2πft)
-        let angle = 2.0 * std::f32::consts::PI * self.sine_frequency * t;
+        let angle = 2.0 * std::f32::consts::PI * frequency * t;
         let sine_value = angle.sin();
 
-        // Apply modulation: output = input × (1 + A × sin(2πft))
-        value * (1.0 + self.sine_amplitude * sine_value)
+        let cycles = elapsed_ms as f64 / 1000.0 * frequency.max(0.0) as f64;
+        Some(Modulation {
+            frequency,
+            amplitude,
+            cycle: cycles as u64,
+            phase: cycles.fract() as f32,
+            // output = input × (1 + A × sin(2πft))
+            factor: 1.0 + amplitude * sine_value,
+        })
+    }
+
+    fn elapsed_ms(&self) -> i64 {
+        (self.clock.now_millis() - self.start_ms).max(0)
+    }
+
+    /// Current modulation of the first modulated physical channel, None if
+    /// none is modulated; waveform cycles follow this one
+    pub fn modulation_now(&self) -> Option<Modulation> {
+        let elapsed_ms = self.elapsed_ms();
+        CHANNEL_NAMES
+            .iter()
+            .filter_map(|name| self.modulation_at(name, elapsed_ms))
+            .find(|modulation| modulation.frequency > 0.0)
     }
 
     pub fn update(&mut self, raw: &UnifiedSensorRaw) -> UnifiedSensorFiltered {
-        // Apply moving average first
-        let no2_avg = Self::moving_average(&mut self.no2, raw.no2, self.window_size);
-        let eth_avg = Self::moving_average(&mut self.eth, raw.eth, self.window_size);
-        let voc_avg = Self::moving_average(&mut self.voc, raw.voc, self.window_size);
-        let co_avg = Self::moving_average(&mut self.co, raw.co, self.window_size);
-        let com_avg = Self::moving_average(&mut self.com, raw.com, self.window_size);
-        let ethm_avg = Self::moving_average(&mut self.ethm, raw.ethm, self.window_size);
-        let vocm_avg = Self::moving_average(&mut self.vocm, raw.vocm, self.window_size);
-
-        // Then apply sinusoidal modulation
+        // Apply each channel's filter chain first
+        let input = StageInput { state: raw.state };
+        let mut smoothed = Channels::default();
+        for channel in Channel::ALL {
+            smoothed[channel] = self.chains[channel.index()].push(raw.channels[channel], &input);
+        }
+        self.record_metrics(&raw.channels, &smoothed);
+
+        // Virtual channels dihitung dari data mentah, lalu difilter seperti kanal fisik
+        let mut virtual_channels = self.virtual_channels.evaluate(raw);
+        for (name, value) in virtual_channels.iter_mut() {
+            if let Some(chain) = self.virtual_chains.get_mut(name) {
+                *value = chain.push(*value, &input);
+            }
+        }
+
+        // Then apply sinusoidal modulation, per channel
+        let elapsed_ms = self.elapsed_ms();
+        let mut modulation = BTreeMap::new();
+        let mut channels = smoothed;
+        for channel in Channel::ALL {
+            if let Some(m) = self.modulation_at(channel.name(), elapsed_ms) {
+                channels[channel] *= m.factor;
+                modulation.insert(channel.name().to_string(), m);
+            }
+        }
+        for (name, value) in virtual_channels.iter_mut() {
+            if let Some(m) = self.modulation_at(name, elapsed_ms) {
+                *value *= m.factor;
+                modulation.insert(name.clone(), m);
+            }
+        }
+        // Formula yang memakai kanal mati tidak punya nilai
+        virtual_channels.retain(|_, value| !value.is_nan());
+
         UnifiedSensorFiltered {
-            no2: self.apply_sine_modulation(no2_avg),
-            eth: self.apply_sine_modulation(eth_avg),
-            voc: self.apply_sine_modulation(voc_avg),
-            co: self.apply_sine_modulation(co_avg),
-            com: self.apply_sine_modulation(com_avg),
-            ethm: self.apply_sine_modulation(ethm_avg),
-            vocm: self.apply_sine_modulation(vocm_avg),
+            channels,
             state: raw.state,
             level: raw.level,
+            // Flow/pressure/RH/heater dipakai untuk alarm & kontrol, tidak difilter
+            flow: raw.flow,
+            pressure: raw.pressure,
+            humidity: raw.humidity,
+            heater_temp: raw.heater_temp,
+            virtual_channels,
+            modulation,
+        }
+    }
+
+    fn record_metrics(&mut self, raw: &Channels, smoothed: &Channels) {
+        if self.metrics_window == 0 {
+            return;
+        }
+        for (channel, value) in raw.iter() {
+            if value.is_nan() {
+                continue;
+            }
+            push_bounded(&mut self.raw_history[channel.index()], value, self.metrics_window);
+            push_bounded(&mut self.smoothed_history[channel.index()], smoothed[channel], self.metrics_window);
+        }
+        self.frames_since_metrics += 1;
+    }
+
+    /// Noise-reduction metrics, returned once per full window of new samples.
+    /// Measured on the smoothing output, before sine modulation.
+    pub fn take_metrics(&mut self) -> Option<FilterMetrics> {
+        if self.metrics_window == 0 || self.frames_since_metrics < self.metrics_window {
+            return None;
         }
+        self.frames_since_metrics = 0;
+
+        let channels = CHANNEL_NAMES
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let raw_variance = variance(&self.raw_history[i]);
+                let filtered_variance = variance(&self.smoothed_history[i]);
+                let ratio = (filtered_variance > f32::EPSILON).then(|| raw_variance / filtered_variance);
+                (*name, ChannelNoise { raw_variance, filtered_variance, noise_reduction_ratio: ratio })
+            })
+            .collect();
+
+        Some(FilterMetrics { window: self.metrics_window, channels })
+    }
+}
+
+fn push_bounded(values: &mut VecDeque<f32>, value: f32, limit: usize) {
+    values.push_back(value);
+    while values.len() > limit {
+        values.pop_front();
+    }
+}
+
+fn variance(values: &VecDeque<f32>) -> f32 {
+    if values.len() < 2 {
+        return 0.0;
     }
+    let n = values.len() as f32;
+    let mean = values.iter().sum::<f32>() / n;
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / (n - 1.0)
 }
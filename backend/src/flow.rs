@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct FlowConfig {
+    /// Alarm when sample flow stays below this value (same unit as firmware)
+    #[serde(default)]
+    pub min_flow: Option<f32>,
+    /// How long flow must stay low before the alarm fires
+    #[serde(default = "default_loss_secs")]
+    pub loss_secs: f32,
+    #[serde(default)]
+    pub min_pressure: Option<f32>,
+    #[serde(default)]
+    pub max_pressure: Option<f32>,
+}
+
+fn default_loss_secs() -> f32 { 5.0 }
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    flow: FlowConfig,
+}
+
+impl FlowConfig {
+    /// Load alarm thresholds, applying the device's profile overrides
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc).try_into::<ConfigFile>().unwrap_or_default().flow
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowAlarmKind {
+    FlowLoss,
+    PressureLow,
+    PressureHigh,
+}
+
+/// Alarm raised or cleared by this frame
+#[derive(Debug, Serialize, Clone)]
+pub struct FlowAlarm {
+    pub alarm: FlowAlarmKind,
+    pub active: bool,
+    pub value: f32,
+    pub timestamp: i64,
+}
+
+// ================= FlowMonitor =================
+#[derive(Clone)]
+pub struct FlowMonitor {
+    config: FlowConfig,
+    low_flow_since: Option<i64>,
+    active: Vec<FlowAlarmKind>,
+    last_state: i32,
+    cycle_valid: bool,
+    /// Alarm that just made the running cycle invalid, taken once per cycle
+    invalidated: Option<FlowAlarmKind>,
+}
+
+impl FlowMonitor {
+    pub fn new(config: &FlowConfig) -> Self {
+        Self {
+            config: config.clone(),
+            low_flow_since: None,
+            active: Vec::new(),
+            last_state: 0,
+            cycle_valid: true,
+            invalidated: None,
+        }
+    }
+
+    /// Whether the current cycle is free of flow/pressure faults
+    pub fn cycle_valid(&self) -> bool {
+        self.cycle_valid
+    }
+
+    /// Alarms currently active
+    pub fn active(&self) -> &[FlowAlarmKind] {
+        &self.active
+    }
+
+    /// The alarm that invalidated the running cycle, once, on the frame it happened
+    pub fn take_invalidation(&mut self) -> Option<FlowAlarmKind> {
+        self.invalidated.take()
+    }
+
+    pub fn update(&mut self, state: i32, flow: Option<f32>, pressure: Option<f32>, now_ms: i64) -> Vec<FlowAlarm> {
+        // Siklus baru dimulai saat masuk PRE_COND
+        if state == 1 && self.last_state != 1 {
+            self.cycle_valid = true;
+        }
+        self.last_state = state;
+
+        let mut events = Vec::new();
+
+        if let (Some(flow), Some(min_flow)) = (flow, self.config.min_flow) {
+            let low = if flow < min_flow {
+                let since = *self.low_flow_since.get_or_insert(now_ms);
+                (now_ms - since) as f32 / 1000.0 >= self.config.loss_secs
+            } else {
+                self.low_flow_since = None;
+                false
+            };
+            self.set(FlowAlarmKind::FlowLoss, low, flow, now_ms, &mut events);
+        }
+
+        if let Some(pressure) = pressure {
+            let low = self.config.min_pressure.is_some_and(|min| pressure < min);
+            let high = self.config.max_pressure.is_some_and(|max| pressure > max);
+            self.set(FlowAlarmKind::PressureLow, low, pressure, now_ms, &mut events);
+            self.set(FlowAlarmKind::PressureHigh, high, pressure, now_ms, &mut events);
+        }
+
+        // Alarm apapun selama siklus aktif membuat siklus tidak valid
+        if self.cycle_valid && (1..=5).contains(&state) {
+            if let Some(kind) = self.active.first() {
+                self.cycle_valid = false;
+                self.invalidated = Some(*kind);
+            }
+        }
+
+        events
+    }
+
+    fn set(&mut self, kind: FlowAlarmKind, condition: bool, value: f32, now_ms: i64, events: &mut Vec<FlowAlarm>) {
+        let was_active = self.active.contains(&kind);
+        if condition == was_active {
+            return;
+        }
+        if condition {
+            self.active.push(kind);
+        } else {
+            self.active.retain(|k| *k != kind);
+        }
+        events.push(FlowAlarm { alarm: kind, active: condition, value, timestamp: now_ms });
+    }
+}
@@ -0,0 +1,157 @@
+use serde_json::{Map, Value};
+use std::path::Path;
+
+use crate::{state_to_name, UnifiedSensorData};
+
+/// Version of the stored frame layout (`UnifiedSensorData` fields and CSV
+/// columns). Bump it and add a migration whenever a field is renamed,
+/// removed or changes meaning. Recordings carry it in metadata.json as
+/// `frame_schema`, InfluxDB points as the `frame_schema` tag.
+pub const FRAME_SCHEMA: u32 = 3;
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// Frame migrations; entry N upgrades a version N + 1 frame to N + 2.
+/// Version 1 is everything recorded before frames were versioned.
+const MIGRATIONS: &[(&str, Migration)] = &[
+    ("fill fields older backends did not send", fill_defaults),
+    ("modulation per channel", modulation_per_channel),
+];
+
+/// v1 → v2: early frames had no state name, device, validity flag or source
+fn fill_defaults(frame: &mut Map<String, Value>) {
+    let state = frame.get("state").and_then(Value::as_i64).unwrap_or(0) as i32;
+    frame.entry("state").or_insert(state.into());
+    frame.entry("state_name").or_insert_with(|| state_to_name(state).into());
+    frame.entry("level").or_insert(0.into());
+    frame.entry("cycle_valid").or_insert(true.into());
+    frame.entry("source").or_insert("arduino".into());
+    frame.entry("device").or_insert("".into());
+}
+
+/// v2 → v3: one `modulation` object applied to every channel became a map
+/// of channel → modulation
+fn modulation_per_channel(frame: &mut Map<String, Value>) {
+    if frame.get("modulation").is_none_or(|m| m.get("factor").is_none()) {
+        return;
+    }
+    let modulation = frame.remove("modulation").unwrap_or_default();
+    let channels: Map<String, Value> = crate::CHANNEL_NAMES
+        .iter()
+        .filter(|name| frame.get(**name).is_some_and(|v| !v.is_null()))
+        .map(|name| (name.to_string(), modulation.clone()))
+        .collect();
+    frame.insert("modulation".to_string(), Value::Object(channels));
+}
+
+/// Bring one stored frame of schema `version` up to the current layout
+pub fn upgrade(frame: Value, version: u32) -> Result<UnifiedSensorData, String> {
+    if version > FRAME_SCHEMA {
+        return Err(format!("frame schema v{} is newer than this release (v{})", version, FRAME_SCHEMA));
+    }
+    let Value::Object(mut frame) = frame else {
+        return Err("frame is not a JSON object".to_string());
+    };
+    for (_, migrate) in MIGRATIONS.iter().skip(version.max(1) as usize - 1) {
+        migrate(&mut frame);
+    }
+    serde_json::from_value(Value::Object(frame)).map_err(|e| e.to_string())
+}
+
+/// Schema a recording was written with; recordings without one predate versioning
+fn recording_version(metadata: &Value) -> u32 {
+    metadata.get("frame_schema").and_then(Value::as_u64).map_or(1, |v| v as u32)
+}
+
+// ================= Archive Readers =================
+/// Every frame of a recording directory (see recording.rs), oldest first,
+/// upgraded to the current schema, with the version it was recorded in.
+/// JSON segments are preferred over CSV since they also hold virtual channels.
+pub fn read_recording(dir: &Path) -> Result<(u32, Vec<UnifiedSensorData>), String> {
+    let path = dir.join("metadata.json");
+    let metadata: Value = std::fs::read_to_string(&path)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e)))?;
+    let version = recording_version(&metadata);
+    if version > FRAME_SCHEMA {
+        return Err(format!("{}: recorded with frame schema v{}, this release only reads up to v{}", dir.display(), version, FRAME_SCHEMA));
+    }
+
+    let files: Vec<&str> = metadata
+        .get("segments")
+        .and_then(Value::as_array)
+        .map(|segments| segments.iter().filter_map(|s| s.get("file").and_then(Value::as_str)).collect())
+        .unwrap_or_default();
+
+    let mut frames = Vec::new();
+    for file in files {
+        let json = dir.join(file.replace(".csv", ".json"));
+        let values = match json.is_file() {
+            true => read_json(&json)?,
+            false => read_csv(&dir.join(file))?,
+        };
+        for (i, value) in values.into_iter().enumerate() {
+            frames.push(upgrade(value, version).map_err(|e| format!("{} frame {}: {}", file, i + 1, e))?);
+        }
+    }
+    frames.sort_by_key(|frame| frame.timestamp);
+    Ok((version, frames))
+}
+
+fn read_json(path: &Path) -> Result<Vec<Value>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Recording CSV → frame objects, by header name so columns added or
+/// dropped between versions are tolerated. Empty cells are missing values.
+fn read_csv(path: &Path) -> Result<Vec<Value>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut lines = content.lines();
+    let header: Vec<&str> = lines.next().unwrap_or_default().split(',').collect();
+
+    let mut frames = Vec::new();
+    for line in lines.filter(|l| !l.trim().is_empty()) {
+        let mut frame = Map::new();
+        for (column, cell) in header.iter().zip(line.split(',')) {
+            match *column {
+                "timestamp" => {
+                    // Kolom lama tanpa epoch_ms: pakai waktu RFC 3339
+                    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(cell) {
+                        frame.entry("timestamp").or_insert(time.timestamp_millis().into());
+                    }
+                }
+                "epoch_ms" => {
+                    if let Ok(ms) = cell.parse::<i64>() {
+                        frame.insert("timestamp".to_string(), ms.into());
+                    }
+                }
+                // CSV menyimpan nama state, bukan angkanya
+                "state" => {
+                    let state = (0..=crate::ambient::AMBIENT).find(|s| state_to_name(*s) == cell).unwrap_or(-1);
+                    frame.insert("state".to_string(), state.into());
+                    frame.insert("state_name".to_string(), cell.into());
+                }
+                "device" => {
+                    frame.insert("device".to_string(), cell.into());
+                }
+                "cycle_valid" => {
+                    frame.insert("cycle_valid".to_string(), (cell != "false").into());
+                }
+                "level" => {
+                    frame.insert("level".to_string(), cell.parse::<i64>().unwrap_or_default().into());
+                }
+                name => {
+                    let value = cell.parse::<f64>().ok().map_or(Value::Null, Value::from);
+                    if !value.is_null() || crate::CHANNEL_NAMES.contains(&name) {
+                        frame.insert(name.to_string(), value);
+                    }
+                }
+            }
+        }
+        // Baris CSV tidak membawa source
+        frame.entry("source").or_insert("recording".into());
+        frames.push(Value::Object(frame));
+    }
+    Ok(frames)
+}
@@ -0,0 +1,309 @@
+use std::collections::VecDeque;
+use std::path::Path;
+
+use crate::channels::Channels;
+use crate::clock::ManualClock;
+use crate::cross_correlation::pair_metric;
+use crate::filtering::{FilterConfig, SensorFilters, UnifiedSensorRaw};
+use crate::CHANNEL_NAMES;
+
+/// Directory (relative to the backend crate) holding the checked-in vectors
+pub const GOLDEN_DIR: &str = "golden";
+
+/// Relative tolerance when comparing against a golden value
+const TOLERANCE: f64 = 1e-5;
+
+/// Filter chains under test, as config.toml snippets
+const FILTER_CASES: &[(&str, &str)] = &[
+    ("moving_average_w5", "window_size = 5\nsine_enabled = false"),
+    ("moving_average_w1", "window_size = 1\nsine_enabled = false"),
+    (
+        "wavelet_haar",
+        "window_size = 5\nsine_enabled = false\nfilter = \"wavelet\"\nwavelet = \"haar\"\nwavelet_level = 2\nwavelet_window = 16",
+    ),
+    ("wavelet_db2", "window_size = 5\nsine_enabled = false\nfilter = \"wavelet\"\nwavelet = \"db2\""),
+    ("wavelet_db4", "window_size = 5\nsine_enabled = false\nfilter = \"wavelet\"\nwavelet = \"db4\""),
+    (
+        "virtual_channels",
+        "window_size = 5\nsine_enabled = false\n[virtual_channels]\nvoc_total = \"0.6*voc + 0.4*vocm\"\nco_ratio = \"co / (no2 + 1)\"",
+    ),
+    ("ema_a03", "window_size = 5\nsine_enabled = false\nfilter = \"ema\"\nema_alpha = 0.3"),
+    ("sine_modulation", "window_size = 5\nsine_amplitude = 0.15\nsine_frequency = 0.5"),
+];
+
+/// Input samples are spaced this far apart on the injected clock (10 Hz)
+const SAMPLE_MS: i64 = 100;
+
+/// Window (samples) for the feature-extraction vector
+const FEATURE_WINDOW: usize = 20;
+
+type Table = (Vec<String>, Vec<Vec<Option<f64>>>);
+
+// ================= Golden Vectors =================
+/// Deterministic raw input: baseline, gas exposure step, recovery, plus
+/// pseudo-random noise so smoothing has something to do
+fn synthetic_input() -> Table {
+    let mut seed: u32 = 0x2545F491;
+    let mut noise = move || {
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        (seed >> 8) as f64 / (1u32 << 24) as f64 - 0.5
+    };
+    let rows = (0..240)
+        .map(|i| {
+            let exposure = if (60..180).contains(&i) { 1.0 - (-((i - 60) as f64) / 15.0).exp() } else { 0.0 };
+            let recovery = if i >= 180 { (-((i - 180) as f64) / 25.0).exp() } else { 0.0 };
+            let response = exposure + recovery * (1.0 - (-120.0f64 / 15.0).exp());
+            CHANNEL_NAMES
+                .iter()
+                .enumerate()
+                .map(|(c, _)| {
+                    let baseline = 100.0 + 40.0 * c as f64;
+                    let gain = 50.0 + 30.0 * c as f64;
+                    Some(((baseline + gain * response + 4.0 * noise()) * 1000.0).round() / 1000.0)
+                })
+                .collect()
+        })
+        .collect();
+    (CHANNEL_NAMES.iter().map(|c| c.to_string()).collect(), rows)
+}
+
+fn raw_frame(row: &[Option<f64>]) -> UnifiedSensorRaw {
+    let value = |i: usize| row.get(i).copied().flatten().unwrap_or_default() as f32;
+    UnifiedSensorRaw {
+        channels: Channels::new(std::array::from_fn(value)),
+        state: 2,
+        level: 1,
+        flow: None,
+        pressure: None,
+        humidity: None,
+        heater_temp: None,
+    }
+}
+
+fn run_filter(config: &str, input: &Table) -> anyhow::Result<Table> {
+    let config: FilterConfig = toml::from_str(config)?;
+    let clock = ManualClock::new(0);
+    let mut filters = SensorFilters::with_clock(&config, clock.clone());
+    let mut columns: Vec<String> = CHANNEL_NAMES.iter().map(|c| c.to_string()).collect();
+    columns.extend(config.virtual_channels.keys().cloned());
+
+    let rows = input
+        .1
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            clock.set(i as i64 * SAMPLE_MS);
+            let filtered = filters.update(&raw_frame(row));
+            let mut out: Vec<Option<f64>> = filtered.channels.iter().map(|(_, v)| Some(v as f64)).collect();
+            out.extend(config.virtual_channels.keys().map(|name| filtered.virtual_channels.get(name).map(|v| *v as f64)));
+            out
+        })
+        .collect();
+    Ok((columns, rows))
+}
+
+/// Windowed features over the moving-average output: noise reduction per
+/// channel plus correlation/ratio for fixed channel pairs
+fn run_features(input: &Table) -> anyhow::Result<Table> {
+    let config: FilterConfig =
+        toml::from_str(&format!("window_size = 5\nsine_enabled = false\nmetrics_window = {}", FEATURE_WINDOW))?;
+    let mut filters = SensorFilters::new(&config);
+    let pairs = [(0, 3), (1, 2), (2, 6), (4, 5)];
+
+    let mut columns = vec!["sample".to_string()];
+    columns.extend(CHANNEL_NAMES.iter().map(|c| format!("{}_noise_reduction", c)));
+    for (a, b) in pairs {
+        columns.push(format!("{}_{}_correlation", CHANNEL_NAMES[a], CHANNEL_NAMES[b]));
+        columns.push(format!("{}_{}_ratio", CHANNEL_NAMES[a], CHANNEL_NAMES[b]));
+    }
+
+    let mut history: [VecDeque<f32>; 7] = Default::default();
+    let mut rows = Vec::new();
+    for (i, row) in input.1.iter().enumerate() {
+        let filtered = filters.update(&raw_frame(row));
+        for (buffer, value) in history.iter_mut().zip(filtered.channels.values()) {
+            buffer.push_back(value);
+            if buffer.len() > FEATURE_WINDOW {
+                buffer.pop_front();
+            }
+        }
+        let Some(metrics) = filters.take_metrics() else {
+            continue;
+        };
+        let mut out = vec![Some(i as f64)];
+        out.extend(CHANNEL_NAMES.iter().map(|c| metrics.channels[c].noise_reduction_ratio.map(|v| v as f64)));
+        for (a, b) in pairs {
+            let (correlation, ratio) = pair_metric(&history[a], &history[b]);
+            out.push(correlation.map(|v| v as f64));
+            out.push(ratio.map(|v| v as f64));
+        }
+        rows.push(out);
+    }
+    Ok((columns, rows))
+}
+
+/// Names of all golden cases (one `<name>.csv` each)
+pub fn cases() -> Vec<&'static str> {
+    FILTER_CASES.iter().map(|(name, _)| *name).chain(["features"]).collect()
+}
+
+fn compute(name: &str, input: &Table) -> anyhow::Result<Table> {
+    if name == "features" {
+        return run_features(input);
+    }
+    match FILTER_CASES.iter().find(|(case, _)| *case == name) {
+        Some((_, config)) => run_filter(config, input),
+        None => anyhow::bail!("unknown golden case {}", name),
+    }
+}
+
+fn write_csv(path: &Path, table: &Table) -> anyhow::Result<()> {
+    let mut out = table.0.join(",");
+    out.push('\n');
+    for row in &table.1 {
+        // Semua nilai asalnya f32, tulis presisi f32 supaya diff mudah dibaca
+        let cells: Vec<String> = row.iter().map(|v| v.map(|v| (v as f32).to_string()).unwrap_or_default()).collect();
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+    std::fs::write(path, out)?;
+    Ok(())
+}
+
+fn read_csv(path: &Path) -> anyhow::Result<Table> {
+    let content = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+    let columns = lines.next().unwrap_or_default().split(',').map(|c| c.trim().to_string()).collect();
+    let rows = lines
+        .map(|line| {
+            line.split(',')
+                .map(|cell| match cell.trim() {
+                    "" => Ok(None),
+                    v => v.parse().map(Some),
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<_, _>>()
+        .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    Ok((columns, rows))
+}
+
+fn load_input(dir: &Path) -> anyhow::Result<Table> {
+    read_csv(&dir.join("input.csv"))
+}
+
+/// Compare one case against its golden CSV; Err lists the first differences
+pub fn check(dir: &Path, name: &str) -> anyhow::Result<()> {
+    let input = load_input(dir)?;
+    let (columns, rows) = compute(name, &input)?;
+    let (expected_columns, expected_rows) = read_csv(&dir.join(format!("{}.csv", name)))?;
+    if columns != expected_columns {
+        anyhow::bail!("{}: columns {:?}, golden has {:?}", name, columns, expected_columns);
+    }
+    if rows.len() != expected_rows.len() {
+        anyhow::bail!("{}: {} rows, golden has {}", name, rows.len(), expected_rows.len());
+    }
+
+    let mut diffs = Vec::new();
+    for (i, (row, expected)) in rows.iter().zip(&expected_rows).enumerate() {
+        for ((column, value), golden) in columns.iter().zip(row).zip(expected) {
+            let matches = match (value, golden) {
+                (Some(v), Some(g)) => (v - g).abs() <= TOLERANCE * g.abs().max(1.0),
+                (None, None) => true,
+                _ => false,
+            };
+            if !matches {
+                diffs.push(format!("row {} {}: got {:?}, golden {:?}", i, column, value, golden));
+            }
+        }
+    }
+    if !diffs.is_empty() {
+        let shown: Vec<_> = diffs.iter().take(10).cloned().collect();
+        anyhow::bail!("{}: {} values differ\n  {}", name, diffs.len(), shown.join("\n  "));
+    }
+    Ok(())
+}
+
+/// Rewrite golden outputs from the current algorithms. input.csv is only
+/// created when missing so existing vectors keep the same input.
+pub fn regenerate(dir: &Path) -> anyhow::Result<Vec<String>> {
+    std::fs::create_dir_all(dir)?;
+    let input_path = dir.join("input.csv");
+    if !input_path.exists() {
+        write_csv(&input_path, &synthetic_input())?;
+    }
+    let input = load_input(dir)?;
+    cases()
+        .into_iter()
+        .map(|name| {
+            let path = dir.join(format!("{}.csv", name));
+            write_csv(&path, &compute(name, &input)?)?;
+            Ok(path.display().to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn golden(name: &str) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDEN_DIR);
+        if let Err(e) = check(&dir, name) {
+            panic!("{}\nIf the change is intended, run `enose golden --regenerate` and review the diff", e);
+        }
+    }
+
+    #[test]
+    fn moving_average_w5() {
+        golden("moving_average_w5");
+    }
+
+    #[test]
+    fn moving_average_w1() {
+        golden("moving_average_w1");
+    }
+
+    #[test]
+    fn wavelet_haar() {
+        golden("wavelet_haar");
+    }
+
+    #[test]
+    fn wavelet_db2() {
+        golden("wavelet_db2");
+    }
+
+    #[test]
+    fn wavelet_db4() {
+        golden("wavelet_db4");
+    }
+
+    #[test]
+    fn virtual_channels() {
+        golden("virtual_channels");
+    }
+
+    #[test]
+    fn ema_a03() {
+        golden("ema_a03");
+    }
+
+    #[test]
+    fn sine_modulation() {
+        golden("sine_modulation");
+    }
+
+    #[test]
+    fn features() {
+        golden("features");
+    }
+
+    #[test]
+    fn every_case_has_a_vector() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDEN_DIR);
+        for name in cases() {
+            assert!(dir.join(format!("{}.csv", name)).exists(), "missing golden/{}.csv", name);
+        }
+    }
+}
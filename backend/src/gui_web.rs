@@ -0,0 +1,255 @@
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::privacy::{PrivacyConfig, Redactor};
+use crate::viewer::ViewerConfig;
+use crate::{AppState, GuiReply, UnifiedSensorData};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GuiConfig {
+    /// HTTP/WebSocket transport for GUI clients and browser dashboards
+    #[serde(default = "default_true")]
+    pub web_enabled: bool,
+    #[serde(default = "default_web_bind")]
+    pub web_bind: String,
+    /// Legacy newline-delimited JSON over TCP (desktop GUI)
+    #[serde(default = "default_true")]
+    pub tcp_enabled: bool,
+    #[serde(default = "default_tcp_bind")]
+    pub tcp_bind: String,
+}
+
+fn default_true() -> bool { true }
+fn default_web_bind() -> String { "0.0.0.0:8085".to_string() }
+fn default_tcp_bind() -> String { "0.0.0.0:8082".to_string() }
+
+impl Default for GuiConfig {
+    fn default() -> Self {
+        Self { web_enabled: true, web_bind: default_web_bind(), tcp_enabled: true, tcp_bind: default_tcp_bind() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    gui: GuiConfig,
+}
+
+impl GuiConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().gui
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandRequest {
+    command: String,
+}
+
+#[derive(Clone)]
+struct WebState {
+    app: AppState,
+    /// Newest frame, for GET /status
+    latest: Arc<Mutex<Option<UnifiedSensorData>>>,
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(serde_json::json!({ "error": message.into() }))).into_response()
+}
+
+// ================= GUI Web Server =================
+/// Same stream and commands as the TCP GUI port, over WebSocket + REST so a
+/// browser dashboard can connect directly
+pub async fn serve(config: GuiConfig, state: AppState) -> anyhow::Result<()> {
+    let listener = state.diagnostics.bind("gui_web", &config.web_bind).await?;
+    let latest = Arc::new(Mutex::new(None));
+    tokio::spawn(track_latest(state.frame_tx.subscribe(), latest.clone()));
+
+    let app = Router::new()
+        .route("/ws", get(websocket))
+        .route("/command", post(command))
+        .route("/status", get(status))
+        .with_state(WebState { app: state, latest });
+
+    println!("📡 GUI web server listening on {} (/ws, /command, /status)", config.web_bind);
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+    Ok(())
+}
+
+async fn track_latest(mut frame_rx: broadcast::Receiver<UnifiedSensorData>, latest: Arc<Mutex<Option<UnifiedSensorData>>>) {
+    loop {
+        match frame_rx.recv().await {
+            Ok(frame) => *latest.lock().unwrap() = Some(frame),
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// GET /ws   one JSON message per frame/event; text messages are commands
+async fn websocket(
+    State(state): State<WebState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade.on_upgrade(move |socket| handle_socket(socket, addr, state.app))
+}
+
+async fn handle_socket(mut socket: WebSocket, addr: SocketAddr, state: AppState) {
+    let mut data_rx = state.data_tx.subscribe();
+    let read_only = ViewerConfig::load("config.toml").applies_to(&addr);
+    println!("✅ GUI connected via WebSocket: {}{}", addr, if read_only { " (read-only viewer)" } else { "" });
+
+    let privacy = PrivacyConfig::load("config.toml");
+    let mut redactor = privacy.applies_to(&addr).then(|| Redactor::new(&privacy));
+    let client = state.clients.register(addr, redactor.is_some(), read_only);
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<String>();
+
+    let mut metadata = crate::channels::metadata_message("config.toml", None);
+    if let Some(redacted) = redactor.as_mut().and_then(|r| r.redact(&metadata)) {
+        metadata = redacted;
+    }
+    client.sent(metadata.len());
+    if socket.send(Message::Text(metadata.into())).await.is_err() {
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            msg = data_rx.recv() => {
+                let msg = match msg {
+                    Ok(msg) => msg,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        client.dropped(n);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let msg = match redactor.as_mut() {
+                    Some(redactor) => match redactor.redact(&msg) {
+                        Some(msg) => msg,
+                        None => continue,
+                    },
+                    None => msg,
+                };
+                client.sent(msg.len());
+                if socket.send(Message::Text(msg.into())).await.is_err() {
+                    break;
+                }
+            }
+
+            Some(reply) = reply_rx.recv() => {
+                client.sent(reply.len());
+                if socket.send(Message::Text(reply.into())).await.is_err() {
+                    break;
+                }
+            }
+
+            msg = socket.recv() => {
+                let cmd = match msg {
+                    Some(Ok(Message::Text(text))) => text.trim().to_string(),
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        eprintln!("❌ WebSocket read error: {}", e);
+                        break;
+                    }
+                };
+                client.received(cmd.len());
+                if cmd.is_empty() {
+                    continue;
+                }
+                let shown = crate::auth::redact(&cmd);
+                println!("📥 GUI command received (ws): '{}'", shown);
+                let client_id = addr.to_string();
+                let identity = state.auth.identity(&client_id);
+                let result = match crate::gui_permission(&cmd, &client_id, identity.as_ref(), &state, read_only) {
+                    Ok(()) => crate::gui_command(&cmd, &client_id, &state, Some(&reply_tx)).await,
+                    Err(e) => Err(e),
+                };
+                let reply = match result {
+                    Ok(GuiReply::Backend(reply)) => reply,
+                    Ok(GuiReply::Forwarded(id)) => crate::command_sent(&cmd, id),
+                    Err(e) => {
+                        eprintln!("❌ Command '{}' failed: {}", shown, e);
+                        Some(serde_json::json!({ "type": "command_error", "command": shown, "error": e }).to_string())
+                    }
+                };
+                if let Some(reply) = reply {
+                    client.sent(reply.len());
+                    if socket.send(Message::Text(reply.into())).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    state.control.release(&addr.to_string(), "disconnected");
+    state.auth.logout(&addr.to_string());
+    println!("❌ WebSocket GUI disconnected: {}", addr);
+}
+
+/// POST /command   {"command": "START_SAMPLING"}
+/// The caller is identified by its IP for the viewer list and control lock,
+/// and by `Authorization: Bearer <token>` for its permission groups.
+async fn command(
+    State(state): State<WebState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<CommandRequest>,
+) -> Response {
+    let state = state.app;
+    let cmd = request.command.trim();
+    if cmd.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "empty command");
+    }
+    let client_id = addr.ip().to_string();
+    let read_only = ViewerConfig::load("config.toml").applies_to(&addr);
+    let identity = match crate::auth::bearer(&headers) {
+        Some(token) => match state.auth.lookup(token) {
+            Some(identity) => Some(identity),
+            None => return error(StatusCode::UNAUTHORIZED, "invalid token"),
+        },
+        None => None,
+    };
+    if let Err(e) = crate::gui_permission(cmd, &client_id, identity.as_ref(), &state, read_only) {
+        return error(StatusCode::FORBIDDEN, e);
+    }
+    println!("📥 GUI command received (http): '{}'", crate::auth::redact(cmd));
+    match crate::gui_command(cmd, &client_id, &state, None).await {
+        Ok(GuiReply::Backend(reply)) => {
+            let reply = reply.map(|r| serde_json::from_str::<serde_json::Value>(&r).unwrap_or(serde_json::Value::String(r)));
+            Json(serde_json::json!({ "command": cmd, "handled_by": "backend", "reply": reply })).into_response()
+        }
+        Ok(GuiReply::Forwarded(_)) => {
+            (StatusCode::ACCEPTED, Json(serde_json::json!({ "command": cmd, "handled_by": "device" }))).into_response()
+        }
+        Err(e) => error(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+/// GET /status   devices, connections, control lock and the newest frame
+async fn status(State(state): State<WebState>) -> Response {
+    let latest = state.latest.lock().unwrap().clone();
+    let state = state.app;
+    Json(serde_json::json!({
+        "backend_version": env!("CARGO_PKG_VERSION"),
+        "failover": state.failover.health(),
+        "devices": state.devices.snapshot(),
+        "gui_clients": state.clients.snapshot(),
+        "control": state.control.status(),
+        "disabled_channels": state.channel_mask.disabled(),
+        "latest": latest,
+    }))
+    .into_response()
+}
@@ -0,0 +1,368 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::pid::PidGains;
+use crate::UnifiedSensorData;
+use crate::devices::DeviceRegistry;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HeaterConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Heater temperature setpoint (°C)
+    #[serde(default = "default_setpoint")]
+    pub setpoint: f32,
+    /// Highest setpoint accepted from config, GUI or scripts (°C)
+    #[serde(default = "default_max_setpoint")]
+    pub max_setpoint: f32,
+    #[serde(default = "default_gains")]
+    pub pid: PidGains,
+    #[serde(default = "default_pid_command")]
+    pub pid_command: String,
+    #[serde(default = "default_setpoint_command")]
+    pub setpoint_command: String,
+    /// Open-loop output command used by autotune
+    #[serde(default = "default_manual_command")]
+    pub manual_command: String,
+    /// Returns the firmware to closed-loop control
+    #[serde(default = "default_auto_command")]
+    pub auto_command: String,
+    #[serde(default = "default_step_output")]
+    pub autotune_step_output: f32,
+    #[serde(default = "default_autotune_baseline_secs")]
+    pub autotune_baseline_secs: u64,
+    #[serde(default = "default_autotune_secs")]
+    pub autotune_secs: u64,
+}
+
+fn default_setpoint() -> f32 { 300.0 }
+fn default_max_setpoint() -> f32 { 450.0 }
+fn default_gains() -> PidGains { PidGains { kp: 2.0, ki: 0.1, kd: 0.5 } }
+fn default_pid_command() -> String { "HEATER_PID {kp} {ki} {kd}".to_string() }
+fn default_setpoint_command() -> String { "HEATER_SET {value}".to_string() }
+fn default_manual_command() -> String { "HEATER_MANUAL {value}".to_string() }
+fn default_auto_command() -> String { "HEATER_AUTO".to_string() }
+fn default_step_output() -> f32 { 50.0 }
+fn default_autotune_baseline_secs() -> u64 { 10 }
+fn default_autotune_secs() -> u64 { 300 }
+
+impl Default for HeaterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            setpoint: default_setpoint(),
+            max_setpoint: default_max_setpoint(),
+            pid: default_gains(),
+            pid_command: default_pid_command(),
+            setpoint_command: default_setpoint_command(),
+            manual_command: default_manual_command(),
+            auto_command: default_auto_command(),
+            autotune_step_output: default_step_output(),
+            autotune_baseline_secs: default_autotune_baseline_secs(),
+            autotune_secs: default_autotune_secs(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    heater: HeaterConfig,
+}
+
+impl HeaterConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().heater
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.max_setpoint.is_finite() || self.max_setpoint <= 0.0 {
+            return Err("heater.max_setpoint must be a positive number".to_string());
+        }
+        self.check_setpoint(self.setpoint).map_err(|e| format!("heater.setpoint: {}", e))?;
+        check_gains(&self.pid).map_err(|e| format!("heater.pid: {}", e))
+    }
+
+    fn check_setpoint(&self, setpoint: f32) -> Result<(), String> {
+        if !setpoint.is_finite() || !(0.0..=self.max_setpoint).contains(&setpoint) {
+            return Err(format!("setpoint must be within 0..{} °C", self.max_setpoint));
+        }
+        Ok(())
+    }
+}
+
+/// Gains the firmware may be given: finite and not negative
+fn check_gains(gains: &PidGains) -> Result<(), String> {
+    let PidGains { kp, ki, kd } = *gains;
+    if [kp, ki, kd].iter().any(|g| !g.is_finite() || *g < 0.0) {
+        return Err("gains must be finite and not negative".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+struct HeaterSettings {
+    setpoint: f32,
+    pid: PidGains,
+}
+
+/// First-order-plus-dead-time model fitted from a step response
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct StepModel {
+    pub gain: f32,
+    pub dead_time: f32,
+    pub time_constant: f32,
+}
+
+// ================= HeaterControl =================
+#[derive(Clone)]
+pub struct HeaterControl {
+    config: Arc<HeaterConfig>,
+    /// Setting yang diubah lewat GUI/autotune, per device; sisanya pakai config
+    settings: Arc<Mutex<HashMap<String, HeaterSettings>>>,
+    /// Device being autotuned
+    autotuning: Arc<Mutex<Option<String>>>,
+    cmd_tx: broadcast::Sender<String>,
+    data_tx: broadcast::Sender<String>,
+    frame_tx: broadcast::Sender<UnifiedSensorData>,
+    devices: DeviceRegistry,
+}
+
+impl HeaterControl {
+    pub fn new(
+        config: HeaterConfig,
+        cmd_tx: broadcast::Sender<String>,
+        data_tx: broadcast::Sender<String>,
+        frame_tx: broadcast::Sender<UnifiedSensorData>,
+        devices: DeviceRegistry,
+    ) -> Self {
+        Self {
+            config: Arc::new(config),
+            settings: Arc::new(Mutex::new(HashMap::new())),
+            autotuning: Arc::new(Mutex::new(None)),
+            cmd_tx,
+            data_tx,
+            frame_tx,
+            devices,
+        }
+    }
+
+    fn defaults(&self) -> HeaterSettings {
+        HeaterSettings { setpoint: self.config.setpoint, pid: self.config.pid }
+    }
+
+    fn settings(&self, device: &str) -> HeaterSettings {
+        self.settings.lock().unwrap().get(device).copied().unwrap_or_else(|| self.defaults())
+    }
+
+    /// Current setpoint of `device`, if the backend manages the heater
+    pub fn setpoint(&self, device: &str) -> Option<f32> {
+        self.config.enabled.then(|| self.settings(device).setpoint)
+    }
+
+    /// Push PID gains and setpoint to the firmware of `device`
+    pub fn push(&self, device: &str) {
+        if !self.config.enabled {
+            return;
+        }
+        let settings = self.settings(device);
+        let pid_cmd = self
+            .config
+            .pid_command
+            .replace("{kp}", &settings.pid.kp.to_string())
+            .replace("{ki}", &settings.pid.ki.to_string())
+            .replace("{kd}", &settings.pid.kd.to_string());
+        let _ = self.cmd_tx.send(format!("{}:{}", device, pid_cmd));
+        let setpoint_cmd = self.config.setpoint_command.replace("{value}", &settings.setpoint.to_string());
+        let _ = self.cmd_tx.send(format!("{}:{}", device, setpoint_cmd));
+        println!(
+            "🔥 Heater settings pushed to {}: setpoint {:.1}, kp {} ki {} kd {}",
+            device, settings.setpoint, settings.pid.kp, settings.pid.ki, settings.pid.kd
+        );
+    }
+
+    pub fn publish(&self) {
+        let defaults = self.defaults();
+        let autotuning = self.autotuning.lock().unwrap().clone();
+        let event = serde_json::json!({
+            "type": "heater",
+            "enabled": self.config.enabled,
+            "setpoint": defaults.setpoint,
+            "pid": defaults.pid,
+            "devices": *self.settings.lock().unwrap(),
+            "autotuning": autotuning.is_some(),
+            "autotune_device": autotuning,
+        });
+        let _ = self.data_tx.send(event.to_string());
+    }
+
+    fn set_gains(&self, device: &str, gains: PidGains) -> Result<(), String> {
+        check_gains(&gains)?;
+        let settings = HeaterSettings { pid: gains, ..self.settings(device) };
+        self.settings.lock().unwrap().insert(device.to_string(), settings);
+        self.push(device);
+        self.publish();
+        Ok(())
+    }
+
+    fn set_setpoint(&self, device: &str, setpoint: f32) -> Result<(), String> {
+        self.config.check_setpoint(setpoint)?;
+        let settings = HeaterSettings { setpoint, ..self.settings(device) };
+        self.settings.lock().unwrap().insert(device.to_string(), settings);
+        self.push(device);
+        self.publish();
+        Ok(())
+    }
+
+    pub fn start_autotune(&self, device: Option<&str>) -> Result<(), String> {
+        if !self.config.enabled {
+            return Err("heater control is disabled in config".to_string());
+        }
+        let device = self.devices.resolve(device)?;
+        {
+            let mut autotuning = self.autotuning.lock().unwrap();
+            if autotuning.is_some() {
+                return Err("autotune already running".to_string());
+            }
+            *autotuning = Some(device.clone());
+        }
+        let heater = self.clone();
+        tokio::spawn(async move {
+            heater.autotune(&device).await;
+            *heater.autotuning.lock().unwrap() = None;
+            heater.publish();
+        });
+        Ok(())
+    }
+
+    // ================= Autotune =================
+    /// Open-loop step test, FOPDT fit, Ziegler-Nichols reaction-curve tuning
+    async fn autotune(&self, device: &str) {
+        println!("🔥 Heater autotune started on {}", device);
+        self.publish();
+        let mut frame_rx = self.frame_tx.subscribe();
+        let manual = |value: &str| format!("{}:{}", device, self.config.manual_command.replace("{value}", value));
+
+        let _ = self.cmd_tx.send(manual("0"));
+        let baseline = collect(&mut frame_rx, device, self.config.autotune_baseline_secs).await;
+        let step = self.config.autotune_step_output;
+        let _ = self.cmd_tx.send(manual(&step.to_string()));
+        let response = collect(&mut frame_rx, device, self.config.autotune_secs).await;
+        let _ = self.cmd_tx.send(format!("{}:{}", device, self.config.auto_command));
+
+        let result = fit_step_response(&baseline, &response, step).and_then(|model| {
+            let gains = ziegler_nichols(&model).filter(|gains| check_gains(gains).is_ok())?;
+            Some((model, gains))
+        });
+
+        match result {
+            Some((model, gains)) => {
+                println!(
+                    "🔥 Autotune {}: K={:.3} L={:.1}s τ={:.1}s → kp {:.3} ki {:.4} kd {:.3}",
+                    device, model.gain, model.dead_time, model.time_constant, gains.kp, gains.ki, gains.kd
+                );
+                let event = serde_json::json!({ "type": "heater_autotune", "device": device, "model": model, "pid": gains });
+                let _ = self.data_tx.send(event.to_string());
+                let _ = self.set_gains(device, gains);
+            }
+            None => {
+                eprintln!("❌ Heater autotune on {} failed: no usable step response", device);
+                self.push(device);
+                let event = serde_json::json!({
+                    "type": "heater_autotune",
+                    "device": device,
+                    "error": "no usable step response",
+                });
+                let _ = self.data_tx.send(event.to_string());
+            }
+        }
+    }
+}
+
+/// (seconds since start, heater temperature) dari `device` selama `secs`
+async fn collect(frame_rx: &mut broadcast::Receiver<UnifiedSensorData>, device: &str, secs: u64) -> Vec<(f32, f32)> {
+    let start = tokio::time::Instant::now();
+    let deadline = start + Duration::from_secs(secs);
+    let mut samples = Vec::new();
+    loop {
+        match tokio::time::timeout_at(deadline, frame_rx.recv()).await {
+            Ok(Ok(frame)) if frame.device == device => {
+                if let Some(temp) = frame.heater_temp {
+                    samples.push((start.elapsed().as_secs_f32(), temp));
+                }
+            }
+            Ok(Ok(_)) | Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+            Ok(Err(broadcast::error::RecvError::Closed)) | Err(_) => break,
+        }
+    }
+    samples
+}
+
+fn fit_step_response(baseline: &[(f32, f32)], response: &[(f32, f32)], step: f32) -> Option<StepModel> {
+    if baseline.is_empty() || response.len() < 10 || step == 0.0 {
+        return None;
+    }
+    let initial = baseline.iter().map(|(_, t)| t).sum::<f32>() / baseline.len() as f32;
+    let tail = &response[response.len() * 9 / 10..];
+    let final_value = tail.iter().map(|(_, t)| t).sum::<f32>() / tail.len() as f32;
+    let delta = final_value - initial;
+    if delta.abs() < f32::EPSILON {
+        return None;
+    }
+
+    // Waktu saat respon mencapai fraksi tertentu dari perubahan total
+    let crossing = |fraction: f32| {
+        response
+            .iter()
+            .find(|(_, t)| (t - initial) / delta >= fraction)
+            .map(|(s, _)| *s)
+    };
+    let dead_time = crossing(0.05)?;
+    let t63 = crossing(0.632)?;
+
+    Some(StepModel {
+        gain: delta / step,
+        dead_time,
+        time_constant: (t63 - dead_time).max(0.1),
+    })
+}
+
+fn ziegler_nichols(model: &StepModel) -> Option<PidGains> {
+    if model.dead_time <= 0.0 || model.gain <= 0.0 {
+        return None;
+    }
+    let kp = 1.2 * model.time_constant / (model.gain * model.dead_time);
+    let ti = 2.0 * model.dead_time;
+    let td = 0.5 * model.dead_time;
+    Some(PidGains { kp, ki: kp / ti, kd: kp * td })
+}
+
+/// GUI commands (device defaults to the only connected one):
+///   HEATER_PID <kp> <ki> <kd> [device]
+///   HEATER_SETPOINT <value> [device]
+///   HEATER_AUTOTUNE [device]
+///   HEATER_STATUS
+pub fn handle_command(heater: &HeaterControl, args: &[&str]) -> Result<(), String> {
+    let num = |s: &str| s.parse::<f32>().map_err(|_| format!("invalid number '{}'", s));
+    let device = |rest: &[&str]| heater.devices.resolve(rest.first().copied());
+    match args {
+        ["HEATER_PID", kp, ki, kd, rest @ ..] if rest.len() <= 1 => {
+            let gains = PidGains { kp: num(kp)?, ki: num(ki)?, kd: num(kd)? };
+            heater.set_gains(&device(rest)?, gains)
+        }
+        ["HEATER_SETPOINT", value, rest @ ..] if rest.len() <= 1 => heater.set_setpoint(&device(rest)?, num(value)?),
+        ["HEATER_AUTOTUNE", rest @ ..] if rest.len() <= 1 => heater.start_autotune(rest.first().copied()),
+        ["HEATER_STATUS"] => {
+            heater.publish();
+            Ok(())
+        }
+        _ => Err(
+            "usage: HEATER_PID <kp> <ki> <kd> [device] | HEATER_SETPOINT <value> [device] | HEATER_AUTOTUNE [device] | HEATER_STATUS"
+                .to_string(),
+        ),
+    }
+}
@@ -0,0 +1,228 @@
+use memmap2::Mmap;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use crate::analytics::{AnalyticsConfig, Sample, Selection};
+
+/// timestamp (i64) + device index (u32) + 7 channels (f32)
+const FIXED_BYTES: usize = 8 + 4 + 7 * 4;
+
+/// One file of fixed-size records. Device ids and virtual channel names are
+/// kept here instead of in every record. The layout holds every virtual
+/// channel seen so far (NaN where a frame lacks one); only a frame with a
+/// name outside it starts a new segment, with the union of both.
+struct Segment {
+    path: PathBuf,
+    devices: Vec<String>,
+    virtual_names: Vec<String>,
+    count: usize,
+    first_ts: i64,
+    last_ts: i64,
+    /// Open segment only
+    writer: Option<BufWriter<File>>,
+    /// Sealed segments are mapped once
+    map: Option<Mmap>,
+}
+
+impl Segment {
+    fn record_bytes(&self) -> usize {
+        FIXED_BYTES + 4 * self.virtual_names.len()
+    }
+
+    fn encode(&mut self, sample: &Sample) -> Vec<u8> {
+        let device = match self.devices.iter().position(|d| *d == sample.device) {
+            Some(i) => i,
+            None => {
+                self.devices.push(sample.device.clone());
+                self.devices.len() - 1
+            }
+        };
+        let mut record = Vec::with_capacity(self.record_bytes());
+        record.extend(sample.timestamp.to_le_bytes());
+        record.extend((device as u32).to_le_bytes());
+        for value in sample.channels {
+            record.extend(value.to_le_bytes());
+        }
+        for name in &self.virtual_names {
+            record.extend(sample.virtual_channels.get(name).copied().unwrap_or(f32::NAN).to_le_bytes());
+        }
+        record
+    }
+
+    fn decode(&self, record: &[u8]) -> Sample {
+        let f32_at = |offset: usize| f32::from_le_bytes(record[offset..offset + 4].try_into().unwrap());
+        let device = u32::from_le_bytes(record[8..12].try_into().unwrap()) as usize;
+        Sample {
+            timestamp: i64::from_le_bytes(record[0..8].try_into().unwrap()),
+            device: self.devices.get(device).cloned().unwrap_or_default(),
+            channels: std::array::from_fn(|i| f32_at(12 + 4 * i)),
+            virtual_channels: self
+                .virtual_names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| (name.clone(), f32_at(FIXED_BYTES + 4 * i)))
+                .filter(|(_, v)| !v.is_nan())
+                .collect(),
+        }
+    }
+
+    /// Stop appending and map the file for reading
+    fn seal(&mut self) -> std::io::Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        let file = File::open(&self.path)?;
+        // SAFETY: segmen hanya ditulis backend ini dan tidak diubah lagi setelah disegel
+        self.map = Some(unsafe { Mmap::map(&file)? });
+        Ok(())
+    }
+}
+
+// ================= History Spill =================
+/// Disk tier behind the in-memory frame history: frames pushed out of
+/// memory are appended to segment files and read back through mmap, so the
+/// history can cover days on a 1 GB Pi. Oldest segments are deleted once
+/// `spill_frames` is exceeded.
+pub struct HistorySpill {
+    dir: PathBuf,
+    segment_frames: usize,
+    max_frames: usize,
+    segments: VecDeque<Segment>,
+    next_id: u64,
+}
+
+impl HistorySpill {
+    pub fn new(config: &AnalyticsConfig) -> std::io::Result<Self> {
+        let dir = PathBuf::from(&config.spill_dir);
+        // History hanya mencakup run ini; sisa spill dari run sebelumnya dibuang
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            segment_frames: config.segment_frames.max(1),
+            max_frames: config.spill_frames,
+            segments: VecDeque::new(),
+            next_id: 0,
+        })
+    }
+
+    /// Total frames on disk
+    fn len(&self) -> usize {
+        self.segments.iter().map(|s| s.count).sum()
+    }
+
+    pub fn append(&mut self, sample: &Sample) -> std::io::Result<()> {
+        // Layout tetap: gabungan semua nama virtual channel sejauh ini
+        let mut virtual_names = self.segments.back().map(|s| s.virtual_names.clone()).unwrap_or_default();
+        let known = virtual_names.len();
+        for name in sample.virtual_channels.keys() {
+            if !virtual_names.contains(name) {
+                virtual_names.push(name.clone());
+            }
+        }
+        let reuse = self
+            .segments
+            .back()
+            .is_some_and(|s| s.writer.is_some() && s.count < self.segment_frames && virtual_names.len() == known);
+        if !reuse {
+            if let Some(last) = self.segments.back_mut() {
+                if last.writer.is_some() {
+                    last.seal()?;
+                }
+            }
+            self.open_segment(virtual_names)?;
+        }
+
+        let segment = self.segments.back_mut().unwrap();
+        let record = segment.encode(sample);
+        segment.writer.as_mut().unwrap().write_all(&record)?;
+        // Min/max, timestamp terkoreksi drift bisa sedikit mundur
+        if segment.count == 0 {
+            (segment.first_ts, segment.last_ts) = (sample.timestamp, sample.timestamp);
+        }
+        segment.first_ts = segment.first_ts.min(sample.timestamp);
+        segment.last_ts = segment.last_ts.max(sample.timestamp);
+        segment.count += 1;
+
+        while self.len() > self.max_frames && self.segments.len() > 1 {
+            if let Some(oldest) = self.segments.pop_front() {
+                let _ = std::fs::remove_file(&oldest.path);
+            }
+        }
+        Ok(())
+    }
+
+    fn open_segment(&mut self, virtual_names: Vec<String>) -> std::io::Result<()> {
+        self.next_id += 1;
+        let path = self.dir.join(format!("segment_{:06}.bin", self.next_id));
+        let writer = BufWriter::new(File::create(&path)?);
+        self.segments.push_back(Segment {
+            path,
+            devices: Vec::new(),
+            virtual_names,
+            count: 0,
+            first_ts: 0,
+            last_ts: 0,
+            writer: Some(writer),
+            map: None,
+        });
+        Ok(())
+    }
+
+    /// Matching frames, newest first, at most `limit`
+    pub fn select(&mut self, selection: &Selection, limit: usize) -> Vec<Sample> {
+        let mut selected = Vec::new();
+        for segment in self.segments.iter_mut().rev() {
+            if selected.len() >= limit {
+                break;
+            }
+            if segment.count == 0
+                || selection.from.is_some_and(|from| segment.last_ts < from)
+                || selection.to.is_some_and(|to| segment.first_ts > to)
+            {
+                continue;
+            }
+            // Segmen yang masih ditulis dipetakan ulang sesuai panjangnya sekarang
+            let open_map;
+            let data: &[u8] = match &segment.map {
+                Some(map) => map,
+                None => {
+                    if let Some(writer) = segment.writer.as_mut() {
+                        let _ = writer.flush();
+                    }
+                    // SAFETY: hanya dibaca sampai count record yang sudah di-flush
+                    open_map = match File::open(&segment.path).and_then(|f| unsafe { Mmap::map(&f) }) {
+                        Ok(map) => map,
+                        Err(e) => {
+                            eprintln!("⚠️ History spill {} unreadable: {}", segment.path.display(), e);
+                            continue;
+                        }
+                    };
+                    &open_map
+                }
+            };
+            let size = segment.record_bytes();
+            let records = (data.len() / size).min(segment.count);
+            for i in (0..records).rev() {
+                let record = &data[i * size..(i + 1) * size];
+                let timestamp = i64::from_le_bytes(record[0..8].try_into().unwrap());
+                if selection.to.is_some_and(|to| timestamp > to) || selection.from.is_some_and(|from| timestamp < from) {
+                    continue;
+                }
+                let sample = segment.decode(record);
+                if selection.device.as_deref().is_none_or(|d| sample.device == d) {
+                    selected.push(sample);
+                    if selected.len() >= limit {
+                        break;
+                    }
+                }
+            }
+        }
+        selected
+    }
+}
+
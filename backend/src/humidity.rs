@@ -0,0 +1,125 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+
+use crate::pid::{Pid, PidGains};
+use crate::UnifiedSensorData;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HumidityConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Target relative humidity in %
+    #[serde(default = "default_setpoint")]
+    pub setpoint: f32,
+    #[serde(default = "default_gains")]
+    pub pid: PidGains,
+    #[serde(default)]
+    pub output_min: f32,
+    #[serde(default = "default_output_max")]
+    pub output_max: f32,
+    /// `{value}` is replaced with the rounded controller output
+    #[serde(default = "default_command_template")]
+    pub command_template: String,
+    /// Sent once when leaving the controlled states
+    #[serde(default = "default_off_command")]
+    pub off_command: String,
+    /// FSM states during which the loop is closed
+    #[serde(default = "default_active_states")]
+    pub active_states: Vec<String>,
+}
+
+fn default_setpoint() -> f32 { 50.0 }
+fn default_gains() -> PidGains { PidGains { kp: 4.0, ki: 0.2, kd: 0.0 } }
+fn default_output_max() -> f32 { 255.0 }
+fn default_command_template() -> String { "HUMIDIFIER {value}".to_string() }
+fn default_off_command() -> String { "HUMIDIFIER 0".to_string() }
+fn default_active_states() -> Vec<String> { vec!["HOLD".to_string()] }
+
+impl Default for HumidityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            setpoint: default_setpoint(),
+            pid: default_gains(),
+            output_min: 0.0,
+            output_max: default_output_max(),
+            command_template: default_command_template(),
+            off_command: default_off_command(),
+            active_states: default_active_states(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    humidity: HumidityConfig,
+}
+
+impl HumidityConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().humidity
+    }
+}
+
+// ================= Humidity Controller =================
+/// Loop state of one device's chamber
+struct Loop {
+    pid: Pid,
+    last_timestamp: Option<i64>,
+    last_output: Option<i32>,
+}
+
+/// Closed loop per device: its RH channel → PID → its humidifier command
+pub async fn run_controller(
+    config: HumidityConfig,
+    cmd_tx: broadcast::Sender<String>,
+    frame_tx: broadcast::Sender<UnifiedSensorData>,
+) {
+    println!("💧 Humidity control enabled, setpoint {:.1}% RH", config.setpoint);
+
+    let mut loops: HashMap<String, Loop> = HashMap::new();
+    let mut frame_rx = frame_tx.subscribe();
+
+    loop {
+        let frame = match frame_rx.recv().await {
+            Ok(frame) => frame,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let device = &frame.device;
+        let control = loops.entry(device.clone()).or_insert_with(|| Loop {
+            pid: Pid::new(config.pid, config.output_min, config.output_max),
+            last_timestamp: None,
+            last_output: None,
+        });
+
+        let active = config.active_states.contains(&frame.state_name);
+        let Some(humidity) = frame.humidity.filter(|_| active) else {
+            if control.last_output.take().is_some() {
+                let _ = cmd_tx.send(format!("{}:{}", device, config.off_command));
+                println!("💧 Humidifier of {} off ({})", device, frame.state_name);
+            }
+            control.pid.reset();
+            control.last_timestamp = None;
+            continue;
+        };
+
+        let dt = control
+            .last_timestamp
+            .map(|t| (frame.timestamp - t) as f32 / 1000.0)
+            .unwrap_or(0.0);
+        control.last_timestamp = Some(frame.timestamp);
+
+        let output = control.pid.update(config.setpoint, humidity, dt).round() as i32;
+
+        // Hanya kirim jika output berubah, supaya firmware tidak dibanjiri command
+        if control.last_output != Some(output) {
+            control.last_output = Some(output);
+            let command = config.command_template.replace("{value}", &output.to_string());
+            let _ = cmd_tx.send(format!("{}:{}", device, command));
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use influxdb2::api::buckets::ListBucketsRequest;
+use influxdb2::api::organization::ListOrganizationRequest;
+use influxdb2::api::task::{CreateTaskRequest, ListTasksRequest};
+use influxdb2::models::retention_rule::Type;
+use influxdb2::models::{PostBucketRequest, RetentionRule};
+use serde::Deserialize;
+
+use crate::influxdb::InfluxDBHandler;
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct InfluxBootstrapConfig {
+    /// Create missing buckets and tasks at startup (needs an all-access token)
+    #[serde(default)]
+    pub enabled: bool,
+    /// Retention of the main bucket, 0 = keep forever
+    #[serde(default)]
+    pub retention_days: u32,
+    #[serde(default)]
+    pub downsample: Vec<DownsampleTask>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DownsampleTask {
+    pub name: String,
+    /// Flux duration, e.g. "1m"; also the aggregation window
+    pub every: String,
+    /// Target bucket, created if missing
+    pub bucket: String,
+    #[serde(default)]
+    pub retention_days: u32,
+    #[serde(default = "default_measurement")]
+    pub measurement: String,
+    /// Flux aggregate function: mean, median, max, ...
+    #[serde(default = "default_function")]
+    pub function: String,
+}
+
+fn default_measurement() -> String { "sensors".to_string() }
+fn default_function() -> String { "mean".to_string() }
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    influx_bootstrap: InfluxBootstrapConfig,
+}
+
+impl InfluxBootstrapConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().influx_bootstrap
+    }
+}
+
+fn retention(days: u32) -> Vec<RetentionRule> {
+    if days == 0 {
+        return Vec::new();
+    }
+    vec![RetentionRule::new(Type::Expire, (days * 86_400) as i32)]
+}
+
+// ================= InfluxDB Bootstrap =================
+/// Check the configured bucket, its retention and the downsampling tasks,
+/// creating whatever is missing. Existing objects are never modified.
+pub async fn run(config: InfluxBootstrapConfig, influx: InfluxDBHandler) {
+    match bootstrap(&config, &influx).await {
+        Ok(0) => println!("🗄️ InfluxDB bootstrap: everything already in place"),
+        Ok(created) => println!("🗄️ InfluxDB bootstrap: created {} object(s)", created),
+        Err(e) => eprintln!("❌ InfluxDB bootstrap failed: {}", e),
+    }
+}
+
+async fn bootstrap(config: &InfluxBootstrapConfig, influx: &InfluxDBHandler) -> anyhow::Result<usize> {
+    let client = influx.client();
+    let org_id = org_id(influx).await?;
+    let mut created = 0;
+
+    let mut buckets = vec![(influx.bucket().to_string(), config.retention_days)];
+    buckets.extend(config.downsample.iter().map(|task| (task.bucket.clone(), task.retention_days)));
+    for (name, days) in buckets {
+        let request = ListBucketsRequest { name: Some(name.clone()), org_id: Some(org_id.clone()), ..Default::default() };
+        match client.list_buckets(Some(request)).await?.buckets.into_iter().next() {
+            Some(bucket) => {
+                let actual = bucket.retention_rules.first().map_or(0, |rule| rule.every_seconds as i64);
+                if actual != days as i64 * 86_400 {
+                    eprintln!(
+                        "⚠️ Bucket '{}' retention is {} s, config wants {} days (not changed)",
+                        name, actual, days
+                    );
+                }
+            }
+            None => {
+                let request =
+                    PostBucketRequest { retention_rules: retention(days), ..PostBucketRequest::new(org_id.clone(), name.clone()) };
+                client.create_bucket(Some(request)).await?;
+                println!("🗄️ Created bucket '{}' (retention {} days)", name, days);
+                created += 1;
+            }
+        }
+    }
+
+    let existing: Vec<String> = client
+        .list_tasks(ListTasksRequest { org_id: Some(org_id.clone()), ..Default::default() })
+        .await?
+        .tasks
+        .into_iter()
+        .map(|task| task.name)
+        .collect();
+    for task in config.downsample.iter().filter(|task| !existing.contains(&task.name)) {
+        let request = CreateTaskRequest {
+            org_id: Some(org_id.clone()),
+            description: Some(format!("E-Nose downsampling into {}", task.bucket)),
+            ..CreateTaskRequest::new(flux(task, influx.bucket(), &org_id))
+        };
+        client.create_task(request).await?;
+        println!("🗄️ Created downsampling task '{}' ({} {} -> {})", task.name, task.every, task.function, task.bucket);
+        created += 1;
+    }
+    Ok(created)
+}
+
+/// The configured org may be an id (16 hex digits) or a name
+async fn org_id(influx: &InfluxDBHandler) -> anyhow::Result<String> {
+    let org = influx.org();
+    if org.len() == 16 && org.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(org.to_string());
+    }
+    let request = ListOrganizationRequest { org: Some(org.to_string()), ..Default::default() };
+    influx
+        .client()
+        .list_organizations(request)
+        .await?
+        .orgs
+        .into_iter()
+        .find_map(|o| o.id)
+        .ok_or_else(|| anyhow::anyhow!("organization '{}' not found", org))
+}
+
+fn flux(task: &DownsampleTask, source: &str, org_id: &str) -> String {
+    format!(
+        r#"option task = {{name: "{name}", every: {every}}}
+
+from(bucket: "{source}")
+  |> range(start: -task.every)
+  |> filter(fn: (r) => r._measurement == "{measurement}")
+  |> filter(fn: (r) => r._field != "cycle_valid")
+  |> aggregateWindow(every: task.every, fn: {function}, createEmpty: false)
+  |> to(bucket: "{bucket}", orgID: "{org_id}")
+"#,
+        name = task.name,
+        every = task.every,
+        source = source,
+        measurement = task.measurement,
+        function = task.function,
+        bucket = task.bucket,
+        org_id = org_id,
+    )
+}
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+/// Line protocol lines waiting to be written together
+#[derive(Debug, Clone)]
+pub struct Batch {
+    /// Queue order, assigned by `WriteQueue::push`
+    pub seq: u64,
+    pub lines: Vec<String>,
+    /// When the first point was queued, epoch ms
+    pub created_ms: i64,
+}
+
+impl Batch {
+    pub fn body(&self) -> String {
+        self.lines.concat()
+    }
+}
+
+/// Batch file in the queue directory: `batch_<seq>_<created_ms>_<points>.lp`
+struct DiskBatch {
+    seq: u64,
+    path: PathBuf,
+    created_ms: i64,
+    points: usize,
+    bytes: u64,
+}
+
+impl DiskBatch {
+    fn parse(path: PathBuf) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.strip_prefix("batch_")?.strip_suffix(".lp")?.to_string();
+        let mut parts = name.split('_').map(|p| p.parse::<i64>().ok());
+        let (seq, created_ms, points) = (parts.next()??, parts.next()??, parts.next()??);
+        let bytes = std::fs::metadata(&path).ok()?.len();
+        Some(Self { seq: seq as u64, path, created_ms, points: points as usize, bytes })
+    }
+}
+
+// ================= InfluxDB Write Queue =================
+/// Batches not yet accepted by InfluxDB, oldest first. Recent batches stay in
+/// memory; while InfluxDB is unreachable, or once more than `memory_batches`
+/// pile up, they move to files in `dir` so a backlog survives restarts and
+/// is drained on the next run. Disk batches are always older than memory
+/// batches, so the front is taken from disk first.
+pub struct WriteQueue {
+    dir: PathBuf,
+    memory_batches: usize,
+    max_bytes: u64,
+    memory: VecDeque<Batch>,
+    disk: VecDeque<DiskBatch>,
+    next_seq: u64,
+    offline: bool,
+    /// Points dropped because the disk queue was full
+    pub dropped_points: u64,
+    /// Batches written to the disk queue
+    pub spilled_batches: u64,
+}
+
+impl WriteQueue {
+    /// Open the queue directory, picking up batches left by a previous run
+    pub fn open(dir: &str, memory_batches: usize, max_mb: u64) -> std::io::Result<Self> {
+        let dir = PathBuf::from(dir);
+        std::fs::create_dir_all(&dir)?;
+        let mut found: Vec<DiskBatch> = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| DiskBatch::parse(entry.path()))
+            .collect();
+        found.sort_by_key(|batch| batch.seq);
+        let next_seq = found.last().map_or(0, |batch| batch.seq) + 1;
+        Ok(Self {
+            dir,
+            memory_batches: memory_batches.max(1),
+            max_bytes: max_mb * 1024 * 1024,
+            memory: VecDeque::new(),
+            disk: found.into_iter().collect(),
+            next_seq,
+            offline: false,
+            dropped_points: 0,
+            spilled_batches: 0,
+        })
+    }
+
+    pub fn push(&mut self, mut batch: Batch) {
+        batch.seq = self.next_seq;
+        self.next_seq += 1;
+        self.memory.push_back(batch);
+        let keep = if self.offline { 0 } else { self.memory_batches };
+        self.spill_memory(keep);
+    }
+
+    /// While offline every batch goes straight to disk
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+        if offline {
+            self.spill_memory(0);
+        }
+    }
+
+    /// Move the oldest memory batches to disk until `keep` are left
+    fn spill_memory(&mut self, keep: usize) {
+        while self.memory.len() > keep {
+            let Some(batch) = self.memory.front() else { break };
+            // Disk penuh/tidak bisa ditulis: sisanya tetap di memori
+            if !self.spill(&batch.clone()) {
+                break;
+            }
+            self.memory.pop_front();
+        }
+    }
+
+    fn spill(&mut self, batch: &Batch) -> bool {
+        let path = self.dir.join(format!("batch_{:010}_{}_{}.lp", batch.seq, batch.created_ms, batch.lines.len()));
+        let body = batch.body();
+        if let Err(e) = std::fs::write(&path, &body) {
+            eprintln!("❌ InfluxDB queue {} not writable: {}", path.display(), e);
+            return false;
+        }
+        self.spilled_batches += 1;
+        self.disk.push_back(DiskBatch { seq: batch.seq, path, created_ms: batch.created_ms, points: batch.lines.len(), bytes: body.len() as u64 });
+
+        while self.disk_bytes() > self.max_bytes && self.disk.len() > 1 {
+            if let Some(oldest) = self.disk.pop_front() {
+                let _ = std::fs::remove_file(&oldest.path);
+                self.dropped_points += oldest.points as u64;
+                eprintln!("⚠️ InfluxDB queue full, dropped {} oldest point(s)", oldest.points);
+            }
+        }
+        true
+    }
+
+    /// Oldest batch, read back from disk if needed
+    pub fn front(&mut self) -> Option<Batch> {
+        while let Some(oldest) = self.disk.front() {
+            match std::fs::read_to_string(&oldest.path) {
+                Ok(content) => {
+                    return Some(Batch {
+                        seq: oldest.seq,
+                        lines: content.split_inclusive('\n').map(str::to_string).collect(),
+                        created_ms: oldest.created_ms,
+                    })
+                }
+                Err(e) => {
+                    eprintln!("❌ InfluxDB queue {} unreadable, skipped: {}", oldest.path.display(), e);
+                    self.dropped_points += oldest.points as u64;
+                    self.disk.pop_front();
+                }
+            }
+        }
+        self.memory.front().cloned()
+    }
+
+    /// Remove a batch returned by `front` once it is written. It may have
+    /// been spilled (or dropped) in the meantime, so it is looked up by seq.
+    pub fn remove(&mut self, seq: u64) {
+        if let Some(i) = self.disk.iter().position(|b| b.seq == seq) {
+            if let Some(batch) = self.disk.remove(i) {
+                let _ = std::fs::remove_file(&batch.path);
+            }
+        } else if let Some(i) = self.memory.iter().position(|b| b.seq == seq) {
+            self.memory.remove(i);
+        }
+    }
+
+    fn disk_bytes(&self) -> u64 {
+        self.disk.iter().map(|b| b.bytes).sum()
+    }
+
+    pub fn batches(&self) -> usize {
+        self.memory.len() + self.disk.len()
+    }
+
+    pub fn disk_batches(&self) -> usize {
+        self.disk.len()
+    }
+
+    pub fn points(&self) -> usize {
+        self.memory.iter().map(|b| b.lines.len()).sum::<usize>() + self.disk.iter().map(|b| b.points).sum::<usize>()
+    }
+
+    pub fn oldest_ms(&self) -> Option<i64> {
+        self.disk.front().map(|b| b.created_ms).or_else(|| self.memory.front().map(|b| b.created_ms))
+    }
+}
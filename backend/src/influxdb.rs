@@ -1,88 +1,571 @@
-use influxdb2::Client;
-use influxdb2::models::DataPoint;
-use tokio::sync::mpsc;
+use influxdb2::{Client, RequestError};
+use influxdb2::models::data_point::DataPointError;
+use influxdb2::models::{DataPoint, WriteDataPoint};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, Notify};
 use anyhow::Result;
 use futures_util::stream;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::channels::Channels;
+use crate::clock::SharedClock;
+use crate::dialer::{Backoff, BackoffConfig};
+use crate::filtering::Modulation;
+use crate::frame_schema::FRAME_SCHEMA;
+use crate::influx_queue::{Batch, WriteQueue};
+use crate::location::Location;
+use crate::provenance::Provenance;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct InfluxWriterConfig {
+    /// Points per write request
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Write a partial batch after this long
+    #[serde(default = "default_flush_ms")]
+    pub flush_ms: u64,
+    #[serde(default = "default_write_timeout_ms")]
+    pub write_timeout_ms: u64,
+    /// Batches kept in memory before spilling to the disk queue
+    #[serde(default = "default_memory_batches")]
+    pub memory_batches: usize,
+    #[serde(default = "default_queue_dir")]
+    pub queue_dir: String,
+    /// Disk queue cap; oldest batches are dropped beyond it
+    #[serde(default = "default_queue_max_mb")]
+    pub queue_max_mb: u64,
+    /// Log the backlog while it is not empty, 0 = off
+    #[serde(default = "default_report_secs")]
+    pub report_secs: u64,
+    /// Retry delay after a failed write (max_retries is ignored)
+    #[serde(default)]
+    pub backoff: BackoffConfig,
+}
+
+fn default_batch_size() -> usize { 500 }
+fn default_flush_ms() -> u64 { 1000 }
+fn default_write_timeout_ms() -> u64 { 10000 }
+fn default_memory_batches() -> usize { 20 }
+fn default_queue_dir() -> String { "influx_queue".to_string() }
+fn default_queue_max_mb() -> u64 { 512 }
+fn default_report_secs() -> u64 { 60 }
+
+impl Default for InfluxWriterConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: default_batch_size(),
+            flush_ms: default_flush_ms(),
+            write_timeout_ms: default_write_timeout_ms(),
+            memory_batches: default_memory_batches(),
+            queue_dir: default_queue_dir(),
+            queue_max_mb: default_queue_max_mb(),
+            report_secs: default_report_secs(),
+            backoff: BackoffConfig::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    influx_writer: InfluxWriterConfig,
+}
+
+impl InfluxWriterConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().influx_writer
+    }
+}
 
 // === Data Structure ===
 #[derive(Debug, Clone)]
 pub struct UnifiedSensorData {
-    pub no2: f32,
-    pub eth: f32,
-    pub voc: f32,
-    pub co: f32,
-    pub com: f32,
-    pub ethm: f32,
-    pub vocm: f32,
+    pub channels: Channels,
     pub state: i32,
     pub level: i32,
+    pub flow: Option<f32>,
+    pub pressure: Option<f32>,
+    pub humidity: Option<f32>,
+    pub heater_temp: Option<f32>,
+    pub heater_setpoint: Option<f32>,
+    pub target_ppm: Option<f32>,
+    pub delivered_ppm: Option<f32>,
+    pub cycle_valid: bool,
     pub timestamp: i64,  // in nanoseconds
     pub source: String,
+    pub device: String,
+    pub session: Option<String>,
+    /// Reference chamber channels, stored as `<channel>_ref` fields
+    pub reference: Option<[f32; 7]>,
+    /// Unfiltered channels (`send_raw`), stored as `<channel>_raw` fields for replays
+    pub raw: Option<[f32; 7]>,
+    /// Stored as latitude/longitude/altitude fields, tags location_source and location (name)
+    pub location: Option<Location>,
+    /// Stored as `<channel>_modulation_*` fields
+    pub modulation: BTreeMap<String, Modulation>,
+    /// Stored as `<channel>_saturated = true` fields
+    pub saturated: Vec<String>,
+    /// Stored as `<channel>_range` fields
+    pub ranges: BTreeMap<String, u8>,
+    pub virtual_channels: BTreeMap<String, f32>,
+    pub provenance: Provenance,
+}
+
+/// Minimum time between two alerts for the same kind of dropped write
+const ALERT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Why a write failed; decides between retrying and dropping the batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WriteErrorKind {
+    /// Connection refused/reset, DNS
+    Network,
+    Timeout,
+    /// 5xx
+    Server,
+    /// 429
+    RateLimit,
+    /// 401/403, and 404 which InfluxDB also returns for a bucket the token cannot see
+    Auth,
+    /// 400/413/422: bad line protocol, field type conflict, batch too large
+    Schema,
+    /// Any other 4xx or an unexpected client error
+    Rejected,
+}
+
+impl WriteErrorKind {
+    fn classify(error: &RequestError) -> Self {
+        match error {
+            RequestError::ReqwestProcessing { source } if source.is_timeout() => WriteErrorKind::Timeout,
+            RequestError::ReqwestProcessing { .. } => WriteErrorKind::Network,
+            RequestError::Http { status, .. } => match status.as_u16() {
+                408 => WriteErrorKind::Timeout,
+                429 => WriteErrorKind::RateLimit,
+                401 | 403 | 404 => WriteErrorKind::Auth,
+                400 | 413 | 422 => WriteErrorKind::Schema,
+                s if s >= 500 => WriteErrorKind::Server,
+                _ => WriteErrorKind::Rejected,
+            },
+            _ => WriteErrorKind::Rejected,
+        }
+    }
+
+    /// Transient failures are retried; the rest would fail the same way again
+    pub fn retryable(self) -> bool {
+        matches!(self, WriteErrorKind::Network | WriteErrorKind::Timeout | WriteErrorKind::Server | WriteErrorKind::RateLimit)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WriteErrorKind::Network => "network",
+            WriteErrorKind::Timeout => "timeout",
+            WriteErrorKind::Server => "server",
+            WriteErrorKind::RateLimit => "rate_limit",
+            WriteErrorKind::Auth => "auth",
+            WriteErrorKind::Schema => "schema",
+            WriteErrorKind::Rejected => "rejected",
+        }
+    }
+}
+
+/// Writer counters, served at GET /api/influx
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct WriterStats {
+    pub online: bool,
+    pub points_received: u64,
+    pub points_written: u64,
+    pub batches_written: u64,
+    pub write_failures: u64,
+    /// Failed writes by `WriteErrorKind` name
+    pub errors: BTreeMap<String, u64>,
+    /// Points in batches dropped after a non-retryable error
+    pub points_rejected: u64,
+    /// Batches moved to the disk queue
+    pub batches_spilled: u64,
+    /// Points lost to a full or unreadable disk queue
+    pub points_dropped: u64,
+    /// Waiting in memory and on disk (the open batch not included)
+    pub queued_points: usize,
+    pub queued_batches: usize,
+    pub disk_batches: usize,
+    /// Age of the oldest queued batch, i.e. how far behind InfluxDB is
+    pub behind_secs: Option<f64>,
+    pub last_write: Option<i64>,
+    /// Acquisition → ingestion delay of the last point, and the largest seen, ms
+    pub last_ingest_delay_ms: Option<i64>,
+    pub max_ingest_delay_ms: Option<i64>,
+    pub last_error: Option<String>,
+    pub last_error_kind: Option<String>,
 }
 
 // === InfluxDB Handler ===
+/// Frames are queued on `send`, collected into line protocol batches and
+/// written by a separate task, so a slow or unreachable InfluxDB never
+/// blocks the pipeline. Failed batches are retried with backoff from the
+/// disk queue (see `WriteQueue`) until they are accepted.
 #[derive(Clone)]
 pub struct InfluxDBHandler {
     tx: mpsc::Sender<UnifiedSensorData>,
+    client: Client,
+    org: String,
+    bucket: String,
+    queue: Arc<Mutex<WriteQueue>>,
+    stats: Arc<Mutex<WriterStats>>,
+    data_tx: broadcast::Sender<String>,
+    /// Stamps ingested_at and batch ages, shared with the pipeline
+    clock: SharedClock,
 }
 
 impl InfluxDBHandler {
-    pub fn new(url: &str, token: &str, org: &str, bucket: &str) -> Self {
+    pub fn new(
+        url: &str,
+        token: &str,
+        org: &str,
+        bucket: &str,
+        config: InfluxWriterConfig,
+        data_tx: broadcast::Sender<String>,
+        clock: SharedClock,
+    ) -> Self {
         let client = Client::new(url, org, token);  // Note: order is url, org, token
         
-        let (tx, mut rx) = mpsc::channel::<UnifiedSensorData>(100);
-        
-        let client_clone = client.clone();
-        let bucket_string = bucket.to_string();
-        
-        // Spawn background task untuk menulis ke InfluxDB
-        tokio::spawn(async move {
-            println!("📊 InfluxDB writer task started");
-            
-            while let Some(data) = rx.recv().await {
-                // Build DataPoint dengan measurement name "sensors"
-                let point = DataPoint::builder("sensors")
-                    .tag("source", data.source.clone())
-                    .field("no2", data.no2 as f64)
-                    .field("eth", data.eth as f64)
-                    .field("voc", data.voc as f64)
-                    .field("co", data.co as f64)
-                    .field("com", data.com as f64)
-                    .field("ethm", data.ethm as f64)
-                    .field("vocm", data.vocm as f64)
-                    .field("state", data.state as i64)
-                    .field("level", data.level as i64)
-                    .timestamp(data.timestamp)  // timestamp harus dalam nanoseconds
-                    .build();
-                
-                match point {
-                    Ok(p) => {
-                        let stream = stream::once(async move { p });
-                        
-                        match client_clone.write(&bucket_string, stream).await {
-                            Ok(_) => {
-                                // Uncomment untuk debug
-                                // println!("✅ Data written to InfluxDB");
-                            }
-                            Err(e) => {
-                                eprintln!("❌ InfluxDB write error: {:?}", e);
-                            }
+        let (tx, rx) = mpsc::channel::<UnifiedSensorData>(config.batch_size.max(100));
+
+        let queue = WriteQueue::open(&config.queue_dir, config.memory_batches, config.queue_max_mb).unwrap_or_else(|e| {
+            eprintln!("❌ InfluxDB queue dir {} unusable ({}), using {}", config.queue_dir, e, std::env::temp_dir().display());
+            let fallback = std::env::temp_dir().join("enose_influx_queue");
+            WriteQueue::open(&fallback.to_string_lossy(), config.memory_batches, config.queue_max_mb)
+                .expect("temp dir must be writable")
+        });
+        let backlog = queue.points();
+        if backlog > 0 {
+            println!("📊 InfluxDB queue: {} point(s) left from the previous run", backlog);
+        }
+
+        let handler = Self {
+            tx,
+            client,
+            org: org.to_string(),
+            bucket: bucket.to_string(),
+            queue: Arc::new(Mutex::new(queue)),
+            stats: Arc::new(Mutex::new(WriterStats { online: true, ..Default::default() })),
+            data_tx,
+            clock,
+        };
+        let notify = Arc::new(Notify::new());
+        tokio::spawn(handler.clone().collect(rx, config.clone(), notify.clone()));
+        tokio::spawn(handler.clone().drain(config.clone(), notify));
+        if config.report_secs > 0 {
+            tokio::spawn(handler.clone().report(config.report_secs));
+        }
+        handler
+    }
+
+    /// Turn queued frames into batches of line protocol
+    async fn collect(self, mut rx: mpsc::Receiver<UnifiedSensorData>, config: InfluxWriterConfig, notify: Arc<Notify>) {
+        println!("📊 InfluxDB writer task started (batch {}, flush {} ms)", config.batch_size, config.flush_ms);
+        let mut batch: Option<Batch> = None;
+        let mut flush = tokio::time::interval(Duration::from_millis(config.flush_ms.max(1)));
+        loop {
+            tokio::select! {
+                data = rx.recv() => {
+                    let Some(data) = data else { break };
+                    let ingested_at = self.clock.now_millis();
+                    {
+                        let mut stats = self.stats.lock().unwrap();
+                        stats.points_received += 1;
+                        let delay = ingested_at - data.timestamp / 1_000_000;
+                        stats.last_ingest_delay_ms = Some(delay);
+                        stats.max_ingest_delay_ms = Some(stats.max_ingest_delay_ms.map_or(delay, |max| max.max(delay)));
+                    }
+                    let line = match point(&data, ingested_at).map(|p| line_protocol(&p)) {
+                        Ok(Ok(line)) => line,
+                        Ok(Err(e)) => {
+                            eprintln!("❌ Line protocol error: {:?}", e);
+                            continue;
+                        }
+                        Err(e) => {
+                            eprintln!("❌ DataPoint build error: {:?}", e);
+                            continue;
                         }
+                    };
+                    let open = batch.get_or_insert_with(|| Batch {
+                        seq: 0,
+                        lines: Vec::new(),
+                        created_ms: self.clock.now_millis(),
+                    });
+                    open.lines.push(line);
+                    if open.lines.len() >= config.batch_size.max(1) {
+                        self.enqueue(batch.take(), &notify);
                     }
-                    Err(e) => {
-                        eprintln!("❌ DataPoint build error: {:?}", e);
+                }
+                _ = flush.tick() => self.enqueue(batch.take(), &notify),
+            }
+        }
+        self.enqueue(batch.take(), &notify);
+        println!("⚠️ InfluxDB writer task exited");
+    }
+
+    fn enqueue(&self, batch: Option<Batch>, notify: &Notify) {
+        if let Some(batch) = batch {
+            self.queue.lock().unwrap().push(batch);
+            notify.notify_one();
+        }
+    }
+
+    /// Write queued batches oldest first, backing off while InfluxDB fails
+    /// transiently and dropping batches it refuses outright
+    async fn drain(self, config: InfluxWriterConfig, notify: Arc<Notify>) {
+        let mut backoff = Backoff::new(BackoffConfig { max_retries: 0, ..config.backoff.clone() });
+        let timeout = Duration::from_millis(config.write_timeout_ms);
+        let mut alerted: HashMap<WriteErrorKind, Instant> = HashMap::new();
+        loop {
+            let next = self.queue.lock().unwrap().front();
+            let Some(batch) = next else {
+                notify.notified().await;
+                continue;
+            };
+            let result = match tokio::time::timeout(timeout, self.client.write_line_protocol(&self.org, &self.bucket, batch.body())).await {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(e)) => Err((WriteErrorKind::classify(&e), e.to_string())),
+                Err(_) => Err((WriteErrorKind::Timeout, format!("no response within {} ms", config.write_timeout_ms))),
+            };
+            if let Err((kind, e)) = &result {
+                let mut stats = self.stats.lock().unwrap();
+                stats.write_failures += 1;
+                *stats.errors.entry(kind.name().to_string()).or_default() += 1;
+                stats.last_error = Some(e.clone());
+                stats.last_error_kind = Some(kind.name().to_string());
+            }
+            match result {
+                Ok(()) => {
+                    backoff.reset();
+                    self.queue.lock().unwrap().remove(batch.seq);
+                    let mut queue = self.queue.lock().unwrap();
+                    queue.set_offline(false);
+                    let mut stats = self.stats.lock().unwrap();
+                    if !stats.online {
+                        println!("✅ InfluxDB reachable again, draining {} queued point(s)", queue.points());
                     }
+                    stats.online = true;
+                    stats.points_written += batch.lines.len() as u64;
+                    stats.batches_written += 1;
+                    stats.last_write = Some(self.clock.now_millis());
+                }
+                Err((kind, e)) if !kind.retryable() => {
+                    // InfluxDB menjawab, jadi batch berikutnya tidak perlu ke disk
+                    backoff.reset();
+                    {
+                        let mut queue = self.queue.lock().unwrap();
+                        queue.remove(batch.seq);
+                        queue.set_offline(false);
+                    }
+                    let rejected = {
+                        let mut stats = self.stats.lock().unwrap();
+                        stats.online = true;
+                        stats.points_rejected += batch.lines.len() as u64;
+                        stats.points_rejected
+                    };
+                    // Satu alert per jenis per ALERT_INTERVAL, bukan per batch
+                    if alerted.get(&kind).is_none_or(|at| at.elapsed() >= ALERT_INTERVAL) {
+                        alerted.insert(kind, Instant::now());
+                        eprintln!(
+                            "🚨 InfluxDB rejected a batch ({}), dropped {} point(s), {} rejected so far: {}",
+                            kind.name(),
+                            batch.lines.len(),
+                            rejected,
+                            e
+                        );
+                        let event = serde_json::json!({
+                            "type": "influx_error",
+                            "kind": kind.name(),
+                            "retryable": false,
+                            "dropped_points": batch.lines.len(),
+                            "points_rejected": rejected,
+                            "error": e,
+                            "timestamp": self.clock.now_millis(),
+                        });
+                        let _ = self.data_tx.send(event.to_string());
+                    }
+                }
+                Err((kind, e)) => {
+                    self.queue.lock().unwrap().set_offline(true);
+                    {
+                        let mut stats = self.stats.lock().unwrap();
+                        if stats.online {
+                            eprintln!("❌ InfluxDB write error ({}), queueing to {}: {}", kind.name(), config.queue_dir, e);
+                        }
+                        stats.online = false;
+                    }
+                    let delay = backoff.next_delay().unwrap_or(Duration::from_millis(config.backoff.max_ms));
+                    tokio::time::sleep(delay).await;
                 }
             }
-            
-            println!("⚠️ InfluxDB writer task exited");
-        });
-        
-        Self { tx }
+        }
     }
-    
+
+    /// Log how far behind the writer is while a backlog exists
+    async fn report(self, secs: u64) {
+        let mut interval = tokio::time::interval(Duration::from_secs(secs));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let stats = self.stats();
+            if stats.queued_batches > 1 || !stats.online {
+                println!(
+                    "📊 InfluxDB backlog: {} point(s) in {} batch(es), {} on disk, {:.0} s behind, {} failed write(s)",
+                    stats.queued_points,
+                    stats.queued_batches,
+                    stats.disk_batches,
+                    stats.behind_secs.unwrap_or_default(),
+                    stats.write_failures,
+                );
+            }
+        }
+    }
+
+    pub fn stats(&self) -> WriterStats {
+        let queue = self.queue.lock().unwrap();
+        let mut stats = self.stats.lock().unwrap().clone();
+        stats.batches_spilled = queue.spilled_batches;
+        stats.points_dropped = queue.dropped_points;
+        stats.queued_points = queue.points();
+        stats.queued_batches = queue.batches();
+        stats.disk_batches = queue.disk_batches();
+        stats.behind_secs = queue.oldest_ms().map(|ms| (self.clock.now_millis() - ms).max(0) as f64 / 1000.0);
+        stats
+    }
+
     pub async fn send(&self, data: UnifiedSensorData) -> Result<()> {
         self.tx.send(data).await?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn org(&self) -> &str {
+        &self.org
+    }
+
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    /// Write arbitrary points directly (imports, derived measurements)
+    pub async fn write_points(&self, points: Vec<DataPoint>) -> Result<()> {
+        if points.is_empty() {
+            return Ok(());
+        }
+        self.client.write(&self.bucket, stream::iter(points)).await?;
+        Ok(())
+    }
+}
+
+/// Frame → "sensors" point. The point time is the acquisition time; both it
+/// and `ingested_at` (when the writer took the frame) are also stored as
+/// `acquired_at` / `ingested_at` fields (epoch ms), so delayed or backfilled
+/// points show up as a large `ingested_at - acquired_at`.
+fn point(data: &UnifiedSensorData, ingested_at: i64) -> Result<DataPoint, DataPointError> {
+    let mut builder = DataPoint::builder("sensors")
+        .tag("source", data.source.clone())
+        .tag("device", data.device.clone())
+        .tag("backend_version", data.provenance.backend_version.clone())
+        .tag("filter_hash", data.provenance.filter_hash.clone())
+        .tag("config_version", data.provenance.config_version.to_string())
+        .tag("frame_schema", FRAME_SCHEMA.to_string())
+        .field("state", data.state as i64)
+        .field("level", data.level as i64)
+        .field("cycle_valid", data.cycle_valid)
+        .field("acquired_at", data.timestamp / 1_000_000)
+        .field("ingested_at", ingested_at);
+    // Kanal yang dimatikan (NaN) dilewati, line protocol tidak menerima NaN
+    for (channel, value) in data.channels.iter() {
+        if value.is_finite() {
+            builder = builder.field(channel.name(), value as f64);
+        }
+    }
+    if let Some(session) = &data.session {
+        builder = builder.tag("session", session.clone());
+    }
+    if let Some(firmware) = &data.provenance.firmware_version {
+        builder = builder.tag("firmware_version", firmware.clone());
+    }
+    if let Some(calibration) = &data.provenance.calibration_version {
+        builder = builder.tag("calibration_version", calibration.clone());
+    }
+    if let Some(model) = &data.provenance.model_version {
+        builder = builder.tag("model_version", model.clone());
+    }
+    if let Some(flow) = data.flow {
+        builder = builder.field("flow", flow as f64);
+    }
+    if let Some(pressure) = data.pressure {
+        builder = builder.field("pressure", pressure as f64);
+    }
+    if let Some(humidity) = data.humidity {
+        builder = builder.field("humidity", humidity as f64);
+    }
+    if let Some(heater_temp) = data.heater_temp {
+        builder = builder.field("heater_temp", heater_temp as f64);
+    }
+    if let Some(heater_setpoint) = data.heater_setpoint {
+        builder = builder.field("heater_setpoint", heater_setpoint as f64);
+    }
+    if let Some(target_ppm) = data.target_ppm {
+        builder = builder.field("target_ppm", target_ppm as f64);
+    }
+    if let Some(delivered_ppm) = data.delivered_ppm {
+        builder = builder.field("delivered_ppm", delivered_ppm as f64);
+    }
+    if let Some(reference) = data.reference {
+        for (name, value) in crate::CHANNEL_NAMES.iter().zip(reference) {
+            builder = builder.field(format!("{}_ref", name), value as f64);
+        }
+    }
+    if let Some(raw) = data.raw {
+        for (name, value) in crate::CHANNEL_NAMES.iter().zip(raw).filter(|(_, v)| v.is_finite()) {
+            builder = builder.field(format!("{}_raw", name), value as f64);
+        }
+    }
+    if let Some(location) = &data.location {
+        builder = builder
+            .tag("location_source", location.source.clone())
+            .field("latitude", location.latitude)
+            .field("longitude", location.longitude);
+        if !location.name.is_empty() {
+            builder = builder.tag("location", location.name.clone());
+        }
+        if let Some(altitude) = location.altitude {
+            builder = builder.field("altitude", altitude);
+        }
+    }
+    for (channel, modulation) in &data.modulation {
+        builder = builder
+            .field(format!("{}_modulation_frequency", channel), modulation.frequency as f64)
+            .field(format!("{}_modulation_amplitude", channel), modulation.amplitude as f64)
+            .field(format!("{}_modulation_cycle", channel), modulation.cycle as i64)
+            .field(format!("{}_modulation_phase", channel), modulation.phase as f64)
+            .field(format!("{}_modulation_factor", channel), modulation.factor as f64);
+    }
+    for channel in &data.saturated {
+        builder = builder.field(format!("{}_saturated", channel), true);
+    }
+    for (channel, code) in &data.ranges {
+        builder = builder.field(format!("{}_range", channel), *code as i64);
+    }
+    for (name, value) in data.virtual_channels.iter().filter(|(_, v)| v.is_finite()) {
+        builder = builder.field(name.clone(), *value as f64);
+    }
+    builder
+        .timestamp(data.timestamp)  // timestamp harus dalam nanoseconds
+        .build()
+}
+
+fn line_protocol(point: &DataPoint) -> std::io::Result<String> {
+    let mut line = Vec::new();
+    point.write_data_point_to(&mut line)?;
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
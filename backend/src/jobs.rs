@@ -0,0 +1,616 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, Notify};
+
+use crate::audit::AuditLog;
+use crate::backfill::parse_time;
+use crate::noise_floor;
+use crate::replay;
+use crate::roc;
+use crate::transfer;
+use crate::AppState;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct JobsConfig {
+    /// Job directories (`<dir>/<id>/job.json` + artifacts)
+    #[serde(default = "default_dir")]
+    pub dir: String,
+    /// Finished jobs kept on disk; older ones are deleted with their artifacts
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+    /// Range queried from InfluxDB at a time by replay/export jobs, minutes
+    #[serde(default = "default_chunk_minutes")]
+    pub chunk_minutes: i64,
+    /// Longest range one replay/export job may cover, hours
+    #[serde(default = "default_max_hours")]
+    pub max_hours: i64,
+}
+
+fn default_dir() -> String { "jobs".to_string() }
+fn default_keep() -> usize { 50 }
+fn default_chunk_minutes() -> i64 { 60 }
+fn default_max_hours() -> i64 { 744 }
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_dir(),
+            keep: default_keep(),
+            chunk_minutes: default_chunk_minutes(),
+            max_hours: default_max_hours(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    jobs: JobsConfig,
+}
+
+impl JobsConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().jobs
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.dir.is_empty() {
+            return Err("jobs.dir must not be empty".to_string());
+        }
+        if self.chunk_minutes <= 0 {
+            return Err("jobs.chunk_minutes must be positive".to_string());
+        }
+        if self.max_hours <= 0 {
+            return Err("jobs.max_hours must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// What a job does
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobSpec {
+    /// Stored range back through the current processing chain (source "reprocessed")
+    Replay { device: String, from: i64, to: i64 },
+    /// Stored range as a file, all fields
+    Export { device: String, from: i64, to: i64, format: ExportFormat },
+    /// Allan deviation curves per channel over a stored range
+    Allan { device: String, from: i64, to: i64 },
+    /// Summary of a closed session as Markdown and JSON
+    Report { session: String },
+    /// ROC thresholds separating sessions labeled `positive` from `negative`,
+    /// written into config.toml with `apply`
+    Calibrate {
+        positive: String,
+        negative: String,
+        #[serde(default)]
+        apply: bool,
+    },
+    /// Per-feature map of `device`'s cycle features onto `master`'s, from
+    /// sessions with the same labels; written into config.toml with `apply`
+    Transfer {
+        device: String,
+        master: String,
+        #[serde(default)]
+        apply: bool,
+    },
+}
+
+impl JobSpec {
+    /// GUI command submitting this kind of job
+    pub fn command(&self) -> &'static str {
+        match self {
+            Self::Replay { .. } => "JOB_REPLAY",
+            Self::Export { .. } => "JOB_EXPORT",
+            Self::Allan { .. } => "JOB_ALLAN",
+            Self::Report { .. } => "JOB_REPORT",
+            Self::Calibrate { .. } => "JOB_CALIBRATE",
+            Self::Transfer { .. } => "JOB_TRANSFER",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "ndjson" | "jsonl" => Ok(Self::Ndjson),
+            _ => Err(format!("unknown export format '{}' (csv, ndjson)", name)),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ndjson => "ndjson",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn finished(self) -> bool {
+        matches!(self, Self::Done | Self::Failed | Self::Cancelled)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Artifact {
+    pub name: String,
+    pub bytes: u64,
+}
+
+/// job.json of a job directory, also the "job" GUI event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Job {
+    pub id: u64,
+    #[serde(flatten)]
+    pub spec: JobSpec,
+    pub submitted_by: String,
+    pub submitted_at: i64,
+    pub started_at: Option<i64>,
+    pub ended_at: Option<i64>,
+    pub status: JobStatus,
+    /// 0..1
+    pub progress: f64,
+    /// Latest progress note, or the error of a failed job
+    pub message: String,
+    pub artifacts: Vec<Artifact>,
+}
+
+struct Entry {
+    job: Job,
+    cancel: Arc<AtomicBool>,
+}
+
+// ================= Background Jobs =================
+/// Queue of reprocessing/export/report jobs run one at a time in the
+/// background; progress goes to GUIs as "job" events and results are
+/// files in the job's directory, listed and served by /api/jobs
+#[derive(Clone)]
+pub struct Jobs {
+    config: Arc<JobsConfig>,
+    entries: Arc<Mutex<Vec<Entry>>>,
+    next_id: Arc<AtomicU64>,
+    wake: Arc<Notify>,
+    audit: AuditLog,
+    data_tx: broadcast::Sender<String>,
+}
+
+impl Jobs {
+    pub fn new(config_path: &str, audit: AuditLog, data_tx: broadcast::Sender<String>) -> Self {
+        let config = JobsConfig::load(config_path);
+        let mut jobs: Vec<Job> = std::fs::read_dir(&config.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| std::fs::read_to_string(entry.path().join("job.json")).ok())
+            .filter_map(|json| serde_json::from_str(&json).ok())
+            .collect();
+        jobs.sort_by_key(|job| job.id);
+        let next_id = jobs.last().map_or(1, |job| job.id + 1);
+
+        let entries = jobs
+            .into_iter()
+            .map(|mut job| {
+                // Antrian tidak dilanjutkan setelah restart
+                if !job.status.finished() {
+                    job.status = JobStatus::Failed;
+                    job.ended_at = Some(chrono::Utc::now().timestamp_millis());
+                    job.message = "interrupted by a backend restart".to_string();
+                    save(&config, &job);
+                }
+                Entry { job, cancel: Arc::new(AtomicBool::new(false)) }
+            })
+            .collect();
+        Self {
+            config: Arc::new(config),
+            entries: Arc::new(Mutex::new(entries)),
+            next_id: Arc::new(AtomicU64::new(next_id)),
+            wake: Arc::new(Notify::new()),
+            audit,
+            data_tx,
+        }
+    }
+
+    pub fn submit(&self, spec: JobSpec, actor: &str) -> Result<Job, String> {
+        match &spec {
+            JobSpec::Replay { from, to, .. } | JobSpec::Export { from, to, .. } | JobSpec::Allan { from, to, .. } => {
+                if from >= to {
+                    return Err("job range is empty".to_string());
+                }
+                if to - from > self.config.max_hours * 3_600_000 {
+                    return Err(format!("job range exceeds jobs.max_hours ({}h)", self.config.max_hours));
+                }
+            }
+            JobSpec::Report { session } if session.is_empty() => return Err("report needs a session id".to_string()),
+            JobSpec::Report { .. } => {}
+            JobSpec::Calibrate { positive, negative, .. } => {
+                if positive.trim().is_empty() || negative.trim().is_empty() {
+                    return Err("calibration needs a positive and a negative label".to_string());
+                }
+                if positive.trim().eq_ignore_ascii_case(negative.trim()) {
+                    return Err("positive and negative labels must differ".to_string());
+                }
+            }
+            JobSpec::Transfer { device, master, .. } => {
+                if device.is_empty() || master.is_empty() || device == master {
+                    return Err("transfer needs two different devices".to_string());
+                }
+            }
+        }
+        let job = Job {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            spec,
+            submitted_by: actor.to_string(),
+            submitted_at: chrono::Utc::now().timestamp_millis(),
+            started_at: None,
+            ended_at: None,
+            status: JobStatus::Queued,
+            progress: 0.0,
+            message: String::new(),
+            artifacts: Vec::new(),
+        };
+        let dir = self.dir(job.id);
+        std::fs::create_dir_all(&dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+        save(&self.config, &job);
+        self.entries.lock().unwrap().push(Entry { job: job.clone(), cancel: Arc::new(AtomicBool::new(false)) });
+        println!("🧾 Job #{} queued: {}", job.id, describe(&job.spec));
+        self.audit.record(actor, "job_submit", serde_json::json!({ "id": job.id, "spec": job.spec }));
+        self.publish(&job);
+        self.wake.notify_one();
+        Ok(job)
+    }
+
+    /// Queued jobs are dropped at once, a running job stops at its next chunk
+    pub fn cancel(&self, id: u64, actor: &str) -> Result<Job, String> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.iter_mut().find(|e| e.job.id == id).ok_or_else(|| format!("unknown job #{}", id))?;
+        match entry.job.status {
+            JobStatus::Queued => {
+                entry.job.status = JobStatus::Cancelled;
+                entry.job.ended_at = Some(chrono::Utc::now().timestamp_millis());
+                save(&self.config, &entry.job);
+            }
+            JobStatus::Running => entry.cancel.store(true, Ordering::Relaxed),
+            status => return Err(format!("job #{} is already {:?}", id, status).to_lowercase()),
+        }
+        let job = entry.job.clone();
+        drop(entries);
+        self.audit.record(actor, "job_cancel", serde_json::json!({ "id": id }));
+        self.publish(&job);
+        Ok(job)
+    }
+
+    /// Every job, newest first
+    pub fn list(&self) -> Vec<Job> {
+        self.entries.lock().unwrap().iter().rev().map(|e| e.job.clone()).collect()
+    }
+
+    pub fn get(&self, id: u64) -> Option<Job> {
+        self.entries.lock().unwrap().iter().find(|e| e.job.id == id).map(|e| e.job.clone())
+    }
+
+    /// Path of a listed artifact; names outside the job's list are refused
+    pub fn artifact_path(&self, id: u64, name: &str) -> Result<PathBuf, String> {
+        let job = self.get(id).ok_or_else(|| format!("unknown job #{}", id))?;
+        job.artifacts
+            .iter()
+            .find(|a| a.name == name)
+            .map(|a| self.dir(id).join(&a.name))
+            .ok_or_else(|| format!("job #{} has no artifact '{}'", id, name))
+    }
+
+    fn dir(&self, id: u64) -> PathBuf {
+        PathBuf::from(&self.config.dir).join(id.to_string())
+    }
+
+    /// Oldest queued job, marked running
+    fn next(&self) -> Option<(Job, Arc<AtomicBool>)> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.iter_mut().find(|e| e.job.status == JobStatus::Queued)?;
+        entry.job.status = JobStatus::Running;
+        entry.job.started_at = Some(chrono::Utc::now().timestamp_millis());
+        save(&self.config, &entry.job);
+        Some((entry.job.clone(), entry.cancel.clone()))
+    }
+
+    fn update(&self, id: u64, change: impl FnOnce(&mut Job)) {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.iter_mut().find(|e| e.job.id == id) else { return };
+        change(&mut entry.job);
+        save(&self.config, &entry.job);
+        let job = entry.job.clone();
+        drop(entries);
+        self.publish(&job);
+    }
+
+    /// Finished jobs beyond `keep` are deleted, oldest first
+    fn prune(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        let finished = entries.iter().filter(|e| e.job.status.finished()).count();
+        let mut excess = finished.saturating_sub(self.config.keep);
+        entries.retain(|e| {
+            if excess == 0 || !e.job.status.finished() {
+                return true;
+            }
+            excess -= 1;
+            let _ = std::fs::remove_dir_all(PathBuf::from(&self.config.dir).join(e.job.id.to_string()));
+            false
+        });
+    }
+
+    /// "job" GUI event
+    pub fn publish(&self, job: &Job) {
+        let mut event = serde_json::to_value(job).unwrap_or_default();
+        event["type"] = "job".into();
+        let _ = self.data_tx.send(event.to_string());
+    }
+}
+
+fn save(config: &JobsConfig, job: &Job) {
+    let path = PathBuf::from(&config.dir).join(job.id.to_string()).join("job.json");
+    if let Err(e) = std::fs::write(&path, serde_json::to_string_pretty(job).unwrap_or_default()) {
+        eprintln!("❌ Failed to write {}: {}", path.display(), e);
+    }
+}
+
+fn describe(spec: &JobSpec) -> String {
+    match spec {
+        JobSpec::Replay { device, from, to } => format!("replay {} {}..{}", device, from, to),
+        JobSpec::Export { device, from, to, format } => format!("export {} {}..{} as {:?}", device, from, to, format),
+        JobSpec::Allan { device, from, to } => format!("Allan deviation {} {}..{}", device, from, to),
+        JobSpec::Report { session } => format!("report of session {}", session),
+        JobSpec::Calibrate { positive, negative, apply } => {
+            format!("thresholds for '{}' vs '{}'{}", positive, negative, if *apply { ", applied" } else { "" })
+        }
+        JobSpec::Transfer { device, master, apply } => {
+            format!("transfer map {} -> {}{}", device, master, if *apply { ", applied" } else { "" })
+        }
+    }
+}
+
+/// Handed to a running job
+pub struct JobContext {
+    jobs: Jobs,
+    id: u64,
+    cancel: Arc<AtomicBool>,
+}
+
+impl JobContext {
+    pub fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    pub fn progress(&self, fraction: f64, message: impl Into<String>) {
+        let message = message.into();
+        self.jobs.update(self.id, |job| {
+            job.progress = fraction.clamp(0.0, 1.0);
+            job.message = message;
+        });
+    }
+
+    /// [from, to) in `chunk_minutes` pieces
+    pub fn chunks(&self, from: i64, to: i64) -> Vec<(i64, i64)> {
+        let step = self.jobs.config.chunk_minutes * 60_000;
+        (0..)
+            .map(|i| from + i * step)
+            .take_while(|start| *start < to)
+            .map(|start| (start, (start + step).min(to)))
+            .collect()
+    }
+
+    /// Path for an artifact being written; listed by `add_artifact` once complete
+    pub fn artifact_path(&self, name: &str) -> PathBuf {
+        self.jobs.dir(self.id).join(name)
+    }
+
+    pub fn add_artifact(&self, name: &str) {
+        let bytes = std::fs::metadata(self.artifact_path(name)).map_or(0, |m| m.len());
+        self.jobs.update(self.id, |job| {
+            job.artifacts.retain(|a| a.name != name);
+            job.artifacts.push(Artifact { name: name.to_string(), bytes });
+        });
+    }
+
+    pub fn write_artifact(&self, name: &str, contents: &[u8]) -> Result<(), String> {
+        let path = self.artifact_path(name);
+        std::fs::write(&path, contents).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        self.add_artifact(name);
+        Ok(())
+    }
+}
+
+/// Worker: runs queued jobs one at a time
+pub async fn run(jobs: Jobs, state: AppState) {
+    loop {
+        let Some((job, cancel)) = jobs.next() else {
+            jobs.wake.notified().await;
+            continue;
+        };
+        println!("🧾 Job #{} running: {}", job.id, describe(&job.spec));
+        jobs.publish(&job);
+        let context = JobContext { jobs: jobs.clone(), id: job.id, cancel };
+        let result = match &job.spec {
+            JobSpec::Replay { device, from, to } => replay::replay(&state, &context, device, *from, *to).await,
+            JobSpec::Export { device, from, to, format } => export(&state, &context, device, *from, *to, *format).await,
+            JobSpec::Allan { device, from, to } => noise_floor::stability(&state, &context, device, *from, *to).await,
+            JobSpec::Report { session } => report(&state, &context, session),
+            JobSpec::Calibrate { positive, negative, apply } => {
+                roc::calibrate(&state, &context, positive, negative, *apply, &job.submitted_by).await
+            }
+            JobSpec::Transfer { device, master, apply } => {
+                transfer::learn(&state, &context, device, master, *apply, &job.submitted_by).await
+            }
+        };
+        let status = match &result {
+            _ if context.cancelled() => JobStatus::Cancelled,
+            Ok(()) => JobStatus::Done,
+            Err(_) => JobStatus::Failed,
+        };
+        match &result {
+            Err(e) if status == JobStatus::Failed => eprintln!("❌ Job #{} failed: {}", job.id, e),
+            _ => println!("🧾 Job #{} {:?}", job.id, status),
+        }
+        jobs.update(job.id, |job| {
+            job.status = status;
+            job.ended_at = Some(chrono::Utc::now().timestamp_millis());
+            match (status, result) {
+                (JobStatus::Done, _) => job.progress = 1.0,
+                (JobStatus::Failed, Err(e)) => job.message = e,
+                _ => {}
+            }
+        });
+        jobs.prune();
+    }
+}
+
+/// Stored points of one device as a file, one row per timestamp
+async fn export(state: &AppState, context: &JobContext, device: &str, from: i64, to: i64, format: ExportFormat) -> Result<(), String> {
+    let name = format!("{}.{}", crate::recording::file_safe(device), format.extension());
+    let path = context.artifact_path(&name);
+    let file = std::fs::File::create(&path).map_err(|e| format!("cannot create {}: {}", path.display(), e))?;
+    let mut out = std::io::BufWriter::new(file);
+    let write_error = |e: std::io::Error| format!("cannot write {}: {}", path.display(), e);
+
+    // Kolom CSV harus diketahui di awal
+    let fields: Vec<String> = replay::stored_fields(state, device, from, to).await?;
+    if format == ExportFormat::Csv {
+        writeln!(out, "timestamp,session,{}", fields.join(",")).map_err(write_error)?;
+    }
+    let chunks = context.chunks(from, to);
+    let mut rows_written = 0;
+    for (i, (start, stop)) in chunks.iter().enumerate() {
+        if context.cancelled() {
+            return Ok(());
+        }
+        for row in replay::stored_rows(state, device, *start, *stop, None).await? {
+            match format {
+                ExportFormat::Csv => {
+                    let values: Vec<String> =
+                        fields.iter().map(|f| row.fields.get(f).map(|v| v.to_string()).unwrap_or_default()).collect();
+                    writeln!(out, "{},{},{}", row.timestamp, row.session, values.join(",")).map_err(write_error)?;
+                }
+                ExportFormat::Ndjson => {
+                    writeln!(out, "{}", serde_json::to_string(&row).unwrap_or_default()).map_err(write_error)?;
+                }
+            }
+            rows_written += 1;
+        }
+        context.progress((i + 1) as f64 / chunks.len() as f64, format!("{} row(s)", rows_written));
+    }
+    out.flush().map_err(write_error)?;
+    drop(out);
+    context.add_artifact(&name);
+    Ok(())
+}
+
+/// Session summary as report.md (for people) and report.json
+fn report(state: &AppState, context: &JobContext, session: &str) -> Result<(), String> {
+    let record = state
+        .sessions
+        .get(session)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("unknown session {}", session))?;
+    let summary = record.summary.as_ref().ok_or_else(|| format!("session {} has no summary (still running?)", session))?;
+    let time = |ms: i64| {
+        chrono::DateTime::from_timestamp_millis(ms)
+            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default()
+    };
+
+    let mut md = format!("# Session {}\n\n", record.id);
+    if let Some(label) = &record.label {
+        md += &format!("**Label:** {}  \n", label);
+    }
+    md += &format!("**Devices:** {}  \n", record.devices.join(", "));
+    md += &format!("**Started:** {}  \n", time(record.started_at));
+    if let Some(stopped_at) = record.stopped_at {
+        md += &format!("**Stopped:** {} ({} s)  \n", time(stopped_at), (stopped_at - record.started_at) / 1000);
+    }
+    md += &format!("**Level:** {}  \n", record.level);
+    md += &format!("**Frames:** {} · **Cycles:** {} · **Alarms:** {}\n\n", summary.frames, summary.cycles, summary.alarms);
+    md += "| Channel | Count | Mean | Min | Max | P5 | P50 | P95 |\n|---|---|---|---|---|---|---|---|\n";
+    for (channel, s) in &summary.channels {
+        md += &format!(
+            "| {} | {} | {:.3} | {:.3} | {:.3} | {:.3} | {:.3} | {:.3} |\n",
+            channel, s.count, s.mean, s.min, s.max, s.p5, s.p50, s.p95
+        );
+    }
+    context.write_artifact("report.md", md.as_bytes())?;
+    context.write_artifact("report.json", serde_json::to_string_pretty(&record).unwrap_or_default().as_bytes())?;
+    context.progress(1.0, format!("{} channel(s)", summary.channels.len()));
+    Ok(())
+}
+
+/// GUI commands:
+///   JOBS                                           -> reply: jobs, newest first
+///   JOB_REPLAY <device> <from> <to>                times: epoch s/ms or RFC3339
+///   JOB_EXPORT <device> <from> <to> [csv|ndjson]
+///   JOB_ALLAN <device> <from> <to>
+///   JOB_REPORT <session>
+///   JOB_CALIBRATE <positive label> <negative label> [apply]
+///   JOB_TRANSFER <device> <master device> [apply]
+///   JOB_CANCEL <id>
+///   REPLAY <device> <from> <to>                    same as JOB_REPLAY (REPLAY_STATUS same as JOBS)
+pub fn handle_command(jobs: &Jobs, client: &str, args: &[&str]) -> Result<Option<String>, String> {
+    let spec = match args {
+        ["JOBS" | "REPLAY_STATUS"] => return Ok(Some(serde_json::json!({ "type": "jobs", "jobs": jobs.list() }).to_string())),
+        ["JOB_CANCEL", id] => {
+            let id = id.trim_start_matches('#').parse().map_err(|_| format!("invalid job id '{}'", id))?;
+            return jobs.cancel(id, client).map(|_| None);
+        }
+        ["JOB_REPLAY" | "REPLAY", device, from, to] => {
+            JobSpec::Replay { device: device.to_string(), from: parse_time(from)?, to: parse_time(to)? }
+        }
+        ["JOB_EXPORT", device, from, to, format @ ..] if format.len() <= 1 => JobSpec::Export {
+            device: device.to_string(),
+            from: parse_time(from)?,
+            to: parse_time(to)?,
+            format: ExportFormat::parse(format.first().copied().unwrap_or("csv"))?,
+        },
+        ["JOB_ALLAN", device, from, to] => {
+            JobSpec::Allan { device: device.to_string(), from: parse_time(from)?, to: parse_time(to)? }
+        }
+        ["JOB_REPORT", session] => JobSpec::Report { session: session.to_string() },
+        ["JOB_CALIBRATE", positive, negative, apply @ ..] if apply.is_empty() || apply == ["apply"] => JobSpec::Calibrate {
+            positive: positive.to_string(),
+            negative: negative.to_string(),
+            apply: !apply.is_empty(),
+        },
+        ["JOB_TRANSFER", device, master, apply @ ..] if apply.is_empty() || apply == ["apply"] => JobSpec::Transfer {
+            device: device.to_string(),
+            master: master.to_string(),
+            apply: !apply.is_empty(),
+        },
+        _ => {
+            return Err("usage: JOBS | JOB_REPLAY <device> <from> <to> | JOB_EXPORT <device> <from> <to> [csv|ndjson] \
+                        | JOB_ALLAN <device> <from> <to> | JOB_REPORT <session> | JOB_CALIBRATE <positive> <negative> [apply] \
+                        | JOB_TRANSFER <device> <master> [apply] | JOB_CANCEL <id>"
+                .to_string())
+        }
+    };
+    let job = jobs.submit(spec, client)?;
+    Ok(Some(serde_json::json!({ "type": "job_submitted", "id": job.id }).to_string()))
+}
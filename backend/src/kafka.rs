@@ -0,0 +1,172 @@
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::UnifiedSensorData;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct KafkaConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Kafka REST endpoint: Redpanda HTTP Proxy or Confluent REST Proxy (v2 API)
+    #[serde(default = "default_rest_url")]
+    pub rest_url: String,
+    /// Verified frames, keyed by device; empty = not published
+    #[serde(default = "default_frames_topic")]
+    pub frames_topic: String,
+    /// Backend events (alarms, sessions, ambient events, ...), keyed by device; empty = not published
+    #[serde(default = "default_events_topic")]
+    pub events_topic: String,
+    /// Only these event types ("type" field); empty = all
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    /// Records per produce request
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Longest a record waits for its batch to fill, ms
+    #[serde(default = "default_linger_ms")]
+    pub linger_ms: u64,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+fn default_rest_url() -> String { "http://localhost:8082".to_string() }
+fn default_frames_topic() -> String { "enose.frames".to_string() }
+fn default_events_topic() -> String { "enose.events".to_string() }
+fn default_batch_size() -> usize { 100 }
+fn default_linger_ms() -> u64 { 500 }
+
+impl Default for KafkaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rest_url: default_rest_url(),
+            frames_topic: default_frames_topic(),
+            events_topic: default_events_topic(),
+            event_types: Vec::new(),
+            batch_size: default_batch_size(),
+            linger_ms: default_linger_ms(),
+            username: None,
+            password: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    kafka: KafkaConfig,
+}
+
+impl KafkaConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().kafka
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.rest_url.is_empty() {
+            return Err("kafka.rest_url is required when kafka is enabled".to_string());
+        }
+        if self.batch_size == 0 {
+            return Err("kafka.batch_size must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Records waiting for one topic
+struct Batch {
+    topic: String,
+    records: Vec<serde_json::Value>,
+}
+
+impl Batch {
+    fn new(topic: &str) -> Self {
+        Self { topic: topic.to_string(), records: Vec::new() }
+    }
+
+    fn push(&mut self, key: &str, value: serde_json::Value) {
+        if !self.topic.is_empty() {
+            self.records.push(serde_json::json!({ "key": key, "value": value }));
+        }
+    }
+}
+
+/// Key of a GUI event: its device, else "backend"
+fn event_key(event: &serde_json::Value) -> String {
+    event.get("device").and_then(|d| d.as_str()).filter(|d| !d.is_empty()).unwrap_or("backend").to_string()
+}
+
+async fn produce(client: &reqwest::Client, config: &KafkaConfig, batch: &mut Batch) {
+    if batch.records.is_empty() {
+        return;
+    }
+    let records = std::mem::take(&mut batch.records);
+    let count = records.len();
+    let url = format!("{}/topics/{}", config.rest_url.trim_end_matches('/'), batch.topic);
+    let mut request = client
+        .post(&url)
+        .header(reqwest::header::CONTENT_TYPE, "application/vnd.kafka.json.v2+json")
+        .header(reqwest::header::ACCEPT, "application/vnd.kafka.v2+json")
+        .json(&serde_json::json!({ "records": records }));
+    if let Some(username) = &config.username {
+        request = request.basic_auth(username, config.password.as_ref());
+    }
+    match request.send().await.and_then(|r| r.error_for_status()) {
+        Ok(_) => {}
+        Err(e) => eprintln!("❌ Kafka produce to {} failed, {} record(s) dropped: {}", batch.topic, count, e),
+    }
+}
+
+// ================= Kafka Sink =================
+/// Publishes verified frames and backend events to Kafka/Redpanda topics
+/// through the REST proxy, keyed by device so each device's records stay
+/// in one partition, in order
+pub async fn run(config: KafkaConfig, frame_tx: broadcast::Sender<UnifiedSensorData>, data_tx: broadcast::Sender<String>) {
+    println!("📨 Kafka sink → {} (frames: '{}', events: '{}')", config.rest_url, config.frames_topic, config.events_topic);
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().unwrap_or_default();
+    let mut frame_rx = frame_tx.subscribe();
+    let mut data_rx = data_tx.subscribe();
+    let mut frames = Batch::new(&config.frames_topic);
+    let mut events = Batch::new(&config.events_topic);
+    let mut linger = tokio::time::interval(Duration::from_millis(config.linger_ms.max(1)));
+
+    loop {
+        tokio::select! {
+            frame = frame_rx.recv() => match frame {
+                Ok(frame) => {
+                    let value = serde_json::to_value(&frame).unwrap_or_default();
+                    frames.push(&frame.device, value);
+                    if frames.records.len() >= config.batch_size {
+                        produce(&client, &config, &mut frames).await;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => eprintln!("⚠️ Kafka sink lagged, {} frame(s) skipped", n),
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = data_rx.recv() => match message {
+                Ok(message) => {
+                    // Frame JSON (tanpa "type") sudah lewat frame_rx
+                    let Ok(event) = serde_json::from_str::<serde_json::Value>(&message) else { continue };
+                    let Some(kind) = event.get("type").and_then(|t| t.as_str()) else { continue };
+                    if !config.event_types.is_empty() && !config.event_types.iter().any(|t| t == kind) {
+                        continue;
+                    }
+                    events.push(&event_key(&event), event.clone());
+                    if events.records.len() >= config.batch_size {
+                        produce(&client, &config, &mut events).await;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => eprintln!("⚠️ Kafka sink lagged, {} event(s) skipped", n),
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = linger.tick() => {
+                produce(&client, &config, &mut frames).await;
+                produce(&client, &config, &mut events).await;
+            }
+        }
+    }
+}
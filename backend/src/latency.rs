@@ -0,0 +1,287 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LatencyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Trace one frame in every N
+    #[serde(default = "default_sample_every")]
+    pub sample_every: u64,
+    /// Publish the histograms as a "latency" GUI event, 0 = on request only
+    #[serde(default = "default_report_secs")]
+    pub report_secs: u64,
+}
+
+fn default_sample_every() -> u64 { 10 }
+fn default_report_secs() -> u64 { 60 }
+
+impl Default for LatencyConfig {
+    fn default() -> Self {
+        Self { enabled: false, sample_every: default_sample_every(), report_secs: default_report_secs() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    latency: LatencyConfig,
+}
+
+impl LatencyConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().latency
+    }
+}
+
+/// Pipeline steps, each measured from the previous one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    /// Line received → values parsed
+    Parse,
+    /// Plugins, channel mask and filter chain
+    Filter,
+    /// Clock drift, alarms, differential, frame assembly
+    Process,
+    /// Session check and data_tx / frame_tx fan-out
+    Broadcast,
+    /// InfluxDB writer queue accepted the point
+    Storage,
+}
+
+const STAGES: [Stage; 5] = [Stage::Parse, Stage::Filter, Stage::Process, Stage::Broadcast, Stage::Storage];
+
+impl Stage {
+    fn name(self) -> &'static str {
+        match self {
+            Stage::Parse => "parse",
+            Stage::Filter => "filter",
+            Stage::Process => "process",
+            Stage::Broadcast => "broadcast",
+            Stage::Storage => "storage",
+        }
+    }
+}
+
+/// Bucket upper bounds in µs; the last bucket catches everything slower
+const BUCKETS_US: [u64; 14] = [10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000];
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    counts: [u64; BUCKETS_US.len() + 1],
+    count: u64,
+    sum_us: u64,
+    max_us: u64,
+}
+
+impl Histogram {
+    fn record(&mut self, elapsed: Duration) {
+        let us = elapsed.as_micros() as u64;
+        let bucket = BUCKETS_US.iter().position(|b| us <= *b).unwrap_or(BUCKETS_US.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum_us += us;
+        self.max_us = self.max_us.max(us);
+    }
+
+    /// Upper bound of the bucket holding quantile `q`
+    fn quantile(&self, q: f64) -> Option<u64> {
+        let rank = (q * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(BUCKETS_US.get(i).copied().unwrap_or(self.max_us));
+            }
+        }
+        None
+    }
+
+    fn snapshot(&self, stage: &str) -> StageHistogram {
+        StageHistogram {
+            stage: stage.to_string(),
+            count: self.count,
+            mean_us: (self.count > 0).then(|| self.sum_us as f64 / self.count as f64),
+            p50_us: self.quantile(0.5),
+            p95_us: self.quantile(0.95),
+            p99_us: self.quantile(0.99),
+            max_us: self.max_us,
+            buckets: BUCKETS_US
+                .iter()
+                .map(|b| Some(*b))
+                .chain([None])
+                .zip(self.counts)
+                .map(|(le_us, count)| Bucket { le_us, count })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Bucket {
+    /// Upper bound in µs, null = +inf
+    pub le_us: Option<u64>,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StageHistogram {
+    pub stage: String,
+    pub count: u64,
+    pub mean_us: Option<f64>,
+    pub p50_us: Option<u64>,
+    pub p95_us: Option<u64>,
+    pub p99_us: Option<u64>,
+    pub max_us: u64,
+    pub buckets: Vec<Bucket>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LatencyReport {
+    pub enabled: bool,
+    pub sample_every: u64,
+    pub frames_seen: u64,
+    pub since: i64,
+    /// One per stage, then "total" (receive → storage enqueue)
+    pub stages: Vec<StageHistogram>,
+}
+
+/// Timestamps of one sampled frame
+pub struct FrameTrace {
+    received: Instant,
+    marks: Vec<(Stage, Instant)>,
+}
+
+impl FrameTrace {
+    pub fn mark(&mut self, stage: Stage) {
+        self.marks.push((stage, Instant::now()));
+    }
+}
+
+struct Histograms {
+    stages: [Histogram; STAGES.len()],
+    total: Histogram,
+    since: i64,
+}
+
+impl Histograms {
+    fn new() -> Self {
+        Self { stages: Default::default(), total: Histogram::default(), since: chrono::Utc::now().timestamp_millis() }
+    }
+}
+
+// ================= Latency Tracing =================
+/// Samples frames through receive → parse → filter → broadcast → storage
+/// enqueue and keeps a latency histogram per step, to show where time goes
+/// under load
+#[derive(Clone)]
+pub struct LatencyTracer {
+    config: Arc<LatencyConfig>,
+    frames: Arc<AtomicU64>,
+    histograms: Arc<Mutex<Histograms>>,
+}
+
+impl LatencyTracer {
+    pub fn new(config: LatencyConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            frames: Arc::new(AtomicU64::new(0)),
+            histograms: Arc::new(Mutex::new(Histograms::new())),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Call when a line arrives; Some for the frames that are traced
+    pub fn start(&self) -> Option<FrameTrace> {
+        if !self.config.enabled {
+            return None;
+        }
+        let n = self.frames.fetch_add(1, Ordering::Relaxed);
+        n.is_multiple_of(self.config.sample_every.max(1)).then(|| FrameTrace { received: Instant::now(), marks: Vec::new() })
+    }
+
+    /// Record a trace; frames that stopped early (refused, unverified) only
+    /// count toward the stages they reached
+    pub fn finish(&self, trace: FrameTrace) {
+        let mut histograms = self.histograms.lock().unwrap();
+        let mut previous = trace.received;
+        for (stage, at) in &trace.marks {
+            if let Some(i) = STAGES.iter().position(|s| s == stage) {
+                histograms.stages[i].record(at.duration_since(previous));
+            }
+            previous = *at;
+        }
+        if trace.marks.last().is_some_and(|(stage, _)| *stage == Stage::Storage) {
+            histograms.total.record(previous.duration_since(trace.received));
+        }
+    }
+
+    pub fn report(&self) -> LatencyReport {
+        let histograms = self.histograms.lock().unwrap();
+        let mut stages: Vec<StageHistogram> =
+            STAGES.iter().zip(&histograms.stages).map(|(stage, h)| h.snapshot(stage.name())).collect();
+        stages.push(histograms.total.snapshot("total"));
+        LatencyReport {
+            enabled: self.config.enabled,
+            sample_every: self.config.sample_every,
+            frames_seen: self.frames.load(Ordering::Relaxed),
+            since: histograms.since,
+            stages,
+        }
+    }
+
+    pub fn reset(&self) {
+        *self.histograms.lock().unwrap() = Histograms::new();
+    }
+}
+
+/// GUI commands:
+///   LATENCY         histograms per stage
+///   LATENCY_RESET
+pub fn handle_command(tracer: &LatencyTracer, args: &[&str]) -> Result<Option<String>, String> {
+    match args {
+        ["LATENCY"] => Ok(Some(event(&tracer.report()))),
+        ["LATENCY_RESET"] => {
+            tracer.reset();
+            Ok(None)
+        }
+        _ => Err("usage: LATENCY | LATENCY_RESET".to_string()),
+    }
+}
+
+fn event(report: &LatencyReport) -> String {
+    let mut event = serde_json::to_value(report).unwrap_or_default();
+    event["type"] = "latency".into();
+    event.to_string()
+}
+
+/// Periodic "latency" event with a one-line summary in the log
+pub async fn run(tracer: LatencyTracer, data_tx: broadcast::Sender<String>) {
+    let secs = tracer.config.report_secs;
+    if secs == 0 {
+        return;
+    }
+    let mut interval = tokio::time::interval(Duration::from_secs(secs));
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let report = tracer.report();
+        let summary: Vec<String> = report
+            .stages
+            .iter()
+            .filter(|s| s.count > 0)
+            .map(|s| format!("{} p95≤{}µs", s.stage, s.p95_us.unwrap_or_default()))
+            .collect();
+        if !summary.is_empty() {
+            println!("⏱️ Pipeline latency: {}", summary.join(", "));
+        }
+        let _ = data_tx.send(event(&report));
+    }
+}
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::UnifiedSensorData;
+use crate::devices::DeviceRegistry;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LeakTestConfig {
+    /// Commands that seal the chamber (close valves, stop/start pump)
+    #[serde(default = "default_seal_commands")]
+    pub seal_commands: Vec<String>,
+    /// Commands that return the rig to normal operation afterwards
+    #[serde(default = "default_restore_commands")]
+    pub restore_commands: Vec<String>,
+    #[serde(default = "default_settle_secs")]
+    pub settle_secs: u64,
+    #[serde(default = "default_measure_secs")]
+    pub measure_secs: u64,
+    /// Maximum acceptable pressure decay (pressure units per second)
+    #[serde(default = "default_max_leak_rate")]
+    pub max_leak_rate: f32,
+    #[serde(default = "default_log_path")]
+    pub log_path: String,
+}
+
+fn default_seal_commands() -> Vec<String> { vec!["VALVES_CLOSE".to_string()] }
+fn default_restore_commands() -> Vec<String> { vec!["VALVES_OPEN".to_string()] }
+fn default_settle_secs() -> u64 { 5 }
+fn default_measure_secs() -> u64 { 60 }
+fn default_max_leak_rate() -> f32 { 0.05 }
+fn default_log_path() -> String { "leak_tests.jsonl".to_string() }
+
+impl Default for LeakTestConfig {
+    fn default() -> Self {
+        Self {
+            seal_commands: default_seal_commands(),
+            restore_commands: default_restore_commands(),
+            settle_secs: default_settle_secs(),
+            measure_secs: default_measure_secs(),
+            max_leak_rate: default_max_leak_rate(),
+            log_path: default_log_path(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    leak_test: LeakTestConfig,
+}
+
+impl LeakTestConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().leak_test
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LeakTestResult {
+    pub device: String,
+    pub timestamp: i64,
+    pub samples: usize,
+    pub start_pressure: f32,
+    pub end_pressure: f32,
+    /// Pressure decay per second (positive = losing pressure)
+    pub leak_rate: f32,
+    pub max_leak_rate: f32,
+    pub passed: bool,
+}
+
+// ================= LeakTester =================
+#[derive(Clone)]
+pub struct LeakTester {
+    config: Arc<LeakTestConfig>,
+    running: Arc<AtomicBool>,
+    cmd_tx: broadcast::Sender<String>,
+    data_tx: broadcast::Sender<String>,
+    frame_tx: broadcast::Sender<UnifiedSensorData>,
+    devices: DeviceRegistry,
+}
+
+impl LeakTester {
+    pub fn new(
+        config: LeakTestConfig,
+        cmd_tx: broadcast::Sender<String>,
+        data_tx: broadcast::Sender<String>,
+        frame_tx: broadcast::Sender<UnifiedSensorData>,
+        devices: DeviceRegistry,
+    ) -> Self {
+        Self {
+            config: Arc::new(config),
+            running: Arc::new(AtomicBool::new(false)),
+            cmd_tx,
+            data_tx,
+            frame_tx,
+            devices,
+        }
+    }
+
+    /// Start the leak test on `device` (or the only connected one) in the background
+    pub fn start(&self, device: Option<&str>) -> Result<(), String> {
+        let device = self.devices.resolve(device)?;
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Err("leak test already running".to_string());
+        }
+        let tester = self.clone();
+        tokio::spawn(async move {
+            tester.run(&device).await;
+            tester.running.store(false, Ordering::SeqCst);
+        });
+        Ok(())
+    }
+
+    async fn run(&self, device: &str) {
+        println!("🔒 Starting chamber leak test on {}", device);
+        let _ = self.data_tx.send(
+            serde_json::json!({ "type": "leak_test", "status": "running", "device": device }).to_string(),
+        );
+
+        for cmd in &self.config.seal_commands {
+            let _ = self.cmd_tx.send(format!("{}:{}", device, cmd));
+        }
+        tokio::time::sleep(Duration::from_secs(self.config.settle_secs)).await;
+
+        let samples = self.collect_pressure(device).await;
+
+        for cmd in &self.config.restore_commands {
+            let _ = self.cmd_tx.send(format!("{}:{}", device, cmd));
+        }
+
+        let event = match self.evaluate(device, &samples) {
+            Some(result) => {
+                if result.passed {
+                    println!("✅ Leak test on {} passed: {:.4}/s (limit {:.4}/s)", device, result.leak_rate, result.max_leak_rate);
+                } else {
+                    eprintln!("🚨 Leak test on {} FAILED: {:.4}/s (limit {:.4}/s)", device, result.leak_rate, result.max_leak_rate);
+                }
+                if let Err(e) = append_result(&self.config.log_path, &result) {
+                    eprintln!("❌ Failed to write leak test log: {}", e);
+                }
+                let mut event = serde_json::to_value(&result).unwrap_or_default();
+                event["type"] = "leak_test".into();
+                event["status"] = if result.passed { "passed" } else { "failed" }.into();
+                event
+            }
+            None => {
+                eprintln!("❌ Leak test on {} aborted: not enough pressure readings", device);
+                serde_json::json!({
+                    "type": "leak_test",
+                    "status": "error",
+                    "device": device,
+                    "error": "not enough pressure readings (is the pressure channel connected?)",
+                })
+            }
+        };
+        let _ = self.data_tx.send(event.to_string());
+    }
+
+    /// (seconds since start, pressure) pairs from `device` during the measurement window
+    async fn collect_pressure(&self, device: &str) -> Vec<(f32, f32)> {
+        let mut frame_rx = self.frame_tx.subscribe();
+        let start = tokio::time::Instant::now();
+        let deadline = start + Duration::from_secs(self.config.measure_secs);
+        let mut samples = Vec::new();
+
+        loop {
+            match tokio::time::timeout_at(deadline, frame_rx.recv()).await {
+                Ok(Ok(frame)) if frame.device == device => {
+                    if let Some(pressure) = frame.pressure {
+                        samples.push((start.elapsed().as_secs_f32(), pressure));
+                    }
+                }
+                Ok(Ok(_)) | Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+                Ok(Err(broadcast::error::RecvError::Closed)) | Err(_) => break,
+            }
+        }
+        samples
+    }
+
+    fn evaluate(&self, device: &str, samples: &[(f32, f32)]) -> Option<LeakTestResult> {
+        if samples.len() < 2 {
+            return None;
+        }
+        // Least-squares slope of pressure vs time
+        let n = samples.len() as f32;
+        let mean_t = samples.iter().map(|(t, _)| t).sum::<f32>() / n;
+        let mean_p = samples.iter().map(|(_, p)| p).sum::<f32>() / n;
+        let cov: f32 = samples.iter().map(|(t, p)| (t - mean_t) * (p - mean_p)).sum();
+        let var: f32 = samples.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+        if var <= f32::EPSILON {
+            return None;
+        }
+        let leak_rate = -(cov / var);
+
+        Some(LeakTestResult {
+            device: device.to_string(),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            samples: samples.len(),
+            start_pressure: samples[0].1,
+            end_pressure: samples[samples.len() - 1].1,
+            leak_rate,
+            max_leak_rate: self.config.max_leak_rate,
+            passed: leak_rate.abs() <= self.config.max_leak_rate,
+        })
+    }
+}
+
+fn append_result(path: &str, result: &LeakTestResult) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(result)?)
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::alerts::{AlertEvent, AlertState, NotifyReason};
+use crate::channels::Channel;
+use crate::UnifiedSensorData;
+
+/// Soft and hard limits of one channel, `[limits.channels.<channel>]`
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ChannelLimits {
+    #[serde(default)]
+    pub soft_high: Option<f64>,
+    #[serde(default)]
+    pub soft_low: Option<f64>,
+    #[serde(default)]
+    pub hard_high: Option<f64>,
+    #[serde(default)]
+    pub hard_low: Option<f64>,
+    /// Protective sequence for this channel instead of `limits.actions`
+    #[serde(default)]
+    pub actions: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LimitsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Commands sent in order to the device when a hard limit is crossed
+    #[serde(default = "default_actions")]
+    pub actions: Vec<String>,
+    /// A tier clears only once the value is this far back inside the limit
+    #[serde(default)]
+    pub hysteresis: f64,
+    /// Physical or virtual channel (e.g. co_ppm) → limits
+    #[serde(default)]
+    pub channels: BTreeMap<String, ChannelLimits>,
+}
+
+fn default_actions() -> Vec<String> { vec!["VALVES_CLOSE".to_string(), "PURGE".to_string()] }
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self { enabled: false, actions: default_actions(), hysteresis: 0.0, channels: BTreeMap::new() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    limits: LimitsConfig,
+}
+
+impl LimitsConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc).try_into::<ConfigFile>().unwrap_or_default().limits
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.hysteresis < 0.0 {
+            return Err("limits.hysteresis must not be negative".to_string());
+        }
+        for (name, limits) in &self.channels {
+            let inside = |hard: Option<f64>, soft: Option<f64>, high: bool| match (hard, soft) {
+                (Some(hard), Some(soft)) => if high { hard < soft } else { hard > soft },
+                _ => false,
+            };
+            if inside(limits.hard_high, limits.soft_high, true) || inside(limits.hard_low, limits.soft_low, false) {
+                return Err(format!("limits.channels.{}: hard limits must lie beyond the soft ones", name));
+            }
+            if let (Some(low), Some(high)) = (limits.soft_low.or(limits.hard_low), limits.soft_high.or(limits.hard_high)) {
+                if low >= high {
+                    return Err(format!("limits.channels.{}: low limits must be below the high ones", name));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Tier {
+    #[default]
+    Normal,
+    /// Notify only
+    Soft,
+    /// Notify and run the protective actions
+    Hard,
+}
+
+impl ChannelLimits {
+    /// Tier of `value`, with the limits relaxed by `slack` (hysteresis)
+    fn tier(&self, value: f64, slack: f64) -> (Tier, Option<f64>) {
+        let crossed = |high: Option<f64>, low: Option<f64>| {
+            high.filter(|h| value >= h - slack).or_else(|| low.filter(|l| value <= l + slack))
+        };
+        if let Some(limit) = crossed(self.hard_high, self.hard_low) {
+            return (Tier::Hard, Some(limit));
+        }
+        if let Some(limit) = crossed(self.soft_high, self.soft_low) {
+            return (Tier::Soft, Some(limit));
+        }
+        (Tier::Normal, None)
+    }
+}
+
+/// "limit" GUI event: a channel changing tier
+#[derive(Debug, Serialize, Clone)]
+pub struct LimitEvent {
+    pub device: String,
+    pub channel: String,
+    pub tier: Tier,
+    pub previous: Tier,
+    pub value: f64,
+    /// Limit crossed, None once back to normal
+    pub limit: Option<f64>,
+    /// Protective commands to send (only when entering the hard tier)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<String>,
+    pub timestamp: i64,
+}
+
+impl LimitEvent {
+    /// Same lifecycle as alert rules, so SMS/MQTT notifiers pick it up
+    pub fn alert_event(&self) -> AlertEvent {
+        let state = if self.tier == Tier::Normal { AlertState::Resolved } else { AlertState::Firing };
+        let tier = if self.tier == Tier::Normal { self.previous } else { self.tier };
+        AlertEvent {
+            name: format!("limit:{}:{}", self.device, self.channel),
+            device: self.device.clone(),
+            severity: if tier == Tier::Hard { "critical" } else { "warning" }.to_string(),
+            condition: match self.limit {
+                Some(limit) => format!("{} {:?} limit {}", self.channel, tier, limit).to_lowercase(),
+                None => format!("{} back within limits", self.channel),
+            },
+            state,
+            reason: NotifyReason::Transition,
+            active: state == AlertState::Firing,
+            escalation_level: 0,
+            acknowledged_by: None,
+            silenced: false,
+            silenced_until: None,
+            targets: Vec::new(),
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+// ================= Limit Monitor =================
+/// Per device: two alarm tiers per channel. Soft limits only notify;
+/// entering a hard limit also returns the protective command sequence
+/// for the device (e.g. close the sample valve, PURGE).
+pub struct LimitMonitor {
+    config: LimitsConfig,
+    tiers: BTreeMap<String, Tier>,
+}
+
+impl LimitMonitor {
+    pub fn new(config: LimitsConfig) -> Option<Self> {
+        config.enabled.then(|| Self { config, tiers: BTreeMap::new() })
+    }
+
+    /// Check one processed frame; returns tier changes
+    pub fn update(&mut self, frame: &UnifiedSensorData) -> Vec<LimitEvent> {
+        let mut events = Vec::new();
+        for (name, limits) in &self.config.channels {
+            let value = match Channel::from_name(name) {
+                Some(channel) => frame.channels[channel],
+                None => match frame.virtual_channels.get(name) {
+                    Some(value) => *value,
+                    None => continue,
+                },
+            };
+            if !value.is_finite() {
+                continue;
+            }
+            let value = value as f64;
+            let previous = self.tiers.get(name).copied().unwrap_or_default();
+            let (crossed, limit) = limits.tier(value, 0.0);
+            // Tier yang sudah aktif baru turun setelah melewati histeresis
+            let (held, held_limit) = limits.tier(value, self.config.hysteresis);
+            let (tier, limit) = if previous.min(held) > crossed { (previous.min(held), held_limit) } else { (crossed, limit) };
+            if tier == previous {
+                continue;
+            }
+            self.tiers.insert(name.clone(), tier);
+            let actions = if tier == Tier::Hard {
+                limits.actions.clone().unwrap_or_else(|| self.config.actions.clone())
+            } else {
+                Vec::new()
+            };
+            events.push(LimitEvent {
+                device: frame.device.clone(),
+                channel: name.clone(),
+                tier,
+                previous,
+                value,
+                limit,
+                actions,
+                timestamp: frame.timestamp,
+            });
+        }
+        events
+    }
+}
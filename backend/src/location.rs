@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::UnifiedSensorData;
+
+/// `GPS:<lat>,<lon>[,<alt>]` from firmware with a GPS module; `GPS:NOFIX` drops the fix
+pub const GPS_PREFIX: &str = "GPS:";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LocationConfig {
+    /// "off", "fixed" (latitude/longitude below) or "gps" (GPS: lines from the device)
+    #[serde(default = "default_source")]
+    pub source: String,
+    #[serde(default)]
+    pub latitude: f64,
+    #[serde(default)]
+    pub longitude: f64,
+    #[serde(default)]
+    pub altitude: Option<f64>,
+    /// InfluxDB `location` tag, e.g. the site or campaign name
+    #[serde(default)]
+    pub name: String,
+    /// A GPS fix older than this no longer tags frames, ms
+    #[serde(default = "default_gps_max_age_ms")]
+    pub gps_max_age_ms: i64,
+    /// Located readings kept for GET /api/geojson, across devices
+    #[serde(default = "default_track_points")]
+    pub track_points: usize,
+}
+
+fn default_source() -> String { "off".to_string() }
+fn default_gps_max_age_ms() -> i64 { 10_000 }
+fn default_track_points() -> usize { 2000 }
+
+impl Default for LocationConfig {
+    fn default() -> Self {
+        Self {
+            source: default_source(),
+            latitude: 0.0,
+            longitude: 0.0,
+            altitude: None,
+            name: String::new(),
+            gps_max_age_ms: default_gps_max_age_ms(),
+            track_points: default_track_points(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    location: LocationConfig,
+}
+
+impl LocationConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc).try_into::<ConfigFile>().unwrap_or_default().location
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !["off", "fixed", "gps"].contains(&self.source.as_str()) {
+            return Err(format!("location.source '{}' must be off, fixed or gps", self.source));
+        }
+        if !in_range(self.latitude, self.longitude) {
+            return Err("location.latitude/longitude out of range".to_string());
+        }
+        if self.gps_max_age_ms <= 0 {
+            return Err("location.gps_max_age_ms must be positive".to_string());
+        }
+        if self.track_points == 0 {
+            return Err("location.track_points must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn in_range(latitude: f64, longitude: f64) -> bool {
+    (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)
+}
+
+/// Where a frame was taken
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub altitude: Option<f64>,
+    /// "gps" or "config"
+    pub source: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+}
+
+/// `<lat>,<lon>[,<alt>]` in decimal degrees / metres
+fn parse_gps(data: &str) -> Result<(f64, f64, Option<f64>), String> {
+    let values = data
+        .split(',')
+        .map(|v| v.trim().parse::<f64>().map_err(|_| format!("invalid GPS value '{}'", v.trim())))
+        .collect::<Result<Vec<_>, _>>()?;
+    match values[..] {
+        [latitude, longitude] if in_range(latitude, longitude) => Ok((latitude, longitude, None)),
+        [latitude, longitude, altitude] if in_range(latitude, longitude) => Ok((latitude, longitude, Some(altitude))),
+        [_, _] | [_, _, _] => Err("GPS coordinates out of range".to_string()),
+        _ => Err("expected <lat>,<lon>[,<alt>]".to_string()),
+    }
+}
+
+// ================= Location Tagging =================
+/// Per-device position for frames: fixed from config, or the last GPS fix
+/// the device reported while it is fresh
+pub struct Locator {
+    config: LocationConfig,
+    /// (backend time ms, fix)
+    fix: Option<(i64, Location)>,
+}
+
+impl Locator {
+    pub fn new(config: LocationConfig) -> Self {
+        Self { config, fix: None }
+    }
+
+    /// New settings from a config reload; the last fix is kept
+    pub fn reload(&mut self, config: LocationConfig) {
+        self.config = config;
+    }
+
+    /// A GPS: line from the device
+    pub fn gps(&mut self, device: &str, data: &str, now: i64) {
+        if data.trim().eq_ignore_ascii_case("NOFIX") {
+            self.fix = None;
+            return;
+        }
+        match parse_gps(data) {
+            Ok((latitude, longitude, altitude)) => {
+                if self.fix.is_none() && self.config.source == "gps" {
+                    println!("🛰️ {} GPS fix: {:.5}, {:.5}", device, latitude, longitude);
+                }
+                let location = Location { latitude, longitude, altitude, source: "gps".to_string(), name: String::new() };
+                self.fix = Some((now, location));
+            }
+            Err(e) => eprintln!("⚠️ {} GPS line ignored: {}", device, e),
+        }
+    }
+
+    /// Location for a frame processed at `now`, None if unknown
+    pub fn locate(&self, now: i64) -> Option<Location> {
+        let mut location = match self.config.source.as_str() {
+            "fixed" => Location {
+                latitude: self.config.latitude,
+                longitude: self.config.longitude,
+                altitude: self.config.altitude,
+                source: "config".to_string(),
+                name: String::new(),
+            },
+            "gps" => match &self.fix {
+                Some((at, fix)) if now - at <= self.config.gps_max_age_ms => fix.clone(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        location.name = self.config.name.clone();
+        Some(location)
+    }
+}
+
+/// One located reading
+struct TrackPoint {
+    timestamp: i64,
+    device: String,
+    session: String,
+    state_name: String,
+    location: Location,
+    values: BTreeMap<String, f32>,
+}
+
+/// GET /api/geojson?device=&from=&to=&limit=
+#[derive(Debug, Deserialize, Default)]
+pub struct TrackQuery {
+    pub device: Option<String>,
+    /// Epoch ms bounds (inclusive)
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Only the newest N matching readings
+    pub limit: Option<usize>,
+}
+
+// ================= Location Track =================
+/// Recent located readings across devices, for maps of mobile campaigns
+#[derive(Clone)]
+pub struct LocationTrack {
+    points: Arc<Mutex<VecDeque<TrackPoint>>>,
+    capacity: usize,
+}
+
+impl LocationTrack {
+    pub fn new(config: &LocationConfig) -> Self {
+        Self { points: Arc::new(Mutex::new(VecDeque::new())), capacity: config.track_points.max(1) }
+    }
+
+    fn push(&self, frame: &UnifiedSensorData) {
+        let Some(location) = &frame.location else { return };
+        let values = frame
+            .channels
+            .iter()
+            .map(|(channel, value)| (channel.name().to_string(), value))
+            .chain(frame.virtual_channels.iter().map(|(name, value)| (name.clone(), *value)))
+            .filter(|(_, value)| value.is_finite())
+            .collect();
+        let mut points = self.points.lock().unwrap();
+        points.push_back(TrackPoint {
+            timestamp: frame.timestamp,
+            device: frame.device.clone(),
+            session: frame.session.clone(),
+            state_name: frame.state_name.clone(),
+            location: location.clone(),
+            values,
+        });
+        while points.len() > self.capacity {
+            points.pop_front();
+        }
+    }
+
+    /// FeatureCollection of Point features in time order; coordinates are
+    /// [lon, lat(, alt)] as GeoJSON wants, readings go in `properties`
+    pub fn geojson(&self, query: &TrackQuery) -> serde_json::Value {
+        let points = self.points.lock().unwrap();
+        let mut features: Vec<serde_json::Value> = points
+            .iter()
+            .rev()
+            .filter(|p| query.device.as_deref().is_none_or(|d| p.device == d))
+            .filter(|p| query.from.is_none_or(|from| p.timestamp >= from))
+            .filter(|p| query.to.is_none_or(|to| p.timestamp <= to))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|p| {
+                let mut coordinates = vec![p.location.longitude, p.location.latitude];
+                coordinates.extend(p.location.altitude);
+                let mut properties = serde_json::json!({
+                    "device": p.device,
+                    "timestamp": p.timestamp,
+                    "state": p.state_name,
+                    "location_source": p.location.source,
+                });
+                if !p.session.is_empty() {
+                    properties["session"] = p.session.clone().into();
+                }
+                if !p.location.name.is_empty() {
+                    properties["location"] = p.location.name.clone().into();
+                }
+                for (name, value) in &p.values {
+                    properties[name] = (*value as f64).into();
+                }
+                serde_json::json!({
+                    "type": "Feature",
+                    "geometry": { "type": "Point", "coordinates": coordinates },
+                    "properties": properties,
+                })
+            })
+            .collect();
+        features.reverse();
+        serde_json::json!({ "type": "FeatureCollection", "features": features })
+    }
+}
+
+/// Feed located frames into the track
+pub async fn run(track: LocationTrack, frame_tx: broadcast::Sender<UnifiedSensorData>) {
+    let mut frame_rx = frame_tx.subscribe();
+    loop {
+        match frame_rx.recv().await {
+            Ok(frame) => track.push(&frame),
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
@@ -1,23 +1,296 @@
 use tokio::{
-    io::{AsyncBufReadExt, BufReader, AsyncWriteExt},
-    net::{TcpListener, TcpStream},
-    sync::broadcast,
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader, AsyncWriteExt},
+    net::TcpStream,
+    sync::{broadcast, mpsc},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use anyhow::Result;
-use chrono::Utc;
 use dotenv::dotenv;
+use std::collections::BTreeMap;
 use std::env;
+use std::net::SocketAddr;
+
+mod config;
+use config::{ConfigStore, ReloadConfig};
+
+mod clock;
+use clock::SharedClock;
+
+mod wavelet;
+
+mod virtual_channels;
+
+mod channels;
+use channels::Channels;
 
 mod filtering;
-use filtering::{SensorFilters, FilterConfig, UnifiedSensorRaw};
+use filtering::{SensorFilters, FilterConfig, Modulation, UnifiedSensorRaw};
+
+mod features;
+use features::{CycleExtractor, FeaturesConfig};
+
+mod classifier;
+
+mod derived;
+use derived::{DerivedConfig, DerivedMetrics, Scope};
+mod spectral;
 
 mod influxdb;
-use influxdb::{InfluxDBHandler, UnifiedSensorData as InfluxData};
+mod influx_queue;
+use influxdb::{InfluxDBHandler, InfluxWriterConfig, UnifiedSensorData as InfluxData};
+
+mod influx_bootstrap;
+use influx_bootstrap::InfluxBootstrapConfig;
+
+mod diagnostics;
+use diagnostics::Diagnostics;
+
+mod board;
+use board::{BoardConfig, Boards, BOARD_PREFIX};
+
+mod debug_bundle;
+use debug_bundle::{DebugBundleConfig, DebugCapture};
+
+mod control_lock;
+use control_lock::{ControlLock, ControlLockConfig};
+
+mod auth;
+use auth::{Auth, AuthConfig, Identity};
+
+mod gui_web;
+use gui_web::GuiConfig;
+
+mod recording;
+use recording::Recorder;
+
+mod frame_schema;
+
+mod console;
+use console::{Console, ConsoleConfig};
+
+mod baseline;
+use baseline::BaselineConfig;
+
+mod experiments;
+use experiments::ExperimentQueue;
+
+mod flow;
+use flow::{FlowConfig, FlowMonitor};
+
+mod pid;
+
+mod humidity;
+use humidity::HumidityConfig;
+
+mod leak_test;
+use leak_test::{LeakTestConfig, LeakTester};
+
+mod heater;
+use heater::{HeaterConfig, HeaterControl};
+
+mod burn_in;
+use burn_in::BurnIn;
+
+mod mfc;
+use mfc::{Dilution, DilutionConfig};
+
+mod trigger;
+use trigger::TriggerConfig;
+
+mod store;
+use store::{LocalStore, StoreConfig};
+
+mod sessions;
+use sessions::{SessionFilter, Sessions, SessionsConfig};
+
+mod backup;
+
+mod disk;
+use disk::DiskConfig;
+
+mod privacy;
+use privacy::{PrivacyConfig, Redactor};
+
+mod clients;
+use clients::ClientRegistry;
+
+mod viewer;
+use viewer::ViewerConfig;
+
+mod dialer;
+use dialer::{Backoff, EndpointConfig, EndpointKind};
+
+mod arduino_link;
+use arduino_link::ArduinoConfig;
+
+mod latency;
+use latency::{FrameTrace, LatencyConfig, LatencyTracer, Stage};
+
+mod devices;
+use devices::{DeviceLink, DeviceRegistry, DeviceRegistryConfig, CAPS_PREFIX, ID_PREFIX};
+
+mod failover;
+use failover::{Failover, FailoverConfig};
+
+mod aggregator;
+use aggregator::AggregatorConfig;
+
+mod uplink;
+use uplink::{Uplink, UplinkConfig};
+
+mod capture;
+use capture::{CaptureConfig, RawCapture};
+
+mod video_sync;
+use video_sync::VideoSyncConfig;
+
+// Outdoor weather next to the sensor data (ambient deployments)
+mod weather;
+use weather::{LatestWeather, WeatherConfig};
+
+mod reference;
+use reference::ReferenceConfig;
+
+mod cross_correlation;
+use cross_correlation::CrossCorrelationConfig;
+
+mod channel_mask;
+use channel_mask::{ChannelMask, ChannelMaskConfig};
+
+mod modulation;
+use modulation::ModulationControl;
+
+mod clock_drift;
+use clock_drift::ClockDrift;
+
+mod backfill;
+use backfill::{Backfill, BackfillConfig, BACKFILL_END, BACKFILL_PREFIX};
+
+mod adaptive;
+use adaptive::{AdaptiveStorage, AdaptiveStorageConfig};
+mod pre_trigger;
+use pre_trigger::{PreTrigger, PreTriggerConfig};
+mod calibration;
+use calibration::CalibrationCurve;
+mod limits;
+use limits::{LimitMonitor, LimitsConfig};
+mod cycle_abort;
+use cycle_abort::{CycleAbort, CycleAbortConfig};
+mod shift_log;
+use shift_log::{ShiftLog, ShiftLogConfig};
+
+mod waveform;
+use waveform::{WaveformConfig, WaveformRecorder, Waveforms};
+
+mod differential;
+use differential::{Differential, REFERENCE_PREFIX};
+
+mod analytics;
+mod history_spill;
+use analytics::{AnalyticsConfig, FrameHistory};
+
+mod deadband;
+use deadband::{Deadband, DeadbandConfig};
 
-fn create_filters() -> SensorFilters {
-    let config = FilterConfig::load("config.toml");
-    SensorFilters::new(&config)
+mod ambient;
+use ambient::{Ambient, AmbientConfig};
+
+// Posisi per frame (lokasi tetap atau GPS) untuk kampanye mobile
+mod location;
+use location::{Location, LocationConfig, LocationTrack, Locator, GPS_PREFIX};
+
+mod campaign;
+use campaign::Campaigns;
+
+mod replay;
+mod roc;
+mod accuracy;
+use accuracy::{AccuracyConfig, AccuracyTracker};
+mod usage;
+use usage::{UsageConfig, UsageStats};
+mod jobs;
+use jobs::Jobs;
+mod models;
+use models::{ModelRegistry, ModelsConfig};
+mod transfer;
+mod noise_floor;
+use noise_floor::{NoiseFloor, NoiseFloorConfig};
+mod saturation;
+use saturation::{SaturationConfig, SaturationDetector};
+mod ranges;
+use ranges::{GainRanges, RangeConfig, RANGE_PREFIX};
+mod device_blocks;
+use device_blocks::{BlockLine, BlockParser};
+mod device_log;
+use device_log::{DeviceLog, DeviceLogConfig};
+
+// Golden-vector regression checks for filters and features
+mod golden;
+
+// Terminal UI client (`enose monitor`)
+mod monitor;
+
+mod audit;
+use audit::{AuditConfig, AuditLog};
+
+mod alerts;
+use alerts::AlertEngine;
+
+mod sms;
+use sms::SmsConfig;
+
+mod mqtt;
+use mqtt::MqttConfig;
+
+mod kafka;
+use kafka::KafkaConfig;
+
+mod nats;
+use nats::NatsConfig;
+
+mod redis_cache;
+use redis_cache::RedisConfig;
+
+mod provenance;
+use provenance::{CalibrationConfig, Provenance};
+
+mod plugins;
+use plugins::{PluginConfig, WasmPlugins};
+
+mod scripting;
+use scripting::{ScriptContext, ScriptingConfig};
+
+mod api;
+use api::ApiConfig;
+
+mod rtc_stream;
+use rtc_stream::{RtcGateway, WebRtcConfig};
+
+fn create_filters(device: Option<&str>, clock: SharedClock) -> SensorFilters {
+    let config = FilterConfig::load("config.toml", device);
+    SensorFilters::with_clock(&config, clock)
+}
+
+fn create_influx(data_tx: broadcast::Sender<String>, clock: SharedClock) -> InfluxDBHandler {
+    // Try to get from env, fallback to hardcoded
+    let influx_url = env::var("INFLUXDB_URL").unwrap_or_else(|_| "http://localhost:8086".to_string());
+    let influx_token = env::var("INFLUXDB_TOKEN").unwrap_or_else(|_| {
+        // ⚠️ GANTI INI DENGAN TOKEN BARU DARI INFLUXDB!
+        "YFwmMyQPO9BqaLrw9HKqlRxUYWWbD0Fulfbr_OgmDuZiCpABq64ch5xn_b8g1lSM4Ow65pci4iFdDMpqf0l_vw==".to_string()
+    });
+    let influx_org = env::var("INFLUXDB_ORG").unwrap_or_else(|_| "011a1a9099df7a18".to_string());
+    let influx_bucket = env::var("INFLUXDB_BUCKET").unwrap_or_else(|_| "E-Nose".to_string());
+
+
+    InfluxDBHandler::new(
+        &influx_url,
+        &influx_token,
+        &influx_org,
+        &influx_bucket,
+        InfluxWriterConfig::load("config.toml"),
+        data_tx,
+        clock,
+    )
 }
 
 /// Map state integer to readable state name
@@ -30,24 +303,141 @@ fn state_to_name(state: i32) -> String {
         4 => "PURGE",
         5 => "RECOVERY",
         6 => "DONE",
+        ambient::AMBIENT => "AMBIENT",
         _ => "UNKNOWN",
     }.to_string()
 }
 
-#[derive(Serialize, Debug, Clone)]
+/// Sensor data line: `SENSOR:<no2>,<eth>,...,<state>,<level>[,...]`
+const SENSOR_PREFIX: &str = "SENSOR:";
+
+/// Channel names in SENSOR line order
+const CHANNEL_NAMES: [&str; 7] = channels::Channel::NAMES;
+
+/// Top-level fields `UnifiedSensorData` serializes besides the channels;
+/// update together with the struct
+const PAYLOAD_FIELDS: [&str; 22] = [
+    "state", "state_name", "level", "flow", "pressure", "humidity", "heater_temp", "heater_setpoint",
+    "target_ppm", "delivered_ppm", "cycle_valid", "timestamp", "source", "device", "session", "reference",
+    "unverified", "location", "raw", "modulation", "saturated", "ranges",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct UnifiedSensorData {
-    no2: f32,
-    eth: f32,
-    voc: f32,
-    co: f32,
-    com: f32,
-    ethm: f32,
-    vocm: f32,
+    // Kanal yang dimatikan bernilai NaN, dikirim sebagai null
+    #[serde(flatten)]
+    channels: Channels,
     state: i32,
     state_name: String,
     level: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flow: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pressure: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    humidity: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heater_temp: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heater_setpoint: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_ppm: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delivered_ppm: Option<f32>,
+    cycle_valid: bool,
     timestamp: i64,
     source: String,
+    /// Device id (aggregated frames: "<edge>/<device>")
+    #[serde(default)]
+    device: String,
+    /// Session the frame was recorded in, empty outside sessions
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    session: String,
+    /// Reference (clean-air) chamber channels in CHANNEL_NAMES order, dual-chamber rigs only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reference: Option<[f32; 7]>,
+    /// Sensor board differs from the confirmed one: shown, never stored
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    unverified: bool,
+    /// Ambient mode between storage intervals: processed and shown, not written to InfluxDB
+    #[serde(skip)]
+    skip_storage: bool,
+    /// Fixed site or the device's GPS fix, see [location]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+    /// Unfiltered channels in CHANNEL_NAMES order, only with `send_raw`
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "nullable_channels")]
+    raw: Option<[f32; 7]>,
+    /// Sine modulation applied per modulated channel
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    modulation: BTreeMap<String, Modulation>,
+    /// Channels railed at the ADC limits, see [saturation]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    saturated: Vec<String>,
+    /// Gain range code per channel reported by the firmware, see [ranges]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    ranges: BTreeMap<String, u8>,
+    /// Virtual channels appear as top-level fields next to physical ones
+    #[serde(flatten)]
+    virtual_channels: BTreeMap<String, f32>,
+}
+
+/// Channels and backend subsystems shared across Arduino and GUI tasks
+#[derive(Clone)]
+struct AppState {
+    data_tx: broadcast::Sender<String>,
+    cmd_tx: broadcast::Sender<String>,
+    frame_tx: broadcast::Sender<UnifiedSensorData>,
+    influx: InfluxDBHandler,
+    queue: ExperimentQueue,
+    leak_test: LeakTester,
+    heater: HeaterControl,
+    burn_in: BurnIn,
+    dilution: Dilution,
+    config: ConfigStore,
+    alerts: AlertEngine,
+    audit: AuditLog,
+    rtc: RtcGateway,
+    clients: ClientRegistry,
+    failover: Failover,
+    uplink: Uplink,
+    sessions: Sessions,
+    clock: SharedClock,
+    history: FrameHistory,
+    track: LocationTrack,
+    /// Latest [weather] reading, for plume bearings in ambient mode
+    weather: LatestWeather,
+    waveforms: Waveforms,
+    storage: AdaptiveStorage,
+    /// Full-rate raw frames flushed when an alarm fires
+    pre_trigger: PreTrigger,
+    backfill: Backfill,
+    channel_mask: ChannelMask,
+    modulation: ModulationControl,
+    diagnostics: Diagnostics,
+    boards: Boards,
+    debug: DebugCapture,
+    control: ControlLock,
+    auth: Auth,
+    recorder: Recorder,
+    campaigns: Campaigns,
+    capture: RawCapture,
+    jobs: Jobs,
+    accuracy: AccuracyTracker,
+    usage: UsageStats,
+    models: ModelRegistry,
+    noise_floor: NoiseFloor,
+    console: Console,
+    /// Non-data device lines, persisted per device
+    device_log: DeviceLog,
+    latency: LatencyTracer,
+    devices: DeviceRegistry,
+    shift_log: ShiftLog,
+}
+
+/// Disabled channels travel as `null`; read them back as NaN
+fn nullable_channels<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<[f32; 7]>, D::Error> {
+    Ok(Option::<[Option<f32>; 7]>::deserialize(deserializer)?.map(|values| values.map(|v| v.unwrap_or(f32::NAN))))
 }
 
 #[tokio::main]
@@ -57,77 +447,783 @@ async fn main() -> Result<()> {
     // Load environment variables (optional)
     dotenv().ok();
 
-    let filters = create_filters();
-
-    // Try to get from env, fallback to hardcoded
-    let influx_url = env::var("INFLUXDB_URL").unwrap_or_else(|_| "http://localhost:8086".to_string());
-    let influx_token = env::var("INFLUXDB_TOKEN").unwrap_or_else(|_| {
-        // ⚠️ GANTI INI DENGAN TOKEN BARU DARI INFLUXDB!
-        "YFwmMyQPO9BqaLrw9HKqlRxUYWWbD0Fulfbr_OgmDuZiCpABq64ch5xn_b8g1lSM4Ow65pci4iFdDMpqf0l_vw==".to_string()
-    });
-    let influx_org = env::var("INFLUXDB_ORG").unwrap_or_else(|_| "011a1a9099df7a18".to_string());
-    let influx_bucket = env::var("INFLUXDB_BUCKET").unwrap_or_else(|_| "E-Nose".to_string());
-    
-    println!("📊 InfluxDB Config:");
-    println!("   URL: {}", influx_url);
-    println!("   Org: {}", influx_org);
-    println!("   Bucket: {}", influx_bucket);
-    if influx_token.len() > 30 {
-        println!("   Token: {}...{}", &influx_token[..15], &influx_token[influx_token.len()-10..]);
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        return run_cli(&args).await;
     }
 
-    let influx = InfluxDBHandler::new(
-        &influx_url,
-        &influx_token,
-        &influx_org,
-        &influx_bucket,
-    );
-
     // Channel untuk broadcast data sensor ke GUI
     let (data_tx, _rx) = broadcast::channel::<String>(100);
+
+    // Satu sumber waktu untuk pipeline dan writer
+    let clock = clock::system();
+
+    let influx = create_influx(data_tx.clone(), clock.clone());
+
+    // Bucket, retention & downsampling task untuk deployment baru
+    let bootstrap_config = InfluxBootstrapConfig::load("config.toml");
+    if bootstrap_config.enabled {
+        tokio::spawn(influx_bootstrap::run(bootstrap_config, influx.clone()));
+    }
+
+    // Local store (SQLite), schema dimigrasi otomatis saat startup
+    let store_path = StoreConfig::load("config.toml").path;
+    let store = LocalStore::open(&store_path)?;
     
     // Channel untuk command dari GUI ke Arduino
     let (cmd_tx, _cmd_rx) = broadcast::channel::<String>(10);
 
-    // Server GUI (TCP 8082)
-    tokio::spawn(gui_server(data_tx.clone(), cmd_tx.clone()));
+    // Channel untuk frame terfilter ke subsistem internal (baseline, dll)
+    let (frame_tx, _frame_rx) = broadcast::channel::<UnifiedSensorData>(100);
+
+    // Banyak e-nose ke satu backend: identitas per device, command beralamat
+    let devices = DeviceRegistry::new(DeviceRegistryConfig::load("config.toml", None), data_tx.clone());
+
+    // Nightly baseline/zero cycle
+    let baseline_config = BaselineConfig::load("config.toml");
+    if baseline_config.enabled {
+        tokio::spawn(baseline::run_scheduler(
+            baseline_config,
+            cmd_tx.clone(),
+            data_tx.clone(),
+            frame_tx.clone(),
+            devices.clone(),
+        ));
+    }
+
+    // Humidity conditioning loop
+    let humidity_config = HumidityConfig::load("config.toml");
+    if humidity_config.enabled {
+        tokio::spawn(humidity::run_controller(humidity_config, cmd_tx.clone(), frame_tx.clone()));
+    }
+
+    // Trigger output untuk instrumen eksternal
+    let trigger_config = TriggerConfig::load("config.toml");
+    if trigger_config.enabled {
+        tokio::spawn(trigger::run_trigger(
+            trigger_config,
+            cmd_tx.clone(),
+            data_tx.clone(),
+            frame_tx.clone(),
+        ));
+    }
+
+    let sessions = Sessions::new(SessionsConfig::load("config.toml"), store.clone())?;
+
+    // Sync marker untuk rekaman kamera eksternal
+    let video_sync_config = VideoSyncConfig::load("config.toml");
+    if video_sync_config.enabled {
+        tokio::spawn(video_sync::run(video_sync_config, data_tx.clone(), frame_tx.clone()));
+    }
+
+    let weather_config = WeatherConfig::load("config.toml");
+    let weather = LatestWeather::default();
+    if weather_config.enabled {
+        tokio::spawn(weather::run(weather_config, weather.clone(), data_tx.clone(), influx.clone()));
+    }
+
+    // Rasio/korelasi antar sensor berbeda jenis
+    let cross_config = CrossCorrelationConfig::load("config.toml");
+    if cross_config.enabled {
+        tokio::spawn(cross_correlation::run(
+            cross_config,
+            data_tx.clone(),
+            frame_tx.clone(),
+            influx.clone(),
+        ));
+    }
+
+    let audit = AuditLog::new(&AuditConfig::load("config.toml"));
+    let config = ConfigStore::new("config.toml", "config_versions");
+
+    // Registry model klasifier berversi (aktivasi eksplisit + rollback)
+    let models = ModelRegistry::new(&ModelsConfig::load("config.toml"), audit.clone(), data_tx.clone());
+
+    // Akurasi klasifier terhadap label sesi (confusion matrix)
+    let accuracy_config = AccuracyConfig::load("config.toml");
+    let report_hours = accuracy_config.report_hours;
+    let accuracy = AccuracyTracker::new(accuracy_config, store.clone());
+    if report_hours > 0 {
+        tokio::spawn(accuracy::run(accuracy.clone(), data_tx.clone(), influx.clone()));
+    }
+
+    // Karakterisasi noise floor per device (SNR untuk alert dan skor kualitas siklus)
+    let noise_floor = NoiseFloor::new(
+        NoiseFloorConfig::load("config.toml"),
+        store.clone(),
+        cmd_tx.clone(),
+        data_tx.clone(),
+        frame_tx.clone(),
+        audit.clone(),
+        devices.clone(),
+    );
+
+    // Fitur per siklus sniff (+ klasifikasi gas)
+    let features_config = FeaturesConfig::load("config.toml");
+    if features_config.enabled {
+        let extractor = CycleExtractor::new(&features_config, models.clone(), noise_floor.clone());
+        tokio::spawn(features::run(
+            features_config,
+            extractor,
+            data_tx.clone(),
+            frame_tx.clone(),
+            influx.clone(),
+            accuracy.clone(),
+            config.subscribe_reloads(),
+        ));
+    }
+
+    // Riwayat frame di memori untuk query analitik (heatmap korelasi, dll.)
+    let history = FrameHistory::new(&AnalyticsConfig::load("config.toml"));
+    tokio::spawn(analytics::run(history.clone(), frame_tx.clone()));
+    let track = LocationTrack::new(&LocationConfig::load("config.toml", None));
+    tokio::spawn(location::run(track.clone(), frame_tx.clone()));
+
+    // Alert rules (expression engine)
+    let alerts = AlertEngine::load("config.toml", data_tx.clone(), noise_floor.clone());
+    tokio::spawn(alerts.clone().run(frame_tx.clone()));
+    tokio::spawn(sessions::watch_alerts(sessions.clone(), alerts.clone()));
+
+    // Statistik pemakaian instrumen (siklus/hari, jam aktif, command, alarm)
+    let usage_config = UsageConfig::load("config.toml");
+    let usage_enabled = usage_config.enabled;
+    let usage = UsageStats::new(usage_config, store.clone());
+    if usage_enabled {
+        tokio::spawn(usage::run(usage.clone(), frame_tx.clone(), alerts.clone(), data_tx.clone(), influx.clone()));
+    }
+
+    let sms_config = SmsConfig::load("config.toml");
+    if sms_config.enabled {
+        tokio::spawn(sms::run_notifier(sms_config, alerts.subscribe()));
+    }
+
+    let mqtt_config = MqttConfig::load("config.toml");
+    if mqtt_config.enabled {
+        tokio::spawn(mqtt::run_alarm_publisher(mqtt_config, alerts.subscribe()));
+    }
+
+    // Frame dan event ke platform data berbasis Kafka (via REST proxy)
+    let kafka_config = KafkaConfig::load("config.toml");
+    if kafka_config.enabled {
+        tokio::spawn(kafka::run(kafka_config, frame_tx.clone(), data_tx.clone()));
+    }
+
+    // Nilai terbaru per device di Redis untuk layanan lain
+    let redis_config = RedisConfig::load("config.toml");
+    if redis_config.enabled {
+        tokio::spawn(redis_cache::run(redis_config, frame_tx.clone()));
+    }
+
+    // Gas dilution (MFC)
+    let dilution = Dilution::new(DilutionConfig::load("config.toml"), data_tx.clone());
+
+    // Antrian eksperimen
+    let queue = ExperimentQueue::load(
+        "config.toml",
+        dilution.clone(),
+        devices.clone(),
+        cmd_tx.clone(),
+        data_tx.clone(),
+        frame_tx.clone(),
+    );
+
+    let leak_test = LeakTester::new(
+        LeakTestConfig::load("config.toml"),
+        cmd_tx.clone(),
+        data_tx.clone(),
+        frame_tx.clone(),
+        devices.clone(),
+    );
+
+    let heater = HeaterControl::new(
+        HeaterConfig::load("config.toml"),
+        cmd_tx.clone(),
+        data_tx.clone(),
+        frame_tx.clone(),
+        devices.clone(),
+    );
+
+    // Self-check startup: config, storage, kalibrasi, plugin (JSON ke diagnostics.json)
+    let diagnostics = Diagnostics::run("config.toml", config.current_version(), &store_path, &influx).await;
+
+    // Burn-in sensor baru: heater nyala terus, baseline akhir jadi kalibrasi awal
+    let burn_in = BurnIn::new(
+        cmd_tx.clone(),
+        data_tx.clone(),
+        frame_tx.clone(),
+        heater.clone(),
+        config.clone(),
+        audit.clone(),
+        devices.clone(),
+    );
+
+    // Edit config.toml langsung diterapkan ke filter tanpa restart / reconnect
+    let reload_config = ReloadConfig::load("config.toml");
+    if reload_config.watch {
+        tokio::spawn(config::watch_file(config.clone(), reload_config));
+    }
+
+    // Pantau sisa ruang SD card, hapus file data lama kalau hampir penuh
+    let disk_config = DiskConfig::load("config.toml");
+    if disk_config.enabled {
+        tokio::spawn(disk::run(disk_config, data_tx.clone(), audit.clone()));
+    }
+
+    // Automation scripts (Rhai)
+    let scripting_config = ScriptingConfig::load("config.toml");
+    if !scripting_config.scripts.is_empty() {
+        let ctx = ScriptContext {
+            cmd_tx: cmd_tx.clone(),
+            data_tx: data_tx.clone(),
+            influx: influx.clone(),
+            alerts: alerts.clone(),
+            audit: audit.clone(),
+        };
+        tokio::spawn(scripting::run(scripting_config, ctx, frame_tx.clone()));
+    }
+
+    let rtc = RtcGateway::new(WebRtcConfig::load("config.toml"), data_tx.clone());
+
+    // Active/standby: standby baru menerima Arduino setelah peer mati
+    let failover_config = FailoverConfig::load("config.toml");
+    let failover = Failover::new(&failover_config);
+    if failover_config.enabled {
+        tokio::spawn(failover::run(failover_config, failover.clone(), data_tx.clone(), audit.clone()));
+    }
+
+    // Edge uplink: frame di-spool ke store lalu diteruskan ke aggregator
+    let uplink_config = UplinkConfig::load("config.toml");
+    let uplink = if uplink_config.enabled {
+        Uplink::start(uplink_config, store.clone())
+    } else {
+        Uplink::default()
+    };
+
+    // Rekaman mentah kontinu (terkompresi) untuk replay/export
+    let capture_config = CaptureConfig::load("config.toml");
+    let capture = if capture_config.enabled {
+        RawCapture::start(capture_config)
+    } else {
+        RawCapture::default()
+    };
+
+    // Simpan 1 dari N frame, full rate di sekitar event (peak, alarm, ganti state)
+    let storage_config = AdaptiveStorageConfig::load("config.toml");
+    let storage = AdaptiveStorage::new(storage_config.clone());
+    if storage_config.enabled && storage_config.on_alarm {
+        tokio::spawn(adaptive::watch_alerts(storage.clone(), alerts.clone(), clock.clone()));
+    }
+    // Data mentah N detik sebelum alarm, disimpan utuh untuk analisis insiden
+    let pre_trigger_config = PreTriggerConfig::load("config.toml");
+    let pre_trigger = PreTrigger::new(pre_trigger_config.clone());
+    if pre_trigger_config.enabled {
+        tokio::spawn(pre_trigger::watch_alerts(pre_trigger.clone(), alerts.clone(), influx.clone(), clock.clone()));
+    }
+
+    // Kanal sensor yang rusak bisa dimatikan saat runtime
+    let channel_mask = ChannelMask::new(&ChannelMaskConfig::load("config.toml"), data_tx.clone());
+    let modulation = ModulationControl::new("config.toml", data_tx.clone());
+
+    // Board sensor yang diganti harus dikonfirmasi operator sebelum datanya dipakai
+    let boards = Boards::new(BoardConfig::load("config.toml"), store.clone(), data_tx.clone());
+
+    // Satu operator memegang kontrol, klien GUI lain hanya menonton
+    let control = ControlLock::new(ControlLockConfig::load("config.toml"), audit.clone(), data_tx.clone());
+    if control.enabled() {
+        tokio::spawn(control_lock::run(control.clone()));
+    }
+
+    // Token per pengguna, command dicek terhadap grup izinnya
+    let auth = Auth::new(AuthConfig::load("config.toml"), audit.clone());
+
+    // Rekaman dataset per sesi (REC_START / REC_STOP)
+    let recorder = Recorder::new("config.toml", audit.clone(), data_tx.clone());
+    tokio::spawn(recording::run(recorder.clone(), frame_tx.clone()));
+
+    // Track GPS + gas untuk kampanye mobile (CAMPAIGN_START / CAMPAIGN_STOP)
+    let campaigns = Campaigns::new("config.toml", audit.clone(), data_tx.clone());
+    tokio::spawn(campaign::run(campaigns.clone(), frame_tx.clone()));
+
+    // Job latar belakang: replay/export/report dengan progres dan artefak
+    let jobs = Jobs::new("config.toml", audit.clone(), data_tx.clone());
+
+    // Sampel latensi per tahap pipeline (receive → storage)
+    let latency = LatencyTracer::new(LatencyConfig::load("config.toml"));
+    if latency.enabled() {
+        tokio::spawn(latency::run(latency.clone(), data_tx.clone()));
+    }
+
+    // Catatan shift operator, terikat ke sesi aktif dan timeline
+    let shift_log = ShiftLog::new(ShiftLogConfig::load("config.toml"), store.clone(), sessions.clone(), influx.clone(), data_tx.clone());
+
+    let state = AppState {
+        data_tx,
+        cmd_tx,
+        frame_tx,
+        influx,
+        queue,
+        leak_test,
+        heater,
+        burn_in,
+        dilution,
+        config,
+        alerts,
+        audit,
+        rtc,
+        clients: ClientRegistry::default(),
+        failover,
+        uplink,
+        sessions,
+        clock,
+        history,
+        track,
+        weather,
+        waveforms: Waveforms::new(WaveformConfig::load("config.toml")),
+        storage,
+        pre_trigger,
+        backfill: Backfill::new(BackfillConfig::load("config.toml"), store.clone()),
+        channel_mask,
+        modulation,
+        diagnostics,
+        boards,
+        debug: DebugCapture::new(&DebugBundleConfig::load("config.toml")),
+        control,
+        auth,
+        recorder,
+        campaigns,
+        capture,
+        jobs,
+        accuracy,
+        usage,
+        models,
+        noise_floor,
+        console: Console::new(ConsoleConfig::load("config.toml")),
+        device_log: DeviceLog::new(DeviceLogConfig::load("config.toml")),
+        latency,
+        devices,
+        shift_log,
+    };
+
+    tokio::spawn(jobs::run(state.jobs.clone(), state.clone()));
+
+    // Server GUI: WebSocket/REST (utama) dan TCP 8082 (legacy)
+    let gui_config = GuiConfig::load("config.toml");
+    if gui_config.web_enabled {
+        tokio::spawn(gui_web::serve(gui_config.clone(), state.clone()));
+    }
+    if gui_config.tcp_enabled {
+        tokio::spawn(gui_server(gui_config, state.clone()));
+    }
+
+    // Listener khusus display publik, tanpa kontrol
+    let viewer_config = ViewerConfig::load("config.toml");
+    if viewer_config.enabled {
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = viewer::serve(viewer_config, state).await {
+                eprintln!("❌ Viewer listener failed: {}", e);
+            }
+        });
+    }
+
+    // NATS: frame ke JetStream, command per device dari subject
+    let nats_config = NatsConfig::load("config.toml");
+    if nats_config.enabled {
+        tokio::spawn(nats::run(nats_config, state.clone()));
+    }
+
+    // REST API untuk otomasi eksternal
+    let api_config = ApiConfig::load("config.toml");
+    if api_config.enabled {
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = api::serve(api_config, state).await {
+                eprintln!("❌ REST API failed: {}", e);
+            }
+        });
+    }
+
+    // Fan-in dari edge backend (mode aggregator)
+    let aggregator_config = AggregatorConfig::load("config.toml");
+    if aggregator_config.enabled {
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = aggregator::serve(aggregator_config, state).await {
+                eprintln!("❌ Aggregator failed: {}", e);
+            }
+        });
+    }
+
+    state.failover.wait_active().await;
+
+    // Koneksi keluar (Arduino dengan IP tetap, dll)
+    for endpoint in EndpointConfig::load_all("config.toml") {
+        match endpoint.kind {
+            EndpointKind::Arduino => tokio::spawn(run_arduino_client(endpoint, state.clone())),
+        };
+    }
+
+    // Arduino lewat USB serial, bisa bersamaan dengan TCP
+    let arduino_config = ArduinoConfig::load("config.toml");
+    if arduino_config.serial_enabled {
+        tokio::spawn(run_serial_arduino(arduino_config.clone(), state.clone()));
+    }
+    if !arduino_config.tcp_enabled {
+        std::future::pending::<()>().await;
+    }
 
     // Server untuk Arduino (TCP 8081)
-    let listener = TcpListener::bind("192.168.100.187:8081").await?;
-    println!("🔌 Listening for Arduino on 192.168.100.187:8081");
+    let listener = state.diagnostics.bind("arduino", &arduino_config.tcp_bind).await?;
+    println!("🔌 Listening for Arduino on {}", arduino_config.tcp_bind);
 
     loop {
         let (stream, addr) = listener.accept().await?;
         println!("✅ Arduino connected: {}", addr);
 
-        let data_tx_clone = data_tx.clone();
-        let cmd_rx = cmd_tx.subscribe();
-        let influx_clone = influx.clone();
-        let mut filters_clone = filters.clone();
+        let state = state.clone();
+        let cmd_rx = state.cmd_tx.subscribe();
+
+        // Profil konfigurasi per device (sementara dikenali dari IP)
+        let device = addr.ip().to_string();
+        let mut pipeline = DevicePipeline::new(&device, &state.config, &state.models, state.clock.clone());
 
         tokio::spawn(async move {
-            handle_arduino(stream, data_tx_clone, cmd_rx, &mut filters_clone, influx_clone).await;
+            handle_arduino(stream, &addr.to_string(), state, cmd_rx, &mut pipeline).await;
         });
     }
 }
 
+/// Dial an Arduino that runs as a TCP server, reconnecting with backoff
+async fn run_arduino_client(endpoint: EndpointConfig, state: AppState) {
+    println!("🔌 Dialing Arduino '{}' at {}", endpoint.name, endpoint.address);
+    let mut backoff = Backoff::new(endpoint.backoff.clone());
+    while let Some(stream) = dialer::connect(&endpoint, &mut backoff).await {
+        println!("✅ Arduino '{}' connected: {}", endpoint.name, endpoint.address);
+        let mut pipeline = DevicePipeline::new(&endpoint.device(), &state.config, &state.models, state.clock.clone());
+        handle_arduino(stream, &endpoint.address, state.clone(), state.cmd_tx.subscribe(), &mut pipeline).await;
+        println!("🔁 Arduino '{}' disconnected, reconnecting", endpoint.name);
+    }
+}
+
+/// Arduino on a USB serial port; reopened whenever it is unplugged and replugged
+async fn run_serial_arduino(config: ArduinoConfig, state: AppState) {
+    println!("🔌 Opening Arduino serial port {} @ {} baud", config.serial_port, config.serial_baud);
+    loop {
+        let port = arduino_link::open_serial(&config).await;
+        println!("✅ Arduino connected: {} (serial)", config.serial_port);
+        let mut pipeline = DevicePipeline::new(&config.serial_device, &state.config, &state.models, state.clock.clone());
+        handle_arduino(port, &config.serial_port, state.clone(), state.cmd_tx.subscribe(), &mut pipeline).await;
+        println!("🔁 Arduino serial {} lost, waiting for it to come back", config.serial_port);
+        tokio::time::sleep(std::time::Duration::from_millis(config.reconnect_ms)).await;
+    }
+}
+
+// ================= CLI =================
+async fn run_cli(args: &[String]) -> Result<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["import-reference", path, rest @ ..] => {
+            let mut config = ReferenceConfig::load("config.toml");
+            let mut device = None;
+            let mut options = rest.iter();
+            while let Some(option) = options.next() {
+                let value = options.next().ok_or_else(|| anyhow::anyhow!("missing value for {}", option))?;
+                match *option {
+                    "--offset" => config.offset_secs = value.parse()?,
+                    "--instrument" => config.instrument = value.to_string(),
+                    "--device" => device = Some(*value),
+                    _ => anyhow::bail!("unknown option {}", option),
+                }
+            }
+            let store = LocalStore::open(&StoreConfig::load("config.toml").path)?;
+            let sessions = Sessions::new(SessionsConfig::load("config.toml"), store)?.list(&SessionFilter::default())?;
+            // Tanpa GUI, event writer tidak ada penerimanya
+            let influx = create_influx(broadcast::channel(1).0, clock::system());
+            let report = reference::import(path, &config, device, &sessions, &influx).await?;
+            println!("✅ Imported {} reference samples from {}", report.imported, path);
+            if !report.outside.is_empty() {
+                eprintln!("⚠️ {} row(s) outside any session, skipped: {:?}", report.outside.len(), report.outside);
+            }
+            if !report.ambiguous.is_empty() {
+                eprintln!(
+                    "⚠️ {} row(s) in multi-device sessions, skipped (pass --device): {:?}",
+                    report.ambiguous.len(),
+                    report.ambiguous
+                );
+            }
+            Ok(())
+        }
+        ["migrate", rest @ ..] => {
+            let path = StoreConfig::load("config.toml").path;
+            if rest.first() == Some(&"--status") {
+                let status = store::status(&path)?;
+                println!("🗄️ {}: schema v{} (latest v{})", path, status.current, status.latest);
+                for (version, name) in status.pending {
+                    println!("   pending v{}: {}", version, name);
+                }
+                return Ok(());
+            }
+            let applied = store::migrate(&path)?;
+            if applied.is_empty() {
+                println!("✅ {} is up to date (schema v{})", path, store::latest_version());
+            } else {
+                println!("✅ Applied {} migration(s) to {}", applied.len(), path);
+            }
+            Ok(())
+        }
+        ["monitor", rest @ ..] => monitor::run(rest.first().copied().unwrap_or("127.0.0.1:8082")).await,
+        ["golden", rest @ ..] => {
+            let dir = std::path::Path::new(golden::GOLDEN_DIR);
+            if rest.first() == Some(&"--regenerate") {
+                for path in golden::regenerate(dir)? {
+                    println!("   {}", path);
+                }
+                println!("✅ Golden vectors regenerated, review the diff before committing");
+                return Ok(());
+            }
+            let mut failed = 0;
+            for name in golden::cases() {
+                match golden::check(dir, name) {
+                    Ok(()) => println!("✅ {}", name),
+                    Err(e) => {
+                        failed += 1;
+                        eprintln!("❌ {}", e);
+                    }
+                }
+            }
+            if failed > 0 {
+                anyhow::bail!("{} golden case(s) differ", failed);
+            }
+            Ok(())
+        }
+        ["capture-debug-bundle", rest @ ..] => {
+            let offline = rest.contains(&"--offline");
+            let path = rest.iter().find(|a| !a.starts_with("--")).map_or_else(debug_bundle::file_name, |p| p.to_string());
+            // Backend yang sedang jalan punya raw line & metrik terbaru; kalau tidak ada, file saja
+            let bind = ApiConfig::load("config.toml").bind.replace("0.0.0.0", "127.0.0.1");
+            let live = match offline {
+                true => None,
+                false => match reqwest::get(format!("http://{}/api/debug-bundle", bind)).await {
+                    Ok(response) if response.status().is_success() => Some(response.bytes().await?.to_vec()),
+                    _ => None,
+                },
+            };
+            let bundle = match live {
+                Some(bundle) => bundle,
+                None => {
+                    println!("⚠️ Backend not reachable on {}, bundling config and logs only", bind);
+                    debug_bundle::capture_offline("config.toml")?
+                }
+            };
+            std::fs::write(&path, bundle)?;
+            println!("✅ Debug bundle written to {}", path);
+            Ok(())
+        }
+        ["export", dir, rest @ ..] => {
+            let dir = std::path::Path::new(dir);
+            let (version, frames) = frame_schema::read_recording(dir).map_err(anyhow::Error::msg)?;
+            let out = match rest.first() {
+                Some(path) => path.to_string(),
+                None => format!("{}.json", dir.file_name().unwrap_or_default().to_string_lossy()),
+            };
+            std::fs::write(&out, serde_json::to_string_pretty(&frames)?)?;
+            if version < frame_schema::FRAME_SCHEMA {
+                println!("🔄 Upgraded frames from schema v{} to v{}", version, frame_schema::FRAME_SCHEMA);
+            }
+            println!("✅ Exported {} frames from {} to {}", frames.len(), dir.display(), out);
+            Ok(())
+        }
+        ["capture-export", path, rest @ ..] => {
+            let (format, out) = match rest {
+                ["--format", format, out @ ..] | [out @ .., "--format", format] => (*format, out.first()),
+                out => ("csv", out.first()),
+            };
+            let path = std::path::Path::new(path);
+            let capture = capture::read(path).map_err(anyhow::Error::msg)?;
+            let exported = capture::export(&capture, format).map_err(anyhow::Error::msg)?;
+            let extension = if format == "sensor" { "txt" } else { format };
+            let out = match out {
+                Some(out) => out.to_string(),
+                None => path.with_extension(extension).to_string_lossy().to_string(),
+            };
+            std::fs::write(&out, exported)?;
+            if capture.torn_bytes > 0 {
+                println!("⚠️ Ignored {} byte(s) of an incomplete last block", capture.torn_bytes);
+            }
+            let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            println!(
+                "✅ Exported {} frames of {} ({:.1} bytes/frame on disk) to {}",
+                capture.frames.len(),
+                capture.device,
+                size as f64 / capture.frames.len().max(1) as f64,
+                out
+            );
+            Ok(())
+        }
+        ["backup", rest @ ..] => {
+            let archive = match rest.first() {
+                Some(path) => path.to_string(),
+                None => format!("enose-backup-{}.tar.gz", chrono::Local::now().format("%Y%m%d-%H%M%S")),
+            };
+            let count = backup::backup("config.toml", &archive)?;
+            println!("✅ Backed up {} files to {}", count, archive);
+            Ok(())
+        }
+        ["restore", archive, rest @ ..] => {
+            let force = rest.contains(&"--force");
+            let restored = backup::restore(archive, force)?;
+            for path in &restored {
+                println!("   {}", path);
+            }
+            println!("✅ Restored {} files from {}", restored.len(), archive);
+            Ok(())
+        }
+        _ => {
+            eprintln!("Usage:");
+            eprintln!("  enose                                   Run the backend server");
+            eprintln!("  enose import-reference <file.csv> [--offset <secs>] [--instrument <name>]");
+            eprintln!("  enose monitor [<host:port>]             Live terminal monitor (default 127.0.0.1:8082)");
+            eprintln!("  enose migrate [--status]                Apply (or list) local store migrations");
+            eprintln!("  enose golden [--regenerate]             Check (or rewrite) filter golden vectors in golden/");
+            eprintln!("  enose export <recording-dir> [<out.json>]  Frames of a recording (any version) as current-schema JSON");
+            eprintln!("  enose capture-export <file.ecap> [--format csv|ndjson|sensor] [<out>]  Decode a raw capture file");
+            eprintln!("  enose backup [<archive.tar.gz>]         Archive config, calibration, local store and logs");
+            eprintln!("  enose capture-debug-bundle [<out.zip>] [--offline]  Zip recent raw lines, logs, config, metrics");
+            eprintln!("  enose restore <archive.tar.gz> [--force]");
+            anyhow::bail!("unknown command: {}", args.join(" "))
+        }
+    }
+}
+
 // ================= Arduino Handler =================
+/// Per-connection processing state, built from the device's profile
+struct DevicePipeline {
+    device: String,
+    plugins: WasmPlugins,
+    filters: SensorFilters,
+    flow_monitor: FlowMonitor,
+    provenance: Provenance,
+    differential: Option<Differential>,
+    waveform: WaveformRecorder,
+    clock_drift: Option<ClockDrift>,
+    /// Frame-scoped derived metrics (rates, ratios, AQI, ...)
+    derived: DerivedMetrics,
+    /// Dead-band on GUI frames only
+    deadband: Deadband,
+    /// Ambient monitoring instead of measurement cycles, if enabled
+    ambient: Option<Ambient>,
+    location: Locator,
+    /// ADC rail detection, if enabled
+    saturation: Option<SaturationDetector>,
+    /// Gain range per channel from RANGE: lines
+    ranges: GainRanges,
+    /// Calibration curves, `<channel>_ppm` per frame
+    curves: BTreeMap<String, CalibrationCurve>,
+    /// Soft/hard channel limits, see [limits]
+    limits: Option<LimitMonitor>,
+    /// Auto-abort rules for a running cycle, see [cycle_abort]
+    cycle_abort: Option<CycleAbort>,
+    /// InfluxDB `source` tag: the announced device ID, else "arduino"
+    source: String,
+    /// Config version changes (RELOAD_CONFIG, CONFIG_SET, file edits)
+    reloads: tokio::sync::watch::Receiver<u64>,
+    /// Active model changes (MODEL_ACTIVATE, MODEL_ROLLBACK)
+    models: tokio::sync::watch::Receiver<Option<String>>,
+}
+
+impl DevicePipeline {
+    fn new(device: &str, config: &ConfigStore, models: &ModelRegistry, clock: SharedClock) -> Self {
+        let filters = create_filters(Some(device), clock.clone());
+        let mut provenance = Provenance::new(
+            filters.config_hash(),
+            config.current_version(),
+            &CalibrationConfig::load("config.toml", Some(device)),
+        );
+        provenance.model_version = models.active_id();
+        Self {
+            device: device.to_string(),
+            plugins: WasmPlugins::new(&PluginConfig::load("config.toml", Some(device))),
+            filters,
+            flow_monitor: FlowMonitor::new(&FlowConfig::load("config.toml", Some(device))),
+            provenance,
+            differential: Differential::new(Some(device), clock),
+            waveform: WaveformRecorder::default(),
+            clock_drift: ClockDrift::new(Some(device)),
+            derived: DerivedMetrics::load(&DerivedConfig::load("config.toml", Some(device)), Scope::Frame),
+            deadband: Deadband::new(DeadbandConfig::load("config.toml", Some(device))),
+            ambient: Ambient::new(AmbientConfig::load("config.toml", Some(device))),
+            location: Locator::new(LocationConfig::load("config.toml", Some(device))),
+            saturation: SaturationDetector::new(SaturationConfig::load("config.toml", Some(device))),
+            ranges: GainRanges::new(RangeConfig::load("config.toml", Some(device))),
+            curves: CalibrationConfig::load("config.toml", Some(device)).curves,
+            limits: LimitMonitor::new(LimitsConfig::load("config.toml", Some(device))),
+            cycle_abort: CycleAbort::new(CycleAbortConfig::load("config.toml", Some(device))),
+            source: "arduino".to_string(),
+            reloads: config.subscribe_reloads(),
+            models: models.subscribe(),
+        }
+    }
+
+    /// Follow the active registry model; returns true if it changed
+    fn reload_model(&mut self) -> bool {
+        if !self.models.has_changed().unwrap_or(false) {
+            return false;
+        }
+        self.provenance.model_version = self.models.borrow_and_update().clone();
+        true
+    }
+
+    /// Rebuild the filter chains if the config changed; returns true if so
+    fn reload_filters(&mut self) -> bool {
+        if !self.reloads.has_changed().unwrap_or(false) {
+            return false;
+        }
+        let version = *self.reloads.borrow_and_update();
+        let config = FilterConfig::load("config.toml", Some(&self.device));
+        self.filters.reload(&config);
+        if let Some(differential) = self.differential.as_mut() {
+            differential.reload_filters(&config);
+        }
+        self.derived = DerivedMetrics::load(&DerivedConfig::load("config.toml", Some(&self.device)), Scope::Frame);
+        self.deadband = Deadband::new(DeadbandConfig::load("config.toml", Some(&self.device)));
+        self.ambient = Ambient::reload(self.ambient.take(), AmbientConfig::load("config.toml", Some(&self.device)));
+        self.location.reload(LocationConfig::load("config.toml", Some(&self.device)));
+        self.saturation = SaturationDetector::new(SaturationConfig::load("config.toml", Some(&self.device)));
+        self.ranges.reload(RangeConfig::load("config.toml", Some(&self.device)));
+        self.curves = CalibrationConfig::load("config.toml", Some(&self.device)).curves;
+        self.limits = LimitMonitor::new(LimitsConfig::load("config.toml", Some(&self.device)));
+        self.cycle_abort = CycleAbort::new(CycleAbortConfig::load("config.toml", Some(&self.device)));
+        self.provenance.filter_hash = self.filters.config_hash().to_string();
+        self.provenance.config_version = version;
+        println!("🔄 {} filters reloaded (config version {})", self.device, version);
+        true
+    }
+}
+
+/// Same handling for TCP and serial links; `transport` is the peer address
+/// or serial port, for the device registry
 async fn handle_arduino(
-    stream: TcpStream,
-    data_tx: broadcast::Sender<String>,
+    stream: impl AsyncRead + AsyncWrite + Send + 'static,
+    transport: &str,
+    state: AppState,
     mut cmd_rx: broadcast::Receiver<String>,
-    filters: &mut SensorFilters,
-    influx: InfluxDBHandler,
+    pipeline: &mut DevicePipeline,
 ) {
     println!("🔧 Arduino handler started");
-    let (reader, mut writer) = stream.into_split();
+    let (reader, mut writer) = tokio::io::split(stream);
     let mut lines = BufReader::new(reader).lines();
+    let link = state.devices.connect(&pipeline.device, transport);
 
     println!("📡 Arduino handler waiting for commands and data...");
 
     // Spawn dedicated task untuk handle commands
+    let writer_link = link.clone();
     let write_handle = tokio::spawn(async move {
         while let Ok(command) = cmd_rx.recv().await {
+            // Command beralamat (DEV1:START) hanya untuk device itu, tanpa prefix
+            let Some(command) = writer_link.route(&command) else {
+                continue;
+            };
             println!("📤 Received command for Arduino: '{}'", command);
             
             let cmd_with_newline = format!("{}\n", command);
@@ -151,12 +1247,83 @@ async fn handle_arduino(
         println!("⚠️ Command handler exited");
     });
 
+    // Kirim setting heater PID yang dikelola backend
+    state.heater.push(&link.device());
+    announce_device(&state, &link, pipeline);
+    let mut refused_id: Option<String> = None;
+    let mut blocks = BlockParser::default();
+
     // Main loop hanya baca dari Arduino
     loop {
         match lines.next_line().await {
             Ok(Some(line)) => {
-                if line.starts_with("SENSOR:") {
-                    process_arduino_line(&line, &data_tx, filters, &influx).await;
+                state.debug.record_line(&pipeline.device, &line);
+                // Balasan firmware (bukan data) untuk POST /api/console
+                if ![SENSOR_PREFIX, BACKFILL_PREFIX, REFERENCE_PREFIX, ID_PREFIX, GPS_PREFIX, RANGE_PREFIX].iter().any(|p| line.starts_with(p)) {
+                    state.console.observe(&pipeline.device, &line);
+                    state.device_log.record(&pipeline.device, &line);
+                }
+                // Pesan multi-baris (BEGIN ... END) dirakit jadi satu event
+                if !line.starts_with(SENSOR_PREFIX) {
+                    match blocks.feed(&pipeline.device, &line, state.clock.now_millis()) {
+                        BlockLine::Pass => {}
+                        BlockLine::Consumed => continue,
+                        BlockLine::Complete(block) => {
+                            println!("📋 {} sent {} ({} lines)", block.device, block.name, block.lines.len());
+                            let _ = state.data_tx.send(block.event().to_string());
+                            continue;
+                        }
+                    }
+                }
+                if let Some(data) = line.strip_prefix(SENSOR_PREFIX) {
+                    let trace = state.latency.start();
+                    let (id, data) = devices::split_sensor_id(data);
+                    if let Some(id) = id {
+                        identify_device(id, &link, &state, pipeline, &mut refused_id);
+                    }
+                    if state.devices.require_id() && !link.identified() {
+                        if refused_id.is_none() {
+                            eprintln!("⚠️ Arduino {}: frames dropped until it sends ID:<device id>", pipeline.device);
+                            refused_id = Some(String::new());
+                        }
+                        continue;
+                    }
+                    process_arduino_line(data, &state, pipeline, trace).await;
+                    link.frame(state.clock.now_millis());
+                } else if let Some(id) = line.strip_prefix(ID_PREFIX) {
+                    identify_device(id.trim(), &link, &state, pipeline, &mut refused_id);
+                } else if let Some(list) = line.strip_prefix(CAPS_PREFIX) {
+                    link.report_commands(list);
+                } else if let Some(data) = line.strip_prefix(GPS_PREFIX) {
+                    pipeline.location.gps(&pipeline.device, data, state.clock.now_millis());
+                } else if let Some(data) = line.strip_prefix(RANGE_PREFIX) {
+                    report_ranges(&state, pipeline, data).await;
+                } else if let Some(data) = line.strip_prefix(BACKFILL_PREFIX) {
+                    if !state.boards.verified(&pipeline.device) {
+                        continue;
+                    }
+                    if let Some(point) = state.backfill.ingest(data, &pipeline.device, &pipeline.provenance, pipeline.clock_drift.as_ref()) {
+                        let _ = state.influx.send(point).await;
+                    }
+                } else if line.trim() == BACKFILL_END {
+                    let points = state.backfill.finish(&pipeline.device);
+                    println!("🗂️ Backfill from {} complete: {} points", pipeline.device, points);
+                    let event = serde_json::json!({ "type": "backfill", "device": pipeline.device, "points": points });
+                    let _ = state.data_tx.send(event.to_string());
+                } else if let Some(data) = line.strip_prefix(REFERENCE_PREFIX) {
+                    // Array referensi (chamber udara bersih) pada rig dual-chamber
+                    match pipeline.differential.as_mut() {
+                        Some(differential) => differential.ingest(data),
+                        None => println!("📝 Arduino: {}", line),
+                    }
+                } else if let Some(board) = line.strip_prefix(BOARD_PREFIX) {
+                    state.boards.identify(&pipeline.device, board);
+                    pipeline.provenance.board_id = Some(board.trim().to_string());
+                    publish_provenance(&state, &pipeline.device, &pipeline.provenance);
+                } else if let Some(version) = line.strip_prefix("FW:") {
+                    println!("📝 Arduino firmware: {}", version.trim());
+                    pipeline.provenance.firmware_version = Some(version.trim().to_string());
+                    publish_provenance(&state, &pipeline.device, &pipeline.provenance);
                 } else {
                     println!("📝 Arduino: {}", line);
                 }
@@ -173,142 +1340,701 @@ async fn handle_arduino(
     }
 
     write_handle.abort();
+    link.disconnect();
     println!("❌ Arduino handler exited");
 }
 
+/// Per-device requests sent when a device connects or identifies itself
+fn announce_device(state: &AppState, link: &DeviceLink, pipeline: &DevicePipeline) {
+    publish_provenance(state, &pipeline.device, &pipeline.provenance);
+
+    // Daftar command untuk panel kontrol GUI, dari profil atau ditanyakan ke firmware
+    if let Some(request) = link.configure_commands("config.toml") {
+        let _ = state.cmd_tx.send(format!("{}:{}", pipeline.device, request));
+    }
+
+    // Mode ambient: setting firmware (mis. sampling lebih lambat)
+    for command in pipeline.ambient.iter().flat_map(|ambient| ambient.commands()) {
+        let _ = state.cmd_tx.send(format!("{}:{}", pipeline.device, command));
+    }
+
+    // Minta serial board sensor, kalibrasi hanya berlaku untuk board yang sama
+    if let Some(request) = state.boards.on_connect(&pipeline.device) {
+        let _ = state.cmd_tx.send(format!("{}:{}", pipeline.device, request));
+    }
+
+    // Minta data yang terlewat selama backend mati dari SD card Arduino
+    if let Some(request) = state.backfill.on_connect(&pipeline.device, state.clock.now_millis()) {
+        let _ = state.cmd_tx.send(format!("{}:{}", pipeline.device, request));
+    }
+}
+
+/// Switch a connection to its announced ID. Profiles, filter state and
+/// board checks are per device, so the pipeline is rebuilt for the new ID.
+fn identify_device(
+    id: &str,
+    link: &DeviceLink,
+    state: &AppState,
+    pipeline: &mut DevicePipeline,
+    refused_id: &mut Option<String>,
+) {
+    if pipeline.device == id || refused_id.as_deref() == Some(id) {
+        return;
+    }
+    match link.identify(id) {
+        Ok(()) => {
+            println!("🪪 Arduino {} identified as {}", pipeline.device, id);
+            *pipeline = DevicePipeline::new(id, &state.config, &state.models, state.clock.clone());
+            pipeline.source = id.to_string();
+            *refused_id = None;
+            // Setting heater bisa berbeda per device
+            state.heater.push(id);
+            announce_device(state, link, pipeline);
+        }
+        Err(e) => {
+            eprintln!("⚠️ Arduino {}: {}", pipeline.device, e);
+            *refused_id = Some(id.to_string());
+        }
+    }
+}
+
+fn publish_provenance(state: &AppState, device: &str, provenance: &Provenance) {
+    state.debug.record_device(device, provenance);
+    let mut event = serde_json::to_value(provenance).unwrap_or_default();
+    event["type"] = "provenance".into();
+    event["device"] = device.into();
+    let _ = state.data_tx.send(event.to_string());
+}
+
+/// SENSOR field order: 7 channels, state, level, then optional rig inputs
+fn parse_sensor_values(values: &[f32]) -> Option<UnifiedSensorRaw> {
+    if values.len() < 9 {
+        return None;
+    }
+    Some(UnifiedSensorRaw {
+        channels: Channels::from_slice(values)?,
+        state: values[7] as i32,
+        level: values[8] as i32,
+        // Kanal opsional dari rig: flow rate, tekanan chamber, RH
+        flow: values.get(9).copied(),
+        pressure: values.get(10).copied(),
+        humidity: values.get(11).copied(),
+        heater_temp: values.get(12).copied(),
+    })
+}
+
+/// One SENSOR frame, `data_str` being the comma-separated values
 async fn process_arduino_line(
-    line: &str,
-    data_tx: &broadcast::Sender<String>,
-    filters: &mut SensorFilters,
-    influx: &InfluxDBHandler,
+    data_str: &str,
+    state: &AppState,
+    pipeline: &mut DevicePipeline,
+    mut trace: Option<FrameTrace>,
 ) {
-    let data_str = line.trim_start_matches("SENSOR:");
     let values: Vec<f32> = data_str
         .split(',')
         .filter_map(|s| s.parse::<f32>().ok())
         .collect();
 
-    if values.len() < 9 {
+    let Some(mut raw) = parse_sensor_values(&values) else {
         return;
+    };
+    // Rail detection needs the ADC values, before range scaling
+    let adc = raw.channels;
+    pipeline.ranges.apply(&mut raw.channels);
+    state.capture.record(&pipeline.device, state.clock.now_millis(), &values);
+    state.pre_trigger.record(&pipeline.device, state.clock.now_millis(), &values);
+    mark(&mut trace, Stage::Parse);
+
+    if pipeline.reload_filters() | pipeline.reload_model() {
+        publish_provenance(state, &pipeline.device, &pipeline.provenance);
     }
 
-    let raw = UnifiedSensorRaw {
-        no2: values[0],
-        eth: values[1],
-        voc: values[2],
-        co: values[3],
-        com: values[4],
-        ethm: values[5],
-        vocm: values[6],
-        state: values[7] as i32,
-        level: values[8] as i32,
-    };
+    pipeline.plugins.process(&mut raw);
+    state.channel_mask.apply(&mut raw);
+    state.modulation.apply(&mut pipeline.filters);
+    let filtered = pipeline.filters.update(&raw);
+    mark(&mut trace, Stage::Filter);
+    let mut timestamp = state.clock.now_millis();
+    // Sesi multi-hari: pakai jam device yang dikoreksi drift-nya
+    if let Some(drift) = pipeline.clock_drift.as_mut() {
+        if let Some(device_ms) = drift.device_time(data_str) {
+            if let Some(estimate) = drift.observe(device_ms, timestamp) {
+                clock_drift::publish(&pipeline.device, &estimate, &state.data_tx, &state.influx).await;
+            }
+            timestamp = drift.correct(device_ms).unwrap_or(timestamp);
+        }
+    }
+    pipeline.waveform.record(&state.waveforms, &pipeline.device, &pipeline.filters, &raw, timestamp);
+    if let Some(detector) = pipeline.saturation.as_mut() {
+        for event in detector.update(&pipeline.device, &adc, timestamp) {
+            publish_saturation(state, event).await;
+        }
+    }
+    state.backfill.seen(&pipeline.device, timestamp);
 
-    let filtered = filters.update(&raw);
+    if let Some(metrics) = pipeline.filters.take_metrics() {
+        let mut event = serde_json::to_value(&metrics).unwrap_or_default();
+        event["type"] = "filter_metrics".into();
+        let _ = state.data_tx.send(event.to_string());
+    }
+
+    for alarm in pipeline.flow_monitor.update(filtered.state, filtered.flow, filtered.pressure, timestamp) {
+        if alarm.active {
+            eprintln!("🚨 Flow alarm {:?}: {:.2}", alarm.alarm, alarm.value);
+            state.storage.trigger(timestamp);
+            let reason = format!("flow {:?}", alarm.alarm);
+            state.pre_trigger.trigger(&state.influx, &reason, Some(&pipeline.device), state.clock.now_millis()).await;
+        } else {
+            println!("✅ Flow alarm {:?} cleared", alarm.alarm);
+        }
+        let mut event = serde_json::to_value(&alarm).unwrap_or_default();
+        event["type"] = "alarm".into();
+        let _ = state.data_tx.send(event.to_string());
+    }
 
-    let payload = UnifiedSensorData {
-        no2: filtered.no2,
-        eth: filtered.eth,
-        voc: filtered.voc,
-        co: filtered.co,
-        com: filtered.com,
-        ethm: filtered.ethm,
-        vocm: filtered.vocm,
+    let (target_ppm, delivered_ppm) = state.dilution.concentration();
+
+    let mut payload = UnifiedSensorData {
+        channels: filtered.channels,
         state: filtered.state,
         state_name: state_to_name(filtered.state),
         level: filtered.level,
-        timestamp: Utc::now().timestamp_millis(),
-        source: "arduino".to_string(),
+        flow: filtered.flow,
+        pressure: filtered.pressure,
+        humidity: filtered.humidity,
+        heater_temp: filtered.heater_temp,
+        heater_setpoint: filtered.heater_temp.and(state.heater.setpoint(&pipeline.device)),
+        target_ppm,
+        delivered_ppm,
+        cycle_valid: pipeline.flow_monitor.cycle_valid(),
+        timestamp,
+        source: pipeline.source.clone(),
+        device: pipeline.device.clone(),
+        session: String::new(),
+        reference: None,
+        unverified: !state.boards.verified(&pipeline.device),
+        skip_storage: false,
+        location: pipeline.location.locate(state.clock.now_millis()),
+        raw: pipeline.filters.raw_channels(&raw),
+        modulation: filtered.modulation,
+        saturated: pipeline.saturation.as_ref().map(|s| s.saturated()).unwrap_or_default(),
+        ranges: pipeline.ranges.codes(),
+        virtual_channels: filtered.virtual_channels,
     };
+    if let Some(differential) = &pipeline.differential {
+        differential.apply(&mut payload);
+    }
+    // Metrik turunan ikut dikirim seperti virtual channel
+    for (name, value) in pipeline.derived.frame(&payload) {
+        payload.virtual_channels.insert(name, value as f32);
+    }
+    for (name, value) in calibration::concentrations(&pipeline.curves, &payload) {
+        payload.virtual_channels.insert(name, value as f32);
+    }
+    if let Some(monitor) = pipeline.limits.as_mut() {
+        for event in monitor.update(&payload) {
+            publish_limit(state, event).await;
+        }
+    }
+    if let Some(abort) = pipeline.cycle_abort.as_mut() {
+        match abort.update(&payload, pipeline.flow_monitor.active()) {
+            Some(event) if event.active => abort_cycle(state, event),
+            Some(event) => {
+                println!("✅ {} next cycle started after abort ({})", event.device, event.rule);
+                state.alerts.raise(&event.alert_event());
+            }
+            None => {}
+        }
+        payload.cycle_valid &= abort.cycle_valid();
+    }
+    if let Some(ambient) = pipeline.ambient.as_mut() {
+        if let Some(event) = ambient.update(&mut payload, &state.weather) {
+            let mut json = serde_json::to_value(&event).unwrap_or_default();
+            json["type"] = "ambient_event".into();
+            let _ = state.data_tx.send(json.to_string());
+            if let Err(e) = state.influx.write_points(event.point().into_iter().collect()).await {
+                eprintln!("❌ Ambient event write error: {:?}", e);
+            }
+        }
+    }
+    mark(&mut trace, Stage::Process);
+
+    publish_frame(state, payload, &pipeline.provenance, Some(&mut pipeline.deadband), trace).await;
+
+    // Siklus yang terkena alarm flow dicatat sekali per siklus (setelah frame
+    // masuk sesi, agar nomor siklusnya sudah yang baru)
+    if let Some(alarm) = pipeline.flow_monitor.take_invalidation() {
+        let reason = format!("flow {:?}", alarm);
+        if let Some((session, cycle)) = state.sessions.invalidate_cycle(&pipeline.device, &reason, timestamp) {
+            eprintln!("⚠️ {} cycle {} of session {} invalid: {}", pipeline.device, cycle, session, reason);
+        }
+    }
+}
+
+/// A RANGE: line: keep the codes for the next frames, record each switch
+async fn report_ranges(state: &AppState, pipeline: &mut DevicePipeline, data: &str) {
+    let timestamp = state.clock.now_millis();
+    let changes = pipeline.ranges.report(&pipeline.device, data);
+    if changes.is_empty() {
+        return;
+    }
+    let mut points = Vec::new();
+    for (channel, previous, code) in changes {
+        let show = |code: Option<u8>| code.map_or("-".to_string(), |c| c.to_string());
+        println!("🎚️ {} {} range {} → {}", pipeline.device, channel.name(), show(previous), show(code));
+        points.extend(code.and_then(|code| ranges::change_point(&pipeline.device, channel, code, timestamp)));
+    }
+    let event = serde_json::json!({ "type": "ranges", "device": pipeline.device, "ranges": pipeline.ranges.codes() });
+    let _ = state.data_tx.send(event.to_string());
+    if let Err(e) = state.influx.write_points(points).await {
+        eprintln!("❌ Range change write error: {:?}", e);
+    }
+}
+
+/// Report a channel entering or leaving saturation; on entry, send the
+/// range switch command if the device supports it and record the change
+async fn publish_saturation(state: &AppState, mut event: saturation::SaturationEvent) {
+    if !event.active {
+        println!("✅ {} {} back within ADC range", event.device, event.channel);
+    } else {
+        eprintln!("📈 {} {} saturated at the {:?} rail: {}", event.device, event.channel, event.rail, event.hint);
+        if let Some(command) = event.command.as_deref() {
+            if state.devices.supports(&event.device, command) {
+                let _ = state.cmd_tx.send(format!("{}:{}", event.device, command));
+                event.sent = true;
+                println!("🎚️ {} range change: {}", event.device, command);
+            }
+        }
+    }
+    let mut json = serde_json::to_value(&event).unwrap_or_default();
+    json["type"] = "saturation".into();
+    let _ = state.data_tx.send(json.to_string());
+    if let Some(point) = event.range_point() {
+        if let Err(e) = state.influx.write_points(vec![point]).await {
+            eprintln!("❌ Range change write error: {:?}", e);
+        }
+    }
+}
+
+/// Report a channel changing limit tier; entering the hard tier sends the
+/// protective command sequence to the device and records it in the audit log
+async fn publish_limit(state: &AppState, event: limits::LimitEvent) {
+    match event.tier {
+        limits::Tier::Normal => println!("✅ {} {} back within limits ({:.2})", event.device, event.channel, event.value),
+        tier => eprintln!(
+            "🚨 {} {} {:?} limit {:.2} crossed: {:.2}",
+            event.device,
+            event.channel,
+            tier,
+            event.limit.unwrap_or_default(),
+            event.value
+        ),
+    }
+    if !event.actions.is_empty() {
+        for command in &event.actions {
+            let _ = state.cmd_tx.send(format!("{}:{}", event.device, command));
+        }
+        println!("🛡️ {} protective action: {}", event.device, event.actions.join(", "));
+        state.audit.record(
+            "limits",
+            "protective_action",
+            serde_json::json!({
+                "device": event.device,
+                "channel": event.channel,
+                "value": event.value,
+                "limit": event.limit,
+                "commands": event.actions,
+            }),
+        );
+        state.storage.trigger(event.timestamp);
+        let reason = format!("hard limit {}", event.channel);
+        state.pre_trigger.trigger(&state.influx, &reason, Some(&event.device), state.clock.now_millis()).await;
+    }
+    let mut json = serde_json::to_value(&event).unwrap_or_default();
+    json["type"] = "limit".into();
+    let _ = state.data_tx.send(json.to_string());
+    state.alerts.raise(&event.alert_event());
+}
+
+/// Stop a cycle an abort rule matched: cancel the queued experiment running
+/// on the device (its abort path) or send the abort command, mark the
+/// session's cycle invalid and notify the operator
+fn abort_cycle(state: &AppState, mut event: cycle_abort::CycleAbortEvent) {
+    event.experiment = state.queue.abort_device(&event.device);
+    if event.experiment.is_none() {
+        let _ = state.cmd_tx.send(format!("{}:{}", event.device, event.command));
+    }
+    let reason = format!("{}: {}", event.rule, event.reason);
+    if let Some((session, cycle)) = state.sessions.invalidate_cycle(&event.device, &reason, event.timestamp) {
+        event.session = Some(session);
+        event.cycle = Some(cycle);
+    }
+    eprintln!(
+        "⛔ {} cycle aborted in {} by rule '{}': {}",
+        event.device,
+        state_to_name(event.state),
+        event.rule,
+        event.reason
+    );
+    let mut json = serde_json::to_value(&event).unwrap_or_default();
+    json["type"] = "cycle_abort".into();
+    let _ = state.data_tx.send(json.to_string());
+    state.alerts.raise(&event.alert_event());
+}
+
+fn mark(trace: &mut Option<FrameTrace>, stage: Stage) {
+    if let Some(trace) = trace.as_mut() {
+        trace.mark(stage);
+    }
+}
+
+/// Fan a processed frame out to the GUI, internal subsystems and InfluxDB;
+/// `deadband` (if any) only thins out what the GUI sees
+async fn publish_frame(
+    state: &AppState,
+    payload: UnifiedSensorData,
+    provenance: &Provenance,
+    deadband: Option<&mut Deadband>,
+    mut trace: Option<FrameTrace>,
+) {
+    fan_out(state, payload, provenance, deadband, &mut trace).await;
+    if let Some(trace) = trace {
+        state.latency.finish(trace);
+    }
+}
 
-    // Kirim JSON ke GUI
-    if let Ok(json) = serde_json::to_string(&payload) {
-        let _ = data_tx.send(json);
+async fn fan_out(
+    state: &AppState,
+    mut payload: UnifiedSensorData,
+    provenance: &Provenance,
+    deadband: Option<&mut Deadband>,
+    trace: &mut Option<FrameTrace>,
+) {
+    // Sesi yang sudah dikunci tidak boleh kemasukan data baru
+    if let Err(e) = state.sessions.observe(&mut payload) {
+        eprintln!("🔒 Frame from {} refused: {}", payload.device, e);
+        return;
+    }
+
+    // Kirim JSON ke GUI (dead-band hanya menahan tampilan, bukan data)
+    let json = match deadband {
+        Some(deadband) => deadband.publish(&payload),
+        None => serde_json::to_string(&payload).ok(),
+    };
+    if let Some(json) = json {
+        let _ = state.data_tx.send(json);
     }
+    // Board belum dikonfirmasi: tampil di GUI saja, tidak disimpan/dipakai subsistem
+    if payload.unverified {
+        return;
+    }
+    let _ = state.frame_tx.send(payload.clone());
+    state.uplink.forward(&payload, provenance);
+    mark(trace, Stage::Broadcast);
 
     // Kirim ke InfluxDB
-    let _ = influx
-        .send(InfluxData {
-            no2: payload.no2,
-            eth: payload.eth,
-            voc: payload.voc,
-            co: payload.co,
-            com: payload.com,
-            ethm: payload.ethm,
-            vocm: payload.vocm,
-            state: payload.state,
-            level: payload.level,
-            timestamp: payload.timestamp * 1_000_000,
-            source: payload.source.clone(),
-        })
-        .await;
+    let data = influx_data(&payload, provenance);
+    if !payload.skip_storage {
+        for data in state.storage.admit(&payload, data) {
+            let _ = state.influx.send(data).await;
+        }
+    }
+    mark(trace, Stage::Storage);
+}
+
+/// Processed frame → InfluxDB point data
+fn influx_data(payload: &UnifiedSensorData, provenance: &Provenance) -> InfluxData {
+    InfluxData {
+        channels: payload.channels,
+        state: payload.state,
+        level: payload.level,
+        flow: payload.flow,
+        pressure: payload.pressure,
+        humidity: payload.humidity,
+        heater_temp: payload.heater_temp,
+        heater_setpoint: payload.heater_setpoint,
+        target_ppm: payload.target_ppm,
+        delivered_ppm: payload.delivered_ppm,
+        cycle_valid: payload.cycle_valid,
+        timestamp: payload.timestamp * 1_000_000,
+        source: payload.source.clone(),
+        device: payload.device.clone(),
+        session: (!payload.session.is_empty()).then(|| payload.session.clone()),
+        reference: payload.reference,
+        raw: payload.raw,
+        location: payload.location.clone(),
+        modulation: payload.modulation.clone(),
+        saturated: payload.saturated.clone(),
+        ranges: payload.ranges.clone(),
+        virtual_channels: payload.virtual_channels.clone(),
+        provenance: provenance.clone(),
+    }
+}
+
+// ================= Backend Commands =================
+/// Ok(Some(json)) is sent back to the issuing client only
+type CommandResult = Result<Option<String>, String>;
+
+/// Handle commands addressed to the backend itself.
+/// Returns None if the command should be forwarded to the Arduino.
+async fn handle_backend_command(cmd: &str, client: &str, state: &AppState) -> Option<CommandResult> {
+    let args: Vec<&str> = cmd.split_whitespace().collect();
+    let result = match args.first().copied() {
+        Some(c) if c.starts_with("QUEUE_") => experiments::handle_command(&state.queue, &args).map(|_| None),
+        Some("LEAK_TEST") => state.leak_test.start(args.get(1).copied()).map(|_| None),
+        Some(c) if c.starts_with("BURN_IN_") => burn_in::handle_command(&state.burn_in, client, &args).map(|_| None),
+        Some(c) if c == "NOISE_FLOOR" || c.starts_with("NOISE_FLOOR_") => {
+            noise_floor::handle_command(&state.noise_floor, client, &args).map(|_| None)
+        }
+        Some(c) if c.starts_with("HEATER_") => heater::handle_command(&state.heater, &args).map(|_| None),
+        Some("DILUTE") | Some("DILUTION_STATUS") => mfc::handle_command(&state.dilution, &args).await.map(|_| None),
+        Some(c) if c.starts_with("CONFIG_") || c == "RELOAD_CONFIG" => config::handle_command(&state.config, client, &args),
+        Some("DEVICE_LOG") => device_log::handle_command(&state.device_log, &args),
+        Some("DEVICES") => Ok(Some(
+            serde_json::json!({ "type": "devices", "devices": state.devices.snapshot() }).to_string(),
+        )),
+        Some("CLIENTS") => Ok(Some(
+            serde_json::json!({ "type": "clients", "clients": state.clients.snapshot() }).to_string(),
+        )),
+        Some("BACKFILL") => backfill::parse_range(&args).and_then(|(from, to, device)| {
+            let device = state.devices.resolve(device)?;
+            let _ = state.cmd_tx.send(format!("{}:{}", device, state.backfill.request(from, to)));
+            Ok(None)
+        }),
+        Some("ACCURACY") => accuracy::handle_command(&state.accuracy, &args),
+        Some("USAGE") => usage::handle_command(&state.usage, &args),
+        Some(c) if c == "MODELS" || c.starts_with("MODEL_") => models::handle_command(&state.models, client, &args),
+        Some(c) if c == "JOBS" || c.starts_with("JOB_") || c.starts_with("REPLAY") => jobs::handle_command(&state.jobs, client, &args),
+        Some("INFLUX_STATUS") => {
+            let mut event = serde_json::to_value(state.influx.stats()).unwrap_or_default();
+            event["type"] = "influx_status".into();
+            Ok(Some(event.to_string()))
+        }
+        Some(c) if c.starts_with("LATENCY") => latency::handle_command(&state.latency, &args),
+        Some(c) if c.starts_with("REC_") => recording::handle_command(&state.recorder, client, &args).map(|_| None),
+        Some(c) if c == "CAMPAIGNS" || c.starts_with("CAMPAIGN_") => campaign::handle_command(&state.campaigns, client, &args),
+        Some(c) if c.starts_with("CONTROL_") => control_lock::handle_command(&state.control, client, &args).map(|_| None),
+        Some(c) if c.starts_with("BOARD_") => board::handle_command(&state.boards, &state.audit, client, &args).map(|_| None),
+        Some(c) if c.starts_with("CHANNEL_") => {
+            channel_mask::handle_command(&state.channel_mask, &state.audit, client, &args).map(|_| None)
+        }
+        Some(c) if c.starts_with("MODULATION_") => {
+            modulation::handle_command(&state.modulation, &state.audit, client, &args).map(|_| None)
+        }
+        Some("CAPTURE_DEBUG_BUNDLE") => debug_bundle::write("config.toml", state)
+            .map(|path| Some(serde_json::json!({ "type": "debug_bundle", "path": path }).to_string()))
+            .map_err(|e| e.to_string()),
+        Some("HISTOGRAM" | "SPECTROGRAM") => analytics::handle_command(&state.history, &args),
+        Some("CHANNELS") => Ok(Some(channels::metadata_message("config.toml", args.get(1).copied()))),
+        Some("SESSION_LOCK") => match args.get(1) {
+            Some(id) => state.sessions.lock(id, client).map(|_| {
+                state.audit.record(client, "session_lock", serde_json::json!({ "session": id }));
+                None
+            }),
+            None => Err("usage: SESSION_LOCK <session_id>".to_string()),
+        },
+        Some(c) if c == "SESSIONS" || c.starts_with("SESSION_") => {
+            sessions::handle_command(&state.sessions, &state.influx, &state.audit, client, &args).await
+        }
+        Some(c) if c.starts_with("ALERT_") => alerts::handle_command(&state.alerts, &state.audit, client, &args).map(|_| None),
+        Some("AUTH") => auth::handle_command(&state.auth, client, &args),
+        Some("SHIFT_NOTE" | "SHIFT_LOG" | "HANDOVER") => {
+            shift_log::handle_command(&state.shift_log, &state.alerts, &state.queue, client, &args).await
+        }
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Outcome of one GUI command
+enum GuiReply {
+    /// Handled by the backend, with an optional reply line
+    Backend(Option<String>),
+    /// Forwarded to the Arduino, with the id its reply lines are tagged with
+    Forwarded(Option<u64>),
+}
+
+/// Viewer, permission-group and control-lock rules, the same for every GUI
+/// transport. `identity` is who the client authenticated as, if anyone.
+fn gui_permission(cmd: &str, client: &str, identity: Option<&Identity>, state: &AppState, read_only: bool) -> Result<(), String> {
+    // Viewer hanya menonton, semua command ditolak (juga tidak diteruskan ke Arduino)
+    if read_only {
+        return Err("read-only viewer: commands are disabled".to_string());
+    }
+    state.auth.permit(identity, cmd)?;
+    if state.auth.required(cmd).is_none() {
+        return Ok(());
+    }
+    state.control.check(client, cmd)
+}
+
+/// Run one GUI command: backend commands first, everything else goes to the
+/// Arduino. Device replies to a forwarded command are sent to `replies`.
+async fn gui_command(
+    cmd: &str,
+    client: &str,
+    state: &AppState,
+    replies: Option<&mpsc::UnboundedSender<String>>,
+) -> std::result::Result<GuiReply, String> {
+    let operator = state.auth.identity(client).map_or_else(|| usage::operator(client), |identity| identity.name);
+    state.usage.command(&operator);
+    if let Some(result) = handle_backend_command(cmd, client, state).await {
+        return result.map(GuiReply::Backend);
+    }
+    // Command beralamat ke device yang tidak terhubung: tolak, jangan broadcast
+    if let Some((device, _)) = state.devices.addressed(cmd) {
+        if !state.devices.connected(&device) {
+            return Err(format!("device '{}' is not connected", device));
+        }
+    }
+    let id = replies.and_then(|replies| state.console.track(cmd, replies.clone()));
+
+    println!("📊 Broadcasting to {} receivers", state.cmd_tx.receiver_count());
+
+    // Forward command ke Arduino
+    match state.cmd_tx.send(cmd.to_string()) {
+        Ok(count) => println!("✅ Command broadcasted to {} receivers", count),
+        Err(e) => eprintln!("❌ Failed to broadcast command: {}", e),
+    }
+    Ok(GuiReply::Forwarded(id))
+}
+
+/// "command_sent" reply: the id the command's device replies will carry
+fn command_sent(cmd: &str, id: Option<u64>) -> Option<String> {
+    id.map(|id| serde_json::json!({ "type": "command_sent", "id": id, "command": cmd }).to_string())
 }
 
 // ================= GUI Server =================
-async fn gui_server(
-    data_tx: broadcast::Sender<String>,
-    cmd_tx: broadcast::Sender<String>,
-) -> Result<()> {
-    let listener = TcpListener::bind("0.0.0.0:8082").await?;
-    println!("📡 GUI server listening on 0.0.0.0:8082");
-    println!("📊 Command channel receiver count: {}", cmd_tx.receiver_count());
+async fn gui_server(config: GuiConfig, state: AppState) -> Result<()> {
+    let listener = state.diagnostics.bind("gui", &config.tcp_bind).await?;
+    println!("📡 GUI server listening on {}", config.tcp_bind);
+    println!("📊 Command channel receiver count: {}", state.cmd_tx.receiver_count());
 
     loop {
         let (socket, addr) = listener.accept().await?;
-        let mut data_rx = data_tx.subscribe();
-        let cmd_tx_clone = cmd_tx.clone();
-        println!("✅ GUI connected: {}", addr);
-        println!("📊 Active receivers: {}", cmd_tx.receiver_count());
+        // Display publik yang terdaftar tetap read-only walau lewat port utama
+        let read_only = ViewerConfig::load("config.toml").applies_to(&addr);
+        tokio::spawn(handle_gui_client(socket, addr, state.clone(), read_only));
+    }
+}
 
-        tokio::spawn(async move {
-            let (reader, mut writer) = socket.into_split();
-            let mut lines = BufReader::new(reader).lines();
-
-            loop {
-                tokio::select! {
-                    // Kirim data sensor ke GUI
-                    Ok(msg) = data_rx.recv() => {
-                        let data_with_newline = format!("{}\n", msg);
-                        if writer.write_all(data_with_newline.as_bytes()).await.is_err() {
-                            println!("❌ Failed to write to GUI");
-                            break;
-                        }
-                        if writer.flush().await.is_err() {
-                            println!("❌ Failed to flush to GUI");
-                            break;
-                        }
+/// Serve one GUI connection: data stream out, commands in (rejected when read-only)
+async fn handle_gui_client(socket: TcpStream, addr: SocketAddr, state: AppState, read_only: bool) {
+    let mut data_rx = state.data_tx.subscribe();
+    println!("✅ GUI connected: {}{}", addr, if read_only { " (read-only viewer)" } else { "" });
+    println!("📊 Active receivers: {}", state.cmd_tx.receiver_count());
+
+    // Klien demo hanya menerima nilai ternormalisasi
+    let privacy = PrivacyConfig::load("config.toml");
+    let mut redactor = privacy.applies_to(&addr).then(|| Redactor::new(&privacy));
+    if redactor.is_some() {
+        println!("🕶️ GUI {} is in privacy mode", addr);
+    }
+    let client = state.clients.register(addr, redactor.is_some(), read_only);
+
+    let (reader, mut writer) = socket.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<String>();
+
+    // Snapshot metadata dulu supaya GUI bisa menyiapkan axis/gauge
+    let mut metadata = channels::metadata_message("config.toml", None);
+    if let Some(redacted) = redactor.as_mut().and_then(|r| r.redact(&metadata)) {
+        metadata = redacted;
+    }
+    let metadata = format!("{}\n", metadata);
+    if writer.write_all(metadata.as_bytes()).await.is_err() {
+        println!("❌ Failed to write to GUI");
+        return;
+    }
+    client.sent(metadata.len());
+
+    loop {
+        tokio::select! {
+            // Kirim data sensor ke GUI
+            msg = data_rx.recv() => {
+                let msg = match msg {
+                    Ok(msg) => msg,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        client.dropped(n);
+                        continue;
                     }
-                    
-                    // Terima command dari GUI
-                    result = lines.next_line() => {
-                        match result {
-                            Ok(Some(cmd)) => {
-                                let cmd = cmd.trim().to_string();
-                                if !cmd.is_empty() {
-                                    println!("📥 GUI command received: '{}'", cmd);
-                                    println!("📊 Broadcasting to {} receivers", cmd_tx_clone.receiver_count());
-                                    
-                                    // Forward command ke Arduino
-                                    match cmd_tx_clone.send(cmd.clone()) {
-                                        Ok(count) => println!("✅ Command broadcasted to {} receivers", count),
-                                        Err(e) => eprintln!("❌ Failed to broadcast command: {}", e),
-                                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let msg = match redactor.as_mut() {
+                    Some(redactor) => match redactor.redact(&msg) {
+                        Some(msg) => msg,
+                        None => continue,
+                    },
+                    None => msg,
+                };
+                let data_with_newline = format!("{}\n", msg);
+                if writer.write_all(data_with_newline.as_bytes()).await.is_err() {
+                    println!("❌ Failed to write to GUI");
+                    break;
+                }
+                if writer.flush().await.is_err() {
+                    println!("❌ Failed to flush to GUI");
+                    break;
+                }
+                client.sent(data_with_newline.len());
+            }
+            
+            // Balasan Arduino untuk command dari klien ini
+            Some(reply) = reply_rx.recv() => {
+                let reply = format!("{}\n", reply);
+                if writer.write_all(reply.as_bytes()).await.is_err() {
+                    break;
+                }
+                client.sent(reply.len());
+            }
+
+            // Terima command dari GUI
+            result = lines.next_line() => {
+                match result {
+                    Ok(Some(cmd)) => {
+                        client.received(cmd.len() + 1);
+                        let cmd = cmd.trim().to_string();
+                        if !cmd.is_empty() {
+                            let shown = auth::redact(&cmd);
+                            println!("📥 GUI command received: '{}'", shown);
+
+                            let client_id = addr.to_string();
+                            let identity = state.auth.identity(&client_id);
+                            let result = match gui_permission(&cmd, &client_id, identity.as_ref(), &state, read_only) {
+                                Ok(()) => gui_command(&cmd, &client_id, &state, Some(&reply_tx)).await,
+                                Err(e) => Err(e),
+                            };
+                            let reply = match result {
+                                Ok(GuiReply::Backend(reply)) => reply,
+                                Ok(GuiReply::Forwarded(id)) => command_sent(&cmd, id),
+                                Err(e) => {
+                                    eprintln!("❌ Command '{}' failed: {}", shown, e);
+                                    Some(serde_json::json!({ "type": "command_error", "command": shown, "error": e }).to_string())
                                 }
-                            }
-                            Ok(None) => {
-                                println!("❌ GUI disconnected (EOF)");
-                                break;
-                            }
-                            Err(e) => {
-                                eprintln!("❌ GUI read error: {}", e);
-                                break;
+                            };
+                            if let Some(reply) = reply {
+                                let reply = format!("{}\n", reply);
+                                if writer.write_all(reply.as_bytes()).await.is_err() {
+                                    break;
+                                }
+                                client.sent(reply.len());
                             }
                         }
                     }
+                    Ok(None) => {
+                        println!("❌ GUI disconnected (EOF)");
+                        break;
+                    }
+                    Err(e) => {
+                        eprintln!("❌ GUI read error: {}", e);
+                        break;
+                    }
                 }
             }
-            
-            println!("❌ GUI handler exited: {}", addr);
-        });
+        }
     }
+    
+    state.control.release(&addr.to_string(), "disconnected");
+    state.auth.logout(&addr.to_string());
+    println!("❌ GUI handler exited: {}", addr);
 }
\ No newline at end of file
@@ -0,0 +1,387 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::broadcast;
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+
+const IO_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MfcProtocol {
+    /// Line-based ASCII protocol (Alicat style)
+    Ascii,
+    /// Modbus RTU holding registers
+    Modbus,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MfcConfig {
+    pub port: String,
+    #[serde(default = "default_baud")]
+    pub baud: u32,
+    pub protocol: MfcProtocol,
+    /// ASCII unit ID ("A") or Modbus slave address ("1")
+    #[serde(default = "default_address")]
+    pub address: String,
+    /// Full-scale flow in sccm
+    pub full_scale_sccm: f32,
+    // --- ASCII ---
+    #[serde(default = "default_set_template")]
+    pub set_template: String,
+    /// Whitespace-separated field holding mass flow in the poll reply
+    #[serde(default = "default_read_field")]
+    pub read_field: usize,
+    // --- Modbus ---
+    #[serde(default)]
+    pub setpoint_register: u16,
+    #[serde(default = "default_flow_register")]
+    pub flow_register: u16,
+    /// Register counts per sccm
+    #[serde(default = "default_register_scale")]
+    pub register_scale: f32,
+}
+
+fn default_baud() -> u32 { 19200 }
+fn default_address() -> String { "A".to_string() }
+fn default_set_template() -> String { "{addr}S{value}".to_string() }
+fn default_read_field() -> usize { 4 }
+fn default_flow_register() -> u16 { 1 }
+fn default_register_scale() -> f32 { 10.0 }
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct DilutionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Analyte concentration in the source cylinder
+    #[serde(default)]
+    pub source_ppm: f32,
+    /// Total flow delivered to the chamber
+    #[serde(default)]
+    pub total_flow_sccm: f32,
+    #[serde(default = "default_poll_secs")]
+    pub poll_secs: u64,
+    pub analyte: Option<MfcConfig>,
+    pub diluent: Option<MfcConfig>,
+}
+
+fn default_poll_secs() -> u64 { 2 }
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    dilution: DilutionConfig,
+}
+
+impl DilutionConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().dilution
+    }
+}
+
+// ================= MFC Driver =================
+struct Mfc {
+    config: MfcConfig,
+    port: tokio::sync::Mutex<Option<SerialStream>>,
+}
+
+impl Mfc {
+    fn new(config: MfcConfig) -> Self {
+        Self { config, port: tokio::sync::Mutex::new(None) }
+    }
+
+    /// Kirim request dan baca reply; port dibuka ulang jika sebelumnya gagal
+    async fn transact(&self, request: &[u8], reply_len: Option<usize>) -> Result<Vec<u8>, String> {
+        let mut guard = self.port.lock().await;
+        if guard.is_none() {
+            let port = tokio_serial::new(&self.config.port, self.config.baud)
+                .open_native_async()
+                .map_err(|e| format!("{}: {}", self.config.port, e))?;
+            *guard = Some(port);
+        }
+        let port = guard.as_mut().unwrap();
+
+        let result = tokio::time::timeout(IO_TIMEOUT, async {
+            port.write_all(request).await?;
+            let mut reply = Vec::new();
+            let mut buf = [0u8; 64];
+            loop {
+                let n = port.read(&mut buf).await?;
+                if n == 0 {
+                    break;
+                }
+                reply.extend_from_slice(&buf[..n]);
+                let done = match reply_len {
+                    Some(len) => reply.len() >= len,
+                    None => reply.ends_with(b"\r"),
+                };
+                if done {
+                    break;
+                }
+            }
+            Ok::<_, std::io::Error>(reply)
+        })
+        .await;
+
+        match result {
+            Ok(Ok(reply)) => Ok(reply),
+            Ok(Err(e)) => {
+                *guard = None;
+                Err(format!("{}: {}", self.config.port, e))
+            }
+            Err(_) => Err(format!("{}: timeout", self.config.port)),
+        }
+    }
+
+    async fn set_flow(&self, sccm: f32) -> Result<(), String> {
+        let sccm = sccm.clamp(0.0, self.config.full_scale_sccm);
+        match self.config.protocol {
+            MfcProtocol::Ascii => {
+                let line = self
+                    .config
+                    .set_template
+                    .replace("{addr}", &self.config.address)
+                    .replace("{value}", &format!("{:.3}", sccm));
+                self.transact(format!("{}\r", line).as_bytes(), None).await?;
+            }
+            MfcProtocol::Modbus => {
+                let value = (sccm * self.config.register_scale).round() as u16;
+                let frame = modbus_frame(self.slave()?, 0x06, self.config.setpoint_register, value);
+                let reply = self.transact(&frame, Some(8)).await?;
+                check_modbus(&reply)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn read_flow(&self) -> Result<f32, String> {
+        match self.config.protocol {
+            MfcProtocol::Ascii => {
+                let reply = self.transact(format!("{}\r", self.config.address).as_bytes(), None).await?;
+                let text = String::from_utf8_lossy(&reply);
+                text.split_whitespace()
+                    .nth(self.config.read_field)
+                    .and_then(|f| f.parse::<f32>().ok())
+                    .ok_or_else(|| format!("unexpected reply '{}'", text.trim()))
+            }
+            MfcProtocol::Modbus => {
+                let frame = modbus_frame(self.slave()?, 0x03, self.config.flow_register, 1);
+                let reply = self.transact(&frame, Some(7)).await?;
+                check_modbus(&reply)?;
+                let raw = u16::from_be_bytes([reply[3], reply[4]]);
+                Ok(raw as f32 / self.config.register_scale)
+            }
+        }
+    }
+
+    fn slave(&self) -> Result<u8, String> {
+        self.config
+            .address
+            .parse()
+            .map_err(|_| format!("invalid modbus address '{}'", self.config.address))
+    }
+}
+
+/// Modbus RTU request: slave, function, register, value/count, CRC16
+fn modbus_frame(slave: u8, function: u8, register: u16, value: u16) -> Vec<u8> {
+    let mut frame = vec![slave, function];
+    frame.extend_from_slice(&register.to_be_bytes());
+    frame.extend_from_slice(&value.to_be_bytes());
+    let crc = crc16(&frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame
+}
+
+fn check_modbus(reply: &[u8]) -> Result<(), String> {
+    if reply.len() < 5 {
+        return Err("short modbus reply".to_string());
+    }
+    let (body, crc) = reply.split_at(reply.len() - 2);
+    if crc16(body).to_le_bytes() != [crc[0], crc[1]] {
+        return Err("modbus CRC mismatch".to_string());
+    }
+    if body[1] & 0x80 != 0 {
+        return Err(format!("modbus exception {}", body[2]));
+    }
+    Ok(())
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for byte in data {
+        crc ^= *byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+#[derive(Debug, Serialize, Clone, Copy, Default)]
+struct DilutionStatus {
+    target_ppm: Option<f32>,
+    delivered_ppm: Option<f32>,
+    analyte_sccm: Option<f32>,
+    diluent_sccm: Option<f32>,
+}
+
+// ================= Dilution Controller =================
+/// Two-MFC dilution: analyte from a source cylinder mixed with zero air
+#[derive(Clone)]
+pub struct Dilution {
+    config: Arc<DilutionConfig>,
+    analyte: Option<Arc<Mfc>>,
+    diluent: Option<Arc<Mfc>>,
+    status: Arc<Mutex<DilutionStatus>>,
+    data_tx: broadcast::Sender<String>,
+}
+
+impl Dilution {
+    pub fn new(config: DilutionConfig, data_tx: broadcast::Sender<String>) -> Self {
+        let enabled = config.enabled;
+        let analyte = config.analyte.clone().filter(|_| enabled).map(|c| Arc::new(Mfc::new(c)));
+        let diluent = config.diluent.clone().filter(|_| enabled).map(|c| Arc::new(Mfc::new(c)));
+        let dilution = Self {
+            config: Arc::new(config),
+            analyte,
+            diluent,
+            status: Arc::new(Mutex::new(DilutionStatus::default())),
+            data_tx,
+        };
+        if dilution.analyte.is_some() {
+            println!("🧴 Gas dilution enabled (source {} ppm)", dilution.config.source_ppm);
+            tokio::spawn(dilution.clone().poll_loop());
+        }
+        dilution
+    }
+
+    /// (target, delivered) concentration for the payload
+    pub fn concentration(&self) -> (Option<f32>, Option<f32>) {
+        let status = self.status.lock().unwrap();
+        (status.target_ppm, status.delivered_ppm)
+    }
+
+    pub async fn set_concentration(&self, ppm: f32) -> Result<(), String> {
+        let analyte = self.analyte.as_ref().ok_or("gas dilution is disabled in config")?;
+        let source = self.config.source_ppm;
+        if source <= 0.0 || !(0.0..=source).contains(&ppm) {
+            return Err(format!("concentration must be within 0..{} ppm", source));
+        }
+
+        let total = self.config.total_flow_sccm;
+        let analyte_flow = total * ppm / source;
+        analyte.set_flow(analyte_flow).await?;
+        if let Some(diluent) = &self.diluent {
+            diluent.set_flow(total - analyte_flow).await?;
+        }
+
+        println!("🧴 Dilution set to {:.2} ppm (analyte {:.1} sccm)", ppm, analyte_flow);
+        self.status.lock().unwrap().target_ppm = Some(ppm);
+        self.publish();
+        Ok(())
+    }
+
+    pub fn publish(&self) {
+        let status = *self.status.lock().unwrap();
+        let mut event = serde_json::to_value(status).unwrap_or_default();
+        event["type"] = "dilution".into();
+        let _ = self.data_tx.send(event.to_string());
+    }
+
+    /// Baca flow aktual secara periodik untuk menghitung konsentrasi yang benar-benar terkirim
+    async fn poll_loop(self) {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.poll_secs.max(1)));
+        loop {
+            interval.tick().await;
+            let Some(analyte) = &self.analyte else { return };
+
+            let analyte_flow = match analyte.read_flow().await {
+                Ok(flow) => Some(flow),
+                Err(e) => {
+                    eprintln!("❌ Analyte MFC read error: {}", e);
+                    None
+                }
+            };
+            let diluent_flow = match &self.diluent {
+                Some(mfc) => match mfc.read_flow().await {
+                    Ok(flow) => Some(flow),
+                    Err(e) => {
+                        eprintln!("❌ Diluent MFC read error: {}", e);
+                        None
+                    }
+                },
+                None => None,
+            };
+
+            let mut status = self.status.lock().unwrap();
+            status.analyte_sccm = analyte_flow;
+            status.diluent_sccm = diluent_flow;
+            status.delivered_ppm = delivered_ppm(&self.config, analyte_flow, diluent_flow);
+        }
+    }
+}
+
+/// Concentration reaching the chamber from the measured flows. Without a
+/// diluent MFC the analyte is mixed into the configured total flow.
+fn delivered_ppm(config: &DilutionConfig, analyte: Option<f32>, diluent: Option<f32>) -> Option<f32> {
+    let analyte = analyte?;
+    let total = match config.diluent {
+        Some(_) => analyte + diluent?,
+        None => config.total_flow_sccm,
+    };
+    (total > 0.0).then(|| config.source_ppm * analyte / total)
+}
+
+/// GUI commands:
+///   DILUTE <ppm>
+///   DILUTION_STATUS
+pub async fn handle_command(dilution: &Dilution, args: &[&str]) -> Result<(), String> {
+    match args {
+        ["DILUTE", ppm] => {
+            let ppm = ppm.parse().map_err(|_| format!("invalid concentration '{}'", ppm))?;
+            dilution.set_concentration(ppm).await
+        }
+        ["DILUTION_STATUS"] => {
+            dilution.publish();
+            Ok(())
+        }
+        _ => Err("usage: DILUTE <ppm> | DILUTION_STATUS".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(diluent: bool) -> DilutionConfig {
+        let mfc: MfcConfig = toml::from_str("port = \"/dev/null\"\nprotocol = \"ascii\"\nfull_scale_sccm = 500.0").unwrap();
+        DilutionConfig {
+            enabled: true,
+            source_ppm: 100.0,
+            total_flow_sccm: 200.0,
+            analyte: Some(mfc.clone()),
+            diluent: diluent.then_some(mfc),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn delivered_with_diluent_uses_measured_flows() {
+        let config = config(true);
+        assert_eq!(delivered_ppm(&config, Some(20.0), Some(180.0)), Some(10.0));
+        // Diluent running low raises the delivered concentration
+        assert_eq!(delivered_ppm(&config, Some(20.0), Some(80.0)), Some(20.0));
+        assert_eq!(delivered_ppm(&config, Some(20.0), None), None);
+        assert_eq!(delivered_ppm(&config, Some(0.0), Some(0.0)), None);
+    }
+
+    #[test]
+    fn delivered_without_diluent_uses_total_flow() {
+        let config = config(false);
+        assert_eq!(delivered_ppm(&config, Some(20.0), None), Some(10.0));
+        assert_eq!(delivered_ppm(&config, Some(50.0), None), Some(25.0));
+        assert_eq!(delivered_ppm(&config, None, None), None);
+    }
+}
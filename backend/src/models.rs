@@ -0,0 +1,345 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::{broadcast, watch};
+
+use crate::audit::AuditLog;
+use crate::classifier::{self, Classifier, ModelFile};
+use crate::provenance::fingerprint;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModelsConfig {
+    /// Registry directory (`registry.json` + `<name>/v<N>.json`)
+    #[serde(default = "default_dir")]
+    pub dir: String,
+}
+
+fn default_dir() -> String { "model_registry".to_string() }
+
+impl Default for ModelsConfig {
+    fn default() -> Self {
+        Self { dir: default_dir() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    models: ModelsConfig,
+}
+
+impl ModelsConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().models
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.dir.is_empty() {
+            return Err("models.dir must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// One stored model version; the file is never changed after registering
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModelVersion {
+    /// `<name>@v<version>`, also the `model` of its predictions
+    pub id: String,
+    pub name: String,
+    pub version: u32,
+    /// Model `type` ("knn", "centroid")
+    pub kind: String,
+    pub features: Vec<String>,
+    pub labels: Vec<String>,
+    pub samples: usize,
+    /// Relative to the registry directory
+    pub file: String,
+    /// FNV-1a of the model file
+    pub fingerprint: String,
+    pub registered_at: i64,
+    pub registered_by: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// One change of the active model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Activation {
+    pub timestamp: i64,
+    pub actor: String,
+    /// "activate" or "rollback"
+    pub action: String,
+    /// None = back to `[features] model`
+    pub model: Option<String>,
+    /// Version a rollback from `model` returns to
+    pub previous: Option<String>,
+}
+
+/// `registry.json`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Registry {
+    pub models: Vec<ModelVersion>,
+    pub active: Option<String>,
+    /// Evaluated on every cycle next to the active model, logged only
+    #[serde(default)]
+    pub shadow: Option<String>,
+    /// Oldest first
+    pub history: Vec<Activation>,
+}
+
+// ================= Model Registry =================
+/// Versioned classifier models in a local directory. Registering only
+/// stores a copy; which version classifies cycles changes solely through
+/// MODEL_ACTIVATE / MODEL_ROLLBACK, recorded in the registry history, the
+/// audit log and the provenance (`model_version`) of every frame after it.
+/// A shadow version can run alongside for comparison before promoting it.
+#[derive(Clone)]
+pub struct ModelRegistry {
+    dir: Arc<PathBuf>,
+    registry: Arc<Mutex<Registry>>,
+    loaded: Arc<RwLock<Option<Arc<dyn Classifier>>>>,
+    shadow: Arc<RwLock<Option<Arc<dyn Classifier>>>>,
+    active_tx: Arc<watch::Sender<Option<String>>>,
+    audit: AuditLog,
+    data_tx: broadcast::Sender<String>,
+}
+
+impl ModelRegistry {
+    pub fn new(config: &ModelsConfig, audit: AuditLog, data_tx: broadcast::Sender<String>) -> Self {
+        let dir = PathBuf::from(&config.dir);
+        let registry: Registry = std::fs::read_to_string(dir.join("registry.json"))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| eprintln!("❌ Model registry unreadable: {}", e)).ok())
+            .unwrap_or_default();
+
+        let loaded = registry.active.as_ref().and_then(|id| match load_version(&dir, &registry, id) {
+            Ok(classifier) => {
+                println!("🧠 Active model {}", id);
+                Some(classifier)
+            }
+            Err(e) => {
+                eprintln!("❌ Active model {} not loaded, using [features] model: {}", id, e);
+                None
+            }
+        });
+        let shadow = registry.shadow.as_ref().and_then(|id| {
+            load_version(&dir, &registry, id).map_err(|e| eprintln!("❌ Shadow model {} not loaded: {}", id, e)).ok()
+        });
+        let (active_tx, _) = watch::channel(loaded.as_ref().and(registry.active.clone()));
+        Self {
+            dir: Arc::new(dir),
+            registry: Arc::new(Mutex::new(registry)),
+            loaded: Arc::new(RwLock::new(loaded)),
+            shadow: Arc::new(RwLock::new(shadow)),
+            active_tx: Arc::new(active_tx),
+            audit,
+            data_tx,
+        }
+    }
+
+    /// Classifier of the active version, if any
+    pub fn active(&self) -> Option<Arc<dyn Classifier>> {
+        self.loaded.read().unwrap().clone()
+    }
+
+    /// Classifier of the shadow version, if any
+    pub fn shadow(&self) -> Option<Arc<dyn Classifier>> {
+        self.shadow.read().unwrap().clone()
+    }
+
+    pub fn active_id(&self) -> Option<String> {
+        self.active_tx.borrow().clone()
+    }
+
+    /// Active version changes, for provenance
+    pub fn subscribe(&self) -> watch::Receiver<Option<String>> {
+        self.active_tx.subscribe()
+    }
+
+    pub fn snapshot(&self) -> Registry {
+        self.registry.lock().unwrap().clone()
+    }
+
+    /// Copy a model file into the registry as the next version of `name`
+    pub fn register(&self, path: &str, name: &str, description: &str, actor: &str) -> Result<ModelVersion, String> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("invalid model name '{}' (letters, digits, _ and -)", name));
+        }
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        classifier::load(path)?;
+        let model: ModelFile = serde_json::from_slice(&bytes).map_err(|e| format!("{}: {}", path, e))?;
+
+        let mut registry = self.registry.lock().unwrap();
+        let version = registry.models.iter().filter(|m| m.name == name).map(|m| m.version).max().unwrap_or(0) + 1;
+        let file = format!("{}/v{}.json", name, version);
+        let target = self.dir.join(&file);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("cannot create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(&target, &bytes).map_err(|e| format!("cannot write {}: {}", target.display(), e))?;
+
+        let entry = ModelVersion {
+            id: format!("{}@v{}", name, version),
+            name: name.to_string(),
+            version,
+            kind: model.kind,
+            features: model.features,
+            labels: model.samples.iter().map(|s| s.label.clone()).collect::<BTreeSet<_>>().into_iter().collect(),
+            samples: model.samples.len(),
+            file,
+            fingerprint: fingerprint(&bytes),
+            registered_at: chrono::Utc::now().timestamp_millis(),
+            registered_by: actor.to_string(),
+            description: description.to_string(),
+        };
+        registry.models.push(entry.clone());
+        self.save(&registry)?;
+        drop(registry);
+        println!("🧠 Model {} registered from {}", entry.id, path);
+        self.audit.record(actor, "model_register", serde_json::json!({ "id": entry.id, "source": path, "fingerprint": entry.fingerprint }));
+        self.publish();
+        Ok(entry)
+    }
+
+    pub fn activate(&self, id: &str, actor: &str) -> Result<(), String> {
+        self.switch(Some(id.to_string()), "activate", actor)
+    }
+
+    /// Back to the version active before the current one (ultimately `[features] model`);
+    /// repeated rollbacks walk further back through the history
+    pub fn rollback(&self, actor: &str) -> Result<Option<String>, String> {
+        let target = {
+            let registry = self.registry.lock().unwrap();
+            registry
+                .history
+                .iter()
+                .rev()
+                .find(|a| a.model == registry.active)
+                .ok_or("no previous model to roll back to")?
+                .previous
+                .clone()
+        };
+        self.switch(target.clone(), "rollback", actor)?;
+        Ok(target)
+    }
+
+    /// Evaluate `id` on every cycle without publishing its decisions (None = stop);
+    /// its predictions go to the accuracy log and "shadow_classification"
+    pub fn set_shadow(&self, id: Option<&str>, actor: &str) -> Result<(), String> {
+        let mut registry = self.registry.lock().unwrap();
+        if id.is_some() && id == registry.active.as_deref() {
+            return Err(format!("model {} is active, shadow another version", id.unwrap_or_default()));
+        }
+        let classifier = id.map(|id| load_version(&self.dir, &registry, id)).transpose()?;
+        let previous = std::mem::replace(&mut registry.shadow, id.map(String::from));
+        self.save(&registry)?;
+        drop(registry);
+
+        *self.shadow.write().unwrap() = classifier;
+        match id {
+            Some(id) => println!("🧠 Shadow model {}", id),
+            None => println!("🧠 Shadow model off"),
+        }
+        self.audit.record(actor, "model_shadow", serde_json::json!({ "model": id, "previous": previous }));
+        self.publish();
+        Ok(())
+    }
+
+    fn switch(&self, target: Option<String>, action: &str, actor: &str) -> Result<(), String> {
+        let mut registry = self.registry.lock().unwrap();
+        if registry.active == target {
+            return Err(match &target {
+                Some(id) => format!("model {} is already active", id),
+                None => "no registry model is active".to_string(),
+            });
+        }
+        let classifier = target.as_ref().map(|id| load_version(&self.dir, &registry, id)).transpose()?;
+        // Shadow yang dipromosikan tidak perlu dievaluasi dua kali
+        let promoted = target.is_some() && registry.shadow == target;
+        if promoted {
+            registry.shadow = None;
+        }
+        // Rollback berantai: "previous" dari aktivasi target sebelumnya
+        let previous = match action {
+            "rollback" => registry.history.iter().rev().find(|a| a.model == target).and_then(|a| a.previous.clone()),
+            _ => registry.active.clone(),
+        };
+        let from = std::mem::replace(&mut registry.active, target.clone());
+        registry.history.push(Activation {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            actor: actor.to_string(),
+            action: action.to_string(),
+            model: target.clone(),
+            previous,
+        });
+        self.save(&registry)?;
+        drop(registry);
+
+        *self.loaded.write().unwrap() = classifier;
+        if promoted {
+            *self.shadow.write().unwrap() = None;
+        }
+        self.active_tx.send_replace(target.clone());
+        println!(
+            "🧠 Model {}: {} -> {}",
+            action,
+            from.as_deref().unwrap_or("[features] model"),
+            target.as_deref().unwrap_or("[features] model")
+        );
+        self.audit.record(
+            actor,
+            &format!("model_{}", action),
+            serde_json::json!({ "model": target, "previous": from, "from_shadow": promoted }),
+        );
+        self.publish();
+        Ok(())
+    }
+
+    fn save(&self, registry: &Registry) -> Result<(), String> {
+        std::fs::create_dir_all(self.dir.as_path()).map_err(|e| format!("cannot create {}: {}", self.dir.display(), e))?;
+        let path = self.dir.join("registry.json");
+        let json = serde_json::to_string_pretty(registry).unwrap_or_default();
+        std::fs::write(&path, json).map_err(|e| format!("cannot write {}: {}", path.display(), e))
+    }
+
+    fn event(&self) -> String {
+        let mut event = serde_json::to_value(self.snapshot()).unwrap_or_default();
+        event["type"] = "models".into();
+        event.to_string()
+    }
+
+    /// "models" event to every GUI
+    fn publish(&self) {
+        let _ = self.data_tx.send(self.event());
+    }
+}
+
+fn load_version(dir: &Path, registry: &Registry, id: &str) -> Result<Arc<dyn Classifier>, String> {
+    let model = registry.models.iter().find(|m| m.id == id).ok_or_else(|| format!("unknown model '{}'", id))?;
+    Ok(Arc::from(classifier::load_named(&dir.join(&model.file).to_string_lossy(), id)?))
+}
+
+/// GUI commands:
+///   MODELS                                  -> reply: "models" (versions, active, shadow, history)
+///   MODEL_REGISTER <path> <name> [description...]
+///   MODEL_ACTIVATE <name@vN>                -> also promotes the shadow version
+///   MODEL_ROLLBACK                          -> previously active version
+///   MODEL_SHADOW <name@vN>|off
+pub fn handle_command(models: &ModelRegistry, client: &str, args: &[&str]) -> Result<Option<String>, String> {
+    match args {
+        ["MODELS"] => Ok(Some(models.event())),
+        ["MODEL_REGISTER", path, name, description @ ..] => {
+            models.register(path, name, &description.join(" "), client).map(|_| None)
+        }
+        ["MODEL_ACTIVATE", id] => models.activate(id, client).map(|_| None),
+        ["MODEL_ROLLBACK"] => models.rollback(client).map(|_| None),
+        ["MODEL_SHADOW", "off"] => models.set_shadow(None, client).map(|_| None),
+        ["MODEL_SHADOW", id] => models.set_shadow(Some(id), client).map(|_| None),
+        _ => Err("usage: MODELS | MODEL_REGISTER <path> <name> [description] | MODEL_ACTIVATE <name@vN> | MODEL_ROLLBACK \
+                  | MODEL_SHADOW <name@vN>|off"
+            .to_string()),
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::audit::AuditLog;
+use crate::filtering::{FilterConfig, ModulationOverride, SensorFilters};
+use crate::CHANNEL_NAMES;
+
+// ================= Modulation Control =================
+/// Runtime sine modulation toggles per channel (or "all"), layered over
+/// sine_* and `[modulation_channels]` on every device until reset. Lets a
+/// single sensor be put under modulation test without editing config.
+#[derive(Clone)]
+pub struct ModulationControl {
+    config_path: Arc<String>,
+    overrides: Arc<Mutex<BTreeMap<String, ModulationOverride>>>,
+    data_tx: broadcast::Sender<String>,
+}
+
+impl ModulationControl {
+    pub fn new(config_path: &str, data_tx: broadcast::Sender<String>) -> Self {
+        Self { config_path: Arc::new(config_path.to_string()), overrides: Arc::new(Mutex::new(BTreeMap::new())), data_tx }
+    }
+
+    /// Physical channels, then the configured virtual ones
+    fn channels(&self) -> Vec<String> {
+        let virtual_channels = FilterConfig::load(&self.config_path, None).virtual_channels;
+        CHANNEL_NAMES.iter().map(|c| c.to_string()).chain(virtual_channels.into_keys()).collect()
+    }
+
+    /// Toggle one channel; "all" replaces every per-channel toggle
+    pub fn set(&self, channel: &str, settings: ModulationOverride) -> Result<(), String> {
+        if channel != "all" && !self.channels().iter().any(|c| c == channel) {
+            return Err(format!("unknown channel '{}'", channel));
+        }
+        settings.validate()?;
+        {
+            let mut overrides = self.overrides.lock().unwrap();
+            if channel == "all" {
+                overrides.clear();
+            }
+            let previous = overrides.get(channel).copied().unwrap_or_default();
+            overrides.insert(channel.to_string(), settings.over(previous));
+        }
+        let detail = match settings.enabled {
+            Some(false) => "off".to_string(),
+            _ => format!(
+                "on{}{}",
+                settings.amplitude.map(|a| format!(", amplitude {}", a)).unwrap_or_default(),
+                settings.frequency.map(|f| format!(", {} Hz", f)).unwrap_or_default()
+            ),
+        };
+        println!("〰️ Modulation {}: {}", channel, detail);
+        self.publish();
+        Ok(())
+    }
+
+    /// Back to config for one channel, or for every channel with None / "all"
+    pub fn reset(&self, channel: Option<&str>) {
+        match channel {
+            Some(channel) if channel != "all" => {
+                self.overrides.lock().unwrap().remove(channel);
+            }
+            _ => self.overrides.lock().unwrap().clear(),
+        }
+        self.publish();
+    }
+
+    /// Hand the current toggles to a device's filters before each frame
+    pub fn apply(&self, filters: &mut SensorFilters) {
+        filters.set_runtime_modulation(&self.overrides.lock().unwrap());
+    }
+
+    /// "modulation" GUI event: runtime toggles and the resulting settings per channel
+    pub fn publish(&self) {
+        let config = FilterConfig::load(&self.config_path, None);
+        let overrides = self.overrides.lock().unwrap().clone();
+        let all = overrides.get("all").copied().unwrap_or_default();
+        let channels: BTreeMap<String, ModulationOverride> = self
+            .channels()
+            .into_iter()
+            .map(|name| {
+                let runtime = overrides.get(&name).copied().unwrap_or_default();
+                let settings = runtime.over(all).over(config.modulation_for(&name));
+                (name, settings)
+            })
+            .collect();
+        let event = serde_json::json!({ "type": "modulation", "runtime": overrides, "channels": channels });
+        let _ = self.data_tx.send(event.to_string());
+    }
+}
+
+/// GUI commands:
+///   MODULATION_ON <channel|all> [amplitude] [frequency]
+///   MODULATION_OFF <channel|all>
+///   MODULATION_RESET [channel|all]   back to config
+///   MODULATION_STATUS
+pub fn handle_command(control: &ModulationControl, audit: &AuditLog, client: &str, args: &[&str]) -> Result<(), String> {
+    let number = |value: Option<&&str>, name: &str| -> Result<Option<f32>, String> {
+        value.map(|v| v.parse::<f32>().map_err(|_| format!("invalid {} '{}'", name, v))).transpose()
+    };
+    match args {
+        ["MODULATION_ON", channel, rest @ ..] if rest.len() <= 2 => {
+            let settings = ModulationOverride {
+                enabled: Some(true),
+                amplitude: number(rest.first(), "amplitude")?,
+                frequency: number(rest.get(1), "frequency")?,
+            };
+            control.set(channel, settings)?;
+            audit.record(client, "modulation_on", serde_json::json!({ "channel": channel, "settings": settings }));
+            Ok(())
+        }
+        ["MODULATION_OFF", channel] => {
+            control.set(channel, ModulationOverride { enabled: Some(false), ..Default::default() })?;
+            audit.record(client, "modulation_off", serde_json::json!({ "channel": channel }));
+            Ok(())
+        }
+        ["MODULATION_RESET", rest @ ..] if rest.len() <= 1 => {
+            control.reset(rest.first().copied());
+            audit.record(client, "modulation_reset", serde_json::json!({ "channel": rest.first() }));
+            Ok(())
+        }
+        ["MODULATION_STATUS"] => {
+            control.publish();
+            Ok(())
+        }
+        _ => Err(
+            "usage: MODULATION_ON <channel|all> [amplitude] [frequency] | MODULATION_OFF <channel|all> | MODULATION_RESET [channel] | MODULATION_STATUS"
+                .to_string(),
+        ),
+    }
+}
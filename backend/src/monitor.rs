@@ -0,0 +1,277 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, Sparkline};
+use ratatui::Frame;
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+use crate::CHANNEL_NAMES;
+
+/// Samples kept per channel for the sparklines
+const HISTORY: usize = 120;
+/// Lines kept in the alert and message panes
+const LOG_LINES: usize = 50;
+
+struct ChannelRow {
+    name: String,
+    label: String,
+    unit: String,
+}
+
+// ================= Live TUI Monitor =================
+/// Terminal view of a running backend, connected like a GUI client
+#[derive(Default)]
+struct Monitor {
+    address: String,
+    channels: Vec<ChannelRow>,
+    values: BTreeMap<String, f64>,
+    history: BTreeMap<String, VecDeque<f64>>,
+    state_name: String,
+    level: i64,
+    device: String,
+    session: String,
+    frames: u64,
+    alerts: VecDeque<String>,
+    messages: VecDeque<String>,
+    input: String,
+    connected: bool,
+}
+
+fn push_log(log: &mut VecDeque<String>, line: String) {
+    log.push_front(line);
+    log.truncate(LOG_LINES);
+}
+
+fn time_of(timestamp: Option<i64>) -> String {
+    timestamp
+        .and_then(chrono::DateTime::from_timestamp_millis)
+        .map(|t| t.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+impl Monitor {
+    fn new(address: &str) -> Self {
+        let channels = CHANNEL_NAMES
+            .iter()
+            .map(|name| ChannelRow { name: name.to_string(), label: name.to_string(), unit: String::new() })
+            .collect();
+        Self { address: address.to_string(), channels, ..Default::default() }
+    }
+
+    fn handle_line(&mut self, line: &str) {
+        let Ok(message) = serde_json::from_str::<Value>(line) else {
+            return;
+        };
+        match message.get("type").and_then(Value::as_str) {
+            // Frame sensor tidak punya field "type"
+            None => self.handle_frame(&message),
+            Some("channel_metadata") => {
+                let channels = message["channels"].as_array().cloned().unwrap_or_default();
+                self.channels = channels
+                    .iter()
+                    .map(|c| ChannelRow {
+                        name: c["name"].as_str().unwrap_or_default().to_string(),
+                        label: c["label"].as_str().or(c["name"].as_str()).unwrap_or_default().to_string(),
+                        unit: c["unit"].as_str().unwrap_or_default().to_string(),
+                    })
+                    .collect();
+            }
+            Some("alert") => {
+                let line = format!(
+                    "{} {} [{}] {}{}",
+                    time_of(message["timestamp"].as_i64()),
+                    message["name"].as_str().unwrap_or("?"),
+                    message["severity"].as_str().unwrap_or("?"),
+                    message["state"].as_str().unwrap_or("?"),
+                    if message["silenced"].as_bool() == Some(true) { " (silenced)" } else { "" },
+                );
+                push_log(&mut self.alerts, line);
+            }
+            Some("command_error") => push_log(
+                &mut self.messages,
+                format!("❌ {}: {}", message["command"].as_str().unwrap_or(""), message["error"].as_str().unwrap_or("")),
+            ),
+            Some(other) => push_log(&mut self.messages, format!("{} {}", other, truncate(line, 200))),
+        }
+    }
+
+    fn handle_frame(&mut self, frame: &Value) {
+        self.frames += 1;
+        self.state_name = frame["state_name"].as_str().unwrap_or_default().to_string();
+        self.level = frame["level"].as_i64().unwrap_or_default();
+        self.device = frame["device"].as_str().unwrap_or_default().to_string();
+        self.session = frame["session"].as_str().unwrap_or_default().to_string();
+        for channel in &self.channels {
+            let Some(value) = frame.get(&channel.name).and_then(Value::as_f64) else {
+                continue;
+            };
+            self.values.insert(channel.name.clone(), value);
+            let history = self.history.entry(channel.name.clone()).or_default();
+            history.push_back(value);
+            if history.len() > HISTORY {
+                history.pop_front();
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body, input] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(5), Constraint::Length(3)]).areas(frame.area());
+        let [channels, side] = Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(body);
+        let [alerts, messages] = Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(side);
+
+        let status = if self.connected {
+            Span::styled("● connected", Style::default().fg(Color::Green))
+        } else {
+            Span::styled("● disconnected", Style::default().fg(Color::Red))
+        };
+        let mut spans = vec![
+            Span::styled(" E-Nose monitor ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("{} ", self.address)),
+            status,
+            Span::raw(format!("  state {} level {}", self.state_name, self.level)),
+        ];
+        if !self.device.is_empty() {
+            spans.push(Span::raw(format!("  device {}", self.device)));
+        }
+        if !self.session.is_empty() {
+            spans.push(Span::raw(format!("  session {}", self.session)));
+        }
+        spans.push(Span::raw(format!("  frames {}", self.frames)));
+        frame.render_widget(Paragraph::new(Line::from(spans)), header);
+
+        self.draw_channels(frame, channels);
+
+        frame.render_widget(log_list(" Alerts ", &self.alerts), alerts);
+        frame.render_widget(log_list(" Messages ", &self.messages), messages);
+
+        let prompt = Paragraph::new(format!("> {}", self.input))
+            .block(Block::default().borders(Borders::ALL).title(" Command (Enter: send, Esc: quit) "));
+        frame.render_widget(prompt, input);
+        frame.set_cursor_position((input.x + 3 + self.input.chars().count() as u16, input.y + 1));
+    }
+
+    fn draw_channels(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::default().borders(Borders::ALL).title(" Channels ");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let rows = Layout::vertical(self.channels.iter().map(|_| Constraint::Length(1))).split(inner);
+        for (channel, row) in self.channels.iter().zip(rows.iter()) {
+            let [name, value, spark] =
+                Layout::horizontal([Constraint::Length(16), Constraint::Length(16), Constraint::Min(10)]).areas(*row);
+            frame.render_widget(Paragraph::new(truncate(&channel.label, 15)), name);
+            let text = match self.values.get(&channel.name) {
+                Some(v) => format!("{:>9.3} {}", v, channel.unit),
+                None => format!("{:>9} {}", "-", channel.unit),
+            };
+            frame.render_widget(Paragraph::new(text), value);
+
+            // Skala relatif ke min/max window supaya perubahan kecil tetap terlihat
+            let history = self.history.get(&channel.name).cloned().unwrap_or_default();
+            let (min, max) = history.iter().fold((f64::MAX, f64::MIN), |(lo, hi), v| (lo.min(*v), hi.max(*v)));
+            let span = (max - min).max(1e-9);
+            let data: Vec<u64> = history.iter().map(|v| ((v - min) / span * 100.0) as u64 + 1).collect();
+            let skip = data.len().saturating_sub(spark.width as usize);
+            frame.render_widget(
+                Sparkline::default().data(&data[skip..]).max(101).style(Style::default().fg(Color::Cyan)),
+                spark,
+            );
+        }
+    }
+}
+
+fn log_list<'a>(title: &'a str, lines: &'a VecDeque<String>) -> List<'a> {
+    List::new(lines.iter().map(|l| ListItem::new(l.as_str())).collect::<Vec<_>>())
+        .block(Block::default().borders(Borders::ALL).title(title))
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
+
+/// `enose monitor [host:port]`: live channel values, sparklines, state,
+/// alerts and a command box, for SSH sessions without a GUI
+pub async fn run(address: &str) -> anyhow::Result<()> {
+    let (line_tx, mut line_rx) = mpsc::unbounded_channel::<Option<String>>();
+    let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel::<String>();
+    let stream = TcpStream::connect(address).await?;
+    let (reader, mut writer) = stream.into_split();
+
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line_tx.send(Some(line)).is_err() {
+                return;
+            }
+        }
+        let _ = line_tx.send(None);
+    });
+    tokio::spawn(async move {
+        while let Some(cmd) = cmd_rx.recv().await {
+            if writer.write_all(format!("{}\n", cmd).as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Keyboard dibaca di thread terpisah karena event::read() blocking
+    let (key_tx, mut key_rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if key_tx.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut terminal = ratatui::init();
+    let mut monitor = Monitor::new(address);
+    monitor.connected = true;
+    let mut redraw = tokio::time::interval(Duration::from_millis(200));
+
+    let result: anyhow::Result<()> = loop {
+        tokio::select! {
+            line = line_rx.recv() => match line.flatten() {
+                Some(line) => monitor.handle_line(&line),
+                None => monitor.connected = false,
+            },
+            Some(event) = key_rx.recv() => {
+                let Event::Key(key) = event else { continue };
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Esc => break Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break Ok(()),
+                    KeyCode::Enter => {
+                        let cmd = monitor.input.trim().to_string();
+                        monitor.input.clear();
+                        if !cmd.is_empty() {
+                            push_log(&mut monitor.messages, format!("> {}", cmd));
+                            let _ = cmd_tx.send(cmd);
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        monitor.input.pop();
+                    }
+                    KeyCode::Char(c) => monitor.input.push(c),
+                    _ => {}
+                }
+            }
+            _ = redraw.tick() => {
+                if let Err(e) = terminal.draw(|frame| monitor.draw(frame)) {
+                    break Err(e.into());
+                }
+            }
+        }
+    };
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,99 @@
+use rumqttc::{AsyncClient, LastWill, MqttOptions, QoS};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::alerts::AlertEvent;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MqttConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Alerts go to `<alert_topic>/<name>`, backend status to `<alert_topic>/status`
+    #[serde(default = "default_alert_topic")]
+    pub alert_topic: String,
+}
+
+fn default_host() -> String { "localhost".to_string() }
+fn default_port() -> u16 { 1883 }
+fn default_client_id() -> String { "enose-backend".to_string() }
+fn default_alert_topic() -> String { "enose/alerts".to_string() }
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: default_host(),
+            port: default_port(),
+            client_id: default_client_id(),
+            username: None,
+            password: None,
+            alert_topic: default_alert_topic(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    mqtt: MqttConfig,
+}
+
+impl MqttConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().mqtt
+    }
+}
+
+// ================= MQTT Alarm Publisher =================
+/// Publishes alert lifecycle events as retained messages so building
+/// controllers (ventilation etc.) always see the last state on subscribe
+pub async fn run_alarm_publisher(config: MqttConfig, mut alert_rx: broadcast::Receiver<AlertEvent>) {
+    let status_topic = format!("{}/status", config.alert_topic);
+
+    let mut options = MqttOptions::new(config.client_id.clone(), config.host.clone(), config.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(&status_topic, "offline", QoS::AtLeastOnce, true));
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        options.set_credentials(username, password);
+    }
+
+    let (client, mut eventloop) = AsyncClient::new(options, 32);
+    // Event loop harus terus di-poll agar koneksi (dan reconnect) berjalan
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = eventloop.poll().await {
+                eprintln!("❌ MQTT connection error: {}", e);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        }
+    });
+
+    println!("📡 MQTT alarm publisher → {}:{} {}/#", config.host, config.port, config.alert_topic);
+    let _ = client.publish(&status_topic, QoS::AtLeastOnce, true, "online").await;
+
+    loop {
+        let event = match alert_rx.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        let topic = format!("{}/{}", config.alert_topic, event.name);
+        let payload = serde_json::to_vec(&event).unwrap_or_default();
+        if let Err(e) = client.publish(topic, QoS::AtLeastOnce, true, payload).await {
+            eprintln!("❌ MQTT publish failed: {}", e);
+        }
+    }
+}
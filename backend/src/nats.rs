@@ -0,0 +1,403 @@
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::Instant;
+
+use crate::dialer::{self, Backoff, BackoffConfig, EndpointConfig, EndpointKind};
+use crate::AppState;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NatsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// NATS server host:port
+    #[serde(default = "default_address")]
+    pub address: String,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub token: Option<String>,
+    /// `{device}` is replaced by the device ID ('.', '*', '>' and spaces become '_')
+    #[serde(default = "default_frames_subject")]
+    pub frames_subject: String,
+    /// Commands for one device arrive here; empty = no command subscription
+    #[serde(default = "default_command_subject")]
+    pub command_subject: String,
+    /// Wait for the JetStream ack of every frame and resend until it comes
+    #[serde(default = "default_jetstream")]
+    pub jetstream: bool,
+    #[serde(default = "default_ack_timeout_ms")]
+    pub ack_timeout_ms: u64,
+    /// Frames kept while unacknowledged or disconnected; the oldest are dropped beyond
+    #[serde(default = "default_max_pending")]
+    pub max_pending: usize,
+    #[serde(default)]
+    pub backoff: BackoffConfig,
+}
+
+fn default_address() -> String { "localhost:4222".to_string() }
+fn default_frames_subject() -> String { "enose.frames.{device}".to_string() }
+fn default_command_subject() -> String { "enose.cmd.{device}".to_string() }
+fn default_jetstream() -> bool { true }
+fn default_ack_timeout_ms() -> u64 { 5000 }
+fn default_max_pending() -> usize { 10_000 }
+
+impl Default for NatsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: default_address(),
+            user: None,
+            password: None,
+            token: None,
+            frames_subject: default_frames_subject(),
+            command_subject: default_command_subject(),
+            jetstream: default_jetstream(),
+            ack_timeout_ms: default_ack_timeout_ms(),
+            max_pending: default_max_pending(),
+            backoff: BackoffConfig::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    nats: NatsConfig,
+}
+
+impl NatsConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().nats
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.frames_subject.is_empty() {
+            return Err("nats.frames_subject must not be empty".to_string());
+        }
+        if !self.command_subject.is_empty() && self.device_token().is_none() {
+            return Err("nats.command_subject needs exactly one '{device}' token, e.g. \"enose.cmd.{device}\"".to_string());
+        }
+        if self.max_pending == 0 {
+            return Err("nats.max_pending must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Position of the `{device}` token in command_subject
+    fn device_token(&self) -> Option<usize> {
+        let tokens: Vec<&str> = self.command_subject.split('.').collect();
+        match tokens.iter().filter(|t| **t == "{device}").count() {
+            1 => tokens.iter().position(|t| *t == "{device}"),
+            _ => None,
+        }
+    }
+}
+
+/// Device ID as one subject token
+fn subject_token(device: &str) -> String {
+    device.chars().map(|c| if c == '.' || c == '*' || c == '>' || c.is_whitespace() { '_' } else { c }).collect()
+}
+
+/// A frame waiting for its JetStream ack
+struct Pending {
+    seq: u64,
+    /// Nats-Msg-Id, lets the stream drop duplicates after a resend
+    id: String,
+    subject: String,
+    payload: String,
+    sent_at: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Outbox {
+    next_seq: u64,
+    frames: VecDeque<Pending>,
+    dropped: u64,
+}
+
+impl Outbox {
+    fn push(&mut self, subject: String, id: String, payload: String, max: usize) {
+        self.next_seq += 1;
+        self.frames.push_back(Pending { seq: self.next_seq, id, subject, payload, sent_at: None });
+        while self.frames.len() > max {
+            self.frames.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    fn ack(&mut self, seq: u64) {
+        self.frames.retain(|p| p.seq != seq);
+    }
+}
+
+// ================= NATS Sink =================
+/// Publishes verified frames to `frames_subject` (JetStream, at-least-once:
+/// resent until acked, deduplicated by Nats-Msg-Id) and forwards messages on
+/// the per-device `command_subject` to that device like a GUI command
+pub async fn run(config: NatsConfig, state: AppState) {
+    let outbox = Arc::new(Mutex::new(Outbox::default()));
+    tokio::spawn(collect(config.clone(), state.frame_tx.clone(), outbox.clone()));
+
+    let endpoint = EndpointConfig {
+        name: "nats".to_string(),
+        address: config.address.clone(),
+        kind: EndpointKind::Arduino,
+        device: None,
+        connect_timeout_ms: 5000,
+        backoff: config.backoff.clone(),
+    };
+    let mut backoff = Backoff::new(config.backoff.clone());
+    println!("📨 NATS sink → {} ({}{})", config.address, config.frames_subject, if config.jetstream { ", JetStream" } else { "" });
+
+    loop {
+        let Some(socket) = dialer::connect(&endpoint, &mut backoff).await else {
+            return;
+        };
+        match run_link(socket, &config, &state, &outbox).await {
+            Ok(()) => println!("📨 NATS connection closed, reconnecting"),
+            Err(e) => eprintln!("⚠️ NATS connection lost: {}, reconnecting", e),
+        }
+        // Yang belum di-ack dikirim ulang setelah tersambung lagi
+        for pending in outbox.lock().unwrap().frames.iter_mut() {
+            pending.sent_at = None;
+        }
+        tokio::time::sleep(backoff.next_delay().unwrap_or(Duration::from_secs(5))).await;
+    }
+}
+
+/// Queue frames also while disconnected
+async fn collect(config: NatsConfig, frame_tx: broadcast::Sender<crate::UnifiedSensorData>, outbox: Arc<Mutex<Outbox>>) {
+    let mut frame_rx = frame_tx.subscribe();
+    loop {
+        match frame_rx.recv().await {
+            Ok(frame) => {
+                let subject = config.frames_subject.replace("{device}", &subject_token(&frame.device));
+                let id = format!("{}-{}", frame.device, frame.timestamp);
+                let payload = serde_json::to_string(&frame).unwrap_or_default();
+                outbox.lock().unwrap().push(subject, id, payload, config.max_pending);
+            }
+            Err(broadcast::error::RecvError::Lagged(n)) => eprintln!("⚠️ NATS sink lagged, {} frame(s) skipped", n),
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+async fn run_link(socket: tokio::net::TcpStream, config: &NatsConfig, state: &AppState, outbox: &Mutex<Outbox>) -> anyhow::Result<()> {
+    let (reader, mut writer) = tokio::io::split(socket);
+    let mut reader = BufReader::new(reader);
+
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    if !line.starts_with("INFO") {
+        anyhow::bail!("unexpected greeting '{}'", line.trim());
+    }
+    let mut connect = serde_json::json!({
+        "verbose": false,
+        "pedantic": false,
+        "name": "enose-backend",
+        "lang": "rust",
+        "version": env!("CARGO_PKG_VERSION"),
+        "protocol": 1,
+        "headers": true,
+        "no_responders": true,
+    });
+    if let (Some(user), Some(password)) = (&config.user, &config.password) {
+        connect["user"] = user.clone().into();
+        connect["pass"] = password.clone().into();
+    }
+    if let Some(token) = &config.token {
+        connect["auth_token"] = token.clone().into();
+    }
+    writer.write_all(format!("CONNECT {}\r\nPING\r\n", connect).as_bytes()).await?;
+    line.clear();
+    reader.read_line(&mut line).await?;
+    if !line.starts_with("PONG") {
+        anyhow::bail!("rejected: {}", line.trim());
+    }
+    println!("📨 NATS connected to {}", config.address);
+
+    // sid 1: command, sid 2: JetStream acks (reply subject per frame)
+    let inbox = format!("_INBOX.enose.{}", std::process::id());
+    let mut subscribe = format!("SUB {}.* 2\r\n", inbox);
+    if !config.command_subject.is_empty() {
+        subscribe += &format!("SUB {} 1\r\n", config.command_subject.replace("{device}", "*"));
+    }
+    writer.write_all(subscribe.as_bytes()).await?;
+
+    // Pembacaan di task sendiri: read_line/read_exact tidak aman dibatalkan oleh select!
+    let (message_tx, mut message_rx) = mpsc::unbounded_channel();
+    let read_task = tokio::spawn(async move {
+        loop {
+            let message = read_message(&mut reader).await;
+            let failed = message.is_err();
+            if message_tx.send(message).is_err() || failed {
+                break;
+            }
+        }
+    });
+    let result = serve(config, state, outbox, &inbox, &mut writer, &mut message_rx).await;
+    read_task.abort();
+    result
+}
+
+async fn serve<W: AsyncWriteExt + Unpin>(
+    config: &NatsConfig,
+    state: &AppState,
+    outbox: &Mutex<Outbox>,
+    inbox: &str,
+    writer: &mut W,
+    message_rx: &mut mpsc::UnboundedReceiver<anyhow::Result<Message>>,
+) -> anyhow::Result<()> {
+    let ack_timeout = Duration::from_millis(config.ack_timeout_ms);
+    let mut poll = tokio::time::interval(Duration::from_millis(100));
+    let mut reported_dropped = 0;
+    loop {
+        tokio::select! {
+            message = message_rx.recv() => {
+                let Some(message) = message else { return Ok(()) };
+                match message? {
+                    Message::Ping => writer.write_all(b"PONG\r\n").await?,
+                    Message::Error(e) => eprintln!("❌ NATS: {}", e),
+                    Message::Msg { subject, reply, headers, payload } => {
+                        if let Some(seq) = subject.strip_prefix(&format!("{}.", inbox)) {
+                            handle_ack(seq, &headers, &payload, outbox);
+                        } else {
+                            let result = match command(config, state, &subject, &payload).await {
+                                Ok(()) => serde_json::json!({ "ok": true }),
+                                Err(e) => serde_json::json!({ "error": e }),
+                            };
+                            send_reply(writer, reply.as_deref(), result).await?;
+                        }
+                    }
+                    Message::Other => {}
+                }
+            }
+            _ = poll.tick() => {
+                let mut batch = Vec::new();
+                {
+                    let mut outbox = outbox.lock().unwrap();
+                    if outbox.dropped > reported_dropped {
+                        eprintln!("⚠️ NATS outbox full, {} frame(s) dropped so far", outbox.dropped);
+                        reported_dropped = outbox.dropped;
+                    }
+                    let now = Instant::now();
+                    for pending in outbox.frames.iter_mut() {
+                        if pending.sent_at.is_some_and(|at| now - at < ack_timeout) {
+                            continue;
+                        }
+                        let frame = if config.jetstream {
+                            let headers = format!("NATS/1.0\r\nNats-Msg-Id: {}\r\n\r\n", pending.id);
+                            format!(
+                                "HPUB {} {}.{} {} {}\r\n{}{}\r\n",
+                                pending.subject,
+                                inbox,
+                                pending.seq,
+                                headers.len(),
+                                headers.len() + pending.payload.len(),
+                                headers,
+                                pending.payload
+                            )
+                        } else {
+                            format!("PUB {} {}\r\n{}\r\n", pending.subject, pending.payload.len(), pending.payload)
+                        };
+                        batch.push(frame);
+                        pending.sent_at = Some(now);
+                    }
+                    // Tanpa JetStream tidak ada ack, terkirim = selesai
+                    if !config.jetstream {
+                        outbox.frames.clear();
+                    }
+                }
+                if !batch.is_empty() {
+                    writer.write_all(batch.concat().as_bytes()).await?;
+                    writer.flush().await?;
+                }
+            }
+        }
+    }
+}
+
+/// PubAck `{"stream": .., "seq": ..}` or an error for the frame `seq`
+fn handle_ack(seq: &str, headers: &str, payload: &[u8], outbox: &Mutex<Outbox>) {
+    let Ok(seq) = seq.parse::<u64>() else { return };
+    // 503: tidak ada stream JetStream untuk subject ini
+    if headers.starts_with("NATS/1.0 503") {
+        eprintln!("❌ NATS: no JetStream stream captures the frames subject, frame dropped");
+        outbox.lock().unwrap().ack(seq);
+        return;
+    }
+    let ack: serde_json::Value = serde_json::from_slice(payload).unwrap_or_default();
+    if let Some(error) = ack.get("error") {
+        eprintln!("❌ NATS JetStream rejected a frame: {}", error);
+    }
+    outbox.lock().unwrap().ack(seq);
+}
+
+/// A message on the command subject: forward it to the device in the subject
+async fn command(config: &NatsConfig, state: &AppState, subject: &str, payload: &[u8]) -> Result<(), String> {
+    let token = config.device_token().and_then(|i| subject.split('.').nth(i)).ok_or("no device in subject")?;
+    let device = state
+        .devices
+        .snapshot()
+        .into_iter()
+        .map(|info| info.device)
+        .find(|device| subject_token(device) == token)
+        .ok_or_else(|| format!("unknown device '{}'", token))?;
+    let cmd = String::from_utf8_lossy(payload).trim().to_string();
+    if cmd.is_empty() {
+        return Err("empty command".to_string());
+    }
+    let cmd = format!("{}:{}", device, cmd);
+    println!("📥 NATS command: '{}'", cmd);
+    crate::gui_permission(&cmd, "nats", Some(&state.auth.nats()), state, false)?;
+    crate::gui_command(&cmd, "nats", state, None).await.map(|_| ())
+}
+
+async fn send_reply<W: AsyncWriteExt + Unpin>(writer: &mut W, reply: Option<&str>, body: serde_json::Value) -> std::io::Result<()> {
+    let Some(reply) = reply else { return Ok(()) };
+    let body = body.to_string();
+    writer.write_all(format!("PUB {} {}\r\n{}\r\n", reply, body.len(), body).as_bytes()).await
+}
+
+enum Message {
+    Ping,
+    Error(String),
+    Msg { subject: String, reply: Option<String>, headers: String, payload: Vec<u8> },
+    Other,
+}
+
+/// One server protocol message: MSG/HMSG with payload, PING, -ERR, or anything else
+async fn read_message<R: AsyncBufReadExt + Unpin>(reader: &mut R) -> anyhow::Result<Message> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        anyhow::bail!("closed by server");
+    }
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let (subject, reply, header_len, total_len) = match parts.as_slice() {
+        ["PING"] => return Ok(Message::Ping),
+        ["-ERR", ..] => return Ok(Message::Error(line.trim().trim_start_matches("-ERR").trim().to_string())),
+        ["MSG", subject, _sid, len] => (subject, None, 0, len.parse::<usize>()?),
+        ["MSG", subject, _sid, reply, len] => (subject, Some(reply), 0, len.parse::<usize>()?),
+        ["HMSG", subject, _sid, headers, len] => (subject, None, headers.parse::<usize>()?, len.parse::<usize>()?),
+        ["HMSG", subject, _sid, reply, headers, len] => (subject, Some(reply), headers.parse::<usize>()?, len.parse::<usize>()?),
+        _ => return Ok(Message::Other),
+    };
+    // Payload diikuti CRLF
+    let mut body = vec![0; total_len + 2];
+    reader.read_exact(&mut body).await?;
+    body.truncate(total_len);
+    let payload = body.split_off(header_len.min(total_len));
+    Ok(Message::Msg {
+        subject: subject.to_string(),
+        reply: reply.map(|r| r.to_string()),
+        headers: String::from_utf8_lossy(&body).into_owned(),
+        payload,
+    })
+}
@@ -0,0 +1,534 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::audit::AuditLog;
+use crate::devices::DeviceRegistry;
+use crate::jobs::JobContext;
+use crate::replay;
+use crate::store::LocalStore;
+use crate::{AppState, UnifiedSensorData, CHANNEL_NAMES};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NoiseFloorConfig {
+    /// Default quiet period when NOISE_FLOOR_START gives none
+    #[serde(default = "default_secs")]
+    pub secs: u64,
+    /// Sent first to put the rig in a quiet state (zero air, heater steady)
+    #[serde(default)]
+    pub quiet_commands: Vec<String>,
+    /// Sent when recording is over
+    #[serde(default)]
+    pub restore_commands: Vec<String>,
+    /// Wait after quiet_commands before recording
+    #[serde(default = "default_settle_secs")]
+    pub settle_secs: u64,
+    /// A cycle response counts towards its quality score at |peak| / RMS ≥ min_snr
+    #[serde(default = "default_min_snr")]
+    pub min_snr: f64,
+}
+
+fn default_secs() -> u64 { 300 }
+fn default_settle_secs() -> u64 { 10 }
+fn default_min_snr() -> f64 { 3.0 }
+
+impl Default for NoiseFloorConfig {
+    fn default() -> Self {
+        Self {
+            secs: default_secs(),
+            quiet_commands: Vec::new(),
+            restore_commands: Vec::new(),
+            settle_secs: default_settle_secs(),
+            min_snr: default_min_snr(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    noise_floor: NoiseFloorConfig,
+}
+
+impl NoiseFloorConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().noise_floor
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.secs == 0 {
+            return Err("noise_floor.secs must be at least 1".to_string());
+        }
+        if self.min_snr.is_nan() || self.min_snr <= 0.0 {
+            return Err("noise_floor.min_snr must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct AllanPoint {
+    pub tau_s: f64,
+    pub deviation: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChannelNoise {
+    pub samples: usize,
+    /// Quiet level
+    pub mean: f64,
+    /// Standard deviation around the linear trend of the period
+    pub rms: f64,
+    /// White-noise amplitude density, rms / √(sample_rate / 2), units/√Hz
+    pub density: f64,
+    /// Allan deviation at octave averaging times; falling = white noise,
+    /// rising = drift
+    pub allan: Vec<AllanPoint>,
+}
+
+/// Device characterization record, kept in the local store
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NoiseRecord {
+    pub device: String,
+    pub measured_at: i64,
+    pub duration_secs: f64,
+    /// From the median frame interval, Hz
+    pub sample_rate: f64,
+    pub channels: BTreeMap<String, ChannelNoise>,
+}
+
+struct AllanLevel {
+    tau: f64,
+    bin: i64,
+    sum: f64,
+    count: usize,
+    /// (bin, average) of the last complete bin
+    previous: Option<(i64, f64)>,
+    squares: f64,
+    pairs: usize,
+}
+
+/// Streaming Allan deviation at octaves τ0·2^k: samples (seconds, value)
+/// in time order are averaged over time bins anchored at the first one and
+/// adjacent complete bins are differenced, so gaps only drop pairs
+pub struct Allan {
+    start: Option<f64>,
+    levels: Vec<AllanLevel>,
+}
+
+impl Allan {
+    pub fn new(tau0: f64, max_tau: f64) -> Self {
+        let levels = (0..)
+            .map(|k| tau0 * 2f64.powi(k))
+            .take_while(|tau| *tau <= max_tau)
+            .map(|tau| AllanLevel { tau, bin: 0, sum: 0.0, count: 0, previous: None, squares: 0.0, pairs: 0 })
+            .collect();
+        Self { start: None, levels }
+    }
+
+    pub fn push(&mut self, t: f64, value: f64) {
+        let start = *self.start.get_or_insert(t);
+        for level in &mut self.levels {
+            // Epsilon: t dan τ kelipatan τ0 tidak boleh jatuh ke bin sebelumnya karena pembulatan
+            let bin = ((t - start) / level.tau + 1e-9).floor() as i64;
+            if bin != level.bin {
+                if level.count > 0 {
+                    let average = level.sum / level.count as f64;
+                    if let Some((_, previous)) = level.previous.filter(|(b, _)| *b == level.bin - 1) {
+                        level.squares += (average - previous).powi(2);
+                        level.pairs += 1;
+                    }
+                    level.previous = Some((level.bin, average));
+                }
+                level.bin = bin;
+                level.sum = 0.0;
+                level.count = 0;
+            }
+            level.sum += value;
+            level.count += 1;
+        }
+    }
+
+    /// Levels with at least one pair of adjacent complete bins
+    pub fn points(&self) -> Vec<AllanPoint> {
+        self.levels
+            .iter()
+            .filter(|level| level.pairs > 0)
+            .map(|level| AllanPoint { tau_s: level.tau, deviation: (level.squares / (2.0 * level.pairs as f64)).sqrt() })
+            .collect()
+    }
+}
+
+fn characterize(values: &[f64], sample_rate: f64) -> ChannelNoise {
+    let n = values.len();
+    let mean_t = (n - 1) as f64 / 2.0;
+    let mean = values.iter().sum::<f64>() / n as f64;
+    let (cov, var) = values.iter().enumerate().fold((0.0, 0.0), |(cov, var), (i, v)| {
+        (cov + (i as f64 - mean_t) * (v - mean), var + (i as f64 - mean_t).powi(2))
+    });
+    let slope = if var > 0.0 { cov / var } else { 0.0 };
+    let rms = (values.iter().enumerate().map(|(i, v)| (v - mean - slope * (i as f64 - mean_t)).powi(2)).sum::<f64>()
+        / n as f64)
+        .sqrt();
+
+    // Minimal 3 bin per τ
+    let mut allan = Allan::new(1.0 / sample_rate, (n / 3) as f64 / sample_rate);
+    for (i, value) in values.iter().enumerate() {
+        allan.push(i as f64 / sample_rate, *value);
+    }
+    ChannelNoise { samples: n, mean, rms, density: rms / (sample_rate / 2.0).sqrt(), allan: allan.points() }
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct Status {
+    device: String,
+    started_at: i64,
+    secs: u64,
+}
+
+// ================= Noise Floor =================
+/// Records a quiet period and characterizes each channel's noise (RMS,
+/// density, Allan deviation). The latest record per device scales alert
+/// inputs (`snr_<channel>`) and cycle quality scores.
+#[derive(Clone)]
+pub struct NoiseFloor {
+    config: Arc<NoiseFloorConfig>,
+    store: LocalStore,
+    /// Latest record per device
+    records: Arc<RwLock<HashMap<String, NoiseRecord>>>,
+    running: Arc<Mutex<Option<Status>>>,
+    cmd_tx: broadcast::Sender<String>,
+    data_tx: broadcast::Sender<String>,
+    frame_tx: broadcast::Sender<UnifiedSensorData>,
+    audit: AuditLog,
+    devices: DeviceRegistry,
+}
+
+impl NoiseFloor {
+    pub fn new(
+        config: NoiseFloorConfig,
+        store: LocalStore,
+        cmd_tx: broadcast::Sender<String>,
+        data_tx: broadcast::Sender<String>,
+        frame_tx: broadcast::Sender<UnifiedSensorData>,
+        audit: AuditLog,
+        devices: DeviceRegistry,
+    ) -> Self {
+        let records = match latest_records(&store) {
+            Ok(records) => records,
+            Err(e) => {
+                eprintln!("❌ Failed to load noise floor records: {}", e);
+                HashMap::new()
+            }
+        };
+        Self {
+            config: Arc::new(config),
+            store,
+            records: Arc::new(RwLock::new(records)),
+            running: Arc::new(Mutex::new(None)),
+            cmd_tx,
+            data_tx,
+            frame_tx,
+            audit,
+            devices,
+        }
+    }
+
+    /// Latest record per device
+    pub fn records(&self) -> BTreeMap<String, NoiseRecord> {
+        self.records.read().unwrap().iter().map(|(device, record)| (device.clone(), record.clone())).collect()
+    }
+
+    /// Distance from the quiet level in noise RMS of every characterized
+    /// value of a device's frame
+    pub fn snr<'a>(&self, device: &str, values: impl Iterator<Item = (&'a String, &'a f64)>) -> Vec<(String, f64)> {
+        let records = self.records.read().unwrap();
+        let Some(record) = records.get(device) else { return Vec::new() };
+        values
+            .filter_map(|(name, value)| {
+                let noise = record.channels.get(name).filter(|n| n.rms > 0.0)?;
+                Some((name.clone(), (value - noise.mean).abs() / noise.rms))
+            })
+            .collect()
+    }
+
+    /// Share of a cycle's channel responses (`<channel>.peak`) standing
+    /// min_snr above the noise; None without a record or responses
+    pub fn quality(&self, device: &str, features: &BTreeMap<String, f64>) -> Option<f64> {
+        let records = self.records.read().unwrap();
+        let record = records.get(device)?;
+        let ratios: Vec<f64> = record
+            .channels
+            .iter()
+            .filter(|(_, noise)| noise.rms > 0.0)
+            .filter_map(|(name, noise)| Some(features.get(&format!("{}.peak", name))?.abs() / noise.rms))
+            .collect();
+        if ratios.is_empty() {
+            return None;
+        }
+        let clear = ratios.iter().filter(|snr| **snr >= self.config.min_snr).count();
+        Some(clear as f64 / ratios.len() as f64)
+    }
+
+    pub fn start(&self, secs: Option<u64>, device: Option<String>, actor: &str) -> Result<(), String> {
+        let config = NoiseFloorConfig::load("config.toml");
+        // Device ditentukan sebelum quiet_commands dikirim
+        let status = Status {
+            device: self.devices.resolve(device.as_deref())?,
+            started_at: chrono::Utc::now().timestamp_millis(),
+            secs: secs.unwrap_or(config.secs),
+        };
+        if status.secs == 0 {
+            return Err("noise floor period must be positive".to_string());
+        }
+        {
+            let mut running = self.running.lock().unwrap();
+            if running.is_some() {
+                return Err("noise floor recording already running".to_string());
+            }
+            *running = Some(status.clone());
+        }
+        self.audit.record(actor, "noise_floor_start", serde_json::json!({ "secs": status.secs, "device": status.device }));
+
+        let noise = self.clone();
+        tokio::spawn(async move {
+            noise.run(config, status).await;
+            *noise.running.lock().unwrap() = None;
+        });
+        Ok(())
+    }
+
+    pub fn publish(&self) {
+        let event = match self.running.lock().unwrap().clone() {
+            Some(status) => serde_json::json!({
+                "type": "noise_floor",
+                "status": "running",
+                "device": status.device,
+                "started_at": status.started_at,
+                "secs": status.secs,
+            }),
+            None => serde_json::json!({ "type": "noise_floor", "status": "idle", "records": self.records() }),
+        };
+        let _ = self.data_tx.send(event.to_string());
+    }
+
+    async fn run(&self, config: NoiseFloorConfig, status: Status) {
+        println!("🤫 Recording noise floor of {} for {} s", status.device, status.secs);
+        for cmd in &config.quiet_commands {
+            let _ = self.cmd_tx.send(format!("{}:{}", status.device, cmd));
+        }
+        self.publish();
+        tokio::time::sleep(Duration::from_secs(config.settle_secs)).await;
+
+        let mut frame_rx = self.frame_tx.subscribe();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(status.secs);
+        let mut frames: Vec<(i64, [f32; 7])> = Vec::new();
+        loop {
+            match tokio::time::timeout_at(deadline, frame_rx.recv()).await {
+                Ok(Ok(frame)) if frame.device == status.device => {
+                    frames.push((frame.timestamp, frame.channels.values()));
+                }
+                Ok(Ok(_)) | Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+                Ok(Err(broadcast::error::RecvError::Closed)) | Err(_) => break,
+            }
+        }
+        for cmd in &config.restore_commands {
+            let _ = self.cmd_tx.send(format!("{}:{}", status.device, cmd));
+        }
+
+        let event = match evaluate(&status.device, &frames) {
+            Some(record) => {
+                println!("🤫 Noise floor of {} recorded from {} frame(s):", record.device, frames.len());
+                for (name, noise) in &record.channels {
+                    println!("   {}: rms {:.4}, {:.4}/√Hz", name, noise.rms, noise.density);
+                }
+                if let Err(e) = self.save(&record) {
+                    eprintln!("❌ Failed to store noise floor record: {}", e);
+                }
+                let mut event = serde_json::to_value(&record).unwrap_or_default();
+                event["type"] = "noise_floor".into();
+                event["status"] = "completed".into();
+                event
+            }
+            None => {
+                eprintln!("❌ Noise floor of {} aborted: not enough frames", status.device);
+                serde_json::json!({
+                    "type": "noise_floor",
+                    "status": "error",
+                    "device": status.device,
+                    "error": "not enough frames in the quiet period",
+                })
+            }
+        };
+        let _ = self.data_tx.send(event.to_string());
+    }
+
+    fn save(&self, record: &NoiseRecord) -> Result<(), String> {
+        let json = serde_json::to_string(record).map_err(|e| e.to_string())?;
+        self.store
+            .conn()
+            .execute(
+                "INSERT INTO characterizations (device, kind, measured_at, record) VALUES (?1, 'noise_floor', ?2, ?3)",
+                rusqlite::params![record.device, record.measured_at, json],
+            )
+            .map_err(|e| e.to_string())?;
+        self.records.write().unwrap().insert(record.device.clone(), record.clone());
+        Ok(())
+    }
+}
+
+fn evaluate(device: &str, frames: &[(i64, [f32; 7])]) -> Option<NoiseRecord> {
+    if frames.len() < 8 {
+        return None;
+    }
+    let mut intervals: Vec<i64> = frames.windows(2).map(|w| w[1].0 - w[0].0).collect();
+    intervals.sort_unstable();
+    let interval = intervals[intervals.len() / 2];
+    if interval <= 0 {
+        return None;
+    }
+    let sample_rate = 1000.0 / interval as f64;
+    // Kanal yang dimatikan (NaN) selama periode tidak dikarakterisasi
+    let channels = crate::CHANNEL_NAMES
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| frames.iter().all(|(_, values)| values[*i].is_finite()))
+        .map(|(i, name)| {
+            let values: Vec<f64> = frames.iter().map(|(_, values)| values[i] as f64).collect();
+            (name.to_string(), characterize(&values, sample_rate))
+        })
+        .collect();
+    Some(NoiseRecord {
+        device: device.to_string(),
+        measured_at: chrono::Utc::now().timestamp_millis(),
+        duration_secs: (frames[frames.len() - 1].0 - frames[0].0) as f64 / 1000.0,
+        sample_rate,
+        channels,
+    })
+}
+
+/// Allan curve of one channel over a stored range
+#[derive(Debug, Serialize, Clone)]
+pub struct AllanCurve {
+    pub channel: String,
+    pub samples: usize,
+    pub points: Vec<AllanPoint>,
+    /// Averaging time of the lowest deviation: longer averaging only adds drift
+    pub optimal_tau_s: Option<f64>,
+    pub minimum: Option<f64>,
+}
+
+fn median_interval(timestamps: &[i64]) -> Option<i64> {
+    let mut intervals: Vec<i64> = timestamps.windows(2).map(|w| w[1] - w[0]).filter(|dt| *dt > 0).collect();
+    intervals.sort_unstable();
+    intervals.get(intervals.len() / 2).copied()
+}
+
+// ================= Allan Stability =================
+/// Allan job: Allan deviation curves per channel over a stored range of
+/// `device`, streamed chunk by chunk, as allan.json and allan.csv (one row
+/// per τ). τ0 is the median frame interval of the first stored chunk.
+pub async fn stability(state: &AppState, job: &JobContext, device: &str, from: i64, to: i64) -> Result<(), String> {
+    let fields: Vec<String> = CHANNEL_NAMES.iter().map(|c| c.to_string()).collect();
+    let chunks = job.chunks(from, to);
+    let mut curves: Option<Vec<(Allan, usize)>> = None;
+    let mut tau0 = 0.0;
+    let mut first = None;
+    for (i, (start, stop)) in chunks.iter().enumerate() {
+        if job.cancelled() {
+            return Ok(());
+        }
+        let rows = replay::stored_rows(state, device, *start, *stop, Some(&fields)).await?;
+        if curves.is_none() {
+            let Some(interval) = median_interval(&rows.iter().map(|r| r.timestamp).collect::<Vec<_>>()) else { continue };
+            tau0 = interval as f64 / 1000.0;
+            let max_tau = (to - from) as f64 / 1000.0 / 3.0;
+            curves = Some(fields.iter().map(|_| (Allan::new(tau0, max_tau), 0)).collect());
+        }
+        let Some(curves) = curves.as_mut() else { continue };
+        for row in &rows {
+            let t = (row.timestamp - *first.get_or_insert(row.timestamp)) as f64 / 1000.0;
+            for (field, (allan, samples)) in fields.iter().zip(curves.iter_mut()) {
+                if let Some(value) = row.fields.get(field).filter(|v| v.is_finite()) {
+                    allan.push(t, *value);
+                    *samples += 1;
+                }
+            }
+        }
+        job.progress((i + 1) as f64 / chunks.len() as f64 * 0.9, format!("{} chunk(s) of {}", i + 1, chunks.len()));
+    }
+    let curves: Vec<AllanCurve> = curves
+        .ok_or_else(|| format!("not enough stored frames of {} in the range", device))?
+        .into_iter()
+        .zip(&fields)
+        .filter(|((_, samples), _)| *samples > 0)
+        .map(|((allan, samples), channel)| {
+            let points = allan.points();
+            let optimal = points.iter().min_by(|a, b| a.deviation.total_cmp(&b.deviation)).copied();
+            AllanCurve {
+                channel: channel.clone(),
+                samples,
+                points,
+                optimal_tau_s: optimal.map(|p| p.tau_s),
+                minimum: optimal.map(|p| p.deviation),
+            }
+        })
+        .collect();
+
+    let report = serde_json::json!({ "device": device, "from": from, "to": to, "tau0_s": tau0, "channels": curves });
+    job.write_artifact("allan.json", serde_json::to_string_pretty(&report).unwrap_or_default().as_bytes())?;
+    let mut taus: Vec<f64> = curves.iter().flat_map(|c| c.points.iter().map(|p| p.tau_s)).collect();
+    taus.sort_by(f64::total_cmp);
+    taus.dedup();
+    let mut csv = format!("tau_s,{}\n", curves.iter().map(|c| c.channel.as_str()).collect::<Vec<_>>().join(","));
+    for tau in taus {
+        let cells: Vec<String> = curves
+            .iter()
+            .map(|c| c.points.iter().find(|p| p.tau_s == tau).map(|p| p.deviation.to_string()).unwrap_or_default())
+            .collect();
+        csv += &format!("{},{}\n", tau, cells.join(","));
+    }
+    job.write_artifact("allan.csv", csv.as_bytes())?;
+    job.progress(1.0, format!("{} channel(s), τ0 {} s", curves.len(), tau0));
+    Ok(())
+}
+
+fn latest_records(store: &LocalStore) -> Result<HashMap<String, NoiseRecord>, String> {
+    let conn = store.conn();
+    let mut statement = conn
+        .prepare(
+            "SELECT record FROM characterizations
+             WHERE id IN (SELECT MAX(id) FROM characterizations WHERE kind = 'noise_floor' GROUP BY device)",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = statement.query_map([], |row| row.get::<_, String>(0)).map_err(|e| e.to_string())?;
+    let mut records = HashMap::new();
+    for row in rows {
+        let record: NoiseRecord = serde_json::from_str(&row.map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        records.insert(record.device.clone(), record);
+    }
+    Ok(records)
+}
+
+/// GUI commands (device defaults to the only connected one):
+///   NOISE_FLOOR_START [secs] [device]
+///   NOISE_FLOOR                        -> status and the latest record per device
+pub fn handle_command(noise: &NoiseFloor, client: &str, args: &[&str]) -> Result<(), String> {
+    match args {
+        ["NOISE_FLOOR_START", rest @ ..] if rest.len() <= 2 => {
+            let secs = rest
+                .first()
+                .map(|s| s.parse::<u64>().map_err(|_| format!("invalid number '{}'", s)))
+                .transpose()?;
+            noise.start(secs, rest.get(1).map(|d| d.to_string()), client)
+        }
+        ["NOISE_FLOOR"] => {
+            noise.publish();
+            Ok(())
+        }
+        _ => Err("usage: NOISE_FLOOR_START [secs] [device] | NOISE_FLOOR".to_string()),
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct PidGains {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+}
+
+// ================= PID Controller =================
+/// Discrete PID with output clamping and anti-windup
+#[derive(Debug, Clone)]
+pub struct Pid {
+    gains: PidGains,
+    output_min: f32,
+    output_max: f32,
+    integral: f32,
+    last_error: Option<f32>,
+}
+
+impl Pid {
+    pub fn new(gains: PidGains, output_min: f32, output_max: f32) -> Self {
+        Self {
+            gains,
+            output_min,
+            output_max,
+            integral: 0.0,
+            last_error: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.last_error = None;
+    }
+
+    /// Compute the next output for `measured` after `dt` seconds
+    pub fn update(&mut self, setpoint: f32, measured: f32, dt: f32) -> f32 {
+        let error = setpoint - measured;
+        let derivative = match self.last_error {
+            Some(last) if dt > 0.0 => (error - last) / dt,
+            _ => 0.0,
+        };
+        self.last_error = Some(error);
+
+        let candidate = self.integral + error * dt;
+        let output = self.gains.kp * error + self.gains.ki * candidate + self.gains.kd * derivative;
+
+        // Anti-windup: hanya integrasi jika output tidak saturasi
+        if (self.output_min..=self.output_max).contains(&output) {
+            self.integral = candidate;
+        }
+        output.clamp(self.output_min, self.output_max)
+    }
+}
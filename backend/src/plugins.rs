@@ -0,0 +1,164 @@
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use wasmtime::{Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+use crate::filtering::UnifiedSensorRaw;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PluginConfig {
+    /// Compiled .wasm modules, applied in order to every raw frame
+    #[serde(default)]
+    pub wasm: Vec<String>,
+    /// Instruction budget per plugin call (runaway plugins are stopped)
+    #[serde(default = "default_fuel")]
+    pub fuel: u64,
+    /// Linear memory a plugin may grow to (MiB); growing past it fails the call
+    #[serde(default = "default_max_memory_mb")]
+    pub max_memory_mb: usize,
+}
+
+fn default_fuel() -> u64 { 10_000_000 }
+fn default_max_memory_mb() -> usize { 64 }
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self { wasm: Vec::new(), fuel: default_fuel(), max_memory_mb: default_max_memory_mb() }
+    }
+}
+
+/// Minimum gap between error lines of one plugin; failures in between are counted
+const ERROR_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    plugins: PluginConfig,
+}
+
+impl PluginConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc)
+            .try_into::<ConfigFile>()
+            .unwrap_or_default()
+            .plugins
+    }
+}
+
+/// One instantiated module. ABI (no imports are provided):
+///   memory                         exported linear memory
+///   alloc(len: i32) -> i32         buffer for the input frame
+///   process(ptr: i32, len: i32) -> i64
+///       input is the raw frame as JSON, result is (out_ptr << 32) | out_len
+///       pointing at the output JSON; keys it omits are left unchanged
+struct Plugin {
+    path: String,
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    process: TypedFunc<(i32, i32), i64>,
+    /// Last error line printed and failures since then
+    last_error_log: Option<Instant>,
+    suppressed: u64,
+}
+
+impl Plugin {
+    fn load(engine: &Engine, path: &str, config: &PluginConfig) -> anyhow::Result<Self> {
+        let module = Module::from_file(engine, path)?;
+        let limits = StoreLimitsBuilder::new().memory_size(config.max_memory_mb * 1024 * 1024).build();
+        let mut store = Store::new(engine, limits);
+        store.limiter(|limits| limits);
+        // Start function juga dibatasi fuel, sama seperti setiap call
+        store.set_fuel(config.fuel)?;
+        let instance = Instance::new(&mut store, &module, &[])?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("module does not export memory"))?;
+        let alloc = instance.get_typed_func(&mut store, "alloc").context("alloc")?;
+        let process = instance.get_typed_func(&mut store, "process").context("process")?;
+        Ok(Self { path: path.to_string(), store, memory, alloc, process, last_error_log: None, suppressed: 0 })
+    }
+
+    /// Print a failure, at most once per ERROR_LOG_INTERVAL
+    fn report_error(&mut self, e: &anyhow::Error) {
+        if self.last_error_log.is_some_and(|last| last.elapsed() < ERROR_LOG_INTERVAL) {
+            self.suppressed += 1;
+            return;
+        }
+        if self.suppressed > 0 {
+            eprintln!("⚠️ WASM plugin {}: {:#} ({} more failure(s) since last report)", self.path, e, self.suppressed);
+        } else {
+            eprintln!("⚠️ WASM plugin {}: {:#}", self.path, e);
+        }
+        self.last_error_log = Some(Instant::now());
+        self.suppressed = 0;
+    }
+
+    fn call(&mut self, input: &[u8], fuel: u64) -> anyhow::Result<Vec<u8>> {
+        self.store.set_fuel(fuel)?;
+        let ptr = self.alloc.call(&mut self.store, input.len() as i32)?;
+        self.memory.write(&mut self.store, ptr as usize, input)?;
+
+        let packed = self.process.call(&mut self.store, (ptr, input.len() as i32))? as u64;
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        let mut output = vec![0u8; out_len];
+        self.memory.read(&self.store, out_ptr, &mut output)?;
+        Ok(output)
+    }
+}
+
+// ================= WASM Pre-processing =================
+/// Per-lab `process(frame) -> frame` transformations applied to raw
+/// frames before filtering. A failing plugin is skipped for that frame.
+pub struct WasmPlugins {
+    plugins: Vec<Plugin>,
+    fuel: u64,
+}
+
+impl WasmPlugins {
+    pub fn new(config: &PluginConfig) -> Self {
+        let mut plugins = Vec::new();
+        if !config.wasm.is_empty() {
+            let mut engine_config = wasmtime::Config::new();
+            engine_config.consume_fuel(true);
+            match Engine::new(&engine_config) {
+                Ok(engine) => {
+                    for path in &config.wasm {
+                        match Plugin::load(&engine, path, config) {
+                            Ok(plugin) => {
+                                println!("🧩 WASM plugin loaded: {}", path);
+                                plugins.push(plugin);
+                            }
+                            Err(e) => eprintln!("❌ WASM plugin {} failed to load: {:#}", path, e),
+                        }
+                    }
+                }
+                Err(e) => eprintln!("❌ WASM engine unavailable: {}", e),
+            }
+        }
+        Self { plugins, fuel: config.fuel }
+    }
+
+    pub fn process(&mut self, raw: &mut UnifiedSensorRaw) {
+        for plugin in self.plugins.iter_mut() {
+            let mut frame = serde_json::to_value(&*raw).unwrap_or_default();
+            let result = plugin
+                .call(frame.to_string().as_bytes(), self.fuel)
+                .and_then(|output| Ok(serde_json::from_slice::<serde_json::Value>(&output)?))
+                .and_then(|output| {
+                    let Some(fields) = output.as_object() else {
+                        return Err(anyhow!("output is not a JSON object"));
+                    };
+                    for (key, value) in fields {
+                        frame[key] = value.clone();
+                    }
+                    Ok(serde_json::from_value::<UnifiedSensorRaw>(frame)?)
+                });
+            match result {
+                Ok(processed) => *raw = processed,
+                Err(e) => plugin.report_error(&e),
+            }
+        }
+    }
+}
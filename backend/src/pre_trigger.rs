@@ -0,0 +1,182 @@
+use influxdb2::models::DataPoint;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::alerts::{AlertEngine, AlertState};
+use crate::capture::value_name;
+use crate::clock::SharedClock;
+use crate::influxdb::InfluxDBHandler;
+use crate::recording::file_safe;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PreTriggerConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Raw frames kept per device before an alarm
+    #[serde(default = "default_secs")]
+    pub secs: u64,
+    /// Cap per device, whatever the frame rate
+    #[serde(default = "default_max_frames")]
+    pub max_frames: usize,
+    /// One CSV per alarm and device: <dir>/<time>_<device>_<reason>.csv
+    #[serde(default = "default_dir")]
+    pub dir: String,
+    /// Also write the frames to InfluxDB as "pre_trigger" points
+    #[serde(default = "default_true")]
+    pub influx: bool,
+}
+
+fn default_secs() -> u64 { 30 }
+fn default_max_frames() -> usize { 20_000 }
+fn default_dir() -> String { "incidents".to_string() }
+fn default_true() -> bool { true }
+
+impl Default for PreTriggerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            secs: default_secs(),
+            max_frames: default_max_frames(),
+            dir: default_dir(),
+            influx: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    pre_trigger: PreTriggerConfig,
+}
+
+impl PreTriggerConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().pre_trigger
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && (self.secs == 0 || self.max_frames == 0) {
+            return Err("pre_trigger.secs and pre_trigger.max_frames must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// (epoch ms, SENSOR values as parsed)
+type RawFrame = (i64, Vec<f32>);
+
+#[derive(Default)]
+struct DeviceBuffer {
+    frames: VecDeque<RawFrame>,
+    /// Newest frame already flushed; a later alarm does not repeat it
+    flushed_until: i64,
+}
+
+// ================= Pre-Trigger Buffer =================
+/// Full-rate circular buffer of raw SENSOR values per device. When an
+/// alarm fires, the frames before it are flushed to an incident CSV (and
+/// InfluxDB) at full resolution, whatever adaptive storage kept.
+#[derive(Clone, Default)]
+pub struct PreTrigger {
+    config: Arc<PreTriggerConfig>,
+    devices: Arc<Mutex<HashMap<String, DeviceBuffer>>>,
+}
+
+impl PreTrigger {
+    pub fn new(config: PreTriggerConfig) -> Self {
+        Self { config: Arc::new(config), ..Default::default() }
+    }
+
+    pub fn record(&self, device: &str, timestamp: i64, values: &[f32]) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut devices = self.devices.lock().unwrap();
+        let buffer = devices.entry(device.to_string()).or_default();
+        buffer.frames.push_back((timestamp, values.to_vec()));
+        let horizon = timestamp - self.config.secs as i64 * 1000;
+        while buffer.frames.len() > self.config.max_frames || buffer.frames.front().is_some_and(|(at, _)| *at < horizon) {
+            buffer.frames.pop_front();
+        }
+    }
+
+    /// Flush the buffered frames of `device` (None = every device) up to `timestamp`
+    pub async fn trigger(&self, influx: &InfluxDBHandler, reason: &str, device: Option<&str>, timestamp: i64) {
+        if !self.config.enabled {
+            return;
+        }
+        let flushed: Vec<(String, Vec<RawFrame>)> = {
+            let mut devices = self.devices.lock().unwrap();
+            devices
+                .iter_mut()
+                .filter(|(name, _)| device.is_none_or(|d| d == name.as_str()))
+                .map(|(name, buffer)| {
+                    let since = buffer.flushed_until;
+                    let frames: Vec<_> =
+                        buffer.frames.iter().filter(|(at, _)| *at > since && *at <= timestamp).cloned().collect();
+                    buffer.flushed_until = frames.last().map_or(since, |(at, _)| *at);
+                    (name.clone(), frames)
+                })
+                .filter(|(_, frames)| !frames.is_empty())
+                .collect()
+        };
+        for (device, frames) in flushed {
+            match self.archive(&device, reason, timestamp, &frames) {
+                Ok(path) => println!("🧷 {} pre-trigger frames of {} saved to {} ({})", frames.len(), device, path.display(), reason),
+                Err(e) => eprintln!("❌ Failed to write pre-trigger archive for {}: {}", device, e),
+            }
+            if self.config.influx {
+                let points = frames.iter().filter_map(|(at, values)| point(&device, reason, *at, values)).collect();
+                if let Err(e) = influx.write_points(points).await {
+                    eprintln!("❌ Pre-trigger write error: {:?}", e);
+                }
+            }
+        }
+    }
+
+    fn archive(&self, device: &str, reason: &str, timestamp: i64, frames: &[RawFrame]) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(&self.config.dir)?;
+        let time = chrono::DateTime::from_timestamp_millis(timestamp).unwrap_or_default().format("%Y%m%dT%H%M%S");
+        let path = PathBuf::from(&self.config.dir).join(format!("{}_{}_{}.csv", time, file_safe(device), file_safe(reason)));
+        let width = frames.iter().map(|(_, values)| values.len()).max().unwrap_or(0);
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+        let mut header = vec!["epoch_ms".to_string()];
+        header.extend((0..width).map(value_name));
+        writeln!(file, "{}", header.join(","))?;
+        for (at, values) in frames {
+            let cells: Vec<String> = values.iter().map(|v| if v.is_finite() { v.to_string() } else { String::new() }).collect();
+            writeln!(file, "{},{}", at, cells.join(","))?;
+        }
+        file.flush()?;
+        Ok(path)
+    }
+}
+
+/// One raw frame as a "pre_trigger" point, tagged with the alarm
+fn point(device: &str, reason: &str, timestamp: i64, values: &[f32]) -> Option<DataPoint> {
+    let mut builder = DataPoint::builder("pre_trigger").tag("device", device).tag("reason", reason);
+    for (i, value) in values.iter().enumerate().filter(|(_, v)| v.is_finite()) {
+        builder = builder.field(value_name(i), *value as f64);
+    }
+    builder.timestamp(timestamp * 1_000_000).build().ok()
+}
+
+/// Firing alert rules flush every device's buffer
+pub async fn watch_alerts(pre_trigger: PreTrigger, alerts: AlertEngine, influx: InfluxDBHandler, clock: SharedClock) {
+    let mut events = alerts.subscribe();
+    loop {
+        match events.recv().await {
+            Ok(event) if event.state == AlertState::Firing => {
+                pre_trigger.trigger(&influx, &format!("alert {}", event.name), None, clock.now_millis()).await
+            }
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
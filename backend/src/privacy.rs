@@ -0,0 +1,127 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PrivacyConfig {
+    /// GUI client IPs that only ever receive normalized values
+    #[serde(default)]
+    pub clients: Vec<String>,
+    /// Samples in the rolling min/max window used for normalization
+    #[serde(default = "default_window")]
+    pub window: usize,
+    /// Event types passed through unchanged; every other event is dropped
+    #[serde(default = "default_allow_types")]
+    pub allow_types: Vec<String>,
+}
+
+fn default_window() -> usize { 300 }
+fn default_allow_types() -> Vec<String> {
+    vec!["trigger".to_string(), "video_sync".to_string()]
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            clients: Vec::new(),
+            window: default_window(),
+            allow_types: default_allow_types(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    privacy: PrivacyConfig,
+}
+
+impl PrivacyConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().privacy
+    }
+
+    pub fn applies_to(&self, addr: &SocketAddr) -> bool {
+        self.clients.contains(&addr.ip().to_string())
+    }
+}
+
+/// Frame fields that are not concentrations and pass through as-is
+const PASS_FIELDS: [&str; 8] = [
+    "state", "level", "timestamp", "flow", "pressure", "humidity", "heater_temp", "heater_setpoint",
+];
+
+/// Calibrated concentrations that cannot be normalized meaningfully
+const DROP_FIELDS: [&str; 2] = ["target_ppm", "delivered_ppm"];
+
+// ================= Redactor =================
+/// Per-connection filter for demo clients: sensor frames are rescaled to
+/// 0..1 over a rolling window, other events are dropped unless allowed
+pub struct Redactor {
+    window: usize,
+    allow_types: Vec<String>,
+    history: HashMap<String, VecDeque<f64>>,
+}
+
+impl Redactor {
+    pub fn new(config: &PrivacyConfig) -> Self {
+        Self {
+            window: config.window.max(2),
+            allow_types: config.allow_types.clone(),
+            history: HashMap::new(),
+        }
+    }
+
+    /// Redacted message, or None if it must not reach the client
+    pub fn redact(&mut self, msg: &str) -> Option<String> {
+        let Ok(Value::Object(mut object)) = serde_json::from_str::<Value>(msg) else {
+            return None;
+        };
+        match object.get("type").and_then(Value::as_str) {
+            // Frame sensor tidak punya field "type"
+            None => {
+                for field in DROP_FIELDS {
+                    object.remove(field);
+                }
+                for (key, value) in object.iter_mut() {
+                    if PASS_FIELDS.contains(&key.as_str()) {
+                        continue;
+                    }
+                    if let Some(v) = value.as_f64() {
+                        *value = self.normalize(key, v).into();
+                    }
+                }
+            }
+            Some("channel_metadata") => {
+                let channels = object.get_mut("channels").and_then(Value::as_array_mut)?;
+                for channel in channels.iter_mut().filter(|c| c["kind"] != "auxiliary") {
+                    channel["unit"] = "normalized".into();
+                    channel["display_min"] = 0.0.into();
+                    channel["display_max"] = 1.0.into();
+                    channel["bands"] = Value::Array(Vec::new());
+                }
+            }
+            Some(t) if self.allow_types.iter().any(|a| a == t) => {}
+            Some(_) => return None,
+        }
+        Some(Value::Object(object).to_string())
+    }
+
+    fn normalize(&mut self, key: &str, value: f64) -> f64 {
+        let history = self.history.entry(key.to_string()).or_default();
+        history.push_back(value);
+        while history.len() > self.window {
+            history.pop_front();
+        }
+        let (min, max) = history
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+        if max <= min {
+            0.5
+        } else {
+            (value - min) / (max - min)
+        }
+    }
+}
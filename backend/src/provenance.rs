@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::calibration::CalibrationCurve;
+
+/// `[calibration]`, resolved per device since every sensor unit is
+/// calibrated separately
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CalibrationConfig {
+    /// Label of the active calibration, bump after recalibrating
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Clean-air channel baselines, written by a completed burn-in
+    #[serde(default)]
+    pub baseline: Option<[f32; 7]>,
+    /// Signal → ppm per channel; frames get `<channel>_ppm` fields
+    #[serde(default)]
+    pub curves: BTreeMap<String, CalibrationCurve>,
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    calibration: CalibrationConfig,
+}
+
+impl CalibrationConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc)
+            .try_into::<ConfigFile>()
+            .unwrap_or_default()
+            .calibration
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        crate::calibration::validate_curves(&self.curves)
+    }
+}
+
+/// Stable short hash (FNV-1a 64) for config fingerprints
+pub fn fingerprint(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+// ================= Provenance =================
+/// Processing context stored with every point, so data can be traced to
+/// the exact firmware/backend/config that produced it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Provenance {
+    pub backend_version: String,
+    /// Announced by the firmware with a `FW:<version>` line
+    pub firmware_version: Option<String>,
+    /// Sensor board serial, announced with a `BOARD:<serial>` line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub board_id: Option<String>,
+    /// Fingerprint of the device's resolved filter settings
+    pub filter_hash: String,
+    /// config.toml revision (see CONFIG_VERSIONS)
+    pub config_version: u64,
+    pub calibration_version: Option<String>,
+    /// Active model registry version (`<name>@v<N>`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_version: Option<String>,
+}
+
+impl Provenance {
+    pub fn new(filter_hash: &str, config_version: u64, calibration: &CalibrationConfig) -> Self {
+        Self {
+            backend_version: env!("CARGO_PKG_VERSION").to_string(),
+            firmware_version: None,
+            board_id: None,
+            filter_hash: filter_hash.to_string(),
+            config_version,
+            calibration_version: calibration.version.clone(),
+            model_version: None,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use influxdb2::models::DataPoint;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::channels::{Channel, Channels};
+
+/// `RANGE:<code>,<code>,...` in CHANNEL_NAMES order, sent by firmware with
+/// switchable gain on connect and after every switch; an empty entry or `-`
+/// means the channel has no ranges
+pub const RANGE_PREFIX: &str = "RANGE:";
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RangeConfig {
+    /// Range code → factor applied to raw values, e.g. `{ "0" = 1.0, "1" = 10.0 }`
+    #[serde(default)]
+    pub scale: BTreeMap<String, f32>,
+    /// Per-channel factors, replacing `scale` for that channel
+    #[serde(default)]
+    pub channels: BTreeMap<String, BTreeMap<String, f32>>,
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    ranges: RangeConfig,
+}
+
+impl RangeConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc).try_into::<ConfigFile>().unwrap_or_default().ranges
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = self.channels.keys().find(|name| Channel::from_name(name).is_none()) {
+            return Err(format!("ranges.channels: unknown channel '{}'", name));
+        }
+        for scale in self.channels.values().chain([&self.scale]) {
+            if let Some((code, factor)) = scale.iter().find(|(c, f)| c.parse::<u8>().is_err() || !f.is_finite() || **f <= 0.0) {
+                return Err(format!("ranges: '{}' = {} needs a code 0-255 and a positive factor", code, factor));
+            }
+        }
+        Ok(())
+    }
+
+    fn factor(&self, channel: Channel, code: u8) -> Option<f32> {
+        let scale = self.channels.get(channel.name()).unwrap_or(&self.scale);
+        scale.get(&code.to_string()).copied()
+    }
+}
+
+// ================= Gain Ranges =================
+/// Per device: the range each channel was last reported in. Raw values are
+/// scaled by the range's factor so readings stay comparable across switches;
+/// frames carry the codes (`ranges`, stored as `<channel>_range` fields).
+pub struct GainRanges {
+    config: RangeConfig,
+    codes: [Option<u8>; 7],
+}
+
+impl GainRanges {
+    pub fn new(config: RangeConfig) -> Self {
+        Self { config, codes: [None; 7] }
+    }
+
+    /// New factors from a config reload; the reported codes are kept
+    pub fn reload(&mut self, config: RangeConfig) {
+        self.config = config;
+    }
+
+    /// A RANGE: line from the device; returns (channel, previous, code) per switch
+    pub fn report(&mut self, device: &str, data: &str) -> Vec<(Channel, Option<u8>, Option<u8>)> {
+        let mut changes = Vec::new();
+        for (channel, entry) in Channel::ALL.into_iter().zip(data.split(',').map(str::trim)) {
+            let code = match entry {
+                "" | "-" => None,
+                entry => match entry.parse::<u8>() {
+                    Ok(code) => Some(code),
+                    Err(_) => {
+                        eprintln!("⚠️ {} RANGE entry '{}' for {} ignored", device, entry, channel.name());
+                        continue;
+                    }
+                },
+            };
+            if let Some(code) = code.filter(|code| self.config.factor(channel, *code).is_none()) {
+                eprintln!("⚠️ {} {} range {} has no factor in [ranges], values left unscaled", device, channel.name(), code);
+            }
+            let previous = std::mem::replace(&mut self.codes[channel.index()], code);
+            if previous != code {
+                changes.push((channel, previous, code));
+            }
+        }
+        changes
+    }
+
+    /// Scale raw values by their channel's range factor
+    pub fn apply(&self, channels: &mut Channels) {
+        for channel in Channel::ALL {
+            let factor = self.codes[channel.index()].and_then(|code| self.config.factor(channel, code));
+            if let Some(factor) = factor {
+                channels[channel] *= factor;
+            }
+        }
+    }
+
+    /// Current code per ranged channel, for the frame
+    pub fn codes(&self) -> BTreeMap<String, u8> {
+        Channel::ALL
+            .iter()
+            .filter_map(|c| Some((c.name().to_string(), self.codes[c.index()]?)))
+            .collect()
+    }
+}
+
+/// "range_change" point for a range the firmware reported switching to
+pub fn change_point(device: &str, channel: Channel, code: u8, timestamp: i64) -> Option<DataPoint> {
+    DataPoint::builder("range_change")
+        .tag("device", device)
+        .tag("channel", channel.name())
+        .field("code", code as i64)
+        .timestamp(timestamp * 1_000_000)
+        .build()
+        .ok()
+}
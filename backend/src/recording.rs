@@ -0,0 +1,320 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::audit::AuditLog;
+use crate::filtering::FilterConfig;
+use crate::frame_schema::FRAME_SCHEMA;
+use crate::{UnifiedSensorData, CHANNEL_NAMES};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RecordingConfig {
+    /// One sub-directory per recording session
+    #[serde(default = "default_dir")]
+    pub dir: String,
+    /// Also write each segment as a JSON array of frames
+    #[serde(default)]
+    pub json: bool,
+}
+
+fn default_dir() -> String { "recordings".to_string() }
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self { dir: default_dir(), json: false }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    recording: RecordingConfig,
+}
+
+impl RecordingConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().recording
+    }
+}
+
+/// One uninterrupted run of a single FSM state on one device
+#[derive(Debug, Serialize, Clone)]
+struct Segment {
+    file: String,
+    device: String,
+    state: String,
+    level: i32,
+    started_at: i64,
+    ended_at: i64,
+    rows: usize,
+}
+
+struct SegmentWriter {
+    segment: Segment,
+    state: i32,
+    csv: BufWriter<File>,
+    frames: Option<Vec<UnifiedSensorData>>,
+}
+
+struct Recording {
+    id: String,
+    label: String,
+    actor: String,
+    dir: PathBuf,
+    started_at: i64,
+    levels: BTreeSet<i32>,
+    segments: Vec<Segment>,
+    /// Open segment per device
+    open: HashMap<String, SegmentWriter>,
+}
+
+// ================= Session Recording =================
+/// REC_START/REC_STOP capture every verified frame into a dataset directory:
+/// one CSV per state segment (PRE_COND, RAMP_UP, ...) and a metadata.json
+/// with label, gas levels, filter config and start/end times
+#[derive(Clone)]
+pub struct Recorder {
+    config: Arc<RecordingConfig>,
+    config_path: Arc<String>,
+    active: Arc<Mutex<Option<Recording>>>,
+    audit: AuditLog,
+    data_tx: broadcast::Sender<String>,
+}
+
+impl Recorder {
+    pub fn new(config_path: &str, audit: AuditLog, data_tx: broadcast::Sender<String>) -> Self {
+        Self {
+            config: Arc::new(RecordingConfig::load(config_path)),
+            config_path: Arc::new(config_path.to_string()),
+            active: Arc::new(Mutex::new(None)),
+            audit,
+            data_tx,
+        }
+    }
+
+    pub fn start(&self, label: &str, actor: &str) -> Result<(), String> {
+        let mut active = self.active.lock().unwrap();
+        if let Some(recording) = active.as_ref() {
+            return Err(format!("already recording '{}'", recording.label));
+        }
+        let now = chrono::Local::now();
+        let id = now.format("%Y%m%d-%H%M%S").to_string();
+        let dir = PathBuf::from(&self.config.dir).join(format!("{}_{}", id, file_safe(label)));
+        std::fs::create_dir_all(&dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+        *active = Some(Recording {
+            id,
+            label: label.to_string(),
+            actor: actor.to_string(),
+            dir: dir.clone(),
+            started_at: now.timestamp_millis(),
+            levels: BTreeSet::new(),
+            segments: Vec::new(),
+            open: HashMap::new(),
+        });
+        drop(active);
+        println!("⏺️ Recording '{}' into {}", label, dir.display());
+        self.audit.record(actor, "recording_start", serde_json::json!({ "label": label, "dir": dir }));
+        self.publish();
+        Ok(())
+    }
+
+    pub fn stop(&self, actor: &str) -> Result<(), String> {
+        let mut recording = self.active.lock().unwrap().take().ok_or("not recording")?;
+        let devices: Vec<String> = recording.open.keys().cloned().collect();
+        for device in devices {
+            self.close_segment(&mut recording, &device);
+        }
+        let ended_at = chrono::Utc::now().timestamp_millis();
+        self.write_metadata(&recording, Some(ended_at));
+        let rows: usize = recording.segments.iter().map(|s| s.rows).sum();
+        println!(
+            "⏹️ Recording '{}' stopped: {} segment(s), {} row(s) in {}",
+            recording.label,
+            recording.segments.len(),
+            rows,
+            recording.dir.display()
+        );
+        self.audit.record(
+            actor,
+            "recording_stop",
+            serde_json::json!({ "label": recording.label, "dir": recording.dir, "segments": recording.segments.len(), "rows": rows }),
+        );
+        self.publish();
+        Ok(())
+    }
+
+    /// Append a frame, starting a new segment when the device's state changes
+    fn observe(&self, frame: &UnifiedSensorData) {
+        let new_segment = {
+            let mut active = self.active.lock().unwrap();
+            let Some(recording) = active.as_mut() else { return };
+            if recording.open.get(&frame.device).is_some_and(|w| w.state != frame.state) {
+                self.close_segment(recording, &frame.device);
+            }
+            let new_segment = !recording.open.contains_key(&frame.device);
+            if new_segment {
+                match self.open_segment(recording, frame) {
+                    Ok(writer) => {
+                        recording.open.insert(frame.device.clone(), writer);
+                    }
+                    Err(e) => {
+                        eprintln!("❌ Failed to open recording segment: {}", e);
+                        return;
+                    }
+                }
+            }
+            recording.levels.insert(frame.level);
+            if let Some(writer) = recording.open.get_mut(&frame.device) {
+                if let Err(e) = writeln!(writer.csv, "{}", csv_row(frame)) {
+                    eprintln!("❌ Failed to write recording: {}", e);
+                }
+                writer.segment.rows += 1;
+                writer.segment.ended_at = frame.timestamp;
+                if let Some(frames) = writer.frames.as_mut() {
+                    frames.push(frame.clone());
+                }
+            }
+            new_segment
+        };
+        if new_segment {
+            self.publish();
+        }
+    }
+
+    fn open_segment(&self, recording: &Recording, frame: &UnifiedSensorData) -> std::io::Result<SegmentWriter> {
+        let index = recording.segments.len() + recording.open.len() + 1;
+        let mut name = format!("{:03}_{}", index, frame.state_name);
+        if !frame.device.is_empty() {
+            name = format!("{}_{}", name, file_safe(&frame.device));
+        }
+        let file = format!("{}.csv", name);
+        let mut csv = BufWriter::new(File::create(recording.dir.join(&file))?);
+        writeln!(csv, "{}", csv_header())?;
+        Ok(SegmentWriter {
+            segment: Segment {
+                file,
+                device: frame.device.clone(),
+                state: frame.state_name.clone(),
+                level: frame.level,
+                started_at: frame.timestamp,
+                ended_at: frame.timestamp,
+                rows: 0,
+            },
+            state: frame.state,
+            csv,
+            frames: self.config.json.then(Vec::new),
+        })
+    }
+
+    fn close_segment(&self, recording: &mut Recording, device: &str) {
+        let Some(mut writer) = recording.open.remove(device) else { return };
+        if let Err(e) = writer.csv.flush() {
+            eprintln!("❌ Failed to write recording: {}", e);
+        }
+        if let Some(frames) = writer.frames.take() {
+            let path = recording.dir.join(writer.segment.file.replace(".csv", ".json"));
+            let result = serde_json::to_string_pretty(&frames)
+                .map_err(std::io::Error::from)
+                .and_then(|json| std::fs::write(&path, json));
+            if let Err(e) = result {
+                eprintln!("❌ Failed to write {}: {}", path.display(), e);
+            }
+        }
+        recording.segments.push(writer.segment);
+        // Metadata ditulis ulang tiap segmen selesai, supaya crash tidak menghilangkan semuanya
+        self.write_metadata(recording, None);
+    }
+
+    fn write_metadata(&self, recording: &Recording, ended_at: Option<i64>) {
+        let metadata = serde_json::json!({
+            "id": recording.id,
+            "label": recording.label,
+            "started_by": recording.actor,
+            "started_at": recording.started_at,
+            "ended_at": ended_at,
+            "levels": recording.levels,
+            "channels": CHANNEL_NAMES,
+            "frame_schema": FRAME_SCHEMA,
+            "filter": FilterConfig::load(&self.config_path, None),
+            "segments": recording.segments,
+        });
+        let path = recording.dir.join("metadata.json");
+        if let Err(e) = std::fs::write(&path, serde_json::to_string_pretty(&metadata).unwrap_or_default()) {
+            eprintln!("❌ Failed to write {}: {}", path.display(), e);
+        }
+    }
+
+    /// "recording" GUI event
+    pub fn publish(&self) {
+        let active = self.active.lock().unwrap();
+        let event = match active.as_ref() {
+            Some(recording) => serde_json::json!({
+                "type": "recording",
+                "status": "recording",
+                "label": recording.label,
+                "dir": recording.dir,
+                "started_at": recording.started_at,
+                "segments": recording.segments.len() + recording.open.len(),
+                "states": recording.open.values().map(|w| &w.segment.state).collect::<Vec<_>>(),
+            }),
+            None => serde_json::json!({ "type": "recording", "status": "idle" }),
+        };
+        let _ = self.data_tx.send(event.to_string());
+    }
+}
+
+pub fn file_safe(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
+
+fn csv_header() -> String {
+    let mut columns = vec!["timestamp", "epoch_ms", "device", "state", "level"];
+    columns.extend(CHANNEL_NAMES);
+    columns.extend(["flow", "pressure", "humidity", "heater_temp", "target_ppm", "delivered_ppm", "cycle_valid"]);
+    columns.join(",")
+}
+
+fn csv_row(frame: &UnifiedSensorData) -> String {
+    // Kanal yang dimatikan (NaN) dan nilai yang tidak ada ditulis sebagai sel kosong
+    let value = |v: Option<f32>| v.filter(|v| v.is_finite()).map(|v| v.to_string()).unwrap_or_default();
+    let timestamp = chrono::DateTime::from_timestamp_millis(frame.timestamp).map(|t| t.to_rfc3339()).unwrap_or_default();
+    let mut cells = vec![timestamp, frame.timestamp.to_string(), frame.device.clone(), frame.state_name.clone(), frame.level.to_string()];
+    cells.extend(frame.channels.values().iter().map(|v| value(Some(*v))));
+    cells.extend(
+        [frame.flow, frame.pressure, frame.humidity, frame.heater_temp, frame.target_ppm, frame.delivered_ppm].map(value),
+    );
+    cells.push(frame.cycle_valid.to_string());
+    cells.join(",")
+}
+
+/// GUI commands:
+///   REC_START <label>   label may contain spaces
+///   REC_STOP
+///   REC_STATUS
+pub fn handle_command(recorder: &Recorder, client: &str, args: &[&str]) -> Result<(), String> {
+    match args {
+        ["REC_START", label @ ..] if !label.is_empty() => recorder.start(&label.join(" "), client),
+        ["REC_STOP"] => recorder.stop(client),
+        ["REC_STATUS"] => {
+            recorder.publish();
+            Ok(())
+        }
+        _ => Err("usage: REC_START <label> | REC_STOP | REC_STATUS".to_string()),
+    }
+}
+
+pub async fn run(recorder: Recorder, frame_tx: broadcast::Sender<UnifiedSensorData>) {
+    let mut frame_rx = frame_tx.subscribe();
+    loop {
+        match frame_rx.recv().await {
+            Ok(frame) => recorder.observe(&frame),
+            Err(broadcast::error::RecvError::Lagged(n)) => eprintln!("⚠️ Recorder lagged, {} frame(s) not recorded", n),
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use serde::Deserialize;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::broadcast;
+
+use crate::dialer::{self, Backoff, BackoffConfig, EndpointConfig, EndpointKind};
+use crate::UnifiedSensorData;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RedisConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Redis host:port
+    #[serde(default = "default_address")]
+    pub address: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub db: u32,
+    #[serde(default = "default_key_prefix")]
+    pub key_prefix: String,
+    /// Frames kept per device in `<prefix>:recent:<device>`, newest first
+    #[serde(default = "default_ring_size")]
+    pub ring_size: usize,
+    /// Expiry of the per-device keys, s; 0 = never (a silent device keeps its last values)
+    #[serde(default)]
+    pub ttl_secs: u64,
+    #[serde(default)]
+    pub backoff: BackoffConfig,
+}
+
+fn default_address() -> String { "localhost:6379".to_string() }
+fn default_key_prefix() -> String { "enose".to_string() }
+fn default_ring_size() -> usize { 100 }
+
+impl Default for RedisConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: default_address(),
+            username: None,
+            password: None,
+            db: 0,
+            key_prefix: default_key_prefix(),
+            ring_size: default_ring_size(),
+            ttl_secs: 0,
+            backoff: BackoffConfig::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    redis: RedisConfig,
+}
+
+impl RedisConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().redis
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.key_prefix.is_empty() {
+            return Err("redis.key_prefix must not be empty".to_string());
+        }
+        if self.ring_size == 0 {
+            return Err("redis.ring_size must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// One command as a RESP array of bulk strings
+fn command(args: &[&str]) -> Vec<u8> {
+    let mut out = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        out.extend(format!("${}\r\n", arg.len()).as_bytes());
+        out.extend(arg.as_bytes());
+        out.extend(b"\r\n");
+    }
+    out
+}
+
+/// Read one reply; Err for a connection problem, Ok(Some(message)) for a Redis error reply
+async fn read_reply<R: AsyncBufRead + Unpin>(reader: &mut R) -> anyhow::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        anyhow::bail!("closed by server");
+    }
+    let line = line.trim_end();
+    let (kind, rest) = line.split_at(line.len().min(1));
+    match kind {
+        "+" | ":" => Ok(None),
+        "-" => Ok(Some(rest.to_string())),
+        "$" => {
+            let len: i64 = rest.parse()?;
+            if len >= 0 {
+                let mut data = vec![0; len as usize + 2];
+                reader.read_exact(&mut data).await?;
+            }
+            Ok(None)
+        }
+        "*" => {
+            let count: i64 = rest.parse()?;
+            let mut error = None;
+            for _ in 0..count.max(0) {
+                error = error.or(Box::pin(read_reply(reader)).await?);
+            }
+            Ok(error)
+        }
+        _ => anyhow::bail!("unexpected reply '{}'", line),
+    }
+}
+
+// ================= Redis Live Cache =================
+/// Keeps the latest verified frame per device (`<prefix>:latest:<device>`,
+/// JSON), a ring of recent frames (`<prefix>:recent:<device>`, newest
+/// first) and the set of devices (`<prefix>:devices`) in Redis, so other
+/// services read current values with a GET instead of the stream
+pub async fn run(config: RedisConfig, frame_tx: broadcast::Sender<UnifiedSensorData>) {
+    let endpoint = EndpointConfig {
+        name: "redis".to_string(),
+        address: config.address.clone(),
+        kind: EndpointKind::Arduino,
+        device: None,
+        connect_timeout_ms: 5000,
+        backoff: config.backoff.clone(),
+    };
+    let mut backoff = Backoff::new(config.backoff.clone());
+    println!("🧰 Redis live cache → {} ({}:latest:<device>)", config.address, config.key_prefix);
+
+    loop {
+        let Some(socket) = dialer::connect(&endpoint, &mut backoff).await else {
+            return;
+        };
+        // Cache hanya perlu nilai terbaru: frame selama putus tidak disusulkan
+        let frame_rx = frame_tx.subscribe();
+        match run_link(socket, &config, frame_rx).await {
+            Ok(()) => return,
+            Err(e) => eprintln!("⚠️ Redis connection lost: {}, reconnecting", e),
+        }
+        tokio::time::sleep(backoff.next_delay().unwrap_or(std::time::Duration::from_secs(5))).await;
+    }
+}
+
+async fn run_link(
+    socket: tokio::net::TcpStream,
+    config: &RedisConfig,
+    mut frame_rx: broadcast::Receiver<UnifiedSensorData>,
+) -> anyhow::Result<()> {
+    let (reader, mut writer) = tokio::io::split(socket);
+    let mut reader = BufReader::new(reader);
+
+    let mut setup = Vec::new();
+    let mut replies = 0;
+    if let Some(password) = &config.password {
+        setup.extend(match &config.username {
+            Some(username) => command(&["AUTH", username, password]),
+            None => command(&["AUTH", password]),
+        });
+        replies += 1;
+    }
+    if config.db != 0 {
+        setup.extend(command(&["SELECT", &config.db.to_string()]));
+        replies += 1;
+    }
+    setup.extend(command(&["PING"]));
+    writer.write_all(&setup).await?;
+    for _ in 0..replies + 1 {
+        if let Some(error) = read_reply(&mut reader).await? {
+            anyhow::bail!("rejected: {}", error);
+        }
+    }
+    println!("🧰 Redis connected to {}", config.address);
+
+    let devices_key = format!("{}:devices", config.key_prefix);
+    let ring_end = (config.ring_size - 1).to_string();
+    let ttl = config.ttl_secs.to_string();
+    loop {
+        let frame = match frame_rx.recv().await {
+            Ok(frame) => frame,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        };
+        let json = serde_json::to_string(&frame).unwrap_or_default();
+        let latest = format!("{}:latest:{}", config.key_prefix, frame.device);
+        let recent = format!("{}:recent:{}", config.key_prefix, frame.device);
+
+        let mut pipeline = command(&["SET", &latest, &json]);
+        pipeline.extend(command(&["LPUSH", &recent, &json]));
+        pipeline.extend(command(&["LTRIM", &recent, "0", &ring_end]));
+        pipeline.extend(command(&["SADD", &devices_key, &frame.device]));
+        let mut replies = 4;
+        if config.ttl_secs > 0 {
+            pipeline.extend(command(&["EXPIRE", &latest, &ttl]));
+            pipeline.extend(command(&["EXPIRE", &recent, &ttl]));
+            replies += 2;
+        }
+        writer.write_all(&pipeline).await?;
+        for _ in 0..replies {
+            if let Some(error) = read_reply(&mut reader).await? {
+                eprintln!("❌ Redis: {}", error);
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use influxdb2::models::DataPoint;
+use serde::Deserialize;
+
+use crate::influxdb::InfluxDBHandler;
+use crate::sessions::SessionRecord;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReferenceConfig {
+    /// Tag value identifying the reference instrument
+    #[serde(default = "default_instrument")]
+    pub instrument: String,
+    /// Added to instrument timestamps to correct its clock against the backend
+    #[serde(default)]
+    pub offset_secs: f64,
+    /// chrono format for naive local timestamps, tried after RFC3339/epoch
+    #[serde(default = "default_timestamp_format")]
+    pub timestamp_format: String,
+}
+
+fn default_instrument() -> String { "gcms".to_string() }
+fn default_timestamp_format() -> String { "%Y-%m-%d %H:%M:%S".to_string() }
+
+impl Default for ReferenceConfig {
+    fn default() -> Self {
+        Self {
+            instrument: default_instrument(),
+            offset_secs: 0.0,
+            timestamp_format: default_timestamp_format(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    reference: ReferenceConfig,
+}
+
+impl ReferenceConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().reference
+    }
+}
+
+/// Parse epoch (s/ms), RFC3339 or naive local time into epoch milliseconds
+fn parse_timestamp(value: &str, format: &str) -> Option<i64> {
+    if let Ok(epoch) = value.parse::<f64>() {
+        // > 1e11 berarti sudah dalam milidetik
+        return Some(if epoch > 1e11 { epoch as i64 } else { (epoch * 1000.0) as i64 });
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.timestamp_millis());
+    }
+    let naive = NaiveDateTime::parse_from_str(value, format).ok()?;
+    Local.from_local_datetime(&naive).single().map(|dt| dt.timestamp_millis())
+}
+
+/// Outcome of an import; row numbers are CSV lines (header = 1)
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Rows whose time falls outside every session (of `device`, when given)
+    pub outside: Vec<usize>,
+    /// Rows in a session with several devices while no device was named
+    pub ambiguous: Vec<usize>,
+}
+
+/// Session running at `timestamp` and the device the sample belongs to
+fn locate<'a>(sessions: &'a [SessionRecord], timestamp: i64, device: Option<&str>) -> Option<(&'a SessionRecord, Option<String>)> {
+    let session = sessions.iter().find(|s| {
+        s.started_at <= timestamp
+            && s.stopped_at.is_none_or(|stopped| timestamp <= stopped)
+            && device.is_none_or(|d| s.devices.iter().any(|x| x == d))
+    })?;
+    let device = match (device, session.devices.as_slice()) {
+        (Some(device), _) => Some(device.to_string()),
+        (None, [only]) => Some(only.clone()),
+        (None, _) => None,
+    };
+    Some((session, device))
+}
+
+/// Import a reference-instrument CSV into the `reference` measurement.
+///
+/// First column is the sample timestamp, remaining columns are numeric
+/// analyte values named by the header row. Timestamps are shifted by the
+/// configured clock offset so they line up with e-nose data in InfluxDB.
+/// Each row is tagged with the session running at its time and that
+/// session's device (`device` picks one when the session had several);
+/// rows that cannot be placed are reported and not written.
+pub async fn import(
+    path: &str,
+    config: &ReferenceConfig,
+    device: Option<&str>,
+    sessions: &[SessionRecord],
+    influx: &InfluxDBHandler,
+) -> Result<ImportReport> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("reading {}", path))?;
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+
+    let header: Vec<String> = lines
+        .next()
+        .ok_or_else(|| anyhow!("{} is empty", path))?
+        .split(',')
+        .map(|h| h.trim().to_lowercase().replace(' ', "_"))
+        .collect();
+    if header.len() < 2 {
+        bail!("expected a timestamp column followed by at least one value column");
+    }
+
+    let offset_ms = (config.offset_secs * 1000.0) as i64;
+    let mut report = ImportReport::default();
+    let mut points = Vec::new();
+
+    for (row, line) in lines.enumerate() {
+        let cells: Vec<&str> = line.split(',').map(str::trim).collect();
+        let Some(timestamp) = parse_timestamp(cells[0], &config.timestamp_format) else {
+            eprintln!("⚠️ Row {}: invalid timestamp '{}', skipped", row + 2, cells[0]);
+            continue;
+        };
+        let timestamp = timestamp + offset_ms;
+
+        // Sampel GC-MS dipasangkan dengan sesi dan device pada waktunya
+        let (session, sample_device) = match locate(sessions, timestamp, device) {
+            Some((session, Some(device))) => (session, device),
+            Some(_) => {
+                report.ambiguous.push(row + 2);
+                continue;
+            }
+            None => {
+                report.outside.push(row + 2);
+                continue;
+            }
+        };
+
+        let mut builder = DataPoint::builder("reference")
+            .tag("source", "reference")
+            .tag("instrument", config.instrument.clone())
+            .tag("device", sample_device)
+            .tag("session", session.id.clone());
+        let mut fields = 0;
+        for (name, cell) in header.iter().zip(&cells).skip(1) {
+            if let Ok(value) = cell.parse::<f64>() {
+                builder = builder.field(name.clone(), value);
+                fields += 1;
+            }
+        }
+        if fields == 0 {
+            continue;
+        }
+        points.push(builder.timestamp(timestamp * 1_000_000).build()?);
+    }
+
+    report.imported = points.len();
+    influx.write_points(points).await?;
+    Ok(report)
+}
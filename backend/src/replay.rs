@@ -0,0 +1,299 @@
+use influxdb2::models::Query;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::channels::Channels;
+use crate::clock::ManualClock;
+use crate::derived::{DerivedConfig, DerivedMetrics, Scope};
+use crate::features::{self, CycleExtractor, FeaturesConfig};
+use crate::filtering::{FilterConfig, SensorFilters, UnifiedSensorRaw};
+use crate::jobs::JobContext;
+use crate::provenance::{CalibrationConfig, Provenance};
+use crate::sessions::SessionRecord;
+use crate::{AppState, UnifiedSensorData, CHANNEL_NAMES};
+
+/// `source` tag of replayed frames and their cycle features
+pub const REPROCESSED: &str = "reprocessed";
+
+/// One stored frame as read back
+struct StoredFrame {
+    timestamp: i64,
+    session: String,
+    raw: UnifiedSensorRaw,
+    /// Channels came from `<channel>_raw` fields
+    unfiltered: bool,
+}
+
+// ================= Replay =================
+/// Replay job: runs a stored time range of one device back through the
+/// current filters, derived metrics and cycle features/classifier, writing
+/// the results with source "reprocessed" next to the original points.
+/// Cycle features found on the way are kept as cycles.json.
+pub async fn replay(state: &AppState, job: &JobContext, device: &str, from: i64, to: i64) -> Result<(), String> {
+    // Rantai baru dari config sekarang, jam mengikuti timestamp asli
+    let clock = ManualClock::new(from);
+    let mut filters = SensorFilters::with_clock(&FilterConfig::load("config.toml", Some(device)), clock.clone());
+    let mut derived = DerivedMetrics::load(&DerivedConfig::load("config.toml", Some(device)), Scope::Frame);
+    let calibration = CalibrationConfig::load("config.toml", Some(device));
+    let mut provenance = Provenance::new(filters.config_hash(), state.config.current_version(), &calibration);
+    provenance.model_version = state.models.active_id();
+    let features_config = FeaturesConfig::load("config.toml");
+    let mut cycles = features_config
+        .enabled
+        .then(|| CycleExtractor::new(&features_config, state.models.clone(), state.noise_floor.clone()));
+
+    let mut found = Vec::new();
+    let (mut frames, mut raw_frames) = (0, 0);
+    let chunks = job.chunks(from, to);
+    for (i, (start, stop)) in chunks.iter().enumerate() {
+        if job.cancelled() {
+            break;
+        }
+        for frame in query(state, device, *start, *stop).await? {
+            clock.set(frame.timestamp);
+            let filtered = filters.update(&frame.raw);
+            let mut payload = UnifiedSensorData {
+                channels: filtered.channels,
+                state: filtered.state,
+                state_name: crate::state_to_name(filtered.state),
+                level: filtered.level,
+                flow: filtered.flow,
+                pressure: filtered.pressure,
+                humidity: filtered.humidity,
+                heater_temp: filtered.heater_temp,
+                heater_setpoint: None,
+                target_ppm: None,
+                delivered_ppm: None,
+                cycle_valid: true,
+                timestamp: frame.timestamp,
+                source: REPROCESSED.to_string(),
+                device: device.to_string(),
+                session: frame.session,
+                reference: None,
+                unverified: false,
+                skip_storage: false,
+                location: None,
+                raw: filters.raw_channels(&frame.raw),
+                modulation: filtered.modulation,
+                saturated: Vec::new(),
+                ranges: BTreeMap::new(),
+                virtual_channels: filtered.virtual_channels,
+            };
+            for (name, value) in derived.frame(&payload) {
+                payload.virtual_channels.insert(name, value as f32);
+            }
+            for (name, value) in crate::calibration::concentrations(&calibration.curves, &payload) {
+                payload.virtual_channels.insert(name, value as f32);
+            }
+            if let Some(result) = cycles.as_mut().and_then(|cycles| cycles.observe(&payload)) {
+                features::publish(&result, features_config.store_features, &state.data_tx, &state.influx).await;
+                found.push(result);
+            }
+            let _ = state.influx.send(crate::influx_data(&payload, &provenance)).await;
+            frames += 1;
+            raw_frames += frame.unfiltered as usize;
+        }
+        job.progress(
+            (i + 1) as f64 / chunks.len() as f64,
+            format!("{} frame(s) ({} unfiltered), {} cycle(s)", frames, raw_frames, found.len()),
+        );
+    }
+    if !found.is_empty() {
+        job.write_artifact("cycles.json", serde_json::to_string_pretty(&found).unwrap_or_default().as_bytes())?;
+    }
+    Ok(())
+}
+
+fn flux_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// One stored timestamp of a device, its fields collected from per-field points
+#[derive(Debug, Serialize)]
+pub struct StoredRow {
+    pub timestamp: i64,
+    pub session: String,
+    pub fields: BTreeMap<String, f64>,
+}
+
+fn source_filter(bucket: &str, device: &str, from: i64, to: i64) -> String {
+    format!(
+        r#"from(bucket: "{bucket}")
+  |> range(start: time(v: {from}), stop: time(v: {to}))
+  |> filter(fn: (r) => r._measurement == "sensors" and r.device == "{device}" and r.source != "{reprocessed}")"#,
+        bucket = flux_string(bucket),
+        from = from * 1_000_000,
+        to = to * 1_000_000,
+        device = flux_string(device),
+        reprocessed = REPROCESSED,
+    )
+}
+
+/// Field names stored for `device` in [from, to), sorted; earlier replays are left out
+pub async fn stored_fields(state: &AppState, device: &str, from: i64, to: i64) -> Result<Vec<String>, String> {
+    let flux = format!(
+        "{}\n  |> keep(columns: [\"_field\"])\n  |> group()\n  |> distinct(column: \"_field\")",
+        source_filter(state.influx.bucket(), device, from, to)
+    );
+    let records = state
+        .influx
+        .client()
+        .query_raw(Some(Query::new(flux)))
+        .await
+        .map_err(|e| format!("InfluxDB query failed: {}", e))?;
+    let mut fields: Vec<String> = records.into_iter().filter_map(|r| r.values.get("_value").and_then(|v| v.string())).collect();
+    fields.sort();
+    fields.dedup();
+    Ok(fields)
+}
+
+/// Stored rows of `device` in [from, to), oldest first, limited to `fields` if given;
+/// earlier replays are left out
+pub async fn stored_rows(
+    state: &AppState,
+    device: &str,
+    from: i64,
+    to: i64,
+    fields: Option<&[String]>,
+) -> Result<Vec<StoredRow>, String> {
+    let mut flux = source_filter(state.influx.bucket(), device, from, to);
+    if let Some(fields) = fields {
+        flux += &format!(
+            "\n  |> filter(fn: (r) => contains(value: r._field, set: {}))",
+            serde_json::to_string(fields).unwrap_or_default()
+        );
+    }
+    // Tanpa pivot(): kolom null hasil pivot membuat parser CSV influxdb2 panic
+    flux += r#"
+  |> map(fn: (r) => ({epoch_ns: int(v: r._time), field: r._field, value: float(v: r._value), session: if exists r.session then r.session else ""}))
+  |> group()
+  |> sort(columns: ["epoch_ns"])"#;
+    let records = state
+        .influx
+        .client()
+        .query_raw(Some(Query::new(flux)))
+        .await
+        .map_err(|e| format!("InfluxDB query failed: {}", e))?;
+
+    // Satu record per field: dikumpulkan per timestamp
+    let mut rows: BTreeMap<i64, StoredRow> = BTreeMap::new();
+    for record in records {
+        let values = record.values;
+        let (Some(epoch_ns), Some(field), Some(value)) = (
+            values.get("epoch_ns").and_then(|v| v.i64()),
+            values.get("field").and_then(|v| v.string()),
+            values.get("value").and_then(|v| v.f64()),
+        ) else {
+            continue;
+        };
+        let timestamp = epoch_ns / 1_000_000;
+        let row = rows.entry(timestamp).or_insert_with(|| StoredRow { timestamp, session: String::new(), fields: BTreeMap::new() });
+        if let Some(session) = values.get("session").and_then(|v| v.string()).filter(|s| !s.is_empty()) {
+            row.session = session;
+        }
+        row.fields.insert(field, value);
+    }
+    Ok(rows.into_values().collect())
+}
+
+/// Stored frames of `device` in [from, to), oldest first
+async fn query(state: &AppState, device: &str, from: i64, to: i64) -> Result<Vec<StoredFrame>, String> {
+    let mut fields: Vec<String> = CHANNEL_NAMES.iter().flat_map(|c| [c.to_string(), format!("{}_raw", c)]).collect();
+    fields.extend(["state", "level", "flow", "pressure", "humidity", "heater_temp"].map(String::from));
+    let rows = stored_rows(state, device, from, to, Some(&fields)).await?;
+
+    let frames = rows
+        .into_iter()
+        .filter_map(|StoredRow { timestamp, session, fields }| {
+            let number = |name: &str| fields.get(name).copied();
+            let unfiltered = CHANNEL_NAMES.iter().any(|c| number(&format!("{}_raw", c)).is_some());
+            let channels: Vec<f32> = CHANNEL_NAMES
+                .iter()
+                .map(|c| {
+                    let stored = if unfiltered { number(&format!("{}_raw", c)) } else { number(c) };
+                    stored.map_or(f32::NAN, |v| v as f32)
+                })
+                .collect();
+            let optional = |name: &str| number(name).map(|v| v as f32);
+            Some(StoredFrame {
+                timestamp,
+                session,
+                raw: UnifiedSensorRaw {
+                    channels: Channels::from_slice(&channels)?,
+                    state: number("state")? as i32,
+                    level: number("level").unwrap_or(0.0) as i32,
+                    flow: optional("flow"),
+                    pressure: optional("pressure"),
+                    humidity: optional("humidity"),
+                    heater_temp: optional("heater_temp"),
+                },
+                unfiltered,
+            })
+        })
+        .collect();
+    Ok(frames)
+}
+
+/// Features of one stored valid cycle
+pub struct StoredCycle {
+    pub session: String,
+    pub device: String,
+    pub features: BTreeMap<String, f64>,
+}
+
+/// Valid cycles of `sessions` from the "cycle_features" measurement; a
+/// session replayed since is read from its reprocessed cycles
+pub async fn stored_cycles(state: &AppState, sessions: &[&SessionRecord]) -> Result<Vec<StoredCycle>, String> {
+    let from = sessions.iter().map(|s| s.started_at).min().unwrap_or_default();
+    let now = chrono::Utc::now().timestamp_millis();
+    let to = sessions.iter().map(|s| s.stopped_at.unwrap_or(now)).max().unwrap_or(now) + 60_000;
+    let ids: Vec<&str> = sessions.iter().map(|s| s.id.as_str()).collect();
+    let flux = format!(
+        r#"from(bucket: "{bucket}")
+  |> range(start: time(v: {from}), stop: time(v: {to}))
+  |> filter(fn: (r) => r._measurement == "cycle_features" and exists r.session and contains(value: r.session, set: {ids}))
+  |> filter(fn: (r) => r._field != "samples" and r._field != "detected")
+  |> map(fn: (r) => ({{epoch_ns: int(v: r._time), field: r._field, value: float(v: r._value), session: r.session, source: r.source, device: r.device}}))
+  |> group()"#,
+        bucket = flux_string(state.influx.bucket()),
+        from = from * 1_000_000,
+        to = to * 1_000_000,
+        ids = serde_json::to_string(&ids).unwrap_or_default(),
+    );
+    let records = state
+        .influx
+        .client()
+        .query_raw(Some(Query::new(flux)))
+        .await
+        .map_err(|e| format!("InfluxDB query failed: {}", e))?;
+
+    // (session, reprocessed, device, epoch_ns) → fitur
+    let mut cycles: BTreeMap<(String, bool, String, i64), BTreeMap<String, f64>> = BTreeMap::new();
+    for record in records {
+        let values = record.values;
+        let text = |key: &str| values.get(key).and_then(|v| v.string());
+        let (Some(epoch_ns), Some(field), Some(value), Some(session)) = (
+            values.get("epoch_ns").and_then(|v| v.i64()),
+            text("field"),
+            values.get("value").and_then(|v| v.f64()),
+            text("session"),
+        ) else {
+            continue;
+        };
+        let reprocessed = text("source").as_deref() == Some(REPROCESSED);
+        let key = (session, reprocessed, text("device").unwrap_or_default(), epoch_ns);
+        cycles.entry(key).or_default().insert(field, value);
+    }
+
+    let replayed: Vec<String> = cycles.keys().filter(|k| k.1).map(|k| k.0.clone()).collect();
+    Ok(cycles
+        .into_iter()
+        .filter(|((session, reprocessed, _, _), _)| *reprocessed || !replayed.contains(session))
+        .filter_map(|((session, _, device, _), mut features)| {
+            if features.remove("valid").is_some_and(|v| v == 0.0) {
+                return None;
+            }
+            Some(StoredCycle { session, device, features })
+        })
+        .collect())
+}
@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::alerts::AlertRuleDef;
+use crate::jobs::JobContext;
+use crate::replay;
+use crate::sessions::{ChannelSummary, SessionFilter, SessionRecord};
+use crate::AppState;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RocConfig {
+    /// Thresholds below this AUC are reported but not applied
+    #[serde(default = "default_min_auc")]
+    pub min_auc: f64,
+    /// Session statistic scored for channel thresholds: "mean", "p50", "p95" or "max"
+    /// (p5/min are used instead for a "below" threshold)
+    #[serde(default = "default_statistic")]
+    pub statistic: String,
+    /// Severity of the alert rules written for channels
+    #[serde(default = "default_severity")]
+    pub severity: String,
+}
+
+fn default_min_auc() -> f64 { 0.8 }
+fn default_statistic() -> String { "p95".to_string() }
+fn default_severity() -> String { "warning".to_string() }
+
+impl Default for RocConfig {
+    fn default() -> Self {
+        Self { min_auc: default_min_auc(), statistic: default_statistic(), severity: default_severity() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    roc: RocConfig,
+}
+
+impl RocConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().roc
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.5..=1.0).contains(&self.min_auc) {
+            return Err("roc.min_auc must be between 0.5 and 1".to_string());
+        }
+        if !["mean", "p50", "p95", "max"].contains(&self.statistic.as_str()) {
+            return Err(format!("roc.statistic '{}' is not mean, p50, p95 or max", self.statistic));
+        }
+        Ok(())
+    }
+
+    /// (statistic for "above", statistic for "below") of a session channel
+    fn statistics(&self, summary: &ChannelSummary) -> (f64, f64) {
+        let (above, below) = match self.statistic.as_str() {
+            "mean" => (summary.mean, summary.mean),
+            "p50" => (summary.p50, summary.p50),
+            "max" => (summary.max, summary.min),
+            _ => (summary.p95, summary.p5),
+        };
+        (above as f64, below as f64)
+    }
+}
+
+/// Best cut of one score between positive and negative samples
+#[derive(Debug, Serialize, Clone)]
+pub struct Threshold {
+    pub name: String,
+    /// "above": positive when the score is greater, "below": when smaller
+    pub direction: String,
+    pub threshold: f64,
+    /// Area under the ROC curve, 0.5 = no separation
+    pub auc: f64,
+    /// True/false positive rate at `threshold` (largest Youden J = tpr - fpr)
+    pub tpr: f64,
+    pub fpr: f64,
+    pub positives: usize,
+    pub negatives: usize,
+}
+
+/// ROC of `score > t` (above) or `score < t` (below); None without samples on both sides
+pub fn roc(name: &str, positives: &[f64], negatives: &[f64], above: bool) -> Option<Threshold> {
+    // Arah "below" = arah "above" pada nilai yang dinegasikan
+    let sign = if above { 1.0 } else { -1.0 };
+    let sorted = |values: &[f64]| {
+        let mut v: Vec<f64> = values.iter().filter(|v| v.is_finite()).map(|v| v * sign).collect();
+        v.sort_by(f64::total_cmp);
+        v
+    };
+    let (pos, neg) = (sorted(positives), sorted(negatives));
+    if pos.is_empty() || neg.is_empty() {
+        return None;
+    }
+    let below = |values: &[f64], t: f64| values.partition_point(|v| *v < t);
+    let not_above = |values: &[f64], t: f64| values.partition_point(|v| *v <= t);
+
+    // AUC = P(pos > neg) + ½ P(pos == neg) (Mann–Whitney)
+    let wins: f64 = pos.iter().map(|p| below(&neg, *p) as f64 + 0.5 * (not_above(&neg, *p) - below(&neg, *p)) as f64).sum();
+    let auc = wins / (pos.len() * neg.len()) as f64;
+
+    // Kandidat threshold: titik tengah antar nilai berurutan
+    let mut values: Vec<f64> = pos.iter().chain(&neg).copied().collect();
+    values.sort_by(f64::total_cmp);
+    values.dedup();
+    let rate = |values: &[f64], t: f64| (values.len() - not_above(values, t)) as f64 / values.len() as f64;
+    let (threshold, tpr, fpr) = values
+        .windows(2)
+        .map(|w| (w[0] + w[1]) / 2.0)
+        .map(|t| (t, rate(&pos, t), rate(&neg, t)))
+        .max_by(|a, b| (a.1 - a.2).total_cmp(&(b.1 - b.2)))
+        .unwrap_or((values[0], 0.0, 0.0));
+
+    Some(Threshold {
+        name: name.to_string(),
+        direction: if above { "above" } else { "below" }.to_string(),
+        threshold: round(threshold * sign),
+        auc,
+        tpr,
+        fpr,
+        positives: pos.len(),
+        negatives: neg.len(),
+    })
+}
+
+/// Six significant digits, so written thresholds stay readable
+pub fn round(value: f64) -> f64 {
+    if value == 0.0 || !value.is_finite() {
+        return value;
+    }
+    let scale = 10f64.powi(5 - value.abs().log10().floor() as i32);
+    (value * scale).round() / scale
+}
+
+fn better(a: Option<Threshold>, b: Option<Threshold>) -> Option<Threshold> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.auc > a.auc { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+// ================= Threshold Calibration =================
+/// Calibration job: from sessions labeled `positive` and `negative`,
+/// finds per channel (session statistics) and per cycle feature the
+/// threshold that best separates them (ROC, largest Youden J). Results go
+/// to thresholds.json/thresholds.md; with `apply`, those reaching min_auc
+/// become [[alerts]] rules (channels) and [features.thresholds] (features)
+/// in config.toml as one config version.
+pub async fn calibrate(state: &AppState, job: &JobContext, positive: &str, negative: &str, apply: bool, actor: &str) -> Result<(), String> {
+    let config = RocConfig::load("config.toml");
+    let labeled = |label: &str| -> Result<Vec<SessionRecord>, String> {
+        let filter = SessionFilter { label: Some(label.to_string()), ..Default::default() };
+        let sessions: Vec<SessionRecord> = state
+            .sessions
+            .list(&filter)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|s| s.summary.is_some() && s.label.as_deref().is_some_and(|l| l.trim().eq_ignore_ascii_case(label.trim())))
+            .collect();
+        if sessions.is_empty() {
+            return Err(format!("no closed sessions labeled '{}'", label));
+        }
+        Ok(sessions)
+    };
+    let (positives, negatives) = (labeled(positive)?, labeled(negative)?);
+
+    // Kanal: satu skor per sesi dari ringkasannya
+    let mut scores: BTreeMap<&str, [Vec<(f64, f64)>; 2]> = BTreeMap::new();
+    for (side, sessions) in [&positives, &negatives].into_iter().enumerate() {
+        for session in sessions {
+            for (channel, summary) in session.summary.iter().flat_map(|s| &s.channels) {
+                scores.entry(channel).or_default()[side].push(config.statistics(summary));
+            }
+        }
+    }
+    let channels: Vec<Threshold> = scores
+        .iter()
+        .filter_map(|(channel, [pos, neg])| {
+            let side = |values: &[(f64, f64)], above: bool| values.iter().map(|v| if above { v.0 } else { v.1 }).collect::<Vec<_>>();
+            better(
+                roc(channel, &side(pos, true), &side(neg, true), true),
+                roc(channel, &side(pos, false), &side(neg, false), false),
+            )
+        })
+        .collect();
+    job.progress(0.3, format!("{} channel threshold(s)", channels.len()));
+    if job.cancelled() {
+        return Ok(());
+    }
+
+    // Fitur: satu skor per siklus
+    let sessions: Vec<&SessionRecord> = positives.iter().chain(&negatives).collect();
+    let cycles = replay::stored_cycles(state, &sessions).await?;
+    let mut samples: BTreeMap<&str, [Vec<f64>; 2]> = BTreeMap::new();
+    for cycle in &cycles {
+        let positive = positives.iter().any(|s| s.id == cycle.session);
+        for (name, value) in &cycle.features {
+            samples.entry(name).or_default()[!positive as usize].push(*value);
+        }
+    }
+    let features: Vec<Threshold> = samples
+        .iter()
+        .filter_map(|(name, [pos, neg])| better(roc(name, pos, neg, true), roc(name, pos, neg, false)))
+        .collect();
+    job.progress(0.8, format!("{} channel and {} feature threshold(s) from {} cycle(s)", channels.len(), features.len(), cycles.len()));
+
+    let report = serde_json::json!({
+        "positive": positive,
+        "negative": negative,
+        "sessions": {
+            "positive": positives.iter().map(|s| &s.id).collect::<Vec<_>>(),
+            "negative": negatives.iter().map(|s| &s.id).collect::<Vec<_>>(),
+        },
+        "statistic": config.statistic,
+        "cycles": cycles.len(),
+        "min_auc": config.min_auc,
+        "channels": channels,
+        "features": features,
+    });
+    job.write_artifact("thresholds.json", serde_json::to_string_pretty(&report).unwrap_or_default().as_bytes())?;
+    job.write_artifact("thresholds.md", markdown(positive, negative, &positives, &negatives, &channels, &features, config.min_auc).as_bytes())?;
+
+    if !apply || job.cancelled() {
+        return Ok(());
+    }
+    let good = |t: &&Threshold| t.auc >= config.min_auc;
+    let rules: Vec<AlertRuleDef> = channels
+        .iter()
+        .filter(good)
+        .map(|t| {
+            let name = format!("{}_{}", crate::recording::file_safe(positive), t.name);
+            let operator = if t.direction == "above" { ">" } else { "<" };
+            let mut rule = AlertRuleDef::new(&name, &format!("{} {} {}", t.name, operator, t.threshold));
+            rule.severity = config.severity.clone();
+            rule
+        })
+        .collect();
+    let applied: Vec<&Threshold> = features.iter().filter(good).collect();
+    if rules.is_empty() && applied.is_empty() {
+        job.progress(1.0, format!("nothing reached roc.min_auc {}", config.min_auc));
+        return Ok(());
+    }
+    let version = state.config.edit(actor, "calibrate", Some(format!("{} vs {}", positive, negative)), |doc| {
+        for rule in &rules {
+            upsert_alert(doc, rule)?;
+        }
+        for t in &applied {
+            let key = format!("features.thresholds.\"{}\"", t.name);
+            crate::config::set_value(doc, &key, &format!("{{ {} = {} }}", t.direction, t.threshold))?;
+        }
+        Ok(())
+    })?;
+    for rule in rules.iter().cloned() {
+        state.alerts.add(rule)?;
+    }
+    state.alerts.publish();
+    println!(
+        "🎯 Calibration '{}' vs '{}' applied as config version {}: {} alert rule(s), {} feature threshold(s)",
+        positive,
+        negative,
+        version,
+        rules.len(),
+        applied.len()
+    );
+    job.progress(1.0, format!("config version {}: {} alert rule(s), {} feature threshold(s)", version, rules.len(), applied.len()));
+    Ok(())
+}
+
+/// Add an `[[alerts]]` entry, or update condition/severity of the one with its name
+fn upsert_alert(doc: &mut toml_edit::DocumentMut, rule: &AlertRuleDef) -> Result<(), String> {
+    let alerts = doc
+        .as_table_mut()
+        .entry("alerts")
+        .or_insert(toml_edit::Item::ArrayOfTables(toml_edit::ArrayOfTables::new()))
+        .as_array_of_tables_mut()
+        .ok_or("'alerts' is not an array of tables")?;
+    let existing = alerts.iter_mut().find(|t| t.get("name").and_then(|n| n.as_str()) == Some(rule.name.as_str()));
+    let table = match existing {
+        Some(table) => table,
+        None => {
+            let mut table = toml_edit::Table::new();
+            table["name"] = toml_edit::value(rule.name.clone());
+            alerts.push(table);
+            alerts.iter_mut().last().ok_or("'alerts' is empty")?
+        }
+    };
+    table["condition"] = toml_edit::value(rule.condition.clone());
+    table["severity"] = toml_edit::value(rule.severity.clone());
+    Ok(())
+}
+
+fn markdown(
+    positive: &str,
+    negative: &str,
+    positives: &[SessionRecord],
+    negatives: &[SessionRecord],
+    channels: &[Threshold],
+    features: &[Threshold],
+    min_auc: f64,
+) -> String {
+    let mut md = format!("# Thresholds: {} vs {}\n\n", positive, negative);
+    md += &format!("**Positive sessions:** {}  \n", positives.iter().map(|s| s.id.as_str()).collect::<Vec<_>>().join(", "));
+    md += &format!("**Negative sessions:** {}  \n", negatives.iter().map(|s| s.id.as_str()).collect::<Vec<_>>().join(", "));
+    md += &format!("Rows marked ✓ reach min_auc {} and are applied with `apply`.\n", min_auc);
+    for (title, thresholds) in [("Channels (per session)", channels), ("Cycle features (per cycle)", features)] {
+        md += &format!("\n## {}\n\n| | Name | Threshold | AUC | TPR | FPR | Pos | Neg |\n|---|---|---|---|---|---|---|---|\n", title);
+        let mut sorted: Vec<&Threshold> = thresholds.iter().collect();
+        sorted.sort_by(|a, b| b.auc.total_cmp(&a.auc));
+        for t in sorted {
+            md += &format!(
+                "| {} | {} | {} {} | {:.3} | {:.2} | {:.2} | {} | {} |\n",
+                if t.auc >= min_auc { "✓" } else { "" },
+                t.name,
+                if t.direction == "above" { ">" } else { "<" },
+                t.threshold,
+                t.auc,
+                t.tpr,
+                t.fpr,
+                t.positives,
+                t.negatives
+            );
+        }
+    }
+    md
+}
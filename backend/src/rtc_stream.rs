@@ -0,0 +1,131 @@
+use serde::Deserialize;
+use tokio::sync::broadcast;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebRtcConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_ice_servers")]
+    #[cfg_attr(not(feature = "webrtc"), allow(dead_code))]
+    pub ice_servers: Vec<String>,
+}
+
+fn default_ice_servers() -> Vec<String> { vec!["stun:stun.l.google.com:19302".to_string()] }
+
+impl Default for WebRtcConfig {
+    fn default() -> Self {
+        Self { enabled: false, ice_servers: default_ice_servers() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    webrtc: WebRtcConfig,
+}
+
+impl WebRtcConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().webrtc
+    }
+}
+
+// ================= WebRTC Streaming =================
+/// Streams every GUI message over a browser-created data channel.
+/// Signaling is a single offer/answer exchange (non-trickle ICE) via
+/// `POST /api/webrtc/offer`; only available with the `webrtc` feature.
+#[derive(Clone)]
+pub struct RtcGateway {
+    config: WebRtcConfig,
+    #[cfg_attr(not(feature = "webrtc"), allow(dead_code))]
+    data_tx: broadcast::Sender<String>,
+}
+
+impl RtcGateway {
+    pub fn new(config: WebRtcConfig, data_tx: broadcast::Sender<String>) -> Self {
+        Self { config, data_tx }
+    }
+
+    /// Accept an SDP offer `{"type": "offer", "sdp": ...}` and return the answer
+    pub async fn answer(&self, offer: serde_json::Value) -> Result<serde_json::Value, String> {
+        if !self.config.enabled {
+            return Err("WebRTC is disabled in config".to_string());
+        }
+        self.connect(offer).await
+    }
+
+    #[cfg(not(feature = "webrtc"))]
+    async fn connect(&self, _offer: serde_json::Value) -> Result<serde_json::Value, String> {
+        Err("backend built without the 'webrtc' feature".to_string())
+    }
+
+    #[cfg(feature = "webrtc")]
+    async fn connect(&self, offer: serde_json::Value) -> Result<serde_json::Value, String> {
+        use std::sync::Arc;
+        use webrtc::api::APIBuilder;
+        use webrtc::data_channel::RTCDataChannel;
+        use webrtc::ice_transport::ice_server::RTCIceServer;
+        use webrtc::peer_connection::configuration::RTCConfiguration;
+        use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+        use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+
+        let offer: RTCSessionDescription = serde_json::from_value(offer).map_err(|e| e.to_string())?;
+        let api = APIBuilder::new().build();
+        let rtc_config = RTCConfiguration {
+            ice_servers: vec![RTCIceServer { urls: self.config.ice_servers.clone(), ..Default::default() }],
+            ..Default::default()
+        };
+        let peer = Arc::new(api.new_peer_connection(rtc_config).await.map_err(|e| e.to_string())?);
+
+        let data_tx = self.data_tx.clone();
+        peer.on_data_channel(Box::new(move |channel: Arc<RTCDataChannel>| {
+            let data_tx = data_tx.clone();
+            Box::pin(async move {
+                let label = channel.label().to_string();
+                let sender = channel.clone();
+                channel.on_open(Box::new(move || {
+                    Box::pin(async move {
+                        println!("🛰️ WebRTC data channel '{}' open", label);
+                        let mut data_rx = data_tx.subscribe();
+                        tokio::spawn(async move {
+                            loop {
+                                match data_rx.recv().await {
+                                    Ok(msg) => {
+                                        if sender.send_text(msg).await.is_err() {
+                                            break;
+                                        }
+                                    }
+                                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                                    Err(broadcast::error::RecvError::Closed) => break,
+                                }
+                            }
+                            println!("🛰️ WebRTC data channel '{}' closed", label);
+                        });
+                    })
+                }));
+            })
+        }));
+
+        let closing = Arc::downgrade(&peer);
+        peer.on_peer_connection_state_change(Box::new(move |state: RTCPeerConnectionState| {
+            let closing = closing.clone();
+            Box::pin(async move {
+                if matches!(state, RTCPeerConnectionState::Failed | RTCPeerConnectionState::Disconnected) {
+                    if let Some(peer) = closing.upgrade() {
+                        let _ = peer.close().await;
+                    }
+                }
+            })
+        }));
+
+        peer.set_remote_description(offer).await.map_err(|e| e.to_string())?;
+        let answer = peer.create_answer(None).await.map_err(|e| e.to_string())?;
+        let mut gathered = peer.gathering_complete_promise().await;
+        peer.set_local_description(answer).await.map_err(|e| e.to_string())?;
+        let _ = gathered.recv().await;
+
+        let local = peer.local_description().await.ok_or("no local description")?;
+        serde_json::to_value(local).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,198 @@
+use influxdb2::models::DataPoint;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::channels::{Channel, Channels};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SaturationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// ADC rails in raw channel units (before filtering)
+    #[serde(default)]
+    pub low: f32,
+    #[serde(default = "default_high")]
+    pub high: f32,
+    /// Per-channel `[low, high]`, e.g. `co = [0.0, 3.3]`
+    #[serde(default)]
+    pub rails: BTreeMap<String, [f32; 2]>,
+    /// Within this much of a rail counts as railed
+    #[serde(default = "default_margin")]
+    pub margin: f32,
+    /// Railed this long before the channel is flagged saturated
+    #[serde(default = "default_hold_ms")]
+    pub hold_ms: i64,
+    /// Range switch for the high / low rail, `{channel}` replaced; sent once
+    /// per saturation only if the device lists the command; "" = hint only
+    #[serde(default)]
+    pub high_command: String,
+    #[serde(default)]
+    pub low_command: String,
+}
+
+fn default_high() -> f32 { 5.0 }
+fn default_margin() -> f32 { 0.01 }
+fn default_hold_ms() -> i64 { 1000 }
+
+impl Default for SaturationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            low: 0.0,
+            high: default_high(),
+            rails: BTreeMap::new(),
+            margin: default_margin(),
+            hold_ms: default_hold_ms(),
+            high_command: String::new(),
+            low_command: String::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    saturation: SaturationConfig,
+}
+
+impl SaturationConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc).try_into::<ConfigFile>().unwrap_or_default().saturation
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.low >= self.high {
+            return Err("saturation.low must be below saturation.high".to_string());
+        }
+        for (name, [low, high]) in &self.rails {
+            if Channel::from_name(name).is_none() {
+                return Err(format!("saturation.rails: unknown channel '{}'", name));
+            }
+            if low >= high {
+                return Err(format!("saturation.rails.{}: low must be below high", name));
+            }
+        }
+        if self.margin < 0.0 || self.hold_ms < 0 {
+            return Err("saturation.margin and saturation.hold_ms must not be negative".to_string());
+        }
+        Ok(())
+    }
+
+    fn rails(&self, channel: Channel) -> (f32, f32) {
+        self.rails.get(channel.name()).map_or((self.low, self.high), |[low, high]| (*low, *high))
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Rail {
+    Low,
+    High,
+}
+
+/// "saturation" GUI event: a channel starting or ending saturation
+#[derive(Debug, Serialize, Clone)]
+pub struct SaturationEvent {
+    pub device: String,
+    pub channel: String,
+    pub rail: Rail,
+    pub active: bool,
+    /// When the channel reached the rail
+    pub since: i64,
+    pub timestamp: i64,
+    pub hint: &'static str,
+    /// Range switch command for this rail, if configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// The device supports the command and it was sent
+    pub sent: bool,
+}
+
+impl SaturationEvent {
+    /// "range_change" point, recorded once the range command was sent
+    pub fn range_point(&self) -> Option<DataPoint> {
+        if !self.sent {
+            return None;
+        }
+        DataPoint::builder("range_change")
+            .tag("device", self.device.clone())
+            .tag("channel", self.channel.clone())
+            .tag("rail", if self.rail == Rail::High { "high" } else { "low" })
+            .field("command", self.command.clone()?)
+            .field("railed_ms", self.timestamp - self.since)
+            .timestamp(self.timestamp * 1_000_000)
+            .build()
+            .ok()
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+struct ChannelState {
+    /// Rail and since when the raw value sits on it
+    railed: Option<(Rail, i64)>,
+    saturated: bool,
+}
+
+// ================= Saturation Detector =================
+/// Per device: flags channels whose raw value stays at an ADC rail for
+/// hold_ms. Frames carry the flagged channels (`<channel>_saturated`),
+/// GUIs get a hint and, where configured, a range switch command.
+pub struct SaturationDetector {
+    config: SaturationConfig,
+    channels: [ChannelState; 7],
+}
+
+impl SaturationDetector {
+    pub fn new(config: SaturationConfig) -> Option<Self> {
+        config.enabled.then(|| Self { config, channels: [ChannelState::default(); 7] })
+    }
+
+    /// Feed one frame's raw channels; returns saturation starts and ends
+    pub fn update(&mut self, device: &str, raw: &Channels, timestamp: i64) -> Vec<SaturationEvent> {
+        let mut events = Vec::new();
+        for (channel, value) in raw.iter() {
+            let (low, high) = self.config.rails(channel);
+            let state = &mut self.channels[channel.index()];
+            let rail = match value {
+                v if !v.is_finite() => None,
+                v if v >= high - self.config.margin => Some(Rail::High),
+                v if v <= low + self.config.margin => Some(Rail::Low),
+                _ => None,
+            };
+            let before = state.railed;
+            let previous = before.filter(|(r, _)| Some(*r) == rail);
+            state.railed = rail.map(|r| previous.unwrap_or((r, timestamp)));
+
+            let held = state.railed.filter(|(_, since)| timestamp - since >= self.config.hold_ms);
+            let (rail, since, active) = match (held, state.saturated) {
+                (Some((rail, since)), false) => (rail, since, true),
+                (None, true) => (before.map_or(Rail::High, |(r, _)| r), timestamp, false),
+                _ => continue,
+            };
+            state.saturated = active;
+            let template = if rail == Rail::High { &self.config.high_command } else { &self.config.low_command };
+            events.push(SaturationEvent {
+                device: device.to_string(),
+                channel: channel.name().to_string(),
+                rail,
+                active,
+                since,
+                timestamp,
+                hint: match (rail, active) {
+                    (_, false) => "back within range",
+                    (Rail::High, true) => "signal above the ADC range: switch to a wider (lower gain) range",
+                    (Rail::Low, true) => "signal below the ADC range: check the offset or switch range",
+                },
+                command: (active && !template.is_empty()).then(|| template.replace("{channel}", channel.name())),
+                sent: false,
+            });
+        }
+        events
+    }
+
+    /// Channels currently flagged saturated
+    pub fn saturated(&self) -> Vec<String> {
+        Channel::ALL.iter().filter(|c| self.channels[c.index()].saturated).map(|c| c.name().to_string()).collect()
+    }
+}
@@ -0,0 +1,242 @@
+use chrono::Utc;
+use influxdb2::models::DataPoint;
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::alerts::{AlertEngine, AlertEvent, AlertRuleDef};
+use crate::audit::AuditLog;
+use crate::influxdb::InfluxDBHandler;
+use crate::UnifiedSensorData;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ScriptingConfig {
+    /// Rhai scripts defining event handlers
+    #[serde(default)]
+    pub scripts: Vec<String>,
+    /// Operation budget per handler call
+    #[serde(default = "default_max_operations")]
+    pub max_operations: u64,
+}
+
+fn default_max_operations() -> u64 { 100_000 }
+
+impl Default for ScriptingConfig {
+    fn default() -> Self {
+        Self { scripts: Vec::new(), max_operations: default_max_operations() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    scripting: ScriptingConfig,
+}
+
+impl ScriptingConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().scripting
+    }
+}
+
+/// What a handler asked for; executed after the (synchronous) call returns
+#[derive(Debug)]
+enum Action {
+    Send(String),
+    Annotate(String),
+    AckAlert(String),
+    SilenceAlert(String, f64),
+    AddAlert(String, String),
+}
+
+/// Backend services scripts may act on
+pub struct ScriptContext {
+    pub cmd_tx: broadcast::Sender<String>,
+    pub data_tx: broadcast::Sender<String>,
+    pub influx: InfluxDBHandler,
+    pub alerts: AlertEngine,
+    pub audit: AuditLog,
+}
+
+struct Script {
+    path: String,
+    ast: AST,
+}
+
+/// Sandboxed engine: no file/network access, only the functions below.
+///   send(cmd)                     command to the Arduino
+///   annotate(text)                annotation (GUI + "annotations" measurement)
+///   ack_alert(name)
+///   silence_alert(name, secs)
+///   add_alert(name, condition)
+fn build_engine(max_operations: u64, actions: Arc<Mutex<Vec<Action>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(max_operations);
+    engine.set_max_call_levels(32);
+    engine.set_max_string_size(4096);
+    engine.set_max_array_size(1024);
+    engine.set_max_map_size(256);
+
+    let push = move |action: Action| actions.lock().unwrap().push(action);
+    let p = push.clone();
+    engine.register_fn("send", move |cmd: &str| p(Action::Send(cmd.to_string())));
+    let p = push.clone();
+    engine.register_fn("annotate", move |text: &str| p(Action::Annotate(text.to_string())));
+    let p = push.clone();
+    engine.register_fn("ack_alert", move |name: &str| p(Action::AckAlert(name.to_string())));
+    let p = push.clone();
+    engine.register_fn("silence_alert", move |name: &str, secs: f64| {
+        p(Action::SilenceAlert(name.to_string(), secs))
+    });
+    let p = push.clone();
+    engine.register_fn("silence_alert", move |name: &str, secs: i64| {
+        p(Action::SilenceAlert(name.to_string(), secs as f64))
+    });
+    engine.register_fn("add_alert", move |name: &str, condition: &str| {
+        push(Action::AddAlert(name.to_string(), condition.to_string()))
+    });
+    engine.on_print(|text| println!("📜 {}", text));
+    engine
+}
+
+fn alert_map(event: &AlertEvent) -> Map {
+    let mut map = Map::new();
+    let state = serde_json::to_value(event.state).unwrap_or_default();
+    let reason = serde_json::to_value(event.reason).unwrap_or_default();
+    map.insert("name".into(), event.name.clone().into());
+    map.insert("device".into(), event.device.clone().into());
+    map.insert("severity".into(), event.severity.clone().into());
+    map.insert("state".into(), state.as_str().unwrap_or_default().into());
+    map.insert("reason".into(), reason.as_str().unwrap_or_default().into());
+    map.insert("active".into(), event.active.into());
+    map
+}
+
+// ================= Automation Scripts =================
+/// Calls script handlers on backend events:
+///   fn on_state_change(from, to, level)   FSM state names, e.g. "HOLD" → "DONE"
+///   fn on_alert(alert)                    map: name, device, severity, state, reason, active
+pub async fn run(config: ScriptingConfig, ctx: ScriptContext, frame_tx: broadcast::Sender<UnifiedSensorData>) {
+    let actions = Arc::new(Mutex::new(Vec::new()));
+    let engine = build_engine(config.max_operations, actions.clone());
+
+    let mut scripts = Vec::new();
+    for path in &config.scripts {
+        let ast = match engine.compile_file(path.into()) {
+            Ok(ast) => ast,
+            Err(e) => {
+                eprintln!("❌ Script {} failed to compile: {}", path, e);
+                continue;
+            }
+        };
+        // Top-level statements dijalankan sekali saat load
+        if let Err(e) = engine.run_ast(&ast) {
+            eprintln!("❌ Script {} failed: {}", path, e);
+            continue;
+        }
+        println!("📜 Script loaded: {}", path);
+        scripts.push(Script { path: path.clone(), ast });
+    }
+    if scripts.is_empty() {
+        return;
+    }
+
+    let mut frame_rx = frame_tx.subscribe();
+    let mut alert_rx = ctx.alerts.subscribe();
+    let mut last_state: Option<(String, i32)> = None;
+
+    loop {
+        let (handler, args): (&str, Vec<Dynamic>) = tokio::select! {
+            frame = frame_rx.recv() => match frame {
+                Ok(frame) => {
+                    let previous = last_state.replace((frame.state_name.clone(), frame.level));
+                    match previous {
+                        Some((from, _)) if from != frame.state_name => (
+                            "on_state_change",
+                            vec![from.into(), frame.state_name.clone().into(), (frame.level as i64).into()],
+                        ),
+                        _ => continue,
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            event = alert_rx.recv() => match event {
+                Ok(event) => ("on_alert", vec![alert_map(&event).into()]),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+
+        for script in &scripts {
+            if !script.ast.iter_functions().any(|f| f.name == handler && f.params.len() == args.len()) {
+                continue;
+            }
+            let options = CallFnOptions::new().eval_ast(false);
+            let result = engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut Scope::new(),
+                &script.ast,
+                handler,
+                args.clone(),
+            );
+            if let Err(e) = result {
+                eprintln!("❌ Script {} {}: {}", script.path, handler, e);
+            }
+
+            let pending: Vec<Action> = std::mem::take(&mut *actions.lock().unwrap());
+            for action in pending {
+                execute(&ctx, &script.path, action).await;
+            }
+        }
+    }
+}
+
+async fn execute(ctx: &ScriptContext, script: &str, action: Action) {
+    let actor = format!("script:{}", script);
+    let result = match action {
+        Action::Send(cmd) => {
+            println!("📜 {} → Arduino: {}", script, cmd);
+            let _ = ctx.cmd_tx.send(cmd);
+            Ok(())
+        }
+        Action::Annotate(text) => {
+            annotate(ctx, &actor, &text).await;
+            Ok(())
+        }
+        Action::AckAlert(name) => ctx.alerts.acknowledge(&name, &actor).map(|_| {
+            ctx.audit.record(&actor, "alert_ack", serde_json::json!({ "alert": name }));
+        }),
+        Action::SilenceAlert(name, secs) => ctx.alerts.silence(&name, secs).map(|_| {
+            ctx.audit.record(&actor, "alert_silence", serde_json::json!({ "alert": name, "secs": secs }));
+        }),
+        Action::AddAlert(name, condition) => ctx.alerts.add(AlertRuleDef::new(&name, &condition)),
+    };
+    if let Err(e) = result {
+        eprintln!("❌ Script {} action failed: {}", script, e);
+    }
+}
+
+async fn annotate(ctx: &ScriptContext, source: &str, text: &str) {
+    let timestamp = Utc::now().timestamp_millis();
+    let event = serde_json::json!({
+        "type": "annotation",
+        "text": text,
+        "source": source,
+        "timestamp": timestamp,
+    });
+    let _ = ctx.data_tx.send(event.to_string());
+
+    let point = DataPoint::builder("annotations")
+        .tag("source", source)
+        .field("text", text)
+        .timestamp(timestamp * 1_000_000)
+        .build();
+    if let Ok(point) = point {
+        if let Err(e) = ctx.influx.write_points(vec![point]).await {
+            eprintln!("❌ Annotation write error: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,604 @@
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::alerts::{AlertEngine, AlertState, NotifyReason};
+use crate::audit::AuditLog;
+use crate::influxdb::InfluxDBHandler;
+use crate::store::LocalStore;
+use crate::UnifiedSensorData;
+
+// FSM states (see state_to_name)
+const PRE_COND: i32 = 1;
+const DONE: i32 = 6;
+
+/// A session runs from leaving IDLE until the FSM is back in IDLE or DONE
+pub fn in_session(state: i32) -> bool {
+    state != 0 && state != DONE
+}
+
+/// Session id from its start time, e.g. "20260115-093000"
+pub fn session_id(start: &chrono::DateTime<chrono::Local>) -> String {
+    start.format("%Y%m%d-%H%M%S").to_string()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SessionsConfig {
+    /// DONE closes the session; false = only IDLE does, so back-to-back
+    /// cycles stay in one session (subject to the split rules below)
+    #[serde(default = "default_close_on_done")]
+    pub close_on_done: bool,
+    /// Local "HH:MM" at which a running session is closed and the next part opened, "" = never
+    #[serde(default)]
+    pub split_daily_at: String,
+    /// Close and open the next part when a session already has this many sniff cycles, 0 = never
+    #[serde(default)]
+    pub split_after_cycles: u32,
+}
+
+fn default_close_on_done() -> bool { true }
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self { close_on_done: default_close_on_done(), split_daily_at: String::new(), split_after_cycles: 0 }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    sessions: SessionsConfig,
+}
+
+impl SessionsConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().sessions
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.split_daily_at.is_empty() {
+            chrono::NaiveTime::parse_from_str(&self.split_daily_at, "%H:%M")
+                .map_err(|_| format!("sessions.split_daily_at '{}' is not HH:MM", self.split_daily_at))?;
+        }
+        Ok(())
+    }
+
+    fn running(&self, state: i32) -> bool {
+        match state {
+            DONE => !self.close_on_done,
+            state => in_session(state),
+        }
+    }
+
+    /// First split_daily_at after `after`
+    fn next_split(&self, after: chrono::DateTime<chrono::Local>) -> Option<chrono::DateTime<chrono::Local>> {
+        let time = chrono::NaiveTime::parse_from_str(&self.split_daily_at, "%H:%M").ok()?;
+        let at = |date: chrono::NaiveDate| date.and_time(time).and_local_timezone(chrono::Local).earliest();
+        at(after.date_naive()).filter(|t| *t > after).or_else(|| at(after.date_naive().succ_opt()?))
+    }
+}
+
+/// Values kept per channel for percentiles; beyond this every other one is dropped
+const SAMPLE_CAPACITY: usize = 4096;
+
+/// Running statistics of one channel over a session. Percentiles come from
+/// an evenly thinned sample, so memory stays bounded on multi-day runs.
+#[derive(Default)]
+struct ChannelStats {
+    count: u64,
+    sum: f64,
+    min: f32,
+    max: f32,
+    sample: Vec<f32>,
+    /// Every stride-th value goes into `sample`
+    stride: u64,
+}
+
+impl ChannelStats {
+    fn push(&mut self, value: f32) {
+        if !value.is_finite() {
+            return;
+        }
+        if self.count == 0 {
+            (self.min, self.max, self.stride) = (value, value, 1);
+        }
+        if self.count.is_multiple_of(self.stride) {
+            self.sample.push(value);
+            if self.sample.len() >= SAMPLE_CAPACITY {
+                self.sample = self.sample.iter().step_by(2).copied().collect();
+                self.stride *= 2;
+            }
+        }
+        self.count += 1;
+        self.sum += value as f64;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn summary(&self) -> Option<ChannelSummary> {
+        if self.count == 0 {
+            return None;
+        }
+        let mut sorted = self.sample.clone();
+        sorted.sort_by(f32::total_cmp);
+        let percentile = |q: f64| sorted[((q * (sorted.len() - 1) as f64).round() as usize).min(sorted.len() - 1)];
+        Some(ChannelSummary {
+            count: self.count,
+            mean: (self.sum / self.count as f64) as f32,
+            min: self.min,
+            max: self.max,
+            p5: percentile(0.05),
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChannelSummary {
+    pub count: u64,
+    pub mean: f32,
+    pub min: f32,
+    pub max: f32,
+    pub p5: f32,
+    pub p50: f32,
+    pub p95: f32,
+}
+
+/// Stored with a session when it closes
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionSummary {
+    pub frames: u64,
+    pub cycles: u32,
+    /// Alerts that started firing during the session
+    pub alarms: u32,
+    /// Cycles aborted by [cycle_abort] rules
+    #[serde(default)]
+    pub invalid_cycles: u32,
+    /// Physical and virtual channels; channels without values are left out
+    pub channels: BTreeMap<String, ChannelSummary>,
+}
+
+/// A `sessions` row, as served by GET /api/sessions
+#[derive(Debug, Serialize)]
+pub struct SessionRecord {
+    pub id: String,
+    pub label: Option<String>,
+    /// Devices that sent frames during the session
+    pub devices: Vec<String>,
+    pub run: Option<String>,
+    pub part: u32,
+    pub started_at: i64,
+    pub stopped_at: Option<i64>,
+    pub level: i32,
+    pub cycles: u32,
+    pub closed_by: Option<String>,
+    pub locked_at: Option<i64>,
+    pub locked_by: Option<String>,
+    /// Null while running and for sessions closed before summaries existed
+    pub summary: Option<SessionSummary>,
+}
+
+/// GET /api/sessions query and SESSIONS arguments; all optional
+#[derive(Debug, Deserialize, Default)]
+pub struct SessionFilter {
+    /// Sessions running at or after this time, epoch ms
+    pub from: Option<i64>,
+    /// Sessions started at or before this time, epoch ms
+    pub to: Option<i64>,
+    /// Case-insensitive substring of the label
+    pub label: Option<String>,
+    pub device: Option<String>,
+}
+
+impl SessionFilter {
+    fn matches(&self, session: &SessionRecord) -> bool {
+        self.from.is_none_or(|from| session.stopped_at.is_none_or(|stopped| stopped >= from))
+            && self.to.is_none_or(|to| session.started_at <= to)
+            && self.label.as_ref().is_none_or(|label| {
+                session.label.as_ref().is_some_and(|l| l.to_lowercase().contains(&label.to_lowercase()))
+            })
+            && self.device.as_ref().is_none_or(|device| session.devices.contains(device))
+    }
+}
+
+/// The open session
+struct Current {
+    id: String,
+    /// Id of the first part of an unattended run
+    run: String,
+    part: u32,
+    cycles: u32,
+    split_at: Option<chrono::DateTime<chrono::Local>>,
+    frames: u64,
+    alarms: u32,
+    invalid_cycles: u32,
+    /// (device, cycle) already counted in invalid_cycles
+    invalidated: BTreeSet<(String, u32)>,
+    channels: BTreeMap<String, ChannelStats>,
+    devices: BTreeSet<String>,
+}
+
+impl Current {
+    fn record(&mut self, frame: &UnifiedSensorData) {
+        self.frames += 1;
+        for (channel, value) in frame.channels.iter() {
+            self.channels.entry(channel.name().to_string()).or_default().push(value);
+        }
+        for (name, value) in &frame.virtual_channels {
+            self.channels.entry(name.clone()).or_default().push(*value);
+        }
+    }
+
+    fn summary(&self) -> SessionSummary {
+        SessionSummary {
+            frames: self.frames,
+            cycles: self.cycles,
+            alarms: self.alarms,
+            invalid_cycles: self.invalid_cycles,
+            channels: self.channels.iter().filter_map(|(name, stats)| Some((name.clone(), stats.summary()?))).collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Tracker {
+    last_state: Option<i32>,
+    current: Option<Current>,
+}
+
+// ================= Session Log =================
+/// Records session start/stop in the local store, tags frames with their
+/// session and refuses frames tagged with a locked (finalized) session.
+/// Long runs are split into parts by the [sessions] rules; the parts share
+/// a `run` (id of the first part) and are numbered by `part`.
+#[derive(Clone)]
+pub struct Sessions {
+    config: Arc<SessionsConfig>,
+    store: LocalStore,
+    tracker: Arc<Mutex<Tracker>>,
+    locked: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Sessions {
+    pub fn new(config: SessionsConfig, store: LocalStore) -> anyhow::Result<Self> {
+        let locked = store
+            .conn()
+            .prepare("SELECT id FROM sessions WHERE locked_at IS NOT NULL")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<HashSet<String>, _>>()?;
+        Ok(Self {
+            config: Arc::new(config),
+            store,
+            tracker: Arc::new(Mutex::new(Tracker::default())),
+            locked: Arc::new(Mutex::new(locked)),
+        })
+    }
+
+    /// Tag a local frame with the running session; frames that already carry
+    /// a session (e.g. forwarded by an edge) keep theirs.
+    /// Err if the frame belongs to a locked session and must not be written.
+    pub fn observe(&self, frame: &mut UnifiedSensorData) -> Result<(), String> {
+        if frame.session.is_empty() {
+            self.track(frame);
+        }
+        if !frame.session.is_empty() && self.locked.lock().unwrap().contains(&frame.session) {
+            return Err(format!("session {} is locked", frame.session));
+        }
+        Ok(())
+    }
+
+    fn track(&self, frame: &mut UnifiedSensorData) {
+        let mut tracker = self.tracker.lock().unwrap();
+        let new_cycle = frame.state == PRE_COND && tracker.last_state != Some(PRE_COND);
+        tracker.last_state = Some(frame.state);
+        let now = chrono::Local::now();
+
+        if !self.config.running(frame.state) {
+            if let Some(current) = tracker.current.take() {
+                self.close(&current, if frame.state == DONE { "done" } else { "idle" }, &now);
+            }
+            return;
+        }
+
+        let split = match &tracker.current {
+            None => None,
+            Some(current) if new_cycle && self.config.split_after_cycles > 0 && current.cycles >= self.config.split_after_cycles => {
+                Some("cycles")
+            }
+            Some(current) if current.split_at.is_some_and(|at| now >= at) => Some("daily"),
+            Some(_) => None,
+        };
+        if let Some(reason) = split {
+            let previous = tracker.current.take();
+            if let Some(previous) = &previous {
+                self.close(previous, reason, &now);
+            }
+            let next = self.open(previous.as_ref(), frame.level, &now);
+            println!("🗂️ Session {} split ({}), continuing in {}", next.run, reason, next.id);
+            tracker.current = Some(next);
+        }
+        if tracker.current.is_none() {
+            tracker.current = Some(self.open(None, frame.level, &now));
+        }
+
+        let Some(current) = tracker.current.as_mut() else { return };
+        // Frame dari board yang belum dikonfirmasi tidak disimpan, jadi tidak dihitung
+        if !frame.unverified {
+            current.record(frame);
+        }
+        if !frame.device.is_empty() && current.devices.insert(frame.device.clone()) {
+            let devices = current.devices.iter().cloned().collect::<Vec<_>>().join(",");
+            if let Err(e) = self.store.conn().execute(
+                "UPDATE sessions SET devices = ?1 WHERE id = ?2",
+                params![devices, current.id],
+            ) {
+                eprintln!("❌ Failed to record session: {}", e);
+            }
+        }
+        if new_cycle {
+            current.cycles += 1;
+            if let Err(e) = self.store.conn().execute(
+                "UPDATE sessions SET cycles = ?1 WHERE id = ?2",
+                params![current.cycles, current.id],
+            ) {
+                eprintln!("❌ Failed to record session: {}", e);
+            }
+        }
+        frame.session = current.id.clone();
+    }
+
+    /// Start a session, the next part of `previous`'s run if given
+    fn open(&self, previous: Option<&Current>, level: i32, now: &chrono::DateTime<chrono::Local>) -> Current {
+        let mut id = session_id(now);
+        let (run, part) = match previous {
+            Some(previous) => (previous.run.clone(), previous.part + 1),
+            None => (id.clone(), 1),
+        };
+        // Bagian baru bisa dibuka pada detik yang sama dengan yang ditutup
+        if previous.is_some_and(|p| p.id == id) {
+            id = format!("{}-{}", id, part);
+        }
+        if let Err(e) = self.store.conn().execute(
+            "INSERT OR IGNORE INTO sessions (id, started_at, level, run, part) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, now.timestamp_millis(), level, run, part],
+        ) {
+            eprintln!("❌ Failed to record session: {}", e);
+        }
+        Current {
+            id,
+            run,
+            part,
+            cycles: 0,
+            split_at: self.config.next_split(*now),
+            frames: 0,
+            alarms: 0,
+            invalid_cycles: 0,
+            invalidated: BTreeSet::new(),
+            channels: BTreeMap::new(),
+            devices: BTreeSet::new(),
+        }
+    }
+
+    fn close(&self, current: &Current, reason: &str, now: &chrono::DateTime<chrono::Local>) {
+        let summary = serde_json::to_string(&current.summary()).unwrap_or_default();
+        if let Err(e) = self.store.conn().execute(
+            "UPDATE sessions SET stopped_at = ?1, closed_by = ?2, cycles = ?3, summary = ?4 WHERE id = ?5",
+            params![now.timestamp_millis(), reason, current.cycles, summary, current.id],
+        ) {
+            eprintln!("❌ Failed to record session: {}", e);
+        }
+    }
+
+    /// An alert started firing
+    fn alarm(&self) {
+        if let Some(current) = self.tracker.lock().unwrap().current.as_mut() {
+            current.alarms += 1;
+        }
+    }
+
+    /// Id of the open session, if any
+    pub fn current(&self) -> Option<String> {
+        self.tracker.lock().unwrap().current.as_ref().map(|current| current.id.clone())
+    }
+
+    /// Mark the running cycle of the open session invalid; returns (session, cycle).
+    /// Every reason is recorded, the cycle is counted once per device.
+    pub fn invalidate_cycle(&self, device: &str, reason: &str, timestamp: i64) -> Option<(String, u32)> {
+        let mut tracker = self.tracker.lock().unwrap();
+        let current = tracker.current.as_mut()?;
+        if current.invalidated.insert((device.to_string(), current.cycles)) {
+            current.invalid_cycles += 1;
+        }
+        if let Err(e) = self.store.conn().execute(
+            "INSERT INTO cycle_aborts (session, cycle, device, reason, timestamp) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![current.id, current.cycles, device, reason, timestamp],
+        ) {
+            eprintln!("❌ Failed to record session: {}", e);
+        }
+        Some((current.id.clone(), current.cycles))
+    }
+
+    /// Sessions matching `filter`, newest first
+    pub fn list(&self, filter: &SessionFilter) -> rusqlite::Result<Vec<SessionRecord>> {
+        let conn = self.store.conn();
+        let mut statement = conn.prepare(&format!("{} ORDER BY started_at DESC", SELECT_SESSION))?;
+        let rows = statement.query_map([], session_record)?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?.into_iter().filter(|s| filter.matches(s)).collect())
+    }
+
+    pub fn get(&self, id: &str) -> rusqlite::Result<Option<SessionRecord>> {
+        self.store
+            .conn()
+            .query_row(&format!("{} WHERE id = ?1", SELECT_SESSION), [id], session_record)
+            .optional()
+    }
+
+    /// Set or clear ("") a session's label
+    pub fn relabel(&self, id: &str, label: &str) -> Result<(), String> {
+        let label = label.trim();
+        let changed = self
+            .store
+            .conn()
+            .execute(
+                "UPDATE sessions SET label = ?1 WHERE id = ?2",
+                params![(!label.is_empty()).then_some(label), id],
+            )
+            .map_err(|e| e.to_string())?;
+        match changed {
+            0 => Err(format!("unknown session {}", id)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Delete a closed, unlocked session: its InfluxDB points (everything
+    /// tagged with it) and then its record, predictions and cycle aborts
+    pub async fn delete(&self, id: &str, influx: &InfluxDBHandler) -> Result<(), String> {
+        let session = self.get(id).map_err(|e| e.to_string())?.ok_or_else(|| format!("unknown session {}", id))?;
+        let Some(stopped_at) = session.stopped_at else {
+            return Err(format!("session {} is still running", id));
+        };
+        if session.locked_at.is_some() {
+            return Err(format!("session {} is locked", id));
+        }
+        // Titik diberi timestamp dari device; beri kelonggaran untuk clock drift
+        let margin = 86_400_000;
+        let range = |ms: i64| chrono::DateTime::from_timestamp_millis(ms).unwrap_or_default().naive_utc();
+        influx
+            .client()
+            .delete(
+                influx.bucket(),
+                range(session.started_at - margin),
+                range(stopped_at + margin),
+                Some(format!("session=\"{}\"", id)),
+            )
+            .await
+            .map_err(|e| format!("InfluxDB delete failed: {}", e))?;
+        // Semua baris lokal milik sesi dihapus bersama, atau tidak sama sekali
+        let mut conn = self.store.conn();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for table in ["predictions", "cycle_aborts"] {
+            tx.execute(&format!("DELETE FROM {} WHERE session = ?1", table), [id]).map_err(|e| e.to_string())?;
+        }
+        tx.execute("DELETE FROM sessions WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        println!("🗑️ Session {} deleted", id);
+        Ok(())
+    }
+
+    /// Finalize a closed session; later points tagged with it are refused.
+    /// There is deliberately no unlock.
+    pub fn lock(&self, id: &str, actor: &str) -> Result<(), String> {
+        let conn = self.store.conn();
+        let row: Option<(Option<i64>, Option<i64>)> = conn
+            .query_row("SELECT stopped_at, locked_at FROM sessions WHERE id = ?1", [id], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .optional()
+            .map_err(|e| e.to_string())?;
+        match row {
+            None => return Err(format!("unknown session {}", id)),
+            Some((None, _)) => return Err(format!("session {} is still running", id)),
+            Some((_, Some(_))) => return Err(format!("session {} is already locked", id)),
+            Some(_) => {}
+        }
+        conn.execute(
+            "UPDATE sessions SET locked_at = ?1, locked_by = ?2 WHERE id = ?3",
+            params![chrono::Utc::now().timestamp_millis(), actor, id],
+        )
+        .map_err(|e| e.to_string())?;
+        self.locked.lock().unwrap().insert(id.to_string());
+        println!("🔒 Session {} locked by {}", id, actor);
+        Ok(())
+    }
+}
+
+const SELECT_SESSION: &str = "SELECT id, run, part, started_at, stopped_at, level, cycles, closed_by, locked_at, locked_by, summary, label, devices FROM sessions";
+
+fn session_record(row: &rusqlite::Row) -> rusqlite::Result<SessionRecord> {
+    let summary: Option<String> = row.get(10)?;
+    let devices: Option<String> = row.get(12)?;
+    Ok(SessionRecord {
+        id: row.get(0)?,
+        label: row.get(11)?,
+        devices: devices.map(|d| d.split(',').map(str::to_string).collect()).unwrap_or_default(),
+        run: row.get(1)?,
+        part: row.get(2)?,
+        started_at: row.get(3)?,
+        stopped_at: row.get(4)?,
+        level: row.get(5)?,
+        cycles: row.get(6)?,
+        closed_by: row.get(7)?,
+        locked_at: row.get(8)?,
+        locked_by: row.get(9)?,
+        summary: summary.and_then(|s| serde_json::from_str(&s).ok()),
+    })
+}
+
+/// Count alerts that start firing against the open session
+pub async fn watch_alerts(sessions: Sessions, alerts: AlertEngine) {
+    let mut events = alerts.subscribe();
+    loop {
+        match events.recv().await {
+            Ok(event) if event.state == AlertState::Firing && event.reason == NotifyReason::Transition => sessions.alarm(),
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// GUI commands:
+///   SESSIONS [from=<ms>] [to=<ms>] [label=<text>] [device=<id>]
+///   SESSION_SUMMARY <id>
+///   SESSION_LABEL <id> [label...]   no label clears it
+///   SESSION_DELETE <id>             closed, unlocked sessions only
+pub async fn handle_command(
+    sessions: &Sessions,
+    influx: &InfluxDBHandler,
+    audit: &AuditLog,
+    client: &str,
+    args: &[&str],
+) -> Result<Option<String>, String> {
+    match args {
+        ["SESSIONS", filters @ ..] => {
+            let mut filter = SessionFilter::default();
+            for arg in filters {
+                let number = |v: &str| v.parse::<i64>().map_err(|_| format!("invalid time '{}'", v));
+                match arg.split_once('=') {
+                    Some(("from", v)) => filter.from = Some(number(v)?),
+                    Some(("to", v)) => filter.to = Some(number(v)?),
+                    Some(("label", v)) => filter.label = Some(v.to_string()),
+                    Some(("device", v)) => filter.device = Some(v.to_string()),
+                    _ => return Err(format!("unknown filter '{}'", arg)),
+                }
+            }
+            let list = sessions.list(&filter).map_err(|e| e.to_string())?;
+            Ok(Some(serde_json::json!({ "type": "sessions", "sessions": list }).to_string()))
+        }
+        ["SESSION_SUMMARY", id] => {
+            let session = sessions.get(id).map_err(|e| e.to_string())?.ok_or_else(|| format!("unknown session {}", id))?;
+            let mut event = serde_json::to_value(session).unwrap_or_default();
+            event["type"] = "session".into();
+            Ok(Some(event.to_string()))
+        }
+        ["SESSION_LABEL", id, label @ ..] => {
+            let label = label.join(" ");
+            sessions.relabel(id, &label)?;
+            audit.record(client, "session_label", serde_json::json!({ "session": id, "label": label }));
+            Ok(None)
+        }
+        ["SESSION_DELETE", id] => {
+            sessions.delete(id, influx).await?;
+            audit.record(client, "session_delete", serde_json::json!({ "session": id }));
+            Ok(None)
+        }
+        _ => Err(
+            "usage: SESSIONS [from=<ms>] [to=<ms>] [label=<text>] [device=<id>] | SESSION_SUMMARY <id> | SESSION_LABEL <id> [label] | SESSION_DELETE <id>"
+                .to_string(),
+        ),
+    }
+}
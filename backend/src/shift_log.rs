@@ -0,0 +1,235 @@
+use influxdb2::models::DataPoint;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::alerts::{AlertEngine, AlertEvent};
+use crate::experiments::{ExperimentQueue, ExperimentRun};
+use crate::influxdb::InfluxDBHandler;
+use crate::sessions::Sessions;
+use crate::store::LocalStore;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ShiftLogConfig {
+    /// Notes covered by a handover when none is given, hours back
+    #[serde(default = "default_handover_hours")]
+    pub handover_hours: f64,
+    #[serde(default = "default_max_chars")]
+    pub max_chars: usize,
+}
+
+fn default_handover_hours() -> f64 { 12.0 }
+fn default_max_chars() -> usize { 2000 }
+
+impl Default for ShiftLogConfig {
+    fn default() -> Self {
+        Self { handover_hours: default_handover_hours(), max_chars: default_max_chars() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    shift_log: ShiftLogConfig,
+}
+
+impl ShiftLogConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().shift_log
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.handover_hours <= 0.0 || self.max_chars == 0 {
+            return Err("shift_log.handover_hours and shift_log.max_chars must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// One operator note, as stored and served
+#[derive(Debug, Serialize, Clone)]
+pub struct ShiftNote {
+    pub id: i64,
+    pub timestamp: i64,
+    pub author: String,
+    pub text: String,
+    /// Session open when the note was posted
+    pub session: Option<String>,
+    pub device: Option<String>,
+}
+
+/// GET /api/shift-log query; all optional
+#[derive(Debug, Deserialize, Default)]
+pub struct NoteQuery {
+    /// Epoch ms bounds (inclusive)
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub session: Option<String>,
+    /// Newest N notes
+    pub limit: Option<usize>,
+}
+
+/// What the next shift needs to know
+#[derive(Debug, Serialize)]
+pub struct Handover {
+    pub generated_at: i64,
+    /// Notes from this time on are included
+    pub since: i64,
+    pub session: Option<String>,
+    pub open_alerts: Vec<AlertEvent>,
+    pub running_experiments: Vec<ExperimentRun>,
+    /// Oldest first
+    pub notes: Vec<ShiftNote>,
+}
+
+// ================= Shift Log =================
+/// Operator notes in the local store. Each note is tied to the open
+/// session and put on the timeline (GUI "shift_note" event, InfluxDB
+/// "annotations" point with source "shift_log").
+#[derive(Clone)]
+pub struct ShiftLog {
+    config: Arc<ShiftLogConfig>,
+    store: LocalStore,
+    sessions: Sessions,
+    influx: InfluxDBHandler,
+    data_tx: broadcast::Sender<String>,
+}
+
+impl ShiftLog {
+    pub fn new(
+        config: ShiftLogConfig,
+        store: LocalStore,
+        sessions: Sessions,
+        influx: InfluxDBHandler,
+        data_tx: broadcast::Sender<String>,
+    ) -> Self {
+        Self { config: Arc::new(config), store, sessions, influx, data_tx }
+    }
+
+    pub async fn post(&self, author: &str, text: &str, device: Option<&str>) -> Result<ShiftNote, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("note text is empty".to_string());
+        }
+        if text.chars().count() > self.config.max_chars {
+            return Err(format!("note is longer than {} characters", self.config.max_chars));
+        }
+        let mut note = ShiftNote {
+            id: 0,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            author: author.to_string(),
+            text: text.to_string(),
+            session: self.sessions.current(),
+            device: device.map(str::to_string),
+        };
+        {
+            let conn = self.store.conn();
+            conn.execute(
+                "INSERT INTO shift_notes (timestamp, author, text, session, device) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![note.timestamp, note.author, note.text, note.session, note.device],
+            )
+            .map_err(|e| e.to_string())?;
+            note.id = conn.last_insert_rowid();
+        }
+        println!("📝 Shift note #{} by {}: {}", note.id, note.author, note.text);
+
+        let mut event = serde_json::to_value(&note).unwrap_or_default();
+        event["type"] = "shift_note".into();
+        let _ = self.data_tx.send(event.to_string());
+
+        let mut point = DataPoint::builder("annotations")
+            .tag("source", "shift_log")
+            .tag("author", note.author.clone())
+            .field("text", note.text.clone());
+        if let Some(session) = &note.session {
+            point = point.tag("session", session.clone());
+        }
+        if let Some(device) = &note.device {
+            point = point.tag("device", device.clone());
+        }
+        if let Ok(point) = point.timestamp(note.timestamp * 1_000_000).build() {
+            if let Err(e) = self.influx.write_points(vec![point]).await {
+                eprintln!("❌ Annotation write error: {:?}", e);
+            }
+        }
+        Ok(note)
+    }
+
+    /// Notes matching `query`, oldest first
+    pub fn notes(&self, query: &NoteQuery) -> Result<Vec<ShiftNote>, String> {
+        let conn = self.store.conn();
+        let mut statement = conn
+            .prepare(
+                "SELECT id, timestamp, author, text, session, device FROM shift_notes
+                 WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2) AND (?3 IS NULL OR session = ?3)
+                 ORDER BY timestamp DESC, id DESC LIMIT ?4",
+            )
+            .map_err(|e| e.to_string())?;
+        let limit = query.limit.map_or(-1, |l| l as i64);
+        let rows = statement
+            .query_map(rusqlite::params![query.from, query.to, query.session, limit], |row| {
+                Ok(ShiftNote {
+                    id: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    author: row.get(2)?,
+                    text: row.get(3)?,
+                    session: row.get(4)?,
+                    device: row.get(5)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        let mut notes = rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+        notes.reverse();
+        Ok(notes)
+    }
+
+    /// Open alerts, running experiments and the notes of the last `hours`
+    pub fn handover(&self, alerts: &AlertEngine, queue: &ExperimentQueue, hours: Option<f64>) -> Result<Handover, String> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let since = now - (hours.unwrap_or(self.config.handover_hours) * 3_600_000.0) as i64;
+        Ok(Handover {
+            generated_at: now,
+            since,
+            session: self.sessions.current(),
+            open_alerts: alerts.open(),
+            running_experiments: queue.running(),
+            notes: self.notes(&NoteQuery { from: Some(since), ..Default::default() })?,
+        })
+    }
+}
+
+/// GUI commands:
+///   SHIFT_NOTE <text...>
+///   SHIFT_LOG [hours]      notes of the last hours (default handover_hours)
+///   HANDOVER [hours]
+pub async fn handle_command(
+    log: &ShiftLog,
+    alerts: &AlertEngine,
+    queue: &ExperimentQueue,
+    client: &str,
+    args: &[&str],
+) -> Result<Option<String>, String> {
+    let hours = |arg: Option<&&str>| {
+        arg.map(|h| h.parse::<f64>().ok().filter(|h| *h > 0.0).ok_or_else(|| format!("invalid number of hours '{}'", h)))
+            .transpose()
+    };
+    match args {
+        ["SHIFT_NOTE", text @ ..] if !text.is_empty() => {
+            log.post(client, &text.join(" "), None).await?;
+            Ok(None)
+        }
+        ["SHIFT_LOG", rest @ ..] if rest.len() <= 1 => {
+            let hours = hours(rest.first())?.unwrap_or(log.config.handover_hours);
+            let from = chrono::Utc::now().timestamp_millis() - (hours * 3_600_000.0) as i64;
+            let notes = log.notes(&NoteQuery { from: Some(from), ..Default::default() })?;
+            Ok(Some(serde_json::json!({ "type": "shift_log", "notes": notes }).to_string()))
+        }
+        ["HANDOVER", rest @ ..] if rest.len() <= 1 => {
+            let mut event = serde_json::to_value(log.handover(alerts, queue, hours(rest.first())?)?).unwrap_or_default();
+            event["type"] = "handover".into();
+            Ok(Some(event.to_string()))
+        }
+        _ => Err("usage: SHIFT_NOTE <text> | SHIFT_LOG [hours] | HANDOVER [hours]".to_string()),
+    }
+}
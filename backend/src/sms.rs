@@ -0,0 +1,147 @@
+use serde::Deserialize;
+use tokio::sync::broadcast;
+
+use crate::alerts::{AlertEvent, AlertState};
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmsProvider {
+    Twilio,
+    /// Generic HTTP gateway: POST JSON {"to": ..., "message": ...}
+    Gateway,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SmsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_provider")]
+    pub provider: SmsProvider,
+    #[serde(default)]
+    pub account_sid: String,
+    /// Falls back to the TWILIO_AUTH_TOKEN environment variable
+    #[serde(default)]
+    pub auth_token: String,
+    #[serde(default)]
+    pub from: String,
+    #[serde(default)]
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub gateway_url: String,
+    /// Alerts with one of these severities are sent
+    #[serde(default = "default_severities")]
+    pub severities: Vec<String>,
+    /// Alerts routed to this target (base or escalation) are sent too
+    #[serde(default = "default_target")]
+    pub target: String,
+    #[serde(default = "default_notify_resolved")]
+    pub notify_resolved: bool,
+}
+
+fn default_provider() -> SmsProvider { SmsProvider::Twilio }
+fn default_severities() -> Vec<String> { vec!["critical".to_string()] }
+fn default_target() -> String { "sms".to_string() }
+fn default_notify_resolved() -> bool { true }
+
+impl Default for SmsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider: default_provider(),
+            account_sid: String::new(),
+            auth_token: String::new(),
+            from: String::new(),
+            to: Vec::new(),
+            gateway_url: String::new(),
+            severities: default_severities(),
+            target: default_target(),
+            notify_resolved: default_notify_resolved(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    sms: SmsConfig,
+}
+
+impl SmsConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().sms
+    }
+
+    fn wants(&self, event: &AlertEvent) -> bool {
+        if !event.should_notify() || (event.state == AlertState::Resolved && !self.notify_resolved) {
+            return false;
+        }
+        self.severities.contains(&event.severity) || event.targets.contains(&self.target)
+    }
+}
+
+fn format_message(event: &AlertEvent) -> String {
+    let what = match event.state {
+        AlertState::Resolved => "resolved".to_string(),
+        _ => format!("{}: {}", event.severity.to_uppercase(), event.condition),
+    };
+    let time = chrono::DateTime::from_timestamp_millis(event.timestamp)
+        .map(|t| t.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+        .unwrap_or_default();
+    format!("[E-Nose {}] {} {} ({})", event.device, event.name, what, time)
+}
+
+async fn send_sms(client: &reqwest::Client, config: &SmsConfig, to: &str, message: &str) -> anyhow::Result<()> {
+    let request = match config.provider {
+        SmsProvider::Twilio => {
+            let token = if config.auth_token.is_empty() {
+                std::env::var("TWILIO_AUTH_TOKEN").unwrap_or_default()
+            } else {
+                config.auth_token.clone()
+            };
+            let url = format!(
+                "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
+                config.account_sid
+            );
+            client
+                .post(url)
+                .basic_auth(&config.account_sid, Some(token))
+                .form(&[("To", to), ("From", config.from.as_str()), ("Body", message)])
+        }
+        SmsProvider::Gateway => client
+            .post(&config.gateway_url)
+            .json(&serde_json::json!({ "to": to, "message": message })),
+    };
+    request.send().await?.error_for_status()?;
+    Ok(())
+}
+
+// ================= SMS Notifier =================
+/// Texts critical alerts to on-site staff when nobody watches the GUI
+pub async fn run_notifier(config: SmsConfig, mut alert_rx: broadcast::Receiver<AlertEvent>) {
+    if config.to.is_empty() {
+        eprintln!("⚠️ SMS notifier enabled without recipients");
+        return;
+    }
+    println!("📱 SMS notifier enabled ({:?}, {} recipient(s))", config.provider, config.to.len());
+    let client = reqwest::Client::new();
+
+    loop {
+        let event = match alert_rx.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if !config.wants(&event) {
+            continue;
+        }
+
+        let message = format_message(&event);
+        for to in &config.to {
+            match send_sms(&client, &config, to, &message).await {
+                Ok(()) => println!("📱 SMS sent to {}: {}", to, message),
+                Err(e) => eprintln!("❌ SMS to {} failed: {}", to, e),
+            }
+        }
+    }
+}
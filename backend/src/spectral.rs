@@ -0,0 +1,98 @@
+use std::f64::consts::PI;
+
+/// In-place radix-2 FFT; the length must be a power of two
+pub fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_re, w_im) = ((angle * k as f64).cos(), (angle * k as f64).sin());
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// One-sided sine-amplitude spectrum of evenly spaced samples: linear
+/// trend removed, Hann window, zero-padded to a power of two; bins are
+/// `bin_hz(len, sample_rate)` apart, starting at 0 Hz
+pub fn magnitudes(samples: &[f64]) -> Vec<f64> {
+    let n = samples.len();
+    if n < 4 {
+        return Vec::new();
+    }
+    let size = n.next_power_of_two();
+    let mean_t = (n - 1) as f64 / 2.0;
+    let mean_v = samples.iter().sum::<f64>() / n as f64;
+    let (cov, var) = samples.iter().enumerate().fold((0.0, 0.0), |(cov, var), (i, v)| {
+        (cov + (i as f64 - mean_t) * (v - mean_v), var + (i as f64 - mean_t).powi(2))
+    });
+    let slope = cov / var;
+
+    let mut re = vec![0.0; size];
+    let mut im = vec![0.0; size];
+    for (i, v) in samples.iter().enumerate() {
+        let hann = 0.5 - 0.5 * (2.0 * PI * i as f64 / (n - 1) as f64).cos();
+        re[i] = (v - mean_v - slope * (i as f64 - mean_t)) * hann;
+    }
+    fft(&mut re, &mut im);
+    // Hann: amplitudo sinus = 4·|X| / n
+    (0..=size / 2).map(|k| 4.0 * re[k].hypot(im[k]) / n as f64).collect()
+}
+
+/// Frequency spacing of the `magnitudes` bins of `len` samples
+pub fn bin_hz(len: usize, sample_rate: f64) -> f64 {
+    sample_rate / len.next_power_of_two() as f64
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Peak {
+    pub frequency: f64,
+    /// Peak power over the median power of the searched band
+    pub snr: f64,
+}
+
+/// Strongest bin in [min_hz, max_hz], refined by parabolic interpolation
+pub fn dominant(magnitudes: &[f64], bin_hz: f64, min_hz: f64, max_hz: f64) -> Option<Peak> {
+    let first = ((min_hz / bin_hz).ceil() as usize).max(1);
+    let last = ((max_hz / bin_hz).floor() as usize).min(magnitudes.len().checked_sub(2)?);
+    if first + 2 > last {
+        return None;
+    }
+    let band = &magnitudes[first..=last];
+    let (offset, &peak) = band.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))?;
+    let k = first + offset;
+    let (a, b, c) = (magnitudes[k - 1], peak, magnitudes[k + 1]);
+    let curvature = a - 2.0 * b + c;
+    let delta = if curvature.abs() > f64::EPSILON { (0.5 * (a - c) / curvature).clamp(-0.5, 0.5) } else { 0.0 };
+
+    let mut powers: Vec<f64> = band.iter().map(|m| m * m).collect();
+    powers.sort_by(f64::total_cmp);
+    let median = powers[powers.len() / 2];
+    Some(Peak {
+        frequency: (k as f64 + delta) * bin_hz,
+        snr: if median > 0.0 { peak * peak / median } else { f64::INFINITY },
+    })
+}
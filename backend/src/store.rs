@@ -0,0 +1,237 @@
+use anyhow::Context;
+use rusqlite::Connection;
+use serde::Deserialize;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct StoreConfig {
+    #[serde(default = "default_path")]
+    pub path: String,
+}
+
+fn default_path() -> String { "enose.db".to_string() }
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self { path: default_path() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    store: StoreConfig,
+}
+
+impl StoreConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().store
+    }
+}
+
+/// Embedded schema migrations; entry N brings the schema to version N + 1.
+/// Released entries must never be edited, only appended to.
+const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "sessions",
+        "CREATE TABLE sessions (
+            id TEXT PRIMARY KEY,
+            started_at INTEGER NOT NULL,
+            stopped_at INTEGER,
+            level INTEGER NOT NULL
+        );",
+    ),
+    (
+        "uplink_queue",
+        "CREATE TABLE uplink_queue (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            payload TEXT NOT NULL
+        );",
+    ),
+    (
+        "session_lock",
+        "ALTER TABLE sessions ADD COLUMN locked_at INTEGER;
+        ALTER TABLE sessions ADD COLUMN locked_by TEXT;",
+    ),
+    (
+        "device_seen",
+        "CREATE TABLE device_seen (
+            device TEXT PRIMARY KEY,
+            last_frame INTEGER NOT NULL
+        );",
+    ),
+    (
+        "device_boards",
+        "CREATE TABLE device_boards (
+            device TEXT PRIMARY KEY,
+            board TEXT NOT NULL,
+            confirmed_at INTEGER NOT NULL,
+            confirmed_by TEXT NOT NULL
+        );",
+    ),
+    (
+        "session_split",
+        "ALTER TABLE sessions ADD COLUMN cycles INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE sessions ADD COLUMN run TEXT;
+        ALTER TABLE sessions ADD COLUMN part INTEGER NOT NULL DEFAULT 1;
+        ALTER TABLE sessions ADD COLUMN closed_by TEXT;",
+    ),
+    ("session_summary", "ALTER TABLE sessions ADD COLUMN summary TEXT;"),
+    (
+        "session_label",
+        "ALTER TABLE sessions ADD COLUMN label TEXT;
+        ALTER TABLE sessions ADD COLUMN devices TEXT;",
+    ),
+    (
+        "predictions",
+        "CREATE TABLE predictions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            device TEXT NOT NULL,
+            session TEXT NOT NULL,
+            label TEXT NOT NULL,
+            confidence REAL NOT NULL,
+            model TEXT NOT NULL
+        );
+        CREATE INDEX predictions_timestamp ON predictions (timestamp);",
+    ),
+    (
+        "shadow_predictions",
+        "ALTER TABLE predictions ADD COLUMN shadow INTEGER NOT NULL DEFAULT 0;",
+    ),
+    (
+        "characterizations",
+        "CREATE TABLE characterizations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            device TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            measured_at INTEGER NOT NULL,
+            record TEXT NOT NULL
+        );
+        CREATE INDEX characterizations_device ON characterizations (device, kind);",
+    ),
+    (
+        "cycle_aborts",
+        "CREATE TABLE cycle_aborts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session TEXT NOT NULL,
+            cycle INTEGER NOT NULL,
+            device TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            timestamp INTEGER NOT NULL
+        );
+        CREATE INDEX cycle_aborts_session ON cycle_aborts (session);",
+    ),
+    (
+        "shift_notes",
+        "CREATE TABLE shift_notes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            author TEXT NOT NULL,
+            text TEXT NOT NULL,
+            session TEXT,
+            device TEXT
+        );
+        CREATE INDEX shift_notes_timestamp ON shift_notes (timestamp);",
+    ),
+    (
+        "usage",
+        "CREATE TABLE usage (
+            day TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            name TEXT NOT NULL,
+            value REAL NOT NULL,
+            PRIMARY KEY (day, kind, name)
+        );",
+    ),
+];
+
+/// Schema version the running binary expects
+pub fn latest_version() -> u32 {
+    MIGRATIONS.len() as u32
+}
+
+fn schema_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub current: u32,
+    pub latest: u32,
+    /// (version, name) of migrations not applied yet
+    pub pending: Vec<(u32, &'static str)>,
+}
+
+// ================= Local Store =================
+/// SQLite store for local metadata (sessions, ...), migrated on open
+#[derive(Clone)]
+pub struct LocalStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl LocalStore {
+    /// Open the store and apply pending migrations
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        let applied = migrate(path)?;
+        if !applied.is_empty() {
+            println!("🗄️ Local store {} migrated to schema v{}", path, latest_version());
+        }
+        let conn = Connection::open(path).with_context(|| format!("opening {}", path))?;
+        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    pub fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub fn status(path: &str) -> anyhow::Result<MigrationStatus> {
+    let conn = Connection::open(path).with_context(|| format!("opening {}", path))?;
+    let current = schema_version(&conn)?;
+    let pending = MIGRATIONS
+        .iter()
+        .enumerate()
+        .skip(current as usize)
+        .map(|(i, (name, _))| (i as u32 + 1, *name))
+        .collect();
+    Ok(MigrationStatus { current, latest: latest_version(), pending })
+}
+
+/// Bring the database at `path` up to the latest schema.
+///
+/// A database written by a newer release is refused rather than touched.
+/// Existing data is copied to `<path>.v<N>.bak` before the first migration,
+/// and every migration runs in its own transaction.
+pub fn migrate(path: &str) -> anyhow::Result<Vec<(u32, &'static str)>> {
+    let status = status(path)?;
+    if status.current > status.latest {
+        anyhow::bail!(
+            "{} has schema v{}, this release only knows up to v{}; refusing to open",
+            path,
+            status.current,
+            status.latest
+        );
+    }
+    if status.pending.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if status.current > 0 {
+        let backup = format!("{}.v{}.bak", path, status.current);
+        std::fs::copy(path, &backup).with_context(|| format!("backing up {} to {}", path, backup))?;
+        println!("🗄️ Backed up {} (schema v{}) to {}", path, status.current, backup);
+    }
+
+    let mut conn = Connection::open(path)?;
+    for &(version, name) in &status.pending {
+        let sql = MIGRATIONS[version as usize - 1].1;
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql).with_context(|| format!("migration v{} ({})", version, name))?;
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+        println!("🗄️ Applied migration v{}: {}", version, name);
+    }
+    Ok(status.pending)
+}
@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::jobs::JobContext;
+use crate::replay::{self, StoredCycle};
+use crate::roc::round;
+use crate::sessions::{SessionFilter, SessionRecord};
+use crate::AppState;
+
+/// Affine map of one feature into the master unit's response space
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct FeatureMap {
+    #[serde(default = "default_gain")]
+    pub gain: f64,
+    #[serde(default)]
+    pub offset: f64,
+}
+
+fn default_gain() -> f64 { 1.0 }
+
+/// `[transfer]`, resolved per device: every unit responds a little
+/// differently, so its cycle features are mapped onto the master unit the
+/// models were trained on before classification
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct TransferConfig {
+    /// Unit the mapping targets
+    #[serde(default)]
+    pub master: Option<String>,
+    /// `<channel>.<feature>` → gain · value + offset; unlisted features pass unchanged
+    #[serde(default)]
+    pub features: BTreeMap<String, FeatureMap>,
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    transfer: TransferConfig,
+}
+
+impl TransferConfig {
+    pub fn load(path: &str, device: Option<&str>) -> Self {
+        let doc = crate::config::load_document(path, device);
+        toml::Value::Table(doc).try_into::<ConfigFile>().unwrap_or_default().transfer
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, map) in &self.features {
+            if !map.gain.is_finite() || map.gain == 0.0 || !map.offset.is_finite() {
+                return Err(format!("transfer.features.\"{}\" needs a finite, non-zero gain and a finite offset", name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    /// Features as the master unit would have measured them
+    pub fn apply(&self, features: &BTreeMap<String, f64>) -> BTreeMap<String, f64> {
+        features
+            .iter()
+            .map(|(name, value)| {
+                let mapped = self.features.get(name).map_or(*value, |m| m.gain * value + m.offset);
+                (name.clone(), mapped)
+            })
+            .collect()
+    }
+}
+
+/// Learned map of one feature with its fit quality over the shared labels
+#[derive(Debug, Serialize, Clone)]
+pub struct Mapping {
+    pub feature: String,
+    pub gain: f64,
+    pub offset: f64,
+    /// Labels measured on both units
+    pub labels: usize,
+    /// RMS difference of the per-label means before / after mapping
+    pub error_before: f64,
+    pub error_after: f64,
+}
+
+/// Least squares `master ≈ gain · device + offset` over per-label means;
+/// one label (or no spread) only shifts
+fn fit(feature: &str, points: &[(f64, f64)]) -> Mapping {
+    let n = points.len() as f64;
+    let (mean_d, mean_m) = (points.iter().map(|p| p.0).sum::<f64>() / n, points.iter().map(|p| p.1).sum::<f64>() / n);
+    let spread: f64 = points.iter().map(|p| (p.0 - mean_d).powi(2)).sum();
+    let gain = if points.len() >= 2 && spread > 1e-12 {
+        let gain = points.iter().map(|p| (p.0 - mean_d) * (p.1 - mean_m)).sum::<f64>() / spread;
+        if gain.abs() > 1e-9 { gain } else { 1.0 }
+    } else {
+        1.0
+    };
+    let gain = round(gain);
+    let offset = round(mean_m - gain * mean_d);
+    let rms = |f: &dyn Fn(f64) -> f64| (points.iter().map(|p| (f(p.0) - p.1).powi(2)).sum::<f64>() / n).sqrt();
+    Mapping {
+        feature: feature.to_string(),
+        gain,
+        offset,
+        labels: points.len(),
+        error_before: round(rms(&|d| d)),
+        error_after: round(rms(&|d| gain * d + offset)),
+    }
+}
+
+// ================= Device Transfer =================
+/// Transfer job: from labeled sessions measured on `device` and on
+/// `master` (same labels, e.g. the same reference gases), fits per cycle
+/// feature an affine map of the device's per-label means onto the
+/// master's. Results go to transfer.json/transfer.md; with `apply` they
+/// replace `[devices.<device>.transfer]` as one config version.
+pub async fn learn(state: &AppState, job: &JobContext, device: &str, master: &str, apply: bool, actor: &str) -> Result<(), String> {
+    let labeled = |unit: &str| -> Result<Vec<SessionRecord>, String> {
+        let filter = SessionFilter { device: Some(unit.to_string()), ..Default::default() };
+        Ok(state
+            .sessions
+            .list(&filter)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|s| s.stopped_at.is_some() && s.label.as_deref().is_some_and(|l| !l.trim().is_empty()))
+            .collect())
+    };
+    let mut sessions = labeled(device)?;
+    for session in labeled(master)? {
+        if !sessions.iter().any(|s| s.id == session.id) {
+            sessions.push(session);
+        }
+    }
+    let cycles = replay::stored_cycles(state, &sessions.iter().collect::<Vec<_>>()).await?;
+    job.progress(0.5, format!("{} cycle(s) from {} labeled session(s)", cycles.len(), sessions.len()));
+    if job.cancelled() {
+        return Ok(());
+    }
+
+    // unit → label → fitur → nilai
+    let mut values: [BTreeMap<String, BTreeMap<&str, Vec<f64>>>; 2] = Default::default();
+    for StoredCycle { session, device: unit, features } in &cycles {
+        let side = match unit.as_str() {
+            u if u == device => 0,
+            u if u == master => 1,
+            _ => continue,
+        };
+        let Some(label) = sessions.iter().find(|s| &s.id == session).and_then(|s| s.label.as_deref()) else { continue };
+        let by_feature = values[side].entry(label.trim().to_lowercase()).or_default();
+        for (name, value) in features {
+            by_feature.entry(name).or_default().push(*value);
+        }
+    }
+    let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+    let mut points: BTreeMap<&str, Vec<(f64, f64)>> = BTreeMap::new();
+    let mut shared = Vec::new();
+    for (label, device_features) in &values[0] {
+        let Some(master_features) = values[1].get(label) else { continue };
+        shared.push(label.clone());
+        for (name, device_values) in device_features {
+            if let Some(master_values) = master_features.get(name) {
+                points.entry(name).or_default().push((mean(device_values), mean(master_values)));
+            }
+        }
+    }
+    if points.is_empty() {
+        return Err(format!("no labeled sessions with cycles of both {} and {} under the same label", device, master));
+    }
+    let mappings: Vec<Mapping> = points.iter().map(|(name, points)| fit(name, points)).collect();
+
+    let report = serde_json::json!({
+        "device": device,
+        "master": master,
+        "labels": shared,
+        "sessions": sessions.iter().map(|s| &s.id).collect::<Vec<_>>(),
+        "cycles": cycles.len(),
+        "features": mappings,
+    });
+    job.write_artifact("transfer.json", serde_json::to_string_pretty(&report).unwrap_or_default().as_bytes())?;
+    job.write_artifact("transfer.md", markdown(device, master, &shared, &mappings).as_bytes())?;
+    job.progress(0.9, format!("{} feature map(s) over {} shared label(s)", mappings.len(), shared.len()));
+    if !apply || job.cancelled() {
+        return Ok(());
+    }
+
+    let base = format!("devices.\"{}\".transfer", device);
+    let version = state.config.edit(actor, "transfer", Some(format!("{} -> {}", device, master)), |doc| {
+        // Peta lama diganti seluruhnya, fitur yang tidak dipelajari lagi tidak tertinggal
+        if let Some(transfer) = doc
+            .get_mut("devices")
+            .and_then(|d| d.get_mut(device))
+            .and_then(|d| d.get_mut("transfer"))
+            .and_then(|t| t.as_table_like_mut())
+        {
+            transfer.remove("features");
+        }
+        crate::config::set_value(doc, &format!("{}.master", base), &format!("\"{}\"", master))?;
+        for m in &mappings {
+            let key = format!("{}.features.\"{}\"", base, m.feature);
+            crate::config::set_value(doc, &key, &format!("{{ gain = {}, offset = {} }}", m.gain, m.offset))?;
+        }
+        Ok(())
+    })?;
+    println!("🔁 Transfer map {} -> {} applied as config version {} ({} feature(s))", device, master, version, mappings.len());
+    job.progress(1.0, format!("config version {}: {} feature map(s)", version, mappings.len()));
+    Ok(())
+}
+
+fn markdown(device: &str, master: &str, labels: &[String], mappings: &[Mapping]) -> String {
+    let mut out = format!("# Transfer {} → {}\n\nShared labels: {}\n\n", device, master, labels.join(", "));
+    out += "| Feature | Gain | Offset | Labels | RMS before | RMS after |\n|---|---|---|---|---|---|\n";
+    for m in mappings {
+        out += &format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            m.feature, m.gain, m.offset, m.labels, m.error_before, m.error_after
+        );
+    }
+    out
+}
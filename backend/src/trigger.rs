@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::UnifiedSensorData;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerOutput {
+    /// Raspberry Pi GPIO via sysfs
+    Gpio,
+    /// Command sent to the device whose FSM changed state, which drives its own pin
+    Arduino,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerEvent {
+    /// FSM enters PRE_COND
+    CycleStart,
+    /// FSM enters HOLD
+    ExposureStart,
+}
+
+impl TriggerEvent {
+    fn state(self) -> i32 {
+        match self {
+            TriggerEvent::CycleStart => 1,
+            TriggerEvent::ExposureStart => 3,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TriggerConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_output")]
+    pub output: TriggerOutput,
+    #[serde(default = "default_events")]
+    pub events: Vec<TriggerEvent>,
+    #[serde(default = "default_gpio_pin")]
+    pub gpio_pin: u32,
+    #[serde(default = "default_pulse_ms")]
+    pub pulse_ms: u64,
+    /// `{event}` is replaced with the event name
+    #[serde(default = "default_arduino_command")]
+    pub arduino_command: String,
+    #[serde(default = "default_log_path")]
+    pub log_path: String,
+}
+
+fn default_output() -> TriggerOutput { TriggerOutput::Arduino }
+fn default_events() -> Vec<TriggerEvent> { vec![TriggerEvent::CycleStart, TriggerEvent::ExposureStart] }
+fn default_gpio_pin() -> u32 { 17 }
+fn default_pulse_ms() -> u64 { 100 }
+fn default_arduino_command() -> String { "TRIGGER {event}".to_string() }
+fn default_log_path() -> String { "triggers.jsonl".to_string() }
+
+impl Default for TriggerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            output: default_output(),
+            events: default_events(),
+            gpio_pin: default_gpio_pin(),
+            pulse_ms: default_pulse_ms(),
+            arduino_command: default_arduino_command(),
+            log_path: default_log_path(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    trigger: TriggerConfig,
+}
+
+impl TriggerConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().trigger
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TriggerRecord {
+    event: TriggerEvent,
+    device: String,
+    timestamp: i64,
+    level: i32,
+}
+
+// ================= Trigger Task =================
+pub async fn run_trigger(
+    config: TriggerConfig,
+    cmd_tx: broadcast::Sender<String>,
+    data_tx: broadcast::Sender<String>,
+    frame_tx: broadcast::Sender<UnifiedSensorData>,
+) {
+    if config.output == TriggerOutput::Gpio {
+        if let Err(e) = gpio_setup(config.gpio_pin).await {
+            eprintln!("❌ Trigger GPIO{} setup failed: {}", config.gpio_pin, e);
+            return;
+        }
+    }
+    println!("⏱️ External trigger output enabled ({:?})", config.output);
+
+    let mut frame_rx = frame_tx.subscribe();
+    // State terakhir per device: frame beberapa device datang berselang-seling
+    let mut last_state: HashMap<String, i32> = HashMap::new();
+
+    loop {
+        let frame = match frame_rx.recv().await {
+            Ok(frame) => frame,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let previous = last_state.insert(frame.device.clone(), frame.state);
+        if previous.is_none() || previous == Some(frame.state) {
+            continue;
+        }
+
+        let Some(event) = config.events.iter().copied().find(|e| e.state() == frame.state) else {
+            continue;
+        };
+
+        // Timestamp diambil sebelum pulse supaya sedekat mungkin dengan sinyal
+        let record = TriggerRecord {
+            event,
+            device: frame.device.clone(),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            level: frame.level,
+        };
+
+        match config.output {
+            TriggerOutput::Gpio => {
+                let pin = config.gpio_pin;
+                let pulse = Duration::from_millis(config.pulse_ms);
+                tokio::spawn(async move {
+                    if let Err(e) = gpio_pulse(pin, pulse).await {
+                        eprintln!("❌ Trigger GPIO{} pulse failed: {}", pin, e);
+                    }
+                });
+            }
+            TriggerOutput::Arduino => {
+                let name = serde_json::to_value(event).unwrap_or_default();
+                let command = config.arduino_command.replace("{event}", name.as_str().unwrap_or_default());
+                let _ = cmd_tx.send(format!("{}:{}", frame.device, command));
+            }
+        }
+
+        println!("⏱️ Trigger {:?} on {} at {}", record.event, record.device, record.timestamp);
+        if let Err(e) = append_record(&config.log_path, &record) {
+            eprintln!("❌ Failed to write trigger log: {}", e);
+        }
+        let mut event = serde_json::to_value(&record).unwrap_or_default();
+        event["type"] = "trigger".into();
+        let _ = data_tx.send(event.to_string());
+    }
+}
+
+async fn gpio_setup(pin: u32) -> std::io::Result<()> {
+    let base = format!("/sys/class/gpio/gpio{}", pin);
+    if tokio::fs::metadata(&base).await.is_err() {
+        tokio::fs::write("/sys/class/gpio/export", pin.to_string()).await?;
+        // sysfs butuh waktu sebentar untuk membuat node
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    tokio::fs::write(format!("{}/direction", base), "out").await?;
+    tokio::fs::write(format!("{}/value", base), "0").await
+}
+
+async fn gpio_pulse(pin: u32, width: Duration) -> std::io::Result<()> {
+    let path = format!("/sys/class/gpio/gpio{}/value", pin);
+    tokio::fs::write(&path, "1").await?;
+    tokio::time::sleep(width).await;
+    tokio::fs::write(&path, "0").await
+}
+
+fn append_record(path: &str, record: &TriggerRecord) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+}
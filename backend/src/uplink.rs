@@ -0,0 +1,236 @@
+use rusqlite::params;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tokio_native_tls::native_tls;
+
+use crate::aggregator::AggregatorReply;
+use crate::dialer::{self, Backoff, BackoffConfig, EndpointConfig, EndpointKind};
+use crate::provenance::Provenance;
+use crate::store::LocalStore;
+use crate::UnifiedSensorData;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct UplinkConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Aggregator host:port
+    #[serde(default)]
+    pub address: String,
+    /// Name checked against the aggregator certificate (default: host of address)
+    #[serde(default)]
+    pub domain: Option<String>,
+    /// Extra PEM root, e.g. for a self-signed aggregator certificate
+    #[serde(default)]
+    pub ca_path: Option<String>,
+    #[serde(default)]
+    pub edge: String,
+    #[serde(default)]
+    pub token: String,
+    /// Oldest frames are dropped beyond this many queued frames
+    #[serde(default = "default_max_queue")]
+    pub max_queue: u64,
+    #[serde(default)]
+    pub backoff: BackoffConfig,
+}
+
+fn default_max_queue() -> u64 { 1_000_000 }
+
+impl Default for UplinkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: String::new(),
+            domain: None,
+            ca_path: None,
+            edge: String::new(),
+            token: String::new(),
+            max_queue: default_max_queue(),
+            backoff: BackoffConfig::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    uplink: UplinkConfig,
+}
+
+impl UplinkConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().uplink
+    }
+}
+
+/// Frames sent but not yet acknowledged before sending pauses
+const MAX_IN_FLIGHT: i64 = 500;
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+// ================= Edge Uplink =================
+/// Hands processed frames to the store-and-forward queue; a no-op when disabled
+#[derive(Clone, Default)]
+pub struct Uplink {
+    tx: Option<mpsc::Sender<(UnifiedSensorData, Provenance)>>,
+}
+
+impl Uplink {
+    pub fn start(config: UplinkConfig, store: LocalStore) -> Self {
+        let (tx, rx) = mpsc::channel(1000);
+        tokio::spawn(enqueue(rx, store.clone(), config.max_queue));
+        tokio::spawn(forward(config, store));
+        Self { tx: Some(tx) }
+    }
+
+    pub fn forward(&self, frame: &UnifiedSensorData, provenance: &Provenance) {
+        if let Some(tx) = &self.tx {
+            if tx.try_send((frame.clone(), provenance.clone())).is_err() {
+                eprintln!("⚠️ Uplink queue full, frame dropped");
+            }
+        }
+    }
+}
+
+/// Persist frames so they survive WAN outages and restarts
+async fn enqueue(mut rx: mpsc::Receiver<(UnifiedSensorData, Provenance)>, store: LocalStore, max_queue: u64) {
+    while let Some((frame, provenance)) = rx.recv().await {
+        let payload = serde_json::json!({ "frame": frame, "provenance": provenance }).to_string();
+        let conn = store.conn();
+        let result = conn
+            .execute("INSERT INTO uplink_queue (payload) VALUES (?1)", [payload])
+            .and_then(|_| {
+                conn.execute(
+                    "DELETE FROM uplink_queue WHERE seq <= (SELECT MAX(seq) FROM uplink_queue) - ?1",
+                    [max_queue as i64],
+                )
+            });
+        match result {
+            Ok(0) => {}
+            Ok(dropped) => eprintln!("⚠️ Uplink queue over max_queue, dropped {} oldest frames", dropped),
+            Err(e) => eprintln!("❌ Failed to queue frame for uplink: {}", e),
+        }
+    }
+}
+
+fn pending(store: &LocalStore, after: i64, limit: i64) -> Vec<(i64, String)> {
+    let conn = store.conn();
+    let rows = conn
+        .prepare_cached("SELECT seq, payload FROM uplink_queue WHERE seq > ?1 ORDER BY seq LIMIT ?2")
+        .and_then(|mut stmt| {
+            stmt.query_map(params![after, limit], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<_>, _>>()
+        });
+    rows.unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read uplink queue: {}", e);
+        Vec::new()
+    })
+}
+
+fn tls_connector(config: &UplinkConfig) -> anyhow::Result<tokio_native_tls::TlsConnector> {
+    let mut builder = native_tls::TlsConnector::builder();
+    if let Some(ca_path) = &config.ca_path {
+        builder.add_root_certificate(native_tls::Certificate::from_pem(&std::fs::read(ca_path)?)?);
+    }
+    Ok(tokio_native_tls::TlsConnector::from(builder.build()?))
+}
+
+/// Keep a TLS link to the aggregator and drain the queue in order, deleting
+/// frames only once acknowledged; unacknowledged frames are resent on reconnect
+async fn forward(config: UplinkConfig, store: LocalStore) {
+    let connector = match tls_connector(&config) {
+        Ok(connector) => connector,
+        Err(e) => {
+            eprintln!("❌ Uplink TLS setup failed: {}", e);
+            return;
+        }
+    };
+    let endpoint = EndpointConfig {
+        name: "uplink".to_string(),
+        address: config.address.clone(),
+        kind: EndpointKind::Arduino,
+        device: None,
+        connect_timeout_ms: 10_000,
+        backoff: config.backoff.clone(),
+    };
+    let domain = config.domain.clone().unwrap_or_else(|| endpoint.device());
+    let mut backoff = Backoff::new(config.backoff.clone());
+    println!("📤 Edge uplink '{}' -> {}", config.edge, config.address);
+
+    loop {
+        let Some(socket) = dialer::connect(&endpoint, &mut backoff).await else {
+            return;
+        };
+        let stream = match connector.connect(&domain, socket).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("❌ Uplink TLS handshake failed: {}", e);
+                tokio::time::sleep(backoff.next_delay().unwrap_or(PING_INTERVAL)).await;
+                continue;
+            }
+        };
+        match run_link(stream, &config, &store).await {
+            Ok(()) => println!("📤 Uplink closed by aggregator, reconnecting"),
+            Err(e) => eprintln!("⚠️ Uplink lost: {}, reconnecting", e),
+        }
+        tokio::time::sleep(backoff.next_delay().unwrap_or(PING_INTERVAL)).await;
+    }
+}
+
+async fn run_link<S>(stream: S, config: &UplinkConfig, store: &LocalStore) -> anyhow::Result<()>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    let hello = serde_json::json!({ "type": "hello", "edge": config.edge, "token": config.token });
+    writer.write_all(format!("{}\n", hello).as_bytes()).await?;
+    match lines.next_line().await?.as_deref().map(serde_json::from_str::<AggregatorReply>) {
+        Some(Ok(AggregatorReply::Welcome)) => println!("📤 Uplink connected to {}", config.address),
+        Some(Ok(AggregatorReply::Error { error })) => anyhow::bail!("rejected: {}", error),
+        _ => anyhow::bail!("unexpected handshake reply"),
+    }
+
+    // Mulai lagi dari frame tertua yang belum di-ack
+    let mut acked: i64 = 0;
+    let mut sent: i64 = 0;
+    let mut poll = tokio::time::interval(Duration::from_millis(200));
+    let mut last_write = tokio::time::Instant::now();
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    return Ok(());
+                };
+                if let Ok(AggregatorReply::Ack { seq }) = serde_json::from_str(&line) {
+                    let seq = seq as i64;
+                    acked = acked.max(seq);
+                    store.conn().execute("DELETE FROM uplink_queue WHERE seq <= ?1", [seq])?;
+                }
+            }
+            _ = poll.tick() => {
+                let room = MAX_IN_FLIGHT - (sent - acked).max(0);
+                let mut batch = String::new();
+                for (seq, payload) in pending(store, sent, room.max(0)) {
+                    let mut message: serde_json::Value = serde_json::from_str(&payload)?;
+                    message["type"] = "frame".into();
+                    message["seq"] = seq.into();
+                    batch.push_str(&message.to_string());
+                    batch.push('\n');
+                    sent = seq;
+                }
+                if batch.is_empty() && last_write.elapsed() >= PING_INTERVAL {
+                    batch = "{\"type\":\"ping\"}\n".to_string();
+                }
+                if !batch.is_empty() {
+                    writer.write_all(batch.as_bytes()).await?;
+                    writer.flush().await?;
+                    last_write = tokio::time::Instant::now();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use influxdb2::models::DataPoint;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+use crate::alerts::{AlertEngine, AlertState, NotifyReason};
+use crate::influxdb::InfluxDBHandler;
+use crate::sessions::in_session;
+use crate::store::LocalStore;
+use crate::UnifiedSensorData;
+
+// FSM state ending a cycle (see state_to_name)
+const RECOVERY: i32 = 5;
+
+const CYCLES: &str = "cycles";
+const INVALID_CYCLES: &str = "invalid_cycles";
+const ACTIVE_HOURS: &str = "active_hours";
+const COMMANDS: &str = "commands";
+const ALARMS: &str = "alarms";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct UsageConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Range of the periodic report and of USAGE without arguments, days
+    #[serde(default = "default_window_days")]
+    pub window_days: u32,
+    /// Interval of the "usage" event and "usage" points, hours; 0 = off
+    #[serde(default = "default_report_hours")]
+    pub report_hours: u64,
+    /// Frames further apart than this do not add active time (device offline)
+    #[serde(default = "default_max_gap_secs")]
+    pub max_gap_secs: f64,
+    /// Counters are written to the local store this often
+    #[serde(default = "default_flush_secs")]
+    pub flush_secs: u64,
+}
+
+fn default_enabled() -> bool { true }
+fn default_window_days() -> u32 { 30 }
+fn default_report_hours() -> u64 { 24 }
+fn default_max_gap_secs() -> f64 { 10.0 }
+fn default_flush_secs() -> u64 { 60 }
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            window_days: default_window_days(),
+            report_hours: default_report_hours(),
+            max_gap_secs: default_max_gap_secs(),
+            flush_secs: default_flush_secs(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    usage: UsageConfig,
+}
+
+impl UsageConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().usage
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_days == 0 || self.flush_secs == 0 {
+            return Err("usage.window_days and usage.flush_secs must be at least 1".to_string());
+        }
+        if self.max_gap_secs <= 0.0 {
+            return Err("usage.max_gap_secs must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Counters of one day or of a whole report
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct UsageCounts {
+    /// Completed cycles per device
+    pub cycles: BTreeMap<String, u64>,
+    /// Of those, aborted or otherwise invalid
+    pub invalid_cycles: BTreeMap<String, u64>,
+    /// Hours per device spent in a session (FSM out of IDLE/DONE)
+    pub active_hours: BTreeMap<String, f64>,
+    /// Commands per operator (token name, or client IP)
+    pub commands: BTreeMap<String, u64>,
+    /// Firing alerts per alert name
+    pub alarms: BTreeMap<String, u64>,
+}
+
+impl UsageCounts {
+    fn add(&mut self, kind: &str, name: String, value: f64) {
+        match kind {
+            CYCLES => *self.cycles.entry(name).or_default() += value as u64,
+            INVALID_CYCLES => *self.invalid_cycles.entry(name).or_default() += value as u64,
+            ACTIVE_HOURS => *self.active_hours.entry(name).or_default() += value,
+            COMMANDS => *self.commands.entry(name).or_default() += value as u64,
+            ALARMS => *self.alarms.entry(name).or_default() += value as u64,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DailyUsage {
+    pub day: String,
+    #[serde(flatten)]
+    pub counts: UsageCounts,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UsageReport {
+    /// First and last local day of the range
+    pub from: String,
+    pub to: String,
+    pub days: u32,
+    pub totals: UsageCounts,
+    /// Share of the range each device was active
+    pub utilization: BTreeMap<String, f64>,
+    /// Completed cycles of all devices, per day of the range
+    pub cycles_per_day: f64,
+    /// Days with any activity, oldest first
+    pub daily: Vec<DailyUsage>,
+}
+
+/// Local day of an epoch ms timestamp
+fn day_of(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(timestamp)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// Client ID without the port, so reconnects count as the same operator
+pub fn operator(client: &str) -> String {
+    client.parse::<SocketAddr>().map_or_else(|_| client.to_string(), |addr| addr.ip().to_string())
+}
+
+#[derive(Default)]
+struct Pending {
+    /// (day, kind, name) → amount not yet in the store
+    counts: BTreeMap<(String, &'static str, String), f64>,
+    /// Device → (state, timestamp, cycle_valid) of its previous frame
+    devices: HashMap<String, (i32, i64, bool)>,
+}
+
+impl Pending {
+    fn add(&mut self, timestamp: i64, kind: &'static str, name: &str, value: f64) {
+        *self.counts.entry((day_of(timestamp), kind, name.to_string())).or_default() += value;
+    }
+}
+
+// ================= Usage Statistics =================
+/// Daily usage counters for planning instrument time: completed cycles and
+/// active hours per device, commands per operator and alarms per alert.
+/// Counted in memory and added to the local store every flush_secs.
+#[derive(Clone)]
+pub struct UsageStats {
+    config: Arc<UsageConfig>,
+    store: LocalStore,
+    pending: Arc<Mutex<Pending>>,
+}
+
+impl UsageStats {
+    pub fn new(config: UsageConfig, store: LocalStore) -> Self {
+        Self { config: Arc::new(config), store, pending: Arc::new(Mutex::new(Pending::default())) }
+    }
+
+    /// Count one command by `operator`
+    pub fn command(&self, operator: &str) {
+        if self.config.enabled {
+            self.pending.lock().unwrap().add(chrono::Utc::now().timestamp_millis(), COMMANDS, operator, 1.0);
+        }
+    }
+
+    fn frame(&self, frame: &UnifiedSensorData) {
+        let mut pending = self.pending.lock().unwrap();
+        let previous = pending.devices.insert(frame.device.clone(), (frame.state, frame.timestamp, frame.cycle_valid));
+        let Some((state, timestamp, valid)) = previous else { return };
+        let gap = (frame.timestamp - timestamp) as f64 / 1000.0;
+        if in_session(state) && gap > 0.0 && gap <= self.config.max_gap_secs {
+            pending.add(frame.timestamp, ACTIVE_HOURS, &frame.device, gap / 3600.0);
+        }
+        if state == RECOVERY && frame.state != RECOVERY {
+            pending.add(frame.timestamp, CYCLES, &frame.device, 1.0);
+            if !valid {
+                pending.add(frame.timestamp, INVALID_CYCLES, &frame.device, 1.0);
+            }
+        }
+    }
+
+    fn alarm(&self, name: &str, timestamp: i64) {
+        self.pending.lock().unwrap().add(timestamp, ALARMS, name, 1.0);
+    }
+
+    /// Add the pending counters to the store
+    pub fn flush(&self) -> Result<(), String> {
+        let counts = std::mem::take(&mut self.pending.lock().unwrap().counts);
+        if counts.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.store.conn();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for ((day, kind, name), value) in &counts {
+            tx.execute(
+                "INSERT INTO usage (day, kind, name, value) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (day, kind, name) DO UPDATE SET value = value + excluded.value",
+                rusqlite::params![day, kind, name, value],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Usage over the last `days` local days including today (default window_days)
+    pub fn report(&self, days: Option<u32>) -> Result<UsageReport, String> {
+        self.flush()?;
+        let days = days.unwrap_or(self.config.window_days).max(1);
+        let today = chrono::Local::now().date_naive();
+        let from = (today - chrono::Days::new(days as u64 - 1)).format("%Y-%m-%d").to_string();
+
+        let rows: Vec<(String, String, String, f64)> = {
+            let conn = self.store.conn();
+            let mut statement = conn
+                .prepare("SELECT day, kind, name, value FROM usage WHERE day >= ?1 ORDER BY day, kind, name")
+                .map_err(|e| e.to_string())?;
+            let rows = statement
+                .query_map(rusqlite::params![from], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+
+        let mut totals = UsageCounts::default();
+        let mut daily: BTreeMap<String, UsageCounts> = BTreeMap::new();
+        for (day, kind, name, value) in rows {
+            totals.add(&kind, name.clone(), value);
+            daily.entry(day).or_default().add(&kind, name, value);
+        }
+        let hours = days as f64 * 24.0;
+        Ok(UsageReport {
+            from,
+            to: today.format("%Y-%m-%d").to_string(),
+            days,
+            utilization: totals.active_hours.iter().map(|(device, h)| (device.clone(), h / hours)).collect(),
+            cycles_per_day: totals.cycles.values().sum::<u64>() as f64 / days as f64,
+            totals,
+            daily: daily.into_iter().map(|(day, counts)| DailyUsage { day, counts }).collect(),
+        })
+    }
+}
+
+/// One "usage" point per counter, tagged kind and name, over the report range
+fn points(report: &UsageReport) -> Vec<DataPoint> {
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    let totals = &report.totals;
+    let counts = [(CYCLES, &totals.cycles), (INVALID_CYCLES, &totals.invalid_cycles), (COMMANDS, &totals.commands), (ALARMS, &totals.alarms)];
+    let values = counts
+        .into_iter()
+        .flat_map(|(kind, map)| map.iter().map(move |(name, v)| (kind, name, *v as f64)))
+        .chain(totals.active_hours.iter().map(|(name, v)| (ACTIVE_HOURS, name, *v)))
+        .chain(report.utilization.iter().map(|(name, v)| ("utilization", name, *v)));
+    values
+        .filter_map(|(kind, name, value)| {
+            DataPoint::builder("usage")
+                .tag("kind", kind)
+                .tag("name", name.clone())
+                .field("value", value)
+                .field("days", report.days as i64)
+                .timestamp(now)
+                .build()
+                .ok()
+        })
+        .collect()
+}
+
+/// Count frames and alarms, flush the counters and publish the periodic
+/// report ("usage" GUI event and InfluxDB points for dashboards)
+pub async fn run(
+    stats: UsageStats,
+    frame_tx: broadcast::Sender<UnifiedSensorData>,
+    alerts: AlertEngine,
+    data_tx: broadcast::Sender<String>,
+    influx: InfluxDBHandler,
+) {
+    let mut frame_rx = frame_tx.subscribe();
+    let mut alert_rx = alerts.subscribe();
+    let mut flush = tokio::time::interval(Duration::from_secs(stats.config.flush_secs));
+    let mut last_report = Instant::now();
+    loop {
+        tokio::select! {
+            frame = frame_rx.recv() => match frame {
+                Ok(frame) => stats.frame(&frame),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            event = alert_rx.recv() => match event {
+                Ok(event) if event.state == AlertState::Firing && event.reason == NotifyReason::Transition => {
+                    stats.alarm(&event.name, event.timestamp)
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = flush.tick() => {
+                if let Err(e) = stats.flush() {
+                    eprintln!("❌ Usage flush failed: {}", e);
+                }
+                let every = Duration::from_secs(stats.config.report_hours * 3600);
+                if stats.config.report_hours == 0 || last_report.elapsed() < every {
+                    continue;
+                }
+                last_report = Instant::now();
+                let report = match stats.report(None) {
+                    Ok(report) => report,
+                    Err(e) => {
+                        eprintln!("❌ Usage report failed: {}", e);
+                        continue;
+                    }
+                };
+                println!(
+                    "📊 Usage over {} day(s): {:.1} cycles/day, {} command(s), {} alarm(s)",
+                    report.days,
+                    report.cycles_per_day,
+                    report.totals.commands.values().sum::<u64>(),
+                    report.totals.alarms.values().sum::<u64>()
+                );
+                let mut event = serde_json::to_value(&report).unwrap_or_default();
+                event["type"] = "usage".into();
+                let _ = data_tx.send(event.to_string());
+                let points = points(&report);
+                if !points.is_empty() {
+                    if let Err(e) = influx.write_points(points).await {
+                        eprintln!("❌ Usage write error: {:?}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// GUI command:
+///   USAGE [days]   -> reply: "usage" report over the last days (default window_days)
+pub fn handle_command(stats: &UsageStats, args: &[&str]) -> Result<Option<String>, String> {
+    let days = match args {
+        ["USAGE"] => None,
+        ["USAGE", days] => Some(days.parse::<u32>().ok().filter(|d| *d > 0).ok_or_else(|| format!("invalid number of days '{}'", days))?),
+        _ => return Err("usage: USAGE [days]".to_string()),
+    };
+    let mut event = serde_json::to_value(stats.report(days)?).unwrap_or_default();
+    event["type"] = "usage".into();
+    Ok(Some(event.to_string()))
+}
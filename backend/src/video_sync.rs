@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use tokio::sync::broadcast;
+
+use crate::sessions::{in_session, session_id};
+use crate::UnifiedSensorData;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct VideoSyncConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Markers are appended here as JSON lines (empty = no file)
+    #[serde(default = "default_marker_path")]
+    pub marker_path: String,
+    /// Each marker is also POSTed as JSON to this URL (empty = no HTTP call)
+    #[serde(default)]
+    pub webhook_url: String,
+}
+
+fn default_marker_path() -> String { "video_sync.jsonl".to_string() }
+
+impl Default for VideoSyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            marker_path: default_marker_path(),
+            webhook_url: String::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    video_sync: VideoSyncConfig,
+}
+
+impl VideoSyncConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().video_sync
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum SyncEvent {
+    SessionStart,
+    SessionStop,
+}
+
+#[derive(Debug, Serialize)]
+struct SyncMarker {
+    event: SyncEvent,
+    /// Start time of the session this marker belongs to, e.g. "20260115-093000"
+    session: String,
+    /// Backend wall clock, epoch ms
+    timestamp: i64,
+    time: String,
+    state: String,
+    level: i32,
+}
+
+// ================= Video Sync Hook =================
+/// Marks session start/stop so external camera recordings can be aligned with sensor data
+pub async fn run(config: VideoSyncConfig, data_tx: broadcast::Sender<String>, frame_tx: broadcast::Sender<UnifiedSensorData>) {
+    println!("🎥 Video sync markers enabled");
+    let client = reqwest::Client::new();
+    let mut frame_rx = frame_tx.subscribe();
+    let mut running: Option<bool> = None;
+    let mut session = String::new();
+
+    loop {
+        let frame = match frame_rx.recv().await {
+            Ok(frame) => frame,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let now_running = in_session(frame.state);
+        let previous = running.replace(now_running);
+        if previous.is_none() || previous == Some(now_running) {
+            continue;
+        }
+
+        let now = chrono::Local::now();
+        let event = if now_running {
+            // Pakai id yang sama dengan tabel sessions kalau frame sudah di-tag
+            session = if frame.session.is_empty() { session_id(&now) } else { frame.session.clone() };
+            SyncEvent::SessionStart
+        } else {
+            SyncEvent::SessionStop
+        };
+        let marker = SyncMarker {
+            event,
+            session: session.clone(),
+            timestamp: now.timestamp_millis(),
+            time: now.to_rfc3339(),
+            state: frame.state_name.clone(),
+            level: frame.level,
+        };
+
+        println!("🎥 {:?} (session {}) at {}", marker.event, marker.session, marker.time);
+        if !config.marker_path.is_empty() {
+            if let Err(e) = append_marker(&config.marker_path, &marker) {
+                eprintln!("❌ Failed to write video sync marker: {}", e);
+            }
+        }
+        if !config.webhook_url.is_empty() {
+            // Jangan tahan loop frame kalau kamera/recorder lambat merespon
+            let request = client.post(&config.webhook_url).json(&marker);
+            tokio::spawn(async move {
+                if let Err(e) = request.send().await.and_then(|r| r.error_for_status()) {
+                    eprintln!("❌ Video sync webhook failed: {}", e);
+                }
+            });
+        }
+
+        let mut event = serde_json::to_value(&marker).unwrap_or_default();
+        event["type"] = "video_sync".into();
+        let _ = data_tx.send(event.to_string());
+    }
+}
+
+fn append_marker(path: &str, marker: &SyncMarker) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(marker)?)
+}
@@ -0,0 +1,54 @@
+use serde::Deserialize;
+use std::net::SocketAddr;
+
+use crate::AppState;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ViewerConfig {
+    /// Separate listener whose connections are always read-only
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_bind")]
+    pub bind: String,
+    /// Client IPs that are read-only on the main GUI port too
+    #[serde(default)]
+    pub clients: Vec<String>,
+}
+
+fn default_bind() -> String { "0.0.0.0:8084".to_string() }
+
+impl Default for ViewerConfig {
+    fn default() -> Self {
+        Self { enabled: false, bind: default_bind(), clients: Vec::new() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    viewer: ViewerConfig,
+}
+
+impl ViewerConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().viewer
+    }
+
+    pub fn applies_to(&self, addr: &SocketAddr) -> bool {
+        self.clients.contains(&addr.ip().to_string())
+    }
+}
+
+// ================= Viewer Listener =================
+/// Public displays (classroom, visitors): same data stream as the GUI port,
+/// but every command line is rejected
+pub async fn serve(config: ViewerConfig, state: AppState) -> anyhow::Result<()> {
+    let listener = state.diagnostics.bind("viewer", &config.bind).await?;
+    println!("👀 Read-only viewer listening on {}", config.bind);
+
+    loop {
+        let (socket, addr) = listener.accept().await?;
+        tokio::spawn(crate::handle_gui_client(socket, addr, state.clone(), true));
+    }
+}
@@ -0,0 +1,76 @@
+use evalexpr::{ContextWithMutableVariables, HashMapContext, Node, Value};
+use std::collections::BTreeMap;
+
+use crate::filtering::UnifiedSensorRaw;
+use crate::{CHANNEL_NAMES, PAYLOAD_FIELDS};
+
+/// Optional rig inputs usable in formulas (skipped while absent)
+pub const AUX_INPUTS: [&str; 4] = ["flow", "pressure", "humidity", "heater_temp"];
+
+/// Parse a formula and check it only references known inputs
+pub fn compile(name: &str, formula: &str) -> Result<Node, String> {
+    // Virtual channel tidak boleh menimpa field payload maupun "type" event GUI
+    if CHANNEL_NAMES.contains(&name) || PAYLOAD_FIELDS.contains(&name) || name == "type" {
+        return Err(format!("virtual channel '{}' shadows a built-in field", name));
+    }
+    let node = evalexpr::build_operator_tree(formula)
+        .map_err(|e| format!("virtual channel '{}': {}", name, e))?;
+    if let Some(unknown) = node
+        .iter_variable_identifiers()
+        .find(|v| !CHANNEL_NAMES.contains(v) && !AUX_INPUTS.contains(v))
+    {
+        return Err(format!("virtual channel '{}': unknown input '{}'", name, unknown));
+    }
+    Ok(node)
+}
+
+// ================= Virtual Channels =================
+/// Channels defined in config as formulas over physical channels,
+/// e.g. `voc_total = "0.6*voc + 0.4*vocm"`
+#[derive(Debug, Clone, Default)]
+pub struct VirtualChannels {
+    defs: Vec<(String, Node)>,
+}
+
+impl VirtualChannels {
+    pub fn new(formulas: &BTreeMap<String, String>) -> Self {
+        let defs = formulas
+            .iter()
+            .filter_map(|(name, formula)| match compile(name, formula) {
+                Ok(node) => Some((name.clone(), node)),
+                Err(e) => {
+                    eprintln!("⚠️ {}", e);
+                    None
+                }
+            })
+            .collect();
+        Self { defs }
+    }
+
+    /// Evaluate every formula against one raw frame
+    pub fn evaluate(&self, raw: &UnifiedSensorRaw) -> BTreeMap<String, f32> {
+        if self.defs.is_empty() {
+            return BTreeMap::new();
+        }
+
+        let mut context = HashMapContext::new();
+        let aux = [raw.flow, raw.pressure, raw.humidity, raw.heater_temp];
+        let inputs = CHANNEL_NAMES
+            .iter()
+            .zip(raw.channels.values().map(Some))
+            .chain(AUX_INPUTS.iter().zip(aux));
+        for (name, value) in inputs {
+            if let Some(value) = value {
+                let _ = context.set_value(name.to_string(), Value::Float(value as f64));
+            }
+        }
+
+        self.defs
+            .iter()
+            .filter_map(|(name, node)| {
+                let value = node.eval_number_with_context(&context).ok()?;
+                value.is_finite().then(|| (name.clone(), value as f32))
+            })
+            .collect()
+    }
+}
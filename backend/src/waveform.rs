@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::filtering::{SensorFilters, UnifiedSensorRaw};
+use crate::CHANNEL_NAMES;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WaveformConfig {
+    /// Buffer the raw input of every modulation cycle
+    #[serde(default)]
+    pub enabled: bool,
+    /// Completed cycles kept in memory for the REST API
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+    /// Every completed cycle is also appended here as one JSON line, "" = off
+    #[serde(default)]
+    pub export_path: String,
+}
+
+fn default_keep() -> usize { 600 }
+
+impl Default for WaveformConfig {
+    fn default() -> Self {
+        Self { enabled: false, keep: default_keep(), export_path: String::new() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    waveform: WaveformConfig,
+}
+
+impl WaveformConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().waveform
+    }
+}
+
+/// Full-rate raw input of one modulation cycle, as dense arrays
+#[derive(Debug, Serialize, Clone)]
+pub struct Cycle {
+    pub id: u64,
+    pub device: String,
+    /// Cycle index since the device's filter chain started
+    pub cycle: u64,
+    pub frequency: f32,
+    pub amplitude: f32,
+    /// Epoch ms of the first sample
+    pub started_at: i64,
+    /// Per sample: ms since `started_at`
+    pub offsets_ms: Vec<i64>,
+    /// Per sample: modulation phase 0..1
+    pub phase: Vec<f32>,
+    /// Per channel: raw values (after plugins, before smoothing)
+    pub channels: BTreeMap<&'static str, Vec<f32>>,
+}
+
+/// Cycle listing without the sample arrays
+#[derive(Debug, Serialize)]
+pub struct CycleSummary {
+    pub id: u64,
+    pub device: String,
+    pub cycle: u64,
+    pub frequency: f32,
+    pub started_at: i64,
+    pub samples: usize,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct CycleQuery {
+    pub device: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Only the newest N matching cycles
+    pub limit: Option<usize>,
+}
+
+// ================= Waveform Store =================
+/// Completed cycles from every device pipeline
+#[derive(Clone)]
+pub struct Waveforms {
+    cycles: Arc<Mutex<VecDeque<Cycle>>>,
+    next_id: Arc<Mutex<u64>>,
+    config: Arc<WaveformConfig>,
+}
+
+impl Waveforms {
+    pub fn new(config: WaveformConfig) -> Self {
+        Self { cycles: Arc::new(Mutex::new(VecDeque::new())), next_id: Arc::new(Mutex::new(1)), config: Arc::new(config) }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    fn push(&self, mut cycle: Cycle) {
+        {
+            let mut next_id = self.next_id.lock().unwrap();
+            cycle.id = *next_id;
+            *next_id += 1;
+        }
+        if !self.config.export_path.is_empty() {
+            if let Err(e) = append(&self.config.export_path, &cycle) {
+                eprintln!("❌ Failed to export waveform: {}", e);
+            }
+        }
+        let mut cycles = self.cycles.lock().unwrap();
+        cycles.push_back(cycle);
+        while cycles.len() > self.config.keep.max(1) {
+            cycles.pop_front();
+        }
+    }
+
+    pub fn list(&self, query: &CycleQuery) -> Vec<CycleSummary> {
+        let cycles = self.cycles.lock().unwrap();
+        let mut list: Vec<CycleSummary> = cycles
+            .iter()
+            .rev()
+            .filter(|c| query.device.as_deref().is_none_or(|d| c.device == d))
+            .filter(|c| query.from.is_none_or(|from| c.started_at >= from))
+            .filter(|c| query.to.is_none_or(|to| c.started_at <= to))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|c| CycleSummary {
+                id: c.id,
+                device: c.device.clone(),
+                cycle: c.cycle,
+                frequency: c.frequency,
+                started_at: c.started_at,
+                samples: c.offsets_ms.len(),
+            })
+            .collect();
+        list.reverse();
+        list
+    }
+
+    pub fn get(&self, id: u64) -> Option<Cycle> {
+        self.cycles.lock().unwrap().iter().find(|c| c.id == id).cloned()
+    }
+}
+
+fn append(path: &str, cycle: &Cycle) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(cycle)?)
+}
+
+// ================= Per-device Recorder =================
+/// Collects raw samples until the modulation phase wraps into a new cycle
+#[derive(Default)]
+pub struct WaveformRecorder {
+    current: Option<Cycle>,
+}
+
+impl WaveformRecorder {
+    pub fn record(&mut self, store: &Waveforms, device: &str, filters: &SensorFilters, raw: &UnifiedSensorRaw, timestamp: i64) {
+        if !store.enabled() {
+            return;
+        }
+        let Some(modulation) = filters.modulation_now() else {
+            // Modulasi dimatikan: siklus yang setengah jalan dibuang
+            self.current = None;
+            return;
+        };
+        let (index, phase) = (modulation.cycle, modulation.phase);
+        if self.current.as_ref().is_some_and(|c| c.cycle != index) {
+            if let Some(done) = self.current.take() {
+                store.push(done);
+            }
+        }
+        let (frequency, amplitude) = (modulation.frequency, modulation.amplitude);
+        let cycle = self.current.get_or_insert_with(|| Cycle {
+            id: 0,
+            device: device.to_string(),
+            cycle: index,
+            frequency,
+            amplitude,
+            started_at: timestamp,
+            offsets_ms: Vec::new(),
+            phase: Vec::new(),
+            channels: CHANNEL_NAMES.iter().map(|name| (*name, Vec::new())).collect(),
+        });
+        cycle.offsets_ms.push(timestamp - cycle.started_at);
+        cycle.phase.push(phase);
+        for (channel, value) in raw.channels.iter() {
+            if let Some(samples) = cycle.channels.get_mut(channel.name()) {
+                samples.push(value);
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Wavelet {
+    Haar,
+    Db2,
+    Db4,
+}
+
+impl Wavelet {
+    /// Low-pass decomposition filter (orthonormal)
+    fn lowpass(self) -> &'static [f64] {
+        match self {
+            Wavelet::Haar => &[
+                std::f64::consts::FRAC_1_SQRT_2,
+                std::f64::consts::FRAC_1_SQRT_2,
+            ],
+            Wavelet::Db2 => &[
+                0.48296291314469025,
+                0.836516303737469,
+                0.22414386804185735,
+                -0.12940952255092145,
+            ],
+            Wavelet::Db4 => &[
+                0.23037781330885523,
+                0.7148465705525415,
+                0.6308807679295904,
+                -0.02798376941698385,
+                -0.18703481171888114,
+                0.030841381835986965,
+                0.032883011666982945,
+                -0.010597401784997278,
+            ],
+        }
+    }
+}
+
+// ================= Wavelet Denoiser =================
+/// Sliding-window wavelet shrinkage: DWT → soft threshold → inverse DWT,
+/// publishing the newest reconstructed sample. Preserves sharp features
+/// (steps, modulation peaks) better than a moving average.
+#[derive(Debug, Clone)]
+pub struct WaveletDenoiser {
+    lowpass: &'static [f64],
+    highpass: Vec<f64>,
+    level: usize,
+    window: usize,
+}
+
+impl WaveletDenoiser {
+    pub fn new(wavelet: Wavelet, level: usize, window: usize) -> Self {
+        let lowpass = wavelet.lowpass();
+        let len = lowpass.len();
+        // Quadrature mirror: g[k] = (-1)^k h[L-1-k]
+        let highpass = (0..len)
+            .map(|k| if k % 2 == 0 { lowpass[len - 1 - k] } else { -lowpass[len - 1 - k] })
+            .collect();
+        Self {
+            lowpass,
+            highpass,
+            level: level.max(1),
+            window: window.max(2),
+        }
+    }
+
+    /// Push a sample into `values` and return the denoised newest value
+    pub fn push(&self, values: &mut Vec<f32>, new_val: f32) -> f32 {
+        values.push(new_val);
+        if values.len() > self.window {
+            values.remove(0);
+        }
+
+        // Ekstensi simetris (x + reverse(x)) menghindari loncatan di batas periodik
+        let block = 1usize << self.level;
+        let usable = values.len() - values.len() % (block / 2).max(1);
+        if usable < block {
+            return values.iter().sum::<f32>() / values.len() as f32;
+        }
+        let recent = &values[values.len() - usable..];
+        let mut signal: Vec<f64> = recent.iter().map(|v| *v as f64).collect();
+        signal.extend(recent.iter().rev().map(|v| *v as f64));
+
+        let denoised = self.denoise(&signal);
+        denoised[usable - 1] as f32
+    }
+
+    fn denoise(&self, signal: &[f64]) -> Vec<f64> {
+        let mut approx = signal.to_vec();
+        let mut details = Vec::with_capacity(self.level);
+        for _ in 0..self.level {
+            if approx.len() < 2 || !approx.len().is_multiple_of(2) {
+                break;
+            }
+            let (a, d) = self.forward(&approx);
+            approx = a;
+            details.push(d);
+        }
+        let Some(finest) = details.first() else {
+            return signal.to_vec();
+        };
+
+        // Universal threshold, sigma dari MAD detail level terhalus
+        let mut abs: Vec<f64> = finest.iter().map(|d| d.abs()).collect();
+        abs.sort_by(|a, b| a.total_cmp(b));
+        let sigma = abs[abs.len() / 2] / 0.6745;
+        let threshold = sigma * (2.0 * (signal.len() as f64).ln()).sqrt();
+
+        for detail in details.iter_mut() {
+            for d in detail.iter_mut() {
+                *d = d.signum() * (d.abs() - threshold).max(0.0);
+            }
+        }
+
+        for detail in details.iter().rev() {
+            approx = self.inverse(&approx, detail);
+        }
+        approx
+    }
+
+    /// One periodic DWT level
+    fn forward(&self, x: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let n = x.len();
+        let half = n / 2;
+        let mut a = vec![0.0; half];
+        let mut d = vec![0.0; half];
+        for i in 0..half {
+            for (k, (h, g)) in self.lowpass.iter().zip(&self.highpass).enumerate() {
+                let sample = x[(2 * i + k) % n];
+                a[i] += h * sample;
+                d[i] += g * sample;
+            }
+        }
+        (a, d)
+    }
+
+    fn inverse(&self, a: &[f64], d: &[f64]) -> Vec<f64> {
+        let n = a.len() * 2;
+        let mut x = vec![0.0; n];
+        for i in 0..a.len() {
+            for (k, (h, g)) in self.lowpass.iter().zip(&self.highpass).enumerate() {
+                x[(2 * i + k) % n] += h * a[i] + g * d[i];
+            }
+        }
+        x
+    }
+}
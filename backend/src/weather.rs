@@ -0,0 +1,191 @@
+use influxdb2::models::DataPoint;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::influxdb::InfluxDBHandler;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WeatherConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub latitude: f64,
+    #[serde(default)]
+    pub longitude: f64,
+    /// InfluxDB `location` tag, e.g. the site name
+    #[serde(default)]
+    pub location: String,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Open-Meteo compatible forecast endpoint
+    #[serde(default = "default_url")]
+    pub url: String,
+}
+
+fn default_interval_secs() -> u64 { 600 }
+fn default_url() -> String { "https://api.open-meteo.com/v1/forecast".to_string() }
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            latitude: 0.0,
+            longitude: 0.0,
+            location: String::new(),
+            interval_secs: default_interval_secs(),
+            url: default_url(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    weather: WeatherConfig,
+}
+
+impl WeatherConfig {
+    pub fn load(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        toml::from_str::<ConfigFile>(&content).unwrap_or_default().weather
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(-90.0..=90.0).contains(&self.latitude) || !(-180.0..=180.0).contains(&self.longitude) {
+            return Err("weather.latitude/longitude out of range".to_string());
+        }
+        if self.interval_secs == 0 {
+            return Err("weather.interval_secs must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// `current` block of an Open-Meteo response
+#[derive(Deserialize)]
+struct Current {
+    temperature_2m: Option<f64>,
+    relative_humidity_2m: Option<f64>,
+    surface_pressure: Option<f64>,
+    wind_speed_10m: Option<f64>,
+    wind_direction_10m: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct Forecast {
+    current: Current,
+}
+
+/// Outdoor conditions at the configured location
+#[derive(Debug, Serialize, Clone)]
+pub struct WeatherReading {
+    pub timestamp: i64,
+    pub location: String,
+    pub temperature_c: Option<f64>,
+    pub humidity_percent: Option<f64>,
+    pub pressure_hpa: Option<f64>,
+    pub wind_speed_kmh: Option<f64>,
+    pub wind_direction_deg: Option<f64>,
+}
+
+impl WeatherReading {
+    fn point(&self) -> Option<DataPoint> {
+        let fields = [
+            ("temperature_c", self.temperature_c),
+            ("humidity_percent", self.humidity_percent),
+            ("pressure_hpa", self.pressure_hpa),
+            ("wind_speed_kmh", self.wind_speed_kmh),
+            ("wind_direction_deg", self.wind_direction_deg),
+        ];
+        let mut builder = DataPoint::builder("weather").tag("location", self.location.clone());
+        for (name, value) in fields {
+            if let Some(value) = value {
+                builder = builder.field(name, value);
+            }
+        }
+        builder.timestamp(self.timestamp * 1_000_000).build().ok()
+    }
+}
+
+/// Wind at the site; direction is where it comes from, degrees from north
+#[derive(Debug, Clone, Copy)]
+pub struct Wind {
+    pub direction_deg: f32,
+    pub speed_kmh: Option<f32>,
+}
+
+/// Last successful reading, shared with ambient mode for plume bearings
+#[derive(Clone, Default)]
+pub struct LatestWeather(Arc<Mutex<Option<WeatherReading>>>);
+
+impl LatestWeather {
+    /// Wind from a reading no older than `max_age_ms` at `now`
+    pub fn wind(&self, now: i64, max_age_ms: i64) -> Option<Wind> {
+        let latest = self.0.lock().unwrap();
+        let reading = latest.as_ref().filter(|r| now - r.timestamp <= max_age_ms)?;
+        Some(Wind {
+            direction_deg: reading.wind_direction_deg? as f32,
+            speed_kmh: reading.wind_speed_kmh.map(|s| s as f32),
+        })
+    }
+}
+
+async fn fetch(client: &reqwest::Client, config: &WeatherConfig) -> Result<WeatherReading, reqwest::Error> {
+    let forecast: Forecast = client
+        .get(&config.url)
+        .query(&[
+            ("latitude", config.latitude.to_string()),
+            ("longitude", config.longitude.to_string()),
+            (
+                "current",
+                "temperature_2m,relative_humidity_2m,surface_pressure,wind_speed_10m,wind_direction_10m".to_string(),
+            ),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let current = forecast.current;
+    Ok(WeatherReading {
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        location: config.location.clone(),
+        temperature_c: current.temperature_2m,
+        humidity_percent: current.relative_humidity_2m,
+        pressure_hpa: current.surface_pressure,
+        wind_speed_kmh: current.wind_speed_10m,
+        wind_direction_deg: current.wind_direction_10m,
+    })
+}
+
+// ================= Weather Enrichment =================
+/// Polls outdoor weather for the site every `interval_secs` and stores it as
+/// the "weather" measurement next to the sensor data, plus a "weather" GUI
+/// event, to help interpret ambient monitoring deployments
+pub async fn run(config: WeatherConfig, latest: LatestWeather, data_tx: broadcast::Sender<String>, influx: InfluxDBHandler) {
+    println!(
+        "🌦️ Weather enrichment enabled ({:.4}, {:.4} every {}s)",
+        config.latitude, config.longitude, config.interval_secs
+    );
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(30)).build().unwrap_or_default();
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs.max(1)));
+    loop {
+        interval.tick().await;
+        let reading = match fetch(&client, &config).await {
+            Ok(reading) => reading,
+            Err(e) => {
+                eprintln!("⚠️ Weather fetch failed: {}", e);
+                continue;
+            }
+        };
+        let mut event = serde_json::to_value(&reading).unwrap_or_default();
+        event["type"] = "weather".into();
+        let _ = data_tx.send(event.to_string());
+        *latest.0.lock().unwrap() = Some(reading.clone());
+        if let Err(e) = influx.write_points(reading.point().into_iter().collect()).await {
+            eprintln!("❌ Weather write error: {:?}", e);
+        }
+    }
+}
@@ -50,6 +50,15 @@ TIMING = {
 
 # Edge Impulse model settings
 DEFAULT_MODEL_PATH = "modelfile.eim"
+# Channels disabled in the backend arrive as null. Only models trained with
+# missing inputs (as 0.0) should classify without them; others pause.
+MODEL_TOLERATES_MISSING = False
+# Feature imputation for missing/disabled channels before classification:
+# "none", "last_good", "baseline" (backend nightly baseline, else the values
+# below) or "model" (linear fit from the best-correlated healthy channel)
+IMPUTATION = "last_good"
+IMPUTATION_BASELINE = {}  # e.g. {"CO": 0.35}
+IMPUTATION_HISTORY = 500  # Complete frames kept to fit the "model" strategy
 
 # Edge Impulse API settings for data ingestion
 EDGE_IMPULSE_API_URL = "https://ingestion.edgeimpulse.com/api/training/data"
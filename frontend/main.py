@@ -23,6 +23,29 @@ import pyqtgraph as pg
 
 from config import BACKEND_HOST, BACKEND_PORT, RECONNECT_DELAY, MAX_DATA_POINTS, SENSORS, STATES, TIMING
 
+from utils import (
+    export_community_csv, export_matlab, export_hdf5, MATLAB_EXPORT_AVAILABLE, HDF5_EXPORT_AVAILABLE
+)
+
+# Save dialog filters; the plain CSV stays the default
+EXPORT_CSV = "CSV Files (*.csv)"
+EXPORT_COMMUNITY_CSV = "E-nose Community CSV (*.csv)"
+EXPORT_MATLAB = "MATLAB (*.mat)"
+EXPORT_HDF5 = "HDF5 Archive (*.h5)"
+EXPORT_FORMATS = (
+    [EXPORT_CSV, EXPORT_COMMUNITY_CSV]
+    + ([EXPORT_MATLAB] if MATLAB_EXPORT_AVAILABLE else [])
+    + ([EXPORT_HDF5] if HDF5_EXPORT_AVAILABLE else [])
+)
+# File extension the exporter needs (the dialog keeps the .csv default name)
+EXPORT_EXTENSIONS = {EXPORT_MATLAB: ".mat", EXPORT_HDF5: ".h5"}
+# Filter -> (exporter, log label)
+EXPORTERS = {
+    EXPORT_COMMUNITY_CSV: (export_community_csv, "community format"),
+    EXPORT_MATLAB: (export_matlab, "MATLAB"),
+    EXPORT_HDF5: (export_hdf5, "HDF5"),
+}
+
 # Try to import Edge Impulse (optional)
 try:
     from utils import EdgeImpulseHandler
@@ -810,7 +833,19 @@ class ENoseGUI(QMainWindow):
         """Handle data dari async loop"""
         # Normalize field names to uppercase
         normalized_data = {k.upper(): v for k, v in data.items()}
-        
+
+        # Status kanal & baseline dari backend untuk imputasi fitur
+        if self.ei_handler and "type" in data:
+            self.ei_handler.imputer.observe_event(data)
+
+        # Balasan Arduino / error untuk command yang dikirim dari GUI ini
+        if data.get("type") == "command_response":
+            self.log.append(f"📟 #{data.get('id')} {data.get('command')}: {data.get('line')}")
+            return
+        if data.get("type") == "command_error":
+            self.log.append(f"❌ {data.get('command')}: {data.get('error')}")
+            return
+
         if self.is_sampling:
             data_row = {'timestamp': datetime.now().isoformat()}
             
@@ -828,6 +863,12 @@ class ENoseGUI(QMainWindow):
                         pass
             
             if any(k in data_row for k in SENSORS.keys()):
+                # Konteks untuk export (state, level, device, session)
+                data_row['state'] = normalized_data.get('STATE_NAME', normalized_data.get('STATE', ''))
+                data_row['level'] = normalized_data.get('LEVEL', '')
+                data_row['device'] = normalized_data.get('DEVICE', '')
+                data_row['session'] = normalized_data.get('SESSION', '')
+                data_row['raw'] = normalized_data.get('RAW')
                 self.current_sample_data.append(data_row)
 
         # Update state display with color coding
@@ -857,7 +898,7 @@ class ENoseGUI(QMainWindow):
 
         # Compact log
         if any(k.upper() in SENSORS for k in data.keys()):
-            sensor_str = ", ".join([f"{k}:{v:.1f}" for k,v in data.items() if k.upper() in SENSORS])
+            sensor_str = ", ".join([f"{k}:{v:.1f}" if v is not None else f"{k}:off" for k,v in data.items() if k.upper() in SENSORS])
             self.log.append(f"✅ {sensor_str}")
             
             # Run classification (if Edge Impulse available)
@@ -871,7 +912,9 @@ class ENoseGUI(QMainWindow):
         if "classification" in result:
             best_label = max(result["classification"], key=result["classification"].get)
             confidence = result["classification"][best_label]
-            self.pred_label.setText(f"PRED: {best_label} ({confidence:.2f})")
+            imputed = result.get("imputed")
+            suffix = f" [imputed: {', '.join(imputed)}]" if imputed else ""
+            self.pred_label.setText(f"PRED: {best_label} ({confidence:.2f}){suffix}")
 
     def update_level_progress(self, current_level: int):
         """Update visual progress bar for levels 1-5"""
@@ -935,13 +978,27 @@ class ENoseGUI(QMainWindow):
         timestamp = datetime.now().strftime("%Y%m%d_%H%M%S")
         default_filename = f"{sample_name}_{timestamp}.csv"
         
-        filename, _ = QFileDialog.getSaveFileName(
-            self, "Save CSV", default_filename, "CSV Files (*.csv)"
+        filename, selected_filter = QFileDialog.getSaveFileName(
+            self, "Save CSV", default_filename, ";;".join(EXPORT_FORMATS)
         )
         
         if not filename:
             return
         
+        if selected_filter in EXPORTERS:
+            exporter, label = EXPORTERS[selected_filter]
+            extension = EXPORT_EXTENSIONS.get(selected_filter, "")
+            if not filename.lower().endswith(extension):
+                filename = filename.rsplit(".csv", 1)[0] + extension
+            try:
+                exporter(filename, self.current_sample_data, sample_name)
+                self.log.append(f"💾 Saved ({label}): {filename}")
+                self.log.append(f"📊 {len(self.current_sample_data)} samples")
+            except Exception as e:
+                self.log.append(f"❌ Error saving {label}: {e}")
+                QMessageBox.critical(self, "Error", f"Failed to save {label}:\n{e}")
+            return
+        
         try:
             collection_date = self.current_sample_data[0]['timestamp']
             
@@ -1,10 +1,14 @@
 """
-E-Nose Utilities - Edge Impulse Handler
+E-Nose Utilities - Edge Impulse Handler, session exporters
 """
 
 import csv
 import json
-from typing import Optional, Dict, Any
+from collections import deque
+from datetime import datetime
+from typing import Optional, Dict, Any, List
+
+from config import MODEL_TOLERATES_MISSING, IMPUTATION, IMPUTATION_BASELINE, IMPUTATION_HISTORY
 
 # Try to import requests (required for Edge Impulse upload)
 try:
@@ -23,6 +27,111 @@ except (ImportError, ModuleNotFoundError) as e:
     ImpulseRunner = None
     # Note: Error message will be shown by main.py if needed
 
+# Try to import scipy (optional, for MATLAB .mat export)
+try:
+    import numpy as np
+    from scipy.io import savemat
+    MATLAB_EXPORT_AVAILABLE = True
+except ImportError:
+    MATLAB_EXPORT_AVAILABLE = False
+
+# Try to import h5py (optional, for HDF5 archives)
+try:
+    import numpy as np
+    import h5py
+    HDF5_EXPORT_AVAILABLE = True
+except ImportError:
+    HDF5_EXPORT_AVAILABLE = False
+
+
+FEATURE_KEYS = ["NO2", "ETH", "VOC", "CO", "COM", "ETHM", "VOCM"]
+
+
+class FeatureImputer:
+    """Isi kanal yang hilang/mati di feature vector supaya klasifikasi tetap jalan
+
+    Strategi (config.IMPUTATION):
+      "none"       tidak ada imputasi
+      "last_good"  nilai sehat terakhir kanal itu
+      "baseline"   baseline dari backend (event "baseline"), fallback IMPUTATION_BASELINE
+      "model"      regresi linear dari kanal sehat yang paling berkorelasi,
+                   dilatih dari frame lengkap terakhir (fallback last_good)
+    """
+
+    def __init__(self, strategy: str = IMPUTATION):
+        self.strategy = strategy
+        self.unhealthy = set()
+        self.last_good: Dict[str, float] = {}
+        self.baseline: Dict[str, float] = {k.upper(): v for k, v in IMPUTATION_BASELINE.items()}
+        self.history = deque(maxlen=IMPUTATION_HISTORY)
+
+    def observe_event(self, event: dict):
+        """Track backend channel health and baselines"""
+        if event.get("type") == "channel_mask":
+            self.unhealthy = {name.upper() for name in event.get("disabled", [])}
+        elif event.get("type") == "baseline":
+            values = event.get("values") or {}
+            self.baseline.update({k.upper(): float(v) for k, v in values.items() if v is not None})
+
+    def impute(self, data: dict) -> (Dict[str, float], List[str]):
+        """
+        Returns:
+            (features per key, keys that were imputed); keys that could not be
+            imputed are left out
+        """
+        features = {}
+        missing = []
+        for key in FEATURE_KEYS:
+            value = data.get(key)
+            if value is None or key in self.unhealthy:
+                missing.append(key)
+                continue
+            features[key] = float(value)
+            self.last_good[key] = features[key]
+        if not missing:
+            self.history.append(features.copy())
+            return features, []
+
+        imputed = []
+        for key in missing:
+            value = self._estimate(key, features)
+            if value is not None:
+                features[key] = value
+                imputed.append(key)
+        return features, imputed
+
+    def _estimate(self, key: str, features: Dict[str, float]) -> Optional[float]:
+        if self.strategy == "last_good":
+            return self.last_good.get(key)
+        if self.strategy == "baseline":
+            return self.baseline.get(key)
+        if self.strategy == "model":
+            predicted = self._regress(key, features)
+            return predicted if predicted is not None else self.last_good.get(key)
+        return None
+
+    def _regress(self, key: str, features: Dict[str, float]) -> Optional[float]:
+        """y = a + b·x dari kanal x dengan r² tertinggi"""
+        if len(self.history) < 10:
+            return None
+        best = None
+        ys = [row[key] for row in self.history]
+        n = len(ys)
+        mean_y = sum(ys) / n
+        for other, x_now in features.items():
+            xs = [row[other] for row in self.history]
+            mean_x = sum(xs) / n
+            sxx = sum((x - mean_x) ** 2 for x in xs)
+            syy = sum((y - mean_y) ** 2 for y in ys)
+            if sxx <= 0 or syy <= 0:
+                continue
+            sxy = sum((x - mean_x) * (y - mean_y) for x, y in zip(xs, ys))
+            r2 = sxy * sxy / (sxx * syy)
+            if best is None or r2 > best[0]:
+                slope = sxy / sxx
+                best = (r2, mean_y + slope * (x_now - mean_x))
+        return best[1] if best else None
+
 
 class EdgeImpulseHandler:
     """Handler untuk Edge Impulse model loading dan classification"""
@@ -32,6 +141,7 @@ class EdgeImpulseHandler:
         self.model_path = model_path
         self.initialized = False
         self.model_info = None
+        self.imputer = FeatureImputer()
         
         if model_path:
             self.load_model(model_path)
@@ -85,15 +195,20 @@ class EdgeImpulseHandler:
         features = []
         try:
             # Order: NO2, ETH, VOC, CO, COM, ETHM, VOCM
-            feature_keys = ["NO2", "ETH", "VOC", "CO", "COM", "ETHM", "VOCM"]
-            for key in feature_keys:
-                if key in data:
-                    features.append(float(data[key]))
+            values, imputed = self.imputer.impute(data)
+            for key in FEATURE_KEYS:
+                if key in values:
+                    features.append(values[key])
+                elif MODEL_TOLERATES_MISSING:
+                    features.append(0.0)  # Channel disabled, model dilatih dengan input kosong
                 else:
                     return None  # Missing data for classification
             
             res = self.runner.classify(features)
-            return res["result"]
+            result = res["result"]
+            if imputed:
+                result["imputed"] = imputed
+            return result
         except Exception as e:
             print(f"❌ Classification error: {e}")
             return None
@@ -300,3 +415,192 @@ class EdgeImpulseHandler:
                 'success': False,
                 'message': f'Error uploading to Edge Impulse: {str(e)}'
             }
+
+
+# ================= Session Exporters =================
+COMMUNITY_CSV_FORMAT = "enose-community-csv"
+COMMUNITY_CSV_SCHEMA_VERSION = "1.0"
+# Fixed column order; new columns only ever get appended with a schema bump
+COMMUNITY_CSV_COLUMNS = ["sample_name", "timestamp", "elapsed_s", "state", "level", "channel", "value", "unit"]
+
+
+def _session_metadata(rows: list, sample_name: str) -> Dict[str, Any]:
+    """Metadata shared by all session exporters"""
+    first = rows[0] if rows else {}
+    return {
+        "sample_name": sample_name,
+        "collection_date": first.get("timestamp", ""),
+        "device": next((r["device"] for r in rows if r.get("device")), ""),
+        "session": next((r["session"] for r in rows if r.get("session")), ""),
+        "channels": FEATURE_KEYS,
+        "unit": "raw",
+        "samples": len(rows),
+        "exported_at": datetime.now().isoformat(),
+        "software": "E-NOSE Dashboard",
+    }
+
+
+def _elapsed(rows: list) -> List[float]:
+    """Seconds since the first row, from the ISO timestamps"""
+    try:
+        times = [datetime.fromisoformat(r["timestamp"]) for r in rows]
+    except (KeyError, ValueError):
+        return [float(i) for i in range(len(rows))]
+    return [(t - times[0]).total_seconds() for t in times]
+
+
+def export_community_csv(filename: str, rows: list, sample_name: str):
+    """
+    Write a session in the e-nose community format for sharing between labs.
+
+    Layout:
+        # key: value          metadata block, first key is "format", then
+        # ...                 "schema_version"; readers skip lines starting with #
+        sample_name,timestamp,elapsed_s,state,level,channel,value,unit
+        one row per sample and channel (tidy long format); missing = empty
+
+    Args:
+        rows: current_sample_data (dicts with timestamp, channel values and
+              optional state/level/device/session)
+    """
+    metadata = _session_metadata(rows, sample_name)
+    metadata["channels"] = ",".join(metadata["channels"])
+    with open(filename, "w", newline="") as f:
+        f.write(f"# format: {COMMUNITY_CSV_FORMAT}\n")
+        f.write(f"# schema_version: {COMMUNITY_CSV_SCHEMA_VERSION}\n")
+        for key, value in metadata.items():
+            f.write(f"# {key}: {value}\n")
+        writer = csv.writer(f, lineterminator="\n")
+        writer.writerow(COMMUNITY_CSV_COLUMNS)
+        for row, elapsed in zip(rows, _elapsed(rows)):
+            for channel in FEATURE_KEYS:
+                value = row.get(channel)
+                writer.writerow([
+                    sample_name,
+                    row.get("timestamp", ""),
+                    f"{elapsed:.3f}",
+                    row.get("state", ""),
+                    row.get("level", ""),
+                    channel,
+                    "" if value is None else value,
+                    metadata["unit"],
+                ])
+
+
+def export_matlab(filename: str, rows: list, sample_name: str):
+    """
+    Write a session as a MATLAB .mat file (requires scipy).
+
+    Variables:
+        time           N×1 seconds since the first sample
+        channels       N×7 values, NaN where a channel was missing
+        channel_names  1×7 cell, column order of `channels`
+        state, level   N×1 cell / N×1 double (NaN if unknown)
+        metadata       struct (sample_name, collection_date, device, ...)
+    """
+    if not MATLAB_EXPORT_AVAILABLE:
+        raise RuntimeError("scipy not installed. Install with: pip install scipy")
+
+    def number(value):
+        try:
+            return float(value)
+        except (TypeError, ValueError):
+            return float("nan")
+
+    metadata = _session_metadata(rows, sample_name)
+    metadata["channels"] = np.array(metadata["channels"], dtype=object)
+    savemat(filename, {
+        "time": np.array(_elapsed(rows), dtype=float).reshape(-1, 1),
+        "channels": np.array([[number(r.get(key)) for key in FEATURE_KEYS] for r in rows], dtype=float).reshape(-1, len(FEATURE_KEYS)),
+        "channel_names": np.array(FEATURE_KEYS, dtype=object),
+        "state": np.array([str(r.get("state", "")) for r in rows], dtype=object).reshape(-1, 1),
+        "level": np.array([number(r.get("level")) for r in rows], dtype=float).reshape(-1, 1),
+        "metadata": metadata,
+    }, do_compression=True)
+
+
+HDF5_FORMAT = "enose-hdf5"
+HDF5_SCHEMA_VERSION = "1.0"
+# Rows of the per-cycle "features" dataset
+HDF5_FEATURES = ["baseline", "peak", "delta", "mean", "area"]
+
+
+def _cycles(rows: list) -> List[list]:
+    """Split a session into cycles: a new cycle starts whenever the level changes"""
+    cycles = []
+    level = object()
+    for row in rows:
+        current = row.get("level", "")
+        if current not in ("", None) and current != level:
+            cycles.append([])
+            level = current
+        if not cycles:
+            cycles.append([])
+        cycles[-1].append(row)
+    return cycles
+
+
+def _cycle_features(time, filtered):
+    """
+    Per-channel response features of one cycle (HDF5_FEATURES × channels).
+    Baseline = mean of the first 10% of the cycle (pre-conditioning).
+    """
+    head = max(1, len(time) // 10)
+    baseline = np.nanmean(filtered[:head], axis=0) if len(time) else np.full(len(FEATURE_KEYS), np.nan)
+    peak = np.nanmax(filtered, axis=0)
+    response = np.nan_to_num(filtered - baseline)
+    trapezoid = getattr(np, "trapezoid", None) or np.trapz  # numpy 2 renamed trapz
+    area = trapezoid(response, time, axis=0) if len(time) > 1 else np.zeros(len(FEATURE_KEYS))
+    return np.vstack([baseline, peak, peak - baseline, np.nanmean(filtered, axis=0), area])
+
+
+def export_hdf5(filename: str, rows: list, sample_name: str):
+    """
+    Write a session as a self-describing HDF5 archive (requires h5py).
+
+    Layout:
+        /                      attrs: format, schema_version, session metadata
+        /cycle_000 ...         one group per level cycle, attrs: level, start,
+                               end, samples
+            time               N seconds since the session start
+            filtered           N×7 frame values (NaN = missing)
+            raw                N×7 unfiltered values, if the backend sends them
+            state              N state names
+            features           len(HDF5_FEATURES)×7, attrs: rows, columns
+    """
+    if not HDF5_EXPORT_AVAILABLE:
+        raise RuntimeError("h5py not installed. Install with: pip install h5py")
+
+    def matrix(values):
+        return np.array([[np.nan if v is None else float(v) for v in row] for row in values],
+                        dtype=float).reshape(-1, len(FEATURE_KEYS))
+
+    strings = h5py.string_dtype()
+    elapsed = dict(zip(map(id, rows), _elapsed(rows)))
+    metadata = _session_metadata(rows, sample_name)
+    with h5py.File(filename, "w") as f:
+        f.attrs["format"] = HDF5_FORMAT
+        f.attrs["schema_version"] = HDF5_SCHEMA_VERSION
+        for key, value in metadata.items():
+            f.attrs[key] = np.array(value, dtype=strings) if isinstance(value, list) else value
+
+        for index, cycle in enumerate(_cycles(rows)):
+            group = f.create_group(f"cycle_{index:03d}")
+            group.attrs["level"] = str(cycle[0].get("level", ""))
+            group.attrs["start"] = cycle[0].get("timestamp", "")
+            group.attrs["end"] = cycle[-1].get("timestamp", "")
+            group.attrs["samples"] = len(cycle)
+
+            time = np.array([elapsed[id(r)] for r in cycle], dtype=float)
+            filtered = matrix([[r.get(key) for key in FEATURE_KEYS] for r in cycle])
+            group.create_dataset("time", data=time).attrs["unit"] = "s"
+            dataset = group.create_dataset("filtered", data=filtered, compression="gzip")
+            dataset.attrs["columns"] = np.array(FEATURE_KEYS, dtype=strings)
+            if any(r.get("raw") for r in cycle):
+                raw = matrix([r.get("raw") or [None] * len(FEATURE_KEYS) for r in cycle])
+                dataset = group.create_dataset("raw", data=raw, compression="gzip")
+                dataset.attrs["columns"] = np.array(FEATURE_KEYS, dtype=strings)
+            group.create_dataset("state", data=np.array([str(r.get("state", "")) for r in cycle], dtype=strings))
+            dataset = group.create_dataset("features", data=_cycle_features(time, filtered))
+            dataset.attrs["rows"] = np.array(HDF5_FEATURES, dtype=strings)
+            dataset.attrs["columns"] = np.array(FEATURE_KEYS, dtype=strings)